@@ -0,0 +1,216 @@
+//! Report and listing queries against a synthetic database of 100k transactions.
+//!
+//! The app is a single binary, so the database layer is compiled in directly.
+
+#[allow(dead_code)]
+#[path = "../src/db.rs"]
+mod db;
+#[allow(dead_code)]
+#[path = "../src/disputes.rs"]
+mod disputes;
+#[allow(dead_code)]
+#[path = "../src/models.rs"]
+mod models;
+#[allow(dead_code)]
+#[path = "../src/money.rs"]
+mod money;
+#[path = "../src/pages.rs"]
+mod pages;
+#[allow(dead_code)]
+#[path = "../src/query.rs"]
+mod query;
+#[allow(dead_code)]
+#[path = "../src/query_inspector.rs"]
+mod query_inspector;
+
+use std::hint::black_box;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{Days, NaiveDate};
+use criterion::{Criterion, criterion_group, criterion_main};
+
+use db::{DbPool, PoolConfig, PoolMetrics};
+use disputes::RefundMonth;
+use models::NewTransaction;
+use query::TransactionQuery;
+
+const TRANSACTIONS: usize = 100_000;
+const CATEGORIES: [(&str, &str); 10] = [
+    ("Еда", "expense"),
+    ("Кафе", "expense"),
+    ("Транспорт", "expense"),
+    ("ЖКХ", "expense"),
+    ("Здоровье", "expense"),
+    ("Одежда", "expense"),
+    ("Развлечения", "expense"),
+    ("Подарки", "expense"),
+    ("Зарплата", "income"),
+    ("Фриланс", "income"),
+];
+const ACCOUNTS: [&str; 3] = ["Карта", "Наличные", "Вклад"];
+const TAGS: [&str; 4] = ["отпуск", "ремонт", "дети", "работа"];
+const BENCH_MONTH: &str = "2025-06";
+/// Concurrent page loads per iteration, well above the pool size.
+const CONCURRENT_LOADS: usize = 32;
+
+/// Small deterministic generator so every run benchmarks the same data.
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self, bound: u64) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        (self.0 >> 33) % bound
+    }
+}
+
+/// Three years of transactions spread over every category and account, with
+/// budgets for each expense category and a tag on every tenth expense.
+fn synthetic_db(config: &PoolConfig, metrics: Arc<PoolMetrics>) -> DbPool {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("report_queries.sqlite");
+    let _ = std::fs::remove_file(&path);
+    let pool = db::init_db(&path, config, metrics);
+    let mut conn = pool.get().expect("db connection");
+
+    for (name, kind) in CATEGORIES {
+        db::insert_category(&conn, name, kind, None).expect("insert category");
+    }
+    for name in ACCOUNTS {
+        db::insert_account(&conn, name, "card", "RUB", 0).expect("insert account");
+    }
+    let categories = db::list_categories(&conn).expect("list categories");
+    let accounts = db::list_accounts(&conn).expect("list accounts");
+
+    let start = NaiveDate::from_ymd_opt(2023, 1, 1).expect("start date");
+    let mut random = Lcg(42);
+    let rows = (0..TRANSACTIONS)
+        .map(|_| {
+            let category = &categories[random.next(categories.len() as u64) as usize];
+            let account = &accounts[random.next(accounts.len() as u64) as usize];
+            let occurred_on = start + Days::new(random.next(3 * 365));
+            NewTransaction {
+                kind: category.kind.clone(),
+                amount_cents: 100 + random.next(500_000) as i64,
+                category_id: Some(category.id),
+                occurred_on: occurred_on.format("%Y-%m-%d").to_string(),
+                note: None,
+                payee: None,
+                account_id: Some(account.id),
+                to_account_id: None,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let tx = conn.transaction().expect("begin");
+    let ids = db::insert_transactions_batch(&tx, &rows).expect("insert transactions");
+    for (id, row) in ids.iter().zip(&rows).step_by(10) {
+        if row.kind == "expense" {
+            let tag = TAGS[(*id as usize) % TAGS.len()].to_string();
+            db::add_transaction_tags(&tx, *id, &[tag]).expect("tag transaction");
+        }
+    }
+    for year in 2023..2026 {
+        for month in 1..=12 {
+            let month = format!("{year}-{month:02}");
+            for category in categories.iter().filter(|c| c.kind == "expense") {
+                db::insert_budget(&tx, category.id, &month, 3_000_000, true)
+                    .expect("insert budget");
+            }
+        }
+    }
+    tx.commit().expect("commit");
+    drop(conn);
+    pool
+}
+
+fn report_queries(c: &mut Criterion) {
+    let pool = synthetic_db(&PoolConfig::default(), Arc::default());
+    let conn = pool.get().expect("db connection");
+
+    c.bench_function("report_months", |b| {
+        b.iter(|| {
+            db::report_months(&conn, black_box(12), RefundMonth::Refund).expect("report_months")
+        })
+    });
+    c.bench_function("dashboard_budgets", |b| {
+        b.iter(|| {
+            db::dashboard_budgets(&conn, black_box(BENCH_MONTH), RefundMonth::Refund)
+                .expect("dashboard_budgets")
+        })
+    });
+    let month = TransactionQuery {
+        limit: Some(200),
+        ..TransactionQuery::month(BENCH_MONTH)
+    };
+    c.bench_function("list_transactions/month", |b| {
+        b.iter(|| db::list_transactions(&conn, black_box(&month)).expect("list_transactions"))
+    });
+    let month_and_tag = TransactionQuery {
+        tag: Some("отпуск".to_string()),
+        limit: Some(200),
+        ..TransactionQuery::month(BENCH_MONTH)
+    };
+    c.bench_function("list_transactions/month_and_tag", |b| {
+        b.iter(|| {
+            db::list_transactions(&conn, black_box(&month_and_tag)).expect("list_transactions")
+        })
+    });
+}
+
+/// The queries behind one dashboard page, with the separate checkouts the
+/// handler makes for the session check and for the page itself.
+fn dashboard_load(pool: &DbPool) {
+    {
+        let conn = pool.get().expect("db connection");
+        db::has_users(&conn).expect("has_users");
+        db::user_by_session(&conn, "bench-session", "2026-01-01T00:00:00Z", "2025-12-31T00:00:00Z")
+            .expect("user_by_session");
+    }
+    let conn = pool.get().expect("db connection");
+    db::report_month_totals(&conn, BENCH_MONTH, RefundMonth::Refund).expect("report_month_totals");
+    db::dashboard_budgets(&conn, BENCH_MONTH, RefundMonth::Refund).expect("dashboard_budgets");
+    db::date_bounds(&conn).expect("date_bounds");
+    db::list_months(&conn, 24).expect("list_months");
+    db::list_accounts(&conn).expect("list_accounts");
+}
+
+/// Many dashboards at once against a small pool: checkouts may wait, but none
+/// may time out.
+fn concurrent_dashboards(c: &mut Criterion) {
+    let config = PoolConfig {
+        max_size: 4,
+        connection_timeout: Duration::from_secs(10),
+        ..PoolConfig::default()
+    };
+    let metrics = Arc::new(PoolMetrics::default());
+    let pool = synthetic_db(&config, metrics.clone());
+
+    c.bench_function("dashboard/concurrent", |b| {
+        b.iter(|| {
+            std::thread::scope(|scope| {
+                for _ in 0..CONCURRENT_LOADS {
+                    scope.spawn(|| dashboard_load(&pool));
+                }
+            })
+        })
+    });
+
+    let stats = metrics.stats(&pool);
+    eprintln!(
+        "pool: {} checkouts, {} timeouts, {} connections, wait avg {:.2} ms, max {:.2} ms",
+        stats.checkouts, stats.timeouts, stats.connections, stats.avg_wait_ms, stats.max_wait_ms
+    );
+    assert_eq!(stats.timeouts, 0, "dashboard loads exhausted the pool");
+    assert!(stats.connections <= config.max_size);
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(20);
+    targets = report_queries, concurrent_dashboards
+}
+criterion_main!(benches);
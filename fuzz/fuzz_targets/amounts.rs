@@ -0,0 +1,36 @@
+//! Amount and date cells as they come from statements and webhooks.
+#![no_main]
+
+#[macro_use]
+extern crate rocket;
+
+#[allow(dead_code)]
+#[path = "../../src/disputes.rs"]
+mod disputes;
+#[allow(dead_code)]
+#[path = "../../src/import.rs"]
+mod import;
+#[allow(dead_code)]
+#[path = "../../src/models.rs"]
+mod models;
+#[allow(dead_code)]
+#[path = "../../src/money.rs"]
+mod money;
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: &str| {
+    if let Some(cents) = money::parse_amount_to_cents(input) {
+        assert!(cents >= 0);
+        assert_eq!(
+            money::parse_amount_to_cents(&money::format_money(cents)),
+            Some(cents)
+        );
+    }
+    if let Some((_, cents)) = import::parse_signed_amount(input) {
+        assert!(cents >= 0);
+    }
+    if let Some(date) = import::parse_date(input) {
+        assert_eq!(import::parse_date(&date).as_deref(), Some(date.as_str()));
+    }
+});
@@ -0,0 +1,71 @@
+//! Bank statement CSV import: reading, column guessing and row parsing.
+#![no_main]
+
+#[macro_use]
+extern crate rocket;
+
+#[allow(dead_code)]
+#[path = "../../src/disputes.rs"]
+mod disputes;
+#[allow(dead_code)]
+#[path = "../../src/import.rs"]
+mod import;
+#[allow(dead_code)]
+#[path = "../../src/models.rs"]
+mod models;
+#[allow(dead_code)]
+#[path = "../../src/money.rs"]
+mod money;
+
+use libfuzzer_sys::fuzz_target;
+
+use import::ImportMapping;
+use models::Category;
+
+fn categories() -> Vec<Category> {
+    [(1, "Еда", "expense"), (2, "Зарплата", "income")]
+        .into_iter()
+        .map(|(id, name, kind)| Category {
+            id,
+            name: name.to_string(),
+            kind: kind.to_string(),
+            parent_id: None,
+            default_amount_cents: None,
+            allow_receipt: false,
+        })
+        .collect()
+}
+
+/// The first bytes pick an explicit column mapping, as the preview form submits one.
+fn submitted_mapping(data: &[u8]) -> ImportMapping {
+    let column = |index: usize| data.get(index).map(|byte| usize::from(byte % 8));
+    ImportMapping {
+        date_col: column(0),
+        amount_col: column(1),
+        note_col: column(2),
+        category_col: column(3),
+        has_header: data.get(4).map(|byte| byte % 2 == 0),
+        positive_as: Some("expense".to_string()),
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let content = String::from_utf8_lossy(data);
+    let Ok(rows) = import::read_rows(&content) else {
+        return;
+    };
+    let categories = categories();
+    for mapping in [ImportMapping::default(), submitted_mapping(data)] {
+        let mapping = import::resolve_mapping(&rows, mapping);
+        let _ = import::column_names(&rows, mapping.has_header.unwrap_or(false));
+        for row in import::parse_rows(&rows, &mapping, &categories) {
+            if row.error.is_none() {
+                assert!(row.amount_cents >= 0);
+                assert_eq!(
+                    import::parse_date(&row.occurred_on).as_deref(),
+                    Some(row.occurred_on.as_str())
+                );
+            }
+        }
+    }
+});
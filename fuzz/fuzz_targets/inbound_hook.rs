@@ -0,0 +1,63 @@
+//! Zapier/IFTTT webhook payloads mapped through a hook's dotted field paths.
+#![no_main]
+
+#[macro_use]
+extern crate rocket;
+
+#[allow(dead_code)]
+#[path = "../../src/disputes.rs"]
+mod disputes;
+#[allow(dead_code)]
+#[path = "../../src/hooks.rs"]
+mod hooks;
+#[allow(dead_code)]
+#[path = "../../src/import.rs"]
+mod import;
+#[allow(dead_code)]
+#[path = "../../src/models.rs"]
+mod models;
+#[allow(dead_code)]
+#[path = "../../src/money.rs"]
+mod money;
+
+use libfuzzer_sys::fuzz_target;
+use serde_json::Value;
+
+use models::{Category, InboundHook};
+
+fn hook() -> InboundHook {
+    InboundHook {
+        id: 1,
+        user_id: 1,
+        name: "fuzz".to_string(),
+        secret: "secret".to_string(),
+        kind: "expense".to_string(),
+        amount_field: "data.amount".to_string(),
+        date_field: Some("data.items.0.date".to_string()),
+        note_field: Some("note".to_string()),
+        category_field: Some("category".to_string()),
+        category_id: Some(1),
+        account_id: None,
+        last_used_at: None,
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(payload) = serde_json::from_slice::<Value>(data) else {
+        return;
+    };
+    let categories = [Category {
+        id: 1,
+        name: "Еда".to_string(),
+        kind: "expense".to_string(),
+        parent_id: None,
+        default_amount_cents: None,
+        allow_receipt: false,
+    }];
+    if let Ok(transaction) = hooks::to_new_transaction(&hook(), &payload, &categories, "2026-01-01")
+    {
+        assert!(transaction.amount_cents > 0);
+        assert!(transaction.kind == "income" || transaction.kind == "expense");
+        assert!(import::parse_date(&transaction.occurred_on).is_some());
+    }
+});
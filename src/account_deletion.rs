@@ -0,0 +1,62 @@
+//! Deleting one's own account.
+//!
+//! Accounts, transactions, budgets, categories and receipts belong to the
+//! household rather than to a login, so what goes depends on who stays. The
+//! login always goes, and with it its sessions, API sign-ins and tokens,
+//! webhooks, notifications and background jobs with their files. When it is
+//! the last login the household goes too: every transaction, budget,
+//! category, account, loan and holding, and the receipt files, and the app is
+//! back at its first-run setup. The rows are deleted in one database
+//! transaction; files are removed once it is committed.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rusqlite::{Connection, Result};
+
+use crate::db;
+use crate::receipt_storage::Storage;
+use crate::thumbnails;
+
+/// What a deletion took with it.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Deleted {
+    /// Whether it was the last login and the household's data went too.
+    pub household: bool,
+    /// Files removed: receipts with their thumbnails, and the results of
+    /// background jobs.
+    pub files: usize,
+}
+
+/// Deletes user `user_id`; `receipts` is where receipt files are kept.
+pub fn delete(conn: &mut Connection, user_id: i64, receipts: &dyn Storage) -> Result<Deleted> {
+    let tx = conn.transaction()?;
+    let files = db::user_job_files(&tx, user_id)?
+        .into_iter()
+        .map(PathBuf::from)
+        .collect::<Vec<_>>();
+    db::delete_user(&tx, user_id)?;
+    let household = !db::has_users(&tx)?;
+    let mut receipt_files = Vec::new();
+    if household {
+        for name in db::receipt_file_names(&tx)? {
+            // Names are generated, but a file outside the directory never goes.
+            if let Some(name) = Path::new(&name).file_name().and_then(|name| name.to_str()) {
+                receipt_files.push(thumbnails::name(name));
+                receipt_files.push(name.to_string());
+            }
+        }
+        db::purge_household(&tx)?;
+    }
+    tx.commit()?;
+
+    let files = files
+        .iter()
+        .filter(|path| fs::remove_file(path).is_ok())
+        .count()
+        + receipt_files
+            .iter()
+            .filter(|name| receipts.remove(name).is_ok())
+            .count();
+    Ok(Deleted { household, files })
+}
@@ -0,0 +1,80 @@
+//! What each account contributes to the monthly cash flow.
+//!
+//! The reports page splits the selected month's income and expenses by
+//! account; [`series`] lays out a run of months for a stacked chart, with an
+//! income and an expense series per account and zero in the months an
+//! account wasn't used.
+
+use serde::Serialize;
+
+use crate::models::ReportAccountMonth;
+
+/// Months in a series, up to the selected one.
+pub const MONTHS: usize = 12;
+/// The name transactions without an account are shown under.
+pub const NO_ACCOUNT: &str = "Без счета";
+
+#[derive(Debug, Serialize)]
+pub struct Series {
+    pub months: Vec<String>,
+    pub accounts: Vec<AccountSeries>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AccountSeries {
+    pub account: String,
+    pub income_cents: Vec<i64>,
+    pub expense_cents: Vec<i64>,
+}
+
+/// [`MONTHS`] months up to and including `last`, oldest first.
+pub fn months_until(last: &str) -> Vec<String> {
+    let mut months =
+        std::iter::successors(Some(last.to_string()), |month| crate::previous_month(month))
+            .take(MONTHS)
+            .collect::<Vec<_>>();
+    months.reverse();
+    months
+}
+
+pub fn account_name(record: &ReportAccountMonth) -> &str {
+    record.account_name.as_deref().unwrap_or(NO_ACCOUNT)
+}
+
+/// `records` of `months` as one series per account, accounts by name with
+/// the transactions without an account last.
+pub fn series(months: &[String], records: &[ReportAccountMonth]) -> Series {
+    let mut names = Vec::<Option<&str>>::new();
+    for record in records {
+        let name = record.account_name.as_deref();
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names.sort_by_key(|name| (name.is_none(), *name));
+
+    let accounts = names
+        .into_iter()
+        .map(|name| {
+            let mut account = AccountSeries {
+                account: name.unwrap_or(NO_ACCOUNT).to_string(),
+                income_cents: vec![0; months.len()],
+                expense_cents: vec![0; months.len()],
+            };
+            for record in records
+                .iter()
+                .filter(|record| record.account_name.as_deref() == name)
+            {
+                if let Some(index) = months.iter().position(|month| *month == record.month) {
+                    account.income_cents[index] += record.income_cents;
+                    account.expense_cents[index] += record.expense_cents;
+                }
+            }
+            account
+        })
+        .collect();
+    Series {
+        months: months.to_vec(),
+        accounts,
+    }
+}
@@ -0,0 +1,230 @@
+use std::collections::BTreeSet;
+use std::io::{Cursor, Read, Write};
+use std::time::Duration;
+
+use chrono::{Datelike, Local, Months};
+use lettre::message::header::ContentType;
+use lettre::message::{Attachment, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use rusqlite::Connection;
+use zip::write::SimpleFileOptions;
+
+use crate::db::{self, DbPool};
+use crate::export;
+use crate::models::NewNotification;
+use crate::notifications;
+use crate::query::TransactionQuery;
+use crate::receipt_storage::Storage;
+
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const SEND_DAY: u32 = 2;
+
+/// Where last month's receipts go; configured through the environment.
+///
+/// `LUMEN_ACCOUNTANT_EMAIL` selects email and needs `LUMEN_SMTP_HOST`,
+/// `LUMEN_SMTP_USER`, `LUMEN_SMTP_PASSWORD` and `LUMEN_SMTP_FROM`. Otherwise
+/// `LUMEN_ACCOUNTANT_WEBDAV_URL` names a WebDAV folder; credentials go in the URL.
+#[derive(Clone)]
+pub enum Delivery {
+    Email {
+        relay: String,
+        username: String,
+        password: String,
+        from: String,
+        to: String,
+    },
+    WebDav {
+        url: String,
+    },
+}
+
+impl Delivery {
+    pub fn from_env() -> Option<Self> {
+        let var = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .filter(|value| !value.trim().is_empty())
+        };
+        if let Some(to) = var("LUMEN_ACCOUNTANT_EMAIL") {
+            return Some(Delivery::Email {
+                relay: var("LUMEN_SMTP_HOST")?,
+                username: var("LUMEN_SMTP_USER")?,
+                password: var("LUMEN_SMTP_PASSWORD")?,
+                from: var("LUMEN_SMTP_FROM")?,
+                to,
+            });
+        }
+        let url = var("LUMEN_ACCOUNTANT_WEBDAV_URL")?;
+        Some(Delivery::WebDav {
+            url: url.trim_end_matches('/').to_string(),
+        })
+    }
+
+    fn target(&self) -> String {
+        match self {
+            Delivery::Email { to, .. } => to.clone(),
+            Delivery::WebDav { .. } => "WebDAV".to_string(),
+        }
+    }
+
+    fn send(&self, month: &str, archive: Vec<u8>) -> Result<(), String> {
+        let filename = format!("lumen-{month}.zip");
+        match self {
+            Delivery::Email {
+                relay,
+                username,
+                password,
+                from,
+                to,
+            } => {
+                let attachment = Attachment::new(filename).body(
+                    archive,
+                    ContentType::parse("application/zip").map_err(|err| err.to_string())?,
+                );
+                let message = Message::builder()
+                    .from(from.parse().map_err(|_| "некорректный адрес отправителя")?)
+                    .to(to.parse().map_err(|_| "некорректный адрес бухгалтера")?)
+                    .subject(format!("Чеки и операции за {month}"))
+                    .multipart(
+                        MultiPart::mixed()
+                            .singlepart(SinglePart::plain(format!(
+                                "Во вложении чеки и выписка операций за {month}."
+                            )))
+                            .singlepart(attachment),
+                    )
+                    .map_err(|err| err.to_string())?;
+                SmtpTransport::relay(relay)
+                    .map_err(|err| err.to_string())?
+                    .credentials(Credentials::new(username.clone(), password.clone()))
+                    .build()
+                    .send(&message)
+                    .map_err(|err| err.to_string())?;
+            }
+            Delivery::WebDav { url } => {
+                ureq::put(&format!("{url}/{filename}"))
+                    .timeout(Duration::from_secs(60))
+                    .set("Content-Type", "application/zip")
+                    .send_bytes(&archive)
+                    .map_err(|err| match err {
+                        // The URL may carry credentials, so it is kept out of the message.
+                        ureq::Error::Status(code, _) => format!("WebDAV вернул {code}"),
+                        ureq::Error::Transport(transport) => transport.kind().to_string(),
+                    })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// ZIP with the month's transactions as CSV and every attached receipt.
+/// `on_file` is told how many of the files are written so far, and of how many.
+pub fn build_archive(
+    conn: &Connection,
+    storage: &dyn Storage,
+    month: &str,
+    mut on_file: impl FnMut(usize, usize),
+) -> Result<Vec<u8>, String> {
+    let records = db::list_transactions(conn, &TransactionQuery::month(month)).map_err(|err| err.to_string())?;
+    let csv = export::transactions_csv(&records).map_err(|err| err.to_string())?;
+
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default();
+    zip.start_file(format!("transactions-{month}.csv"), options)
+        .map_err(|err| err.to_string())?;
+    zip.write_all(&csv).map_err(|err| err.to_string())?;
+    // The same upload is saved once, so transactions may share a receipt.
+    let receipts = records
+        .iter()
+        .filter_map(|record| record.receipt_path.as_deref())
+        .collect::<BTreeSet<_>>();
+    let total = receipts.len() + 1;
+    on_file(1, total);
+    for (index, name) in receipts.into_iter().enumerate() {
+        let mut content = Vec::new();
+        storage
+            .open(name)
+            .and_then(|object| {
+                let mut object = object.ok_or(std::io::ErrorKind::NotFound)?;
+                object.body.read_to_end(&mut content)
+            })
+            .map_err(|err| format!("{name}: {err}"))?;
+        zip.start_file(format!("receipts/{name}"), options)
+            .map_err(|err| err.to_string())?;
+        zip.write_all(&content).map_err(|err| err.to_string())?;
+        on_file(index + 2, total);
+    }
+    let cursor = zip.finish().map_err(|err| err.to_string())?;
+    Ok(cursor.into_inner())
+}
+
+fn notify_all(conn: &Connection, title: String, body: String) {
+    for user_id in db::user_ids(conn).unwrap_or_default() {
+        let _ = notifications::dispatch(
+            conn,
+            user_id,
+            &NewNotification {
+                event: "accountant_export".to_string(),
+                title: title.clone(),
+                body: body.clone(),
+                payload: None,
+            },
+        );
+    }
+}
+
+/// Sends last month's archive once it is the 2nd or later.
+///
+/// A month is sent once; after a failure it is retried the next day so the
+/// hourly check does not flood notifications.
+pub fn run_due(pool: &DbPool, storage: &dyn Storage, delivery: &Delivery) {
+    let now = Local::now();
+    if now.day() < SEND_DAY {
+        return;
+    }
+    let Some(month) = now
+        .date_naive()
+        .checked_sub_months(Months::new(1))
+        .map(|date| date.format("%Y-%m").to_string())
+    else {
+        return;
+    };
+    let today = now.format("%Y-%m-%d").to_string();
+    let Ok(conn) = pool.get() else {
+        return;
+    };
+    match db::accountant_export_state(&conn, &month) {
+        Ok(Some((status, attempted_at)))
+            if status == "sent" || attempted_at.starts_with(&today) =>
+        {
+            return;
+        }
+        Ok(_) => {}
+        Err(_) => return,
+    }
+
+    let result = build_archive(&conn, storage, &month, |_, _| {}).and_then(|archive| delivery.send(&month, archive));
+    let attempted_at = now.to_rfc3339();
+    match result {
+        Ok(()) => {
+            let _ = db::record_accountant_export(&conn, &month, "sent", &attempted_at, None);
+            notify_all(
+                &conn,
+                format!("Чеки за {month} отправлены"),
+                format!(
+                    "Архив с чеками и операциями отправлен: {}",
+                    delivery.target()
+                ),
+            );
+        }
+        Err(error) => {
+            let _ =
+                db::record_accountant_export(&conn, &month, "failed", &attempted_at, Some(&error));
+            notify_all(
+                &conn,
+                format!("Не удалось отправить чеки за {month}"),
+                format!("{error}. Повторная попытка завтра."),
+            );
+        }
+    }
+}
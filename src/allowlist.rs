@@ -0,0 +1,164 @@
+//! Networks the maintenance pages may be opened from.
+//!
+//! `LUMEN_ADMIN_NETWORKS` lists addresses and CIDR ranges, e.g.
+//! `192.168.1.0/24, ::1`. When it is set, the [`AdminNetwork`] guard answers
+//! 403 to any other address, even with a valid session, so a stolen cookie
+//! can't reach them from outside the home network. Unset, nothing changes.
+//!
+//! The address is the peer's, see [`client_addr`]: headers naming another one
+//! are only believed from the proxies in `LUMEN_TRUSTED_PROXIES`.
+
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+
+/// One address range, `addr/prefix`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Network {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl FromStr for Network {
+    type Err = String;
+
+    /// A bare address is a range of one.
+    fn from_str(value: &str) -> Result<Self, String> {
+        let (addr, prefix) = match value.trim().split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (value.trim(), None),
+        };
+        let addr = IpAddr::from_str(addr)
+            .map_err(|_| format!("{value}: не IP-адрес"))?
+            .to_canonical();
+        let bits = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .parse()
+                .ok()
+                .filter(|prefix| *prefix <= bits)
+                .ok_or_else(|| format!("{value}: длина префикса от 0 до {bits}"))?,
+            None => bits,
+        };
+        Ok(Network { addr, prefix })
+    }
+}
+
+impl Network {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, ip.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Allowlist {
+    pub networks: Vec<Network>,
+}
+
+impl Allowlist {
+    /// `None` when `LUMEN_ADMIN_NETWORKS` is unset or blank. Entries that
+    /// don't parse are left out, which only narrows the list.
+    pub fn from_env() -> Option<Self> {
+        let value = std::env::var("LUMEN_ADMIN_NETWORKS").unwrap_or_default();
+        Self::parse(&value)
+    }
+
+    /// Networks separated by commas or whitespace.
+    pub fn parse(value: &str) -> Option<Self> {
+        let entries = value
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|entry| !entry.is_empty())
+            .collect::<Vec<_>>();
+        if entries.is_empty() {
+            return None;
+        }
+        let networks = entries
+            .into_iter()
+            .filter_map(|entry| entry.parse().ok())
+            .collect();
+        Some(Allowlist { networks })
+    }
+
+    pub fn allows(&self, ip: IpAddr) -> bool {
+        self.networks.iter().any(|network| network.contains(ip))
+    }
+}
+
+/// Proxies in front of the app, from `LUMEN_TRUSTED_PROXIES` in the same
+/// form as `LUMEN_ADMIN_NETWORKS`. Only a request coming from one of them may
+/// name its client in Rocket's `ip_header` (`X-Real-IP` by default); anyone
+/// else could put any address there.
+#[derive(Clone, Debug, Default)]
+pub struct TrustedProxies(pub Option<Allowlist>);
+
+impl TrustedProxies {
+    pub fn from_env() -> Self {
+        let value = std::env::var("LUMEN_TRUSTED_PROXIES").unwrap_or_default();
+        TrustedProxies(Allowlist::parse(&value))
+    }
+
+    /// The client behind `peer`: the `forwarded` address when the peer is a
+    /// trusted proxy that sent one, otherwise the peer itself.
+    pub fn client(&self, peer: Option<IpAddr>, forwarded: Option<IpAddr>) -> Option<IpAddr> {
+        match (&self.0, peer) {
+            (Some(proxies), Some(ip)) if proxies.allows(ip) => forwarded.or(peer),
+            _ => peer,
+        }
+    }
+}
+
+/// The address a request came from, see [`TrustedProxies`]. Use this rather
+/// than Rocket's `client_ip`, which believes the header from anyone.
+pub fn client_addr(request: &Request<'_>) -> Option<IpAddr> {
+    let peer = request.remote().map(|addr| addr.ip());
+    match request.rocket().state::<TrustedProxies>() {
+        Some(proxies) => proxies.client(peer, request.real_ip()),
+        None => peer,
+    }
+}
+
+/// [`client_addr`] as a request guard.
+pub struct ClientAddr(pub Option<IpAddr>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ClientAddr {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, ()> {
+        Outcome::Success(ClientAddr(client_addr(request)))
+    }
+}
+
+/// Passes when no allowlist is configured or the client is inside it.
+pub struct AdminNetwork;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AdminNetwork {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, ()> {
+        let Some(Some(allowlist)) = request.rocket().state::<Option<Allowlist>>() else {
+            return Outcome::Success(AdminNetwork);
+        };
+        match client_addr(request) {
+            Some(ip) if allowlist.allows(ip) => Outcome::Success(AdminNetwork),
+            _ => Outcome::Error((Status::Forbidden, ())),
+        }
+    }
+}
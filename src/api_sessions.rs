@@ -0,0 +1,117 @@
+//! Sign-in for the mobile app and other API clients.
+//!
+//! A client trades the password for a pair of tokens: an access token that
+//! works as a bearer token for a quarter of an hour and a refresh token that
+//! buys the next pair. Every refresh token works once. Presenting one a second
+//! time means it was copied, so the whole family of pairs descending from that
+//! sign-in is revoked and both holders have to sign in again.
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{Connection, Result};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::db;
+use crate::models::{NewApiSession, User};
+
+pub const ACCESS_TTL: chrono::Duration = chrono::Duration::minutes(15);
+pub const REFRESH_TTL: chrono::Duration = chrono::Duration::days(30);
+
+/// What the token endpoints answer with.
+#[derive(Debug, Serialize)]
+pub struct TokenPair {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub token_type: &'static str,
+    /// Seconds the access token stays valid.
+    pub expires_in: i64,
+}
+
+/// Why a refresh token was turned down.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Refused {
+    Unknown,
+    Expired,
+    Revoked,
+    /// Used before: the family is revoked now.
+    Reused,
+}
+
+/// Timestamps are stored in this form so they compare correctly as text.
+pub fn timestamp(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn token() -> String {
+    Uuid::new_v4().simple().to_string()
+}
+
+fn issue(conn: &Connection, user_id: i64, family: String, now: DateTime<Utc>) -> Result<TokenPair> {
+    let session = NewApiSession {
+        user_id,
+        family,
+        access_token: token(),
+        access_expires_at: timestamp(now + ACCESS_TTL),
+        refresh_token: token(),
+        refresh_expires_at: timestamp(now + REFRESH_TTL),
+    };
+    db::insert_api_session(conn, &session, &timestamp(now))?;
+    Ok(TokenPair {
+        access_token: session.access_token,
+        refresh_token: session.refresh_token,
+        token_type: "Bearer",
+        expires_in: ACCESS_TTL.num_seconds(),
+    })
+}
+
+/// The first pair of a new family, after the password has been checked.
+pub fn sign_in(conn: &Connection, user_id: i64, now: DateTime<Utc>) -> Result<TokenPair> {
+    db::prune_api_sessions(conn, &timestamp(now))?;
+    issue(conn, user_id, token(), now)
+}
+
+/// Trades `refresh_token` for the next pair of its family; the old pair stops
+/// working.
+pub fn refresh(
+    conn: &mut Connection,
+    refresh_token: &str,
+    now: DateTime<Utc>,
+) -> Result<std::result::Result<TokenPair, Refused>> {
+    let tx = conn.transaction()?;
+    let Some(session) = db::api_session_by_refresh_token(&tx, refresh_token)? else {
+        return Ok(Err(Refused::Unknown));
+    };
+    let at = timestamp(now);
+    let refused = if session.revoked_at.is_some() {
+        Some(Refused::Revoked)
+    } else if session.rotated_at.is_some() {
+        db::revoke_api_session_family(&tx, &session.family, &at)?;
+        Some(Refused::Reused)
+    } else if session.refresh_expires_at <= at {
+        Some(Refused::Expired)
+    } else {
+        None
+    };
+    if let Some(refused) = refused {
+        tx.commit()?;
+        return Ok(Err(refused));
+    }
+    db::mark_api_session_rotated(&tx, session.id, &at)?;
+    let pair = issue(&tx, session.user_id, session.family, now)?;
+    tx.commit()?;
+    Ok(Ok(pair))
+}
+
+/// Signs out the family `refresh_token` belongs to; an unknown token is
+/// ignored.
+pub fn revoke(conn: &Connection, refresh_token: &str, now: DateTime<Utc>) -> Result<()> {
+    if let Some(session) = db::api_session_by_refresh_token(conn, refresh_token)? {
+        db::revoke_api_session_family(conn, &session.family, &timestamp(now))?;
+    }
+    Ok(())
+}
+
+/// The user an access token is valid for right now.
+pub fn user(conn: &Connection, access_token: &str, now: DateTime<Utc>) -> Result<Option<User>> {
+    db::user_by_access_token(conn, access_token, &timestamp(now))
+}
@@ -0,0 +1,119 @@
+//! Bearer tokens for scripts and dashboards calling `/api/v1`.
+//!
+//! Every token has one scope and scopes are ordered: `write` can also read and
+//! `admin` can do anything. An endpoint states the scope it needs through the
+//! [`Api`] guard, so a `read` token given to Grafana gets 403 from anything that
+//! changes data. A browser session and an access token from
+//! [`api_sessions`](crate::api_sessions) keep full access, as in the pages.
+
+use std::marker::PhantomData;
+
+use chrono::{Local, Utc};
+use rocket::State;
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+
+use crate::api_sessions;
+use crate::db::{self, DbPool};
+use crate::models::User;
+use crate::sessions;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Scope {
+    Read,
+    Write,
+    Admin,
+}
+
+impl Scope {
+    pub const ALL: [Scope; 3] = [Scope::Read, Scope::Write, Scope::Admin];
+
+    pub fn from_param(value: &str) -> Option<Scope> {
+        Scope::ALL.into_iter().find(|scope| scope.as_str() == value)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Scope::Read => "read",
+            Scope::Write => "write",
+            Scope::Admin => "admin",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Scope::Read => "Только чтение",
+            Scope::Write => "Чтение и запись",
+            Scope::Admin => "Полный доступ",
+        }
+    }
+}
+
+/// The scope an endpoint needs, as a type for [`Api`].
+pub trait Required {
+    const SCOPE: Scope;
+}
+
+pub struct Read;
+pub struct Write;
+pub struct Admin;
+
+impl Required for Read {
+    const SCOPE: Scope = Scope::Read;
+}
+
+impl Required for Write {
+    const SCOPE: Scope = Scope::Write;
+}
+
+impl Required for Admin {
+    const SCOPE: Scope = Scope::Admin;
+}
+
+/// The caller of an API endpoint that needs scope `S`: 401 without a valid
+/// token or session, 403 when the token's scope is lower.
+pub struct Api<S> {
+    pub user: User,
+    scope: PhantomData<S>,
+}
+
+/// The token of an `Authorization: Bearer` header.
+pub(crate) fn bearer<'r>(request: &'r Request<'_>) -> Option<&'r str> {
+    let value = request.headers().get_one("Authorization")?;
+    let (kind, token) = value.trim().split_once(' ')?;
+    kind.eq_ignore_ascii_case("bearer").then(|| token.trim())
+}
+
+#[rocket::async_trait]
+impl<'r, S: Required> FromRequest<'r> for Api<S> {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, ()> {
+        let Outcome::Success(pool) = request.guard::<&State<DbPool>>().await else {
+            return Outcome::Error((Status::InternalServerError, ()));
+        };
+        let Ok(conn) = pool.get() else {
+            return Outcome::Error((Status::ServiceUnavailable, ()));
+        };
+        let granted = match bearer(request) {
+            Some(token) => match db::user_by_api_token(&conn, token, &Local::now().to_rfc3339()) {
+                Ok(Some((user, scope))) => Scope::from_param(&scope).map(|scope| (user, scope)),
+                _ => api_sessions::user(&conn, token, Utc::now())
+                    .ok()
+                    .flatten()
+                    .map(|user| (user, Scope::Admin)),
+            },
+            None => sessions::token(request.cookies())
+                .and_then(|token| sessions::user(&conn, &token, Utc::now()).ok().flatten())
+                .map(|user| (user, Scope::Admin)),
+        };
+        match granted {
+            Some((user, scope)) if scope >= S::SCOPE => Outcome::Success(Api {
+                user,
+                scope: PhantomData,
+            }),
+            Some(_) => Outcome::Error((Status::Forbidden, ())),
+            None => Outcome::Error((Status::Unauthorized, ())),
+        }
+    }
+}
@@ -0,0 +1,121 @@
+//! Static files under content-hashed names, so browsers can keep them for good.
+//!
+//! Names are hashed once at startup. Templates ask for `asset(name="style.css")`
+//! and get `/static/style.<hash>.css`, which is served as immutable; a changed
+//! file gets a new name on the next start. The plain name keeps working but
+//! must be revalidated on every use.
+
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hasher};
+use std::path::{Path, PathBuf};
+
+use rocket::fs::NamedFile;
+use rocket::http::Header;
+use rocket_dyn_templates::tera;
+
+pub const DIR: &str = "static";
+
+const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+const REVALIDATE: &str = "no-cache";
+
+#[derive(Clone, Debug, Default)]
+pub struct Assets {
+    dir: PathBuf,
+    /// `style.css` -> `style.<hash>.css`.
+    hashed: HashMap<String, String>,
+    /// `style.<hash>.css` -> `style.css`.
+    originals: HashMap<String, String>,
+}
+
+#[derive(Responder)]
+pub struct StaticFile {
+    file: NamedFile,
+    cache_control: Header<'static>,
+}
+
+impl Assets {
+    /// Hashes every file under `dir`. Without `fingerprint` the plain names
+    /// are handed out, so edits show up on reload during development.
+    pub fn load(dir: &Path, fingerprint: bool) -> Self {
+        let mut assets = Assets {
+            dir: dir.to_path_buf(),
+            ..Default::default()
+        };
+        if fingerprint {
+            let mut files = Vec::new();
+            collect_files(dir, dir, &mut files);
+            for (name, contents) in files {
+                let hashed = hashed_name(&name, &contents);
+                assets.originals.insert(hashed.clone(), name.clone());
+                assets.hashed.insert(name, hashed);
+            }
+        }
+        assets
+    }
+
+    /// URL to link `name` (relative to the static directory) by.
+    pub fn url(&self, name: &str) -> String {
+        let name = self.hashed.get(name).map_or(name, String::as_str);
+        format!("/static/{name}")
+    }
+
+    /// Opens a requested file: hashed names get a year of caching, plain ones
+    /// are revalidated every time.
+    pub async fn open(&self, requested: &str) -> Option<StaticFile> {
+        let (name, cache_control) = match self.originals.get(requested) {
+            Some(original) => (original.as_str(), IMMUTABLE),
+            None => (requested, REVALIDATE),
+        };
+        let file = NamedFile::open(self.dir.join(name)).await.ok()?;
+        Some(StaticFile {
+            file,
+            cache_control: Header::new("Cache-Control", cache_control),
+        })
+    }
+
+    /// The `asset(name=...)` template function.
+    pub fn tera_function(&self) -> impl tera::Function + use<> {
+        let assets = self.clone();
+        move |args: &HashMap<String, tera::Value>| -> tera::Result<tera::Value> {
+            let name = args
+                .get("name")
+                .and_then(tera::Value::as_str)
+                .ok_or_else(|| tera::Error::msg("asset() needs a `name` string"))?;
+            Ok(tera::Value::String(assets.url(name)))
+        }
+    }
+}
+
+fn collect_files(root: &Path, dir: &Path, out: &mut Vec<(String, Vec<u8>)>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_files(root, &path, out);
+        } else if let (Ok(relative), Ok(contents)) = (path.strip_prefix(root), std::fs::read(&path))
+        {
+            let name = relative
+                .components()
+                .map(|part| part.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            out.push((name, contents));
+        }
+    }
+}
+
+/// `dir/style.css` -> `dir/style.<hash>.css`. The std hasher is only stable
+/// within one build, which is enough: a new build may at worst rename files.
+fn hashed_name(name: &str, contents: &[u8]) -> String {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(contents);
+    let hash = format!("{:016x}", hasher.finish());
+    match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() && !stem.ends_with('/') => {
+            format!("{stem}.{hash}.{ext}")
+        }
+        _ => format!("{name}.{hash}"),
+    }
+}
@@ -0,0 +1,72 @@
+//! Backups of the whole database for scripts on another machine.
+//!
+//! `VACUUM INTO` writes a consistent copy while the app keeps running:
+//! readers and writers only wait for it as they would for a long read. The
+//! copy goes to a file of its own under [`dir`], which is opened and removed
+//! right away, so it is streamed from the open handle and nothing is left
+//! behind once the download ends or the client goes away.
+
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDate;
+use rocket::http::ContentType;
+use rocket::response::{self, Responder};
+use rocket::tokio::fs::File;
+use rocket::{Request, Response};
+use rusqlite::Connection;
+use uuid::Uuid;
+
+/// A copy of the database, already unlinked, ready to be sent.
+pub struct Backup {
+    file: File,
+    len: u64,
+    name: String,
+}
+
+pub fn dir() -> PathBuf {
+    let mut dir = PathBuf::from("data");
+    dir.push("backups");
+    dir
+}
+
+/// Copies the database of `conn` to a new file in `dir` and returns its path.
+pub fn write(conn: &Connection, dir: &Path) -> Result<PathBuf, String> {
+    std::fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    let path = dir.join(format!("{}.sqlite3", Uuid::new_v4()));
+    let target = path.to_string_lossy();
+    if let Err(err) = conn.execute("VACUUM INTO ?1", [target.as_ref()]) {
+        let _ = std::fs::remove_file(&path);
+        return Err(err.to_string());
+    }
+    Ok(path)
+}
+
+/// The file name a backup taken on `today` is offered under.
+pub fn file_name(today: NaiveDate) -> String {
+    format!("lumen-{}.sqlite3", today.format("%Y-%m-%d"))
+}
+
+impl Backup {
+    /// Opens the copy at `path` and removes it from the directory.
+    pub async fn open(path: &Path, name: String) -> Option<Backup> {
+        let opened = File::open(path).await;
+        let _ = std::fs::remove_file(path);
+        let file = opened.ok()?;
+        let len = file.metadata().await.ok()?.len();
+        Some(Backup { file, len, name })
+    }
+}
+
+impl<'r> Responder<'r, 'static> for Backup {
+    fn respond_to(self, _: &'r Request<'_>) -> response::Result<'static> {
+        Response::build()
+            .header(ContentType::new("application", "vnd.sqlite3"))
+            .raw_header(
+                "Content-Disposition",
+                format!("attachment; filename=\"{}\"", self.name),
+            )
+            .raw_header("Cache-Control", "no-store")
+            .sized_body(self.len as usize, self.file)
+            .ok()
+    }
+}
@@ -0,0 +1,98 @@
+use chrono::Local;
+use rusqlite::{Connection, Error, Result, Transaction};
+
+use crate::db;
+use crate::models::BulkChange;
+
+pub struct BulkOutcome<C> {
+    pub changes: Vec<C>,
+}
+
+/// Reversible log of the rows a bulk operation touched.
+#[derive(Default)]
+pub struct ChangeSet {
+    entries: Vec<BulkChange>,
+}
+
+impl ChangeSet {
+    pub fn inserted(&mut self, table: &str, id: i64) {
+        self.entries.push(BulkChange {
+            table_name: table.to_string(),
+            row_id: id,
+            action: "insert".to_string(),
+            snapshot: None,
+        });
+    }
+
+    /// Records `id` as it is now, before the caller updates it.
+    pub fn updating(&mut self, conn: &Connection, table: &str, id: i64) -> Result<()> {
+        self.snapshot(conn, table, id, "update")
+    }
+
+    /// Records `id` as it is now, before the caller deletes it.
+    pub fn deleting(&mut self, conn: &Connection, table: &str, id: i64) -> Result<()> {
+        self.snapshot(conn, table, id, "delete")
+    }
+
+    fn snapshot(&mut self, conn: &Connection, table: &str, id: i64, action: &str) -> Result<()> {
+        let snapshot = db::row_snapshot(conn, table, id)?.ok_or(Error::QueryReturnedNoRows)?;
+        self.entries.push(BulkChange {
+            table_name: table.to_string(),
+            row_id: id,
+            action: action.to_string(),
+            snapshot: Some(snapshot),
+        });
+        Ok(())
+    }
+}
+
+/// Runs a bulk operation inside a single SQLite transaction.
+///
+/// With `dry_run` the transaction is rolled back instead of committed, so the
+/// returned changes are exactly what a real run would write. Otherwise the
+/// rows recorded in the change set are saved as one undoable operation.
+pub fn run<C>(
+    conn: &mut Connection,
+    dry_run: bool,
+    kind: &str,
+    label: &str,
+    op: impl FnOnce(&Transaction, &mut ChangeSet) -> Result<Vec<C>>,
+) -> Result<BulkOutcome<C>> {
+    let tx = conn.transaction()?;
+    let mut change_set = ChangeSet::default();
+    let changes = op(&tx, &mut change_set)?;
+    if dry_run {
+        tx.rollback()?;
+        return Ok(BulkOutcome { changes });
+    }
+    if !change_set.entries.is_empty() {
+        let operation_id =
+            db::insert_bulk_operation(&tx, kind, label, &Local::now().to_rfc3339())?;
+        for entry in &change_set.entries {
+            db::insert_bulk_change(&tx, operation_id, entry)?;
+        }
+    }
+    tx.commit()?;
+    Ok(BulkOutcome { changes })
+}
+
+/// Reverts every change of an operation, newest first, in one transaction.
+pub fn undo(conn: &mut Connection, operation_id: i64) -> Result<()> {
+    let tx = conn.transaction()?;
+    match db::bulk_operation_undone(&tx, operation_id)? {
+        None => return Err(Error::QueryReturnedNoRows),
+        Some(true) => return Ok(()),
+        Some(false) => {}
+    }
+    for change in db::bulk_changes(&tx, operation_id)?.iter().rev() {
+        let snapshot = change.snapshot.as_deref().unwrap_or("{}");
+        match change.action.as_str() {
+            "insert" => db::delete_row(&tx, &change.table_name, change.row_id)?,
+            "update" => db::restore_row(&tx, &change.table_name, change.row_id, snapshot)?,
+            "delete" => db::reinsert_row(&tx, &change.table_name, snapshot)?,
+            _ => {}
+        }
+    }
+    db::mark_bulk_operation_undone(&tx, operation_id, &Local::now().to_rfc3339())?;
+    tx.commit()
+}
@@ -0,0 +1,34 @@
+//! Cash taken out of an ATM.
+//!
+//! A withdrawal isn't spending: it moves money from a card or bank account to
+//! a cash account, as a transfer, and the cash account works as an envelope
+//! that later cash expenses draw down. Only the bank's fee is an expense. A
+//! withdrawal already entered as an expense can be turned into a transfer;
+//! such expenses are recognized by their note or payee.
+
+/// What banks put on ATM withdrawals, lowercase; the Russian stems match
+/// any case ending.
+const WITHDRAWAL_MARKERS: &[&str] = &[
+    "банкомат",
+    "снятие наличных",
+    "выдача наличных",
+    "cash withdrawal",
+];
+
+/// Note of the transfer the withdrawal form records.
+pub const WITHDRAWAL_NOTE: &str = "Снятие наличных";
+
+/// Note of the fee expense recorded next to it.
+pub const FEE_NOTE: &str = "Комиссия за снятие наличных";
+
+/// Whether a note or payee reads like an ATM withdrawal.
+pub fn looks_like_withdrawal(text: &str) -> bool {
+    let text = text.to_lowercase();
+    // "ATM" only as a word of its own, not inside one such as "Batman".
+    let atm = text
+        .split(|c: char| !c.is_alphanumeric())
+        .any(|word| word == "atm");
+    atm || WITHDRAWAL_MARKERS
+        .iter()
+        .any(|marker| text.contains(marker))
+}
@@ -0,0 +1,133 @@
+//! Turning an expense category into an income one or back.
+//!
+//! The kind decides which budgets a category can have and which side of the
+//! reports its history lands on, so the change is planned first: [`impact`]
+//! counts what it touches, and [`change_kind`] applies it as one undoable
+//! operation in the activity log.
+
+use rusqlite::{Connection, Result};
+
+use crate::bulk::ChangeSet;
+use crate::db;
+use crate::models::Category;
+
+/// What happens to the transactions filed under the category.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum History {
+    /// They keep their kind under a category of the other kind.
+    Keep,
+    /// They switch kind along with the category, which moves account balances.
+    Rekind,
+    /// They move to another category of the old kind.
+    Reassign(i64),
+}
+
+pub struct Impact {
+    pub from: String,
+    pub to: String,
+    /// The category followed by its children, which change with it.
+    pub categories: Vec<Category>,
+    pub transactions: i64,
+    pub amount_cents: i64,
+    pub hooks: i64,
+    /// Expense limits lose their meaning on an income category and go.
+    pub budgets: Vec<(String, i64)>,
+    /// A child can't stay under a parent of the other kind.
+    pub detaches: bool,
+    /// Categories of the new kind already named like one that would move.
+    pub clashes: Vec<String>,
+}
+
+pub fn other_kind(kind: &str) -> &'static str {
+    if kind == "income" {
+        "expense"
+    } else {
+        "income"
+    }
+}
+
+pub fn impact(conn: &Connection, category: &Category, all: &[Category]) -> Result<Impact> {
+    let from = category.kind.clone();
+    let to = other_kind(&from).to_string();
+    let mut categories = vec![category.clone()];
+    categories.extend(
+        all.iter()
+            .filter(|child| child.parent_id == Some(category.id))
+            .cloned(),
+    );
+    let mut impact = Impact {
+        from,
+        to,
+        categories,
+        transactions: 0,
+        amount_cents: 0,
+        hooks: 0,
+        budgets: Vec::new(),
+        detaches: category.parent_id.is_some(),
+        clashes: Vec::new(),
+    };
+    for member in &impact.categories {
+        let key = db::category_name_key(&member.name);
+        impact.clashes.extend(
+            all.iter()
+                .filter(|other| {
+                    other.kind == impact.to && db::category_name_key(&other.name) == key
+                })
+                .map(|other| other.name.clone()),
+        );
+        for (table, _, amount_cents) in db::category_entries(conn, member.id, &impact.from)? {
+            if table == "transactions" {
+                impact.transactions += 1;
+                impact.amount_cents += amount_cents;
+            } else {
+                impact.hooks += 1;
+            }
+        }
+        if impact.to == "income" {
+            impact
+                .budgets
+                .extend(db::category_budgets(conn, member.id)?);
+        }
+    }
+    Ok(impact)
+}
+
+/// Applies the planned change, recording every row it touches in `changes`.
+/// Returns the ids of the categories that changed kind.
+pub fn change_kind(
+    conn: &Connection,
+    changes: &mut ChangeSet,
+    impact: &Impact,
+    history: History,
+) -> Result<Vec<i64>> {
+    for member in &impact.categories {
+        let entries = db::category_entries(conn, member.id, &impact.from)?;
+        if history != History::Keep {
+            for (table, id, _) in &entries {
+                changes.updating(conn, table, *id)?;
+            }
+        }
+        match history {
+            History::Keep => {}
+            History::Rekind => {
+                db::rekind_category_entries(conn, member.id, &impact.from, &impact.to)?
+            }
+            History::Reassign(target) => {
+                db::move_category_entries(conn, member.id, &impact.from, target)?
+            }
+        }
+    }
+    for (table, id) in &impact.budgets {
+        changes.deleting(conn, table, *id)?;
+        db::delete_row(conn, table, *id)?;
+    }
+    let mut ids = Vec::new();
+    for (index, member) in impact.categories.iter().enumerate() {
+        changes.updating(conn, "categories", member.id)?;
+        // The category itself leaves its parent; its children stay under it.
+        let parent_id = if index == 0 { None } else { member.parent_id };
+        db::set_category_kind(conn, member.id, &impact.to, parent_id)?;
+        ids.push(member.id);
+    }
+    Ok(ids)
+}
@@ -0,0 +1,141 @@
+//! Gzip/Brotli for text responses, negotiated per request via `Accept-Encoding`.
+//!
+//! Only HTML, JSON and CSV bodies are compressed: images and receipts are
+//! already compressed, and static files are cached by the browser anyway.
+
+use std::io::{Cursor, Write};
+
+use flate2::write::GzEncoder;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::{ContentType, Header, Method};
+use rocket::{Request, Response};
+
+/// Bodies below this gain less than the header overhead.
+const MIN_SIZE: usize = 1024;
+/// Brotli's default 11 is too slow to run on every page view.
+const BROTLI_QUALITY: i32 = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    Brotli,
+    Gzip,
+}
+
+impl Encoding {
+    fn name(self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gzip",
+        }
+    }
+
+    fn encode(self, body: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            Encoding::Brotli => {
+                let mut out = Vec::new();
+                let params = brotli::enc::BrotliEncoderParams {
+                    quality: BROTLI_QUALITY,
+                    ..Default::default()
+                };
+                brotli::BrotliCompress(&mut Cursor::new(body), &mut out, &params)?;
+                Ok(out)
+            }
+            Encoding::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(body)?;
+                encoder.finish()
+            }
+        }
+    }
+}
+
+/// Picks the encoding the client weighs highest, Brotli on a tie. `q=0`
+/// refuses an encoding; `*` stands for any encoding not listed by name.
+pub fn negotiate(accept_encoding: &str) -> Option<Encoding> {
+    let mut brotli = None;
+    let mut gzip = None;
+    let mut any = None;
+    for item in accept_encoding.split(',') {
+        let mut parts = item.split(';');
+        let name = parts.next().unwrap_or_default().trim().to_ascii_lowercase();
+        let quality = parts
+            .filter_map(|param| param.trim().strip_prefix("q="))
+            .find_map(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+        match name.as_str() {
+            "br" => brotli = Some(quality),
+            "gzip" | "x-gzip" => gzip = Some(quality),
+            "*" => any = Some(quality),
+            _ => {}
+        }
+    }
+    let brotli = brotli.or(any).unwrap_or(0.0);
+    let gzip = gzip.or(any).unwrap_or(0.0);
+    if brotli > 0.0 && brotli >= gzip {
+        Some(Encoding::Brotli)
+    } else if gzip > 0.0 {
+        Some(Encoding::Gzip)
+    } else {
+        None
+    }
+}
+
+/// Templates named `*.tera` rather than `*.html.tera` are sent as plain text,
+/// so that counts as a page too.
+fn compressible(content_type: &ContentType) -> bool {
+    [
+        ContentType::HTML,
+        ContentType::Plain,
+        ContentType::JSON,
+        ContentType::CSV,
+    ]
+        .iter()
+        .any(|candidate| candidate.media_type() == content_type.media_type())
+}
+
+pub struct Compression;
+
+#[rocket::async_trait]
+impl Fairing for Compression {
+    fn info(&self) -> Info {
+        Info {
+            name: "Response compression",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        // Byte ranges count bytes of the file as stored, not as compressed.
+        if request.method() == Method::Head
+            || response.headers().contains("Content-Encoding")
+            || response.headers().contains("Accept-Ranges")
+            || !response.content_type().is_some_and(|ct| compressible(&ct))
+        {
+            return;
+        }
+        // The body may differ by this header even when it ends up uncompressed.
+        response.adjoin_header(Header::new("Vary", "Accept-Encoding"));
+        let Some(encoding) = request
+            .headers()
+            .get_one("Accept-Encoding")
+            .and_then(negotiate)
+        else {
+            return;
+        };
+        let Ok(body) = response.body_mut().to_bytes().await else {
+            return;
+        };
+        let encoded = if body.len() < MIN_SIZE {
+            None
+        } else {
+            encoding.encode(&body).ok()
+        };
+        match encoded {
+            Some(encoded) => {
+                response.set_header(Header::new("Content-Encoding", encoding.name()));
+                response.set_sized_body(encoded.len(), Cursor::new(encoded));
+            }
+            None => response.set_sized_body(body.len(), Cursor::new(body)),
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//! Per-session tokens against forms posted from other sites.
+//!
+//! Every browser session gets a random token stored next to it. Pages go out
+//! with a hidden `csrf_token` field added to each `method="post"` form, and a
+//! POST carrying a session cookie only reaches its handler with the session's
+//! token, in that field or in an `X-CSRF-Token` header. A fairing can't answer
+//! a request itself, so a refused one is rerouted to [`REJECTED`], which
+//! answers 403.
+//!
+//! The field is added as the first one of its form, so it falls within the
+//! part of the body a fairing may look at without taking it from the handler,
+//! both URL-encoded and multipart.
+//!
+//! Login and setup come before there is a session. API calls with an
+//! `Authorization: Bearer` header, the API's sign-in endpoints and webhooks
+//! carry their secret in the request itself and are left alone; an API call
+//! authenticated by the session cookie needs the token like a form does.
+
+use std::io::Cursor;
+
+use rocket::data::Data;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::uri::Origin;
+use rocket::http::Method;
+use rocket::{Request, Response};
+
+use crate::api_tokens;
+use crate::db::{self, DbPool};
+use crate::pages;
+use crate::sessions;
+
+pub const FIELD: &str = "csrf_token";
+pub const HEADER: &str = "X-CSRF-Token";
+pub const REJECTED: &str = "/csrf/rejected";
+
+/// As much of a body as Rocket lets a fairing peek at.
+const PEEK_BYTES: usize = 512;
+
+/// The token of the request's session, looked up once per request.
+struct SessionToken(Option<String>);
+
+fn exempt(request: &Request<'_>) -> bool {
+    let path = request.uri().path().as_str();
+    matches!(path, "/login" | "/setup" | "/telegram/webhook")
+        || path.starts_with("/api/v1/auth/")
+        || path.starts_with("/api/hooks/")
+        || (path.starts_with("/api/") && api_tokens::bearer(request).is_some())
+}
+
+fn session_token(request: &Request<'_>) -> Option<String> {
+    let pool = request.rocket().state::<DbPool>()?;
+    let session = sessions::token(request.cookies())?;
+    let conn = pool.get().ok()?;
+    db::session_csrf_token(&conn, &session).ok().flatten()
+}
+
+/// The token sent as the first field of a URL-encoded or multipart body.
+fn form_token(body: &[u8]) -> Option<String> {
+    let body = String::from_utf8_lossy(body);
+    let value = match body.strip_prefix("csrf_token=") {
+        Some(rest) => rest.split('&').next(),
+        None => body
+            .split_once("name=\"csrf_token\"\r\n\r\n")
+            .and_then(|(_, rest)| rest.split("\r\n").next()),
+    };
+    value.map(str::to_string)
+}
+
+/// Compares every byte, so the time taken says nothing about the token.
+pub(crate) fn same_token(sent: &str, expected: &str) -> bool {
+    sent.len() == expected.len()
+        && sent
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// `page` with a hidden token field opening each POST form.
+pub fn with_token_fields(page: &str, token: &str) -> String {
+    let field = format!(r#"<input type="hidden" name="{FIELD}" value="{token}" />"#);
+    let mut out = String::with_capacity(page.len());
+    let mut rest = page;
+    while let Some(start) = rest.find("<form") {
+        let Some(end) = rest[start..].find('>') else {
+            break;
+        };
+        let (tag, after) = rest.split_at(start + end + 1);
+        out.push_str(tag);
+        if tag[start..].contains(r#"method="post""#) {
+            out.push_str(&field);
+        }
+        rest = after;
+    }
+    out.push_str(rest);
+    out
+}
+
+pub struct Protection;
+
+#[rocket::async_trait]
+impl Fairing for Protection {
+    fn info(&self) -> Info {
+        Info {
+            name: "CSRF protection",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, data: &mut Data<'_>) {
+        let token = session_token(request);
+        request.local_cache(|| SessionToken(token.clone()));
+        if request.method() != Method::Post || exempt(request) {
+            return;
+        }
+        // Without a session the handler sends the visitor to log in anyway.
+        let Some(expected) = token else {
+            return;
+        };
+        let sent = match request.headers().get_one(HEADER) {
+            Some(header) => Some(header.to_string()),
+            None => form_token(data.peek(PEEK_BYTES).await),
+        };
+        if !sent.is_some_and(|sent| same_token(&sent, &expected)) {
+            request.set_uri(Origin::parse(REJECTED).expect("valid route"));
+        }
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let SessionToken(Some(token)) = request.local_cache(|| SessionToken(None)) else {
+            return;
+        };
+        if response.headers().contains("Content-Encoding")
+            || !response.content_type().is_some_and(|ct| pages::is_page(&ct))
+        {
+            return;
+        }
+        let Ok(page) = response.body_mut().to_string().await else {
+            return;
+        };
+        let page = with_token_fields(&page, token);
+        response.set_sized_body(page.len(), Cursor::new(page));
+    }
+}
@@ -0,0 +1,116 @@
+//! The end-of-day summary.
+//!
+//! Whoever set a time for it in the settings gets one message a day once that
+//! local time has passed, by Telegram, email or both: how many transactions
+//! today has, its income and expenses by category, and what is left of the
+//! week's budget. The week's budget is this month's budgets, carry-over
+//! included, spread evenly over the days of the month; it is spent by the
+//! expenses since Monday in budgeted categories. A summary that no channel
+//! delivered is retried on the next check the same day.
+
+use std::time::Duration;
+
+use chrono::{Datelike, Local, Months, NaiveDate};
+use rusqlite::{Connection, Result};
+
+use crate::db::{self, DbPool};
+use crate::digest::{category_lines, week_start};
+use crate::format_money;
+use crate::login_alert::Mailer;
+use crate::telegram::TelegramConfig;
+
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+/// Categories the day's summary names, see [`category_lines`].
+const TOP_CATEGORIES: usize = 5;
+
+/// A week's share of the budget limits of the month `today` falls in.
+fn weekly_budget(month_limit_cents: i64, today: NaiveDate) -> i64 {
+    let first = today.with_day(1).unwrap_or(today);
+    let days = (first + Months::new(1) - first).num_days();
+    (month_limit_cents * 7 + days / 2) / days
+}
+
+/// The summary text for `today`.
+pub fn build(conn: &Connection, user_id: i64, today: NaiveDate) -> Result<String> {
+    let refunds = db::refund_month(conn, user_id)?;
+    let day = today.format("%Y-%m-%d").to_string();
+    let totals = db::day_totals(conn, &day, refunds)?;
+    let mut lines = vec![format!("Итоги дня {day}")];
+
+    lines.push(String::new());
+    if totals.entries == 0 {
+        lines.push("Сегодня операций нет".to_string());
+    } else {
+        lines.push(format!("Операций: {}", totals.entries));
+        if totals.income_cents != 0 {
+            lines.push(format!("Доход: {}", format_money(totals.income_cents)));
+        }
+        lines.push(format!("Расход: {}", format_money(totals.expense_cents)));
+        let expenses = db::expenses_by_category(conn, &day, &day, refunds)?;
+        lines.extend(category_lines(&expenses, TOP_CATEGORIES));
+    }
+
+    let month = today.format("%Y-%m").to_string();
+    let month_limit: i64 = db::dashboard_budgets(conn, &month, refunds)?
+        .iter()
+        .map(|budget| budget.budget_cents + budget.carried_cents)
+        .sum();
+    if month_limit > 0 {
+        let budget = weekly_budget(month_limit, today);
+        // This month's budgets only cover the part of the week in this month.
+        let from = week_start(today).max(today.with_day(1).unwrap_or(today));
+        let spent = db::budgeted_expenses(
+            conn,
+            &month,
+            &from.format("%Y-%m-%d").to_string(),
+            &day,
+            refunds,
+        )?;
+        lines.push(String::new());
+        if spent > budget {
+            lines.push(format!(
+                "Бюджет недели превышен на {} из {}",
+                format_money(spent - budget),
+                format_money(budget)
+            ));
+        } else {
+            lines.push(format!(
+                "Бюджет недели: осталось {} из {}",
+                format_money(budget - spent),
+                format_money(budget)
+            ));
+        }
+    }
+    Ok(lines.join("\n"))
+}
+
+/// The scheduled check: sends the summary to whoever's time has come and
+/// hasn't had it today.
+pub fn run_due(pool: &DbPool, telegram: Option<&TelegramConfig>, mailer: Option<&Mailer>) {
+    let now = Local::now();
+    let today = now.date_naive();
+    let today_ymd = today.format("%Y-%m-%d").to_string();
+    let Ok(conn) = pool.get() else {
+        return;
+    };
+    let recipients =
+        db::daily_summary_recipients(&conn, &today_ymd, &now.format("%H:%M").to_string())
+            .unwrap_or_default();
+    for recipient in recipients {
+        let Ok(text) = build(&conn, recipient.user_id, today) else {
+            continue;
+        };
+        let mut sent = false;
+        if let (Some(config), Some(chat_id)) = (telegram, recipient.chat_id) {
+            sent |= config.send_message(chat_id, &text, None).is_ok();
+        }
+        if let (Some(mailer), Some(email)) = (mailer, &recipient.email) {
+            sent |= mailer
+                .send_letter(email, &format!("Итоги дня {today_ymd}"), text.clone())
+                .is_ok();
+        }
+        if sent {
+            let _ = db::mark_daily_summary_sent(&conn, recipient.user_id, &today_ymd);
+        }
+    }
+}
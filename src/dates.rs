@@ -0,0 +1,97 @@
+//! Repairing transaction dates that aren't `YYYY-MM-DD`.
+//!
+//! Early manual entries weren't checked, so some dates read `2024-7-3` or
+//! `03.07.2024` and month filters, which match on the text, miss them. A date
+//! with one possible reading is rewritten by [`fix`]; the rest are left for
+//! someone to pick on the review list.
+
+use chrono::NaiveDate;
+use rusqlite::{Connection, Result};
+
+use crate::bulk::ChangeSet;
+use crate::db;
+use crate::models::MalformedDate;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Reading {
+    Fixed(NaiveDate),
+    /// Day and month could be either way round, e.g. `03/07/2024`.
+    Ambiguous(Vec<NaiveDate>),
+    Unreadable,
+}
+
+/// How `raw` can be read as a date; `None` when it already is `YYYY-MM-DD`.
+///
+/// Dotted dates are Russian, day first. Slashed ones may be written either
+/// way, so both readings are offered unless only one is a real date.
+pub fn read(raw: &str) -> Option<Reading> {
+    if NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+        .is_ok_and(|date| date.format("%Y-%m-%d").to_string() == raw)
+    {
+        return None;
+    }
+    let raw = raw.trim();
+    let parts = raw
+        .split(['-', '.', '/', ' '])
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>();
+    let numbers = parts
+        .iter()
+        .filter(|part| part.chars().all(|ch| ch.is_ascii_digit()))
+        .filter_map(|part| part.parse::<u32>().ok())
+        .collect::<Vec<_>>();
+    if parts.len() != 3 || numbers.len() != 3 {
+        return Some(Reading::Unreadable);
+    }
+    let ymd = |year: u32, month: u32, day: u32| NaiveDate::from_ymd_opt(year as i32, month, day);
+    let mut candidates = Vec::new();
+    if parts[0].len() == 4 {
+        candidates.extend(ymd(numbers[0], numbers[1], numbers[2]));
+    } else if matches!(parts[2].len(), 2 | 4) {
+        let year = if parts[2].len() == 2 {
+            2000 + numbers[2]
+        } else {
+            numbers[2]
+        };
+        candidates.extend(ymd(year, numbers[1], numbers[0]));
+        if !raw.contains('.') {
+            candidates.extend(ymd(year, numbers[0], numbers[1]));
+        }
+        candidates.dedup();
+    }
+    Some(match candidates.len() {
+        0 => Reading::Unreadable,
+        1 => Reading::Fixed(candidates[0]),
+        _ => Reading::Ambiguous(candidates),
+    })
+}
+
+/// Transactions whose date isn't `YYYY-MM-DD`, with how each can be read.
+pub fn scan(conn: &Connection) -> Result<Vec<(MalformedDate, Reading)>> {
+    let mut out = Vec::new();
+    for record in db::malformed_dates(conn)? {
+        if let Some(reading) = read(&record.occurred_on) {
+            out.push((record, reading));
+        }
+    }
+    Ok(out)
+}
+
+/// Rewrites every date with a single reading, recording the old values in
+/// `changes`. Returns the ids of the fixed transactions.
+pub fn fix(conn: &Connection, changes: &mut ChangeSet) -> Result<Vec<i64>> {
+    let mut fixed = Vec::new();
+    for (record, reading) in scan(conn)? {
+        if let Reading::Fixed(date) = reading {
+            set(conn, changes, record.id, date)?;
+            fixed.push(record.id);
+        }
+    }
+    Ok(fixed)
+}
+
+/// Sets the date someone picked for one transaction.
+pub fn set(conn: &Connection, changes: &mut ChangeSet, id: i64, date: NaiveDate) -> Result<()> {
+    changes.updating(conn, "transactions", id)?;
+    db::set_transaction_date(conn, id, &date.format("%Y-%m-%d").to_string())
+}
@@ -1,18 +1,155 @@
 use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
 
 use r2d2::Pool;
+use r2d2::event::{CheckoutEvent, HandleEvent, TimeoutEvent};
 use r2d2_sqlite::SqliteConnectionManager;
-use rusqlite::{params, Connection, Result};
+use rusqlite::types::{Value, ValueRef};
+use rusqlite::{params, params_from_iter, Connection, Result};
+use sha2::{Digest, Sha256};
 
 use crate::models::{
-    BudgetRecord, Category, DashboardBudget, ReportCategory, ReportMonth, TransactionRecord, User,
+    AcceptedSuggestion, Account, ApiSession, ApiToken, AuditEntry, BrowserSession, BudgetRecord, BulkChange, BulkOperationRecord, CashEnvelope, Category, CategoryDuplicate, CategoryUse, DailySummaryRecipient, DailySummarySettings, DashboardBudget, DayTotals, Dispute,
+    ExchangeRate, Holding, InboundHook, ItemCount, ItemPrice, Job, Loan, LoanPayment, MalformedDate, Meter, MeterReading, NewApiSession, NewInboundHook, NewLoan, NewNotification, NewReceiptItem, NewRule, NewTransaction, NotificationPreference,
+    NotificationRecord, PendingNotification, PoolStats, ReportAccountMonth, ReportCategory, ReportCategoryMonth, ReportDay, ReportFigure, ReportMonth, ReportPayee, ReportSnapshot, ReportTag,
+    ReceiptCandidate, ReceiptItem, ReceiptShare, ReceiptShareView, ReceiptUpload, RememberToken, Rule, StandingBudget, StatementLine, Tariff, TransactionRecord, TrashItem, User, UtilityExpense, WithdrawalCandidate,
 };
+use crate::disputes::RefundMonth;
+use crate::money::Rounding;
+use crate::query::TransactionQuery;
 
 pub type DbPool = Pool<SqliteConnectionManager>;
 
-pub fn init_db(path: &Path) -> DbPool {
-    let manager = SqliteConnectionManager::file(path);
-    let pool = Pool::new(manager).expect("db pool");
+/// Connection pool settings, read from the environment.
+///
+/// `LUMEN_DB_POOL_SIZE` caps open connections, `LUMEN_DB_POOL_TIMEOUT_SECS` is how
+/// long a request waits for one, `LUMEN_DB_MAX_LIFETIME_SECS` recycles connections
+/// (0 keeps them forever) and `LUMEN_DB_STATEMENT_CACHE` sizes each connection's
+/// prepared statement cache. Unset values keep the r2d2 and rusqlite defaults.
+#[derive(Clone, Debug)]
+pub struct PoolConfig {
+    pub max_size: u32,
+    pub connection_timeout: Duration,
+    pub max_lifetime: Option<Duration>,
+    pub statement_cache: usize,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        PoolConfig {
+            max_size: 10,
+            connection_timeout: Duration::from_secs(30),
+            max_lifetime: Some(Duration::from_secs(30 * 60)),
+            statement_cache: 16,
+        }
+    }
+}
+
+impl PoolConfig {
+    pub fn from_env() -> Self {
+        fn var<T: std::str::FromStr>(name: &str) -> Option<T> {
+            std::env::var(name).ok()?.trim().parse().ok()
+        }
+        let default = PoolConfig::default();
+        PoolConfig {
+            max_size: var("LUMEN_DB_POOL_SIZE")
+                .filter(|size| *size > 0)
+                .unwrap_or(default.max_size),
+            connection_timeout: var("LUMEN_DB_POOL_TIMEOUT_SECS")
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs)
+                .unwrap_or(default.connection_timeout),
+            max_lifetime: match var::<u64>("LUMEN_DB_MAX_LIFETIME_SECS") {
+                Some(0) => None,
+                Some(secs) => Some(Duration::from_secs(secs)),
+                None => default.max_lifetime,
+            },
+            statement_cache: var("LUMEN_DB_STATEMENT_CACHE").unwrap_or(default.statement_cache),
+        }
+    }
+}
+
+/// Checkout counters fed by r2d2 pool events.
+#[derive(Debug, Default)]
+pub struct PoolMetrics {
+    checkouts: AtomicU64,
+    timeouts: AtomicU64,
+    wait_micros: AtomicU64,
+    max_wait_micros: AtomicU64,
+}
+
+impl PoolMetrics {
+    pub fn stats(&self, pool: &DbPool) -> PoolStats {
+        let state = pool.state();
+        let checkouts = self.checkouts.load(Ordering::Relaxed);
+        let wait_micros = self.wait_micros.load(Ordering::Relaxed);
+        PoolStats {
+            max_size: pool.max_size(),
+            connections: state.connections,
+            idle_connections: state.idle_connections,
+            checkouts,
+            timeouts: self.timeouts.load(Ordering::Relaxed),
+            avg_wait_ms: if checkouts == 0 {
+                0.0
+            } else {
+                wait_micros as f64 / checkouts as f64 / 1000.0
+            },
+            max_wait_ms: self.max_wait_micros.load(Ordering::Relaxed) as f64 / 1000.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct MetricsHandler(Arc<PoolMetrics>);
+
+impl HandleEvent for MetricsHandler {
+    fn handle_checkout(&self, event: CheckoutEvent) {
+        let micros = event.duration().as_micros() as u64;
+        self.0.checkouts.fetch_add(1, Ordering::Relaxed);
+        self.0.wait_micros.fetch_add(micros, Ordering::Relaxed);
+        self.0.max_wait_micros.fetch_max(micros, Ordering::Relaxed);
+    }
+
+    fn handle_timeout(&self, _event: TimeoutEvent) {
+        self.0.timeouts.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+pub fn init_db(path: &Path, config: &PoolConfig, metrics: Arc<PoolMetrics>) -> DbPool {
+    init_pool(SqliteConnectionManager::file(path), config, metrics)
+}
+
+/// Fresh in-memory database; shared cache lets every pooled connection see it.
+#[cfg(test)]
+pub fn init_memory_db(config: &PoolConfig, metrics: Arc<PoolMetrics>) -> DbPool {
+    let name = uuid::Uuid::new_v4();
+    let manager =
+        SqliteConnectionManager::file(format!("file:lumen-{name}?mode=memory&cache=shared"));
+    init_pool(manager, config, metrics)
+}
+
+fn init_pool(
+    manager: SqliteConnectionManager,
+    config: &PoolConfig,
+    metrics: Arc<PoolMetrics>,
+) -> DbPool {
+    let statement_cache = config.statement_cache;
+    let manager = manager.with_init(move |conn| {
+        // Per connection, so every one enforces references and cascades.
+        conn.execute_batch("PRAGMA foreign_keys = ON")?;
+        conn.set_prepared_statement_cache_capacity(statement_cache);
+        crate::query_inspector::install(conn);
+        Ok(())
+    });
+    let pool = Pool::builder()
+        .max_size(config.max_size)
+        .connection_timeout(config.connection_timeout)
+        .max_lifetime(config.max_lifetime)
+        .event_handler(Box::new(MetricsHandler(metrics)))
+        .build(manager)
+        .expect("db pool");
     {
         let conn = pool.get().expect("db connection");
         run_migrations(&conn).expect("db migrations");
@@ -20,7 +157,7 @@ pub fn init_db(path: &Path) -> DbPool {
     pool
 }
 
-fn run_migrations(conn: &Connection) -> Result<()> {
+pub fn run_migrations(conn: &Connection) -> Result<()> {
     conn.execute_batch(
         "
         PRAGMA foreign_keys = ON;
@@ -31,9 +168,16 @@ fn run_migrations(conn: &Connection) -> Result<()> {
             kind TEXT NOT NULL CHECK(kind IN ('income', 'expense'))
         );
 
+        CREATE TABLE IF NOT EXISTS accounts (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            kind TEXT NOT NULL CHECK(kind IN ('cash', 'card', 'bank')),
+            opening_balance_cents INTEGER NOT NULL DEFAULT 0
+        );
+
         CREATE TABLE IF NOT EXISTS transactions (
             id INTEGER PRIMARY KEY,
-            kind TEXT NOT NULL CHECK(kind IN ('income', 'expense')),
+            kind TEXT NOT NULL CHECK(kind IN ('income', 'expense', 'transfer', 'adjustment')),
             amount_cents INTEGER NOT NULL,
             category_id INTEGER,
             occurred_on TEXT NOT NULL,
@@ -42,6 +186,19 @@ fn run_migrations(conn: &Connection) -> Result<()> {
             FOREIGN KEY(category_id) REFERENCES categories(id)
         );
 
+        CREATE TABLE IF NOT EXISTS tags (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE
+        );
+
+        CREATE TABLE IF NOT EXISTS transaction_tags (
+            transaction_id INTEGER NOT NULL,
+            tag_id INTEGER NOT NULL,
+            PRIMARY KEY(transaction_id, tag_id),
+            FOREIGN KEY(transaction_id) REFERENCES transactions(id) ON DELETE CASCADE,
+            FOREIGN KEY(tag_id) REFERENCES tags(id) ON DELETE CASCADE
+        );
+
         CREATE TABLE IF NOT EXISTS budgets (
             id INTEGER PRIMARY KEY,
             category_id INTEGER NOT NULL,
@@ -50,6 +207,13 @@ fn run_migrations(conn: &Connection) -> Result<()> {
             FOREIGN KEY(category_id) REFERENCES categories(id)
         );
 
+        CREATE TABLE IF NOT EXISTS standing_budgets (
+            id INTEGER PRIMARY KEY,
+            category_id INTEGER NOT NULL UNIQUE,
+            amount_cents INTEGER NOT NULL,
+            FOREIGN KEY(category_id) REFERENCES categories(id)
+        );
+
         CREATE TABLE IF NOT EXISTS users (
             id INTEGER PRIMARY KEY,
             username TEXT NOT NULL UNIQUE,
@@ -64,20 +228,532 @@ fn run_migrations(conn: &Connection) -> Result<()> {
             created_at TEXT NOT NULL,
             FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
         );
+
+        CREATE TABLE IF NOT EXISTS notification_preferences (
+            user_id INTEGER NOT NULL,
+            event TEXT NOT NULL,
+            channel TEXT NOT NULL,
+            enabled INTEGER NOT NULL,
+            PRIMARY KEY(user_id, event, channel),
+            FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS notifications (
+            id INTEGER PRIMARY KEY,
+            user_id INTEGER NOT NULL,
+            event TEXT NOT NULL,
+            channel TEXT NOT NULL,
+            title TEXT NOT NULL,
+            body TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            read_at TEXT,
+            sent_at TEXT,
+            payload TEXT,
+            FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS bulk_operations (
+            id INTEGER PRIMARY KEY,
+            kind TEXT NOT NULL,
+            label TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            undone_at TEXT
+        );
+
+        CREATE TABLE IF NOT EXISTS bulk_changes (
+            id INTEGER PRIMARY KEY,
+            operation_id INTEGER NOT NULL,
+            table_name TEXT NOT NULL,
+            row_id INTEGER NOT NULL,
+            action TEXT NOT NULL CHECK(action IN ('insert', 'update', 'delete')),
+            snapshot TEXT,
+            FOREIGN KEY(operation_id) REFERENCES bulk_operations(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS revaluations (
+            id INTEGER PRIMARY KEY,
+            account_id INTEGER NOT NULL,
+            month TEXT NOT NULL,
+            rate REAL NOT NULL,
+            transaction_id INTEGER NOT NULL,
+            UNIQUE(account_id, month),
+            FOREIGN KEY(account_id) REFERENCES accounts(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS exchange_rates (
+            currency TEXT NOT NULL,
+            rate_date TEXT NOT NULL,
+            rate REAL NOT NULL,
+            source TEXT NOT NULL CHECK(source IN ('auto', 'manual')),
+            updated_at TEXT NOT NULL,
+            PRIMARY KEY(currency, rate_date)
+        );
+
+        CREATE TABLE IF NOT EXISTS budget_increases (
+            fiscal_year INTEGER PRIMARY KEY,
+            applied_at TEXT NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS accountant_exports (
+            month TEXT PRIMARY KEY,
+            status TEXT NOT NULL CHECK(status IN ('sent', 'failed')),
+            attempted_at TEXT NOT NULL,
+            detail TEXT
+        );
+
+        CREATE TABLE IF NOT EXISTS api_tokens (
+            id INTEGER PRIMARY KEY,
+            user_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            token TEXT NOT NULL UNIQUE,
+            scope TEXT NOT NULL CHECK(scope IN ('read', 'write', 'admin')),
+            created_at TEXT NOT NULL,
+            last_used_at TEXT,
+            FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS known_devices (
+            user_id INTEGER NOT NULL,
+            user_agent TEXT NOT NULL,
+            first_seen_at TEXT NOT NULL,
+            last_seen_at TEXT NOT NULL,
+            PRIMARY KEY(user_id, user_agent),
+            FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS login_failures (
+            key TEXT PRIMARY KEY,
+            failures INTEGER NOT NULL,
+            last_failed_at TEXT NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS remember_tokens (
+            id INTEGER PRIMARY KEY,
+            user_id INTEGER NOT NULL,
+            series TEXT NOT NULL UNIQUE,
+            token_hash TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            expires_at TEXT NOT NULL,
+            used_at TEXT,
+            FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS report_snapshots (
+            month TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            category_name TEXT NOT NULL DEFAULT '',
+            amount_cents INTEGER NOT NULL,
+            refunds TEXT NOT NULL,
+            closed_at TEXT NOT NULL,
+            PRIMARY KEY (month, kind, category_name)
+        );
+
+        CREATE TABLE IF NOT EXISTS category_suggestions (
+            field TEXT NOT NULL CHECK(field IN ('payee', 'note')),
+            text TEXT NOT NULL,
+            category_id INTEGER NOT NULL,
+            accepted INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (field, text, category_id),
+            FOREIGN KEY(category_id) REFERENCES categories(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS login_alerts (
+            token TEXT PRIMARY KEY,
+            user_id INTEGER NOT NULL,
+            user_agent TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            used_at TEXT,
+            FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS receipt_uploads (
+            id INTEGER PRIMARY KEY,
+            file_name TEXT NOT NULL,
+            original_name TEXT,
+            occurred_on TEXT,
+            amount_cents INTEGER,
+            uploaded_at TEXT NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS receipt_hashes (
+            file_name TEXT PRIMARY KEY,
+            sha256 TEXT NOT NULL
+        );
+
+        CREATE INDEX IF NOT EXISTS receipt_hashes_sha256 ON receipt_hashes(sha256);
+
+        CREATE TABLE IF NOT EXISTS receipt_items (
+            id INTEGER PRIMARY KEY,
+            transaction_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            name_key TEXT NOT NULL,
+            quantity REAL NOT NULL CHECK(quantity > 0),
+            price_cents INTEGER NOT NULL CHECK(price_cents >= 0),
+            FOREIGN KEY(transaction_id) REFERENCES transactions(id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS receipt_items_transaction ON receipt_items(transaction_id);
+        CREATE INDEX IF NOT EXISTS receipt_items_name_key ON receipt_items(name_key);
+
+        CREATE TABLE IF NOT EXISTS receipt_shares (
+            id INTEGER PRIMARY KEY,
+            user_id INTEGER NOT NULL,
+            token_hash TEXT NOT NULL UNIQUE,
+            file_name TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            expires_at TEXT NOT NULL,
+            revoked_at TEXT,
+            FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS receipt_share_views (
+            id INTEGER PRIMARY KEY,
+            share_id INTEGER NOT NULL,
+            viewed_at TEXT NOT NULL,
+            ip TEXT,
+            user_agent TEXT NOT NULL,
+            FOREIGN KEY(share_id) REFERENCES receipt_shares(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS api_sessions (
+            id INTEGER PRIMARY KEY,
+            user_id INTEGER NOT NULL,
+            family TEXT NOT NULL,
+            access_token TEXT NOT NULL UNIQUE,
+            access_expires_at TEXT NOT NULL,
+            refresh_token TEXT NOT NULL UNIQUE,
+            refresh_expires_at TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            rotated_at TEXT,
+            revoked_at TEXT,
+            FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_api_sessions_family ON api_sessions(family);
+
+        CREATE TABLE IF NOT EXISTS inbound_hooks (
+            id INTEGER PRIMARY KEY,
+            user_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            secret TEXT NOT NULL UNIQUE,
+            kind TEXT NOT NULL CHECK(kind IN ('income', 'expense')),
+            amount_field TEXT NOT NULL,
+            date_field TEXT,
+            note_field TEXT,
+            category_field TEXT,
+            category_id INTEGER,
+            account_id INTEGER,
+            created_at TEXT NOT NULL,
+            last_used_at TEXT,
+            FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE,
+            FOREIGN KEY(category_id) REFERENCES categories(id),
+            FOREIGN KEY(account_id) REFERENCES accounts(id)
+        );
+
+        CREATE TABLE IF NOT EXISTS holdings (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            kind TEXT NOT NULL CHECK(kind IN ('asset', 'liability'))
+        );
+
+        CREATE TABLE IF NOT EXISTS holding_valuations (
+            id INTEGER PRIMARY KEY,
+            holding_id INTEGER NOT NULL,
+            valued_on TEXT NOT NULL,
+            amount_cents INTEGER NOT NULL,
+            UNIQUE(holding_id, valued_on),
+            FOREIGN KEY(holding_id) REFERENCES holdings(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS jobs (
+            id INTEGER PRIMARY KEY,
+            user_id INTEGER NOT NULL,
+            label TEXT NOT NULL,
+            state TEXT NOT NULL CHECK(state IN ('running', 'done', 'failed')),
+            done INTEGER NOT NULL DEFAULT 0,
+            total INTEGER NOT NULL DEFAULT 0,
+            result_url TEXT,
+            error TEXT,
+            file_path TEXT,
+            file_name TEXT,
+            created_at TEXT NOT NULL,
+            finished_at TEXT,
+            FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS loans (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            principal_cents INTEGER NOT NULL CHECK(principal_cents > 0),
+            annual_rate REAL NOT NULL CHECK(annual_rate >= 0),
+            term_months INTEGER NOT NULL CHECK(term_months > 0),
+            first_month TEXT NOT NULL,
+            payment_day INTEGER NOT NULL CHECK(payment_day BETWEEN 1 AND 31)
+        );
+
+        CREATE TABLE IF NOT EXISTS loan_payments (
+            loan_id INTEGER NOT NULL,
+            transaction_id INTEGER NOT NULL UNIQUE,
+            FOREIGN KEY(loan_id) REFERENCES loans(id) ON DELETE CASCADE,
+            FOREIGN KEY(transaction_id) REFERENCES transactions(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS meters (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            kind TEXT NOT NULL CHECK(kind IN ('electricity', 'cold_water', 'hot_water', 'gas'))
+        );
+
+        CREATE TABLE IF NOT EXISTS meter_readings (
+            id INTEGER PRIMARY KEY,
+            meter_id INTEGER NOT NULL,
+            month TEXT NOT NULL,
+            value REAL NOT NULL CHECK(value >= 0),
+            read_on TEXT NOT NULL,
+            transaction_id INTEGER,
+            UNIQUE(meter_id, month),
+            FOREIGN KEY(meter_id) REFERENCES meters(id) ON DELETE CASCADE,
+            FOREIGN KEY(transaction_id) REFERENCES transactions(id) ON DELETE SET NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS tariffs (
+            id INTEGER PRIMARY KEY,
+            kind TEXT NOT NULL CHECK(kind IN ('electricity', 'cold_water', 'hot_water', 'gas')),
+            rate_cents INTEGER NOT NULL CHECK(rate_cents > 0),
+            effective_from TEXT NOT NULL,
+            UNIQUE(kind, effective_from)
+        );
+
+        CREATE TABLE IF NOT EXISTS rules (
+            id INTEGER PRIMARY KEY,
+            pattern TEXT NOT NULL,
+            min_cents INTEGER,
+            max_cents INTEGER,
+            category_id INTEGER REFERENCES categories(id),
+            kind TEXT CHECK(kind IN ('income', 'expense')),
+            tags TEXT,
+            created_at TEXT NOT NULL
+        );
         ",
     )?;
     ensure_column(conn, "transactions", "receipt_path", "TEXT")?;
+    ensure_column(conn, "transactions", "account_id", "INTEGER REFERENCES accounts(id)")?;
+    ensure_column(conn, "transactions", "to_account_id", "INTEGER REFERENCES accounts(id)")?;
+    update_transaction_kinds(conn)?;
+    // Every pooled connection enforces foreign keys (see `init_pool`), but one
+    // opened without them, such as the sqlite3 shell's, would leave tag, loan
+    // payment, receipt item and meter reading links behind, so triggers clean
+    // them up too. They and the index are created after a possible table
+    // rebuild.
+    conn.execute_batch(
+        "
+        CREATE TRIGGER IF NOT EXISTS transaction_tags_cleanup
+        AFTER DELETE ON transactions
+        BEGIN
+            DELETE FROM transaction_tags WHERE transaction_id = OLD.id;
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS loan_payments_cleanup
+        AFTER DELETE ON transactions
+        BEGIN
+            DELETE FROM loan_payments WHERE transaction_id = OLD.id;
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS receipt_items_cleanup
+        AFTER DELETE ON transactions
+        BEGIN
+            DELETE FROM receipt_items WHERE transaction_id = OLD.id;
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS meter_readings_cleanup
+        AFTER DELETE ON transactions
+        BEGIN
+            UPDATE meter_readings SET transaction_id = NULL WHERE transaction_id = OLD.id;
+        END;
+
+        CREATE INDEX IF NOT EXISTS transactions_category_date
+            ON transactions(category_id, occurred_on);
+        ",
+    )?;
+    ensure_column(conn, "categories", "parent_id", "INTEGER REFERENCES categories(id)")?;
+    ensure_column(conn, "categories", "name_key", "TEXT")?;
+    ensure_column(conn, "categories", "default_amount_cents", "INTEGER")?;
+    normalize_category_names(conn)?;
+    ensure_category_name_index(conn)?;
+    if !has_column(conn, "categories", "allow_receipt")? {
+        ensure_column(conn, "categories", "allow_receipt", "INTEGER NOT NULL DEFAULT 0")?;
+        // Receipts used to be taken for utility bills only.
+        conn.execute(
+            "UPDATE categories SET allow_receipt = 1 WHERE kind = 'expense' AND name_key = ?1",
+            params![category_name_key("ЖКХ")],
+        )?;
+    }
+    ensure_column(conn, "accounts", "currency", "TEXT NOT NULL DEFAULT 'RUB'")?;
+    ensure_column(conn, "notifications", "payload", "TEXT")?;
+    ensure_column(conn, "users", "telegram_chat_id", "INTEGER")?;
+    ensure_column(conn, "users", "email", "TEXT")?;
+    ensure_column(conn, "budgets", "rollover", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_column(conn, "standing_budgets", "yearly_increase_percent", "REAL")?;
+    ensure_column(conn, "jobs", "expires_at", "TEXT")?;
+    ensure_column(conn, "transactions", "payee", "TEXT")?;
+    ensure_column(conn, "transactions", "deleted_at", "TEXT")?;
+    ensure_column(conn, "budgets", "deleted_at", "TEXT")?;
+    ensure_column(conn, "standing_budgets", "deleted_at", "TEXT")?;
+    ensure_column(conn, "budgets", "note", "TEXT")?;
+    ensure_column(conn, "standing_budgets", "note", "TEXT")?;
+    ensure_column(conn, "sessions", "csrf_token", "TEXT")?;
+    ensure_column(conn, "transactions", "starred", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_column(conn, "transactions", "status", "TEXT NOT NULL DEFAULT 'normal'")?;
+    ensure_column(conn, "transactions", "disputed_on", "TEXT")?;
+    ensure_column(conn, "transactions", "effective_month", "TEXT")?;
+    ensure_column(conn, "transactions", "fiscal_id", "TEXT")?;
+    ensure_column(conn, "receipt_uploads", "recognized", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_column(
+        conn,
+        "transactions",
+        "refund_of",
+        "INTEGER REFERENCES transactions(id) ON DELETE SET NULL",
+    )?;
+    ensure_column(conn, "users", "refund_month", "TEXT NOT NULL DEFAULT 'refund'")?;
+    ensure_column(conn, "users", "report_rounding", "TEXT NOT NULL DEFAULT 'kopecks'")?;
+    ensure_column(conn, "sessions", "expires_at", "TEXT")?;
+    ensure_column(conn, "sessions", "last_used_at", "TEXT")?;
+    ensure_column(conn, "sessions", "user_agent", "TEXT NOT NULL DEFAULT ''")?;
+    ensure_column(conn, "users", "telegram_digest", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_column(conn, "users", "telegram_digest_sent_on", "TEXT")?;
+    ensure_column(conn, "users", "daily_summary_at", "TEXT")?;
+    ensure_column(conn, "users", "daily_summary_telegram", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_column(conn, "users", "daily_summary_email", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_column(conn, "users", "daily_summary_sent_on", "TEXT")?;
+    ensure_column(conn, "sessions", "ip", "TEXT")?;
+    // Sessions from before expiry had none; they have to log in again.
+    conn.execute("DELETE FROM sessions WHERE expires_at IS NULL", [])?;
+    conn.execute(
+        "UPDATE sessions SET csrf_token = lower(hex(randomblob(16))) WHERE csrf_token IS NULL",
+        [],
+    )?;
+    hash_stored_tokens(conn, "sessions", "token")?;
+    hash_stored_tokens(conn, "api_tokens", "token")?;
+    hash_stored_tokens(conn, "api_sessions", "access_token")?;
+    hash_stored_tokens(conn, "api_sessions", "refresh_token")?;
+    // Double-submitted budget forms used to leave duplicate rows; the latest one
+    // wins before the unique index makes further duplicates impossible.
+    conn.execute_batch(
+        "
+        DELETE FROM budgets
+        WHERE id NOT IN (SELECT MAX(id) FROM budgets GROUP BY category_id, month);
+
+        CREATE UNIQUE INDEX IF NOT EXISTS budgets_category_month
+            ON budgets(category_id, month);
+        ",
+    )?;
+    ensure_transaction_search(conn)?;
     Ok(())
 }
 
-fn ensure_column(conn: &Connection, table: &str, column: &str, column_type: &str) -> Result<()> {
+/// Full-text index over notes and payees. It only stores the words, the text
+/// stays in `transactions`; triggers keep the two in step, and an index created
+/// over existing transactions is filled once.
+fn ensure_transaction_search(conn: &Connection) -> Result<()> {
+    let exists: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE name = 'transactions_fts')",
+        [],
+        |row| row.get(0),
+    )?;
+    conn.execute_batch(
+        "
+        CREATE VIRTUAL TABLE IF NOT EXISTS transactions_fts USING fts5(
+            note, payee,
+            content = 'transactions', content_rowid = 'id',
+            tokenize = 'unicode61 remove_diacritics 2'
+        );
+
+        CREATE TRIGGER IF NOT EXISTS transactions_fts_insert
+        AFTER INSERT ON transactions
+        BEGIN
+            INSERT INTO transactions_fts (rowid, note, payee) VALUES (NEW.id, NEW.note, NEW.payee);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS transactions_fts_delete
+        AFTER DELETE ON transactions
+        BEGIN
+            INSERT INTO transactions_fts (transactions_fts, rowid, note, payee)
+            VALUES ('delete', OLD.id, OLD.note, OLD.payee);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS transactions_fts_update
+        AFTER UPDATE OF note, payee ON transactions
+        BEGIN
+            INSERT INTO transactions_fts (transactions_fts, rowid, note, payee)
+            VALUES ('delete', OLD.id, OLD.note, OLD.payee);
+            INSERT INTO transactions_fts (rowid, note, payee) VALUES (NEW.id, NEW.note, NEW.payee);
+        END;
+        ",
+    )?;
+    if !exists {
+        conn.execute("INSERT INTO transactions_fts (transactions_fts) VALUES ('rebuild')", [])?;
+    }
+    Ok(())
+}
+
+/// SQLite cannot alter a CHECK constraint, so databases created before the
+/// newest transaction kind existed get their transactions table rebuilt once.
+fn update_transaction_kinds(conn: &Connection) -> Result<()> {
+    let sql: String = conn.query_row(
+        "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = 'transactions'",
+        [],
+        |row| row.get(0),
+    )?;
+    if sql.contains("'adjustment'") {
+        return Ok(());
+    }
+    conn.execute_batch(
+        "
+        BEGIN;
+
+        CREATE TABLE transactions_new (
+            id INTEGER PRIMARY KEY,
+            kind TEXT NOT NULL CHECK(kind IN ('income', 'expense', 'transfer', 'adjustment')),
+            amount_cents INTEGER NOT NULL,
+            category_id INTEGER,
+            occurred_on TEXT NOT NULL,
+            note TEXT,
+            receipt_path TEXT,
+            account_id INTEGER REFERENCES accounts(id),
+            to_account_id INTEGER REFERENCES accounts(id),
+            FOREIGN KEY(category_id) REFERENCES categories(id)
+        );
+
+        INSERT INTO transactions_new
+            (id, kind, amount_cents, category_id, occurred_on, note, receipt_path, account_id,
+             to_account_id)
+        SELECT id, kind, amount_cents, category_id, occurred_on, note, receipt_path, account_id,
+               to_account_id
+        FROM transactions;
+
+        DROP TABLE transactions;
+        ALTER TABLE transactions_new RENAME TO transactions;
+
+        COMMIT;
+        ",
+    )
+}
+
+fn has_column(conn: &Connection, table: &str, column: &str) -> Result<bool> {
     let mut stmt = conn.prepare(&format!("PRAGMA table_info({table})"))?;
     let rows = stmt.query_map([], |row| row.get::<_, String>(1))?;
     for row in rows {
         if row? == column {
-            return Ok(());
+            return Ok(true);
         }
     }
+    Ok(false)
+}
+
+fn ensure_column(conn: &Connection, table: &str, column: &str, column_type: &str) -> Result<()> {
+    if has_column(conn, table, column)? {
+        return Ok(());
+    }
     conn.execute(
         &format!("ALTER TABLE {table} ADD COLUMN {column} {column_type}"),
         [],
@@ -85,12 +761,15 @@ fn ensure_column(conn: &Connection, table: &str, column: &str, column_type: &str
     Ok(())
 }
 
+/// Categories ordered as a tree: each top-level category is followed by its children.
 pub fn list_categories(conn: &Connection) -> Result<Vec<Category>> {
     let mut stmt = conn.prepare(
         "
-        SELECT id, name, kind
-        FROM categories
-        ORDER BY kind, name
+        SELECT c.id, c.name, c.kind, c.parent_id, c.default_amount_cents, c.allow_receipt
+        FROM categories c
+        LEFT JOIN categories p ON c.parent_id = p.id
+        ORDER BY c.kind, COALESCE(p.name, c.name), COALESCE(p.id, c.id),
+                 c.parent_id IS NOT NULL, c.name
         ",
     )?;
     let rows = stmt.query_map([], |row| {
@@ -98,6 +777,9 @@ pub fn list_categories(conn: &Connection) -> Result<Vec<Category>> {
             id: row.get(0)?,
             name: row.get(1)?,
             kind: row.get(2)?,
+            parent_id: row.get(3)?,
+            default_amount_cents: row.get(4)?,
+            allow_receipt: row.get(5)?,
         })
     })?;
 
@@ -108,149 +790,4318 @@ pub fn list_categories(conn: &Connection) -> Result<Vec<Category>> {
     Ok(out)
 }
 
-pub fn insert_category(conn: &Connection, name: &str, kind: &str) -> Result<()> {
-    conn.execute(
-        "INSERT INTO categories (name, kind) VALUES (?1, ?2)",
-        params![name, kind],
-    )?;
-    Ok(())
-}
-
-pub fn has_users(conn: &Connection) -> Result<bool> {
+/// A category name as typed, with runs of whitespace collapsed to one space.
+pub fn clean_category_name(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// What two names of the same kind may not share: «Еда» and « еда » are one
+/// category. SQLite's `lower` only folds ASCII, so the key is computed here.
+pub fn category_name_key(name: &str) -> String {
+    clean_category_name(name).to_lowercase()
+}
+
+/// Cleans names and fills in keys of categories written before the keys existed.
+fn normalize_category_names(conn: &Connection) -> Result<()> {
+    let mut stmt = conn.prepare("SELECT id, name, name_key FROM categories")?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, Option<String>>(2)?,
+        ))
+    })?;
+    let mut stale = Vec::new();
+    for row in rows {
+        let (id, name, key) = row?;
+        let clean = clean_category_name(&name);
+        if clean != name || key.as_deref() != Some(category_name_key(&name).as_str()) {
+            stale.push((id, clean));
+        }
+    }
+    for (id, name) in stale {
+        conn.execute(
+            "UPDATE categories SET name = ?2, name_key = ?3 WHERE id = ?1",
+            params![id, name, category_name_key(&name)],
+        )?;
+    }
+    Ok(())
+}
+
+/// What the sessions table keeps of a cookie's token: its SHA-256 in hex, so
+/// reading the database doesn't let anyone sign in as its users.
+pub fn session_token_hash(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Replaces tokens in `table.column` stored as they were with their hash;
+/// cookies and scripts holding them keep working. A token is a UUID, never
+/// 64 characters long like a hash.
+fn hash_stored_tokens(conn: &Connection, table: &str, column: &str) -> Result<()> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, {column} FROM {table} WHERE length({column}) != 64"
+    ))?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?;
+    let mut plain = Vec::new();
+    for row in rows {
+        plain.push(row?);
+    }
+    for (id, token) in plain {
+        conn.execute(
+            &format!("UPDATE {table} SET {column} = ?2 WHERE id = ?1"),
+            params![id, session_token_hash(&token)],
+        )?;
+    }
+    Ok(())
+}
+
+/// Makes names unique per kind once no duplicates are left to merge; returns
+/// whether they are.
+pub fn ensure_category_name_index(conn: &Connection) -> Result<bool> {
+    let duplicated: bool = conn.query_row(
+        "
+        SELECT EXISTS (
+            SELECT 1 FROM categories GROUP BY kind, name_key HAVING COUNT(*) > 1
+        )
+        ",
+        [],
+        |row| row.get(0),
+    )?;
+    if !duplicated {
+        conn.execute(
+            "CREATE UNIQUE INDEX IF NOT EXISTS categories_kind_name ON categories(kind, name_key)",
+            [],
+        )?;
+    }
+    Ok(!duplicated)
+}
+
+/// Another category of `kind` whose name only differs from `name` in case or spacing.
+pub fn category_name_taken(
+    conn: &Connection,
+    kind: &str,
+    name: &str,
+    except_id: Option<i64>,
+) -> Result<Option<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT name FROM categories WHERE kind = ?1 AND name_key = ?2 AND id IS NOT ?3",
+    )?;
+    let mut rows = stmt.query(params![kind, category_name_key(name), except_id])?;
+    match rows.next()? {
+        Some(row) => Ok(Some(row.get(0)?)),
+        None => Ok(None),
+    }
+}
+
+/// Categories that share their kind and name key with another one, grouped
+/// by the two.
+pub fn category_duplicates(conn: &Connection) -> Result<Vec<CategoryDuplicate>> {
+    let mut stmt = conn.prepare(
+        "
+        SELECT c.id, c.name, c.kind, c.name_key, p.name,
+               (SELECT COUNT(*) FROM transactions t WHERE t.category_id = c.id AND t.deleted_at IS NULL)
+        FROM categories c
+        LEFT JOIN categories p ON p.id = c.parent_id
+        WHERE (c.kind, c.name_key) IN (
+            SELECT kind, name_key FROM categories GROUP BY kind, name_key HAVING COUNT(*) > 1
+        )
+        ORDER BY c.kind, c.name_key, c.id
+        ",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(CategoryDuplicate {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            kind: row.get(2)?,
+            name_key: row.get(3)?,
+            parent_name: row.get(4)?,
+            transactions: row.get(5)?,
+        })
+    })?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+pub fn insert_category(
+    conn: &Connection,
+    name: &str,
+    kind: &str,
+    parent_id: Option<i64>,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO categories (name, kind, parent_id, name_key) VALUES (?1, ?2, ?3, ?4)",
+        params![name, kind, parent_id, category_name_key(name)],
+    )?;
+    Ok(())
+}
+
+pub fn update_category(
+    conn: &Connection,
+    id: i64,
+    name: &str,
+    parent_id: Option<i64>,
+    default_amount_cents: Option<i64>,
+    allow_receipt: bool,
+) -> Result<()> {
+    conn.execute(
+        "
+        UPDATE categories
+        SET name = ?2, parent_id = ?3, name_key = ?4, default_amount_cents = ?5,
+            allow_receipt = ?6
+        WHERE id = ?1
+        ",
+        params![
+            id,
+            name,
+            parent_id,
+            category_name_key(name),
+            default_amount_cents,
+            allow_receipt
+        ],
+    )?;
+    Ok(())
+}
+
+/// Deletes a category, moving its transactions, budgets and webhook defaults to
+/// `reassign_to`, or leaving them uncategorized when it is `None`.
+///
+/// Budgets have no meaning without a category and are dropped in that case; a
+/// budget for a month the target already has is added to the target's, and so
+/// is a standing budget when the target has one. Children
+/// move under the target when it is top-level, otherwise they become top-level.
+/// Run it inside a transaction.
+pub fn delete_category(conn: &Connection, id: i64, reassign_to: Option<i64>) -> Result<()> {
+    conn.execute(
+        "UPDATE transactions SET category_id = ?2 WHERE category_id = ?1",
+        params![id, reassign_to],
+    )?;
+    conn.execute(
+        "UPDATE inbound_hooks SET category_id = ?2 WHERE category_id = ?1",
+        params![id, reassign_to],
+    )?;
+    conn.execute(
+        "UPDATE rules SET category_id = ?2 WHERE category_id = ?1",
+        params![id, reassign_to],
+    )?;
+    if let Some(target) = reassign_to {
+        conn.execute(
+            "
+            UPDATE budgets
+            SET amount_cents = amount_cents + (
+                SELECT SUM(s.amount_cents)
+                FROM budgets s
+                WHERE s.category_id = ?1 AND s.month = budgets.month AND s.deleted_at IS NULL
+            )
+            WHERE category_id = ?2
+              AND month IN (SELECT month FROM budgets WHERE category_id = ?1)
+            ",
+            params![id, target],
+        )?;
+        conn.execute(
+            "
+            DELETE FROM budgets
+            WHERE category_id = ?1
+              AND month IN (SELECT month FROM budgets WHERE category_id = ?2)
+            ",
+            params![id, target],
+        )?;
+        conn.execute(
+            "UPDATE budgets SET category_id = ?2 WHERE category_id = ?1",
+            params![id, target],
+        )?;
+        conn.execute(
+            "
+            UPDATE standing_budgets
+            SET amount_cents = amount_cents + (
+                SELECT amount_cents FROM standing_budgets WHERE category_id = ?1
+            )
+            WHERE category_id = ?2
+              AND EXISTS (SELECT 1 FROM standing_budgets WHERE category_id = ?1)
+            ",
+            params![id, target],
+        )?;
+        conn.execute(
+            "
+            DELETE FROM standing_budgets
+            WHERE category_id = ?1
+              AND EXISTS (SELECT 1 FROM standing_budgets WHERE category_id = ?2)
+            ",
+            params![id, target],
+        )?;
+        conn.execute(
+            "UPDATE standing_budgets SET category_id = ?2 WHERE category_id = ?1",
+            params![id, target],
+        )?;
+        conn.execute(
+            "
+            INSERT INTO category_suggestions (field, text, category_id, accepted)
+            SELECT field, text, ?2, accepted FROM category_suggestions WHERE category_id = ?1
+            ON CONFLICT(field, text, category_id) DO UPDATE SET accepted = accepted + excluded.accepted
+            ",
+            params![id, target],
+        )?;
+    } else {
+        conn.execute("DELETE FROM budgets WHERE category_id = ?1", params![id])?;
+        conn.execute("DELETE FROM standing_budgets WHERE category_id = ?1", params![id])?;
+    }
+    conn.execute(
+        "
+        UPDATE categories
+        SET parent_id = (SELECT id FROM categories WHERE id = ?2 AND parent_id IS NULL)
+        WHERE parent_id = ?1
+        ",
+        params![id, reassign_to],
+    )?;
+    conn.execute("DELETE FROM categories WHERE id = ?1", params![id])?;
+    Ok(())
+}
+
+pub fn category_by_id(conn: &Connection, category_id: i64) -> Result<Option<Category>> {
+    let mut stmt = conn.prepare(
+        "
+        SELECT id, name, kind, parent_id, default_amount_cents, allow_receipt
+        FROM categories
+        WHERE id = ?1
+        ",
+    )?;
+    let mut rows = stmt.query(params![category_id])?;
+    if let Some(row) = rows.next()? {
+        Ok(Some(Category {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            kind: row.get(2)?,
+            parent_id: row.get(3)?,
+            default_amount_cents: row.get(4)?,
+            allow_receipt: row.get(5)?,
+        }))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Transactions and webhooks of `kind` filed under the category, as
+/// `(table, id, amount_cents)`; webhooks have no amount and count as 0.
+pub fn category_entries(conn: &Connection, category_id: i64, kind: &str) -> Result<Vec<(String, i64, i64)>> {
+    let mut stmt = conn.prepare(
+        "
+        SELECT 'transactions', id, amount_cents FROM transactions WHERE category_id = ?1 AND kind = ?2
+        UNION ALL
+        SELECT 'inbound_hooks', id, 0 FROM inbound_hooks WHERE category_id = ?1 AND kind = ?2
+        ",
+    )?;
+    let rows = stmt.query_map(params![category_id, kind], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+/// Monthly and standing budgets of the category, as `(table, id)`.
+pub fn category_budgets(conn: &Connection, category_id: i64) -> Result<Vec<(String, i64)>> {
+    let mut stmt = conn.prepare(
+        "
+        SELECT 'budgets', id FROM budgets WHERE category_id = ?1
+        UNION ALL
+        SELECT 'standing_budgets', id FROM standing_budgets WHERE category_id = ?1
+        ",
+    )?;
+    let rows = stmt.query_map(params![category_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+pub fn set_category_kind(conn: &Connection, id: i64, kind: &str, parent_id: Option<i64>) -> Result<()> {
+    conn.execute(
+        "UPDATE categories SET kind = ?2, parent_id = ?3 WHERE id = ?1",
+        params![id, kind, parent_id],
+    )?;
+    Ok(())
+}
+
+/// Turns the category's `from`-kind transactions and webhooks into `to`.
+pub fn rekind_category_entries(conn: &Connection, category_id: i64, from: &str, to: &str) -> Result<()> {
+    for table in ["transactions", "inbound_hooks"] {
+        conn.execute(
+            &format!("UPDATE {table} SET kind = ?3 WHERE category_id = ?1 AND kind = ?2"),
+            params![category_id, from, to],
+        )?;
+    }
+    Ok(())
+}
+
+/// Files the category's `kind` transactions and webhooks under `target`.
+pub fn move_category_entries(conn: &Connection, category_id: i64, kind: &str, target: i64) -> Result<()> {
+    for table in ["transactions", "inbound_hooks"] {
+        conn.execute(
+            &format!("UPDATE {table} SET category_id = ?3 WHERE category_id = ?1 AND kind = ?2"),
+            params![category_id, kind, target],
+        )?;
+    }
+    Ok(())
+}
+
+pub fn has_users(conn: &Connection) -> Result<bool> {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM users)",
+        [],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|value| value == 1)
+}
+
+pub fn insert_user(conn: &Connection, username: &str, password_hash: &str, created_at: &str) -> Result<i64> {
+    conn.execute(
+        "INSERT INTO users (username, password_hash, created_at) VALUES (?1, ?2, ?3)",
+        params![username, password_hash, created_at],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn user_ids(conn: &Connection) -> Result<Vec<i64>> {
+    let mut stmt = conn.prepare("SELECT id FROM users ORDER BY id")?;
+    let rows = stmt.query_map([], |row| row.get(0))?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+pub fn list_users(conn: &Connection) -> Result<Vec<User>> {
+    let mut stmt = conn.prepare("SELECT id, username FROM users ORDER BY username, id")?;
+    let rows = stmt.query_map([], |row| {
+        Ok(User {
+            id: row.get(0)?,
+            username: row.get(1)?,
+        })
+    })?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+/// Hands everything user `from` owns over to `into` and deletes `from`; see
+/// `user_merge`. Run it inside a transaction.
+pub fn merge_user(conn: &Connection, from: i64, into: i64) -> Result<()> {
+    for table in ["api_tokens", "inbound_hooks", "notifications", "jobs", "receipt_shares"] {
+        conn.execute(
+            &format!("UPDATE {table} SET user_id = ?2 WHERE user_id = ?1"),
+            params![from, into],
+        )?;
+    }
+    // Where both have a row, the one of `into` stays.
+    conn.execute(
+        "
+        INSERT OR IGNORE INTO notification_preferences (user_id, event, channel, enabled)
+        SELECT ?2, event, channel, enabled FROM notification_preferences WHERE user_id = ?1
+        ",
+        params![from, into],
+    )?;
+    conn.execute(
+        "
+        INSERT OR IGNORE INTO known_devices (user_id, user_agent, first_seen_at, last_seen_at)
+        SELECT ?2, user_agent, first_seen_at, last_seen_at FROM known_devices WHERE user_id = ?1
+        ",
+        params![from, into],
+    )?;
+    conn.execute(
+        "
+        UPDATE users SET
+            telegram_chat_id = COALESCE(telegram_chat_id, (SELECT telegram_chat_id FROM users WHERE id = ?1)),
+            email = COALESCE(email, (SELECT email FROM users WHERE id = ?1))
+        WHERE id = ?2
+        ",
+        params![from, into],
+    )?;
+    // Sessions, API sign-ins and login links of `from` go with it.
+    delete_user(conn, from)
+}
+
+/// Deletes the user; sessions, API sign-ins, tokens, webhooks, notifications,
+/// jobs and settings of theirs go with it. Each is deleted here rather than
+/// left to `ON DELETE CASCADE`, which holds only where foreign keys are on.
+pub fn delete_user(conn: &Connection, user_id: i64) -> Result<()> {
+    conn.execute(
+        "DELETE FROM receipt_share_views WHERE share_id IN (SELECT id FROM receipt_shares WHERE user_id = ?1)",
+        params![user_id],
+    )?;
+    for table in [
+        "sessions",
+        "remember_tokens",
+        "api_tokens",
+        "api_sessions",
+        "login_alerts",
+        "known_devices",
+        "notification_preferences",
+        "notifications",
+        "inbound_hooks",
+        "jobs",
+        "receipt_shares",
+    ] {
+        conn.execute(&format!("DELETE FROM {table} WHERE user_id = ?1"), params![user_id])?;
+    }
+    conn.execute("DELETE FROM users WHERE id = ?1", params![user_id])?;
+    Ok(())
+}
+
+/// Files the user's background jobs left for download.
+pub fn user_job_files(conn: &Connection, user_id: i64) -> Result<Vec<String>> {
+    let mut stmt =
+        conn.prepare("SELECT file_path FROM jobs WHERE user_id = ?1 AND file_path IS NOT NULL")?;
+    let rows = stmt.query_map(params![user_id], |row| row.get(0))?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+/// Every receipt file name, of transactions, deleted ones included, and of
+/// the receipt inbox.
+pub fn receipt_file_names(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "
+        SELECT receipt_path FROM transactions WHERE receipt_path IS NOT NULL
+        UNION
+        SELECT file_name FROM receipt_uploads
+        ",
+    )?;
+    let rows = stmt.query_map([], |row| row.get(0))?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+/// Deletes everything the household entered, for when its last login goes.
+/// Exchange rates, which are fetched rather than entered, stay.
+pub fn purge_household(conn: &Connection) -> Result<()> {
+    // Rows that point at others go first.
+    for table in [
+        "transaction_tags",
+        "loan_payments",
+        "receipt_items",
+        "meter_readings",
+        "revaluations",
+        "bulk_changes",
+        "bulk_operations",
+        "category_suggestions",
+        "rules",
+        "inbound_hooks",
+        "transactions",
+        "tags",
+        "budgets",
+        "standing_budgets",
+        "loans",
+        "meters",
+        "tariffs",
+        "holding_valuations",
+        "holdings",
+        "accounts",
+        "categories",
+        "report_snapshots",
+        "receipt_uploads",
+        "receipt_hashes",
+        "budget_increases",
+        "accountant_exports",
+    ] {
+        conn.execute(&format!("DELETE FROM {table}"), [])?;
+    }
+    Ok(())
+}
+
+pub fn user_credentials(conn: &Connection, username: &str) -> Result<Option<(i64, String)>> {
+    let mut stmt = conn.prepare(
+        "
+        SELECT id, password_hash
+        FROM users
+        WHERE username = ?1
+        ",
+    )?;
+    let mut rows = stmt.query(params![username])?;
+    if let Some(row) = rows.next()? {
+        Ok(Some((row.get(0)?, row.get(1)?)))
+    } else {
+        Ok(None)
+    }
+}
+
+pub fn create_session(
+    conn: &Connection,
+    user_id: i64,
+    token: &str,
+    user_agent: &str,
+    ip: Option<&str>,
+    created_at: &str,
+    expires_at: &str,
+) -> Result<()> {
+    conn.execute(
+        "
+        INSERT INTO sessions (user_id, token, csrf_token, user_agent, ip, created_at, expires_at, last_used_at)
+        VALUES (?1, ?2, lower(hex(randomblob(16))), ?3, ?4, ?5, ?6, ?5)
+        ",
+        params![user_id, session_token_hash(token), user_agent, ip, created_at, expires_at],
+    )?;
+    Ok(())
+}
+
+/// The session's user, unless it expired by `now` or was last used before
+/// `idle_since`.
+pub fn user_by_session(conn: &Connection, token: &str, now: &str, idle_since: &str) -> Result<Option<User>> {
+    let mut stmt = conn.prepare_cached(
+        "
+        SELECT u.id, u.username
+        FROM sessions s
+        JOIN users u ON s.user_id = u.id
+        WHERE s.token = ?1 AND s.expires_at > ?2 AND s.last_used_at > ?3
+        ",
+    )?;
+    let mut rows = stmt.query(params![session_token_hash(token), now, idle_since])?;
+    if let Some(row) = rows.next()? {
+        Ok(Some(User {
+            id: row.get(0)?,
+            username: row.get(1)?,
+        }))
+    } else {
+        Ok(None)
+    }
+}
+
+/// The token forms of this browser session have to send back, see `csrf`.
+pub fn session_csrf_token(conn: &Connection, token: &str) -> Result<Option<String>> {
+    let mut stmt = conn.prepare_cached("SELECT csrf_token FROM sessions WHERE token = ?1")?;
+    let mut rows = stmt.query(params![session_token_hash(token)])?;
+    let Some(row) = rows.next()? else {
+        return Ok(None);
+    };
+    row.get(0)
+}
+
+/// The user an API token belongs to and the token's scope; marks it used.
+/// Tokens are kept as their [`session_token_hash`].
+pub fn user_by_api_token(conn: &Connection, token: &str, used_at: &str) -> Result<Option<(User, String)>> {
+    let token = session_token_hash(token);
+    let mut stmt = conn.prepare_cached(
+        "
+        SELECT u.id, u.username, a.scope
+        FROM api_tokens a
+        JOIN users u ON a.user_id = u.id
+        WHERE a.token = ?1
+        ",
+    )?;
+    let mut rows = stmt.query(params![token])?;
+    let Some(row) = rows.next()? else {
+        return Ok(None);
+    };
+    let user = User {
+        id: row.get(0)?,
+        username: row.get(1)?,
+    };
+    let scope = row.get(2)?;
+    conn.execute(
+        "UPDATE api_tokens SET last_used_at = ?2 WHERE token = ?1",
+        params![token, used_at],
+    )?;
+    Ok(Some((user, scope)))
+}
+
+pub fn insert_api_token(
+    conn: &Connection,
+    user_id: i64,
+    name: &str,
+    token: &str,
+    scope: &str,
+    created_at: &str,
+) -> Result<i64> {
+    conn.execute(
+        "
+        INSERT INTO api_tokens (user_id, name, token, scope, created_at)
+        VALUES (?1, ?2, ?3, ?4, ?5)
+        ",
+        params![user_id, name, session_token_hash(token), scope, created_at],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// The user's tokens, newest first.
+pub fn list_api_tokens(conn: &Connection, user_id: i64) -> Result<Vec<ApiToken>> {
+    let mut stmt = conn.prepare(
+        "
+        SELECT id, name, scope, created_at, last_used_at
+        FROM api_tokens
+        WHERE user_id = ?1
+        ORDER BY id DESC
+        ",
+    )?;
+    let rows = stmt.query_map(params![user_id], |row| {
+        Ok(ApiToken {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            scope: row.get(2)?,
+            created_at: row.get(3)?,
+            last_used_at: row.get(4)?,
+        })
+    })?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+/// Revokes one of the user's tokens; `false` when it isn't theirs.
+pub fn delete_api_token(conn: &Connection, user_id: i64, id: i64) -> Result<bool> {
+    let deleted = conn.execute(
+        "DELETE FROM api_tokens WHERE id = ?1 AND user_id = ?2",
+        params![id, user_id],
+    )?;
+    Ok(deleted > 0)
+}
+
+pub fn delete_api_tokens_for_user(conn: &Connection, user_id: i64) -> Result<()> {
+    conn.execute("DELETE FROM api_tokens WHERE user_id = ?1", params![user_id])?;
+    Ok(())
+}
+
+/// Both tokens are kept as their [`session_token_hash`], like those of the
+/// functions below.
+pub fn insert_api_session(conn: &Connection, session: &NewApiSession, created_at: &str) -> Result<()> {
+    conn.execute(
+        "
+        INSERT INTO api_sessions (
+            user_id, family, access_token, access_expires_at, refresh_token, refresh_expires_at, created_at
+        )
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+        ",
+        params![
+            session.user_id,
+            session.family,
+            session_token_hash(&session.access_token),
+            session.access_expires_at,
+            session_token_hash(&session.refresh_token),
+            session.refresh_expires_at,
+            created_at
+        ],
+    )?;
+    Ok(())
+}
+
+/// The user behind an access token that is neither expired, replaced by a
+/// refresh nor revoked at `now`. Times are UTC RFC 3339 and compare as text.
+pub fn user_by_access_token(conn: &Connection, token: &str, now: &str) -> Result<Option<User>> {
+    let mut stmt = conn.prepare_cached(
+        "
+        SELECT u.id, u.username
+        FROM api_sessions s
+        JOIN users u ON s.user_id = u.id
+        WHERE s.access_token = ?1
+          AND s.access_expires_at > ?2
+          AND s.rotated_at IS NULL
+          AND s.revoked_at IS NULL
+        ",
+    )?;
+    let mut rows = stmt.query(params![session_token_hash(token), now])?;
+    if let Some(row) = rows.next()? {
+        Ok(Some(User {
+            id: row.get(0)?,
+            username: row.get(1)?,
+        }))
+    } else {
+        Ok(None)
+    }
+}
+
+pub fn api_session_by_refresh_token(conn: &Connection, token: &str) -> Result<Option<ApiSession>> {
+    let mut stmt = conn.prepare(
+        "
+        SELECT id, user_id, family, refresh_expires_at, rotated_at, revoked_at
+        FROM api_sessions
+        WHERE refresh_token = ?1
+        ",
+    )?;
+    let mut rows = stmt.query(params![session_token_hash(token)])?;
+    let Some(row) = rows.next()? else {
+        return Ok(None);
+    };
+    Ok(Some(ApiSession {
+        id: row.get(0)?,
+        user_id: row.get(1)?,
+        family: row.get(2)?,
+        refresh_expires_at: row.get(3)?,
+        rotated_at: row.get(4)?,
+        revoked_at: row.get(5)?,
+    }))
+}
+
+pub fn mark_api_session_rotated(conn: &Connection, id: i64, rotated_at: &str) -> Result<()> {
+    conn.execute(
+        "UPDATE api_sessions SET rotated_at = ?2 WHERE id = ?1",
+        params![id, rotated_at],
+    )?;
+    Ok(())
+}
+
+pub fn revoke_api_session_family(conn: &Connection, family: &str, revoked_at: &str) -> Result<()> {
+    conn.execute(
+        "UPDATE api_sessions SET revoked_at = ?2 WHERE family = ?1 AND revoked_at IS NULL",
+        params![family, revoked_at],
+    )?;
+    Ok(())
+}
+
+pub fn revoke_api_sessions_for_user(conn: &Connection, user_id: i64, revoked_at: &str) -> Result<()> {
+    conn.execute(
+        "UPDATE api_sessions SET revoked_at = ?2 WHERE user_id = ?1 AND revoked_at IS NULL",
+        params![user_id, revoked_at],
+    )?;
+    Ok(())
+}
+
+/// Signed-in API clients: families whose latest refresh token still works.
+pub fn api_session_count(conn: &Connection, user_id: i64, now: &str) -> Result<i64> {
+    conn.query_row(
+        "
+        SELECT COUNT(*)
+        FROM api_sessions
+        WHERE user_id = ?1
+          AND rotated_at IS NULL
+          AND revoked_at IS NULL
+          AND refresh_expires_at > ?2
+        ",
+        params![user_id, now],
+        |row| row.get(0),
+    )
+}
+
+/// Drops pairs whose refresh token has expired; nothing can use them anymore.
+pub fn prune_api_sessions(conn: &Connection, now: &str) -> Result<()> {
+    conn.execute(
+        "DELETE FROM api_sessions WHERE refresh_expires_at <= ?1",
+        params![now],
+    )?;
+    Ok(())
+}
+
+pub fn user_by_telegram_chat(conn: &Connection, chat_id: i64) -> Result<Option<User>> {
+    let mut stmt = conn.prepare(
+        "
+        SELECT id, username
+        FROM users
+        WHERE telegram_chat_id = ?1
+        ",
+    )?;
+    let mut rows = stmt.query(params![chat_id])?;
+    if let Some(row) = rows.next()? {
+        Ok(Some(User {
+            id: row.get(0)?,
+            username: row.get(1)?,
+        }))
+    } else {
+        Ok(None)
+    }
+}
+
+pub fn telegram_chat_id(conn: &Connection, user_id: i64) -> Result<Option<i64>> {
+    conn.query_row(
+        "SELECT telegram_chat_id FROM users WHERE id = ?1",
+        params![user_id],
+        |row| row.get(0),
+    )
+}
+
+pub fn set_telegram_chat_id(conn: &Connection, user_id: i64, chat_id: Option<i64>) -> Result<()> {
+    conn.execute(
+        "UPDATE users SET telegram_chat_id = ?2 WHERE id = ?1",
+        params![user_id, chat_id],
+    )?;
+    Ok(())
+}
+
+pub fn telegram_digest(conn: &Connection, user_id: i64) -> Result<bool> {
+    conn.query_row(
+        "SELECT telegram_digest FROM users WHERE id = ?1",
+        params![user_id],
+        |row| row.get(0),
+    )
+}
+
+pub fn set_telegram_digest(conn: &Connection, user_id: i64, enabled: bool) -> Result<()> {
+    conn.execute(
+        "UPDATE users SET telegram_digest = ?2 WHERE id = ?1",
+        params![user_id, enabled],
+    )?;
+    Ok(())
+}
+
+/// Users who asked for the weekly digest, have a linked chat and haven't
+/// been sent one on `today`, with the chat.
+pub fn digest_recipients(conn: &Connection, today: &str) -> Result<Vec<(i64, i64)>> {
+    let mut stmt = conn.prepare(
+        "
+        SELECT id, telegram_chat_id
+        FROM users
+        WHERE telegram_digest = 1
+          AND telegram_chat_id IS NOT NULL
+          AND (telegram_digest_sent_on IS NULL OR telegram_digest_sent_on < ?1)
+        ORDER BY id
+        ",
+    )?;
+    let rows = stmt.query_map(params![today], |row| Ok((row.get(0)?, row.get(1)?)))?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+pub fn mark_digest_sent(conn: &Connection, user_id: i64, on: &str) -> Result<()> {
+    conn.execute(
+        "UPDATE users SET telegram_digest_sent_on = ?2 WHERE id = ?1",
+        params![user_id, on],
+    )?;
+    Ok(())
+}
+
+pub fn daily_summary(conn: &Connection, user_id: i64) -> Result<DailySummarySettings> {
+    conn.query_row(
+        "
+        SELECT daily_summary_at, daily_summary_telegram, daily_summary_email
+        FROM users
+        WHERE id = ?1
+        ",
+        params![user_id],
+        |row| {
+            Ok(DailySummarySettings {
+                at: row.get(0)?,
+                telegram: row.get(1)?,
+                email: row.get(2)?,
+            })
+        },
+    )
+}
+
+pub fn set_daily_summary(
+    conn: &Connection,
+    user_id: i64,
+    settings: &DailySummarySettings,
+) -> Result<()> {
+    conn.execute(
+        "
+        UPDATE users
+        SET daily_summary_at = ?2, daily_summary_telegram = ?3, daily_summary_email = ?4
+        WHERE id = ?1
+        ",
+        params![user_id, settings.at, settings.telegram, settings.email],
+    )?;
+    Ok(())
+}
+
+/// Users whose summary time has come by `now` (`HH:MM`) and who haven't been
+/// sent one on `today`, with the chat and address of each channel they picked
+/// and can be reached on.
+pub fn daily_summary_recipients(
+    conn: &Connection,
+    today: &str,
+    now: &str,
+) -> Result<Vec<DailySummaryRecipient>> {
+    let mut stmt = conn.prepare(
+        "
+        SELECT id,
+               CASE WHEN daily_summary_telegram = 1 THEN telegram_chat_id END,
+               CASE WHEN daily_summary_email = 1 THEN email END
+        FROM users
+        WHERE daily_summary_at IS NOT NULL
+          AND daily_summary_at <= ?2
+          AND (daily_summary_sent_on IS NULL OR daily_summary_sent_on < ?1)
+        ORDER BY id
+        ",
+    )?;
+    let rows = stmt.query_map(params![today, now], |row| {
+        Ok(DailySummaryRecipient {
+            user_id: row.get(0)?,
+            chat_id: row.get(1)?,
+            email: row.get(2)?,
+        })
+    })?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        let recipient = row?;
+        if recipient.chat_id.is_some() || recipient.email.is_some() {
+            out.push(recipient);
+        }
+    }
+    Ok(out)
+}
+
+pub fn mark_daily_summary_sent(conn: &Connection, user_id: i64, on: &str) -> Result<()> {
+    conn.execute(
+        "UPDATE users SET daily_summary_sent_on = ?2 WHERE id = ?1",
+        params![user_id, on],
+    )?;
+    Ok(())
+}
+
+pub fn user_email(conn: &Connection, user_id: i64) -> Result<Option<String>> {
+    conn.query_row(
+        "SELECT email FROM users WHERE id = ?1",
+        params![user_id],
+        |row| row.get(0),
+    )
+}
+
+pub fn set_user_email(conn: &Connection, user_id: i64, email: Option<&str>) -> Result<()> {
+    conn.execute(
+        "UPDATE users SET email = ?2 WHERE id = ?1",
+        params![user_id, email],
+    )?;
+    Ok(())
+}
+
+pub fn known_device_count(conn: &Connection, user_id: i64) -> Result<i64> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM known_devices WHERE user_id = ?1",
+        params![user_id],
+        |row| row.get(0),
+    )
+}
+
+/// Notes a login from `user_agent`; true when it was never seen for the user.
+pub fn record_device(conn: &Connection, user_id: i64, user_agent: &str, seen_at: &str) -> Result<bool> {
+    let inserted = conn.execute(
+        "
+        INSERT OR IGNORE INTO known_devices (user_id, user_agent, first_seen_at, last_seen_at)
+        VALUES (?1, ?2, ?3, ?3)
+        ",
+        params![user_id, user_agent, seen_at],
+    )?;
+    if inserted == 0 {
+        conn.execute(
+            "UPDATE known_devices SET last_seen_at = ?3 WHERE user_id = ?1 AND user_agent = ?2",
+            params![user_id, user_agent, seen_at],
+        )?;
+    }
+    Ok(inserted > 0)
+}
+
+pub fn insert_login_alert(
+    conn: &Connection,
+    token: &str,
+    user_id: i64,
+    user_agent: &str,
+    created_at: &str,
+) -> Result<()> {
+    conn.execute(
+        "
+        INSERT INTO login_alerts (token, user_id, user_agent, created_at)
+        VALUES (?1, ?2, ?3, ?4)
+        ",
+        params![token, user_id, user_agent, created_at],
+    )?;
+    Ok(())
+}
+
+/// Spends an alert's revoke link issued at `not_before` or later and returns
+/// whose it was; a link works once.
+pub fn use_login_alert(conn: &Connection, token: &str, not_before: &str, used_at: &str) -> Result<Option<i64>> {
+    let mut stmt = conn.prepare(
+        "
+        SELECT user_id
+        FROM login_alerts
+        WHERE token = ?1 AND used_at IS NULL AND created_at >= ?2
+        ",
+    )?;
+    let mut rows = stmt.query(params![token, not_before])?;
+    let Some(row) = rows.next()? else {
+        return Ok(None);
+    };
+    let user_id = row.get(0)?;
+    conn.execute(
+        "UPDATE login_alerts SET used_at = ?2 WHERE token = ?1",
+        params![token, used_at],
+    )?;
+    Ok(Some(user_id))
+}
+
+/// Failures in a row counted under `key` and when the last one was.
+pub fn login_failures(conn: &Connection, key: &str) -> Result<Option<(i64, String)>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT failures, last_failed_at FROM login_failures WHERE key = ?1",
+    )?;
+    let mut rows = stmt.query(params![key])?;
+    let Some(row) = rows.next()? else {
+        return Ok(None);
+    };
+    Ok(Some((row.get(0)?, row.get(1)?)))
+}
+
+/// Counts a failure under `key`, starting over when the last one was before
+/// `forget_before`.
+pub fn record_login_failure(conn: &Connection, key: &str, failed_at: &str, forget_before: &str) -> Result<()> {
+    conn.execute(
+        "
+        INSERT INTO login_failures (key, failures, last_failed_at)
+        VALUES (?1, 1, ?2)
+        ON CONFLICT(key) DO UPDATE SET
+            failures = CASE WHEN last_failed_at < ?3 THEN 1 ELSE failures + 1 END,
+            last_failed_at = ?2
+        ",
+        params![key, failed_at, forget_before],
+    )?;
+    Ok(())
+}
+
+pub fn clear_login_failures(conn: &Connection, key: &str) -> Result<()> {
+    conn.execute("DELETE FROM login_failures WHERE key = ?1", params![key])?;
+    Ok(())
+}
+
+pub fn delete_session(conn: &Connection, token: &str) -> Result<()> {
+    conn.execute("DELETE FROM sessions WHERE token = ?1", params![session_token_hash(token)])?;
+    Ok(())
+}
+
+/// The user's sessions still alive at `now`, most recently used first;
+/// `current` is the token of the browser asking.
+pub fn browser_sessions(
+    conn: &Connection,
+    user_id: i64,
+    current: &str,
+    now: &str,
+    idle_since: &str,
+) -> Result<Vec<BrowserSession>> {
+    let mut stmt = conn.prepare(
+        "
+        SELECT id, user_agent, ip, created_at, last_used_at, token = ?2
+        FROM sessions
+        WHERE user_id = ?1 AND expires_at > ?3 AND last_used_at > ?4
+        ORDER BY last_used_at DESC, id DESC
+        ",
+    )?;
+    let current = session_token_hash(current);
+    let rows = stmt.query_map(params![user_id, current, now, idle_since], |row| {
+        Ok(BrowserSession {
+            id: row.get(0)?,
+            user_agent: row.get(1)?,
+            ip: row.get(2)?,
+            created_at: row.get(3)?,
+            last_used_at: row.get(4)?,
+            current: row.get(5)?,
+        })
+    })?;
+    let mut sessions = Vec::new();
+    for row in rows {
+        sessions.push(row?);
+    }
+    Ok(sessions)
+}
+
+pub fn delete_user_session(conn: &Connection, user_id: i64, id: i64) -> Result<bool> {
+    let deleted = conn.execute(
+        "DELETE FROM sessions WHERE id = ?1 AND user_id = ?2",
+        params![id, user_id],
+    )?;
+    Ok(deleted > 0)
+}
+
+/// Records a use of the session, unless one was recorded since `fresh_since`.
+pub fn touch_session(conn: &Connection, token: &str, used_at: &str, fresh_since: &str) -> Result<()> {
+    conn.execute(
+        "UPDATE sessions SET last_used_at = ?2 WHERE token = ?1 AND last_used_at < ?3",
+        params![session_token_hash(token), used_at, fresh_since],
+    )?;
+    Ok(())
+}
+
+pub fn delete_expired_sessions(conn: &Connection, user_id: i64, now: &str, idle_since: &str) -> Result<()> {
+    conn.execute(
+        "DELETE FROM sessions WHERE user_id = ?1 AND (expires_at <= ?2 OR last_used_at <= ?3)",
+        params![user_id, now, idle_since],
+    )?;
+    Ok(())
+}
+
+pub fn insert_remember_token(
+    conn: &Connection,
+    user_id: i64,
+    series: &str,
+    token_hash: &str,
+    created_at: &str,
+    expires_at: &str,
+) -> Result<()> {
+    conn.execute(
+        "
+        INSERT INTO remember_tokens (user_id, series, token_hash, created_at, expires_at)
+        VALUES (?1, ?2, ?3, ?4, ?5)
+        ",
+        params![user_id, series, token_hash, created_at, expires_at],
+    )?;
+    Ok(())
+}
+
+pub fn remember_token(conn: &Connection, series: &str) -> Result<Option<RememberToken>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, user_id, token_hash, expires_at FROM remember_tokens WHERE series = ?1",
+    )?;
+    let mut rows = stmt.query(params![series])?;
+    let Some(row) = rows.next()? else {
+        return Ok(None);
+    };
+    Ok(Some(RememberToken {
+        id: row.get(0)?,
+        user_id: row.get(1)?,
+        token_hash: row.get(2)?,
+        expires_at: row.get(3)?,
+    }))
+}
+
+pub fn rotate_remember_token(conn: &Connection, id: i64, token_hash: &str, used_at: &str) -> Result<()> {
+    conn.execute(
+        "UPDATE remember_tokens SET token_hash = ?2, used_at = ?3 WHERE id = ?1",
+        params![id, token_hash, used_at],
+    )?;
+    Ok(())
+}
+
+pub fn delete_remember_series(conn: &Connection, series: &str) -> Result<()> {
+    conn.execute("DELETE FROM remember_tokens WHERE series = ?1", params![series])?;
+    Ok(())
+}
+
+pub fn delete_remember_tokens_for_user(conn: &Connection, user_id: i64) -> Result<()> {
+    conn.execute("DELETE FROM remember_tokens WHERE user_id = ?1", params![user_id])?;
+    Ok(())
+}
+
+pub fn delete_sessions_for_user(conn: &Connection, user_id: i64) -> Result<()> {
+    conn.execute("DELETE FROM sessions WHERE user_id = ?1", params![user_id])?;
+    Ok(())
+}
+
+pub fn prune_sessions(conn: &Connection, user_id: i64, keep: i64) -> Result<()> {
+    conn.execute(
+        "
+        DELETE FROM sessions
+        WHERE user_id = ?1
+          AND id NOT IN (
+            SELECT id
+            FROM sessions
+            WHERE user_id = ?1
+            ORDER BY created_at DESC, id DESC
+            LIMIT ?2
+          )
+        ",
+        params![user_id, keep],
+    )?;
+    Ok(())
+}
+
+pub fn notification_preferences(conn: &Connection, user_id: i64) -> Result<Vec<NotificationPreference>> {
+    let mut stmt = conn.prepare(
+        "
+        SELECT event, channel, enabled
+        FROM notification_preferences
+        WHERE user_id = ?1
+        ",
+    )?;
+    let rows = stmt.query_map(params![user_id], |row| {
+        Ok(NotificationPreference {
+            event: row.get(0)?,
+            channel: row.get(1)?,
+            enabled: row.get::<_, i64>(2)? == 1,
+        })
+    })?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+pub fn set_notification_preference(
+    conn: &Connection,
+    user_id: i64,
+    event: &str,
+    channel: &str,
+    enabled: bool,
+) -> Result<()> {
+    conn.execute(
+        "
+        INSERT INTO notification_preferences (user_id, event, channel, enabled)
+        VALUES (?1, ?2, ?3, ?4)
+        ON CONFLICT(user_id, event, channel) DO UPDATE SET enabled = excluded.enabled
+        ",
+        params![user_id, event, channel, enabled as i64],
+    )?;
+    Ok(())
+}
+
+pub fn insert_notification(
+    conn: &Connection,
+    user_id: i64,
+    channel: &str,
+    notification: &NewNotification,
+    created_at: &str,
+) -> Result<()> {
+    conn.execute(
+        "
+        INSERT INTO notifications (user_id, event, channel, title, body, payload, created_at)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+        ",
+        params![
+            user_id,
+            notification.event,
+            channel,
+            notification.title,
+            notification.body,
+            notification.payload,
+            created_at
+        ],
+    )?;
+    Ok(())
+}
+
+pub fn pending_notifications(conn: &Connection, channel: &str) -> Result<Vec<PendingNotification>> {
+    let mut stmt = conn.prepare(
+        "
+        SELECT n.id, n.event, n.title, n.body, n.payload, u.telegram_chat_id
+        FROM notifications n
+        JOIN users u ON n.user_id = u.id
+        WHERE n.channel = ?1 AND n.sent_at IS NULL
+        ORDER BY n.id
+        ",
+    )?;
+    let rows = stmt.query_map(params![channel], |row| {
+        Ok(PendingNotification {
+            id: row.get(0)?,
+            event: row.get(1)?,
+            title: row.get(2)?,
+            body: row.get(3)?,
+            payload: row.get(4)?,
+            telegram_chat_id: row.get(5)?,
+        })
+    })?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+pub fn mark_notification_sent(conn: &Connection, id: i64, sent_at: &str) -> Result<()> {
+    conn.execute(
+        "UPDATE notifications SET sent_at = ?2 WHERE id = ?1",
+        params![id, sent_at],
+    )?;
+    Ok(())
+}
+
+pub fn list_notifications(
+    conn: &Connection,
+    user_id: i64,
+    event: Option<&str>,
+) -> Result<Vec<NotificationRecord>> {
+    let mut stmt = conn.prepare(
+        "
+        SELECT id, event, title, body, created_at, read_at
+        FROM notifications
+        WHERE user_id = ?1
+          AND channel = 'in_app'
+          AND (?2 IS NULL OR event = ?2)
+        ORDER BY created_at DESC, id DESC
+        ",
+    )?;
+    let rows = stmt.query_map(params![user_id, event], |row| {
+        Ok(NotificationRecord {
+            id: row.get(0)?,
+            event: row.get(1)?,
+            title: row.get(2)?,
+            body: row.get(3)?,
+            created_at: row.get(4)?,
+            read_at: row.get(5)?,
+        })
+    })?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+pub fn mark_all_notifications_read(conn: &Connection, user_id: i64, read_at: &str) -> Result<()> {
+    conn.execute(
+        "
+        UPDATE notifications
+        SET read_at = ?2
+        WHERE user_id = ?1 AND channel = 'in_app' AND read_at IS NULL
+        ",
+        params![user_id, read_at],
+    )?;
+    Ok(())
+}
+
+/// Transactions matching `query` in its sort order, newest first by default;
+/// `query.sort` is a closed set, so only known orders reach the SQL.
+pub fn list_transactions(conn: &Connection, query: &TransactionQuery) -> Result<Vec<TransactionRecord>> {
+    let (sql, values) = query.build();
+    let mut stmt = conn.prepare_cached(&sql)?;
+    let rows = stmt.query_map(params_from_iter(values), transaction_from_row)?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+/// How many transactions `query` matches in all, whatever its limit and offset.
+pub fn count_transactions(conn: &Connection, query: &TransactionQuery) -> Result<i64> {
+    let (sql, values) = query.count();
+    conn.prepare_cached(&sql)?
+        .query_row(params_from_iter(values), |row| row.get(0))
+}
+
+fn transaction_from_row(row: &rusqlite::Row<'_>) -> Result<TransactionRecord> {
+    Ok(TransactionRecord {
+        id: row.get(0)?,
+        kind: row.get(1)?,
+        amount_cents: row.get(2)?,
+        occurred_on: row.get(3)?,
+        note: row.get(4)?,
+        category_name: row.get(5)?,
+        receipt_path: row.get(6)?,
+        account_name: row.get(7)?,
+        category_id: row.get(8)?,
+        to_account_name: row.get(9)?,
+        tags: row.get(10)?,
+        payee: row.get(11)?,
+        starred: row.get(12)?,
+        status: row.get(13)?,
+        refund_of: row.get(14)?,
+        effective_month: row.get(15)?,
+    })
+}
+
+pub fn insert_transaction(
+    conn: &Connection,
+    transaction: &NewTransaction,
+    receipt_path: Option<&str>,
+) -> Result<i64> {
+    conn.execute(
+        "
+        INSERT INTO transactions
+            (kind, amount_cents, category_id, occurred_on, note, receipt_path, account_id, to_account_id,
+             payee)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+        ",
+        params![
+            transaction.kind,
+            transaction.amount_cents,
+            transaction.category_id,
+            transaction.occurred_on,
+            transaction.note,
+            receipt_path,
+            transaction.account_id,
+            transaction.to_account_id,
+            transaction.payee
+        ],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// The stored fields of a transaction, as it would be inserted again.
+pub fn transaction_by_id(conn: &Connection, id: i64) -> Result<Option<NewTransaction>> {
+    let mut stmt = conn.prepare(
+        "
+        SELECT kind, amount_cents, category_id, occurred_on, note, payee, account_id, to_account_id
+        FROM transactions
+        WHERE id = ?1 AND deleted_at IS NULL
+        ",
+    )?;
+    let mut rows = stmt.query(params![id])?;
+    let Some(row) = rows.next()? else {
+        return Ok(None);
+    };
+    Ok(Some(NewTransaction {
+        kind: row.get(0)?,
+        amount_cents: row.get(1)?,
+        category_id: row.get(2)?,
+        occurred_on: row.get(3)?,
+        note: row.get(4)?,
+        payee: row.get(5)?,
+        account_id: row.get(6)?,
+        to_account_id: row.get(7)?,
+    }))
+}
+
+/// Like `list_transactions`, but with the note or payee matching
+/// `query.term` best first; word beginnings count, so «электрик» finds
+/// «электрика» too.
+pub fn search_transactions(conn: &Connection, query: &TransactionQuery) -> Result<Vec<TransactionRecord>> {
+    let Some((sql, values)) = query.search() else {
+        return Ok(Vec::new());
+    };
+    let mut stmt = conn.prepare_cached(&sql)?;
+    let rows = stmt.query_map(params_from_iter(values), transaction_from_row)?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+/// Inserts all rows with one prepared statement; run it through `bulk::run` so the
+/// batch lands in a single SQLite transaction.
+pub fn insert_transactions_batch(conn: &Connection, rows: &[NewTransaction]) -> Result<Vec<i64>> {
+    let mut stmt = conn.prepare(
+        "
+        INSERT INTO transactions
+            (kind, amount_cents, category_id, occurred_on, note, account_id, to_account_id, payee)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+        ",
+    )?;
+    let mut ids = Vec::with_capacity(rows.len());
+    for row in rows {
+        ids.push(stmt.insert(params![
+            row.kind,
+            row.amount_cents,
+            row.category_id,
+            row.occurred_on,
+            row.note,
+            row.account_id,
+            row.to_account_id,
+            row.payee
+        ])?);
+    }
+    Ok(ids)
+}
+
+/// Transactions whose date SQLite can't read back as the same `YYYY-MM-DD`.
+pub fn malformed_dates(conn: &Connection) -> Result<Vec<MalformedDate>> {
+    let mut stmt = conn.prepare(
+        "
+        SELECT t.id, t.occurred_on, t.kind, t.amount_cents, t.note, c.name
+        FROM transactions t
+        LEFT JOIN categories c ON t.category_id = c.id
+        WHERE date(t.occurred_on) IS NOT t.occurred_on AND t.deleted_at IS NULL
+        ORDER BY t.id
+        ",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(MalformedDate {
+            id: row.get(0)?,
+            occurred_on: row.get(1)?,
+            kind: row.get(2)?,
+            amount_cents: row.get(3)?,
+            note: row.get(4)?,
+            category_name: row.get(5)?,
+        })
+    })?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+pub fn set_transaction_date(conn: &Connection, id: i64, occurred_on: &str) -> Result<()> {
+    conn.execute(
+        "UPDATE transactions SET occurred_on = ?2 WHERE id = ?1",
+        params![id, occurred_on],
+    )?;
+    Ok(())
+}
+
+/// Counts the transaction toward `month` (`YYYY-MM`), or toward the month of
+/// its date again for `None`.
+pub fn set_effective_month(conn: &Connection, id: i64, month: Option<&str>) -> Result<()> {
+    conn.execute(
+        "UPDATE transactions SET effective_month = ?2 WHERE id = ?1",
+        params![id, month],
+    )?;
+    Ok(())
+}
+
+/// Marks the transaction as made from the fiscal receipt `fiscal_id`, see
+/// `fiscal_receipts`.
+pub fn set_fiscal_id(conn: &Connection, id: i64, fiscal_id: &str) -> Result<()> {
+    conn.execute(
+        "UPDATE transactions SET fiscal_id = ?2 WHERE id = ?1",
+        params![id, fiscal_id],
+    )?;
+    Ok(())
+}
+
+/// The transaction outside the trash made from the fiscal receipt `fiscal_id`.
+pub fn transaction_by_fiscal_id(conn: &Connection, fiscal_id: &str) -> Result<Option<i64>> {
+    let mut stmt = conn.prepare(
+        "SELECT id FROM transactions WHERE fiscal_id = ?1 AND deleted_at IS NULL LIMIT 1",
+    )?;
+    let mut rows = stmt.query(params![fiscal_id])?;
+    let Some(row) = rows.next()? else {
+        return Ok(None);
+    };
+    Ok(Some(row.get(0)?))
+}
+
+/// Attaches tags by name, creating the ones that do not exist yet.
+pub fn add_transaction_tags(conn: &Connection, transaction_id: i64, names: &[String]) -> Result<()> {
+    for name in names {
+        conn.execute(
+            "INSERT INTO tags (name) VALUES (?1) ON CONFLICT(name) DO NOTHING",
+            params![name],
+        )?;
+        conn.execute(
+            "
+            INSERT OR IGNORE INTO transaction_tags (transaction_id, tag_id)
+            SELECT ?1, id FROM tags WHERE name = ?2
+            ",
+            params![transaction_id, name],
+        )?;
+    }
+    Ok(())
+}
+
+pub fn copy_transaction_tags(conn: &Connection, from_id: i64, to_id: i64) -> Result<()> {
+    conn.execute(
+        "
+        INSERT OR IGNORE INTO transaction_tags (transaction_id, tag_id)
+        SELECT ?2, tag_id FROM transaction_tags WHERE transaction_id = ?1
+        ",
+        params![from_id, to_id],
+    )?;
+    Ok(())
+}
+
+pub fn insert_receipt_upload(
+    conn: &Connection,
+    file_name: &str,
+    original_name: Option<&str>,
+    receipt: Option<(&str, i64)>,
+    recognized: bool,
+    uploaded_at: &str,
+) -> Result<i64> {
+    conn.execute(
+        "
+        INSERT INTO receipt_uploads (file_name, original_name, occurred_on, amount_cents, recognized, uploaded_at)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+        ",
+        params![
+            file_name,
+            original_name,
+            receipt.map(|(occurred_on, _)| occurred_on),
+            receipt.map(|(_, amount_cents)| amount_cents),
+            recognized,
+            uploaded_at
+        ],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Uploaded receipts not attached to a transaction yet, oldest first.
+pub fn list_receipt_uploads(conn: &Connection) -> Result<Vec<ReceiptUpload>> {
+    let mut stmt = conn.prepare(
+        "
+        SELECT id, file_name, original_name, occurred_on, amount_cents, recognized, uploaded_at
+        FROM receipt_uploads
+        ORDER BY id
+        ",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(ReceiptUpload {
+            id: row.get(0)?,
+            file_name: row.get(1)?,
+            original_name: row.get(2)?,
+            occurred_on: row.get(3)?,
+            amount_cents: row.get(4)?,
+            recognized: row.get(5)?,
+            uploaded_at: row.get(6)?,
+        })
+    })?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+pub fn receipt_upload_file(conn: &Connection, id: i64) -> Result<Option<String>> {
+    let mut stmt = conn.prepare("SELECT file_name FROM receipt_uploads WHERE id = ?1")?;
+    let mut rows = stmt.query(params![id])?;
+    let Some(row) = rows.next()? else {
+        return Ok(None);
+    };
+    Ok(Some(row.get(0)?))
+}
+
+/// Sets the date and total of an upload, as recognized on the photo or as
+/// typed in or confirmed; `false` when there is no such upload.
+pub fn set_receipt_upload_details(
+    conn: &Connection,
+    id: i64,
+    occurred_on: &str,
+    amount_cents: i64,
+    recognized: bool,
+) -> Result<bool> {
+    let changed = conn.execute(
+        "UPDATE receipt_uploads SET occurred_on = ?2, amount_cents = ?3, recognized = ?4 WHERE id = ?1",
+        params![id, occurred_on, amount_cents, recognized],
+    )?;
+    Ok(changed > 0)
+}
+
+pub fn delete_receipt_upload(conn: &Connection, id: i64) -> Result<()> {
+    conn.execute("DELETE FROM receipt_uploads WHERE id = ?1", params![id])?;
+    Ok(())
+}
+
+/// Expenses of exactly `amount_cents` without a receipt, dated at most `days`
+/// away from `occurred_on`, closest first.
+pub fn receipt_candidates(
+    conn: &Connection,
+    occurred_on: &str,
+    amount_cents: i64,
+    days: i64,
+    limit: i64,
+) -> Result<Vec<ReceiptCandidate>> {
+    let mut stmt = conn.prepare_cached(
+        "
+        SELECT t.id, t.occurred_on, c.name, t.payee, t.note
+        FROM transactions t
+        LEFT JOIN categories c ON c.id = t.category_id
+        WHERE t.kind = 'expense'
+          AND t.receipt_path IS NULL
+          AND t.deleted_at IS NULL
+          AND t.amount_cents = ?2
+          AND t.occurred_on BETWEEN date(?1, '-' || ?3 || ' days') AND date(?1, '+' || ?3 || ' days')
+        ORDER BY ABS(julianday(t.occurred_on) - julianday(?1)), t.id
+        LIMIT ?4
+        ",
+    )?;
+    let rows = stmt.query_map(params![occurred_on, amount_cents, days, limit], |row| {
+        Ok(ReceiptCandidate {
+            transaction_id: row.get(0)?,
+            occurred_on: row.get(1)?,
+            category_name: row.get(2)?,
+            payee: row.get(3)?,
+            note: row.get(4)?,
+        })
+    })?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+/// Attaches `file_name` to a transaction that has no receipt yet; `false`
+/// when there is no such transaction or it has one.
+pub fn attach_receipt(conn: &Connection, transaction_id: i64, file_name: &str) -> Result<bool> {
+    let changed = conn.execute(
+        "
+        UPDATE transactions SET receipt_path = ?2
+        WHERE id = ?1 AND receipt_path IS NULL AND deleted_at IS NULL
+        ",
+        params![transaction_id, file_name],
+    )?;
+    Ok(changed > 0)
+}
+
+/// Whether a transaction, in the trash or not, has `file_name` as its receipt.
+/// The receipt that was saved for an upload with the SHA-256 `sha256`, while
+/// a transaction or the inbox still has it.
+pub fn receipt_with_hash(conn: &Connection, sha256: &str) -> Result<Option<String>> {
+    let mut stmt = conn.prepare(
+        "
+        SELECT h.file_name FROM receipt_hashes h
+        WHERE h.sha256 = ?1
+            AND (EXISTS (SELECT 1 FROM transactions t WHERE t.receipt_path = h.file_name)
+                OR EXISTS (SELECT 1 FROM receipt_uploads u WHERE u.file_name = h.file_name))
+        ORDER BY h.file_name
+        LIMIT 1
+        ",
+    )?;
+    let mut rows = stmt.query(params![sha256])?;
+    let Some(row) = rows.next()? else {
+        return Ok(None);
+    };
+    Ok(Some(row.get(0)?))
+}
+
+pub fn record_receipt_hash(conn: &Connection, file_name: &str, sha256: &str) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO receipt_hashes (file_name, sha256) VALUES (?1, ?2)",
+        params![file_name, sha256],
+    )?;
+    Ok(())
+}
+
+/// Forgets the hash of receipt `file_name` once no transaction or inbox
+/// upload has it; returns whether the file can be removed then.
+pub fn release_receipt(conn: &Connection, file_name: &str) -> Result<bool> {
+    if receipt_known(conn, file_name)? {
+        return Ok(false);
+    }
+    conn.execute("DELETE FROM receipt_hashes WHERE file_name = ?1", params![file_name])?;
+    Ok(true)
+}
+
+pub fn receipt_in_use(conn: &Connection, file_name: &str) -> Result<bool> {
+    conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM transactions WHERE receipt_path = ?1)",
+        params![file_name],
+        |row| row.get(0),
+    )
+}
+
+/// Whether `file_name` is a receipt of the household: of a transaction,
+/// deleted ones included, or waiting in the receipt inbox.
+pub fn receipt_known(conn: &Connection, file_name: &str) -> Result<bool> {
+    conn.query_row(
+        "
+        SELECT EXISTS (SELECT 1 FROM transactions WHERE receipt_path = ?1)
+            OR EXISTS (SELECT 1 FROM receipt_uploads WHERE file_name = ?1)
+        ",
+        params![file_name],
+        |row| row.get(0),
+    )
+}
+
+pub fn insert_receipt_share(
+    conn: &Connection,
+    user_id: i64,
+    token: &str,
+    file_name: &str,
+    created_at: &str,
+    expires_at: &str,
+) -> Result<i64> {
+    conn.execute(
+        "
+        INSERT INTO receipt_shares (user_id, token_hash, file_name, created_at, expires_at)
+        VALUES (?1, ?2, ?3, ?4, ?5)
+        ",
+        params![user_id, session_token_hash(token), file_name, created_at, expires_at],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// The id and receipt of the link with `token`, while it is neither revoked
+/// nor expired at `now`.
+pub fn live_receipt_share(conn: &Connection, token: &str, now: &str) -> Result<Option<(i64, String)>> {
+    let mut stmt = conn.prepare(
+        "
+        SELECT id, file_name
+        FROM receipt_shares
+        WHERE token_hash = ?1 AND revoked_at IS NULL AND expires_at > ?2
+        ",
+    )?;
+    let mut rows = stmt.query(params![session_token_hash(token), now])?;
+    if let Some(row) = rows.next()? {
+        Ok(Some((row.get(0)?, row.get(1)?)))
+    } else {
+        Ok(None)
+    }
+}
+
+pub fn insert_receipt_share_view(
+    conn: &Connection,
+    share_id: i64,
+    viewed_at: &str,
+    ip: Option<&str>,
+    user_agent: &str,
+) -> Result<()> {
+    conn.execute(
+        "
+        INSERT INTO receipt_share_views (share_id, viewed_at, ip, user_agent)
+        VALUES (?1, ?2, ?3, ?4)
+        ",
+        params![share_id, viewed_at, ip, user_agent],
+    )?;
+    Ok(())
+}
+
+/// The user's links with their openings, the newest link first.
+pub fn list_receipt_shares(conn: &Connection, user_id: i64) -> Result<Vec<ReceiptShare>> {
+    let mut stmt = conn.prepare(
+        "
+        SELECT id, file_name, created_at, expires_at, revoked_at
+        FROM receipt_shares
+        WHERE user_id = ?1
+        ORDER BY created_at DESC, id DESC
+        ",
+    )?;
+    let rows = stmt.query_map(params![user_id], |row| {
+        Ok(ReceiptShare {
+            id: row.get(0)?,
+            file_name: row.get(1)?,
+            created_at: row.get(2)?,
+            expires_at: row.get(3)?,
+            revoked_at: row.get(4)?,
+            views: Vec::new(),
+        })
+    })?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+
+    let mut stmt = conn.prepare(
+        "
+        SELECT v.share_id, v.viewed_at, v.ip, v.user_agent
+        FROM receipt_share_views v
+        JOIN receipt_shares s ON s.id = v.share_id
+        WHERE s.user_id = ?1
+        ORDER BY v.viewed_at DESC, v.id DESC
+        ",
+    )?;
+    let rows = stmt.query_map(params![user_id], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            ReceiptShareView {
+                viewed_at: row.get(1)?,
+                ip: row.get(2)?,
+                user_agent: row.get(3)?,
+            },
+        ))
+    })?;
+    for row in rows {
+        let (share_id, view) = row?;
+        if let Some(share) = out.iter_mut().find(|share| share.id == share_id) {
+            share.views.push(view);
+        }
+    }
+    Ok(out)
+}
+
+/// Revokes the user's link `id`; false when there is no such live link.
+pub fn revoke_receipt_share(conn: &Connection, user_id: i64, id: i64, revoked_at: &str) -> Result<bool> {
+    let revoked = conn.execute(
+        "
+        UPDATE receipt_shares SET revoked_at = ?3
+        WHERE id = ?1 AND user_id = ?2 AND revoked_at IS NULL
+        ",
+        params![id, user_id, revoked_at],
+    )?;
+    Ok(revoked > 0)
+}
+
+/// Takes `file_name` off the transactions that have it as their receipt, for
+/// a file that never made it into place.
+pub fn detach_receipt(conn: &Connection, file_name: &str) -> Result<()> {
+    conn.execute(
+        "UPDATE transactions SET receipt_path = NULL WHERE receipt_path = ?1",
+        params![file_name],
+    )?;
+    Ok(())
+}
+
+/// Payee and note pairs of categorized income and expenses with their
+/// category and how often the pair went to it, the most recently used first.
+pub fn category_history(conn: &Connection, limit: i64) -> Result<Vec<CategoryUse>> {
+    let mut stmt = conn.prepare_cached(
+        "
+        SELECT payee, note, category_id, COUNT(*)
+        FROM transactions
+        WHERE category_id IS NOT NULL
+          AND kind IN ('income', 'expense')
+          AND refund_of IS NULL
+          AND deleted_at IS NULL
+          AND (COALESCE(payee, '') != '' OR COALESCE(note, '') != '')
+        GROUP BY payee, note, category_id
+        ORDER BY MAX(occurred_on) DESC, MAX(id) DESC
+        LIMIT ?1
+        ",
+    )?;
+    let rows = stmt.query_map(params![limit], |row| {
+        Ok(CategoryUse {
+            payee: row.get(0)?,
+            note: row.get(1)?,
+            category_id: row.get(2)?,
+            uses: row.get(3)?,
+        })
+    })?;
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+pub fn accepted_suggestions(conn: &Connection) -> Result<Vec<AcceptedSuggestion>> {
+    let mut stmt = conn.prepare_cached(
+        "
+        SELECT s.field, s.text, s.category_id, s.accepted
+        FROM category_suggestions s
+        JOIN categories c ON c.id = s.category_id
+        ",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(AcceptedSuggestion {
+            field: row.get(0)?,
+            text: row.get(1)?,
+            category_id: row.get(2)?,
+            accepted: row.get(3)?,
+        })
+    })?;
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+pub fn record_accepted_suggestion(
+    conn: &Connection,
+    field: &str,
+    text: &str,
+    category_id: i64,
+) -> Result<()> {
+    conn.execute(
+        "
+        INSERT INTO category_suggestions (field, text, category_id, accepted) VALUES (?1, ?2, ?3, 1)
+        ON CONFLICT(field, text, category_id) DO UPDATE SET accepted = accepted + 1
+        ",
+        params![field, text, category_id],
+    )?;
+    Ok(())
+}
+
+pub fn list_tags(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "
+        SELECT g.name
+        FROM tags g
+        WHERE EXISTS (SELECT 1 FROM transaction_tags tt WHERE tt.tag_id = g.id)
+        ORDER BY g.name
+        ",
+    )?;
+    let rows = stmt.query_map([], |row| row.get(0))?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+/// Adds lines to the receipt of transaction `transaction_id`. Items are
+/// matched by name like categories are, see `category_name_key`.
+pub fn add_receipt_items(conn: &Connection, transaction_id: i64, items: &[NewReceiptItem]) -> Result<()> {
+    let mut stmt = conn.prepare_cached(
+        "
+        INSERT INTO receipt_items (transaction_id, name, name_key, quantity, price_cents)
+        VALUES (?1, ?2, ?3, ?4, ?5)
+        ",
+    )?;
+    for item in items {
+        stmt.execute(params![
+            transaction_id,
+            clean_category_name(&item.name),
+            category_name_key(&item.name),
+            item.quantity,
+            item.price_cents
+        ])?;
+    }
+    Ok(())
+}
+
+/// The lines of a receipt in the order they were added.
+pub fn list_receipt_items(conn: &Connection, transaction_id: i64) -> Result<Vec<ReceiptItem>> {
+    let mut stmt = conn.prepare_cached(
+        "
+        SELECT id, name, quantity, price_cents
+        FROM receipt_items
+        WHERE transaction_id = ?1
+        ORDER BY id
+        ",
+    )?;
+    let rows = stmt.query_map(params![transaction_id], |row| {
+        Ok(ReceiptItem {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            quantity: row.get(2)?,
+            price_cents: row.get(3)?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// Returns false when the transaction has no such item.
+pub fn delete_receipt_item(conn: &Connection, transaction_id: i64, item_id: i64) -> Result<bool> {
+    let deleted = conn.execute(
+        "DELETE FROM receipt_items WHERE id = ?1 AND transaction_id = ?2",
+        params![item_id, transaction_id],
+    )?;
+    Ok(deleted > 0)
+}
+
+/// Purchases outside the trash of items whose name contains `term`, oldest
+/// first.
+pub fn item_prices(conn: &Connection, term: &str, limit: i64) -> Result<Vec<ItemPrice>> {
+    let mut stmt = conn.prepare_cached(
+        "
+        SELECT * FROM (
+            SELECT t.id, t.occurred_on, i.name, t.payee, i.quantity, i.price_cents, i.id AS item_id
+            FROM receipt_items i
+            JOIN transactions t ON t.id = i.transaction_id
+            WHERE instr(i.name_key, ?1) > 0 AND t.deleted_at IS NULL
+            ORDER BY t.occurred_on DESC, item_id DESC
+            LIMIT ?2
+        )
+        ORDER BY occurred_on, item_id
+        ",
+    )?;
+    let rows = stmt.query_map(params![category_name_key(term), limit], |row| {
+        Ok(ItemPrice {
+            transaction_id: row.get(0)?,
+            occurred_on: row.get(1)?,
+            name: row.get(2)?,
+            payee: row.get(3)?,
+            quantity: row.get(4)?,
+            price_cents: row.get(5)?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// The items bought in most expenses outside the trash.
+pub fn frequent_items(conn: &Connection, limit: i64) -> Result<Vec<ItemCount>> {
+    let mut stmt = conn.prepare_cached(
+        "
+        SELECT MAX(i.name), COUNT(DISTINCT i.transaction_id) AS purchases
+        FROM receipt_items i
+        JOIN transactions t ON t.id = i.transaction_id
+        WHERE t.deleted_at IS NULL
+        GROUP BY i.name_key
+        ORDER BY purchases DESC, i.name_key
+        LIMIT ?1
+        ",
+    )?;
+    let rows = stmt.query_map(params![limit], |row| {
+        Ok(ItemCount {
+            name: row.get(0)?,
+            purchases: row.get(1)?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// Balances are in the account's own currency; revaluation adjustments only
+/// change its value in rubles, which is `balance × latest_rate`.
+pub fn list_accounts(conn: &Connection) -> Result<Vec<Account>> {
+    let mut stmt = conn.prepare_cached(
+        "
+        SELECT a.id, a.name, a.kind, a.opening_balance_cents,
+               a.opening_balance_cents
+                 + COALESCE((
+                     SELECT SUM(CASE t.kind
+                                  WHEN 'income' THEN t.amount_cents
+                                  WHEN 'adjustment' THEN 0
+                                  ELSE -t.amount_cents
+                                END)
+                     FROM transactions t
+                     WHERE t.account_id = a.id AND t.deleted_at IS NULL
+                   ), 0)
+                 + COALESCE((
+                     SELECT SUM(t.amount_cents)
+                     FROM transactions t
+                     WHERE t.kind = 'transfer' AND t.to_account_id = a.id AND t.deleted_at IS NULL
+                   ), 0),
+               a.currency,
+               (
+                 SELECT r.rate
+                 FROM revaluations r
+                 WHERE r.account_id = a.id
+                 ORDER BY r.month DESC
+                 LIMIT 1
+               )
+        FROM accounts a
+        ORDER BY a.name
+        ",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(Account {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            kind: row.get(2)?,
+            opening_balance_cents: row.get(3)?,
+            balance_cents: row.get(4)?,
+            currency: row.get(5)?,
+            latest_rate: row.get(6)?,
+        })
+    })?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+pub fn insert_account(
+    conn: &Connection,
+    name: &str,
+    kind: &str,
+    currency: &str,
+    opening_balance_cents: i64,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO accounts (name, kind, currency, opening_balance_cents) VALUES (?1, ?2, ?3, ?4)",
+        params![name, kind, currency, opening_balance_cents],
+    )?;
+    Ok(())
+}
+
+pub fn update_account(
+    conn: &Connection,
+    id: i64,
+    name: &str,
+    kind: &str,
+    currency: &str,
+    opening_balance_cents: i64,
+) -> Result<()> {
+    conn.execute(
+        "
+        UPDATE accounts
+        SET name = ?2, kind = ?3, currency = ?4, opening_balance_cents = ?5
+        WHERE id = ?1
+        ",
+        params![id, name, kind, currency, opening_balance_cents],
+    )?;
+    Ok(())
+}
+
+/// Deletes the account and leaves its transactions and hooks without an account.
+pub fn delete_account(conn: &Connection, id: i64) -> Result<()> {
+    conn.execute(
+        "UPDATE transactions SET account_id = NULL WHERE account_id = ?1",
+        params![id],
+    )?;
+    conn.execute(
+        "UPDATE transactions SET to_account_id = NULL WHERE to_account_id = ?1",
+        params![id],
+    )?;
+    conn.execute(
+        "UPDATE inbound_hooks SET account_id = NULL WHERE account_id = ?1",
+        params![id],
+    )?;
+    conn.execute("DELETE FROM revaluations WHERE account_id = ?1", params![id])?;
+    conn.execute("DELETE FROM accounts WHERE id = ?1", params![id])?;
+    Ok(())
+}
+
+/// Balance in the account's currency as of the end of `until` (inclusive).
+pub fn account_balance_until(conn: &Connection, account_id: i64, until: &str) -> Result<i64> {
+    conn.query_row(
+        "
+        SELECT a.opening_balance_cents
+                 + COALESCE((
+                     SELECT SUM(CASE t.kind
+                                  WHEN 'income' THEN t.amount_cents
+                                  WHEN 'adjustment' THEN 0
+                                  ELSE -t.amount_cents
+                                END)
+                     FROM transactions t
+                     WHERE t.account_id = a.id AND t.occurred_on <= ?2 AND t.deleted_at IS NULL
+                   ), 0)
+                 + COALESCE((
+                     SELECT SUM(t.amount_cents)
+                     FROM transactions t
+                     WHERE t.kind = 'transfer' AND t.to_account_id = a.id AND t.occurred_on <= ?2
+                       AND t.deleted_at IS NULL
+                   ), 0)
+        FROM accounts a
+        WHERE a.id = ?1
+        ",
+        params![account_id, until],
+        |row| row.get(0),
+    )
+}
+
+/// The account's transactions from `from` to `to` inclusive, oldest first,
+/// signed by their effect on its balance. Revaluation adjustments don't
+/// change the balance in the account's currency and are left out.
+pub fn account_statement(conn: &Connection, account_id: i64, from: &str, to: &str) -> Result<Vec<StatementLine>> {
+    let mut stmt = conn.prepare(
+        "
+        SELECT t.id, t.occurred_on, t.kind, t.note, c.name,
+               CASE WHEN t.account_id = ?1 THEN ta.name ELSE fa.name END,
+               CASE
+                 WHEN t.kind = 'transfer' AND t.account_id = ?1 AND t.to_account_id = ?1 THEN 0
+                 WHEN t.kind = 'transfer' AND t.to_account_id = ?1 THEN t.amount_cents
+                 WHEN t.kind = 'income' THEN t.amount_cents
+                 ELSE -t.amount_cents
+               END
+        FROM transactions t
+        LEFT JOIN categories c ON c.id = t.category_id
+        LEFT JOIN accounts fa ON fa.id = t.account_id
+        LEFT JOIN accounts ta ON ta.id = t.to_account_id
+        WHERE (t.account_id = ?1 OR (t.kind = 'transfer' AND t.to_account_id = ?1))
+          AND t.kind != 'adjustment'
+          AND t.occurred_on BETWEEN ?2 AND ?3
+          AND t.deleted_at IS NULL
+        ORDER BY t.occurred_on, t.id
+        ",
+    )?;
+    let rows = stmt.query_map(params![account_id, from, to], |row| {
+        Ok(StatementLine {
+            id: row.get(0)?,
+            occurred_on: row.get(1)?,
+            kind: row.get(2)?,
+            note: row.get(3)?,
+            category_name: row.get(4)?,
+            counterparty: row.get(5)?,
+            amount_cents: row.get(6)?,
+        })
+    })?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+/// Rate of the latest revaluation before `month`, if the account was revalued.
+pub fn previous_revaluation_rate(conn: &Connection, account_id: i64, month: &str) -> Result<Option<f64>> {
+    let mut stmt = conn.prepare(
+        "
+        SELECT rate
+        FROM revaluations
+        WHERE account_id = ?1 AND month < ?2
+        ORDER BY month DESC
+        LIMIT 1
+        ",
+    )?;
+    let mut rows = stmt.query(params![account_id, month])?;
+    if let Some(row) = rows.next()? {
+        Ok(Some(row.get(0)?))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Replaces the account's revaluation for `month` together with its adjustment entry.
+pub fn save_revaluation(
+    conn: &Connection,
+    account_id: i64,
+    month: &str,
+    rate: f64,
+    adjustment: &NewTransaction,
+) -> Result<()> {
+    conn.execute(
+        "
+        DELETE FROM transactions
+        WHERE id IN (SELECT transaction_id FROM revaluations WHERE account_id = ?1 AND month = ?2)
+        ",
+        params![account_id, month],
+    )?;
+    conn.execute(
+        "DELETE FROM revaluations WHERE account_id = ?1 AND month = ?2",
+        params![account_id, month],
+    )?;
+    let transaction_id = insert_transaction(conn, adjustment, None)?;
+    conn.execute(
+        "
+        INSERT INTO revaluations (account_id, month, rate, transaction_id)
+        VALUES (?1, ?2, ?3, ?4)
+        ",
+        params![account_id, month, rate, transaction_id],
+    )?;
+    Ok(())
+}
+
+/// Rate of the latest revaluation up to and including `month`.
+pub fn revaluation_rate_until(conn: &Connection, account_id: i64, month: &str) -> Result<Option<f64>> {
+    let mut stmt = conn.prepare_cached(
+        "
+        SELECT rate
+        FROM revaluations
+        WHERE account_id = ?1 AND month <= ?2
+        ORDER BY month DESC
+        LIMIT 1
+        ",
+    )?;
+    let mut rows = stmt.query(params![account_id, month])?;
+    if let Some(row) = rows.next()? {
+        Ok(Some(row.get(0)?))
+    } else {
+        Ok(None)
+    }
+}
+
+fn loan_from_row(row: &rusqlite::Row<'_>) -> Result<Loan> {
+    Ok(Loan {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        principal_cents: row.get(2)?,
+        annual_rate: row.get(3)?,
+        term_months: row.get(4)?,
+        first_month: row.get(5)?,
+        payment_day: row.get(6)?,
+    })
+}
+
+pub fn list_loans(conn: &Connection) -> Result<Vec<Loan>> {
+    let mut stmt = conn.prepare_cached(
+        "
+        SELECT id, name, principal_cents, annual_rate, term_months, first_month, payment_day
+        FROM loans
+        ORDER BY first_month, name
+        ",
+    )?;
+    let rows = stmt.query_map([], loan_from_row)?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+pub fn loan_by_id(conn: &Connection, id: i64) -> Result<Option<Loan>> {
+    let mut stmt = conn.prepare_cached(
+        "
+        SELECT id, name, principal_cents, annual_rate, term_months, first_month, payment_day
+        FROM loans
+        WHERE id = ?1
+        ",
+    )?;
+    let mut rows = stmt.query(params![id])?;
+    match rows.next()? {
+        Some(row) => Ok(Some(loan_from_row(row)?)),
+        None => Ok(None),
+    }
+}
+
+pub fn insert_loan(conn: &Connection, loan: &NewLoan) -> Result<i64> {
+    conn.execute(
+        "
+        INSERT INTO loans (name, principal_cents, annual_rate, term_months, first_month, payment_day)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+        ",
+        params![
+            loan.name,
+            loan.principal_cents,
+            loan.annual_rate,
+            loan.term_months,
+            loan.first_month,
+            loan.payment_day
+        ],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Deletes the loan; its payment transactions stay as ordinary expenses.
+pub fn delete_loan(conn: &Connection, id: i64) -> Result<()> {
+    conn.execute("DELETE FROM loan_payments WHERE loan_id = ?1", params![id])?;
+    conn.execute("DELETE FROM loans WHERE id = ?1", params![id])?;
+    Ok(())
+}
+
+pub fn link_loan_payment(conn: &Connection, loan_id: i64, transaction_id: i64) -> Result<()> {
+    conn.execute(
+        "INSERT INTO loan_payments (loan_id, transaction_id) VALUES (?1, ?2)",
+        params![loan_id, transaction_id],
+    )?;
+    Ok(())
+}
+
+/// Payments on the loan, oldest first.
+pub fn loan_payments(conn: &Connection, loan_id: i64) -> Result<Vec<LoanPayment>> {
+    let mut stmt = conn.prepare_cached(
+        "
+        SELECT t.id, t.occurred_on, t.amount_cents
+        FROM loan_payments p
+        JOIN transactions t ON t.id = p.transaction_id
+        WHERE p.loan_id = ?1 AND t.deleted_at IS NULL
+        ORDER BY t.occurred_on, t.id
+        ",
+    )?;
+    let rows = stmt.query_map(params![loan_id], |row| {
+        Ok(LoanPayment {
+            transaction_id: row.get(0)?,
+            occurred_on: row.get(1)?,
+            amount_cents: row.get(2)?,
+        })
+    })?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+pub fn list_meters(conn: &Connection) -> Result<Vec<Meter>> {
+    let mut stmt = conn.prepare_cached("SELECT id, name, kind FROM meters ORDER BY kind, name, id")?;
+    let rows = stmt.query_map([], |row| {
+        Ok(Meter {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            kind: row.get(2)?,
+        })
+    })?;
+    rows.collect()
+}
+
+pub fn meter_by_id(conn: &Connection, id: i64) -> Result<Option<Meter>> {
+    let mut stmt = conn.prepare_cached("SELECT id, name, kind FROM meters WHERE id = ?1")?;
+    let mut rows = stmt.query(params![id])?;
+    let Some(row) = rows.next()? else {
+        return Ok(None);
+    };
+    Ok(Some(Meter {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        kind: row.get(2)?,
+    }))
+}
+
+pub fn insert_meter(conn: &Connection, name: &str, kind: &str) -> Result<i64> {
+    conn.execute(
+        "INSERT INTO meters (name, kind) VALUES (?1, ?2)",
+        params![name, kind],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Deletes the meter and its readings; the expenses they were paid by stay.
+pub fn delete_meter(conn: &Connection, id: i64) -> Result<()> {
+    conn.execute("DELETE FROM meter_readings WHERE meter_id = ?1", params![id])?;
+    conn.execute("DELETE FROM meters WHERE id = ?1", params![id])?;
+    Ok(())
+}
+
+/// Readings of the meter, oldest month first.
+pub fn meter_readings(conn: &Connection, meter_id: i64) -> Result<Vec<MeterReading>> {
+    let mut stmt = conn.prepare_cached(
+        "
+        SELECT r.id, r.month, r.value, r.read_on, r.transaction_id, t.occurred_on, t.amount_cents
+        FROM meter_readings r
+        LEFT JOIN transactions t ON t.id = r.transaction_id AND t.deleted_at IS NULL
+        WHERE r.meter_id = ?1
+        ORDER BY r.month
+        ",
+    )?;
+    let rows = stmt.query_map(params![meter_id], |row| {
+        Ok(MeterReading {
+            id: row.get(0)?,
+            month: row.get(1)?,
+            value: row.get(2)?,
+            read_on: row.get(3)?,
+            transaction_id: row.get(4)?,
+            paid_on: row.get(5)?,
+            paid_cents: row.get(6)?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// Records the reading of `month`, replacing one already there; the
+/// expense it was linked to stays linked.
+pub fn set_meter_reading(conn: &Connection, meter_id: i64, month: &str, value: f64, read_on: &str) -> Result<()> {
+    conn.execute(
+        "
+        INSERT INTO meter_readings (meter_id, month, value, read_on)
+        VALUES (?1, ?2, ?3, ?4)
+        ON CONFLICT(meter_id, month) DO UPDATE SET value = excluded.value, read_on = excluded.read_on
+        ",
+        params![meter_id, month, value, read_on],
+    )?;
+    Ok(())
+}
+
+/// Links the reading to the expense that paid for it, or unlinks it;
+/// `false` when the meter has no such reading.
+pub fn link_meter_reading(
+    conn: &Connection,
+    meter_id: i64,
+    reading_id: i64,
+    transaction_id: Option<i64>,
+) -> Result<bool> {
+    let changed = conn.execute(
+        "UPDATE meter_readings SET transaction_id = ?3 WHERE id = ?1 AND meter_id = ?2",
+        params![reading_id, meter_id, transaction_id],
+    )?;
+    Ok(changed > 0)
+}
+
+/// Tariffs by kind, the latest first.
+pub fn list_tariffs(conn: &Connection) -> Result<Vec<Tariff>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, kind, rate_cents, effective_from FROM tariffs ORDER BY kind, effective_from DESC",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(Tariff {
+            id: row.get(0)?,
+            kind: row.get(1)?,
+            rate_cents: row.get(2)?,
+            effective_from: row.get(3)?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// Sets the rate of `kind` from `effective_from` on, replacing one set
+/// for the same date.
+pub fn set_tariff(conn: &Connection, kind: &str, rate_cents: i64, effective_from: &str) -> Result<()> {
+    conn.execute(
+        "
+        INSERT INTO tariffs (kind, rate_cents, effective_from)
+        VALUES (?1, ?2, ?3)
+        ON CONFLICT(kind, effective_from) DO UPDATE SET rate_cents = excluded.rate_cents
+        ",
+        params![kind, rate_cents, effective_from],
+    )?;
+    Ok(())
+}
+
+pub fn delete_tariff(conn: &Connection, id: i64) -> Result<()> {
+    conn.execute("DELETE FROM tariffs WHERE id = ?1", params![id])?;
+    Ok(())
+}
+
+/// Expenses outside the trash from `from` to `to` in the category `ЖКХ`
+/// or one under it, oldest first.
+pub fn utility_expenses(conn: &Connection, from: &str, to: &str) -> Result<Vec<UtilityExpense>> {
+    let mut stmt = conn.prepare_cached(
+        "
+        SELECT t.id, t.occurred_on, t.amount_cents, c.name, t.payee
+        FROM transactions t
+        JOIN categories c ON c.id = t.category_id
+        LEFT JOIN categories p ON p.id = c.parent_id
+        WHERE t.kind = 'expense' AND t.deleted_at IS NULL
+          AND (c.name_key = ?1 OR p.name_key = ?1)
+          AND t.occurred_on BETWEEN ?2 AND ?3
+        ORDER BY t.occurred_on, t.id
+        ",
+    )?;
+    let rows = stmt.query_map(params![category_name_key("ЖКХ"), from, to], |row| {
+        Ok(UtilityExpense {
+            transaction_id: row.get(0)?,
+            occurred_on: row.get(1)?,
+            amount_cents: row.get(2)?,
+            category_name: row.get(3)?,
+            payee: row.get(4)?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// Assets first, then liabilities, each with its latest valuation.
+pub fn list_holdings(conn: &Connection) -> Result<Vec<Holding>> {
+    let mut stmt = conn.prepare_cached(
+        "
+        SELECT h.id, h.name, h.kind, v.amount_cents, v.valued_on
+        FROM holdings h
+        LEFT JOIN holding_valuations v ON v.id = (
+            SELECT id
+            FROM holding_valuations
+            WHERE holding_id = h.id
+            ORDER BY valued_on DESC
+            LIMIT 1
+        )
+        ORDER BY h.kind, h.name
+        ",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(Holding {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            kind: row.get(2)?,
+            amount_cents: row.get(3)?,
+            valued_on: row.get(4)?,
+        })
+    })?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+pub fn insert_holding(conn: &Connection, name: &str, kind: &str) -> Result<i64> {
+    conn.execute(
+        "INSERT INTO holdings (name, kind) VALUES (?1, ?2)",
+        params![name, kind],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Records the holding's value on a day, replacing an earlier one for that day.
+pub fn record_valuation(conn: &Connection, holding_id: i64, valued_on: &str, amount_cents: i64) -> Result<()> {
+    conn.execute(
+        "
+        INSERT INTO holding_valuations (holding_id, valued_on, amount_cents)
+        SELECT id, ?2, ?3 FROM holdings WHERE id = ?1
+        ON CONFLICT(holding_id, valued_on) DO UPDATE SET amount_cents = excluded.amount_cents
+        ",
+        params![holding_id, valued_on, amount_cents],
+    )?;
+    Ok(())
+}
+
+pub fn delete_holding(conn: &Connection, id: i64) -> Result<()> {
+    conn.execute("DELETE FROM holding_valuations WHERE holding_id = ?1", params![id])?;
+    conn.execute("DELETE FROM holdings WHERE id = ?1", params![id])?;
+    Ok(())
+}
+
+/// Assets and liabilities as of `until` (inclusive): each holding counts with
+/// its latest valuation by then, holdings not yet valued don't count.
+pub fn holdings_until(conn: &Connection, until: &str) -> Result<(i64, i64)> {
+    conn.query_row(
+        "
+        SELECT COALESCE(SUM(CASE WHEN h.kind = 'asset' THEN v.amount_cents END), 0),
+               COALESCE(SUM(CASE WHEN h.kind = 'liability' THEN v.amount_cents END), 0)
+        FROM holdings h
+        JOIN holding_valuations v ON v.id = (
+            SELECT id
+            FROM holding_valuations
+            WHERE holding_id = h.id AND valued_on <= ?1
+            ORDER BY valued_on DESC
+            LIMIT 1
+        )
+        ",
+        params![until],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )
+}
+
+/// Stores a rate for one day; fetched rates never replace a manual override.
+pub fn upsert_exchange_rate(
+    conn: &Connection,
+    currency: &str,
+    rate_date: &str,
+    rate: f64,
+    source: &str,
+    updated_at: &str,
+) -> Result<()> {
+    conn.execute(
+        "
+        INSERT INTO exchange_rates (currency, rate_date, rate, source, updated_at)
+        VALUES (?1, ?2, ?3, ?4, ?5)
+        ON CONFLICT(currency, rate_date) DO UPDATE
+        SET rate = excluded.rate, source = excluded.source, updated_at = excluded.updated_at
+        WHERE exchange_rates.source = 'auto' OR excluded.source = 'manual'
+        ",
+        params![currency, rate_date, rate, source, updated_at],
+    )?;
+    Ok(())
+}
+
+fn exchange_rates_from(conn: &Connection, query: &str) -> Result<Vec<ExchangeRate>> {
+    let mut stmt = conn.prepare(query)?;
+    let rows = stmt.query_map([], |row| {
+        Ok(ExchangeRate {
+            currency: row.get(0)?,
+            rate_date: row.get(1)?,
+            rate: row.get(2)?,
+            source: row.get(3)?,
+        })
+    })?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+/// Most recent rate for every currency.
+pub fn latest_exchange_rates(conn: &Connection) -> Result<Vec<ExchangeRate>> {
+    exchange_rates_from(
+        conn,
+        "
+        SELECT e.currency, e.rate_date, e.rate, e.source
+        FROM exchange_rates e
+        WHERE e.rate_date = (
+            SELECT MAX(rate_date) FROM exchange_rates WHERE currency = e.currency
+        )
+        ORDER BY e.currency
+        ",
+    )
+}
+
+pub fn manual_exchange_rates(conn: &Connection) -> Result<Vec<ExchangeRate>> {
+    exchange_rates_from(
+        conn,
+        "
+        SELECT currency, rate_date, rate, source
+        FROM exchange_rates
+        WHERE source = 'manual'
+        ORDER BY rate_date DESC, currency
+        ",
+    )
+}
+
+/// Rate in effect on `date`: the latest one known on or before that day.
+pub fn exchange_rate_on(conn: &Connection, currency: &str, date: &str) -> Result<Option<f64>> {
+    let mut stmt = conn.prepare(
+        "
+        SELECT rate
+        FROM exchange_rates
+        WHERE currency = ?1 AND rate_date <= ?2
+        ORDER BY rate_date DESC
+        LIMIT 1
+        ",
+    )?;
+    let mut rows = stmt.query(params![currency, date])?;
+    if let Some(row) = rows.next()? {
+        Ok(Some(row.get(0)?))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Removes a manual override; the next fetch fills that day in again.
+pub fn delete_manual_exchange_rate(conn: &Connection, currency: &str, rate_date: &str) -> Result<()> {
+    conn.execute(
+        "DELETE FROM exchange_rates WHERE currency = ?1 AND rate_date = ?2 AND source = 'manual'",
+        params![currency, rate_date],
+    )?;
+    Ok(())
+}
+
+/// Status and time of the last attempt to send a month to the accountant.
+pub fn accountant_export_state(conn: &Connection, month: &str) -> Result<Option<(String, String)>> {
+    let mut stmt = conn.prepare("SELECT status, attempted_at FROM accountant_exports WHERE month = ?1")?;
+    let mut rows = stmt.query(params![month])?;
+    if let Some(row) = rows.next()? {
+        Ok(Some((row.get(0)?, row.get(1)?)))
+    } else {
+        Ok(None)
+    }
+}
+
+pub fn record_accountant_export(
+    conn: &Connection,
+    month: &str,
+    status: &str,
+    attempted_at: &str,
+    detail: Option<&str>,
+) -> Result<()> {
+    conn.execute(
+        "
+        INSERT INTO accountant_exports (month, status, attempted_at, detail)
+        VALUES (?1, ?2, ?3, ?4)
+        ON CONFLICT(month) DO UPDATE
+        SET status = excluded.status, attempted_at = excluded.attempted_at, detail = excluded.detail
+        ",
+        params![month, status, attempted_at, detail],
+    )?;
+    Ok(())
+}
+
+const INBOUND_HOOK_COLUMNS: &str = "id, user_id, name, secret, kind, amount_field, date_field,
+    note_field, category_field, category_id, account_id, last_used_at";
+
+fn inbound_hook_from_row(row: &rusqlite::Row<'_>) -> Result<InboundHook> {
+    Ok(InboundHook {
+        id: row.get(0)?,
+        user_id: row.get(1)?,
+        name: row.get(2)?,
+        secret: row.get(3)?,
+        kind: row.get(4)?,
+        amount_field: row.get(5)?,
+        date_field: row.get(6)?,
+        note_field: row.get(7)?,
+        category_field: row.get(8)?,
+        category_id: row.get(9)?,
+        account_id: row.get(10)?,
+        last_used_at: row.get(11)?,
+    })
+}
+
+pub fn list_inbound_hooks(conn: &Connection) -> Result<Vec<InboundHook>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {INBOUND_HOOK_COLUMNS} FROM inbound_hooks ORDER BY name"
+    ))?;
+    let rows = stmt.query_map([], inbound_hook_from_row)?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+pub fn inbound_hook_by_secret(conn: &Connection, secret: &str) -> Result<Option<InboundHook>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {INBOUND_HOOK_COLUMNS} FROM inbound_hooks WHERE secret = ?1"
+    ))?;
+    let mut rows = stmt.query(params![secret])?;
+    if let Some(row) = rows.next()? {
+        Ok(Some(inbound_hook_from_row(row)?))
+    } else {
+        Ok(None)
+    }
+}
+
+pub fn insert_inbound_hook(
+    conn: &Connection,
+    user_id: i64,
+    hook: &NewInboundHook,
+    secret: &str,
+    created_at: &str,
+) -> Result<()> {
+    conn.execute(
+        "
+        INSERT INTO inbound_hooks
+            (user_id, name, secret, kind, amount_field, date_field, note_field,
+             category_field, category_id, account_id, created_at)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+        ",
+        params![
+            user_id,
+            hook.name,
+            secret,
+            hook.kind,
+            hook.amount_field,
+            hook.date_field,
+            hook.note_field,
+            hook.category_field,
+            hook.category_id,
+            hook.account_id,
+            created_at
+        ],
+    )?;
+    Ok(())
+}
+
+pub fn mark_inbound_hook_used(conn: &Connection, id: i64, used_at: &str) -> Result<()> {
+    conn.execute(
+        "UPDATE inbound_hooks SET last_used_at = ?2 WHERE id = ?1",
+        params![id, used_at],
+    )?;
+    Ok(())
+}
+
+pub fn delete_inbound_hook(conn: &Connection, id: i64) -> Result<()> {
+    conn.execute("DELETE FROM inbound_hooks WHERE id = ?1", params![id])?;
+    Ok(())
+}
+
+/// Rules in the order they are tried: oldest first.
+pub fn list_rules(conn: &Connection) -> Result<Vec<Rule>> {
+    let mut stmt = conn.prepare(
+        "
+        SELECT r.id, r.pattern, r.min_cents, r.max_cents, r.category_id, c.name, r.kind, r.tags
+        FROM rules r
+        LEFT JOIN categories c ON c.id = r.category_id
+        ORDER BY r.id
+        ",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(Rule {
+            id: row.get(0)?,
+            pattern: row.get(1)?,
+            min_cents: row.get(2)?,
+            max_cents: row.get(3)?,
+            category_id: row.get(4)?,
+            category_name: row.get(5)?,
+            kind: row.get(6)?,
+            tags: row.get(7)?,
+        })
+    })?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+pub fn insert_rule(conn: &Connection, rule: &NewRule, created_at: &str) -> Result<i64> {
+    conn.execute(
+        "
+        INSERT INTO rules (pattern, min_cents, max_cents, category_id, kind, tags, created_at)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+        ",
+        params![
+            rule.pattern,
+            rule.min_cents,
+            rule.max_cents,
+            rule.category_id,
+            rule.kind,
+            rule.tags,
+            created_at
+        ],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn delete_rule(conn: &Connection, id: i64) -> Result<()> {
+    conn.execute("DELETE FROM rules WHERE id = ?1", params![id])?;
+    Ok(())
+}
+
+/// The budgets in force for month `?2`: its own rows, plus standing budgets
+/// of categories that have none for that month.
+const MONTH_BUDGETS: &str = "
+    month_budgets AS (
+        SELECT id, category_id, amount_cents, rollover, 0 AS standing, note
+        FROM budgets
+        WHERE month = ?2 AND deleted_at IS NULL
+        UNION ALL
+        SELECT id, category_id, amount_cents, 0, 1, note
+        FROM standing_budgets
+        WHERE deleted_at IS NULL
+          AND category_id NOT IN (
+              SELECT category_id FROM budgets WHERE month = ?2 AND deleted_at IS NULL
+          )
+    )";
+
+/// The month's budgets with what was spent, refunds netted as the reports
+/// net them.
+pub fn list_budgets(
+    conn: &Connection,
+    month: &str,
+    refunds: RefundMonth,
+) -> Result<Vec<BudgetRecord>> {
+    let mut stmt = conn.prepare(&format!(
+        "
+        WITH {MONTH_BUDGETS}, {}
+        SELECT b.id, b.category_id, c.name, ?2, b.amount_cents,
+               COALESCE(SUM(t.amount_cents), 0) AS spent_cents, b.rollover, b.standing, b.note
+        FROM month_budgets b
+        JOIN categories c ON b.category_id = c.id
+        LEFT JOIN report_expenses t
+            ON t.category_id = b.category_id
+           AND t.month = ?1
+        GROUP BY b.id, b.standing, b.category_id, c.name, b.amount_cents, b.rollover, b.note
+        ORDER BY c.name
+        ",
+        report_expenses(refunds)
+    ))?;
+    let rows = stmt.query_map(params![month, month], |row| {
+        Ok(BudgetRecord {
+            id: row.get(0)?,
+            category_id: row.get(1)?,
+            category_name: row.get(2)?,
+            month: row.get(3)?,
+            amount_cents: row.get(4)?,
+            spent_cents: row.get(5)?,
+            rollover: row.get(6)?,
+            standing: row.get(7)?,
+            note: row.get(8)?,
+            carried_cents: 0,
+        })
+    })?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        let mut budget = row?;
+        budget.carried_cents = carried_over(conn, budget.category_id, month, refunds)?;
+        out.push(budget);
+    }
+    Ok(out)
+}
+
+/// What the category's rollover budgets bring into `month`: the unbroken run
+/// of consecutive earlier months with a rollover budget passes on its limits
+/// minus everything spent in those months.
+fn carried_over(
+    conn: &Connection,
+    category_id: i64,
+    month: &str,
+    refunds: RefundMonth,
+) -> Result<i64> {
+    let mut stmt = conn.prepare_cached(
+        "
+        SELECT month, strftime('%Y-%m', month || '-01', '+1 month'), amount_cents, rollover
+        FROM budgets
+        WHERE category_id = ?1 AND month < ?2 AND deleted_at IS NULL
+        ORDER BY month DESC
+        ",
+    )?;
+    let mut rows = stmt.query(params![category_id, month])?;
+    let mut first_month = month.to_string();
+    let mut limits_cents = 0;
+    while let Some(row) = rows.next()? {
+        let next_month: Option<String> = row.get(1)?;
+        let rollover: bool = row.get(3)?;
+        if !rollover || next_month.as_deref() != Some(first_month.as_str()) {
+            break;
+        }
+        limits_cents += row.get::<_, i64>(2)?;
+        first_month = row.get(0)?;
+    }
+    if first_month == month {
+        return Ok(0);
+    }
+
+    let spent_cents: i64 = conn
+        .prepare_cached(&format!(
+            "
+            WITH {}
+            SELECT COALESCE(SUM(amount_cents), 0)
+            FROM report_expenses
+            WHERE category_id = ?1 AND month >= ?2 AND month < ?3
+            ",
+            report_expenses(refunds)
+        ))?
+        .query_row(params![category_id, first_month, month], |row| row.get(0))?;
+    Ok(limits_cents - spent_cents)
+}
+
+/// Sets the category's budget for the month, replacing an existing one.
+pub fn insert_budget(
+    conn: &Connection,
+    category_id: i64,
+    month: &str,
+    amount_cents: i64,
+    rollover: bool,
+) -> Result<()> {
+    conn.execute(
+        "
+        INSERT INTO budgets (category_id, month, amount_cents, rollover) VALUES (?1, ?2, ?3, ?4)
+        ON CONFLICT(category_id, month)
+        DO UPDATE SET amount_cents = excluded.amount_cents, rollover = excluded.rollover,
+                      deleted_at = NULL
+        ",
+        params![category_id, month, amount_cents, rollover],
+    )?;
+    Ok(())
+}
+
+/// Copies `from`'s budgets into `to` for categories without one there yet and
+/// returns the new row ids.
+pub fn copy_budgets(conn: &Connection, from: &str, to: &str) -> Result<Vec<i64>> {
+    let mut stmt = conn.prepare(
+        "
+        SELECT category_id, amount_cents, rollover, note
+        FROM budgets
+        WHERE month = ?1
+          AND deleted_at IS NULL
+          AND category_id NOT IN (
+              SELECT category_id FROM budgets WHERE month = ?2 AND deleted_at IS NULL
+          )
+        ORDER BY category_id
+        ",
+    )?;
+    let rows = stmt.query_map(params![from, to], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, i64>(1)?,
+            row.get::<_, bool>(2)?,
+            row.get::<_, Option<String>>(3)?,
+        ))
+    })?;
+    let mut source = Vec::new();
+    for row in rows {
+        source.push(row?);
+    }
+
+    let mut ids = Vec::new();
+    for (category_id, amount_cents, rollover, note) in source {
+        // A trashed budget for the month would clash with the copy; the copy replaces it.
+        conn.execute(
+            "DELETE FROM budgets WHERE category_id = ?1 AND month = ?2 AND deleted_at IS NOT NULL",
+            params![category_id, to],
+        )?;
+        conn.execute(
+            "
+            INSERT INTO budgets (category_id, month, amount_cents, rollover, note)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+            ",
+            params![category_id, to, amount_cents, rollover, note],
+        )?;
+        ids.push(conn.last_insert_rowid());
+    }
+    Ok(ids)
+}
+
+/// Sets or, with `None`, clears the budget's note; `false` when there is no such budget.
+pub fn set_budget_note(conn: &Connection, id: i64, note: Option<&str>) -> Result<bool> {
+    let changed = conn.execute(
+        "UPDATE budgets SET note = ?2 WHERE id = ?1 AND deleted_at IS NULL",
+        params![id, note],
+    )?;
+    Ok(changed > 0)
+}
+
+pub fn set_standing_budget_note(conn: &Connection, id: i64, note: Option<&str>) -> Result<bool> {
+    let changed = conn.execute(
+        "UPDATE standing_budgets SET note = ?2 WHERE id = ?1 AND deleted_at IS NULL",
+        params![id, note],
+    )?;
+    Ok(changed > 0)
+}
+
+pub fn list_standing_budgets(conn: &Connection) -> Result<Vec<StandingBudget>> {
+    let mut stmt = conn.prepare(
+        "
+        SELECT s.id, s.category_id, c.name, s.amount_cents, s.yearly_increase_percent
+        FROM standing_budgets s
+        JOIN categories c ON s.category_id = c.id
+        WHERE s.deleted_at IS NULL
+        ORDER BY c.name
+        ",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(StandingBudget {
+            id: row.get(0)?,
+            category_id: row.get(1)?,
+            category_name: row.get(2)?,
+            amount_cents: row.get(3)?,
+            yearly_increase_percent: row.get(4)?,
+        })
+    })?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+/// Sets the budget the category gets in every month without one of its own.
+pub fn set_standing_budget(conn: &Connection, category_id: i64, amount_cents: i64) -> Result<()> {
+    conn.execute(
+        "
+        INSERT INTO standing_budgets (category_id, amount_cents) VALUES (?1, ?2)
+        ON CONFLICT(category_id)
+        DO UPDATE SET amount_cents = excluded.amount_cents, deleted_at = NULL
+        ",
+        params![category_id, amount_cents],
+    )?;
+    Ok(())
+}
+
+/// Sets or, with `None`, clears the yearly increase; `false` when there is no such budget.
+pub fn set_standing_budget_increase(conn: &Connection, id: i64, percent: Option<f64>) -> Result<bool> {
+    let changed = conn.execute(
+        "UPDATE standing_budgets SET yearly_increase_percent = ?2 WHERE id = ?1",
+        params![id, percent],
+    )?;
+    Ok(changed > 0)
+}
+
+pub fn set_standing_budget_amount(conn: &Connection, id: i64, amount_cents: i64) -> Result<()> {
+    conn.execute(
+        "UPDATE standing_budgets SET amount_cents = ?2 WHERE id = ?1",
+        params![id, amount_cents],
+    )?;
+    Ok(())
+}
+
+/// Whether standing budgets were already raised for the fiscal year.
+pub fn budget_increase_done(conn: &Connection, fiscal_year: i32) -> Result<bool> {
+    conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM budget_increases WHERE fiscal_year = ?1)",
+        params![fiscal_year],
+        |row| row.get(0),
+    )
+}
+
+pub fn record_budget_increase(conn: &Connection, fiscal_year: i32, applied_at: &str) -> Result<()> {
+    conn.execute(
+        "INSERT OR IGNORE INTO budget_increases (fiscal_year, applied_at) VALUES (?1, ?2)",
+        params![fiscal_year, applied_at],
+    )?;
+    Ok(())
+}
+
+/// Moves a transaction to the trash; `false` when there is no such
+/// transaction or it is a revaluation adjustment, which goes with its
+/// revaluation instead.
+pub fn refund_month(conn: &Connection, user_id: i64) -> Result<RefundMonth> {
+    let value: String = conn.query_row(
+        "SELECT refund_month FROM users WHERE id = ?1",
+        params![user_id],
+        |row| row.get(0),
+    )?;
+    Ok(RefundMonth::from_param(&value).unwrap_or_default())
+}
+
+pub fn set_refund_month(conn: &Connection, user_id: i64, refunds: RefundMonth) -> Result<()> {
+    conn.execute(
+        "UPDATE users SET refund_month = ?2 WHERE id = ?1",
+        params![user_id, refunds.param()],
+    )?;
+    Ok(())
+}
+
+pub fn report_rounding(conn: &Connection, user_id: i64) -> Result<Rounding> {
+    let value: String = conn.query_row(
+        "SELECT report_rounding FROM users WHERE id = ?1",
+        params![user_id],
+        |row| row.get(0),
+    )?;
+    Ok(Rounding::from_param(&value).unwrap_or_default())
+}
+
+pub fn set_report_rounding(conn: &Connection, user_id: i64, rounding: Rounding) -> Result<()> {
+    conn.execute(
+        "UPDATE users SET report_rounding = ?2 WHERE id = ?1",
+        params![user_id, rounding.param()],
+    )?;
+    Ok(())
+}
+
+/// False unless `id` is an expense that is neither disputed nor refunded.
+pub fn dispute_transaction(conn: &Connection, id: i64, disputed_on: &str) -> Result<bool> {
+    let changed = conn.execute(
+        "
+        UPDATE transactions SET status = 'disputed', disputed_on = ?2
+        WHERE id = ?1 AND kind = 'expense' AND status = 'normal' AND deleted_at IS NULL
+        ",
+        params![id, disputed_on],
+    )?;
+    Ok(changed > 0)
+}
+
+/// A dispute that ended without money back; false if `id` wasn't disputed.
+pub fn close_dispute(conn: &Connection, id: i64) -> Result<bool> {
+    let changed = conn.execute(
+        "
+        UPDATE transactions SET status = 'normal', disputed_on = NULL
+        WHERE id = ?1 AND status = 'disputed' AND deleted_at IS NULL
+        ",
+        params![id],
+    )?;
+    Ok(changed > 0)
+}
+
+/// Marks expense `id` refunded and records the money coming back as an income
+/// linked to it, on the same account and category. `None` when `id` isn't an
+/// expense still waiting for a refund.
+pub fn record_refund(
+    conn: &Connection,
+    id: i64,
+    amount_cents: i64,
+    occurred_on: &str,
+) -> Result<Option<i64>> {
+    let changed = conn.execute(
+        "
+        UPDATE transactions SET status = 'refunded'
+        WHERE id = ?1 AND kind = 'expense' AND status != 'refunded' AND deleted_at IS NULL
+        ",
+        params![id],
+    )?;
+    if changed == 0 {
+        return Ok(None);
+    }
+    conn.execute(
+        "
+        INSERT INTO transactions (
+            kind, amount_cents, category_id, occurred_on, note, payee, account_id, refund_of
+        )
+        SELECT 'income', ?2, category_id, ?3, note, payee, account_id, id
+        FROM transactions
+        WHERE id = ?1
+        ",
+        params![id, amount_cents, occurred_on],
+    )?;
+    Ok(Some(conn.last_insert_rowid()))
+}
+
+/// Disputed expenses, and refunded ones with what came back, open cases first
+/// and the longest waiting among them.
+pub fn list_disputes(conn: &Connection, refunded_limit: i64) -> Result<Vec<Dispute>> {
+    let mut stmt = conn.prepare_cached(
+        "
+        SELECT * FROM (
+            SELECT t.id, t.occurred_on, t.amount_cents, c.name, t.payee, t.note, t.status,
+                   t.disputed_on, NULL AS refund_cents, NULL AS refunded_on
+            FROM transactions t
+            LEFT JOIN categories c ON c.id = t.category_id
+            WHERE t.status = 'disputed' AND t.deleted_at IS NULL
+            UNION ALL
+            SELECT * FROM (
+                SELECT t.id, t.occurred_on, t.amount_cents, c.name, t.payee, t.note, t.status,
+                       t.disputed_on, r.amount_cents, r.occurred_on
+                FROM transactions t
+                LEFT JOIN categories c ON c.id = t.category_id
+                JOIN transactions r ON r.refund_of = t.id AND r.deleted_at IS NULL
+                WHERE t.status = 'refunded' AND t.deleted_at IS NULL
+                ORDER BY r.occurred_on DESC, r.id DESC
+                LIMIT ?1
+            )
+        )
+        ORDER BY status = 'refunded',
+                 CASE WHEN status = 'disputed' THEN disputed_on END,
+                 refunded_on DESC,
+                 id
+        ",
+    )?;
+    let rows = stmt.query_map(params![refunded_limit], |row| {
+        Ok(Dispute {
+            transaction_id: row.get(0)?,
+            occurred_on: row.get(1)?,
+            amount_cents: row.get(2)?,
+            category_name: row.get(3)?,
+            payee: row.get(4)?,
+            note: row.get(5)?,
+            status: row.get(6)?,
+            disputed_on: row.get(7)?,
+            refund_cents: row.get(8)?,
+            refunded_on: row.get(9)?,
+        })
+    })?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+/// Every cash account with what went in and out of it during `month`.
+pub fn cash_envelopes(conn: &Connection, month: &str) -> Result<Vec<CashEnvelope>> {
+    let mut stmt = conn.prepare_cached(&format!(
+        "
+        SELECT a.id, a.name,
+               COALESCE((
+                 SELECT SUM(t.amount_cents)
+                 FROM transactions t
+                 WHERE t.kind = 'transfer' AND t.to_account_id = a.id
+                   AND {month} = ?1 AND t.deleted_at IS NULL
+               ), 0),
+               COALESCE((
+                 SELECT SUM(t.amount_cents)
+                 FROM transactions t
+                 WHERE t.kind = 'expense' AND t.account_id = a.id
+                   AND {month} = ?1 AND t.deleted_at IS NULL
+               ), 0),
+               (
+                 SELECT MAX(t.occurred_on)
+                 FROM transactions t
+                 WHERE t.kind = 'transfer' AND t.to_account_id = a.id AND t.deleted_at IS NULL
+               )
+        FROM accounts a
+        WHERE a.kind = 'cash'
+        ORDER BY a.name
+        ",
+        month = month_of("t")
+    ))?;
+    let rows = stmt.query_map(params![month], |row| {
+        Ok(CashEnvelope {
+            account_id: row.get(0)?,
+            name: row.get(1)?,
+            withdrawn_cents: row.get(2)?,
+            spent_cents: row.get(3)?,
+            last_withdrawal_on: row.get(4)?,
+        })
+    })?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+/// Expenses since `since` paid from a card or bank account, newest first;
+/// the caller picks those that read like ATM withdrawals.
+pub fn withdrawal_candidates(conn: &Connection, since: &str) -> Result<Vec<WithdrawalCandidate>> {
+    let mut stmt = conn.prepare_cached(
+        "
+        SELECT t.id, t.occurred_on, t.amount_cents, a.name, t.payee, t.note
+        FROM transactions t
+        JOIN accounts a ON a.id = t.account_id
+        WHERE t.kind = 'expense' AND a.kind != 'cash' AND t.status = 'normal'
+          AND t.occurred_on >= ?1 AND t.deleted_at IS NULL
+        ORDER BY t.occurred_on DESC, t.id DESC
+        ",
+    )?;
+    let rows = stmt.query_map(params![since], |row| {
+        Ok(WithdrawalCandidate {
+            transaction_id: row.get(0)?,
+            occurred_on: row.get(1)?,
+            amount_cents: row.get(2)?,
+            account_name: row.get(3)?,
+            payee: row.get(4)?,
+            note: row.get(5)?,
+        })
+    })?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+/// Turns expense `id` into a transfer from its account to cash account
+/// `cash_account_id`; false if it isn't a plain expense from another account
+/// or the target isn't a cash account.
+pub fn convert_to_withdrawal(conn: &Connection, id: i64, cash_account_id: i64) -> Result<bool> {
+    let changed = conn.execute(
+        "
+        UPDATE transactions SET kind = 'transfer', to_account_id = ?2, category_id = NULL
+        WHERE id = ?1 AND kind = 'expense' AND status = 'normal' AND deleted_at IS NULL
+          AND account_id IS NOT NULL AND account_id != ?2
+          AND EXISTS (SELECT 1 FROM accounts WHERE id = ?2 AND kind = 'cash')
+        ",
+        params![id, cash_account_id],
+    )?;
+    Ok(changed > 0)
+}
+
+/// False when there is no such transaction outside the trash.
+pub fn set_transaction_starred(conn: &Connection, id: i64, starred: bool) -> Result<bool> {
+    let changed = conn.execute(
+        "UPDATE transactions SET starred = ?2 WHERE id = ?1 AND deleted_at IS NULL",
+        params![id, starred],
+    )?;
+    Ok(changed > 0)
+}
+
+pub fn trash_transaction(conn: &Connection, id: i64, at: &str) -> Result<bool> {
+    let changed = conn.execute(
+        "
+        UPDATE transactions SET deleted_at = ?2
+        WHERE id = ?1 AND deleted_at IS NULL AND kind != 'adjustment'
+        ",
+        params![id, at],
+    )?;
+    Ok(changed > 0)
+}
+
+pub fn trash_budget(conn: &Connection, id: i64, at: &str) -> Result<bool> {
+    let changed = conn.execute(
+        "UPDATE budgets SET deleted_at = ?2 WHERE id = ?1 AND deleted_at IS NULL",
+        params![id, at],
+    )?;
+    Ok(changed > 0)
+}
+
+pub fn trash_standing_budget(conn: &Connection, id: i64, at: &str) -> Result<bool> {
+    let changed = conn.execute(
+        "UPDATE standing_budgets SET deleted_at = ?2 WHERE id = ?1 AND deleted_at IS NULL",
+        params![id, at],
+    )?;
+    Ok(changed > 0)
+}
+
+/// Everything in the trash, most recently deleted first.
+pub fn list_trash(conn: &Connection) -> Result<Vec<TrashItem>> {
+    let mut stmt = conn.prepare(
+        "
+        SELECT 'transactions', t.id, t.deleted_at, t.occurred_on, c.name, COALESCE(t.note, t.payee),
+               CASE WHEN t.kind = 'income' THEN t.amount_cents ELSE -t.amount_cents END
+        FROM transactions t
+        LEFT JOIN categories c ON c.id = t.category_id
+        WHERE t.deleted_at IS NOT NULL
+        UNION ALL
+        SELECT 'budgets', b.id, b.deleted_at, b.month, c.name, NULL, b.amount_cents
+        FROM budgets b
+        JOIN categories c ON c.id = b.category_id
+        WHERE b.deleted_at IS NOT NULL
+        UNION ALL
+        SELECT 'standing_budgets', s.id, s.deleted_at, NULL, c.name, NULL, s.amount_cents
+        FROM standing_budgets s
+        JOIN categories c ON c.id = s.category_id
+        WHERE s.deleted_at IS NOT NULL
+        ORDER BY 3 DESC, 2 DESC
+        ",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(TrashItem {
+            table: row.get(0)?,
+            id: row.get(1)?,
+            deleted_at: row.get(2)?,
+            occurred_on: row.get(3)?,
+            category_name: row.get(4)?,
+            note: row.get(5)?,
+            amount_cents: row.get(6)?,
+        })
+    })?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+/// Takes a row out of the trash; `false` when it isn't there. `table` is one
+/// of the names [`TrashItem::table`] takes, never request input.
+pub fn restore_from_trash(conn: &Connection, table: &'static str, id: i64) -> Result<bool> {
+    let changed = conn.execute(
+        &format!("UPDATE {table} SET deleted_at = NULL WHERE id = ?1 AND deleted_at IS NOT NULL"),
+        params![id],
+    )?;
+    Ok(changed > 0)
+}
+
+/// Deletes for good whatever went to the trash before `before` and returns
+/// the receipt files of the transactions among it, for the caller to remove.
+pub fn purge_trash(conn: &Connection, before: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "
+        SELECT receipt_path FROM transactions
+        WHERE deleted_at < ?1 AND receipt_path IS NOT NULL
+        ",
+    )?;
+    let rows = stmt.query_map(params![before], |row| row.get(0))?;
+    let mut receipts: Vec<String> = Vec::new();
+    for row in rows {
+        receipts.push(row?);
+    }
+    receipts.sort();
+    receipts.dedup();
+
+    conn.execute("DELETE FROM transactions WHERE deleted_at < ?1", params![before])?;
+    conn.execute("DELETE FROM budgets WHERE deleted_at < ?1", params![before])?;
+    conn.execute("DELETE FROM standing_budgets WHERE deleted_at < ?1", params![before])?;
+    let mut released = Vec::new();
+    for name in receipts {
+        if release_receipt(conn, &name)? {
+            released.push(name);
+        }
+    }
+    Ok(released)
+}
+
+/// Multiplies the category's budget for the month by `percent`/100 and returns the new total.
+/// A month its standing budget fills gets a budget of its own at that amount first.
+pub fn scale_budget(conn: &Connection, category_id: i64, month: &str, percent: i64) -> Result<Option<i64>> {
+    conn.execute(
+        "
+        INSERT INTO budgets (category_id, month, amount_cents, note)
+        SELECT category_id, ?2, amount_cents, note
+        FROM standing_budgets
+        WHERE category_id = ?1 AND deleted_at IS NULL
+          AND NOT EXISTS (
+              SELECT 1 FROM budgets WHERE category_id = ?1 AND month = ?2 AND deleted_at IS NULL
+          )
+        ON CONFLICT(category_id, month)
+        DO UPDATE SET amount_cents = excluded.amount_cents, rollover = 0, note = excluded.note,
+                      deleted_at = NULL
+        ",
+        params![category_id, month],
+    )?;
+    let changed = conn.execute(
+        "
+        UPDATE budgets
+        SET amount_cents = (amount_cents * ?3 + 50) / 100
+        WHERE category_id = ?1 AND month = ?2 AND deleted_at IS NULL
+        ",
+        params![category_id, month, percent],
+    )?;
+    if changed == 0 {
+        return Ok(None);
+    }
+    conn.query_row(
+        "SELECT SUM(amount_cents) FROM budgets WHERE category_id = ?1 AND month = ?2 AND deleted_at IS NULL",
+        params![category_id, month],
+        |row| row.get(0),
+    )
+}
+
+/// Expenses for reports with their refunds as negative amounts. A refund
+/// keeps its expense's id, category and payee, and is dated as `refunds` says;
+/// its account is the one the money came back to.
+fn report_expenses(refunds: RefundMonth) -> String {
+    let source = refunds.source();
+    format!(
+        "
+    report_expenses AS (
+        SELECT id, category_id, payee, account_id, occurred_on, {} AS month, amount_cents
+        FROM transactions
+        WHERE kind = 'expense' AND deleted_at IS NULL
+        UNION ALL
+        SELECT o.id, o.category_id, o.payee, COALESCE(r.account_id, o.account_id),
+               {source}.occurred_on, {} AS month, -r.amount_cents
+        FROM transactions r
+        JOIN transactions o ON o.id = r.refund_of
+        WHERE o.kind = 'expense' AND r.deleted_at IS NULL AND o.deleted_at IS NULL
+    )",
+        month_of("transactions"),
+        month_of(source)
+    )
+}
+
+/// The month a transaction counts toward in every month total: the
+/// effective month set on it, else the month of its date. `table` is the
+/// name or alias of `transactions` in the query.
+fn month_of(table: &str) -> String {
+    format!("COALESCE({table}.effective_month, substr({table}.occurred_on, 1, 7))")
+}
+
+/// Income as the month totals count it, with the month it counts toward.
+const REPORT_INCOME: &str = "
+            SELECT account_id, occurred_on,
+                   COALESCE(effective_month, substr(occurred_on, 1, 7)) AS month,
+                   amount_cents AS income_cents, 0 AS expense_cents
+            FROM transactions
+            WHERE kind = 'income' AND refund_of IS NULL AND deleted_at IS NULL";
+
+pub fn dashboard_budgets(
+    conn: &Connection,
+    month: &str,
+    refunds: RefundMonth,
+) -> Result<Vec<DashboardBudget>> {
+    let mut stmt = conn.prepare_cached(&format!(
+        "
+        WITH {MONTH_BUDGETS}, {}
+        SELECT c.name, b.amount_cents,
+               COALESCE(SUM(t.amount_cents), 0) AS spent_cents, b.category_id
+        FROM month_budgets b
+        JOIN categories c ON b.category_id = c.id
+        LEFT JOIN report_expenses t
+            ON t.category_id = b.category_id
+           AND t.month = ?1
+        GROUP BY c.name, b.amount_cents, b.category_id
+        ORDER BY c.name
+        ",
+        report_expenses(refunds)
+    ))?;
+    let rows = stmt.query_map(params![month, month], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, i64>(1)?,
+            row.get::<_, i64>(2)?,
+            row.get::<_, i64>(3)?,
+        ))
+    })?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        let (category_name, budget_cents, spent_cents, category_id) = row?;
+        let carried_cents = carried_over(conn, category_id, month, refunds)?;
+        out.push(DashboardBudget {
+            category_name,
+            budget_cents,
+            carried_cents,
+            spent_cents,
+            remaining_cents: budget_cents + carried_cents - spent_cents,
+        });
+    }
+    Ok(out)
+}
+
+/// Income and expenses per month; refunds count against expenses, not as
+/// income.
+pub fn report_months(conn: &Connection, limit: i64, refunds: RefundMonth) -> Result<Vec<ReportMonth>> {
+    let mut stmt = conn.prepare_cached(&format!(
+        "
+        WITH {}
+        SELECT month, SUM(income_cents), SUM(expense_cents)
+        FROM ({REPORT_INCOME}
+            UNION ALL
+            SELECT account_id, occurred_on, month, 0, amount_cents
+            FROM report_expenses
+        )
+        GROUP BY month
+        ORDER BY month DESC
+        LIMIT ?1
+        ",
+        report_expenses(refunds)
+    ))?;
+    let rows = stmt.query_map(params![limit], |row| {
+        let income: i64 = row.get(1)?;
+        let expense: i64 = row.get(2)?;
+        Ok(ReportMonth {
+            month: row.get(0)?,
+            income_cents: income,
+            expense_cents: expense,
+            net_cents: income - expense,
+        })
+    })?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+/// Income and expenses per month from `from` to `to`, both `YYYY-MM` and
+/// included, and per account; months in order, each account once a month,
+/// by name with the transactions without an account last.
+pub fn report_accounts(
+    conn: &Connection,
+    from: &str,
+    to: &str,
+    refunds: RefundMonth,
+) -> Result<Vec<ReportAccountMonth>> {
+    let mut stmt = conn.prepare_cached(&format!(
+        "
+        WITH {}
+        SELECT f.month, a.name, SUM(f.income_cents), SUM(f.expense_cents)
+        FROM ({REPORT_INCOME}
+            UNION ALL
+            SELECT account_id, occurred_on, month, 0, amount_cents
+            FROM report_expenses
+        ) f
+        LEFT JOIN accounts a ON a.id = f.account_id
+        WHERE f.month >= ?1 AND f.month <= ?2
+        GROUP BY f.month, a.id
+        ORDER BY f.month, a.id IS NULL, a.name, a.id
+        ",
+        report_expenses(refunds)
+    ))?;
+    let rows = stmt.query_map(params![from, to], |row| {
+        Ok(ReportAccountMonth {
+            month: row.get(0)?,
+            account_name: row.get(1)?,
+            income_cents: row.get(2)?,
+            expense_cents: row.get(3)?,
+        })
+    })?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+/// Income and expenses of `month`, as a row of [`report_months`] has them.
+pub fn report_month_totals(conn: &Connection, month: &str, refunds: RefundMonth) -> Result<(i64, i64)> {
+    conn.prepare_cached(&format!(
+        "
+        WITH {}
+        SELECT COALESCE(SUM(income_cents), 0), COALESCE(SUM(expense_cents), 0)
+        FROM ({REPORT_INCOME}
+            UNION ALL
+            SELECT account_id, occurred_on, month, 0, amount_cents
+            FROM report_expenses
+        )
+        WHERE month = ?1
+        ",
+        report_expenses(refunds)
+    ))?
+    .query_row(params![month], |row| Ok((row.get(0)?, row.get(1)?)))
+}
+
+/// How many transactions `day` has, and its income and expenses the way the
+/// reports count them.
+pub fn day_totals(conn: &Connection, day: &str, refunds: RefundMonth) -> Result<DayTotals> {
+    let entries = conn.query_row(
+        "SELECT COUNT(*) FROM transactions WHERE occurred_on = ?1 AND deleted_at IS NULL",
+        params![day],
+        |row| row.get(0),
+    )?;
+    conn.prepare_cached(&format!(
+        "
+        WITH {}
+        SELECT COALESCE(SUM(income_cents), 0), COALESCE(SUM(expense_cents), 0)
+        FROM ({REPORT_INCOME}
+            UNION ALL
+            SELECT account_id, occurred_on, month, 0, amount_cents
+            FROM report_expenses
+        )
+        WHERE occurred_on = ?1
+        ",
+        report_expenses(refunds)
+    ))?
+    .query_row(params![day], |row| {
+        Ok(DayTotals {
+            entries,
+            income_cents: row.get(0)?,
+            expense_cents: row.get(1)?,
+        })
+    })
+}
+
+/// Expenses from `from` to `to` in the categories budgeted for `month`.
+pub fn budgeted_expenses(
+    conn: &Connection,
+    month: &str,
+    from: &str,
+    to: &str,
+    refunds: RefundMonth,
+) -> Result<i64> {
+    conn.prepare_cached(&format!(
+        "
+        WITH {MONTH_BUDGETS}, {}
+        SELECT COALESCE(SUM(amount_cents), 0)
+        FROM report_expenses
+        WHERE occurred_on BETWEEN ?1 AND ?3
+          AND category_id IN (SELECT category_id FROM month_budgets)
+        ",
+        report_expenses(refunds)
+    ))?
+    .query_row(params![from, month, to], |row| row.get(0))
+}
+
+/// Replaces the month's snapshot with `figures`.
+pub fn save_report_snapshot(
+    conn: &Connection,
+    month: &str,
+    figures: &[ReportFigure],
+    refunds: RefundMonth,
+    closed_at: &str,
+) -> Result<()> {
+    conn.execute("DELETE FROM report_snapshots WHERE month = ?1", params![month])?;
+    let mut stmt = conn.prepare(
+        "
+        INSERT INTO report_snapshots (month, kind, category_name, amount_cents, refunds, closed_at)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+        ",
+    )?;
+    for figure in figures {
+        stmt.execute(params![
+            month,
+            figure.kind,
+            figure.category_name,
+            figure.amount_cents,
+            refunds.param(),
+            closed_at
+        ])?;
+    }
+    Ok(())
+}
+
+pub fn report_snapshot(conn: &Connection, month: &str) -> Result<Option<ReportSnapshot>> {
+    let mut stmt = conn.prepare_cached(
+        "
+        SELECT kind, category_name, amount_cents, refunds, closed_at
+        FROM report_snapshots
+        WHERE month = ?1
+        ORDER BY rowid
+        ",
+    )?;
+    let mut rows = stmt.query(params![month])?;
+    let mut snapshot: Option<ReportSnapshot> = None;
+    while let Some(row) = rows.next()? {
+        let figure = ReportFigure {
+            kind: row.get(0)?,
+            category_name: row.get(1)?,
+            amount_cents: row.get(2)?,
+        };
+        match &mut snapshot {
+            Some(snapshot) => snapshot.figures.push(figure),
+            None => {
+                let refunds: String = row.get(3)?;
+                snapshot = Some(ReportSnapshot {
+                    month: month.to_string(),
+                    refunds: RefundMonth::from_param(&refunds).unwrap_or_default(),
+                    closed_at: row.get(4)?,
+                    figures: vec![figure],
+                });
+            }
+        }
+    }
+    Ok(snapshot)
+}
+
+/// Reopens the month; `false` when it wasn't closed.
+pub fn delete_report_snapshot(conn: &Connection, month: &str) -> Result<bool> {
+    let changed = conn.execute("DELETE FROM report_snapshots WHERE month = ?1", params![month])?;
+    Ok(changed > 0)
+}
+
+/// Expenses from `from` to `to`, both `YYYY-MM-DD` and included, per
+/// top-level category with child spending rolled up, largest first; an empty
+/// name for expenses without a category.
+pub fn expenses_by_category(
+    conn: &Connection,
+    from: &str,
+    to: &str,
+    refunds: RefundMonth,
+) -> Result<Vec<(String, i64)>> {
+    let mut stmt = conn.prepare(&format!(
+        "
+        WITH {}
+        SELECT COALESCE(p.name, c.name, '') AS top_name, SUM(t.amount_cents) AS expense_cents
+        FROM report_expenses t
+        LEFT JOIN categories c ON t.category_id = c.id
+        LEFT JOIN categories p ON c.parent_id = p.id
+        WHERE t.occurred_on BETWEEN ?1 AND ?2
+        GROUP BY top_name
+        HAVING expense_cents > 0
+        ORDER BY expense_cents DESC, top_name
+        ",
+        report_expenses(refunds)
+    ))?;
+    let rows = stmt.query_map(params![from, to], |row| Ok((row.get(0)?, row.get(1)?)))?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+/// Expenses per top-level category in each of `months` that has any, the way
+/// [`report_categories`] totals them, by month and category name.
+pub fn report_category_months(
+    conn: &Connection,
+    months: &[String],
+    refunds: RefundMonth,
+) -> Result<Vec<ReportCategoryMonth>> {
+    let placeholders = (1..=months.len())
+        .map(|index| format!("?{index}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut stmt = conn.prepare(&format!(
+        "
+        WITH {}
+        SELECT t.month, COALESCE(p.name, c.name, '') AS top_name, SUM(t.amount_cents)
+        FROM report_expenses t
+        LEFT JOIN categories c ON t.category_id = c.id
+        LEFT JOIN categories p ON c.parent_id = p.id
+        WHERE t.month IN ({placeholders})
+        GROUP BY t.month, top_name
+        ORDER BY t.month, top_name
+        ",
+        report_expenses(refunds)
+    ))?;
+    let rows = stmt.query_map(params_from_iter(months), |row| {
+        Ok(ReportCategoryMonth {
+            month: row.get(0)?,
+            category_name: row.get(1)?,
+            expense_cents: row.get(2)?,
+        })
+    })?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+/// Expenses per top-level category with child spending rolled up, each total
+/// followed by the children that contributed to it.
+pub fn report_categories(
+    conn: &Connection,
+    month: &str,
+    refunds: RefundMonth,
+) -> Result<Vec<ReportCategory>> {
+    let mut stmt = conn.prepare(&format!(
+        "
+        WITH {}
+        SELECT c.name, p.name, COALESCE(SUM(t.amount_cents), 0) AS expense_cents
+        FROM report_expenses t
+        JOIN categories c ON t.category_id = c.id
+        LEFT JOIN categories p ON c.parent_id = p.id
+        WHERE t.month = ?1
+        GROUP BY c.id
+        ORDER BY expense_cents DESC
+        ",
+        report_expenses(refunds)
+    ))?;
+    let rows = stmt.query_map(params![month], |row| {
+        Ok(ReportCategory {
+            category_name: row.get(0)?,
+            parent_name: row.get(1)?,
+            expense_cents: row.get(2)?,
+        })
+    })?;
+
+    let mut totals: Vec<ReportCategory> = Vec::new();
+    let mut children = Vec::new();
+    for row in rows {
+        let row = row?;
+        let top = row.parent_name.clone().unwrap_or_else(|| row.category_name.clone());
+        match totals.iter_mut().find(|total| total.category_name == top) {
+            Some(total) => total.expense_cents += row.expense_cents,
+            None => totals.push(ReportCategory {
+                category_name: top,
+                parent_name: None,
+                expense_cents: row.expense_cents,
+            }),
+        }
+        if row.parent_name.is_some() {
+            children.push(row);
+        }
+    }
+    totals.sort_by_key(|total| std::cmp::Reverse(total.expense_cents));
+
+    let mut out = Vec::new();
+    for total in totals {
+        let name = total.category_name.clone();
+        out.push(total);
+        out.extend(
+            children
+                .iter()
+                .filter(|child| child.parent_name.as_deref() == Some(name.as_str()))
+                .map(|child| ReportCategory {
+                    category_name: child.category_name.clone(),
+                    parent_name: child.parent_name.clone(),
+                    expense_cents: child.expense_cents,
+                }),
+        );
+    }
+    Ok(out)
+}
+
+/// Expenses per tag; a transaction with several tags counts toward each of them.
+pub fn report_tags(conn: &Connection, month: &str, refunds: RefundMonth) -> Result<Vec<ReportTag>> {
+    let mut stmt = conn.prepare(&format!(
+        "
+        WITH {}
+        SELECT g.name, COALESCE(SUM(t.amount_cents), 0) AS expense_cents
+        FROM transaction_tags tt
+        JOIN tags g ON g.id = tt.tag_id
+        JOIN report_expenses t ON t.id = tt.transaction_id
+        WHERE t.month = ?1
+        GROUP BY g.name
+        ORDER BY expense_cents DESC
+        ",
+        report_expenses(refunds)
+    ))?;
+    let rows = stmt.query_map(params![month], |row| {
+        Ok(ReportTag {
+            tag_name: row.get(0)?,
+            expense_cents: row.get(1)?,
+        })
+    })?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+/// The payees that got the most of `month`'s expenses, biggest first; a
+/// refund isn't a transaction of its own.
+pub fn report_payees(
+    conn: &Connection,
+    month: &str,
+    limit: i64,
+    refunds: RefundMonth,
+) -> Result<Vec<ReportPayee>> {
+    let mut stmt = conn.prepare(&format!(
+        "
+        WITH {}
+        SELECT payee, COUNT(DISTINCT id), SUM(amount_cents) AS expense_cents
+        FROM report_expenses
+        WHERE payee IS NOT NULL AND month = ?1
+        GROUP BY payee
+        ORDER BY expense_cents DESC, payee
+        LIMIT ?2
+        ",
+        report_expenses(refunds)
+    ))?;
+    let rows = stmt.query_map(params![month, limit], |row| {
+        Ok(ReportPayee {
+            payee: row.get(0)?,
+            transactions: row.get(1)?,
+            expense_cents: row.get(2)?,
+        })
+    })?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+/// Expenses per day of `month`, by date even when they count toward another
+/// month; days without any are left out.
+pub fn report_days(conn: &Connection, month: &str, refunds: RefundMonth) -> Result<Vec<ReportDay>> {
+    let like_month = format!("{}-%", month);
+    let mut stmt = conn.prepare(&format!(
+        "
+        WITH {}
+        SELECT occurred_on, SUM(amount_cents)
+        FROM report_expenses
+        WHERE occurred_on LIKE ?1
+        GROUP BY occurred_on
+        ORDER BY occurred_on
+        ",
+        report_expenses(refunds)
+    ))?;
+    let rows = stmt.query_map(params![like_month], |row| {
+        Ok(ReportDay {
+            day: row.get(0)?,
+            expense_cents: row.get(1)?,
+        })
+    })?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+pub fn list_months(conn: &Connection, limit: i64) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare_cached(&format!(
+        "
+        SELECT {} AS month
+        FROM transactions
+        WHERE deleted_at IS NULL
+        GROUP BY month
+        ORDER BY month DESC
+        LIMIT ?1
+        ",
+        month_of("transactions")
+    ))?;
+    let rows = stmt.query_map(params![limit], |row| row.get(0))?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+pub fn list_budget_months(conn: &Connection, limit: i64) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "
+        SELECT month
+        FROM budgets
+        WHERE deleted_at IS NULL
+        GROUP BY month
+        ORDER BY month DESC
+        LIMIT ?1
+        ",
+    )?;
+    let rows = stmt.query_map(params![limit], |row| row.get(0))?;
+
+    let mut out = Vec::new();
+    for row in rows {
+        out.push(row?);
+    }
+    Ok(out)
+}
+
+/// The first and last `YYYY-MM-DD` with a transaction or a budget, which
+/// counts from the first of its month; `None` on an empty database. Dates
+/// that aren't well formed are left out, see `malformed_dates`.
+pub fn date_bounds(conn: &Connection) -> Result<Option<(String, String)>> {
     conn.query_row(
-        "SELECT EXISTS(SELECT 1 FROM users)",
+        "
+        SELECT MIN(day), MAX(day)
+        FROM (
+          SELECT occurred_on AS day FROM transactions
+          WHERE date(occurred_on) IS occurred_on AND deleted_at IS NULL
+          UNION ALL
+          SELECT month || '-01' FROM budgets
+          WHERE date(month || '-01') IS month || '-01' AND deleted_at IS NULL
+        )
+        ",
         [],
-        |row| row.get::<_, i64>(0),
+        |row| {
+            let first: Option<String> = row.get(0)?;
+            let last: Option<String> = row.get(1)?;
+            Ok(first.zip(last))
+        },
     )
-    .map(|value| value == 1)
 }
 
-pub fn insert_user(conn: &Connection, username: &str, password_hash: &str, created_at: &str) -> Result<i64> {
+fn job_from_row(row: &rusqlite::Row<'_>) -> Result<Job> {
+    Ok(Job {
+        id: row.get(0)?,
+        user_id: row.get(1)?,
+        label: row.get(2)?,
+        state: row.get(3)?,
+        done: row.get(4)?,
+        total: row.get(5)?,
+        result_url: row.get(6)?,
+        error: row.get(7)?,
+        file_path: row.get(8)?,
+        file_name: row.get(9)?,
+        created_at: row.get(10)?,
+        finished_at: row.get(11)?,
+        expires_at: row.get(12)?,
+    })
+}
+
+pub fn insert_job(conn: &Connection, user_id: i64, label: &str, created_at: &str) -> Result<i64> {
     conn.execute(
-        "INSERT INTO users (username, password_hash, created_at) VALUES (?1, ?2, ?3)",
-        params![username, password_hash, created_at],
+        "INSERT INTO jobs (user_id, label, state, created_at) VALUES (?1, ?2, 'running', ?3)",
+        params![user_id, label, created_at],
     )?;
     Ok(conn.last_insert_rowid())
 }
 
-pub fn user_credentials(conn: &Connection, username: &str) -> Result<Option<(i64, String)>> {
-    let mut stmt = conn.prepare(
+pub fn job_by_id(conn: &Connection, id: i64) -> Result<Option<Job>> {
+    let mut stmt = conn.prepare_cached(
         "
-        SELECT id, password_hash
-        FROM users
-        WHERE username = ?1
+        SELECT id, user_id, label, state, done, total, result_url, error, file_path, file_name,
+               created_at, finished_at, expires_at
+        FROM jobs
+        WHERE id = ?1
         ",
     )?;
-    let mut rows = stmt.query(params![username])?;
-    if let Some(row) = rows.next()? {
-        Ok(Some((row.get(0)?, row.get(1)?)))
-    } else {
-        Ok(None)
+    let mut rows = stmt.query(params![id])?;
+    match rows.next()? {
+        Some(row) => Ok(Some(job_from_row(row)?)),
+        None => Ok(None),
     }
 }
 
-pub fn create_session(conn: &Connection, user_id: i64, token: &str, created_at: &str) -> Result<()> {
+/// Stores how the job ended; everything but its id, owner, label and start.
+pub fn save_job_result(conn: &Connection, job: &Job) -> Result<()> {
     conn.execute(
-        "INSERT INTO sessions (user_id, token, created_at) VALUES (?1, ?2, ?3)",
-        params![user_id, token, created_at],
-    )?;
-    Ok(())
-}
-
-pub fn user_by_session(conn: &Connection, token: &str) -> Result<Option<User>> {
-    let mut stmt = conn.prepare(
         "
-        SELECT u.id, u.username
-        FROM sessions s
-        JOIN users u ON s.user_id = u.id
-        WHERE s.token = ?1
+        UPDATE jobs
+        SET state = ?2, done = ?3, total = ?4, result_url = ?5, error = ?6, file_path = ?7,
+            file_name = ?8, finished_at = ?9, expires_at = ?10
+        WHERE id = ?1
         ",
+        params![
+            job.id,
+            job.state,
+            job.done,
+            job.total,
+            job.result_url,
+            job.error,
+            job.file_path,
+            job.file_name,
+            job.finished_at,
+            job.expires_at
+        ],
     )?;
-    let mut rows = stmt.query(params![token])?;
-    if let Some(row) = rows.next()? {
-        Ok(Some(User {
-            id: row.get(0)?,
-            username: row.get(1)?,
-        }))
-    } else {
-        Ok(None)
-    }
-}
-
-pub fn delete_session(conn: &Connection, token: &str) -> Result<()> {
-    conn.execute("DELETE FROM sessions WHERE token = ?1", params![token])?;
     Ok(())
 }
 
-pub fn session_count(conn: &Connection, user_id: i64) -> Result<i64> {
-    conn.query_row(
-        "SELECT COUNT(*) FROM sessions WHERE user_id = ?1",
-        params![user_id],
-        |row| row.get(0),
+/// Fails every job still marked running; at startup those lost their thread.
+pub fn fail_running_jobs(conn: &Connection, error: &str, finished_at: &str) -> Result<usize> {
+    conn.execute(
+        "UPDATE jobs SET state = 'failed', error = ?1, finished_at = ?2 WHERE state = 'running'",
+        params![error, finished_at],
     )
 }
 
-pub fn delete_sessions_for_user(conn: &Connection, user_id: i64) -> Result<()> {
-    conn.execute("DELETE FROM sessions WHERE user_id = ?1", params![user_id])?;
-    Ok(())
-}
-
-pub fn prune_sessions(conn: &Connection, user_id: i64, keep: i64) -> Result<()> {
-    conn.execute(
+/// The user's generated files that haven't expired yet, newest first.
+pub fn list_downloads(conn: &Connection, user_id: i64, now: &str) -> Result<Vec<Job>> {
+    let mut stmt = conn.prepare(
         "
-        DELETE FROM sessions
+        SELECT id, user_id, label, state, done, total, result_url, error, file_path, file_name,
+               created_at, finished_at, expires_at
+        FROM jobs
         WHERE user_id = ?1
-          AND id NOT IN (
-            SELECT id
-            FROM sessions
-            WHERE user_id = ?1
-            ORDER BY created_at DESC, id DESC
-            LIMIT ?2
-          )
+          AND file_path IS NOT NULL
+          AND (expires_at IS NULL OR expires_at > ?2)
+        ORDER BY id DESC
         ",
-        params![user_id, keep],
     )?;
-    Ok(())
-}
-
-pub fn list_transactions(conn: &Connection, month: Option<&str>) -> Result<Vec<TransactionRecord>> {
-    let (query, params) = if let Some(month) = month {
-        (
-            "
-            SELECT t.id, t.kind, t.amount_cents, t.occurred_on, t.note, c.name, t.receipt_path
-            FROM transactions t
-            LEFT JOIN categories c ON t.category_id = c.id
-            WHERE t.occurred_on LIKE ?1
-            ORDER BY t.occurred_on DESC, t.id DESC
-            LIMIT 200
-            ",
-            params![format!("{}-%", month)],
-        )
-    } else {
-        (
-            "
-            SELECT t.id, t.kind, t.amount_cents, t.occurred_on, t.note, c.name, t.receipt_path
-            FROM transactions t
-            LEFT JOIN categories c ON t.category_id = c.id
-            ORDER BY t.occurred_on DESC, t.id DESC
-            LIMIT 200
-            ",
-            params![],
-        )
-    };
-
-    let mut stmt = conn.prepare(query)?;
-    let rows = stmt.query_map(params, |row| {
-        Ok(TransactionRecord {
-            id: row.get(0)?,
-            kind: row.get(1)?,
-            amount_cents: row.get(2)?,
-            occurred_on: row.get(3)?,
-            note: row.get(4)?,
-            category_name: row.get(5)?,
-            receipt_path: row.get(6)?,
-        })
-    })?;
+    let rows = stmt.query_map(params![user_id, now], job_from_row)?;
 
     let mut out = Vec::new();
     for row in rows {
@@ -259,128 +5110,93 @@ pub fn list_transactions(conn: &Connection, month: Option<&str>) -> Result<Vec<T
     Ok(out)
 }
 
-pub fn insert_transaction(
-    conn: &Connection,
-    kind: &str,
-    amount_cents: i64,
-    category_id: Option<i64>,
-    occurred_on: &str,
-    note: Option<&str>,
-    receipt_path: Option<&str>,
-) -> Result<()> {
+/// Forgets files that expired by `now` and returns their paths; the jobs
+/// themselves stay, without a download.
+pub fn expire_downloads(conn: &Connection, now: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT file_path FROM jobs WHERE file_path IS NOT NULL AND expires_at <= ?1",
+    )?;
+    let rows = stmt.query_map(params![now], |row| row.get(0))?;
+
+    let mut files = Vec::new();
+    for row in rows {
+        files.push(row?);
+    }
     conn.execute(
         "
-        INSERT INTO transactions (kind, amount_cents, category_id, occurred_on, note, receipt_path)
-        VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+        UPDATE jobs
+        SET file_path = NULL, file_name = NULL, result_url = NULL
+        WHERE file_path IS NOT NULL AND expires_at <= ?1
         ",
-        params![
-            kind,
-            amount_cents,
-            category_id,
-            occurred_on,
-            note,
-            receipt_path
-        ],
+        params![now],
     )?;
-    Ok(())
+    Ok(files)
 }
 
-pub fn list_budgets(conn: &Connection, month: &str) -> Result<Vec<BudgetRecord>> {
-    let like_month = format!("{}-%", month);
-    let mut stmt = conn.prepare(
-        "
-        SELECT b.id, b.category_id, c.name, b.month, b.amount_cents,
-               COALESCE(SUM(t.amount_cents), 0) AS spent_cents
-        FROM budgets b
-        JOIN categories c ON b.category_id = c.id
-        LEFT JOIN transactions t
-            ON t.category_id = b.category_id
-           AND t.kind = 'expense'
-           AND t.occurred_on LIKE ?1
-        WHERE b.month = ?2
-        GROUP BY b.id, b.category_id, c.name, b.month, b.amount_cents
-        ORDER BY c.name
-        ",
-    )?;
-    let rows = stmt.query_map(params![like_month, month], |row| {
-        Ok(BudgetRecord {
-            id: row.get(0)?,
-            category_id: row.get(1)?,
-            category_name: row.get(2)?,
-            month: row.get(3)?,
-            amount_cents: row.get(4)?,
-            spent_cents: row.get(5)?,
-        })
-    })?;
+/// Deletes finished jobs beyond the newest `keep` and returns their files.
+pub fn prune_jobs(conn: &Connection, keep: i64) -> Result<Vec<String>> {
+    let stale = "
+        SELECT id FROM jobs
+        WHERE state != 'running'
+          AND id NOT IN (SELECT id FROM jobs ORDER BY id DESC LIMIT ?1)
+    ";
+    let mut stmt = conn.prepare(&format!(
+        "SELECT file_path FROM jobs WHERE file_path IS NOT NULL AND id IN ({stale})"
+    ))?;
+    let rows = stmt.query_map(params![keep], |row| row.get(0))?;
 
-    let mut out = Vec::new();
+    let mut files = Vec::new();
     for row in rows {
-        out.push(row?);
+        files.push(row?);
     }
-    Ok(out)
+    conn.execute(&format!("DELETE FROM jobs WHERE id IN ({stale})"), params![keep])?;
+    Ok(files)
 }
 
-pub fn insert_budget(
-    conn: &Connection,
-    category_id: i64,
-    month: &str,
-    amount_cents: i64,
-) -> Result<()> {
+pub fn insert_bulk_operation(conn: &Connection, kind: &str, label: &str, created_at: &str) -> Result<i64> {
     conn.execute(
-        "INSERT INTO budgets (category_id, month, amount_cents) VALUES (?1, ?2, ?3)",
-        params![category_id, month, amount_cents],
+        "INSERT INTO bulk_operations (kind, label, created_at) VALUES (?1, ?2, ?3)",
+        params![kind, label, created_at],
     )?;
-    Ok(())
+    Ok(conn.last_insert_rowid())
 }
 
-pub fn month_totals(conn: &Connection, month: &str) -> Result<(i64, i64)> {
-    let like_month = format!("{}-%", month);
-    let income: i64 = conn.query_row(
-        "
-        SELECT COALESCE(SUM(amount_cents), 0)
-        FROM transactions
-        WHERE kind = 'income' AND occurred_on LIKE ?1
-        ",
-        params![like_month],
-        |row| row.get(0),
-    )?;
-    let expense: i64 = conn.query_row(
+pub fn insert_bulk_change(conn: &Connection, operation_id: i64, change: &BulkChange) -> Result<()> {
+    conn.execute(
         "
-        SELECT COALESCE(SUM(amount_cents), 0)
-        FROM transactions
-        WHERE kind = 'expense' AND occurred_on LIKE ?1
+        INSERT INTO bulk_changes (operation_id, table_name, row_id, action, snapshot)
+        VALUES (?1, ?2, ?3, ?4, ?5)
         ",
-        params![like_month],
-        |row| row.get(0),
+        params![
+            operation_id,
+            change.table_name,
+            change.row_id,
+            change.action,
+            change.snapshot
+        ],
     )?;
-    Ok((income, expense))
+    Ok(())
 }
 
-pub fn dashboard_budgets(conn: &Connection, month: &str) -> Result<Vec<DashboardBudget>> {
-    let like_month = format!("{}-%", month);
+pub fn list_bulk_operations(conn: &Connection, limit: i64) -> Result<Vec<BulkOperationRecord>> {
     let mut stmt = conn.prepare(
         "
-        SELECT c.name, b.amount_cents,
-               COALESCE(SUM(t.amount_cents), 0) AS spent_cents
-        FROM budgets b
-        JOIN categories c ON b.category_id = c.id
-        LEFT JOIN transactions t
-            ON t.category_id = b.category_id
-           AND t.kind = 'expense'
-           AND t.occurred_on LIKE ?1
-        WHERE b.month = ?2
-        GROUP BY c.name, b.amount_cents
-        ORDER BY c.name
+        SELECT o.id, o.kind, o.label, o.created_at, o.undone_at, COUNT(c.id)
+        FROM bulk_operations o
+        LEFT JOIN bulk_changes c ON c.operation_id = o.id
+        GROUP BY o.id
+        ORDER BY o.created_at DESC, o.id DESC
+        LIMIT ?1
         ",
     )?;
-    let rows = stmt.query_map(params![like_month, month], |row| {
-        let budget_cents: i64 = row.get(1)?;
-        let spent_cents: i64 = row.get(2)?;
-        Ok(DashboardBudget {
-            category_name: row.get(0)?,
-            budget_cents,
-            spent_cents,
-            remaining_cents: budget_cents - spent_cents,
+    let rows = stmt.query_map(params![limit], |row| {
+        Ok(BulkOperationRecord {
+            id: row.get(0)?,
+            kind: row.get(1)?,
+            label: row.get(2)?,
+            created_at: row.get(3)?,
+            undone_at: row.get(4)?,
+            change_count: row.get(5)?,
         })
     })?;
 
@@ -391,26 +5207,31 @@ pub fn dashboard_budgets(conn: &Connection, month: &str) -> Result<Vec<Dashboard
     Ok(out)
 }
 
-pub fn report_months(conn: &Connection, limit: i64) -> Result<Vec<ReportMonth>> {
+/// The activity log change by change, oldest first, for operations made on
+/// `from` through `to` (`YYYY-MM-DD`, both optional).
+pub fn audit_log(conn: &Connection, from: Option<&str>, to: Option<&str>) -> Result<Vec<AuditEntry>> {
     let mut stmt = conn.prepare(
         "
-        SELECT substr(occurred_on, 1, 7) AS month,
-               COALESCE(SUM(CASE WHEN kind = 'income' THEN amount_cents END), 0) AS income_cents,
-               COALESCE(SUM(CASE WHEN kind = 'expense' THEN amount_cents END), 0) AS expense_cents
-        FROM transactions
-        GROUP BY month
-        ORDER BY month DESC
-        LIMIT ?1
+        SELECT o.id, o.created_at, o.kind, o.label, o.undone_at,
+               c.table_name, c.row_id, c.action, c.snapshot
+        FROM bulk_operations o
+        LEFT JOIN bulk_changes c ON c.operation_id = o.id
+        WHERE (?1 IS NULL OR substr(o.created_at, 1, 10) >= ?1)
+          AND (?2 IS NULL OR substr(o.created_at, 1, 10) <= ?2)
+        ORDER BY o.created_at, o.id, c.id
         ",
     )?;
-    let rows = stmt.query_map(params![limit], |row| {
-        let income: i64 = row.get(1)?;
-        let expense: i64 = row.get(2)?;
-        Ok(ReportMonth {
-            month: row.get(0)?,
-            income_cents: income,
-            expense_cents: expense,
-            net_cents: income - expense,
+    let rows = stmt.query_map(params![from, to], |row| {
+        Ok(AuditEntry {
+            operation_id: row.get(0)?,
+            created_at: row.get(1)?,
+            kind: row.get(2)?,
+            label: row.get(3)?,
+            undone_at: row.get(4)?,
+            table_name: row.get(5)?,
+            row_id: row.get(6)?,
+            action: row.get(7)?,
+            snapshot: row.get(8)?,
         })
     })?;
 
@@ -421,22 +5242,31 @@ pub fn report_months(conn: &Connection, limit: i64) -> Result<Vec<ReportMonth>>
     Ok(out)
 }
 
-pub fn report_categories(conn: &Connection, month: &str) -> Result<Vec<ReportCategory>> {
-    let like_month = format!("{}-%", month);
+pub fn bulk_operation_undone(conn: &Connection, operation_id: i64) -> Result<Option<bool>> {
+    let mut stmt = conn.prepare("SELECT undone_at IS NOT NULL FROM bulk_operations WHERE id = ?1")?;
+    let mut rows = stmt.query(params![operation_id])?;
+    if let Some(row) = rows.next()? {
+        Ok(Some(row.get(0)?))
+    } else {
+        Ok(None)
+    }
+}
+
+pub fn bulk_changes(conn: &Connection, operation_id: i64) -> Result<Vec<BulkChange>> {
     let mut stmt = conn.prepare(
         "
-        SELECT c.name, COALESCE(SUM(t.amount_cents), 0) AS expense_cents
-        FROM transactions t
-        JOIN categories c ON t.category_id = c.id
-        WHERE t.kind = 'expense' AND t.occurred_on LIKE ?1
-        GROUP BY c.name
-        ORDER BY expense_cents DESC
+        SELECT table_name, row_id, action, snapshot
+        FROM bulk_changes
+        WHERE operation_id = ?1
+        ORDER BY id
         ",
     )?;
-    let rows = stmt.query_map(params![like_month], |row| {
-        Ok(ReportCategory {
-            category_name: row.get(0)?,
-            expense_cents: row.get(1)?,
+    let rows = stmt.query_map(params![operation_id], |row| {
+        Ok(BulkChange {
+            table_name: row.get(0)?,
+            row_id: row.get(1)?,
+            action: row.get(2)?,
+            snapshot: row.get(3)?,
         })
     })?;
 
@@ -447,56 +5277,96 @@ pub fn report_categories(conn: &Connection, month: &str) -> Result<Vec<ReportCat
     Ok(out)
 }
 
-pub fn list_months(conn: &Connection, limit: i64) -> Result<Vec<String>> {
-    let mut stmt = conn.prepare(
-        "
-        SELECT substr(occurred_on, 1, 7) AS month
-        FROM transactions
-        GROUP BY month
-        ORDER BY month DESC
-        LIMIT ?1
-        ",
+pub fn mark_bulk_operation_undone(conn: &Connection, operation_id: i64, undone_at: &str) -> Result<()> {
+    conn.execute(
+        "UPDATE bulk_operations SET undone_at = ?2 WHERE id = ?1",
+        params![operation_id, undone_at],
     )?;
-    let rows = stmt.query_map(params![limit], |row| row.get(0))?;
+    Ok(())
+}
 
-    let mut out = Vec::new();
-    for row in rows {
-        out.push(row?);
+/// The row as a JSON object of its columns, kept to undo an update or delete.
+pub fn row_snapshot(conn: &Connection, table: &str, id: i64) -> Result<Option<String>> {
+    let mut stmt = conn.prepare(&format!("SELECT * FROM {table} WHERE id = ?1"))?;
+    let names = stmt
+        .column_names()
+        .into_iter()
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    let mut rows = stmt.query(params![id])?;
+    let Some(row) = rows.next()? else {
+        return Ok(None);
+    };
+    let mut object = serde_json::Map::new();
+    for (index, name) in names.into_iter().enumerate() {
+        let value = match row.get_ref(index)? {
+            ValueRef::Null | ValueRef::Blob(_) => serde_json::Value::Null,
+            ValueRef::Integer(value) => value.into(),
+            ValueRef::Real(value) => value.into(),
+            ValueRef::Text(value) => String::from_utf8_lossy(value).into_owned().into(),
+        };
+        object.insert(name, value);
     }
-    Ok(out)
+    Ok(Some(serde_json::Value::Object(object).to_string()))
 }
 
-pub fn list_budget_months(conn: &Connection, limit: i64) -> Result<Vec<String>> {
-    let mut stmt = conn.prepare(
-        "
-        SELECT month
-        FROM budgets
-        GROUP BY month
-        ORDER BY month DESC
-        LIMIT ?1
-        ",
-    )?;
-    let rows = stmt.query_map(params![limit], |row| row.get(0))?;
+/// Columns and values of a [`row_snapshot`].
+fn snapshot_columns(snapshot: &str) -> Result<Vec<(String, Value)>> {
+    let object: serde_json::Map<String, serde_json::Value> = serde_json::from_str(snapshot)
+        .map_err(|err| rusqlite::Error::ToSqlConversionFailure(Box::new(err)))?;
+    Ok(object
+        .into_iter()
+        .map(|(name, value)| {
+            let value = match value {
+                serde_json::Value::Number(number) => match number.as_i64() {
+                    Some(integer) => Value::Integer(integer),
+                    None => Value::Real(number.as_f64().unwrap_or_default()),
+                },
+                serde_json::Value::String(text) => Value::Text(text),
+                _ => Value::Null,
+            };
+            (name, value)
+        })
+        .collect())
+}
 
-    let mut out = Vec::new();
-    for row in rows {
-        out.push(row?);
-    }
-    Ok(out)
+/// Puts an updated row back the way its snapshot recorded it.
+pub fn restore_row(conn: &Connection, table: &str, id: i64, snapshot: &str) -> Result<()> {
+    let columns = snapshot_columns(snapshot)?;
+    let assignments = columns
+        .iter()
+        .enumerate()
+        .map(|(index, (name, _))| format!("\"{name}\" = ?{}", index + 2))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let values = std::iter::once(Value::Integer(id)).chain(columns.into_iter().map(|(_, value)| value));
+    conn.execute(
+        &format!("UPDATE {table} SET {assignments} WHERE id = ?1"),
+        params_from_iter(values),
+    )?;
+    Ok(())
 }
 
-pub fn category_name_by_id(conn: &Connection, category_id: i64) -> Result<Option<String>> {
-    let mut stmt = conn.prepare(
-        "
-        SELECT name
-        FROM categories
-        WHERE id = ?1
-        ",
+/// Inserts a deleted row again from its snapshot, with its old id.
+pub fn reinsert_row(conn: &Connection, table: &str, snapshot: &str) -> Result<()> {
+    let columns = snapshot_columns(snapshot)?;
+    let names = columns
+        .iter()
+        .map(|(name, _)| format!("\"{name}\""))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let placeholders = (1..=columns.len())
+        .map(|index| format!("?{index}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    conn.execute(
+        &format!("INSERT INTO {table} ({names}) VALUES ({placeholders})"),
+        params_from_iter(columns.into_iter().map(|(_, value)| value)),
     )?;
-    let mut rows = stmt.query(params![category_id])?;
-    if let Some(row) = rows.next()? {
-        Ok(Some(row.get(0)?))
-    } else {
-        Ok(None)
-    }
+    Ok(())
+}
+
+pub fn delete_row(conn: &Connection, table: &str, id: i64) -> Result<()> {
+    conn.execute(&format!("DELETE FROM {table} WHERE id = ?1"), params![id])?;
+    Ok(())
 }
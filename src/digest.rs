@@ -0,0 +1,134 @@
+//! The weekly Telegram digest.
+//!
+//! On Sunday evening every user who turned it on in the settings and linked a
+//! chat gets one message: the week's expenses by category, where this month's
+//! budgets stand and the loan payments due in the coming week. A digest that
+//! fails to send is retried on the next check the same evening.
+
+use std::time::Duration;
+
+use chrono::{Datelike, Local, NaiveDate, Timelike, Weekday};
+use rusqlite::{Connection, Result};
+
+use crate::db::{self, DbPool};
+use crate::format_money;
+use crate::loans;
+use crate::telegram::TelegramConfig;
+
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(15 * 60);
+const SEND_DAY: Weekday = Weekday::Sun;
+/// Local hour from which the digest goes out.
+const SEND_HOUR: u32 = 18;
+/// Categories listed by name; the rest are summed up as one line.
+const TOP_CATEGORIES: usize = 8;
+
+/// Monday of the week `today` falls in.
+pub fn week_start(today: NaiveDate) -> NaiveDate {
+    today - chrono::Days::new(u64::from(today.weekday().num_days_from_monday()))
+}
+
+/// A line per category of `expenses`, largest first: the first `top` by
+/// name, the rest summed up as one line.
+pub fn category_lines(expenses: &[(String, i64)], top: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for (name, cents) in expenses.iter().take(top) {
+        let name = if name.is_empty() {
+            "Без категории"
+        } else {
+            name
+        };
+        lines.push(format!("{name} — {}", format_money(*cents)));
+    }
+    let rest: i64 = expenses.iter().skip(top).map(|(_, cents)| cents).sum();
+    if rest > 0 {
+        lines.push(format!("Остальное — {}", format_money(rest)));
+    }
+    lines
+}
+
+/// The digest text for the week that ends `today`.
+pub fn build(conn: &Connection, user_id: i64, today: NaiveDate) -> Result<String> {
+    let from = week_start(today);
+    let refunds = db::refund_month(conn, user_id)?;
+    let expenses = db::expenses_by_category(
+        conn,
+        &from.format("%Y-%m-%d").to_string(),
+        &today.format("%Y-%m-%d").to_string(),
+        refunds,
+    )?;
+    let mut lines = vec![format!("Итоги недели {from} — {today}")];
+
+    let total: i64 = expenses.iter().map(|(_, cents)| cents).sum();
+    lines.push(String::new());
+    if expenses.is_empty() {
+        lines.push("Расходов за неделю нет".to_string());
+    } else {
+        lines.push(format!("Расходы: {}", format_money(total)));
+        lines.extend(category_lines(&expenses, TOP_CATEGORIES));
+    }
+
+    let month = today.format("%Y-%m").to_string();
+    let budgets = db::dashboard_budgets(conn, &month, refunds)?;
+    if !budgets.is_empty() {
+        lines.push(String::new());
+        lines.push(format!("Бюджеты за {month}:"));
+        for budget in budgets {
+            let limit = budget.budget_cents + budget.carried_cents;
+            let over = if budget.remaining_cents < 0 {
+                " — превышен"
+            } else {
+                ""
+            };
+            lines.push(format!(
+                "{}: {} из {}{over}",
+                budget.category_name,
+                format_money(budget.spent_cents),
+                format_money(limit)
+            ));
+        }
+    }
+
+    let until = (today + chrono::Days::new(7))
+        .format("%Y-%m-%d")
+        .to_string();
+    let mut bills = Vec::new();
+    for loan in db::list_loans(conn)? {
+        let payments = db::loan_payments(conn, loan.id)?;
+        if let Some(installment) = loans::next_installment(&loan, &payments)
+            .filter(|installment| installment.due_on <= until)
+        {
+            bills.push((installment.due_on, loan.name, installment.payment_cents));
+        }
+    }
+    if !bills.is_empty() {
+        bills.sort();
+        lines.push(String::new());
+        lines.push("Платежи на неделе:".to_string());
+        for (due_on, name, cents) in bills {
+            lines.push(format!("{due_on} {name} — {}", format_money(cents)));
+        }
+    }
+    Ok(lines.join("\n"))
+}
+
+/// The scheduled check: sends the digest on Sunday evening to whoever hasn't
+/// had it today.
+pub fn run_due(pool: &DbPool, config: &TelegramConfig) {
+    let now = Local::now();
+    if now.weekday() != SEND_DAY || now.hour() < SEND_HOUR {
+        return;
+    }
+    let today = now.date_naive();
+    let today_ymd = today.format("%Y-%m-%d").to_string();
+    let Ok(conn) = pool.get() else {
+        return;
+    };
+    for (user_id, chat_id) in db::digest_recipients(&conn, &today_ymd).unwrap_or_default() {
+        let Ok(text) = build(&conn, user_id, today) else {
+            continue;
+        };
+        if config.send_message(chat_id, &text, None).is_ok() {
+            let _ = db::mark_digest_sent(&conn, user_id, &today_ymd);
+        }
+    }
+}
@@ -0,0 +1,51 @@
+//! Disputed charges and the refunds that settle them.
+//!
+//! An expense is `normal` until it is disputed with the bank or the shop, and
+//! `refunded` once money came back. A refund is recorded as an income linked
+//! to the expense through `refund_of`; reports don't count it as income but
+//! take it off the expense, either in the month of the purchase or in the
+//! month the money came back, as the user prefers.
+
+use chrono::NaiveDate;
+
+/// Which month a refund is taken off the expenses of.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RefundMonth {
+    /// The purchase's month, as if it had cost less.
+    Original,
+    /// The month the money came back.
+    #[default]
+    Refund,
+}
+
+impl RefundMonth {
+    /// The setting as stored and sent by the settings form; `None` for
+    /// anything else.
+    pub fn from_param(value: &str) -> Option<RefundMonth> {
+        [RefundMonth::Original, RefundMonth::Refund]
+            .into_iter()
+            .find(|month| month.param() == value)
+    }
+
+    pub fn param(self) -> &'static str {
+        match self {
+            RefundMonth::Original => "original",
+            RefundMonth::Refund => "refund",
+        }
+    }
+
+    /// The transaction whose date and month a refund is reported under, `o`
+    /// the expense or `r` the refund.
+    pub fn source(self) -> &'static str {
+        match self {
+            RefundMonth::Original => "o",
+            RefundMonth::Refund => "r",
+        }
+    }
+}
+
+/// Whole days a dispute opened on `disputed_on` has been waiting by `today`.
+pub fn days_open(disputed_on: &str, today: NaiveDate) -> Option<i64> {
+    let opened = NaiveDate::parse_from_str(disputed_on, "%Y-%m-%d").ok()?;
+    Some((today - opened).num_days().max(0))
+}
@@ -0,0 +1,108 @@
+//! Counting a transaction toward another month than the one it is dated in.
+//!
+//! A salary paid on the 31st is next month's money. A transaction with an
+//! effective month moves to that month in every month total: the dashboard,
+//! budgets and what they roll over, cash envelopes, reports and closed
+//! months. The transaction list and the charts by day still go by its date.
+//!
+//! The bulk tool picks transactions by date, kind, category and day of the
+//! month, and counts them toward the month after their date, the month
+//! before, or their own again. Each run is one operation in the activity
+//! log, so it can be undone.
+
+use chrono::{Datelike, Months, NaiveDate};
+use rusqlite::{Connection, Result};
+
+use crate::bulk::ChangeSet;
+use crate::db;
+use crate::models::TransactionRecord;
+use crate::query::TransactionQuery;
+
+/// Where the bulk tool moves the transactions it picked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shift {
+    Next,
+    Previous,
+    /// Back to the month of the date.
+    Own,
+}
+
+impl Shift {
+    pub const ALL: [Shift; 3] = [Shift::Next, Shift::Previous, Shift::Own];
+
+    pub fn from_param(value: &str) -> Option<Shift> {
+        Shift::ALL.into_iter().find(|shift| shift.as_str() == value)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Shift::Next => "next",
+            Shift::Previous => "previous",
+            Shift::Own => "own",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Shift::Next => "В следующий месяц",
+            Shift::Previous => "В предыдущий месяц",
+            Shift::Own => "В месяц даты",
+        }
+    }
+
+    /// The effective month of a transaction dated `occurred_on` after the
+    /// shift; `None` for the month of the date, and for a date that isn't
+    /// `YYYY-MM-DD`.
+    pub fn month(self, occurred_on: &str) -> Option<String> {
+        let date = NaiveDate::parse_from_str(occurred_on, "%Y-%m-%d").ok()?;
+        let first = date.with_day(1)?;
+        let month = match self {
+            Shift::Next => first.checked_add_months(Months::new(1))?,
+            Shift::Previous => first.checked_sub_months(Months::new(1))?,
+            Shift::Own => return None,
+        };
+        Some(month.format("%Y-%m").to_string())
+    }
+}
+
+/// A transaction the bulk tool moves, and the effective month it gets.
+pub struct Shifted {
+    pub record: TransactionRecord,
+    pub month: Option<String>,
+}
+
+/// The month a transaction dated `occurred_on` counts toward, the way the
+/// reports pick it: its effective month when it has one.
+pub fn month_of<'a>(occurred_on: &'a str, effective_month: Option<&'a str>) -> &'a str {
+    effective_month.unwrap_or_else(|| occurred_on.get(..7).unwrap_or(occurred_on))
+}
+
+/// `YYYY-MM` typed into a form, `None` for anything else.
+pub fn parse_month(value: &str) -> Option<String> {
+    let value = value.trim();
+    NaiveDate::parse_from_str(&format!("{value}-01"), "%Y-%m-%d")
+        .ok()
+        .map(|first| first.format("%Y-%m").to_string())
+        .filter(|month| month == value)
+}
+
+/// Moves every transaction `query` matches by `shift`, leaving out those
+/// already counted where the shift would put them.
+pub fn shift(
+    conn: &Connection,
+    changes: &mut ChangeSet,
+    query: &TransactionQuery,
+    shift: Shift,
+) -> Result<Vec<Shifted>> {
+    let mut shifted = Vec::new();
+    for record in db::list_transactions(conn, query)? {
+        let month = shift.month(&record.occurred_on);
+        if (month.is_none() && shift != Shift::Own) || record.effective_month == month {
+            continue;
+        }
+        changes.updating(conn, "transactions", record.id)?;
+        db::set_effective_month(conn, record.id, month.as_deref())?;
+        shifted.push(Shifted { record, month });
+    }
+    Ok(shifted)
+}
@@ -0,0 +1,45 @@
+//! How a page handler fails without panicking the worker.
+
+use rocket::http::Status;
+use rocket::response::Redirect;
+use rocket_dyn_templates::Template;
+
+#[derive(Responder)]
+pub enum AppError {
+    /// Not logged in, or no user set up yet. Redirects and templates are
+    /// boxed to keep the error, and so every handler's `Result`, small.
+    Redirect(Box<Redirect>),
+    /// The page re-rendered with an error message.
+    Page(Box<Template>),
+    /// A form sent back with messages next to the fields that failed, see
+    /// `validation`.
+    #[response(status = 422)]
+    Invalid(Box<Template>),
+    Status(Status),
+}
+
+impl From<Redirect> for AppError {
+    fn from(redirect: Redirect) -> Self {
+        AppError::Redirect(Box::new(redirect))
+    }
+}
+
+impl From<Template> for AppError {
+    fn from(page: Template) -> Self {
+        AppError::Page(Box::new(page))
+    }
+}
+
+impl From<Status> for AppError {
+    fn from(status: Status) -> Self {
+        AppError::Status(status)
+    }
+}
+
+/// No connection became free within the pool timeout. The request is refused
+/// and can be retried, instead of taking the worker down.
+impl From<r2d2::Error> for AppError {
+    fn from(_: r2d2::Error) -> Self {
+        AppError::Status(Status::ServiceUnavailable)
+    }
+}
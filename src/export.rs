@@ -0,0 +1,143 @@
+use rocket::http::{ContentType, Header};
+
+use crate::format_money;
+use crate::money::Rounding;
+use crate::account_flow;
+use crate::models::{AuditEntry, ReportAccountMonth, ReportCategory, TransactionRecord};
+use crate::statement::{self, Statement};
+
+/// A generated file sent for download rather than shown.
+#[derive(Responder)]
+pub struct Attachment {
+    body: Vec<u8>,
+    content_type: ContentType,
+    disposition: Header<'static>,
+}
+
+impl Attachment {
+    fn new(filename: &str, content_type: ContentType, body: Vec<u8>) -> Self {
+        Attachment {
+            body,
+            content_type,
+            disposition: Header::new(
+                "Content-Disposition",
+                format!("attachment; filename=\"{filename}\""),
+            ),
+        }
+    }
+
+    pub fn csv(filename: &str, body: Vec<u8>) -> Self {
+        Attachment::new(filename, ContentType::CSV, body)
+    }
+
+    pub fn pdf(filename: &str, body: Vec<u8>) -> Self {
+        Attachment::new(filename, ContentType::PDF, body)
+    }
+
+    pub fn json(filename: &str, body: Vec<u8>) -> Self {
+        Attachment::new(filename, ContentType::JSON, body)
+    }
+}
+
+/// Starts the file with a UTF-8 BOM so spreadsheets detect the encoding of Cyrillic text.
+fn writer() -> csv::Writer<Vec<u8>> {
+    csv::Writer::from_writer(b"\xef\xbb\xbf".to_vec())
+}
+
+fn finish(writer: csv::Writer<Vec<u8>>) -> Result<Vec<u8>, csv::Error> {
+    writer.into_inner().map_err(|err| err.into_error().into())
+}
+
+pub fn transactions_csv(records: &[TransactionRecord]) -> Result<Vec<u8>, csv::Error> {
+    let mut out = writer();
+    out.write_record(["Дата", "Тип", "Категория", "Сумма", "Заметка", "Теги"])?;
+    for record in records {
+        out.write_record([
+            record.occurred_on.as_str(),
+            record.kind.as_str(),
+            record.category_name.as_deref().unwrap_or(""),
+            format_money(record.amount_cents).as_str(),
+            record.note.as_deref().unwrap_or(""),
+            record.tags.as_deref().unwrap_or(""),
+        ])?;
+    }
+    finish(out)
+}
+
+pub fn audit_csv(entries: &[AuditEntry]) -> Result<Vec<u8>, csv::Error> {
+    let mut out = writer();
+    out.write_record([
+        "Операция", "Время", "Вид", "Описание", "Отменена", "Таблица", "Запись", "Действие", "Было",
+    ])?;
+    for entry in entries {
+        let operation_id = entry.operation_id.to_string();
+        let row_id = entry.row_id.map(|id| id.to_string()).unwrap_or_default();
+        out.write_record([
+            operation_id.as_str(),
+            entry.created_at.as_str(),
+            entry.kind.as_str(),
+            entry.label.as_str(),
+            entry.undone_at.as_deref().unwrap_or(""),
+            entry.table_name.as_deref().unwrap_or(""),
+            row_id.as_str(),
+            entry.action.as_deref().unwrap_or(""),
+            entry.snapshot.as_deref().unwrap_or(""),
+        ])?;
+    }
+    finish(out)
+}
+
+/// Amounts are written rounded as the reports page shows them.
+pub fn report_categories_csv(records: &[ReportCategory], rounding: Rounding) -> Result<Vec<u8>, csv::Error> {
+    let mut out = writer();
+    out.write_record(["Категория", "Входит в", rounding.heading("Расход").as_str()])?;
+    for record in records {
+        out.write_record([
+            record.category_name.as_str(),
+            record.parent_name.as_deref().unwrap_or(""),
+            rounding.number(record.expense_cents).as_str(),
+        ])?;
+    }
+    finish(out)
+}
+
+pub fn report_accounts_csv(records: &[ReportAccountMonth], rounding: Rounding) -> Result<Vec<u8>, csv::Error> {
+    let mut out = writer();
+    out.write_record([
+        "Месяц",
+        "Счет",
+        rounding.heading("Доход").as_str(),
+        rounding.heading("Расход").as_str(),
+    ])?;
+    for record in records {
+        out.write_record([
+            record.month.as_str(),
+            account_flow::account_name(record),
+            rounding.number(record.income_cents).as_str(),
+            rounding.number(record.expense_cents).as_str(),
+        ])?;
+    }
+    finish(out)
+}
+
+/// The statement with its opening and closing balances as the first and last rows.
+pub fn statement_csv(statement: &Statement) -> Result<Vec<u8>, csv::Error> {
+    let mut out = writer();
+    out.write_record(["Дата", "Операция", "Заметка", "Приход", "Расход", "Остаток"])?;
+    let opening = format_money(statement.opening_cents);
+    out.write_record([statement.from.as_str(), "Входящий остаток", "", "", "", opening.as_str()])?;
+    for entry in &statement.entries {
+        let (credit, debit) = statement::credit_debit(entry.line.amount_cents);
+        out.write_record([
+            entry.line.occurred_on.as_str(),
+            statement::description(&entry.line).as_str(),
+            entry.line.note.as_deref().unwrap_or(""),
+            credit.as_str(),
+            debit.as_str(),
+            format_money(entry.balance_cents).as_str(),
+        ])?;
+    }
+    let closing = format_money(statement.closing_cents);
+    out.write_record([statement.to.as_str(), "Исходящий остаток", "", "", "", closing.as_str()])?;
+    finish(out)
+}
@@ -0,0 +1,155 @@
+//! Transactions made from the QR code of a Russian fiscal receipt.
+//!
+//! Besides the date (`t`) and total (`s`) the code carries the fiscal drive
+//! (`fn`), the document number (`i`) and its fiscal sign (`fp`), which
+//! together identify the receipt, so the same one isn't added twice, and the
+//! kind of operation (`n`): 1 is a purchase, 2 its refund.
+//!
+//! The code doesn't name the shop or what was bought. With
+//! `LUMEN_RECEIPT_CHECK_TOKEN` set the receipt is looked up at
+//! proverkacheka.com, or at `LUMEN_RECEIPT_CHECK_URL` with the same API; the
+//! seller becomes the payee unless one was typed in, and the lines of a
+//! purchase become its items (`receipt_items`). Without it, or when the
+//! service doesn't know the receipt yet, the payee is what was typed in.
+
+use std::time::Duration;
+
+use serde_json::Value;
+
+use crate::models::NewReceiptItem;
+use crate::receipt_inbox::FiscalQr;
+
+const CHECK_URL: &str = "https://proverkacheka.com/api/v1/check/get";
+const CHECK_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// A fiscal receipt as its QR code describes it.
+#[derive(Debug, PartialEq, Eq)]
+pub struct FiscalReceipt {
+    pub qr: FiscalQr,
+    /// `fn`, `i` and `fp` joined with dashes.
+    pub id: String,
+    /// `expense` for a purchase, `income` for its refund.
+    pub kind: &'static str,
+}
+
+impl FiscalReceipt {
+    /// Reads the text of a QR code; the error is for the form.
+    pub fn parse(text: &str) -> Result<Self, &'static str> {
+        let qr = FiscalQr::parse(text).ok_or("В QR-коде нет даты и суммы чека")?;
+        let field = |name: &str| {
+            text.trim()
+                .split('&')
+                .find_map(|pair| match pair.split_once('=') {
+                    Some((key, value)) if key == name && !value.trim().is_empty() => {
+                        Some(value.trim())
+                    }
+                    _ => None,
+                })
+        };
+        let kind = match field("n") {
+            None | Some("1") => "expense",
+            Some("2") => "income",
+            Some(_) => return Err("Это чек выплаты продавца, а не покупки"),
+        };
+        let (Some(drive), Some(document), Some(sign)) = (field("fn"), field("i"), field("fp"))
+        else {
+            return Err("В QR-коде нет номеров чека (fn, i, fp)");
+        };
+        Ok(FiscalReceipt {
+            qr,
+            id: format!("{drive}-{document}-{sign}"),
+            kind,
+        })
+    }
+}
+
+/// What the check service knows about a receipt.
+#[derive(Debug, Default, PartialEq)]
+pub struct Checked {
+    pub seller: Option<String>,
+    pub items: Vec<NewReceiptItem>,
+}
+
+/// The receipt check service, see the module documentation.
+#[derive(Clone)]
+pub struct Lookup {
+    url: String,
+    token: String,
+}
+
+impl Lookup {
+    pub fn from_env() -> Option<Self> {
+        let var = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        Some(Lookup {
+            token: var("LUMEN_RECEIPT_CHECK_TOKEN")?,
+            url: var("LUMEN_RECEIPT_CHECK_URL").unwrap_or_else(|| CHECK_URL.to_string()),
+        })
+    }
+
+    /// The seller and lines of the receipt with QR code `qr`, if the
+    /// service knows it.
+    pub fn check(&self, qr: &str) -> Option<Checked> {
+        let payload: Value = ureq::post(&self.url)
+            .timeout(CHECK_TIMEOUT)
+            .send_form(&[("token", self.token.as_str()), ("qrraw", qr.trim())])
+            .ok()?
+            .into_json()
+            .ok()?;
+        if payload["code"].as_i64() != Some(1) {
+            return None;
+        }
+        Some(Checked {
+            seller: seller_of(&payload),
+            items: items_of(&payload),
+        })
+    }
+}
+
+/// Reads the check service's answer: `code` 1 and the receipt under
+/// `data.json`, whose `user` is the seller and `retailPlace` the shop.
+pub fn seller_of(payload: &Value) -> Option<String> {
+    if payload["code"].as_i64() != Some(1) {
+        return None;
+    }
+    let receipt = &payload["data"]["json"];
+    ["user", "retailPlace"]
+        .iter()
+        .filter_map(|field| receipt[field].as_str())
+        .map(|name| name.split_whitespace().collect::<Vec<_>>().join(" "))
+        .find(|name| !name.is_empty())
+}
+
+/// The lines of the receipt in the service's answer, `data.json.items`, with
+/// prices in kopecks per unit.
+pub fn items_of(payload: &Value) -> Vec<NewReceiptItem> {
+    if payload["code"].as_i64() != Some(1) {
+        return Vec::new();
+    }
+    let Some(items) = payload["data"]["json"]["items"].as_array() else {
+        return Vec::new();
+    };
+    items
+        .iter()
+        .filter_map(|item| {
+            let name = item["name"]
+                .as_str()?
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ");
+            let quantity = item["quantity"]
+                .as_f64()
+                .filter(|quantity| *quantity > 0.0)?;
+            let price_cents = item["price"].as_i64().filter(|price| *price >= 0)?;
+            (!name.is_empty()).then_some(NewReceiptItem {
+                name,
+                quantity,
+                price_cents,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,127 @@
+use std::time::Duration;
+
+use chrono::{Local, Months, NaiveDate};
+use rusqlite::{Connection, Result};
+use serde_json::Value;
+
+use crate::db::{self, DbPool};
+use crate::models::{Account, NewTransaction};
+
+pub const BASE_CURRENCY: &str = "RUB";
+pub const REFRESH_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+const CBR_DAILY_URL: &str = "https://www.cbr-xml-daily.ru/daily_json.js";
+
+/// Where daily rates come from: the CBR feed unless `LUMEN_FX_URL` points at
+/// another source with the same JSON shape; `LUMEN_FX_URL=off` disables fetching.
+#[derive(Clone)]
+pub struct RatesSource {
+    pub url: String,
+}
+
+impl RatesSource {
+    pub fn from_env() -> Option<Self> {
+        let url = std::env::var("LUMEN_FX_URL").unwrap_or_else(|_| CBR_DAILY_URL.to_string());
+        let url = url.trim();
+        if url.is_empty() || url == "off" {
+            return None;
+        }
+        Some(RatesSource {
+            url: url.to_string(),
+        })
+    }
+
+    fn fetch(&self) -> Option<Value> {
+        ureq::get(&self.url)
+            .timeout(Duration::from_secs(15))
+            .call()
+            .ok()?
+            .into_json()
+            .ok()
+    }
+}
+
+/// Reads a CBR-style payload: `Date` plus `Valute.<CODE>.{Value, Nominal}`.
+fn parse_daily(payload: &Value) -> Option<(String, Vec<(String, f64)>)> {
+    let date = payload["Date"].as_str()?.get(..10)?.to_string();
+    let rates = payload["Valute"]
+        .as_object()?
+        .iter()
+        .filter_map(|(code, entry)| {
+            let value = entry["Value"].as_f64()?;
+            let nominal = entry["Nominal"].as_f64().unwrap_or(1.0);
+            (nominal > 0.0).then(|| (code.to_uppercase(), value / nominal))
+        })
+        .collect();
+    Some((date, rates))
+}
+
+/// Fetches today's rates and stores them; manual overrides are kept.
+pub fn refresh_rates(pool: &DbPool, source: &RatesSource) {
+    let Some((date, rates)) = source.fetch().as_ref().and_then(parse_daily) else {
+        return;
+    };
+    let Ok(conn) = pool.get() else {
+        return;
+    };
+    let updated_at = Local::now().to_rfc3339();
+    for (currency, rate) in rates {
+        let _ = db::upsert_exchange_rate(&conn, &currency, &date, rate, "auto", &updated_at);
+    }
+}
+
+/// Accepts a three-letter code in any case; an empty value means rubles.
+pub fn normalize_currency(input: &str) -> Option<String> {
+    let code = input.trim().to_uppercase();
+    if code.is_empty() {
+        return Some(BASE_CURRENCY.to_string());
+    }
+    (code.len() == 3 && code.chars().all(|c| c.is_ascii_alphabetic())).then_some(code)
+}
+
+pub fn parse_rate(input: &str) -> Option<f64> {
+    let rate: f64 = input.trim().replace(',', ".").parse().ok()?;
+    (rate.is_finite() && rate > 0.0).then_some(rate)
+}
+
+/// Last day of a `YYYY-MM` month as `YYYY-MM-DD`.
+pub fn month_end(month: &str) -> Option<String> {
+    let first = NaiveDate::parse_from_str(&format!("{month}-01"), "%Y-%m-%d").ok()?;
+    let last = first.checked_add_months(Months::new(1))?.pred_opt()?;
+    Some(last.format("%Y-%m-%d").to_string())
+}
+
+/// Value of a currency amount in rubles at `rate`, rounded to kopecks.
+pub fn to_base_cents(cents: i64, rate: f64) -> i64 {
+    (cents as f64 * rate).round() as i64
+}
+
+/// Revalues a foreign-currency account at the end of `month`.
+///
+/// The adjustment is the month-end balance times the rate change since the
+/// previous revaluation; the first revaluation only fixes the starting rate.
+/// Revaluing a month again replaces its earlier entry. Returns the adjustment
+/// in rubles.
+pub fn revalue(conn: &mut Connection, account: &Account, month: &str, rate: f64) -> Result<i64> {
+    let until =
+        month_end(month).ok_or_else(|| rusqlite::Error::InvalidParameterName(month.to_string()))?;
+    let tx = conn.transaction()?;
+    let balance = db::account_balance_until(&tx, account.id, &until)?;
+    let previous = db::previous_revaluation_rate(&tx, account.id, month)?.unwrap_or(rate);
+    let adjustment_cents = to_base_cents(balance, rate) - to_base_cents(balance, previous);
+    let adjustment = NewTransaction {
+        kind: "adjustment".to_string(),
+        amount_cents: adjustment_cents,
+        category_id: None,
+        occurred_on: until,
+        note: Some(format!(
+            "Переоценка {} за {month}: курс {previous} → {rate}",
+            account.currency
+        )),
+        payee: None,
+        account_id: Some(account.id),
+        to_account_id: None,
+    };
+    db::save_revaluation(&tx, account.id, month, rate, &adjustment)?;
+    tx.commit()?;
+    Ok(adjustment_cents)
+}
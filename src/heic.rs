@@ -0,0 +1,80 @@
+//! HEIC photos, which iPhones take and most browsers can't show, turned into
+//! JPEG when they are uploaded, so a receipt link always opens as an image.
+//!
+//! Decoding needs libheif 1.18 or later, linked when the app is built with
+//! `--features heic`. Without it, or for a file libheif can't read, the photo
+//! is kept as it came and opens as a download.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// JPEG quality of converted photos; receipts are read, not admired.
+#[cfg(feature = "heic")]
+const QUALITY: u8 = 85;
+
+pub fn is_heic(name: &str) -> bool {
+    Path::new(name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("heic") || ext.eq_ignore_ascii_case("heif"))
+}
+
+/// `name` with a `.jpg` extension in place of its own.
+pub fn jpeg_name(name: &str) -> String {
+    Path::new(name)
+        .with_extension("jpg")
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Writes the photo at `from` to `to` as a JPEG, turned the way the camera
+/// was held.
+#[cfg(feature = "heic")]
+pub fn to_jpeg(from: &Path, to: &Path) -> Result<(), String> {
+    use image::ExtendedColorType;
+    use image::codecs::jpeg::JpegEncoder;
+    use libheif_rs::{ColorSpace, HeifContext, LibHeif, RgbChroma};
+
+    let from = from.to_str().ok_or("path is not UTF-8")?;
+    let context = HeifContext::read_from_file(from).map_err(|err| err.to_string())?;
+    let handle = context
+        .primary_image_handle()
+        .map_err(|err| err.to_string())?;
+    // libheif applies the rotation stored in the file while decoding.
+    let decoded = LibHeif::new()
+        .decode(&handle, ColorSpace::Rgb(RgbChroma::Rgb), None)
+        .map_err(|err| err.to_string())?;
+    let plane = decoded
+        .planes()
+        .interleaved
+        .ok_or("no interleaved RGB plane")?;
+    let row = plane.width as usize * 3;
+    let mut pixels = Vec::with_capacity(row * plane.height as usize);
+    for line in plane.data.chunks(plane.stride).take(plane.height as usize) {
+        pixels.extend_from_slice(&line[..row]);
+    }
+    let file = fs::File::create(to).map_err(|err| err.to_string())?;
+    JpegEncoder::new_with_quality(std::io::BufWriter::new(file), QUALITY)
+        .encode(&pixels, plane.width, plane.height, ExtendedColorType::Rgb8)
+        .map_err(|err| err.to_string())
+}
+
+#[cfg(not(feature = "heic"))]
+pub fn to_jpeg(_from: &Path, _to: &Path) -> Result<(), String> {
+    Err("built without the heic feature".to_string())
+}
+
+/// Converts the photo at `from` into a JPEG at `to` on a blocking thread.
+/// Afterwards only one of them is left: `to` when this returns true, else
+/// `from`.
+pub async fn convert(from: PathBuf, to: PathBuf) -> bool {
+    let (source, target) = (from.clone(), to.clone());
+    let converted = rocket::tokio::task::spawn_blocking(move || to_jpeg(&source, &target)).await;
+    if matches!(converted, Ok(Ok(()))) {
+        let _ = fs::remove_file(from);
+        true
+    } else {
+        let _ = fs::remove_file(to);
+        false
+    }
+}
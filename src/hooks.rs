@@ -0,0 +1,73 @@
+use serde_json::Value;
+
+use crate::import::{parse_date, parse_signed_amount};
+use crate::models::{Category, InboundHook, NewTransaction};
+
+/// Looks up a dotted path such as `data.amount` in the request payload.
+fn field<'a>(payload: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .try_fold(payload, |value, key| match key.parse::<usize>() {
+            Ok(index) if value.is_array() => value.get(index),
+            _ => value.get(key),
+        })
+}
+
+fn text(payload: &Value, path: Option<&str>) -> Option<String> {
+    let value = match field(payload, path?)? {
+        Value::String(value) => value.trim().to_string(),
+        Value::Number(value) => value.to_string(),
+        _ => return None,
+    };
+    Some(value).filter(|value| !value.is_empty())
+}
+
+/// Builds a transaction from a webhook payload using the hook's field mapping.
+///
+/// A negative amount flips the hook's kind, a missing date falls back to
+/// `today`, and a category name that matches nothing falls back to the hook's
+/// default category.
+pub fn to_new_transaction(
+    hook: &InboundHook,
+    payload: &Value,
+    categories: &[Category],
+    today: &str,
+) -> Result<NewTransaction, String> {
+    let amount = text(payload, Some(&hook.amount_field))
+        .ok_or_else(|| format!("нет поля `{}`", hook.amount_field))?;
+    let (negative, amount_cents) = parse_signed_amount(&amount)
+        .filter(|(_, cents)| *cents > 0)
+        .ok_or_else(|| format!("поле `{}` не является суммой", hook.amount_field))?;
+    let kind = match (hook.kind.as_str(), negative) {
+        ("income", true) => "expense",
+        ("expense", true) => "income",
+        (kind, _) => kind,
+    };
+
+    let occurred_on = match text(payload, hook.date_field.as_deref()) {
+        Some(value) => {
+            parse_date(&value).ok_or_else(|| format!("не удалось разобрать дату `{value}`"))?
+        }
+        None => today.to_string(),
+    };
+
+    let category_id = text(payload, hook.category_field.as_deref())
+        .and_then(|name| {
+            let wanted = name.to_lowercase();
+            categories
+                .iter()
+                .find(|category| category.kind == kind && category.name.to_lowercase() == wanted)
+                .map(|category| category.id)
+        })
+        .or(hook.category_id.filter(|_| kind == hook.kind));
+
+    Ok(NewTransaction {
+        kind: kind.to_string(),
+        amount_cents,
+        category_id,
+        occurred_on,
+        note: text(payload, hook.note_field.as_deref()),
+        payee: None,
+        account_id: hook.account_id,
+        to_account_id: None,
+    })
+}
@@ -0,0 +1,221 @@
+use std::path::PathBuf;
+
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use crate::models::{Category, NewTransaction};
+
+const DATE_FORMATS: &[&str] = &["%Y-%m-%d", "%d.%m.%Y", "%d/%m/%Y", "%d.%m.%y", "%Y/%m/%d"];
+
+#[derive(FromForm, Default)]
+pub struct ImportMapping {
+    pub date_col: Option<usize>,
+    pub amount_col: Option<usize>,
+    pub note_col: Option<usize>,
+    pub category_col: Option<usize>,
+    pub has_header: Option<bool>,
+    pub positive_as: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ParsedRow {
+    pub line: usize,
+    pub occurred_on: String,
+    pub kind: String,
+    pub amount_cents: i64,
+    pub note: Option<String>,
+    pub category_id: Option<i64>,
+    pub category_name: Option<String>,
+    pub error: Option<String>,
+}
+
+impl ParsedRow {
+    pub fn to_new_transaction(&self) -> NewTransaction {
+        NewTransaction {
+            kind: self.kind.clone(),
+            amount_cents: self.amount_cents,
+            category_id: self.category_id,
+            occurred_on: self.occurred_on.clone(),
+            note: self.note.clone(),
+            payee: None,
+            account_id: None,
+            to_account_id: None,
+        }
+    }
+}
+
+pub fn imports_dir() -> PathBuf {
+    let mut dir = PathBuf::from("data");
+    dir.push("imports");
+    dir
+}
+
+pub fn detect_delimiter(content: &str) -> u8 {
+    let first_line = content.lines().next().unwrap_or("");
+    [b';', b',', b'\t']
+        .into_iter()
+        .max_by_key(|delimiter| first_line.bytes().filter(|b| b == delimiter).count())
+        .unwrap_or(b',')
+}
+
+pub fn read_rows(content: &str) -> Result<Vec<Vec<String>>, csv::Error> {
+    let content = content.trim_start_matches('\u{feff}');
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(detect_delimiter(content))
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(content.as_bytes());
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        if record.iter().all(|field| field.trim().is_empty()) {
+            continue;
+        }
+        rows.push(record.iter().map(|field| field.trim().to_string()).collect());
+    }
+    Ok(rows)
+}
+
+pub fn parse_date(input: &str) -> Option<String> {
+    let value = input.split([' ', 'T']).next()?.trim();
+    DATE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(value, format).ok())
+        .map(|date| date.format("%Y-%m-%d").to_string())
+}
+
+/// Parses a bank-style amount such as `-1 234,50 ₽`; returns the sign and cents.
+pub fn parse_signed_amount(input: &str) -> Option<(bool, i64)> {
+    let cleaned: String = input
+        .trim()
+        .trim_end_matches('₽')
+        .trim_end_matches("RUB")
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '\u{a0}' && *c != '\u{202f}')
+        .collect();
+    let (negative, digits) = match cleaned.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, cleaned.strip_prefix('+').unwrap_or(&cleaned)),
+    };
+    let cents = crate::money::parse_amount_to_cents(digits)?;
+    Some((negative, cents))
+}
+
+fn guess_column(header: &[String], names: &[&str]) -> Option<usize> {
+    header.iter().position(|column| {
+        let column = column.to_lowercase();
+        names.iter().any(|name| column.contains(name))
+    })
+}
+
+/// Guesses columns from header names on the first visit and decides whether the
+/// first row is a header.
+pub fn resolve_mapping(rows: &[Vec<String>], mapping: ImportMapping) -> ImportMapping {
+    let first = rows.first().cloned().unwrap_or_default();
+    let submitted = mapping.date_col.is_some() || mapping.amount_col.is_some();
+    let date_col = mapping
+        .date_col
+        .or_else(|| guess_column(&first, &["дата", "date"]))
+        .or(Some(0));
+    let amount_col = mapping
+        .amount_col
+        .or_else(|| guess_column(&first, &["сумма", "amount"]))
+        .or(Some(1));
+    let note_col = mapping.note_col.or_else(|| {
+        let names = ["описание", "назначение", "комментарий", "note", "description"];
+        guess_column(&first, &names).filter(|_| !submitted)
+    });
+    let category_col = mapping
+        .category_col
+        .or_else(|| guess_column(&first, &["категория", "category"]).filter(|_| !submitted));
+    let has_header = mapping.has_header.or_else(|| {
+        let amount = amount_col.and_then(|col| first.get(col));
+        Some(amount.is_none_or(|value| parse_signed_amount(value).is_none()))
+    });
+    let positive_as = mapping
+        .positive_as
+        .filter(|kind| kind == "income" || kind == "expense")
+        .or_else(|| Some("income".to_string()));
+
+    ImportMapping {
+        date_col,
+        amount_col,
+        note_col,
+        category_col,
+        has_header,
+        positive_as,
+    }
+}
+
+pub fn column_names(rows: &[Vec<String>], has_header: bool) -> Vec<String> {
+    let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    (0..width)
+        .map(|index| {
+            let name = rows
+                .first()
+                .filter(|_| has_header)
+                .and_then(|row| row.get(index))
+                .filter(|name| !name.is_empty());
+            match name {
+                Some(name) => name.clone(),
+                None => format!("Колонка {}", index + 1),
+            }
+        })
+        .collect()
+}
+
+pub fn parse_rows(
+    rows: &[Vec<String>],
+    mapping: &ImportMapping,
+    categories: &[Category],
+) -> Vec<ParsedRow> {
+    let skip = if mapping.has_header.unwrap_or(false) { 1 } else { 0 };
+    let positive_kind = mapping.positive_as.as_deref().unwrap_or("income");
+    let negative_kind = if positive_kind == "income" { "expense" } else { "income" };
+    let cell = |row: &Vec<String>, col: Option<usize>| {
+        col.and_then(|col| row.get(col))
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+
+    rows.iter()
+        .enumerate()
+        .skip(skip)
+        .map(|(index, row)| {
+            let mut parsed = ParsedRow {
+                line: index + 1,
+                occurred_on: String::new(),
+                kind: positive_kind.to_string(),
+                amount_cents: 0,
+                note: cell(row, mapping.note_col),
+                category_id: None,
+                category_name: cell(row, mapping.category_col),
+                error: None,
+            };
+
+            match cell(row, mapping.date_col).as_deref().and_then(parse_date) {
+                Some(date) => parsed.occurred_on = date,
+                None => parsed.error = Some("Не удалось разобрать дату".to_string()),
+            }
+            match cell(row, mapping.amount_col).as_deref().and_then(parse_signed_amount) {
+                Some((negative, cents)) => {
+                    parsed.amount_cents = cents;
+                    if negative {
+                        parsed.kind = negative_kind.to_string();
+                    }
+                }
+                None => {
+                    parsed.error.get_or_insert_with(|| "Не удалось разобрать сумму".to_string());
+                }
+            }
+            if let Some(name) = &parsed.category_name {
+                let wanted = name.to_lowercase();
+                parsed.category_id = categories
+                    .iter()
+                    .find(|category| category.kind == parsed.kind && category.name.to_lowercase() == wanted)
+                    .map(|category| category.id);
+            }
+            parsed
+        })
+        .collect()
+}
@@ -0,0 +1,125 @@
+//! Yearly increase of standing budgets, so they keep pace with prices.
+//!
+//! A standing budget may carry a percentage. Once a fiscal year has started,
+//! the hourly check raises every such budget by its percentage as one
+//! operation in the activity log, where it can be undone. Each fiscal year is
+//! raised at most once, so an undone raise stays undone.
+
+use std::time::Duration;
+
+use chrono::{Datelike, Local, NaiveDate};
+use rusqlite::{Connection, Result};
+
+use crate::bulk;
+use crate::db::{self, DbPool};
+use crate::format_money;
+use crate::models::NewNotification;
+use crate::notifications;
+
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// When the fiscal year starts; configured through the environment.
+#[derive(Clone, Copy, Debug)]
+pub struct Schedule {
+    /// 1 for January.
+    pub start_month: u32,
+}
+
+impl Schedule {
+    /// `LUMEN_FISCAL_YEAR_START` is the first month, 1–12, January by default;
+    /// `off` turns the yearly increase off.
+    pub fn from_env() -> Option<Self> {
+        let value = std::env::var("LUMEN_FISCAL_YEAR_START").unwrap_or_default();
+        let value = value.trim();
+        if value == "off" {
+            return None;
+        }
+        let start_month = value
+            .parse()
+            .ok()
+            .filter(|month| (1..=12).contains(month))
+            .unwrap_or(1);
+        Some(Schedule { start_month })
+    }
+
+    /// The fiscal year `date` falls in, named by the calendar year it starts in.
+    pub fn fiscal_year(&self, date: NaiveDate) -> i32 {
+        if date.month() >= self.start_month {
+            date.year()
+        } else {
+            date.year() - 1
+        }
+    }
+}
+
+/// `amount_cents` raised by `percent`, to the nearest kopeck.
+pub fn raised(amount_cents: i64, percent: f64) -> i64 {
+    (amount_cents as f64 * (1.0 + percent / 100.0)).round() as i64
+}
+
+/// Raises the standing budgets for the fiscal year `today` is in, unless that
+/// year is done already. Returns `(category, old, new)` for each budget raised.
+pub fn apply(
+    conn: &mut Connection,
+    schedule: Schedule,
+    today: NaiveDate,
+) -> Result<Vec<(String, i64, i64)>> {
+    let year = schedule.fiscal_year(today);
+    if db::budget_increase_done(conn, year)? {
+        return Ok(Vec::new());
+    }
+    let label = format!("Индексация ежемесячных бюджетов на {year} год");
+    let outcome = bulk::run(conn, false, "budget_increase", &label, |tx, changes| {
+        let mut out = Vec::new();
+        for budget in db::list_standing_budgets(tx)? {
+            let Some(percent) = budget.yearly_increase_percent else {
+                continue;
+            };
+            let amount_cents = raised(budget.amount_cents, percent);
+            changes.updating(tx, "standing_budgets", budget.id)?;
+            db::set_standing_budget_amount(tx, budget.id, amount_cents)?;
+            out.push((budget.category_name, budget.amount_cents, amount_cents));
+        }
+        // Outside the change set: undoing the raise keeps the year done.
+        db::record_budget_increase(tx, year, &Local::now().to_rfc3339())?;
+        Ok(out)
+    })?;
+    Ok(outcome.changes)
+}
+
+/// The scheduled check: raises the budgets when a fiscal year has begun and
+/// tells every user what changed.
+pub fn run_due(pool: &DbPool, schedule: Schedule) {
+    let Ok(mut conn) = pool.get() else {
+        return;
+    };
+    let Ok(raised) = apply(&mut conn, schedule, Local::now().date_naive()) else {
+        return;
+    };
+    if raised.is_empty() {
+        return;
+    }
+    let lines = raised
+        .iter()
+        .map(|(category, from, to)| {
+            format!(
+                "{category}: {} → {}",
+                format_money(*from),
+                format_money(*to)
+            )
+        })
+        .collect::<Vec<_>>();
+    let body = format!("{}\nОтменить можно в журнале действий.", lines.join("\n"));
+    for user_id in db::user_ids(&conn).unwrap_or_default() {
+        let _ = notifications::dispatch(
+            &conn,
+            user_id,
+            &NewNotification {
+                event: "budget_increase".to_string(),
+                title: "Ежемесячные бюджеты проиндексированы".to_string(),
+                body: body.clone(),
+                payload: None,
+            },
+        );
+    }
+}
@@ -0,0 +1,357 @@
+//! Long operations run in the background while the user watches a progress page.
+//!
+//! A handler hands the work to [`Jobs::spawn`] and redirects to `/jobs/<id>`
+//! right away, so a big import or archive never holds a request open until
+//! the client or a proxy gives up. Every job is a row in `jobs`, so results
+//! and downloads survive a restart; a job that was running when the process
+//! stopped is marked failed on the next start. Progress counters change too
+//! often to write, and a running import holds the write lock anyway, so they
+//! live in memory until the job ends. Only the latest [`MAX_JOBS`] are kept.
+//!
+//! Generated files stay downloadable for [`DOWNLOAD_DAYS`] and can be fetched
+//! in parts, so a dropped mobile connection resumes instead of starting over.
+
+use std::collections::HashMap;
+use std::io::SeekFrom;
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use chrono::{Duration, Local};
+use rocket::http::{ContentType, Status};
+use rocket::request::{self, FromRequest};
+use rocket::response::{self, Responder};
+use rocket::tokio::fs::File;
+use rocket::tokio::io::{AsyncReadExt, AsyncSeekExt};
+use rocket::{Request, Response};
+use rusqlite::Connection;
+use uuid::Uuid;
+
+use crate::db::{self, DbPool};
+use crate::models::Job;
+
+const MAX_JOBS: i64 = 50;
+pub const DOWNLOAD_DAYS: i64 = 7;
+
+/// What a finished job leaves behind.
+pub enum Outcome {
+    /// A page that shows the result, e.g. the imported transactions.
+    Page(String),
+    /// A generated file, offered for download under `name`.
+    File { path: PathBuf, name: String },
+}
+
+/// 0–100; a job that hasn't counted its work yet is at 0 until done.
+pub fn percent(job: &Job) -> i64 {
+    match (job.state.as_str(), job.total) {
+        ("done", _) => 100,
+        (_, 0) => 0,
+        _ => (job.done * 100 / job.total).clamp(0, 100),
+    }
+}
+
+#[derive(Clone)]
+pub struct Jobs {
+    pool: DbPool,
+    /// `(done, total)` of the running jobs.
+    live: Arc<Mutex<HashMap<i64, (i64, i64)>>>,
+}
+
+impl Jobs {
+    /// Jobs stored in `pool`. Any still marked running lost their thread with
+    /// the previous process, so they are failed.
+    pub fn new(pool: DbPool) -> Self {
+        if let Ok(conn) = pool.get() {
+            let now = Local::now().to_rfc3339();
+            let _ = db::fail_running_jobs(&conn, "Прервано перезапуском сервера", &now);
+            expire(&conn);
+        }
+        Jobs {
+            pool,
+            live: Arc::default(),
+        }
+    }
+
+    fn live(&self) -> MutexGuard<'_, HashMap<i64, (i64, i64)>> {
+        // A panicking job only ever leaves counters half-updated.
+        self.live
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// The stored job, with the current counters while it runs.
+    pub fn get(&self, conn: &Connection, id: i64) -> rusqlite::Result<Option<Job>> {
+        let Some(mut job) = db::job_by_id(conn, id)? else {
+            return Ok(None);
+        };
+        if let Some(&(done, total)) = self.live().get(&id) {
+            job.done = done;
+            job.total = total;
+        }
+        Ok(Some(job))
+    }
+
+    /// Records the job and starts `work` on the blocking thread pool; returns
+    /// the job id at once. An `Err` from `work` is shown to the user as is.
+    pub fn spawn<F>(
+        &self,
+        conn: &Connection,
+        user_id: i64,
+        label: &str,
+        work: F,
+    ) -> rusqlite::Result<i64>
+    where
+        F: FnOnce(&Progress) -> Result<Outcome, String> + Send + 'static,
+    {
+        let id = db::insert_job(conn, user_id, label, &Local::now().to_rfc3339())?;
+        prune(conn)?;
+        self.live().insert(id, (0, 0));
+        let progress = Progress {
+            jobs: self.clone(),
+            id,
+        };
+        rocket::tokio::task::spawn_blocking(move || {
+            let result = std::panic::catch_unwind(AssertUnwindSafe(|| work(&progress)))
+                .unwrap_or_else(|_| Err("Внутренняя ошибка".to_string()));
+            progress.finish(result);
+        });
+        Ok(id)
+    }
+}
+
+/// The running job's handle for reporting how far it got.
+pub struct Progress {
+    jobs: Jobs,
+    id: i64,
+}
+
+impl Progress {
+    pub fn set_total(&self, total: u64) {
+        if let Some(counters) = self.jobs.live().get_mut(&self.id) {
+            counters.1 = total as i64;
+        }
+    }
+
+    pub fn advance(&self, done: u64) {
+        if let Some(counters) = self.jobs.live().get_mut(&self.id) {
+            counters.0 += done as i64;
+        }
+    }
+
+    /// Stores the outcome. Should that fail, the job stays running until the
+    /// next start fails it.
+    fn finish(&self, result: Result<Outcome, String>) {
+        let Ok(conn) = self.jobs.pool.get() else {
+            return;
+        };
+        let Ok(Some(mut job)) = self.jobs.get(&conn, self.id) else {
+            return;
+        };
+        job.finished_at = Some(Local::now().to_rfc3339());
+        match result {
+            Ok(Outcome::Page(url)) => {
+                job.state = "done".to_string();
+                job.result_url = Some(url);
+            }
+            Ok(Outcome::File { path, name }) => attach_file(&mut job, &path, name),
+            Err(error) => {
+                job.state = "failed".to_string();
+                job.error = Some(error);
+            }
+        }
+        if db::save_job_result(&conn, &job).is_ok() {
+            self.jobs.live().remove(&self.id);
+        }
+    }
+}
+
+fn attach_file(job: &mut Job, path: &Path, name: String) {
+    let now = Local::now();
+    job.state = "done".to_string();
+    job.result_url = Some(format!("/jobs/{}/download", job.id));
+    job.file_path = Some(path.to_string_lossy().into_owned());
+    job.file_name = Some(name);
+    job.finished_at = Some(now.to_rfc3339());
+    job.expires_at = Some((now + Duration::days(DOWNLOAD_DAYS)).to_rfc3339());
+}
+
+fn prune(conn: &Connection) -> rusqlite::Result<()> {
+    for path in db::prune_jobs(conn, MAX_JOBS)? {
+        let _ = std::fs::remove_file(path);
+    }
+    Ok(())
+}
+
+/// Deletes the files whose time ran out.
+pub fn expire(conn: &Connection) {
+    if let Ok(paths) = db::expire_downloads(conn, &Local::now().to_rfc3339()) {
+        for path in paths {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Keeps a file generated during a request, such as a CSV export, among the
+/// user's downloads as an already finished job.
+pub fn keep_file(
+    conn: &Connection,
+    user_id: i64,
+    label: &str,
+    name: &str,
+    body: &[u8],
+) -> Result<i64, String> {
+    let dir = exports_dir();
+    let extension = Path::new(name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("bin");
+    let path = dir.join(format!("{}.{extension}", Uuid::new_v4()));
+    std::fs::create_dir_all(&dir)
+        .and_then(|_| std::fs::write(&path, body))
+        .map_err(|err| err.to_string())?;
+    let stored = db::insert_job(conn, user_id, label, &Local::now().to_rfc3339())
+        .and_then(|id| db::job_by_id(conn, id))
+        .and_then(|job| {
+            let mut job = job.ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+            attach_file(&mut job, &path, name.to_string());
+            db::save_job_result(conn, &job)?;
+            prune(conn)?;
+            Ok(job.id)
+        });
+    stored.map_err(|err| {
+        let _ = std::fs::remove_file(&path);
+        err.to_string()
+    })
+}
+
+pub fn exports_dir() -> PathBuf {
+    let mut dir = PathBuf::from("data");
+    dir.push("exports");
+    dir
+}
+
+/// A single `Range: bytes=…` request. Several ranges at once are unusual
+/// enough for downloads that they are answered with the whole file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ByteRange {
+    /// `bytes=500-`
+    From(u64),
+    /// `bytes=500-999`, both ends included.
+    Between(u64, u64),
+    /// `bytes=-500`: the final 500 bytes.
+    Last(u64),
+}
+
+impl ByteRange {
+    pub fn parse(header: &str) -> Option<ByteRange> {
+        let spec = header.trim().strip_prefix("bytes=")?;
+        if spec.contains(',') {
+            return None;
+        }
+        let (start, end) = spec.split_once('-')?;
+        let (start, end) = (start.trim(), end.trim());
+        match (start.is_empty(), end.is_empty()) {
+            (false, true) => Some(ByteRange::From(start.parse().ok()?)),
+            (false, false) => {
+                let (start, end) = (start.parse().ok()?, end.parse().ok()?);
+                (start <= end).then_some(ByteRange::Between(start, end))
+            }
+            (true, false) => Some(ByteRange::Last(end.parse().ok()?)),
+            (true, true) => None,
+        }
+    }
+
+    /// First and last byte within a file of `len` bytes, or `None` when the
+    /// range lies entirely past its end.
+    pub fn resolve(self, len: u64) -> Option<(u64, u64)> {
+        let (start, end) = match self {
+            ByteRange::From(start) => (start, len.checked_sub(1)?),
+            ByteRange::Between(start, end) => (start, end.min(len.checked_sub(1)?)),
+            ByteRange::Last(0) => return None,
+            ByteRange::Last(count) => (len.saturating_sub(count), len.checked_sub(1)?),
+        };
+        (start <= end).then_some((start, end))
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ByteRange {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, ()> {
+        match request
+            .headers()
+            .get_one("Range")
+            .and_then(ByteRange::parse)
+        {
+            Some(range) => request::Outcome::Success(range),
+            None => request::Outcome::Forward(Status::Ok),
+        }
+    }
+}
+
+/// A finished job's file, sent as an attachment, whole or from the byte
+/// range the client asked for.
+pub struct Download {
+    file: File,
+    len: u64,
+    name: String,
+    /// `None` for the whole file; `Some(None)` when the range can't be served.
+    range: Option<Option<(u64, u64)>>,
+}
+
+impl Download {
+    pub async fn open(job: &Job, range: Option<ByteRange>) -> Option<Download> {
+        let path = job.file_path.as_ref()?;
+        let name = job.file_name.clone()?;
+        let mut file = File::open(path).await.ok()?;
+        let len = file.metadata().await.ok()?.len();
+        let range = range.map(|range| range.resolve(len));
+        if let Some(Some((start, _))) = range {
+            file.seek(SeekFrom::Start(start)).await.ok()?;
+        }
+        Some(Download {
+            file,
+            len,
+            name,
+            range,
+        })
+    }
+}
+
+impl<'r> Responder<'r, 'static> for Download {
+    fn respond_to(self, _: &'r Request<'_>) -> response::Result<'static> {
+        let mut response = Response::build();
+        response.raw_header("Accept-Ranges", "bytes");
+        match self.range {
+            None => {
+                response.sized_body(self.len as usize, self.file);
+            }
+            Some(Some((start, end))) => {
+                response
+                    .status(Status::PartialContent)
+                    .raw_header("Content-Range", format!("bytes {start}-{end}/{}", self.len))
+                    .streamed_body(self.file.take(end - start + 1));
+            }
+            Some(None) => {
+                return Response::build()
+                    .status(Status::RangeNotSatisfiable)
+                    .raw_header("Content-Range", format!("bytes */{}", self.len))
+                    .ok();
+            }
+        }
+        if let Some(content_type) = Path::new(&self.name)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(ContentType::from_extension)
+        {
+            response.header(content_type);
+        }
+        response
+            .raw_header(
+                "Content-Disposition",
+                format!("attachment; filename=\"{}\"", self.name),
+            )
+            .ok()
+    }
+}
@@ -0,0 +1,116 @@
+//! Annuity loans: the planned schedule and the position after actual payments.
+//!
+//! Interest accrues monthly at a twelfth of the yearly rate and is rounded to
+//! kopecks each month, the way banks print their schedules.
+
+use chrono::{Datelike, Months, NaiveDate};
+use serde::Serialize;
+
+use crate::models::{Loan, LoanPayment};
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Installment {
+    pub number: i64,
+    pub due_on: String,
+    pub payment_cents: i64,
+    pub interest_cents: i64,
+    pub principal_cents: i64,
+    /// Left to repay after this installment.
+    pub balance_cents: i64,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct LoanStatus {
+    pub paid_cents: i64,
+    pub principal_paid_cents: i64,
+    pub interest_paid_cents: i64,
+    pub remaining_cents: i64,
+}
+
+fn monthly_rate(loan: &Loan) -> f64 {
+    loan.annual_rate / 100.0 / 12.0
+}
+
+fn interest(balance_cents: i64, rate: f64) -> i64 {
+    (balance_cents as f64 * rate).round() as i64
+}
+
+/// The equal monthly payment that repays the loan over its term.
+pub fn monthly_payment(loan: &Loan) -> i64 {
+    let rate = monthly_rate(loan);
+    let principal = loan.principal_cents as f64;
+    let term = loan.term_months as i32;
+    if rate == 0.0 {
+        return (principal / term as f64).ceil() as i64;
+    }
+    (principal * rate / (1.0 - (1.0 + rate).powi(-term))).round() as i64
+}
+
+/// Due date of installment `index` (0-based): the payment day of the month,
+/// or the month's last day when it is shorter.
+fn due_on(loan: &Loan, index: i64) -> Option<NaiveDate> {
+    let first = NaiveDate::parse_from_str(&format!("{}-01", loan.first_month), "%Y-%m-%d").ok()?;
+    let month = first.checked_add_months(Months::new(u32::try_from(index).ok()?))?;
+    let day = u32::try_from(loan.payment_day).ok()?;
+    (1..=day).rev().find_map(|day| month.with_day(day))
+}
+
+/// Planned installments; the last one settles whatever rounding left over.
+pub fn schedule(loan: &Loan) -> Vec<Installment> {
+    let rate = monthly_rate(loan);
+    let payment = monthly_payment(loan);
+    let mut balance = loan.principal_cents;
+    let mut out = Vec::new();
+    for index in 0..loan.term_months {
+        if balance <= 0 {
+            break;
+        }
+        let interest_cents = interest(balance, rate);
+        let principal_cents = if index + 1 == loan.term_months {
+            balance
+        } else {
+            (payment - interest_cents).clamp(0, balance)
+        };
+        balance -= principal_cents;
+        out.push(Installment {
+            number: index + 1,
+            due_on: due_on(loan, index)
+                .map(|date| date.format("%Y-%m-%d").to_string())
+                .unwrap_or_default(),
+            payment_cents: principal_cents + interest_cents,
+            interest_cents,
+            principal_cents,
+            balance_cents: balance,
+        });
+    }
+    out
+}
+
+/// Where the loan stands after `payments`, oldest first. Each payment first
+/// covers a month of interest on the balance left, the rest repays principal;
+/// paying more than is owed doesn't make the balance negative.
+pub fn status(loan: &Loan, payments: &[LoanPayment]) -> LoanStatus {
+    let rate = monthly_rate(loan);
+    let mut status = LoanStatus {
+        remaining_cents: loan.principal_cents,
+        ..Default::default()
+    };
+    for payment in payments {
+        let interest_cents = interest(status.remaining_cents, rate).min(payment.amount_cents);
+        let principal_cents = (payment.amount_cents - interest_cents).min(status.remaining_cents);
+        status.paid_cents += payment.amount_cents;
+        status.interest_paid_cents += interest_cents;
+        status.principal_paid_cents += principal_cents;
+        status.remaining_cents -= principal_cents;
+    }
+    status
+}
+
+/// The installment due next: the first one `payments` haven't covered, none
+/// once the loan is repaid.
+pub fn next_installment(loan: &Loan, payments: &[LoanPayment]) -> Option<Installment> {
+    if status(loan, payments).remaining_cents <= 0 {
+        return None;
+    }
+    schedule(loan).into_iter().nth(payments.len())
+}
@@ -0,0 +1,183 @@
+//! Email about a login from a device the account hasn't been used from.
+//!
+//! Every successful login records the browser's user agent. When it is new for
+//! the account, and the account has an email address and SMTP is configured,
+//! the owner gets a letter with a single-use link that signs out every session
+//! and API client. The very first device of an account is not reported.
+
+use chrono::{DateTime, Utc};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use rocket::request::{FromRequest, Outcome, Request};
+use rusqlite::{Connection, Result};
+use uuid::Uuid;
+
+use crate::api_sessions;
+use crate::db;
+
+/// How long the link in a letter keeps working.
+pub const LINK_TTL: chrono::Duration = chrono::Duration::days(7);
+
+/// The browser's `User-Agent`, empty when it sent none.
+pub struct UserAgent(pub String);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for UserAgent {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, ()> {
+        let agent = request.headers().get_one("User-Agent").unwrap_or("");
+        Outcome::Success(UserAgent(agent.trim().to_string()))
+    }
+}
+
+/// The letter to send; the link token is stored already.
+#[derive(Debug)]
+pub struct Alert {
+    pub to: String,
+    pub username: String,
+    pub user_agent: String,
+    pub token: String,
+}
+
+impl Alert {
+    pub fn revoke_path(&self) -> String {
+        format!("/login/revoke/{}", self.token)
+    }
+
+    fn body(&self, public_url: &str) -> String {
+        let device = if self.user_agent.is_empty() {
+            "неизвестное устройство"
+        } else {
+            &self.user_agent
+        };
+        format!(
+            "В аккаунт {} выполнен вход с нового устройства:\n{device}\n\n\
+             Если это были не вы, перейдите по ссылке — все сессии будут завершены:\n{public_url}{}\n\n\
+             Ссылка действует {} дней. После этого смените пароль.",
+            self.username,
+            self.revoke_path(),
+            LINK_TTL.num_days(),
+        )
+    }
+}
+
+/// SMTP settings shared with the accountant export (`LUMEN_SMTP_HOST`,
+/// `LUMEN_SMTP_USER`, `LUMEN_SMTP_PASSWORD`, `LUMEN_SMTP_FROM`) and
+/// `LUMEN_PUBLIC_URL`, the address the app is opened at, for the link.
+#[derive(Clone)]
+pub struct Mailer {
+    relay: String,
+    username: String,
+    password: String,
+    from: String,
+    public_url: String,
+}
+
+impl Mailer {
+    pub fn from_env() -> Option<Self> {
+        let var = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .filter(|value| !value.trim().is_empty())
+        };
+        Some(Mailer {
+            relay: var("LUMEN_SMTP_HOST")?,
+            username: var("LUMEN_SMTP_USER")?,
+            password: var("LUMEN_SMTP_PASSWORD")?,
+            from: var("LUMEN_SMTP_FROM")?,
+            public_url: var("LUMEN_PUBLIC_URL")?.trim_end_matches('/').to_string(),
+        })
+    }
+
+    /// Sends a plain-text letter; also used for the end-of-day summary.
+    pub fn send_letter(
+        &self,
+        to: &str,
+        subject: &str,
+        body: String,
+    ) -> std::result::Result<(), String> {
+        let message = Message::builder()
+            .from(
+                self.from
+                    .parse()
+                    .map_err(|_| "некорректный адрес отправителя")?,
+            )
+            .to(to.parse().map_err(|_| "некорректный адрес получателя")?)
+            .subject(subject)
+            .body(body)
+            .map_err(|err| err.to_string())?;
+        SmtpTransport::relay(&self.relay)
+            .map_err(|err| err.to_string())?
+            .credentials(Credentials::new(
+                self.username.clone(),
+                self.password.clone(),
+            ))
+            .build()
+            .send(&message)
+            .map_err(|err| err.to_string())?;
+        Ok(())
+    }
+
+    fn send(&self, alert: &Alert) -> std::result::Result<(), String> {
+        self.send_letter(
+            &alert.to,
+            "Вход с нового устройства",
+            alert.body(&self.public_url),
+        )
+    }
+
+    /// Sends off the request thread, so a slow SMTP server doesn't hold up
+    /// the login; a failed letter is not retried.
+    pub fn send_in_background(&self, alert: Alert) {
+        let mailer = self.clone();
+        std::thread::spawn(move || {
+            let _ = mailer.send(&alert);
+        });
+    }
+}
+
+/// Records the login's device and, when it is new for an account that has an
+/// email address and `mail` is on, stores the link for the letter to send.
+pub fn check(
+    conn: &Connection,
+    mail: bool,
+    user_id: i64,
+    username: &str,
+    user_agent: &str,
+    now: DateTime<Utc>,
+) -> Result<Option<Alert>> {
+    let seen_before = db::known_device_count(conn, user_id)? > 0;
+    let at = api_sessions::timestamp(now);
+    let new_device = db::record_device(conn, user_id, user_agent, &at)?;
+    if !(new_device && seen_before && mail) {
+        return Ok(None);
+    }
+    let Some(to) = db::user_email(conn, user_id)? else {
+        return Ok(None);
+    };
+    let token = Uuid::new_v4().simple().to_string();
+    db::insert_login_alert(conn, &token, user_id, user_agent, &at)?;
+    Ok(Some(Alert {
+        to,
+        username: username.to_string(),
+        user_agent: user_agent.to_string(),
+        token,
+    }))
+}
+
+/// Follows a letter's link: signs the account out everywhere and drops its
+/// API tokens. Returns whose it was, or `None` for a used, expired or unknown
+/// link.
+pub fn revoke(conn: &Connection, token: &str, now: DateTime<Utc>) -> Result<Option<i64>> {
+    let not_before = api_sessions::timestamp(now - LINK_TTL);
+    let at = api_sessions::timestamp(now);
+    let Some(user_id) = db::use_login_alert(conn, token, &not_before, &at)? else {
+        return Ok(None);
+    };
+    db::delete_sessions_for_user(conn, user_id)?;
+    db::delete_remember_tokens_for_user(conn, user_id)?;
+    db::revoke_api_sessions_for_user(conn, user_id, &at)?;
+    db::delete_api_tokens_for_user(conn, user_id)?;
+    Ok(Some(user_id))
+}
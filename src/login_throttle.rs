@@ -0,0 +1,95 @@
+//! Slowing down password guessing on the login form.
+//!
+//! Failed logins are counted per client address and per username. The address
+//! is the peer's unless it is a trusted proxy (`allowlist::client_addr`), so
+//! a forged `X-Real-IP` doesn't start a fresh count. The first
+//! [`FREE_ATTEMPTS`] cost nothing; after that each failure doubles the wait
+//! before the next attempt is even checked, and [`LOCKOUT_AFTER`] failures
+//! lock the address or name out for [`LOCKOUT`]. A count is forgotten
+//! [`FORGET_AFTER`] the last failure and cleared by a successful login.
+//!
+//! Counts live in the database, so a restart doesn't reset them.
+
+use std::net::IpAddr;
+
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{Connection, Result};
+
+use crate::api_sessions;
+use crate::db;
+
+pub const FREE_ATTEMPTS: i64 = 3;
+pub const LOCKOUT_AFTER: i64 = 10;
+pub const LOCKOUT: Duration = Duration::minutes(15);
+pub const FORGET_AFTER: Duration = Duration::hours(1);
+
+/// How long to wait after the `failures`-th failure in a row: 1 s after the
+/// first one past the free attempts, doubling up to the lockout.
+pub fn delay(failures: i64) -> Duration {
+    if failures <= FREE_ATTEMPTS {
+        Duration::zero()
+    } else if failures >= LOCKOUT_AFTER {
+        LOCKOUT
+    } else {
+        Duration::seconds(1 << (failures - FREE_ATTEMPTS - 1))
+    }
+}
+
+/// What a client's failures are counted under, when its address is known.
+pub fn address_key(client_ip: Option<IpAddr>) -> Option<String> {
+    client_ip.map(|ip| format!("ip:{ip}"))
+}
+
+/// What failures are counted under: the client's address and the username
+/// as typed, ignoring case.
+pub fn keys(client_ip: Option<IpAddr>, username: &str) -> Vec<String> {
+    address_key(client_ip)
+        .into_iter()
+        .chain([format!("user:{}", username.trim().to_lowercase())])
+        .collect()
+}
+
+/// The longest wait any of `keys` is under at `now`.
+pub fn wait(conn: &Connection, keys: &[String], now: DateTime<Utc>) -> Result<Option<Duration>> {
+    let mut longest = None;
+    for key in keys {
+        let Some((failures, last_failed_at)) = db::login_failures(conn, key)? else {
+            continue;
+        };
+        let Ok(last) = DateTime::parse_from_rfc3339(&last_failed_at) else {
+            continue;
+        };
+        let left = last.with_timezone(&Utc) + delay(failures) - now;
+        if left > Duration::zero() && longest.is_none_or(|longest| left > longest) {
+            longest = Some(left);
+        }
+    }
+    Ok(longest)
+}
+
+pub fn record_failure(conn: &Connection, keys: &[String], now: DateTime<Utc>) -> Result<()> {
+    let at = api_sessions::timestamp(now);
+    let forget_before = api_sessions::timestamp(now - FORGET_AFTER);
+    for key in keys {
+        db::record_login_failure(conn, key, &at, &forget_before)?;
+    }
+    Ok(())
+}
+
+pub fn clear(conn: &Connection, keys: &[String]) -> Result<()> {
+    for key in keys {
+        db::clear_login_failures(conn, key)?;
+    }
+    Ok(())
+}
+
+/// The login page's notice for a wait, rounded up to whole seconds or minutes.
+pub fn wait_message(wait: Duration) -> String {
+    let seconds = (wait.num_milliseconds() + 999) / 1000;
+    let left = if seconds < 60 {
+        format!("{seconds} с")
+    } else {
+        format!("{} мин", (seconds + 59) / 60)
+    };
+    format!("Слишком много неудачных попыток входа. Повторите через {left}")
+}
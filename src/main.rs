@@ -1,23 +1,93 @@
 #[macro_use]
 extern crate rocket;
 
+mod account_deletion;
+mod account_flow;
+mod accountant;
+mod allowlist;
+mod api_sessions;
+mod api_tokens;
+mod assets;
+mod backup;
+mod bulk;
+mod cash;
+mod category_kind;
+mod compression;
+mod csrf;
+mod daily_summary;
+mod dates;
+mod digest;
+mod disputes;
 mod db;
+mod effective_months;
+mod error;
+mod export;
+mod fiscal_receipts;
+mod fx;
+mod heic;
+mod hooks;
+mod import;
+mod indexation;
+mod jobs;
+mod login_alert;
+mod login_throttle;
+mod loans;
+mod meters;
 mod models;
+mod money;
+mod month_comparison;
+mod months;
+mod networth;
+mod notifications;
+mod password_strength;
+mod pages;
+mod photo_metadata;
+mod pdf;
+mod query;
+mod query_inspector;
+mod receipt_inbox;
+mod receipt_items;
+mod receipt_ocr;
+mod receipt_files;
+mod receipt_shares;
+mod receipt_storage;
+mod receipts;
+mod remember;
+mod report_snapshots;
+mod rules;
+mod security_headers;
+mod sessions;
+mod statement;
+mod suggestions;
+mod telegram;
+mod thumbnails;
+mod trash;
+mod user_merge;
+mod validation;
+#[cfg(test)]
+mod tests;
 
-use std::collections::BTreeSet;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
 
-use chrono::Local;
+use chrono::{Datelike, Local, Months, NaiveDate};
 use db::DbPool;
-use models::{BudgetRecord, DashboardBudget, ReportCategory, ReportMonth, TransactionRecord, User};
+use error::AppError;
+use money::{format_money, parse_amount_to_cents, Rounding};
+use models::{
+    Account, BudgetRecord, Category, CategoryDuplicate, DashboardBudget, Holding, Job, Loan, LoanPayment, NetWorthMonth,
+    NewInboundHook, NewLoan, NewNotification, NewReceiptItem, NewRule, NewTransaction, ReceiptCandidate, ReportCategory, ReportDay, ReportMonth, ReportPayee, ReportTag, TransactionRecord, User,
+};
+use query::{Sort, TransactionQuery};
+use validation::FormState;
 use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
-use password_hash::SaltString;
-use rand_core::OsRng;
 use rusqlite::params;
 use rocket::form::Form;
-use rocket::fs::{FileServer, TempFile};
-use rocket::http::{Cookie, CookieJar, SameSite};
-use rocket::response::Redirect;
+use rocket::fairing::AdHoc;
+use rocket::fs::TempFile;
+use rocket::http::{Cookie, CookieJar};
+use rocket::request::FlashMessage;
+use rocket::response::{Flash, Redirect};
 use rocket::serde::Serialize;
 use rocket::State;
 use rocket_dyn_templates::Template;
@@ -29,6 +99,33 @@ const MAX_SESSIONS: i64 = 5;
 struct CategoryForm {
     name: String,
     kind: String,
+    parent_id: Option<i64>,
+}
+
+#[derive(FromForm)]
+struct CategoryUpdateForm {
+    name: String,
+    parent_id: Option<i64>,
+    /// Empty or missing clears it.
+    default_amount: Option<String>,
+    allow_receipt: bool,
+}
+
+#[derive(FromForm)]
+struct CategoryDeleteForm {
+    reassign_to: Option<i64>,
+}
+
+#[derive(FromForm)]
+struct CategoryMergeForm {
+    keep: i64,
+}
+
+#[derive(FromForm)]
+struct CategoryKindForm {
+    /// `keep`, `rekind` or `reassign`.
+    history: String,
+    reassign_to: Option<i64>,
 }
 
 #[derive(FromForm)]
@@ -36,678 +133,5661 @@ struct TransactionForm<'r> {
     kind: String,
     amount: String,
     category_id: Option<i64>,
+    account_id: Option<i64>,
     occurred_on: String,
     note: Option<String>,
+    payee: Option<String>,
+    tags: Option<String>,
     receipt: Option<TempFile<'r>>,
+    /// `YYYY-MM` to count it toward instead of the month of its date.
+    effective_month: Option<String>,
+    /// The category suggestion the user clicked, see `suggestions`.
+    suggested_category_id: Option<i64>,
 }
 
+/// The new-transaction form sent back by GET to fill in a category's defaults
+/// without JavaScript; every field may be missing or empty.
 #[derive(FromForm)]
-struct BudgetForm {
-    category_id: i64,
-    month: String,
-    amount: String,
+struct TransactionDraft {
+    kind: Option<String>,
+    amount: Option<String>,
+    category_id: Option<String>,
+    account_id: Option<String>,
+    occurred_on: Option<String>,
+    note: Option<String>,
+    payee: Option<String>,
+    tags: Option<String>,
 }
 
-#[derive(FromForm)]
-struct LoginForm {
-    username: String,
-    password: String,
+/// Query of the transactions page; every field may be missing or empty.
+#[derive(Clone, Default, FromForm, UriDisplayQuery)]
+struct TransactionFilter {
+    month: Option<String>,
+    tag: Option<String>,
+    q: Option<String>,
+    kind: Option<String>,
+    category: Option<String>,
+    min: Option<String>,
+    max: Option<String>,
+    /// Any value shows only starred transactions.
+    starred: Option<String>,
+    /// `Sort` param; with `q` and no sort, best matches come first.
+    sort: Option<String>,
+    /// From 1; past the last page shows the last one.
+    page: Option<String>,
 }
 
+const TRANSACTIONS_PER_PAGE: i64 = 100;
+
 #[derive(FromForm)]
-struct SetupForm {
-    username: String,
-    password: String,
-    confirm_password: String,
+struct RuleForm {
+    pattern: String,
+    min_amount: String,
+    max_amount: String,
+    category_id: Option<i64>,
+    /// Empty when the category decides.
+    kind: String,
+    tags: String,
 }
 
-#[derive(FromForm)]
-struct ChangePasswordForm {
-    current_password: String,
-    new_password: String,
-    confirm_password: String,
+/// The transactions the month-shifting tool picks and where it moves them;
+/// empty fields don't narrow the pick.
+#[derive(FromForm, Default)]
+struct EffectiveMonthForm {
+    from: Option<String>,
+    to: Option<String>,
+    kind: Option<String>,
+    category_id: Option<i64>,
+    from_day: Option<String>,
+    shift: Option<String>,
 }
 
-#[derive(Serialize)]
-struct TransactionView {
-    id: i64,
-    kind: String,
-    amount: String,
+#[derive(FromForm)]
+struct DateForm {
     occurred_on: String,
-    note: Option<String>,
-    category_name: Option<String>,
-    receipt_url: Option<String>,
 }
 
-#[derive(Serialize)]
-struct BudgetView {
-    id: i64,
-    category_name: String,
-    month: String,
+#[derive(FromForm)]
+struct TransferForm {
+    from_account_id: Option<i64>,
+    to_account_id: Option<i64>,
     amount: String,
-    spent: String,
-    remaining: String,
-    percent: i64,
+    occurred_on: String,
+    note: Option<String>,
 }
 
-#[derive(Serialize)]
-struct DashboardBudgetView {
-    category_name: String,
-    budget: String,
-    spent: String,
-    remaining: String,
-    percent: i64,
+#[derive(FromForm)]
+struct AccountForm {
+    name: String,
+    kind: String,
+    currency: String,
+    opening_balance: String,
 }
 
-#[derive(Serialize)]
-struct ReportMonthView {
+#[derive(FromForm)]
+struct ArchiveForm {
     month: String,
-    income: String,
-    expense: String,
-    net: String,
 }
 
-#[derive(Serialize)]
-struct ReportCategoryView {
-    category_name: String,
-    expense: String,
+#[derive(FromForm)]
+struct LoanForm {
+    name: String,
+    principal: String,
+    annual_rate: String,
+    term_months: i64,
+    first_month: String,
+    payment_day: i64,
 }
 
-fn format_money(cents: i64) -> String {
-    let sign = if cents < 0 { "-" } else { "" };
-    let abs = cents.abs();
-    let whole = abs / 100;
-    let frac = abs % 100;
-    format!("{sign}{whole}.{frac:02}")
+#[derive(FromForm)]
+struct MeterForm {
+    name: String,
+    kind: String,
 }
 
-fn parse_amount_to_cents(input: &str) -> Option<i64> {
-    let mut s = input.trim().to_string();
-    if s.is_empty() {
-        return None;
-    }
-    if s.starts_with('-') {
-        return None;
-    }
-    s = s.replace(',', ".");
-    let mut parts = s.split('.');
-    let whole_str = parts.next()?;
-    let frac_str = parts.next();
-    if parts.next().is_some() {
-        return None;
-    }
-    let whole: i64 = whole_str.parse().ok()?;
-    let frac = match frac_str {
-        None => 0,
-        Some(frac) => {
-            if frac.len() > 2 {
-                return None;
-            }
-            let mut padded = frac.to_string();
-            while padded.len() < 2 {
-                padded.push('0');
-            }
-            padded.parse::<i64>().ok()?
-        }
-    };
-    Some(whole * 100 + frac)
+#[derive(FromForm)]
+struct TariffForm {
+    kind: String,
+    /// Price of a unit.
+    rate: String,
+    effective_from: String,
 }
 
-fn today_ymd() -> String {
-    Local::now().date_naive().format("%Y-%m-%d").to_string()
+#[derive(FromForm)]
+struct MeterReadingForm {
+    /// `YYYY-MM`.
+    month: String,
+    value: String,
 }
 
-fn current_month() -> String {
-    Local::now().date_naive().format("%Y-%m").to_string()
+#[derive(FromForm)]
+struct MeterExpenseForm {
+    /// Unlinks the expense when missing.
+    transaction_id: Option<i64>,
 }
 
-fn selected_month(month: Option<String>) -> String {
-    month
-        .map(|value| value.trim().to_string())
-        .filter(|value| !value.is_empty())
-        .unwrap_or_else(current_month)
+#[derive(FromForm)]
+struct LoanPaymentForm {
+    amount: String,
+    occurred_on: String,
+    account_id: Option<i64>,
+    category_id: Option<i64>,
 }
 
-fn is_receipt_category(name: &str) -> bool {
-    name.trim().to_lowercase() == "жкх"
+#[derive(FromForm)]
+struct HoldingForm {
+    name: String,
+    kind: String,
+    amount: String,
+    valued_on: String,
 }
 
-fn receipts_dir() -> PathBuf {
-    let mut dir = PathBuf::from("data");
-    dir.push("receipts");
-    dir
+#[derive(FromForm)]
+struct ValuationForm {
+    amount: String,
+    valued_on: String,
 }
 
-fn allowed_extension(name: &str) -> Option<String> {
-    let ext = Path::new(name).extension()?.to_str()?.to_lowercase();
-    match ext.as_str() {
-        "jpg" | "jpeg" | "png" | "webp" | "heic" => Some(ext),
-        _ => None,
-    }
+#[derive(FromForm)]
+struct ExchangeRateForm {
+    currency: String,
+    rate_date: String,
+    rate: String,
 }
 
-async fn persist_receipt(
-    receipt: Option<TempFile<'_>>,
-    category_name: Option<&str>,
-    kind: &str,
-) -> Result<Option<String>, rocket::http::Status> {
-    let Some(mut receipt) = receipt else {
-        return Ok(None);
-    };
-    let Some(category_name) = category_name else {
-        return Ok(None);
-    };
-    if kind != "expense" || !is_receipt_category(category_name) {
-        return Ok(None);
-    }
-
-    let ext = receipt
-        .name()
-        .and_then(allowed_extension)
-        .unwrap_or_else(|| "jpg".to_string());
-    let filename = format!("receipt-{}.{}", Local::now().timestamp_millis(), ext);
-    let dir = receipts_dir();
-    std::fs::create_dir_all(&dir).map_err(|_| rocket::http::Status::InternalServerError)?;
-    let path = dir.join(&filename);
-    receipt
-        .persist_to(&path)
-        .await
-        .map_err(|_| rocket::http::Status::InternalServerError)?;
-    Ok(Some(filename))
+#[derive(FromForm)]
+struct ExchangeRateKeyForm {
+    currency: String,
+    rate_date: String,
 }
 
-fn available_months(conn: &rusqlite::Connection) -> Vec<String> {
-    let mut set = BTreeSet::new();
-    for month in db::list_months(conn, 24).unwrap_or_default() {
-        set.insert(month);
-    }
-    for month in db::list_budget_months(conn, 24).unwrap_or_default() {
-        set.insert(month);
-    }
-    set.insert(current_month());
-    set.into_iter().rev().collect()
+#[derive(FromForm)]
+struct RevaluationForm {
+    month: String,
+    rate: String,
 }
 
-fn hash_password(password: &str) -> Result<String, rocket::http::Status> {
-    let salt = SaltString::generate(&mut OsRng);
-    let argon2 = Argon2::default();
-    let hash = argon2
-        .hash_password(password.as_bytes(), &salt)
-        .map_err(|_| rocket::http::Status::InternalServerError)?;
-    Ok(hash.to_string())
+#[derive(FromForm)]
+struct InboundHookForm {
+    name: String,
+    kind: String,
+    amount_field: String,
+    date_field: String,
+    note_field: String,
+    category_field: String,
+    category_id: Option<i64>,
+    account_id: Option<i64>,
 }
 
-fn verify_password(hash: &str, password: &str) -> bool {
-    let parsed = match PasswordHash::new(hash) {
-        Ok(parsed) => parsed,
-        Err(_) => return false,
-    };
-    Argon2::default()
-        .verify_password(password.as_bytes(), &parsed)
-        .is_ok()
+#[derive(FromForm)]
+struct ReceiptShareForm {
+    /// The receipt's file name.
+    name: String,
+    /// One of `receipt_shares::DAYS`; the default when missing.
+    days: Option<i64>,
 }
 
-fn require_user(pool: &State<DbPool>, cookies: &CookieJar<'_>) -> Result<User, Redirect> {
-    let conn = pool.get().map_err(|_| Redirect::to("/login"))?;
-    if !db::has_users(&conn).unwrap_or(false) {
-        return Err(Redirect::to("/setup"));
-    }
-    if let Some(cookie) = cookies.get("session") {
-        if let Ok(Some(user)) = db::user_by_session(&conn, cookie.value()) {
-            return Ok(user);
-        }
-    }
-    Err(Redirect::to("/login"))
+#[derive(FromForm)]
+struct ApiTokenForm {
+    name: String,
+    scope: String,
 }
 
-fn current_user(pool: &State<DbPool>, cookies: &CookieJar<'_>) -> Option<User> {
-    let conn = pool.get().ok()?;
-    let token = cookies.get("session")?.value().to_string();
-    db::user_by_session(&conn, &token).ok().flatten()
+/// Body of `POST /api/v1/auth/token`.
+#[derive(serde::Deserialize)]
+struct ApiSignIn {
+    username: String,
+    password: String,
 }
 
-fn render_login(error: Option<&str>) -> Template {
-    Template::render(
-        "login",
-        serde_json::json!({
-            "error": error,
-        }),
-    )
+/// Body of `POST /api/v1/auth/refresh` and `/api/v1/auth/revoke`.
+#[derive(serde::Deserialize)]
+struct ApiRefresh {
+    refresh_token: String,
 }
 
-fn render_setup(error: Option<&str>) -> Template {
-    Template::render(
-        "setup",
-        serde_json::json!({
-            "error": error,
-        }),
-    )
+/// Body of `POST /api/v1/transactions`.
+#[derive(serde::Deserialize)]
+struct ApiTransaction {
+    kind: String,
+    amount_cents: i64,
+    category_id: Option<i64>,
+    /// Today when missing.
+    occurred_on: Option<String>,
+    note: Option<String>,
+    payee: Option<String>,
+    account_id: Option<i64>,
+    /// Comma-separated, as in the form.
+    tags: Option<String>,
+    #[serde(default)]
+    starred: bool,
 }
 
-fn render_settings(username: &str, sessions: i64, error: Option<&str>, notice: Option<&str>) -> Template {
-    Template::render(
-        "settings",
-        serde_json::json!({
-            "username": username,
-            "active_sessions": sessions,
-            "error": error,
-            "notice": notice,
-        }),
-    )
+/// Body of `POST /api/v1/transactions/<id>/star`.
+#[derive(serde::Deserialize)]
+struct ApiStar {
+    starred: bool,
 }
 
-#[get("/setup")]
-fn setup(pool: &State<DbPool>, cookies: &CookieJar<'_>) -> Result<Template, Redirect> {
-    let conn = pool.get().map_err(|_| Redirect::to("/login"))?;
-    if db::has_users(&conn).unwrap_or(false) {
-        if current_user(pool, cookies).is_some() {
-            return Err(Redirect::to("/"));
-        }
-        return Err(Redirect::to("/login"));
-    }
-    Ok(render_setup(None))
+#[derive(FromForm)]
+struct StarForm {
+    starred: bool,
 }
 
-#[post("/setup", data = "<form>")]
-fn setup_post(
-    pool: &State<DbPool>,
-    cookies: &CookieJar<'_>,
-    form: Form<SetupForm>,
-) -> Result<Redirect, Template> {
-    let conn = pool.get().map_err(|_| render_setup(Some("Ошибка подключения к базе")))?;
-    if db::has_users(&conn).unwrap_or(false) {
-        return Ok(Redirect::to("/login"));
-    }
-
-    let form = form.into_inner();
-    let username = form.username.trim();
-    if username.is_empty() {
-        return Err(render_setup(Some("Введите логин")));
-    }
-    if form.password.len() < 6 {
-        return Err(render_setup(Some("Пароль должен быть не короче 6 символов")));
-    }
-    if form.password != form.confirm_password {
-        return Err(render_setup(Some("Пароли не совпадают")));
-    }
+#[derive(FromForm)]
+struct WithdrawalForm {
+    from_account_id: Option<i64>,
+    to_account_id: Option<i64>,
+    amount: String,
+    /// The bank's fee, if any.
+    fee: String,
+    occurred_on: String,
+}
 
-    let password_hash = hash_password(&form.password)
-        .map_err(|_| render_setup(Some("Не удалось сохранить пароль")))?;
-    let created_at = Local::now().to_rfc3339();
-    let user_id = db::insert_user(&conn, username, &password_hash, &created_at)
-        .map_err(|_| render_setup(Some("Такой логин уже существует")))?;
+#[derive(FromForm)]
+struct ConvertWithdrawalForm {
+    to_account_id: i64,
+}
 
-    let token = Uuid::new_v4().to_string();
-    db::create_session(&conn, user_id, &token, &created_at)
-        .map_err(|_| render_setup(Some("Не удалось создать сессию")))?;
-    db::prune_sessions(&conn, user_id, MAX_SESSIONS)
-        .map_err(|_| render_setup(Some("Не удалось обновить сессии")))?;
+#[derive(FromForm)]
+struct ReceiptItemForm {
+    name: String,
+    /// One when empty.
+    quantity: String,
+    /// Per unit.
+    price: String,
+}
 
-    let mut cookie = Cookie::new("session", token);
-    cookie.set_path("/");
-    cookie.set_http_only(true);
-    cookie.set_same_site(SameSite::Lax);
-    cookies.add(cookie);
+#[derive(FromForm)]
+struct RefundForm {
+    /// The whole purchase when empty.
+    amount: String,
+    occurred_on: String,
+}
 
-    Ok(Redirect::to("/"))
+#[derive(FromForm)]
+struct RefundMonthForm {
+    refund_month: String,
 }
 
-#[get("/login")]
-fn login(pool: &State<DbPool>, cookies: &CookieJar<'_>) -> Result<Template, Redirect> {
-    let conn = pool.get().map_err(|_| Redirect::to("/login"))?;
-    if !db::has_users(&conn).unwrap_or(false) {
-        return Err(Redirect::to("/setup"));
-    }
-    if current_user(pool, cookies).is_some() {
-        return Err(Redirect::to("/"));
-    }
-    Ok(render_login(None))
+#[derive(FromForm)]
+struct RoundingForm {
+    report_rounding: String,
+}
+
+#[derive(FromForm)]
+struct BudgetForm {
+    category_id: Option<i64>,
+    month: String,
+    amount: String,
+    rollover: bool,
+    /// Applies to every month without its own budget; `month` is then ignored.
+    standing: bool,
+}
+
+#[derive(FromForm)]
+struct BudgetIncreaseForm {
+    /// Percent a year, e.g. `7,5`; empty turns the increase off.
+    percent: String,
+}
+
+#[derive(FromForm)]
+struct BudgetNoteForm {
+    /// Empty removes the note.
+    note: String,
+}
+
+#[derive(FromForm)]
+struct ReportMonthForm {
+    month: String,
+}
+
+#[derive(FromForm)]
+struct BudgetCopyForm {
+    month: String,
+}
+
+#[derive(FromForm)]
+struct ImportUploadForm<'r> {
+    file: TempFile<'r>,
+}
+
+/// Receipt photos for the inbox; the page adds the text of each photo's QR
+/// code, in the same order, when the browser could read it.
+#[derive(FromForm)]
+struct ReceiptUploadForm<'r> {
+    files: Vec<TempFile<'r>>,
+    qr: Vec<String>,
+}
+
+/// The QR code of a fiscal receipt, see `fiscal_receipts`.
+#[derive(FromForm)]
+struct FiscalQrForm<'r> {
+    qr: String,
+    category_id: Option<i64>,
+    account_id: Option<i64>,
+    payee: Option<String>,
+    receipt: Option<TempFile<'r>>,
+}
+
+#[derive(FromForm)]
+struct ReceiptDetailsForm {
+    occurred_on: String,
+    amount: String,
+}
+
+#[derive(FromForm)]
+struct LoginForm {
+    username: String,
+    password: String,
+    /// Stay signed in after the browser closes, see `remember`.
+    remember: bool,
+}
+
+#[derive(FromForm)]
+struct SetupForm {
+    username: String,
+    password: String,
+    confirm_password: String,
+}
+
+#[derive(FromForm)]
+struct ChangePasswordForm {
+    current_password: String,
+    new_password: String,
+    confirm_password: String,
+}
+
+#[derive(FromForm)]
+struct NotificationPreferencesForm {
+    enabled: Vec<String>,
+}
+
+#[derive(FromForm)]
+struct TelegramForm {
+    chat_id: String,
+    /// The Sunday digest, see `digest`.
+    digest: bool,
+}
+
+#[derive(FromForm)]
+struct MergeUsersForm {
+    /// The duplicate, deleted by the merge.
+    from_id: i64,
+    into_id: i64,
+    /// The signed-in user's own, as for deleting an account.
+    password: String,
+}
+
+#[derive(FromForm)]
+struct EmailForm {
+    email: String,
+}
+
+#[derive(FromForm)]
+struct DeleteAccountForm {
+    password: String,
+}
+
+#[derive(FromForm)]
+struct DailySummaryForm {
+    /// Local `HH:MM`; empty turns the summary off.
+    at: String,
+    telegram: bool,
+    email: bool,
+}
+
+#[derive(Serialize)]
+struct TransactionView {
+    id: i64,
+    kind: String,
+    amount: String,
+    occurred_on: String,
+    /// The month it counts toward when that isn't the month of its date.
+    effective_month: Option<String>,
+    note: Option<String>,
+    payee: Option<String>,
+    category_name: Option<String>,
+    account_name: Option<String>,
+    to_account_name: Option<String>,
+    tags: Vec<String>,
+    receipt_name: Option<String>,
+    receipt_url: Option<String>,
+    thumbnail_url: Option<String>,
+    starred: bool,
+    status: String,
+    /// An income that refunds an expense.
+    refund: bool,
+}
+
+#[derive(Serialize)]
+struct CategoryView {
+    id: i64,
+    name: String,
+    kind: String,
+    parent_id: Option<i64>,
+    default_amount: Option<String>,
+    allow_receipt: bool,
+}
+
+#[derive(Serialize)]
+struct AccountView {
+    id: i64,
+    name: String,
+    kind: String,
+    currency: String,
+    opening_balance: String,
+    balance: String,
+    value: Option<String>,
+    rate: Option<f64>,
+}
+
+#[derive(Serialize)]
+struct BudgetView {
+    id: i64,
+    category_name: String,
+    month: String,
+    amount: String,
+    rollover: bool,
+    standing: bool,
+    carried: Option<String>,
+    limit: String,
+    spent: String,
+    remaining: String,
+    percent: i64,
+    note: Option<String>,
+}
+
+#[derive(Serialize)]
+struct StandingBudgetView {
+    id: i64,
+    category_name: String,
+    amount: String,
+    yearly_increase: Option<f64>,
+}
+
+#[derive(Serialize)]
+struct ReceiptUploadView {
+    id: i64,
+    url: String,
+    thumbnail_url: String,
+    original_name: Option<String>,
+    occurred_on: Option<String>,
+    amount: Option<String>,
+    proposals: Vec<ReceiptCandidate>,
+    /// Why there is no new file for this photo, when it was uploaded before.
+    reused: Option<&'static str>,
+    /// The date and total were recognized on the photo and wait to be
+    /// confirmed.
+    recognized: bool,
+    /// The new-expense form filled in with the date and total.
+    new_expense_url: Option<String>,
+}
+
+#[derive(Serialize)]
+struct DisputeView {
+    transaction_id: i64,
+    occurred_on: String,
+    amount: String,
+    category_name: Option<String>,
+    payee: Option<String>,
+    note: Option<String>,
+    status: String,
+    disputed_on: Option<String>,
+    days_open: Option<i64>,
+    refund: Option<String>,
+    refunded_on: Option<String>,
+}
+
+#[derive(Serialize)]
+struct SessionView {
+    id: i64,
+    device: String,
+    user_agent: String,
+    ip: Option<String>,
+    created_at: String,
+    last_used_at: String,
+    current: bool,
+}
+
+#[derive(Serialize)]
+struct CashEnvelopeView {
+    account_id: i64,
+    name: String,
+    balance: String,
+    /// Spent more cash than was recorded going in.
+    overdrawn: bool,
+    withdrawn: String,
+    spent: String,
+    last_withdrawal_on: Option<String>,
+}
+
+#[derive(Serialize)]
+struct WithdrawalCandidateView {
+    transaction_id: i64,
+    occurred_on: String,
+    amount: String,
+    account_name: String,
+    payee: Option<String>,
+    note: Option<String>,
+}
+
+#[derive(Serialize)]
+struct TrashView {
+    table: String,
+    id: i64,
+    what: &'static str,
+    occurred_on: Option<String>,
+    category_name: Option<String>,
+    note: Option<String>,
+    amount: String,
+    deleted_at: String,
+    /// The day the purge takes it.
+    purged_on: String,
+}
+
+#[derive(Serialize)]
+struct DashboardBudgetView {
+    category_name: String,
+    budget: String,
+    carried: Option<String>,
+    spent: String,
+    remaining: String,
+    percent: i64,
+}
+
+#[derive(Serialize)]
+struct LoanView {
+    id: i64,
+    name: String,
+    principal: String,
+    annual_rate: f64,
+    term_months: i64,
+    first_month: String,
+    payment_day: i64,
+    payment: String,
+    paid: String,
+    interest_paid: String,
+    remaining: String,
+    payments_made: usize,
+    next_due: Option<String>,
+}
+
+#[derive(Serialize)]
+struct InstallmentView {
+    number: i64,
+    due_on: String,
+    payment: String,
+    interest: String,
+    principal: String,
+    balance: String,
+    paid: bool,
+}
+
+#[derive(Serialize)]
+struct LoanPaymentView {
+    occurred_on: String,
+    amount: String,
+}
+
+#[derive(Serialize)]
+struct HoldingView {
+    id: i64,
+    name: String,
+    kind: String,
+    amount: Option<String>,
+    valued_on: Option<String>,
+}
+
+#[derive(Serialize)]
+struct NetWorthMonthView {
+    month: String,
+    accounts: String,
+    assets: String,
+    liabilities: String,
+    net_worth: String,
+}
+
+#[derive(Serialize)]
+struct ReportMonthView {
+    month: String,
+    income: String,
+    expense: String,
+    net: String,
+    closed: bool,
+    /// Closed, and the figures have changed since.
+    differs: bool,
+}
+
+#[derive(Serialize)]
+struct ReportAccountView {
+    account: String,
+    income: String,
+    expense: String,
+}
+
+#[derive(Serialize)]
+struct ReportSnapshotView {
+    closed_at: String,
+    differences: Vec<ReportDifferenceView>,
+}
+
+#[derive(Serialize)]
+struct ReportDifferenceView {
+    label: String,
+    snapshot: String,
+    current: String,
+}
+
+#[derive(Serialize)]
+struct ReportComparisonView {
+    months: Vec<String>,
+    rows: Vec<ReportComparisonRowView>,
+    totals: Vec<ReportComparisonCellView>,
+}
+
+#[derive(Serialize)]
+struct ReportComparisonRowView {
+    category_name: String,
+    cells: Vec<ReportComparisonCellView>,
+}
+
+/// A month's expenses and, after the first month, the change from the month
+/// before it.
+#[derive(Serialize)]
+struct ReportComparisonCellView {
+    expense: String,
+    delta: Option<String>,
+    increase: bool,
+}
+
+#[derive(Serialize)]
+struct ReportCategoryView {
+    category_name: String,
+    parent_name: Option<String>,
+    expense: String,
+}
+
+#[derive(Serialize)]
+struct ReportTagView {
+    tag_name: String,
+    expense: String,
+}
+
+#[derive(Serialize)]
+struct ReportPayeeView {
+    payee: String,
+    transactions: i64,
+    expense: String,
+}
+
+#[derive(Serialize)]
+struct HeatmapDayView {
+    date: String,
+    day: u32,
+    expense: String,
+    /// 0 for no spending, up to 4 for the month's heaviest day.
+    level: i64,
+}
+
+fn today_ymd() -> String {
+    Local::now().date_naive().format("%Y-%m-%d").to_string()
+}
+
+/// A stored RFC 3339 timestamp in local time to the minute, or as stored
+/// when it doesn't parse.
+fn local_time(at: &str) -> String {
+    chrono::DateTime::parse_from_rfc3339(at)
+        .map(|at| at.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|_| at.to_string())
+}
+
+fn current_month() -> String {
+    Local::now().date_naive().format("%Y-%m").to_string()
+}
+
+fn selected_month(month: Option<String>) -> String {
+    month
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .unwrap_or_else(current_month)
+}
+
+/// The `YYYY-MM` month before `month`, or `None` when it isn't a month.
+fn previous_month(month: &str) -> Option<String> {
+    let first = NaiveDate::parse_from_str(&format!("{month}-01"), "%Y-%m-%d").ok()?;
+    let previous = first.checked_sub_months(Months::new(1))?;
+    Some(previous.format("%Y-%m").to_string())
+}
+
+/// The last `count` months up to the current one, newest first.
+fn recent_months(count: usize) -> Vec<String> {
+    std::iter::successors(Some(current_month()), |month| previous_month(month))
+        .take(count)
+        .collect()
+}
+
+fn receipts_dir() -> PathBuf {
+    let mut dir = PathBuf::from("data");
+    dir.push("receipts");
+    dir
+}
+
+fn thumbnail_url(receipt: &str) -> String {
+    format!("/receipts/thumbnails/{receipt}")
+}
+
+fn allowed_extension(name: &str) -> Option<String> {
+    let ext = Path::new(name).extension()?.to_str()?.to_lowercase();
+    match ext.as_str() {
+        "jpg" | "jpeg" | "png" | "webp" | "heic" | "heif" => Some(ext),
+        _ => None,
+    }
+}
+
+/// The receipt of the new-transaction form, unless the file field was left
+/// empty.
+fn sent_receipt(receipt: Option<TempFile<'_>>) -> Option<TempFile<'_>> {
+    receipt.filter(|receipt| receipt.len() > 0)
+}
+
+/// Writes an uploaded receipt under its temporary name, as a JPEG when it
+/// came as HEIC and without its metadata unless `metadata` keeps it; the
+/// transaction saved with `receipt_files::save` puts it in place. The same
+/// bytes as a kept receipt give that receipt instead.
+async fn stage_receipt(
+    receipt: Option<TempFile<'_>>,
+    pool: &DbPool,
+    storage: &receipt_storage::Receipts,
+    metadata: &photo_metadata::Policy,
+) -> Result<Option<receipt_files::Staged>, rocket::http::Status> {
+    let Some(mut receipt) = receipt else {
+        return Ok(None);
+    };
+    let stem = format!("receipt-{}", Local::now().timestamp_millis());
+    stage_photo(&mut receipt, stem, pool, storage, metadata).await.map(Some)
+}
+
+/// `stage_receipt` for one of several photos sent together, named `stem`
+/// and its extension.
+async fn stage_photo(
+    receipt: &mut TempFile<'_>,
+    stem: String,
+    pool: &DbPool,
+    storage: &receipt_storage::Receipts,
+    metadata: &photo_metadata::Policy,
+) -> Result<receipt_files::Staged, rocket::http::Status> {
+    // `name()` drops the extension, which is all that is taken from the raw one.
+    let ext = receipt
+        .raw_name()
+        .and_then(|name| allowed_extension(name.dangerous_unsafe_unsanitized_raw().as_str()))
+        .unwrap_or_else(|| "jpg".to_string());
+    let filename = format!("{stem}.{ext}");
+    let dir = receipts_dir();
+    let staged = receipt_files::stage(receipt, &dir, filename)
+        .await
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    if let Some(existing) = earlier_receipt(pool, storage, staged.sha256()).await? {
+        staged.discard();
+        return Ok(receipt_files::Staged::existing(&dir, existing));
+    }
+    if heic::is_heic(staged.name()) {
+        let jpeg = staged.renamed(heic::jpeg_name(staged.name()));
+        // A converted photo carries no metadata.
+        if heic::convert(staged.temp_path(), jpeg.temp_path()).await {
+            return Ok(jpeg);
+        }
+    }
+    photo_metadata::strip_in_background(metadata, staged.temp_path()).await;
+    Ok(staged)
+}
+
+/// The kept receipt in `storage` that was saved for an upload hashed `sha256`.
+async fn earlier_receipt(
+    pool: &DbPool,
+    storage: &receipt_storage::Receipts,
+    sha256: Option<&str>,
+) -> Result<Option<String>, rocket::http::Status> {
+    let Some(sha256) = sha256 else {
+        return Ok(None);
+    };
+    let name = {
+        let conn = pool.get().map_err(|_| rocket::http::Status::ServiceUnavailable)?;
+        db::receipt_with_hash(&conn, sha256)
+            .map_err(|_| rocket::http::Status::InternalServerError)?
+    };
+    let Some(name) = name else {
+        return Ok(None);
+    };
+    let storage = storage.clone();
+    let found = rocket::tokio::task::spawn_blocking(move || {
+        storage.exists(&name).map(|exists| exists.then_some(name))
+    })
+    .await;
+    match found {
+        Ok(Ok(name)) => Ok(name),
+        _ => Err(rocket::http::Status::InternalServerError),
+    }
+}
+
+/// Makes the thumbnail of the finished receipt `name` and hands both to
+/// `storage`. A file that couldn't be sent stays in the receipts directory,
+/// where every storage looks first.
+async fn store_receipt(storage: &receipt_storage::Receipts, name: String) {
+    thumbnails::create_in_background(receipts_dir(), name.clone()).await;
+    let storage = storage.clone();
+    let _ = rocket::tokio::task::spawn_blocking(move || {
+        storage.put(&thumbnails::name(&name))?;
+        storage.put(&name)
+    })
+    .await;
+}
+
+fn month_nav(
+    conn: &rusqlite::Connection,
+    path: &'static str,
+    month: &str,
+) -> Result<months::MonthNav, rocket::http::Status> {
+    months::navigator(conn, path, month, Local::now().date_naive())
+        .map_err(|_| rocket::http::Status::InternalServerError)
+}
+
+fn hash_password(password: &str) -> Result<String, rocket::http::Status> {
+    let argon2 = Argon2::default();
+    let hash = argon2
+        .hash_password(password.as_bytes())
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(hash.to_string())
+}
+
+fn verify_password(hash: &str, password: &str) -> bool {
+    let parsed = match PasswordHash::new(hash) {
+        Ok(parsed) => parsed,
+        Err(_) => return false,
+    };
+    Argon2::default()
+        .verify_password(password.as_bytes(), &parsed)
+        .is_ok()
+}
+
+/// The account `password` opens, given what was found under the typed name.
+/// An unknown name is checked against a throwaway hash, so it takes as long
+/// as a wrong password and the timing doesn't tell which names exist.
+fn verify_login(creds: Option<(i64, String)>, password: &str) -> Option<i64> {
+    static UNKNOWN_USER_HASH: OnceLock<String> = OnceLock::new();
+    match creds {
+        Some((user_id, hash)) => verify_password(&hash, password).then_some(user_id),
+        None => {
+            let hash = UNKNOWN_USER_HASH
+                .get_or_init(|| hash_password(&Uuid::new_v4().to_string()).unwrap_or_default());
+            verify_password(hash, password);
+            None
+        }
+    }
+}
+
+fn require_user(pool: &State<DbPool>, cookies: &CookieJar<'_>) -> Result<User, AppError> {
+    let conn = pool.get()?;
+    if !db::has_users(&conn).unwrap_or(false) {
+        return Err(Redirect::to("/setup").into());
+    }
+    if let Some(token) = sessions::token(cookies)
+        && let Ok(Some(user)) = sessions::user(&conn, &token, chrono::Utc::now())
+    {
+        return Ok(user);
+    }
+    Err(Redirect::to("/login").into())
+}
+
+fn current_user(pool: &State<DbPool>, cookies: &CookieJar<'_>) -> Option<User> {
+    let conn = pool.get().ok()?;
+    let token = sessions::token(cookies)?;
+    sessions::user(&conn, &token, chrono::Utc::now()).ok().flatten()
+}
+
+fn render_login(error: Option<&str>) -> Template {
+    Template::render(
+        "login",
+        serde_json::json!({
+            "error": error,
+        }),
+    )
+}
+
+fn render_setup(error: Option<&str>) -> Template {
+    Template::render(
+        "setup",
+        serde_json::json!({
+            "error": error,
+        }),
+    )
+}
+
+fn render_settings(
+    conn: &rusqlite::Connection,
+    user: &User,
+    cookies: &CookieJar<'_>,
+    error: Option<&str>,
+    notice: Option<&str>,
+) -> Template {
+    let current = sessions::token(cookies).unwrap_or_default();
+    let sessions = sessions::list(conn, user.id, &current, chrono::Utc::now())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|session| SessionView {
+            id: session.id,
+            device: sessions::device(&session.user_agent),
+            user_agent: session.user_agent,
+            ip: session.ip,
+            created_at: local_time(&session.created_at),
+            last_used_at: local_time(&session.last_used_at),
+            current: session.current,
+        })
+        .collect::<Vec<_>>();
+    let now = api_sessions::timestamp(chrono::Utc::now());
+    let api_clients = db::api_session_count(conn, user.id, &now).unwrap_or(0);
+    let notification_prefs = notifications::preference_matrix(conn, user.id).unwrap_or_default();
+    let telegram_chat_id = db::telegram_chat_id(conn, user.id).unwrap_or(None);
+    let telegram_digest = db::telegram_digest(conn, user.id).unwrap_or(false);
+    let email = db::user_email(conn, user.id).unwrap_or(None);
+    let daily_summary = db::daily_summary(conn, user.id).unwrap_or_default();
+    let last_account = db::user_ids(conn).is_ok_and(|ids| ids.len() <= 1);
+    let exchange_rates = db::latest_exchange_rates(conn).unwrap_or_default();
+    let manual_rates = db::manual_exchange_rates(conn).unwrap_or_default();
+    let refund_month = db::refund_month(conn, user.id).unwrap_or_default();
+    let report_rounding = db::report_rounding(conn, user.id).unwrap_or_default();
+    Template::render(
+        "settings",
+        serde_json::json!({
+            "username": user.username,
+            "sessions": sessions,
+            "api_clients": api_clients,
+            "notification_events": notification_prefs,
+            "notification_channels": notifications::CHANNELS
+                .iter()
+                .map(|(_, label)| label)
+                .collect::<Vec<_>>(),
+            "telegram_chat_id": telegram_chat_id,
+            "telegram_digest": telegram_digest,
+            "email": email,
+            "daily_summary": daily_summary,
+            "last_account": last_account,
+            "exchange_rates": exchange_rates,
+            "manual_rates": manual_rates,
+            "refund_month": refund_month.param(),
+            "report_rounding": report_rounding.param(),
+            "today": today_ymd(),
+            "error": error,
+            "notice": notice,
+        }),
+    )
+}
+
+#[get("/setup")]
+fn setup(pool: &State<DbPool>, cookies: &CookieJar<'_>) -> Result<Template, AppError> {
+    let conn = pool.get()?;
+    if db::has_users(&conn).unwrap_or(false) {
+        if current_user(pool, cookies).is_some() {
+            return Err(Redirect::to("/").into());
+        }
+        return Err(Redirect::to("/login").into());
+    }
+    Ok(render_setup(None))
+}
+
+#[post("/setup", data = "<form>")]
+fn setup_post(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    user_agent: login_alert::UserAgent,
+    client: allowlist::ClientAddr,
+    form: Form<SetupForm>,
+) -> Result<Redirect, AppError> {
+    let conn = pool.get().map_err(|_| render_setup(Some("Ошибка подключения к базе")))?;
+    if db::has_users(&conn).unwrap_or(false) {
+        return Ok(Redirect::to("/login"));
+    }
+
+    let form = form.into_inner();
+    let username = form.username.trim();
+    if username.is_empty() {
+        return Err(render_setup(Some("Введите логин")).into());
+    }
+    if form.password.len() < 6 {
+        return Err(render_setup(Some("Пароль должен быть не короче 6 символов")).into());
+    }
+    if form.password != form.confirm_password {
+        return Err(render_setup(Some("Пароли не совпадают")).into());
+    }
+    if let Err(weakness) = password_strength::check(&form.password, username) {
+        return Err(render_setup(Some(&weakness.message())).into());
+    }
+
+    let password_hash = hash_password(&form.password)
+        .map_err(|_| render_setup(Some("Не удалось сохранить пароль")))?;
+    let created_at = Local::now().to_rfc3339();
+    let user_id = db::insert_user(&conn, username, &password_hash, &created_at)
+        .map_err(|_| render_setup(Some("Такой логин уже существует")))?;
+
+    let token = Uuid::new_v4().to_string();
+    sessions::start(&conn, user_id, &token, &user_agent.0, client.0, chrono::Utc::now())
+        .map_err(|_| render_setup(Some("Не удалось создать сессию")))?;
+    db::prune_sessions(&conn, user_id, MAX_SESSIONS)
+        .map_err(|_| render_setup(Some("Не удалось обновить сессии")))?;
+    cookies.add(sessions::cookie(token));
+
+    Ok(Redirect::to("/"))
+}
+
+#[get("/login")]
+fn login(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    client: allowlist::ClientAddr,
+) -> Result<Template, AppError> {
+    let conn = pool.get()?;
+    if !db::has_users(&conn).unwrap_or(false) {
+        return Err(Redirect::to("/setup").into());
+    }
+    if current_user(pool, cookies).is_some() {
+        return Err(Redirect::to("/").into());
+    }
+    // Only the address is known before a name is typed.
+    let keys = login_throttle::address_key(client.0).into_iter().collect::<Vec<_>>();
+    let wait = login_throttle::wait(&conn, &keys, chrono::Utc::now()).unwrap_or(None);
+    Ok(render_login(wait.map(login_throttle::wait_message).as_deref()))
 }
 
 #[post("/login", data = "<form>")]
 fn login_post(
     pool: &State<DbPool>,
     cookies: &CookieJar<'_>,
-    form: Form<LoginForm>,
-) -> Result<Redirect, Template> {
-    let conn = pool.get().map_err(|_| render_login(Some("Ошибка подключения к базе")))?;
-    if !db::has_users(&conn).unwrap_or(false) {
-        return Ok(Redirect::to("/setup"));
-    }
+    mailer: &State<Option<login_alert::Mailer>>,
+    user_agent: login_alert::UserAgent,
+    client: allowlist::ClientAddr,
+    form: Form<LoginForm>,
+) -> Result<Redirect, AppError> {
+    let conn = pool.get().map_err(|_| render_login(Some("Ошибка подключения к базе")))?;
+    if !db::has_users(&conn).unwrap_or(false) {
+        return Ok(Redirect::to("/setup"));
+    }
+    let form = form.into_inner();
+    let username = form.username.trim();
+    if username.is_empty() || form.password.is_empty() {
+        return Err(render_login(Some("Введите логин и пароль")).into());
+    }
+
+    // An attempt made while waiting isn't checked, so it doesn't count either.
+    let throttle = login_throttle::keys(client.0, username);
+    let now = chrono::Utc::now();
+    if let Ok(Some(wait)) = login_throttle::wait(&conn, &throttle, now) {
+        return Err(render_login(Some(&login_throttle::wait_message(wait))).into());
+    }
+    let creds = db::user_credentials(&conn, username)
+        .map_err(|_| render_login(Some("Ошибка поиска пользователя")))?;
+    let Some(user_id) = verify_login(creds, &form.password) else {
+        let _ = login_throttle::record_failure(&conn, &throttle, now);
+        return Err(render_login(Some("Неверный логин или пароль")).into());
+    };
+    let _ = login_throttle::clear(&conn, &throttle);
+
+    let token = Uuid::new_v4().to_string();
+    sessions::start(&conn, user_id, &token, &user_agent.0, client.0, now)
+        .map_err(|_| render_login(Some("Не удалось создать сессию")))?;
+    db::prune_sessions(&conn, user_id, MAX_SESSIONS)
+        .map_err(|_| render_login(Some("Не удалось обновить сессии")))?;
+    let _ = notifications::dispatch(
+        &conn,
+        user_id,
+        &NewNotification {
+            event: "login".to_string(),
+            title: "Вход в аккаунт".to_string(),
+            body: format!("Выполнен вход в аккаунт {username}"),
+            payload: None,
+        },
+    );
+    let mailer = mailer.inner().as_ref();
+    let alert = login_alert::check(
+        &conn,
+        mailer.is_some(),
+        user_id,
+        username,
+        &user_agent.0,
+        chrono::Utc::now(),
+    );
+    if let (Some(mailer), Ok(Some(alert))) = (mailer, alert) {
+        mailer.send_in_background(alert);
+    }
+
+    cookies.add(sessions::cookie(token));
+    if form.remember {
+        let remembered = remember::issue(&conn, user_id, now)
+            .map_err(|_| render_login(Some("Не удалось запомнить вход")))?;
+        cookies.add(remember::cookie(&remembered));
+    }
+
+    Ok(Redirect::to("/"))
+}
+
+/// The link from a new-device letter only asks; mail scanners follow links.
+#[get("/login/revoke/<token>")]
+fn revoke_from_alert_page(token: &str) -> Template {
+    Template::render(
+        "login_revoke",
+        serde_json::json!({
+            "action": format!("/login/revoke/{token}"),
+        }),
+    )
+}
+
+/// Signs the account out everywhere, as confirmed on the letter's page.
+#[post("/login/revoke/<token>")]
+fn revoke_from_alert(pool: &State<DbPool>, token: &str) -> Template {
+    let Ok(conn) = pool.get() else {
+        return render_login(Some("Ошибка подключения к базе"));
+    };
+    match login_alert::revoke(&conn, token, chrono::Utc::now()) {
+        Ok(Some(_)) => render_login(Some("Все сессии завершены. Войдите и смените пароль.")),
+        _ => render_login(Some("Ссылка недействительна или устарела")),
+    }
+}
+
+#[get("/settings")]
+fn settings(pool: &State<DbPool>, cookies: &CookieJar<'_>) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    Ok(render_settings(&conn, &user, cookies, None, None))
+}
+
+#[post("/settings/password", data = "<form>")]
+fn settings_password(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    form: Form<ChangePasswordForm>,
+) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let form = form.into_inner();
+
+    if form.new_password.len() < 6 {
+        return Ok(render_settings(
+            &conn,
+            &user,
+            cookies,
+            Some("Новый пароль должен быть не короче 6 символов"),
+            None,
+        ));
+    }
+    if form.new_password != form.confirm_password {
+        return Ok(render_settings(
+            &conn,
+            &user,
+            cookies,
+            Some("Пароли не совпадают"),
+            None,
+        ));
+    }
+    if let Err(weakness) = password_strength::check(&form.new_password, &user.username) {
+        return Ok(render_settings(
+            &conn,
+            &user,
+            cookies,
+            Some(&weakness.message()),
+            None,
+        ));
+    }
+
+    let creds = db::user_credentials(&conn, &user.username)
+        .map_err(|_| Redirect::to("/login"))?;
+    let Some((_user_id, hash)) = creds else {
+        return Ok(render_settings(
+            &conn,
+            &user,
+            cookies,
+            Some("Пользователь не найден"),
+            None,
+        ));
+    };
+    if !verify_password(&hash, &form.current_password) {
+        return Ok(render_settings(
+            &conn,
+            &user,
+            cookies,
+            Some("Текущий пароль неверный"),
+            None,
+        ));
+    }
+
+    let new_hash = hash_password(&form.new_password).map_err(|_| Redirect::to("/login"))?;
+    conn.execute(
+        "UPDATE users SET password_hash = ?1 WHERE id = ?2",
+        params![new_hash, user.id],
+    )
+    .map_err(|_| Redirect::to("/login"))?;
+    Ok(render_settings(
+        &conn,
+        &user,
+        cookies,
+        None,
+        Some("Пароль обновлен"),
+    ))
+}
+
+#[post("/settings/notifications", data = "<form>")]
+fn settings_notifications(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    form: Form<NotificationPreferencesForm>,
+) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let form = form.into_inner();
+    if notifications::save_preferences(&conn, user.id, &form.enabled).is_err() {
+        return Ok(render_settings(
+            &conn,
+            &user,
+            cookies,
+            Some("Не удалось сохранить настройки уведомлений"),
+            None,
+        ));
+    }
+    Ok(render_settings(
+        &conn,
+        &user,
+        cookies,
+        None,
+        Some("Настройки уведомлений сохранены"),
+    ))
+}
+
+#[post("/settings/telegram", data = "<form>")]
+fn settings_telegram(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    form: Form<TelegramForm>,
+) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let form = form.into_inner();
+    let raw = form.chat_id;
+    let chat_id = if raw.trim().is_empty() {
+        None
+    } else {
+        match raw.trim().parse::<i64>() {
+            Ok(value) => Some(value),
+            Err(_) => {
+                return Ok(render_settings(
+                    &conn,
+                    &user,
+                    cookies,
+                    Some("Chat ID должен быть числом"),
+                    None,
+                ));
+            }
+        }
+    };
+    if db::set_telegram_chat_id(&conn, user.id, chat_id).is_err() {
+        return Ok(render_settings(
+            &conn,
+            &user,
+            cookies,
+            Some("Этот чат уже привязан к другому аккаунту"),
+            None,
+        ));
+    }
+    db::set_telegram_digest(&conn, user.id, form.digest)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(render_settings(&conn, &user, cookies, None, Some("Telegram обновлен")))
+}
+
+#[post("/settings/email", data = "<form>")]
+fn settings_email(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    form: Form<EmailForm>,
+) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let email = form.into_inner().email.trim().to_string();
+    if !email.is_empty() && email.parse::<lettre::Address>().is_err() {
+        return Ok(render_settings(&conn, &user, cookies, Some("Некорректный email"), None));
+    }
+    db::set_user_email(&conn, user.id, Some(email.as_str()).filter(|email| !email.is_empty()))
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(render_settings(&conn, &user, cookies, None, Some("Email обновлен")))
+}
+
+#[post("/settings/daily-summary", data = "<form>")]
+fn settings_daily_summary(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    form: Form<DailySummaryForm>,
+) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let form = form.into_inner();
+    let at = form.at.trim();
+    let settings = if at.is_empty() {
+        models::DailySummarySettings::default()
+    } else {
+        let Ok(at) = chrono::NaiveTime::parse_from_str(at, "%H:%M") else {
+            return Ok(render_settings(
+                &conn,
+                &user,
+                cookies,
+                Some("Время итогов дня указано неверно"),
+                None,
+            ));
+        };
+        if !form.telegram && !form.email {
+            return Ok(render_settings(
+                &conn,
+                &user,
+                cookies,
+                Some("Выберите, куда присылать итоги дня"),
+                None,
+            ));
+        }
+        models::DailySummarySettings {
+            at: Some(at.format("%H:%M").to_string()),
+            telegram: form.telegram,
+            email: form.email,
+        }
+    };
+    db::set_daily_summary(&conn, user.id, &settings)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(render_settings(&conn, &user, cookies, None, Some("Итоги дня обновлены")))
+}
+
+#[post("/settings/refunds", data = "<form>")]
+fn settings_refunds(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    form: Form<RefundMonthForm>,
+) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let refunds = disputes::RefundMonth::from_param(&form.refund_month)
+        .ok_or(rocket::http::Status::BadRequest)?;
+    db::set_refund_month(&conn, user.id, refunds)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(render_settings(&conn, &user, cookies, None, Some("Учет возвратов обновлен")))
+}
+
+#[post("/settings/rounding", data = "<form>")]
+fn settings_rounding(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    form: Form<RoundingForm>,
+) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let rounding = Rounding::from_param(&form.report_rounding)
+        .ok_or(rocket::http::Status::BadRequest)?;
+    db::set_report_rounding(&conn, user.id, rounding)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(render_settings(&conn, &user, cookies, None, Some("Округление в отчетах обновлено")))
+}
+
+#[post("/settings/rates", data = "<form>")]
+fn settings_rates(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    form: Form<ExchangeRateForm>,
+) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let form = form.into_inner();
+    let currency = fx::normalize_currency(&form.currency)
+        .filter(|currency| currency != fx::BASE_CURRENCY);
+    let rate_date = import::parse_date(&form.rate_date);
+    let (Some(currency), Some(rate_date), Some(rate)) =
+        (currency, rate_date, fx::parse_rate(&form.rate))
+    else {
+        return Ok(render_settings(
+            &conn,
+            &user,
+            cookies,
+            Some("Укажите код валюты, дату и курс"),
+            None,
+        ));
+    };
+    let updated_at = Local::now().to_rfc3339();
+    if db::upsert_exchange_rate(&conn, &currency, &rate_date, rate, "manual", &updated_at).is_err() {
+        return Ok(render_settings(&conn, &user, cookies, Some("Не удалось сохранить курс"), None));
+    }
+    Ok(render_settings(&conn, &user, cookies, None, Some("Курс сохранен")))
+}
+
+#[post("/settings/rates/delete", data = "<form>")]
+fn settings_rates_delete(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    form: Form<ExchangeRateKeyForm>,
+) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let form = form.into_inner();
+    if db::delete_manual_exchange_rate(&conn, &form.currency, &form.rate_date).is_err() {
+        return Ok(render_settings(&conn, &user, cookies, Some("Не удалось удалить курс"), None));
+    }
+    Ok(render_settings(&conn, &user, cookies, None, Some("Ручной курс удален")))
+}
+
+#[post("/telegram/webhook", data = "<body>")]
+async fn telegram_webhook(
+    pool: &State<DbPool>,
+    config: &State<Option<telegram::TelegramConfig>>,
+    secret: telegram::SecretToken<'_>,
+    body: String,
+) -> rocket::http::Status {
+    let Some(config) = config.inner() else {
+        return rocket::http::Status::NotFound;
+    };
+    if !csrf::same_token(secret.0, &config.secret) {
+        return rocket::http::Status::Forbidden;
+    }
+    let Ok(update) = serde_json::from_str::<serde_json::Value>(&body) else {
+        return rocket::http::Status::BadRequest;
+    };
+    let (pool, config) = (pool.inner().clone(), config.clone());
+    let _ = rocket::tokio::task::spawn_blocking(move || {
+        telegram::handle_update(&pool, &config, &update)
+    })
+    .await;
+    rocket::http::Status::Ok
+}
+
+#[get("/notifications?<event>")]
+fn notifications_page(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    event: Option<String>,
+) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let event = event.filter(|value| notifications::EVENTS.iter().any(|(known, _)| known == value));
+    let list = db::list_notifications(&conn, user.id, event.as_deref()).unwrap_or_default();
+    let unread = list.iter().filter(|n| n.read_at.is_none()).count();
+    let views = list
+        .into_iter()
+        .map(|n| {
+            serde_json::json!({
+                "event_label": notifications::event_label(&n.event),
+                "title": n.title,
+                "body": n.body,
+                "created_at": n.created_at.get(..16).unwrap_or(&n.created_at).replace('T', " "),
+                "unread": n.read_at.is_none(),
+            })
+        })
+        .collect::<Vec<_>>();
+    let events = notifications::EVENTS
+        .iter()
+        .map(|(event, label)| serde_json::json!({ "event": event, "label": label }))
+        .collect::<Vec<_>>();
+
+    let context = serde_json::json!({
+        "username": user.username,
+        "event": event,
+        "events": events,
+        "notifications": views,
+        "unread": unread,
+    });
+    Ok(Template::render("notifications", &context))
+}
+
+#[post("/notifications/read_all")]
+fn notifications_read_all(pool: &State<DbPool>, cookies: &CookieJar<'_>) -> Redirect {
+    if let Some(user) = current_user(pool, cookies)
+        && let Ok(conn) = pool.get()
+    {
+        let _ = db::mark_all_notifications_read(&conn, user.id, &Local::now().to_rfc3339());
+    }
+    Redirect::to("/notifications")
+}
+
+/// Signs out one of the user's browsers; revoking the current one is logging out.
+#[post("/settings/sessions/<id>/revoke")]
+fn settings_revoke_session(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+) -> Result<Redirect, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    db::delete_user_session(&conn, user.id, id)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    if current_user(pool, cookies).is_none() {
+        let _ = remember::forget(&conn, cookies);
+        let mut cookie = Cookie::from("session");
+        cookie.set_path("/");
+        cookies.remove(cookie);
+        return Ok(Redirect::to("/login"));
+    }
+    Ok(Redirect::to("/settings"))
+}
+
+#[post("/settings/logout_all")]
+fn settings_logout_all(pool: &State<DbPool>, cookies: &CookieJar<'_>) -> Redirect {
+    if let Ok(conn) = pool.get()
+        && let Some(user) = current_user(pool, cookies)
+    {
+        let _ = db::delete_sessions_for_user(&conn, user.id);
+        let _ = db::delete_remember_tokens_for_user(&conn, user.id);
+        let now = api_sessions::timestamp(chrono::Utc::now());
+        let _ = db::revoke_api_sessions_for_user(&conn, user.id, &now);
+    }
+    cookies.remove(remember::removal());
+    let mut cookie = Cookie::from("session");
+    cookie.set_path("/");
+    cookies.remove(cookie);
+    Redirect::to("/login")
+}
+
+/// Transactions with malformed dates: the ones that can be fixed as they are
+/// and the review list of those someone has to decide on.
+#[get("/settings/dates")]
+fn date_repair(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    _network: allowlist::AdminNetwork,
+) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let found = dates::scan(&conn).map_err(|_| rocket::http::Status::InternalServerError)?;
+    let mut fixable = Vec::new();
+    let mut review = Vec::new();
+    for (record, reading) in found {
+        let amount = format_money(record.amount_cents);
+        let entry = serde_json::json!({ "record": record, "amount": amount });
+        match reading {
+            dates::Reading::Fixed(date) => {
+                fixable.push(serde_json::json!({ "entry": entry, "date": date.to_string() }))
+            }
+            dates::Reading::Ambiguous(candidates) => {
+                let candidates = candidates.iter().map(ToString::to_string).collect::<Vec<_>>();
+                review.push(serde_json::json!({ "entry": entry, "candidates": candidates }))
+            }
+            dates::Reading::Unreadable => {
+                review.push(serde_json::json!({ "entry": entry, "candidates": [] }))
+            }
+        }
+    }
+    let context = serde_json::json!({
+        "username": user.username,
+        "fixable": fixable,
+        "review": review,
+    });
+    Ok(Template::render("dates", &context))
+}
+
+#[post("/settings/dates")]
+fn fix_dates(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    _network: allowlist::AdminNetwork,
+) -> Result<Redirect, AppError> {
+    require_user(pool, cookies)?;
+    let mut conn = pool.get()?;
+    bulk::run(&mut conn, false, "date_repair", "Исправлены даты операций", |tx, changes| {
+        dates::fix(tx, changes)
+    })
+    .map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(Redirect::to("/settings/dates"))
+}
+
+#[post("/settings/dates/<id>", data = "<form>")]
+fn set_transaction_date(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    _network: allowlist::AdminNetwork,
+    id: i64,
+    form: Form<DateForm>,
+) -> Result<Redirect, AppError> {
+    require_user(pool, cookies)?;
+    let date = NaiveDate::parse_from_str(form.occurred_on.trim(), "%Y-%m-%d")
+        .map_err(|_| rocket::http::Status::BadRequest)?;
+    let mut conn = pool.get()?;
+    let found = dates::scan(&conn).map_err(|_| rocket::http::Status::InternalServerError)?;
+    let (record, _) = found
+        .iter()
+        .find(|(record, _)| record.id == id)
+        .ok_or(rocket::http::Status::NotFound)?;
+    let label = format!("Дата операции: {} → {date}", record.occurred_on);
+    bulk::run(&mut conn, false, "date_repair", &label, |tx, changes| {
+        dates::set(tx, changes, id, date).map(|()| vec![id])
+    })
+    .map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(Redirect::to("/settings/dates"))
+}
+
+/// The user accounts, for merging a duplicate into the one to keep.
+#[get("/settings/users")]
+fn users_page(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    _network: allowlist::AdminNetwork,
+    flash: Option<FlashMessage<'_>>,
+) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let users = db::list_users(&conn).map_err(|_| rocket::http::Status::InternalServerError)?;
+    let context = serde_json::json!({
+        "username": user.username,
+        "current_user_id": user.id,
+        "users": users,
+        "flash": flash,
+    });
+    Ok(Template::render("users", &context))
+}
+
+#[post("/settings/users/merge", data = "<form>")]
+fn merge_users(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    _network: allowlist::AdminNetwork,
+    form: Form<MergeUsersForm>,
+) -> Result<Flash<Redirect>, AppError> {
+    let user = require_user(pool, cookies)?;
+    let mut conn = pool.get()?;
+    let hash = db::user_credentials(&conn, &user.username)
+        .map_err(|_| rocket::http::Status::InternalServerError)?
+        .map(|(_, hash)| hash);
+    if !hash.is_some_and(|hash| verify_password(&hash, &form.password)) {
+        return Ok(Flash::error(
+            Redirect::to("/settings/users"),
+            "Пароль неверный, аккаунты не объединены",
+        ));
+    }
+    match user_merge::merge(&mut conn, user.id, form.from_id, form.into_id) {
+        Ok(Ok(())) => Ok(Flash::success(Redirect::to("/settings/users"), "Аккаунты объединены")),
+        Ok(Err(refused)) => Ok(Flash::error(Redirect::to("/settings/users"), refused.message())),
+        Err(_) => Err(rocket::http::Status::InternalServerError.into()),
+    }
+}
+
+/// Deletes the signed-in account after the password is confirmed, see
+/// `account_deletion`.
+#[post("/settings/delete-account", data = "<form>")]
+fn settings_delete_account(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    storage: &State<receipt_storage::Receipts>,
+    form: Form<DeleteAccountForm>,
+) -> Result<Redirect, AppError> {
+    let user = require_user(pool, cookies)?;
+    let mut conn = pool.get()?;
+    let hash = db::user_credentials(&conn, &user.username)
+        .map_err(|_| rocket::http::Status::InternalServerError)?
+        .map(|(_, hash)| hash);
+    if !hash.is_some_and(|hash| verify_password(&hash, &form.password)) {
+        return Err(AppError::Page(Box::new(render_settings(
+            &conn,
+            &user,
+            cookies,
+            Some("Пароль неверный, аккаунт не удален"),
+            None,
+        ))));
+    }
+    account_deletion::delete(&mut conn, user.id, &***storage)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    let _ = remember::forget(&conn, cookies);
+    let mut cookie = Cookie::from("session");
+    cookie.set_path("/");
+    cookies.remove(cookie);
+    Ok(Redirect::to("/login"))
+}
+
+#[post("/logout")]
+fn logout(pool: &State<DbPool>, cookies: &CookieJar<'_>) -> Redirect {
+    if let Ok(conn) = pool.get() {
+        if let Some(token) = sessions::token(cookies) {
+            let _ = db::delete_session(&conn, &token);
+        }
+        let _ = remember::forget(&conn, cookies);
+    }
+    let mut cookie = Cookie::from("session");
+    cookie.set_path("/");
+    cookies.remove(cookie);
+    Redirect::to("/login")
+}
+
+/// Where `csrf::Protection` sends a POST without the session's token.
+#[post("/csrf/rejected")]
+fn csrf_rejected() -> rocket::http::Status {
+    rocket::http::Status::Forbidden
+}
+
+/// Connection pool usage as JSON, for watching load tests.
+#[get("/metrics/pool")]
+fn pool_metrics(
+    pool: &State<DbPool>,
+    metrics: &State<Arc<db::PoolMetrics>>,
+    cookies: &CookieJar<'_>,
+    _network: allowlist::AdminNetwork,
+) -> Result<(rocket::http::ContentType, String), AppError> {
+    require_user(pool, cookies)?;
+    let stats = metrics.stats(pool);
+    let body = serde_json::to_string(&stats).unwrap_or_default();
+    Ok((rocket::http::ContentType::JSON, body))
+}
+
+/// The SQL of the last requests, when the query inspector is on.
+#[get("/admin/queries")]
+fn recent_queries(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    _network: allowlist::AdminNetwork,
+    queries: &State<query_inspector::Queries>,
+) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    if !queries.enabled {
+        return Err(rocket::http::Status::NotFound.into());
+    }
+    let context = serde_json::json!({
+        "username": user.username,
+        "requests": queries.recent(),
+        "kept": query_inspector::KEPT,
+    });
+    Ok(Template::render("queries", &context))
+}
+
+#[get("/?<month>")]
+fn dashboard(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    month: Option<String>,
+) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let selected = selected_month(month);
+    let conn = pool.get()?;
+    let refunds = db::refund_month(&conn, user.id).unwrap_or_default();
+    let (income_cents, expense_cents) =
+        db::report_month_totals(&conn, &selected, refunds).unwrap_or((0, 0));
+    let budgets = db::dashboard_budgets(&conn, &selected, refunds).unwrap_or_default();
+    let budget_views = budgets
+        .into_iter()
+        .map(dashboard_budget_view)
+        .collect::<Vec<_>>();
+    let nav = month_nav(&conn, "/", &selected)?;
+    let accounts = db::list_accounts(&conn).unwrap_or_default();
+    let account_views = accounts.into_iter().map(account_view).collect::<Vec<_>>();
+    let starred = TransactionQuery {
+        starred: true,
+        ..Default::default()
+    };
+    let starred_count = db::count_transactions(&conn, &starred).unwrap_or(0);
+
+    let context = serde_json::json!({
+        "month": selected,
+        "nav": nav,
+        "username": user.username,
+        "accounts": account_views,
+        "starred": starred_count,
+        "income": format_money(income_cents),
+        "expense": format_money(expense_cents),
+        "net": format_money(income_cents - expense_cents),
+        "budgets": budget_views,
+    });
+    Ok(Template::render("dashboard", &context))
+}
+
+/// The month's transactions narrowed by the filters. With `q` the ones whose
+/// note or payee matches it come best first, from any month unless one is
+/// picked; `month=all` drops the month filter without a search too.
+#[get("/transactions?<filter..>")]
+fn transactions(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    filter: TransactionFilter,
+    flash: Option<FlashMessage<'_>>,
+) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    render_transactions(
+        &conn,
+        &user,
+        filter,
+        flash,
+        FormState::default(),
+        FormState::default(),
+    )
+}
+
+/// The transactions page; `transaction_form` and `transfer_form` hold what
+/// was sent back, see `validation`.
+fn render_transactions(
+    conn: &rusqlite::Connection,
+    user: &User,
+    filter: TransactionFilter,
+    flash: Option<FlashMessage<'_>>,
+    transaction_form: FormState,
+    transfer_form: FormState,
+) -> Result<Template, AppError> {
+    let page_link = |page: i64| {
+        let filter = TransactionFilter {
+            page: Some(page.to_string()),
+            ..filter.clone()
+        };
+        uri!(transactions(filter)).to_string()
+    };
+    let sort_link = |sort: Sort| {
+        let filter = TransactionFilter {
+            sort: Some(sort.param().to_string()),
+            page: None,
+            ..filter.clone()
+        };
+        uri!(transactions(filter)).to_string()
+    };
+    let TransactionFilter {
+        month,
+        tag,
+        q,
+        kind,
+        category,
+        min,
+        max,
+        starred,
+        sort,
+        page,
+    } = filter.clone();
+    let sort = match sort.as_deref().and_then(optional_field) {
+        Some(value) => Some(Sort::from_param(&value).ok_or(rocket::http::Status::BadRequest)?),
+        None => None,
+    };
+    let search = q.as_deref().and_then(optional_field);
+    let month = month.as_deref().and_then(optional_field);
+    let all_months = month.as_deref() == Some("all") || (month.is_none() && search.is_some());
+    let selected = selected_month(month.filter(|_| !all_months));
+    let tag = tag
+        .map(|value| value.trim().to_lowercase())
+        .filter(|value| !value.is_empty());
+    let kind = kind.as_deref().and_then(optional_field);
+    if kind
+        .as_deref()
+        .is_some_and(|kind| !matches!(kind, "income" | "expense" | "transfer"))
+    {
+        return Err(rocket::http::Status::BadRequest.into());
+    }
+    let category_id = match category.as_deref().map(str::trim) {
+        None | Some("") => None,
+        Some(value) => Some(
+            value
+                .parse::<i64>()
+                .map_err(|_| rocket::http::Status::BadRequest)?,
+        ),
+    };
+    let amount = |value: Option<&str>| match value.map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(value) => parse_amount_to_cents(value)
+            .map(Some)
+            .ok_or(rocket::http::Status::BadRequest),
+    };
+    let min_cents = amount(min.as_deref())?;
+    let max_cents = amount(max.as_deref())?;
+    let starred = starred.as_deref().and_then(optional_field).is_some();
+    let mut query = TransactionQuery {
+        month: Some(selected.clone()).filter(|_| !all_months),
+        from: None,
+        to: None,
+        from_day: None,
+        tag: tag.clone(),
+        kind: kind.clone(),
+        category_id,
+        min_cents,
+        max_cents,
+        term: search.clone(),
+        starred,
+        sort: sort.unwrap_or_default(),
+        limit: Some(TRANSACTIONS_PER_PAGE),
+        offset: None,
+    };
+    let total = db::count_transactions(conn, &query)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    let pages = ((total + TRANSACTIONS_PER_PAGE - 1) / TRANSACTIONS_PER_PAGE).max(1);
+    let page = page
+        .and_then(|value| value.trim().parse::<i64>().ok())
+        .unwrap_or(1)
+        .clamp(1, pages);
+    let offset = (page - 1) * TRANSACTIONS_PER_PAGE;
+    query.offset = Some(offset);
+    let records = match (&search, sort) {
+        (Some(_), None) => db::search_transactions(conn, &query).unwrap_or_default(),
+        _ => db::list_transactions(conn, &query).unwrap_or_default(),
+    };
+    // `None` while search results come by relevance.
+    let current = sort.or(search.is_none().then_some(Sort::Newest));
+    let toggle = |first: Sort, second: Sort| {
+        sort_link(if current == Some(first) { second } else { first })
+    };
+    let sort_links = serde_json::json!({
+        "date": toggle(Sort::Newest, Sort::Oldest),
+        "amount": toggle(Sort::Largest, Sort::Smallest),
+        "category": sort_link(Sort::Category),
+    });
+    let tags = db::list_tags(conn).unwrap_or_default();
+    let categories = db::list_categories(conn).unwrap_or_default();
+    let form_kind = form_kind(transaction_form.values.get("kind").map(String::as_str));
+    let form_categories = categories_of_kind(&categories, form_kind);
+    let views = records.into_iter().map(transaction_view).collect::<Vec<_>>();
+    let nav = month_nav(conn, "/transactions", &selected)?;
+    let accounts = db::list_accounts(conn).unwrap_or_default();
+    let account_views = accounts.into_iter().map(account_view).collect::<Vec<_>>();
+
+    let context = serde_json::json!({
+        "month": selected,
+        "nav": nav,
+        "username": user.username,
+        "today": today_ymd(),
+        "all_months": all_months,
+        "defaults": {
+            "kind": form_kind,
+            "amount": transaction_form.values.get("amount"),
+        },
+        "sort": current.map(Sort::param),
+        "sort_links": sort_links,
+        "total": total,
+        "page": page,
+        "pages": pages,
+        "first_row": offset + 1,
+        "last_row": (offset + TRANSACTIONS_PER_PAGE).min(total),
+        "prev_page": (page > 1).then(|| page_link(page - 1)),
+        "next_page": (page < pages).then(|| page_link(page + 1)),
+        "filtered": query.term.is_some()
+            || query.kind.is_some()
+            || query.category_id.is_some()
+            || query.min_cents.is_some()
+            || query.max_cents.is_some()
+            || query.starred,
+        "tag": tag,
+        "tags": tags,
+        "q": search,
+        "kind": kind,
+        "category": category_id,
+        "min": min.as_deref().and_then(optional_field),
+        "max": max.as_deref().and_then(optional_field),
+        "starred": starred,
+        "transactions": views,
+        "categories": categories,
+        "form_categories": form_categories,
+        "accounts": account_views,
+        "flash": flash,
+        "transaction_form": transaction_form,
+        "transfer_form": transfer_form,
+        "share_days": receipt_shares::DAYS,
+        "share_default_days": receipt_shares::DEFAULT_DAYS,
+    });
+    Ok(Template::render("transactions", &context))
+}
+
+#[get("/transactions/export.csv?<month>")]
+fn export_transactions(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    month: Option<String>,
+) -> Result<export::Attachment, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let month = month.filter(|value| !value.trim().is_empty());
+    let query = TransactionQuery {
+        month: month.clone(),
+        ..Default::default()
+    };
+    let records = db::list_transactions(&conn, &query).unwrap_or_default();
+    let filename = match &month {
+        Some(month) => format!("transactions-{month}.csv"),
+        None => "transactions-all.csv".to_string(),
+    };
+    let body = export::transactions_csv(&records).unwrap_or_default();
+    let label = match &month {
+        Some(month) => format!("Операции за {month}"),
+        None => "Все операции".to_string(),
+    };
+    // Losing the copy for the downloads page shouldn't fail the export itself.
+    let _ = jobs::keep_file(&conn, user.id, &label, &filename, &body);
+    Ok(export::Attachment::csv(&filename, body))
+}
+
+/// The kind and amount fields of the new-transaction form filled in for the
+/// picked category: its kind, and its default amount unless a valid amount
+/// is typed already. The page swaps them in when the category changes.
+#[get("/transactions/defaults?<category_id>&<amount>")]
+fn transaction_defaults(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    category_id: Option<i64>,
+    amount: Option<String>,
+) -> Result<Template, AppError> {
+    require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let category = match category_id {
+        Some(id) => Some(
+            db::category_by_id(&conn, id)
+                .map_err(|_| rocket::http::Status::InternalServerError)?
+                .ok_or(rocket::http::Status::NotFound)?,
+        ),
+        None => None,
+    };
+    let (kind, amount) = category_defaults(category, amount);
+    let context = serde_json::json!({
+        "defaults": {
+            "kind": kind,
+            "amount": amount,
+        },
+    });
+    Ok(Template::render("transaction_defaults", &context))
+}
+
+/// The kind the new-transaction form is for: the one sent, or expense on a
+/// fresh form.
+fn form_kind(sent: Option<&str>) -> &'static str {
+    match sent {
+        Some("income") => "income",
+        _ => "expense",
+    }
+}
+
+/// The categories the new-transaction form offers for `kind`.
+fn categories_of_kind<'a>(categories: &'a [Category], kind: &str) -> Vec<&'a Category> {
+    categories
+        .iter()
+        .filter(|category| category.kind == kind)
+        .collect()
+}
+
+/// The category options of the new-transaction form for another kind,
+/// swapped in when the kind is changed; `category_id` stays picked if it fits.
+#[get("/transactions/categories?<kind>&<category_id>")]
+fn transaction_categories(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    kind: &str,
+    category_id: Option<i64>,
+) -> Result<Template, AppError> {
+    require_user(pool, cookies)?;
+    if !matches!(kind, "income" | "expense") {
+        return Err(rocket::http::Status::BadRequest.into());
+    }
+    let conn = pool.get()?;
+    let categories = db::list_categories(&conn)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    let context = serde_json::json!({
+        "form_categories": categories_of_kind(&categories, kind),
+        "transaction_form": {
+            "values": { "category_id": category_id.map(|id| id.to_string()) },
+        },
+    });
+    Ok(Template::render("transaction_categories", &context))
+}
+
+/// Categories earlier transactions with a similar payee or note went to, for
+/// the new-transaction form to offer; only those of `kind` when it is given.
+#[get("/transactions/suggestions?<payee>&<note>&<kind>")]
+fn category_suggestions(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    payee: Option<String>,
+    note: Option<String>,
+    kind: Option<String>,
+) -> Result<(rocket::http::ContentType, String), AppError> {
+    require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let suggestions = suggestions::suggest(
+        &conn,
+        payee.as_deref(),
+        note.as_deref(),
+        kind.as_deref().and_then(optional_field).as_deref(),
+    )
+    .map_err(|_| rocket::http::Status::InternalServerError)?;
+    let body = serde_json::json!({ "suggestions": suggestions });
+    Ok((rocket::http::ContentType::JSON, body.to_string()))
+}
+
+/// The kind and amount the new-transaction form gets for a picked category:
+/// its kind, and the amount typed so far or else its usual one.
+fn category_defaults(
+    category: Option<Category>,
+    typed: Option<String>,
+) -> (Option<String>, Option<String>) {
+    let typed = typed
+        .map(|value| value.trim().to_string())
+        .filter(|value| parse_amount_to_cents(value).is_some());
+    match category {
+        Some(category) => {
+            let amount = typed.or_else(|| category.default_amount_cents.map(format_money));
+            (Some(category.kind), amount)
+        }
+        None => (None, typed),
+    }
+}
+
+/// The same as `transaction_defaults` for browsers without JavaScript: the
+/// whole transactions page, with the form filled in again.
+#[get("/transactions/new?<draft..>")]
+fn transaction_draft(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    draft: TransactionDraft,
+) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let category = match draft.category_id.as_deref().and_then(optional_field) {
+        Some(id) => {
+            let id = id.parse::<i64>().map_err(|_| rocket::http::Status::BadRequest)?;
+            db::category_by_id(&conn, id).map_err(|_| rocket::http::Status::InternalServerError)?
+        }
+        None => None,
+    };
+    let mut form = FormState::default();
+    for (field, value) in [
+        ("category_id", &draft.category_id),
+        ("account_id", &draft.account_id),
+        ("occurred_on", &draft.occurred_on),
+        ("note", &draft.note),
+        ("payee", &draft.payee),
+        ("tags", &draft.tags),
+    ] {
+        if let Some(value) = value {
+            form.value(field, value);
+        }
+    }
+    let (kind, amount) = category_defaults(category, draft.amount);
+    if let Some(kind) = kind.or(draft.kind) {
+        form.value("kind", &kind);
+    }
+    if let Some(amount) = amount {
+        form.value("amount", &amount);
+    }
+    let filter = TransactionFilter::default();
+    render_transactions(&conn, &user, filter, None, form, FormState::default())
+}
+
+#[post("/transactions", data = "<form>")]
+async fn add_transaction(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    storage: &State<receipt_storage::Receipts>,
+    metadata: &State<photo_metadata::Policy>,
+    form: Form<TransactionForm<'_>>,
+) -> Result<Flash<Redirect>, AppError> {
+    let user = require_user(pool, cookies)?;
+    let mut form = form.into_inner();
+    let mut sent = FormState::default();
+    sent.value("kind", &form.kind);
+    sent.value("amount", &form.amount);
+    sent.value("occurred_on", &form.occurred_on);
+    sent.value("category_id", &form.category_id.map(|id| id.to_string()).unwrap_or_default());
+    sent.value("account_id", &form.account_id.map(|id| id.to_string()).unwrap_or_default());
+    sent.value("payee", form.payee.as_deref().unwrap_or(""));
+    sent.value("note", form.note.as_deref().unwrap_or(""));
+    sent.value("tags", form.tags.as_deref().unwrap_or(""));
+    sent.value("effective_month", form.effective_month.as_deref().unwrap_or(""));
+    if !matches!(form.kind.as_str(), "income" | "expense") {
+        sent.error("kind", "Выберите доход или расход");
+    }
+    let amount_cents = form_amount(&mut sent, "amount", &form.amount);
+    let occurred_on = form_date(&mut sent, "occurred_on", &form.occurred_on);
+    let effective_month = match form.effective_month.as_deref().and_then(optional_field) {
+        Some(value) => {
+            let month = effective_months::parse_month(&value);
+            if month.is_none() {
+                sent.error("effective_month", "Месяц в формате ГГГГ-ММ");
+            }
+            month.filter(|month| !occurred_on.starts_with(month.as_str()))
+        }
+        None => None,
+    };
+    let conn = pool.get()?;
+    let category = match form.category_id {
+        Some(category_id) => db::category_by_id(&conn, category_id)
+            .map_err(|_| rocket::http::Status::InternalServerError)?,
+        None => None,
+    };
+    match &category {
+        None if form.category_id.is_some() => sent.error("category_id", "Категория не найдена"),
+        Some(category) if category.kind != form.kind => sent.error(
+            "category_id",
+            if category.kind == "income" {
+                "Это категория доходов, выберите категорию расходов"
+            } else {
+                "Это категория расходов, выберите категорию доходов"
+            },
+        ),
+        _ => {}
+    }
+    let receipt = sent_receipt(form.receipt.take());
+    if receipt.is_some() && !category.as_ref().is_some_and(|category| category.allow_receipt) {
+        sent.error(
+            "receipt",
+            "Чек можно приложить к расходу в категории, где чеки включены на странице категорий",
+        );
+    }
+    if !sent.is_valid() {
+        let filter = TransactionFilter::default();
+        let page = render_transactions(&conn, &user, filter, None, sent, FormState::default())?;
+        return Err(AppError::Invalid(Box::new(page)));
+    }
+    drop(conn);
+    let receipt = stage_receipt(receipt, pool, storage, metadata).await?;
+    let receipt_reused = receipt.as_ref().is_some_and(receipt_files::Staged::reused);
+    let receipt_name = receipt
+        .as_ref()
+        .filter(|staged| !staged.reused())
+        .map(|staged| staged.name().to_string());
+
+    let mut conn = pool.get()?;
+    let mut transaction = NewTransaction {
+        kind: form.kind.clone(),
+        amount_cents,
+        category_id: form.category_id,
+        occurred_on: occurred_on.clone(),
+        note: form.note.clone(),
+        payee: form.payee.as_deref().and_then(optional_field),
+        account_id: form.account_id,
+        to_account_id: None,
+    };
+    let rules = db::list_rules(&conn).unwrap_or_default();
+    let categories = db::list_categories(&conn).unwrap_or_default();
+    let mut tags = parse_tags(form.tags.as_deref().unwrap_or(""));
+    for tag in rules::apply(&rules, &categories, &mut transaction) {
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    let (_, receipt_kept) = receipt_files::save(&mut conn, receipt, |tx, receipt_path| {
+        let transaction_id = db::insert_transaction(tx, &transaction, receipt_path)?;
+        if let Some(month) = &effective_month {
+            db::set_effective_month(tx, transaction_id, Some(month))?;
+        }
+        db::add_transaction_tags(tx, transaction_id, &tags)
+    })
+    .map_err(|_| rocket::http::Status::InternalServerError)?;
+    if let (Some(category_id), "expense") = (transaction.category_id, transaction.kind.as_str()) {
+        let month = effective_months::month_of(&occurred_on, effective_month.as_deref());
+        notify_budget_exceeded(&conn, user.id, category_id, month, amount_cents);
+    }
+    if let Some(category_id) = form.category_id.filter(|id| form.suggested_category_id == Some(*id)) {
+        let _ = suggestions::record_accepted(
+            &conn,
+            form.payee.as_deref(),
+            form.note.as_deref(),
+            category_id,
+        );
+    }
+
+    if !receipt_kept {
+        return Ok(Flash::error(
+            Redirect::to("/transactions"),
+            "Операция добавлена, но чек сохранить не удалось",
+        ));
+    }
+    if let Some(name) = receipt_name {
+        drop(conn);
+        store_receipt(storage, name).await;
+    }
+    if receipt_reused {
+        return Ok(Flash::success(
+            Redirect::to("/transactions"),
+            "Операция добавлена; такой чек уже был загружен, приложен прежний файл",
+        ));
+    }
+    Ok(Flash::success(Redirect::to("/transactions"), "Операция добавлена"))
+}
+
+/// A positive amount from `value`, or an error at `field`; the placeholder
+/// returned then is never saved.
+fn form_amount(sent: &mut FormState, field: &'static str, value: &str) -> i64 {
+    match parse_amount_to_cents(value).filter(|cents| *cents > 0) {
+        Some(cents) => cents,
+        None => {
+            sent.error(field, "Введите сумму больше нуля, например 1500.00");
+            0
+        }
+    }
+}
+
+/// The `YYYY-MM-DD` date in `value`, today when it is empty, or an error at
+/// `field`.
+fn form_date(sent: &mut FormState, field: &'static str, value: &str) -> String {
+    match value.trim() {
+        "" => today_ymd(),
+        value => match NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+            Ok(date) => date.format("%Y-%m-%d").to_string(),
+            Err(_) => {
+                sent.error(field, "Дата в формате ГГГГ-ММ-ДД");
+                String::new()
+            }
+        },
+    }
+}
+
+/// The pick and the shift of the month-shifting tool, or `None` with the
+/// errors in `sent`.
+fn effective_month_selection(
+    sent: &mut FormState,
+    form: &EffectiveMonthForm,
+) -> Option<(TransactionQuery, effective_months::Shift)> {
+    let from = form.from.as_deref().unwrap_or("");
+    let to = form.to.as_deref().unwrap_or("");
+    let from_day = form.from_day.as_deref().unwrap_or("");
+    let shift = form.shift.as_deref().unwrap_or(effective_months::Shift::Next.as_str());
+    sent.value("from", from);
+    sent.value("to", to);
+    sent.value("kind", form.kind.as_deref().unwrap_or(""));
+    sent.value("category_id", &form.category_id.map(|id| id.to_string()).unwrap_or_default());
+    sent.value("from_day", from_day);
+    sent.value("shift", shift);
+    let mut bound = |field, value: &str| match NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d") {
+        Ok(date) => Some(date.format("%Y-%m-%d").to_string()),
+        Err(_) => {
+            sent.error(field, "Дата в формате ГГГГ-ММ-ДД");
+            None
+        }
+    };
+    let from = bound("from", from);
+    let to = bound("to", to);
+    if from.is_some() && to.is_some() && from > to {
+        sent.error("to", "Конец периода раньше начала");
+    }
+    let kind = optional_field(form.kind.as_deref().unwrap_or(""));
+    if kind.as_deref().is_some_and(|kind| !matches!(kind, "income" | "expense")) {
+        sent.error("kind", "Выберите доход или расход");
+    }
+    let from_day = match from_day.trim() {
+        "" => None,
+        value => {
+            let day = value.parse::<u32>().ok().filter(|day| (1..=31).contains(day));
+            if day.is_none() {
+                sent.error("from_day", "Число от 1 до 31");
+            }
+            day
+        }
+    };
+    let shift = effective_months::Shift::from_param(shift);
+    if shift.is_none() {
+        sent.error("shift", "Выберите, куда перенести");
+    }
+    if !sent.is_valid() {
+        return None;
+    }
+    let query = TransactionQuery {
+        from,
+        to,
+        from_day,
+        kind,
+        category_id: form.category_id,
+        sort: Sort::Oldest,
+        ..Default::default()
+    };
+    Some((query, shift?))
+}
+
+fn render_effective_months(
+    conn: &rusqlite::Connection,
+    user: &User,
+    sent: FormState,
+    preview: Option<Vec<effective_months::Shifted>>,
+    flash: Option<FlashMessage<'_>>,
+) -> Result<Template, AppError> {
+    let categories = db::list_categories(conn).unwrap_or_default();
+    let shifts = effective_months::Shift::ALL
+        .iter()
+        .map(|shift| serde_json::json!({ "value": shift.as_str(), "label": shift.label() }))
+        .collect::<Vec<_>>();
+    let preview = preview.map(|shifted| {
+        shifted
+            .into_iter()
+            .map(|shifted| {
+                let own = shifted.record.occurred_on.get(..7).unwrap_or_default().to_string();
+                let before = shifted.record.effective_month.clone().unwrap_or_else(|| own.clone());
+                serde_json::json!({
+                    "before": before,
+                    "after": shifted.month.unwrap_or(own),
+                    "amount": format_money(shifted.record.amount_cents),
+                    "record": shifted.record,
+                })
+            })
+            .collect::<Vec<_>>()
+    });
+    let context = serde_json::json!({
+        "username": user.username,
+        "categories": categories,
+        "shifts": shifts,
+        "form": sent,
+        "preview": preview,
+        "flash": flash,
+    });
+    Ok(Template::render("effective_months", &context))
+}
+
+/// The month-shifting tool; with a pick sent, the transactions it would move.
+#[get("/transactions/months?<form..>")]
+fn effective_months_page(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    form: Option<EffectiveMonthForm>,
+    flash: Option<FlashMessage<'_>>,
+) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let mut conn = pool.get()?;
+    let Some(form) = form.filter(|form| form.from.is_some() || form.to.is_some()) else {
+        let mut sent = FormState::default();
+        sent.value("shift", effective_months::Shift::Next.as_str());
+        return render_effective_months(&conn, &user, sent, None, flash);
+    };
+    let mut sent = FormState::default();
+    let Some((query, shift)) = effective_month_selection(&mut sent, &form) else {
+        return render_effective_months(&conn, &user, sent, None, flash);
+    };
+    let preview = bulk::run(&mut conn, true, "effective_month", "", |tx, changes| {
+        effective_months::shift(tx, changes, &query, shift)
+    })
+    .map_err(|_| rocket::http::Status::InternalServerError)?;
+    render_effective_months(&conn, &user, sent, Some(preview.changes), flash)
+}
+
+#[post("/transactions/months", data = "<form>")]
+fn shift_effective_months(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    form: Form<EffectiveMonthForm>,
+) -> Result<Flash<Redirect>, AppError> {
+    let user = require_user(pool, cookies)?;
+    let mut conn = pool.get()?;
+    let mut sent = FormState::default();
+    let Some((query, shift)) = effective_month_selection(&mut sent, &form) else {
+        let page = render_effective_months(&conn, &user, sent, None, None)?;
+        return Err(AppError::Invalid(Box::new(page)));
+    };
+    let label = format!(
+        "Перенос по месяцам: {} — {}, {}",
+        query.from.as_deref().unwrap_or_default(),
+        query.to.as_deref().unwrap_or_default(),
+        shift.label().to_lowercase()
+    );
+    let outcome = bulk::run(&mut conn, false, "effective_month", &label, |tx, changes| {
+        effective_months::shift(tx, changes, &query, shift)
+    })
+    .map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(Flash::success(
+        Redirect::to("/transactions/months"),
+        format!("Перенесено операций: {}", outcome.changes.len()),
+    ))
+}
+
+/// Records the transaction again, dated today, with the same tags but without
+/// the receipt; for purchases that repeat without a schedule.
+#[post("/transactions/<id>/duplicate")]
+fn duplicate_transaction(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+) -> Result<Redirect, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let mut copy = db::transaction_by_id(&conn, id)
+        .map_err(|_| rocket::http::Status::InternalServerError)?
+        .ok_or(rocket::http::Status::NotFound)?;
+    copy.occurred_on = today_ymd();
+    let copy_id = db::insert_transaction(&conn, &copy, None)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    db::copy_transaction_tags(&conn, id, copy_id)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    if let (Some(category_id), "expense") = (copy.category_id, copy.kind.as_str()) {
+        let month = effective_months::month_of(&copy.occurred_on, None);
+        notify_budget_exceeded(&conn, user.id, category_id, month, copy.amount_cents);
+    }
+    Ok(Redirect::to("/transactions"))
+}
+
+/// Stars a transaction worth finding again, or takes the star off.
+#[post("/transactions/<id>/star", data = "<form>")]
+fn star_transaction(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+    form: Form<StarForm>,
+) -> Result<Redirect, AppError> {
+    require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let found = db::set_transaction_starred(&conn, id, form.starred)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    if !found {
+        return Err(rocket::http::Status::NotFound.into());
+    }
+    Ok(Redirect::to("/transactions"))
+}
+
+/// Moves a transaction to the trash; revaluation adjustments can't be deleted
+/// on their own.
+#[post("/transactions/<id>/delete")]
+fn delete_transaction(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+) -> Result<Redirect, AppError> {
+    require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let trashed = db::trash_transaction(&conn, id, &trash::deleted_at(chrono::Utc::now()))
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    if !trashed {
+        return Err(rocket::http::Status::BadRequest.into());
+    }
+    Ok(Redirect::to("/transactions"))
+}
+
+#[post("/transactions/transfer", data = "<form>")]
+fn add_transfer(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    form: Form<TransferForm>,
+) -> Result<Flash<Redirect>, AppError> {
+    let user = require_user(pool, cookies)?;
+    let form = form.into_inner();
+    let mut sent = FormState::default();
+    let account = |id: Option<i64>| id.map(|id| id.to_string()).unwrap_or_default();
+    sent.value("from_account_id", &account(form.from_account_id));
+    sent.value("to_account_id", &account(form.to_account_id));
+    sent.value("amount", &form.amount);
+    sent.value("occurred_on", &form.occurred_on);
+    sent.value("note", form.note.as_deref().unwrap_or(""));
+    match (form.from_account_id, form.to_account_id) {
+        (None, _) => sent.error("from_account_id", "Выберите счет"),
+        (_, None) => sent.error("to_account_id", "Выберите счет"),
+        (from, to) if from == to => sent.error("to_account_id", "Счета должны различаться"),
+        _ => {}
+    }
+    let amount_cents = form_amount(&mut sent, "amount", &form.amount);
+    let occurred_on = form_date(&mut sent, "occurred_on", &form.occurred_on);
+    let conn = pool.get()?;
+    if !sent.is_valid() {
+        let filter = TransactionFilter::default();
+        let page = render_transactions(&conn, &user, filter, None, FormState::default(), sent)?;
+        return Err(AppError::Invalid(Box::new(page)));
+    }
+
+    let transfer = NewTransaction {
+        kind: "transfer".to_string(),
+        amount_cents,
+        category_id: None,
+        occurred_on,
+        note: form.note,
+        payee: None,
+        account_id: form.from_account_id,
+        to_account_id: form.to_account_id,
+    };
+    db::insert_transaction(&conn, &transfer, None)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(Flash::success(Redirect::to("/transactions"), "Перевод записан"))
+}
+
+/// Tells the user when an expense of `amount_cents` took the category's
+/// budget for `month`, the month the expense counts toward, over its limit.
+fn notify_budget_exceeded(
+    conn: &rusqlite::Connection,
+    user_id: i64,
+    category_id: i64,
+    month: &str,
+    amount_cents: i64,
+) {
+    let refunds = db::refund_month(conn, user_id).unwrap_or_default();
+    let budgets = db::list_budgets(conn, month, refunds).unwrap_or_default();
+    let Some(budget) = budgets.iter().find(|b| b.category_id == category_id) else {
+        return;
+    };
+    let limit = budget.amount_cents + budget.carried_cents;
+    let spent_before = budget.spent_cents - amount_cents;
+    if spent_before <= limit && budget.spent_cents > limit {
+        let payload = serde_json::json!({ "category_id": category_id, "month": month });
+        let mut body = format!(
+            "Потрачено {} из {} за {}",
+            format_money(budget.spent_cents),
+            format_money(limit),
+            month
+        );
+        if let Some(note) = &budget.note {
+            body.push_str(&format!("\nЦель: {note}"));
+        }
+        let _ = notifications::dispatch(
+            conn,
+            user_id,
+            &NewNotification {
+                event: "budget_exceeded".to_string(),
+                title: format!("Превышен бюджет: {}", budget.category_name),
+                body,
+                payload: Some(payload.to_string()),
+            },
+        );
+    }
+}
+
+fn render_import(username: &str, error: Option<&str>) -> Template {
+    Template::render(
+        "import",
+        serde_json::json!({
+            "username": username,
+            "error": error,
+        }),
+    )
+}
+
+fn read_import(token: &str) -> Result<Vec<Vec<String>>, &'static str> {
+    let token = Uuid::parse_str(token).map_err(|_| "Файл импорта не найден")?;
+    let path = import::imports_dir().join(format!("{token}.csv"));
+    let content = std::fs::read(&path).map_err(|_| "Файл импорта не найден")?;
+    let content = String::from_utf8(content).map_err(|_| "Файл должен быть в кодировке UTF-8")?;
+    import::read_rows(&content).map_err(|_| "Не удалось прочитать CSV")
+}
+
+#[get("/import")]
+fn import_page(pool: &State<DbPool>, cookies: &CookieJar<'_>) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    Ok(render_import(&user.username, None))
+}
+
+#[post("/import", data = "<form>")]
+async fn import_upload(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    form: Form<ImportUploadForm<'_>>,
+) -> Result<Redirect, AppError> {
+    let user = require_user(pool, cookies)?;
+    let mut form = form.into_inner();
+    let token = Uuid::new_v4();
+    let dir = import::imports_dir();
+    std::fs::create_dir_all(&dir)
+        .map_err(|_| render_import(&user.username, Some("Не удалось сохранить файл")))?;
+    form.file
+        .persist_to(dir.join(format!("{token}.csv")))
+        .await
+        .map_err(|_| render_import(&user.username, Some("Не удалось сохранить файл")))?;
+    Ok(Redirect::to(format!("/import/{token}")))
+}
+
+fn render_import_preview(
+    conn: &rusqlite::Connection,
+    username: &str,
+    token: &str,
+    rows: &[Vec<String>],
+    mapping: &import::ImportMapping,
+    notice: Option<&str>,
+) -> Template {
+    let categories = db::list_categories(conn).unwrap_or_default();
+    let parsed = import::parse_rows(rows, mapping, &categories);
+    let valid = parsed.iter().filter(|row| row.error.is_none()).count();
+    let preview = parsed
+        .iter()
+        .take(100)
+        .map(|row| {
+            serde_json::json!({
+                "line": row.line,
+                "occurred_on": row.occurred_on,
+                "kind": row.kind,
+                "amount": format_money(row.amount_cents),
+                "note": row.note,
+                "category_name": row.category_name,
+                "category_matched": row.category_id.is_some(),
+                "error": row.error,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let context = serde_json::json!({
+        "username": username,
+        "token": token,
+        "notice": notice,
+        "columns": import::column_names(rows, mapping.has_header.unwrap_or(false)),
+        "mapping": {
+            "date_col": mapping.date_col,
+            "amount_col": mapping.amount_col,
+            "note_col": mapping.note_col,
+            "category_col": mapping.category_col,
+            "has_header": mapping.has_header,
+            "positive_as": mapping.positive_as,
+        },
+        "rows": preview,
+        "total": parsed.len(),
+        "valid": valid,
+        "invalid": parsed.len() - valid,
+    });
+    Template::render("import", &context)
+}
+
+#[get("/import/<token>?<mapping..>")]
+fn import_preview(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    token: &str,
+    mapping: import::ImportMapping,
+) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let rows = match read_import(token) {
+        Ok(rows) => rows,
+        Err(error) => return Ok(render_import(&user.username, Some(error))),
+    };
+    let conn = pool.get()?;
+    let mapping = import::resolve_mapping(&rows, mapping);
+    Ok(render_import_preview(&conn, &user.username, token, &rows, &mapping, None))
+}
+
+/// Imports run as a background job: the rows are inserted in chunks of this
+/// many, one undoable operation in a single transaction.
+const IMPORT_CHUNK: usize = 500;
+
+#[post("/import/<token>?<dry_run>", data = "<form>")]
+fn import_commit(
+    pool: &State<DbPool>,
+    jobs: &State<jobs::Jobs>,
+    cookies: &CookieJar<'_>,
+    token: &str,
+    dry_run: bool,
+    form: Form<import::ImportMapping>,
+) -> Result<Redirect, AppError> {
+    let user = require_user(pool, cookies)?;
+    let rows = read_import(token).map_err(|error| render_import(&user.username, Some(error)))?;
+    let mut conn = pool
+        .get()
+        .map_err(|_| render_import(&user.username, Some("Ошибка подключения к базе")))?;
+    let categories = db::list_categories(&conn).unwrap_or_default();
+    let rules = db::list_rules(&conn).unwrap_or_default();
+    let mapping = import::resolve_mapping(&rows, form.into_inner());
+    let mut batch = import::parse_rows(&rows, &mapping, &categories)
+        .iter()
+        .filter(|row| row.error.is_none())
+        .map(import::ParsedRow::to_new_transaction)
+        .collect::<Vec<_>>();
+    let tags = batch
+        .iter_mut()
+        .map(|row| rules::apply(&rules, &categories, row))
+        .collect::<Vec<_>>();
+    let label = format!("Импорт CSV, операций: {}", batch.len());
+    if dry_run {
+        let outcome = bulk::run(&mut conn, true, "import", &label, |tx, _| {
+            db::insert_transactions_batch(tx, &batch)
+        })
+        .map_err(|_| render_import(&user.username, Some("Не удалось сохранить операции")))?;
+        let notice = format!(
+            "Пробный запуск: будет добавлено операций — {}. Данные не изменены.",
+            outcome.changes.len()
+        );
+        return Err(render_import_preview(
+            &conn,
+            &user.username,
+            token,
+            &rows,
+            &mapping,
+            Some(&notice),
+        )
+        .into());
+    }
+
+    let pool = pool.inner().clone();
+    let token = token.to_string();
+    let job_label = label.clone();
+    let spawned = jobs.spawn(&conn, user.id, &job_label, move |progress| {
+        let mut conn = pool.get().map_err(|_| "Ошибка подключения к базе")?;
+        progress.set_total(batch.len() as u64);
+        bulk::run(&mut conn, false, "import", &label, |tx, changes| {
+            let mut ids = Vec::with_capacity(batch.len());
+            for (chunk, chunk_tags) in batch.chunks(IMPORT_CHUNK).zip(tags.chunks(IMPORT_CHUNK)) {
+                let inserted = db::insert_transactions_batch(tx, chunk)?;
+                for (id, names) in inserted.into_iter().zip(chunk_tags) {
+                    db::add_transaction_tags(tx, id, names)?;
+                    changes.inserted("transactions", id);
+                    ids.push(id);
+                }
+                progress.advance(chunk.len() as u64);
+            }
+            Ok(ids)
+        })
+        .map_err(|_| "Не удалось сохранить операции")?;
+        let _ = std::fs::remove_file(import::imports_dir().join(format!("{token}.csv")));
+        Ok(jobs::Outcome::Page("/transactions".to_string()))
+    });
+    let id = spawned
+        .map_err(|_| render_import(&user.username, Some("Не удалось запустить импорт")))?;
+    Ok(Redirect::to(format!("/jobs/{id}")))
+}
+
+/// The month's transactions and receipts as a ZIP, built in the background.
+#[post("/transactions/archive", data = "<form>")]
+fn export_archive(
+    pool: &State<DbPool>,
+    jobs: &State<jobs::Jobs>,
+    cookies: &CookieJar<'_>,
+    storage: &State<receipt_storage::Receipts>,
+    form: Form<ArchiveForm>,
+) -> Result<Redirect, AppError> {
+    let user = require_user(pool, cookies)?;
+    let month = selected_month(Some(form.into_inner().month));
+    if fx::month_end(&month).is_none() {
+        return Err(rocket::http::Status::BadRequest.into());
+    }
+    let conn = pool.get()?;
+    let (pool, storage) = (pool.inner().clone(), storage.inner().clone());
+    let label = format!("Архив с чеками за {month}");
+    let spawned = jobs.spawn(&conn, user.id, &label, move |progress| {
+        let conn = pool.get().map_err(|_| "Ошибка подключения к базе")?;
+        let archive = accountant::build_archive(&conn, &*storage, &month, |_, total| {
+            progress.set_total(total as u64);
+            progress.advance(1);
+        })?;
+        let dir = jobs::exports_dir();
+        let path = dir.join(format!("{}.zip", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir)
+            .and_then(|_| std::fs::write(&path, archive))
+            .map_err(|_| "Не удалось сохранить архив")?;
+        Ok(jobs::Outcome::File {
+            path,
+            name: format!("lumen-{month}.zip"),
+        })
+    });
+    let id = spawned.map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(Redirect::to(format!("/jobs/{id}")))
+}
+
+/// The user's own job; anyone else's is as good as missing.
+fn user_job(
+    jobs: &jobs::Jobs,
+    conn: &rusqlite::Connection,
+    user: &User,
+    id: i64,
+) -> Result<Job, AppError> {
+    jobs.get(conn, id)
+        .map_err(|_| rocket::http::Status::InternalServerError)?
+        .filter(|job| job.user_id == user.id)
+        .ok_or_else(|| rocket::http::Status::NotFound.into())
+}
+
+#[get("/jobs/<id>")]
+fn job_page(
+    pool: &State<DbPool>,
+    jobs: &State<jobs::Jobs>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let job = user_job(jobs, &conn, &user, id)?;
+    let context = serde_json::json!({
+        "username": user.username,
+        "percent": jobs::percent(&job),
+        "job": job,
+    });
+    Ok(Template::render("job", &context))
+}
+
+/// State and progress of a job, polled by its page.
+#[get("/api/v1/jobs/<id>")]
+fn api_job(
+    pool: &State<DbPool>,
+    jobs: &State<jobs::Jobs>,
+    api: api_tokens::Api<api_tokens::Read>,
+    id: i64,
+) -> Result<(rocket::http::ContentType, String), AppError> {
+    let conn = pool.get()?;
+    let job = user_job(jobs, &conn, &api.user, id)?;
+    let body = serde_json::json!({
+        "id": job.id,
+        "label": job.label,
+        "state": job.state,
+        "percent": jobs::percent(&job),
+        "done": job.done,
+        "total": job.total,
+        "error": job.error,
+        "created_at": job.created_at,
+        "finished_at": job.finished_at,
+        "links": {
+            "self": format!("/api/v1/jobs/{}", job.id),
+            "page": format!("/jobs/{}", job.id),
+            "result": job.result_url,
+        },
+    });
+    Ok((rocket::http::ContentType::JSON, body.to_string()))
+}
+
+type JsonResult = Result<(rocket::http::ContentType, String), (rocket::http::Status, String)>;
+
+fn json_body<T: serde::de::DeserializeOwned>(body: &str) -> Result<T, (rocket::http::Status, String)> {
+    serde_json::from_str(body)
+        .map_err(|err| (rocket::http::Status::BadRequest, format!("некорректный JSON: {err}")))
+}
+
+/// Signs an API client in with the password; answers with a token pair.
+#[post("/api/v1/auth/token", data = "<body>")]
+fn api_sign_in(pool: &State<DbPool>, body: String) -> JsonResult {
+    let input: ApiSignIn = json_body(&body)?;
+    let conn = pool
+        .get()
+        .map_err(|_| (rocket::http::Status::InternalServerError, String::new()))?;
+    let denied = || (rocket::http::Status::Unauthorized, "неверный логин или пароль".to_string());
+    let creds = db::user_credentials(&conn, input.username.trim())
+        .map_err(|_| (rocket::http::Status::InternalServerError, String::new()))?;
+    let user_id = verify_login(creds, &input.password).ok_or_else(denied)?;
+    let pair = api_sessions::sign_in(&conn, user_id, chrono::Utc::now())
+        .map_err(|_| (rocket::http::Status::InternalServerError, String::new()))?;
+    let _ = notifications::dispatch(
+        &conn,
+        user_id,
+        &NewNotification {
+            event: "login".to_string(),
+            title: "Вход в аккаунт".to_string(),
+            body: format!("Выполнен вход в аккаунт {} через API", input.username.trim()),
+            payload: None,
+        },
+    );
+    let body = serde_json::to_string(&pair).unwrap_or_default();
+    Ok((rocket::http::ContentType::JSON, body))
+}
+
+/// Trades a refresh token for the next pair. A token used twice signs the
+/// whole chain out.
+#[post("/api/v1/auth/refresh", data = "<body>")]
+fn api_refresh(pool: &State<DbPool>, body: String) -> JsonResult {
+    let input: ApiRefresh = json_body(&body)?;
+    let mut conn = pool
+        .get()
+        .map_err(|_| (rocket::http::Status::InternalServerError, String::new()))?;
+    let refreshed = api_sessions::refresh(&mut conn, input.refresh_token.trim(), chrono::Utc::now())
+        .map_err(|_| (rocket::http::Status::InternalServerError, String::new()))?;
+    let pair = refreshed.map_err(|refused| {
+        let reason = match refused {
+            api_sessions::Refused::Unknown => "неизвестный токен",
+            api_sessions::Refused::Expired => "срок действия токена истек",
+            api_sessions::Refused::Revoked => "токен отозван",
+            api_sessions::Refused::Reused => "токен уже использован, все сессии цепочки отозваны",
+        };
+        (rocket::http::Status::Unauthorized, reason.to_string())
+    })?;
+    let body = serde_json::to_string(&pair).unwrap_or_default();
+    Ok((rocket::http::ContentType::JSON, body))
+}
+
+/// Signs out the client holding the refresh token.
+#[post("/api/v1/auth/revoke", data = "<body>")]
+fn api_revoke(
+    pool: &State<DbPool>,
+    body: String,
+) -> Result<rocket::http::Status, (rocket::http::Status, String)> {
+    let input: ApiRefresh = json_body(&body)?;
+    let conn = pool
+        .get()
+        .map_err(|_| (rocket::http::Status::InternalServerError, String::new()))?;
+    api_sessions::revoke(&conn, input.refresh_token.trim(), chrono::Utc::now())
+        .map_err(|_| (rocket::http::Status::InternalServerError, String::new()))?;
+    Ok(rocket::http::Status::NoContent)
+}
+
+/// Transactions of a month, the current one by default.
+/// The month's transactions; `starred=true` keeps only starred ones.
+#[get("/api/v1/transactions?<month>&<starred>")]
+fn api_transactions(
+    pool: &State<DbPool>,
+    _api: api_tokens::Api<api_tokens::Read>,
+    month: Option<String>,
+    starred: Option<bool>,
+) -> Result<(rocket::http::ContentType, String), AppError> {
+    let month = month.unwrap_or_else(current_month);
+    let conn = pool.get()?;
+    let query = TransactionQuery {
+        starred: starred.unwrap_or(false),
+        ..TransactionQuery::month(&month)
+    };
+    let transactions = db::list_transactions(&conn, &query)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    let body = serde_json::json!({ "month": month, "transactions": transactions });
+    Ok((rocket::http::ContentType::JSON, body.to_string()))
+}
+
+/// Records a transaction the way the form does: rules apply and budgets are
+/// checked. Answers 201 with the new id.
+#[post("/api/v1/transactions", data = "<body>")]
+fn api_add_transaction(
+    pool: &State<DbPool>,
+    api: api_tokens::Api<api_tokens::Write>,
+    body: String,
+) -> Result<(rocket::http::Status, (rocket::http::ContentType, String)), (rocket::http::Status, String)> {
+    let input: ApiTransaction = json_body(&body)?;
+    if !matches!(input.kind.as_str(), "income" | "expense") {
+        return Err((rocket::http::Status::UnprocessableEntity, "kind: income или expense".to_string()));
+    }
+    if input.amount_cents <= 0 {
+        return Err((rocket::http::Status::UnprocessableEntity, "amount_cents: больше нуля".to_string()));
+    }
+    let occurred_on = match input.occurred_on.as_deref().map(str::trim) {
+        None | Some("") => today_ymd(),
+        Some(date) => NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map(|date| date.format("%Y-%m-%d").to_string())
+            .map_err(|_| (rocket::http::Status::UnprocessableEntity, "occurred_on: ГГГГ-ММ-ДД".to_string()))?,
+    };
+    let conn = pool
+        .get()
+        .map_err(|_| (rocket::http::Status::InternalServerError, String::new()))?;
+    let mut transaction = NewTransaction {
+        kind: input.kind,
+        amount_cents: input.amount_cents,
+        category_id: input.category_id,
+        occurred_on,
+        note: input.note.as_deref().and_then(optional_field),
+        payee: input.payee.as_deref().and_then(optional_field),
+        account_id: input.account_id,
+        to_account_id: None,
+    };
+    let rules = db::list_rules(&conn).unwrap_or_default();
+    let categories = db::list_categories(&conn).unwrap_or_default();
+    let mut tags = parse_tags(input.tags.as_deref().unwrap_or(""));
+    for tag in rules::apply(&rules, &categories, &mut transaction) {
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    let transaction_id = db::insert_transaction(&conn, &transaction, None)
+        .map_err(|_| (rocket::http::Status::UnprocessableEntity, "неизвестная категория или счет".to_string()))?;
+    let _ = db::add_transaction_tags(&conn, transaction_id, &tags);
+    if input.starred {
+        let _ = db::set_transaction_starred(&conn, transaction_id, true);
+    }
+    if let (Some(category_id), "expense") = (transaction.category_id, transaction.kind.as_str()) {
+        notify_budget_exceeded(
+            &conn,
+            api.user.id,
+            category_id,
+            effective_months::month_of(&transaction.occurred_on, None),
+            transaction.amount_cents,
+        );
+    }
+    let body = serde_json::json!({ "id": transaction_id });
+    Ok((rocket::http::Status::Created, (rocket::http::ContentType::JSON, body.to_string())))
+}
+
+/// Stars or unstars a transaction; 204, or 404 for one that doesn't exist.
+#[post("/api/v1/transactions/<id>/star", data = "<body>")]
+fn api_star_transaction(
+    pool: &State<DbPool>,
+    _api: api_tokens::Api<api_tokens::Write>,
+    id: i64,
+    body: String,
+) -> Result<rocket::http::Status, (rocket::http::Status, String)> {
+    let input: ApiStar = json_body(&body)?;
+    let conn = pool
+        .get()
+        .map_err(|_| (rocket::http::Status::InternalServerError, String::new()))?;
+    match db::set_transaction_starred(&conn, id, input.starred) {
+        Ok(true) => Ok(rocket::http::Status::NoContent),
+        Ok(false) => Err((rocket::http::Status::NotFound, String::new())),
+        Err(_) => Err((rocket::http::Status::InternalServerError, String::new())),
+    }
+}
+
+/// The caller's tokens, without their values.
+#[get("/api/v1/tokens")]
+fn api_token_list(
+    pool: &State<DbPool>,
+    api: api_tokens::Api<api_tokens::Admin>,
+) -> Result<(rocket::http::ContentType, String), AppError> {
+    let conn = pool.get()?;
+    let tokens = db::list_api_tokens(&conn, api.user.id)
+        .map_err(|_| rocket::http::Status::InternalServerError)?
+        .into_iter()
+        .map(|token| {
+            serde_json::json!({
+                "id": token.id,
+                "name": token.name,
+                "scope": token.scope,
+                "created_at": token.created_at,
+                "last_used_at": token.last_used_at,
+            })
+        })
+        .collect::<Vec<_>>();
+    let body = serde_json::json!({ "tokens": tokens });
+    Ok((rocket::http::ContentType::JSON, body.to_string()))
+}
+
+/// A copy of the whole database, for backup scripts without access to the
+/// server's files.
+#[get("/api/v1/backup")]
+async fn api_backup(
+    pool: &State<DbPool>,
+    _api: api_tokens::Api<api_tokens::Admin>,
+) -> Result<backup::Backup, AppError> {
+    let conn = pool.get()?;
+    // VACUUM INTO reads the whole database, too long for an async worker.
+    let written = rocket::tokio::task::spawn_blocking(move || backup::write(&conn, &backup::dir())).await;
+    let Ok(Ok(path)) = written else {
+        return Err(rocket::http::Status::InternalServerError.into());
+    };
+    let name = backup::file_name(Local::now().date_naive());
+    backup::Backup::open(&path, name)
+        .await
+        .ok_or_else(|| rocket::http::Status::InternalServerError.into())
+}
+
+#[get("/jobs/<id>/download")]
+async fn job_download(
+    pool: &State<DbPool>,
+    jobs: &State<jobs::Jobs>,
+    cookies: &CookieJar<'_>,
+    range: Option<jobs::ByteRange>,
+    id: i64,
+) -> Result<jobs::Download, AppError> {
+    let user = require_user(pool, cookies)?;
+    let job = {
+        let conn = pool.get()?;
+        user_job(jobs, &conn, &user, id)?
+    };
+    jobs::Download::open(&job, range)
+        .await
+        .ok_or_else(|| rocket::http::Status::NotFound.into())
+}
+
+/// Generated files still kept, to fetch again without rebuilding them.
+#[get("/downloads")]
+fn downloads(pool: &State<DbPool>, cookies: &CookieJar<'_>) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    jobs::expire(&conn);
+    let now = Local::now().to_rfc3339();
+    let files = db::list_downloads(&conn, user.id, &now)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|job| {
+            let size = job
+                .file_path
+                .as_ref()
+                .and_then(|path| std::fs::metadata(path).ok())
+                .map(|metadata| metadata.len());
+            serde_json::json!({
+                "label": job.label,
+                "name": job.file_name,
+                "url": format!("/jobs/{}/download", job.id),
+                "size": size.map(format_size),
+                "created_at": job.created_at,
+                "expires_at": job.expires_at,
+            })
+        })
+        .collect::<Vec<_>>();
+    let context = serde_json::json!({
+        "username": user.username,
+        "files": files,
+        "days": jobs::DOWNLOAD_DAYS,
+    });
+    Ok(Template::render("downloads", &context))
+}
+
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..1024 => format!("{bytes} Б"),
+        1024..1_048_576 => format!("{:.1} КБ", bytes as f64 / 1024.0),
+        _ => format!("{:.1} МБ", bytes as f64 / 1_048_576.0),
+    }
+}
+
+#[get("/trash")]
+fn trash_page(pool: &State<DbPool>, cookies: &CookieJar<'_>) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let items = db::list_trash(&conn)
+        .map_err(|_| rocket::http::Status::InternalServerError)?
+        .into_iter()
+        .map(|item| {
+            let deleted = chrono::DateTime::parse_from_rfc3339(&item.deleted_at)
+                .map(|at| at.with_timezone(&Local))
+                .ok();
+            TrashView {
+                what: match item.table.as_str() {
+                    "transactions" => "Операция",
+                    "budgets" => "Бюджет",
+                    _ => "Ежемесячный бюджет",
+                },
+                occurred_on: item.occurred_on,
+                category_name: item.category_name,
+                note: item.note,
+                amount: format_money(item.amount_cents),
+                deleted_at: deleted
+                    .map(|at| at.format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or(item.deleted_at),
+                purged_on: deleted
+                    .map(|at| (at + trash::RETENTION).format("%Y-%m-%d").to_string())
+                    .unwrap_or_default(),
+                table: item.table,
+                id: item.id,
+            }
+        })
+        .collect::<Vec<_>>();
+    let context = serde_json::json!({
+        "username": user.username,
+        "items": items,
+        "retention_days": trash::RETENTION.num_days(),
+    });
+    Ok(Template::render("trash", &context))
+}
+
+/// Open disputes, longest waiting first, then the latest refunds.
+#[get("/disputes")]
+fn disputes_page(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    flash: Option<FlashMessage<'_>>,
+) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    Ok(render_disputes(&conn, &user, flash, FormState::default()))
+}
+
+fn render_disputes(
+    conn: &rusqlite::Connection,
+    user: &User,
+    flash: Option<FlashMessage<'_>>,
+    refund_form: FormState,
+) -> Template {
+    let today = Local::now().date_naive();
+    let disputes = db::list_disputes(conn, 20)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|dispute| DisputeView {
+            transaction_id: dispute.transaction_id,
+            occurred_on: dispute.occurred_on,
+            amount: format_money(dispute.amount_cents),
+            category_name: dispute.category_name,
+            payee: dispute.payee,
+            note: dispute.note,
+            days_open: dispute
+                .disputed_on
+                .as_deref()
+                .filter(|_| dispute.status == "disputed")
+                .and_then(|on| disputes::days_open(on, today)),
+            status: dispute.status,
+            disputed_on: dispute.disputed_on,
+            refund: dispute.refund_cents.map(format_money),
+            refunded_on: dispute.refunded_on,
+        })
+        .collect::<Vec<_>>();
+    let context = serde_json::json!({
+        "username": user.username,
+        "disputes": disputes,
+        "today": today_ymd(),
+        "flash": flash,
+        "refund_form": refund_form,
+    });
+    Template::render("disputes", &context)
+}
+
+/// Opens a dispute over an expense, from today.
+#[post("/transactions/<id>/dispute")]
+fn open_dispute(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+) -> Result<Flash<Redirect>, AppError> {
+    require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let opened = db::dispute_transaction(&conn, id, &today_ymd())
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    if !opened {
+        return Err(rocket::http::Status::BadRequest.into());
+    }
+    Ok(Flash::success(Redirect::to("/disputes"), "Спор открыт"))
+}
+
+/// Records money coming back for a disputed or plain expense.
+#[post("/disputes/<id>/refund", data = "<form>")]
+fn refund_dispute(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+    form: Form<RefundForm>,
+) -> Result<Flash<Redirect>, AppError> {
+    let user = require_user(pool, cookies)?;
+    let form = form.into_inner();
+    let mut conn = pool.get()?;
+    let expense = db::transaction_by_id(&conn, id)
+        .map_err(|_| rocket::http::Status::InternalServerError)?
+        .filter(|expense| expense.kind == "expense")
+        .ok_or(rocket::http::Status::NotFound)?;
+    let mut sent = FormState::for_row(id);
+    sent.value("amount", &form.amount);
+    sent.value("occurred_on", &form.occurred_on);
+    let amount_cents = match form.amount.trim() {
+        "" => expense.amount_cents,
+        value => form_amount(&mut sent, "amount", value),
+    };
+    if amount_cents > expense.amount_cents {
+        sent.error("amount", "Возврат не может быть больше покупки");
+    }
+    let occurred_on = form_date(&mut sent, "occurred_on", &form.occurred_on);
+    if !sent.is_valid() {
+        let page = render_disputes(&conn, &user, None, sent);
+        return Err(AppError::Invalid(Box::new(page)));
+    }
+    let tx = conn
+        .transaction()
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    let refund = db::record_refund(&tx, id, amount_cents, &occurred_on)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    if refund.is_none() {
+        return Err(rocket::http::Status::BadRequest.into());
+    }
+    tx.commit()
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(Flash::success(Redirect::to("/disputes"), "Возврат записан"))
+}
+
+/// Closes a dispute that ended without money back.
+#[post("/disputes/<id>/close")]
+fn close_dispute(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+) -> Result<Flash<Redirect>, AppError> {
+    require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let closed = db::close_dispute(&conn, id)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    if !closed {
+        return Err(rocket::http::Status::NotFound.into());
+    }
+    Ok(Flash::success(Redirect::to("/disputes"), "Спор закрыт без возврата"))
+}
+
+/// The lines of an expense's receipt and the form to add one.
+#[get("/transactions/<id>/items")]
+fn receipt_items_page(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+    flash: Option<FlashMessage<'_>>,
+) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    render_receipt_items(&conn, &user, id, flash, FormState::default())
+}
+
+fn render_receipt_items(
+    conn: &rusqlite::Connection,
+    user: &User,
+    id: i64,
+    flash: Option<FlashMessage<'_>>,
+    item_form: FormState,
+) -> Result<Template, AppError> {
+    let expense = db::transaction_by_id(conn, id)
+        .map_err(|_| rocket::http::Status::InternalServerError)?
+        .filter(|expense| expense.kind == "expense")
+        .ok_or(rocket::http::Status::NotFound)?;
+    let items = db::list_receipt_items(conn, id)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    let listed_cents: i64 = items
+        .iter()
+        .map(|item| receipt_items::sum_cents(item.quantity, item.price_cents))
+        .sum();
+    let unlisted = (!items.is_empty() && listed_cents != expense.amount_cents)
+        .then(|| format_money(expense.amount_cents - listed_cents));
+    let items = items
+        .into_iter()
+        .map(|item| {
+            serde_json::json!({
+                "id": item.id,
+                "name": item.name,
+                "quantity": receipt_items::format_quantity(item.quantity),
+                "price": format_money(item.price_cents),
+                "sum": format_money(receipt_items::sum_cents(item.quantity, item.price_cents)),
+            })
+        })
+        .collect::<Vec<_>>();
+    let context = serde_json::json!({
+        "username": user.username,
+        "transaction_id": id,
+        "occurred_on": expense.occurred_on,
+        "payee": expense.payee,
+        "note": expense.note,
+        "amount": format_money(expense.amount_cents),
+        "items": items,
+        "listed": format_money(listed_cents),
+        "unlisted": unlisted,
+        "flash": flash,
+        "item_form": item_form,
+    });
+    Ok(Template::render("receipt_items", &context))
+}
+
+#[post("/transactions/<id>/items", data = "<form>")]
+fn add_receipt_item(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+    form: Form<ReceiptItemForm>,
+) -> Result<Flash<Redirect>, AppError> {
+    let user = require_user(pool, cookies)?;
+    let form = form.into_inner();
+    let conn = pool.get()?;
+    let mut sent = FormState::default();
+    sent.value("name", &form.name);
+    sent.value("quantity", &form.quantity);
+    sent.value("price", &form.price);
+    let name = db::clean_category_name(&form.name);
+    if name.is_empty() {
+        sent.error("name", "Введите название");
+    }
+    let quantity = match receipt_items::parse_quantity(&form.quantity) {
+        Some(quantity) => quantity,
+        None => {
+            sent.error("quantity", "Количество больше нуля, например 2 или 0.5");
+            0.0
+        }
+    };
+    let price_cents = form_amount(&mut sent, "price", &form.price);
+    if !sent.is_valid() {
+        let page = render_receipt_items(&conn, &user, id, None, sent)?;
+        return Err(AppError::Invalid(Box::new(page)));
+    }
+    db::transaction_by_id(&conn, id)
+        .map_err(|_| rocket::http::Status::InternalServerError)?
+        .filter(|expense| expense.kind == "expense")
+        .ok_or(rocket::http::Status::NotFound)?;
+    let item = NewReceiptItem {
+        name,
+        quantity,
+        price_cents,
+    };
+    db::add_receipt_items(&conn, id, &[item])
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(Flash::success(
+        Redirect::to(format!("/transactions/{id}/items")),
+        "Позиция добавлена",
+    ))
+}
+
+#[post("/transactions/<id>/items/<item_id>/delete")]
+fn delete_receipt_item(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+    item_id: i64,
+) -> Result<Flash<Redirect>, AppError> {
+    require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let deleted = db::delete_receipt_item(&conn, id, item_id)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    if !deleted {
+        return Err(rocket::http::Status::NotFound.into());
+    }
+    Ok(Flash::success(
+        Redirect::to(format!("/transactions/{id}/items")),
+        "Позиция удалена",
+    ))
+}
+
+/// How far back expenses are searched for withdrawals entered as spending.
+const WITHDRAWAL_LOOKBACK_DAYS: u64 = 90;
+
+/// Cash accounts as envelopes for the month, the withdrawal form and expenses
+/// that look like withdrawals.
+#[get("/cash?<month>")]
+fn cash_page(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    month: Option<String>,
+    flash: Option<FlashMessage<'_>>,
+) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    render_cash(&conn, &user, selected_month(month), flash, FormState::default())
+}
+
+fn render_cash(
+    conn: &rusqlite::Connection,
+    user: &User,
+    selected: String,
+    flash: Option<FlashMessage<'_>>,
+    withdrawal_form: FormState,
+) -> Result<Template, AppError> {
+    let accounts = db::list_accounts(conn).unwrap_or_default();
+    let envelopes = db::cash_envelopes(conn, &selected)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|envelope| {
+            let balance_cents = accounts
+                .iter()
+                .find(|account| account.id == envelope.account_id)
+                .map_or(0, |account| account.balance_cents);
+            CashEnvelopeView {
+                account_id: envelope.account_id,
+                name: envelope.name,
+                balance: format_money(balance_cents),
+                overdrawn: balance_cents < 0,
+                withdrawn: format_money(envelope.withdrawn_cents),
+                spent: format_money(envelope.spent_cents),
+                last_withdrawal_on: envelope.last_withdrawal_on,
+            }
+        })
+        .collect::<Vec<_>>();
+    let since = Local::now().date_naive() - chrono::Days::new(WITHDRAWAL_LOOKBACK_DAYS);
+    let candidates = db::withdrawal_candidates(conn, &since.format("%Y-%m-%d").to_string())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|candidate| {
+            [&candidate.payee, &candidate.note]
+                .into_iter()
+                .flatten()
+                .any(|text| cash::looks_like_withdrawal(text))
+        })
+        .map(|candidate| WithdrawalCandidateView {
+            transaction_id: candidate.transaction_id,
+            occurred_on: candidate.occurred_on,
+            amount: format_money(candidate.amount_cents),
+            account_name: candidate.account_name,
+            payee: candidate.payee,
+            note: candidate.note,
+        })
+        .collect::<Vec<_>>();
+    let (cash_accounts, other_accounts): (Vec<_>, Vec<_>) =
+        accounts.into_iter().partition(|account| account.kind == "cash");
+    let nav = month_nav(conn, "/cash", &selected)?;
+    let context = serde_json::json!({
+        "username": user.username,
+        "month": selected,
+        "nav": nav,
+        "envelopes": envelopes,
+        "candidates": candidates,
+        "cash_accounts": cash_accounts,
+        "other_accounts": other_accounts,
+        "today": today_ymd(),
+        "flash": flash,
+        "withdrawal_form": withdrawal_form,
+    });
+    Ok(Template::render("cash", &context))
+}
+
+/// Records cash taken from a card or bank account as a transfer to a cash
+/// account, and the bank's fee, if any, as an expense of the card.
+#[post("/cash/withdrawals", data = "<form>")]
+fn add_withdrawal(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    form: Form<WithdrawalForm>,
+) -> Result<Flash<Redirect>, AppError> {
+    let user = require_user(pool, cookies)?;
+    let form = form.into_inner();
+    let mut sent = FormState::default();
+    let account = |id: Option<i64>| id.map(|id| id.to_string()).unwrap_or_default();
+    sent.value("from_account_id", &account(form.from_account_id));
+    sent.value("to_account_id", &account(form.to_account_id));
+    sent.value("amount", &form.amount);
+    sent.value("fee", &form.fee);
+    sent.value("occurred_on", &form.occurred_on);
+    let mut conn = pool.get()?;
+    let accounts = db::list_accounts(&conn).unwrap_or_default();
+    let kind = |id: Option<i64>| {
+        accounts
+            .iter()
+            .find(|account| Some(account.id) == id)
+            .map(|account| account.kind.as_str())
+    };
+    if kind(form.from_account_id).is_none_or(|kind| kind == "cash") {
+        sent.error("from_account_id", "Выберите карту или счет");
+    }
+    if kind(form.to_account_id) != Some("cash") {
+        sent.error("to_account_id", "Выберите счет наличных");
+    }
+    let amount_cents = form_amount(&mut sent, "amount", &form.amount);
+    let fee_cents = match form.fee.trim() {
+        "" => 0,
+        fee => form_amount(&mut sent, "fee", fee),
+    };
+    let occurred_on = form_date(&mut sent, "occurred_on", &form.occurred_on);
+    if !sent.is_valid() {
+        let page = render_cash(&conn, &user, current_month(), None, sent)?;
+        return Err(AppError::Invalid(Box::new(page)));
+    }
+
+    let withdrawal = NewTransaction {
+        kind: "transfer".to_string(),
+        amount_cents,
+        category_id: None,
+        occurred_on: occurred_on.clone(),
+        note: Some(cash::WITHDRAWAL_NOTE.to_string()),
+        payee: None,
+        account_id: form.from_account_id,
+        to_account_id: form.to_account_id,
+    };
+    let tx = conn
+        .transaction()
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    db::insert_transaction(&tx, &withdrawal, None)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    if fee_cents > 0 {
+        let fee = NewTransaction {
+            kind: "expense".to_string(),
+            amount_cents: fee_cents,
+            category_id: None,
+            occurred_on,
+            note: Some(cash::FEE_NOTE.to_string()),
+            payee: None,
+            account_id: form.from_account_id,
+            to_account_id: None,
+        };
+        db::insert_transaction(&tx, &fee, None)
+            .map_err(|_| rocket::http::Status::InternalServerError)?;
+    }
+    tx.commit()
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(Flash::success(Redirect::to("/cash"), "Снятие наличных записано"))
+}
+
+/// Turns an expense that was really an ATM withdrawal into a transfer.
+#[post("/cash/convert/<id>", data = "<form>")]
+fn convert_withdrawal(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+    form: Form<ConvertWithdrawalForm>,
+) -> Result<Flash<Redirect>, AppError> {
+    require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let converted = db::convert_to_withdrawal(&conn, id, form.to_account_id)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    if !converted {
+        return Err(rocket::http::Status::BadRequest.into());
+    }
+    Ok(Flash::success(Redirect::to("/cash"), "Расход переделан в снятие наличных"))
+}
+
+#[post("/trash/<table>/<id>/restore")]
+fn restore_from_trash(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    table: &str,
+    id: i64,
+) -> Result<Redirect, AppError> {
+    require_user(pool, cookies)?;
+    let table = trash::table(table).ok_or(rocket::http::Status::NotFound)?;
+    let conn = pool.get()?;
+    let restored = db::restore_from_trash(&conn, table, id)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    if !restored {
+        return Err(rocket::http::Status::NotFound.into());
+    }
+    Ok(Redirect::to("/trash"))
+}
+
+#[get("/activity")]
+fn activity(pool: &State<DbPool>, cookies: &CookieJar<'_>) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let operations = db::list_bulk_operations(&conn, 100).unwrap_or_default();
+    let context = serde_json::json!({
+        "username": user.username,
+        "operations": operations,
+    });
+    Ok(Template::render("activity", &context))
+}
+
+/// The activity log for a date range, change by change, as CSV or JSON.
+#[get("/activity/export?<format>&<from>&<to>")]
+fn export_activity(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    format: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<export::Attachment, AppError> {
+    let user = require_user(pool, cookies)?;
+    let date = |value: Option<String>| match value.as_deref().map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(value) => NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .map(|date| Some(date.format("%Y-%m-%d").to_string()))
+            .map_err(|_| rocket::http::Status::BadRequest),
+    };
+    let from = date(from)?;
+    let to = date(to)?;
+    let conn = pool.get()?;
+    let entries = db::audit_log(&conn, from.as_deref(), to.as_deref())
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    let range = format!(
+        "{}-{}",
+        from.as_deref().unwrap_or("start"),
+        to.as_deref().unwrap_or("now")
+    );
+    let label = format!(
+        "Журнал действий {} — {}",
+        from.as_deref().unwrap_or("с начала"),
+        to.as_deref().unwrap_or("по сегодня")
+    );
+    let format = format.unwrap_or_else(|| "csv".to_string());
+    let body = match format.as_str() {
+        "csv" => export::audit_csv(&entries).unwrap_or_default(),
+        "json" => serde_json::to_vec_pretty(&entries).unwrap_or_default(),
+        _ => return Err(rocket::http::Status::BadRequest.into()),
+    };
+    let filename = format!("activity-{range}.{format}");
+    let _ = jobs::keep_file(&conn, user.id, &label, &filename, &body);
+    Ok(match format.as_str() {
+        "json" => export::Attachment::json(&filename, body),
+        _ => export::Attachment::csv(&filename, body),
+    })
+}
+
+#[post("/activity/<id>/undo")]
+fn activity_undo(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+) -> Result<Redirect, AppError> {
+    require_user(pool, cookies)?;
+    let mut conn = pool.get()?;
+    bulk::undo(&mut conn, id).map_err(|err| match err {
+        rusqlite::Error::QueryReturnedNoRows => rocket::http::Status::NotFound,
+        _ => rocket::http::Status::InternalServerError,
+    })?;
+    Ok(Redirect::to("/activity"))
+}
+
+#[get("/categories")]
+fn categories(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    flash: Option<FlashMessage<'_>>,
+) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    Ok(render_categories(&conn, &user, flash, FormState::default(), FormState::default()))
+}
+
+/// The categories page; `category_form` is the new-category form sent back,
+/// `edit_form` a row's form.
+fn render_categories(
+    conn: &rusqlite::Connection,
+    user: &User,
+    flash: Option<FlashMessage<'_>>,
+    category_form: FormState,
+    edit_form: FormState,
+) -> Template {
+    let list = db::list_categories(conn).unwrap_or_default();
+    let views = list.into_iter().map(category_view).collect::<Vec<_>>();
+    let duplicates = duplicate_groups(db::category_duplicates(conn).unwrap_or_default());
+    let context = serde_json::json!({
+        "username": user.username,
+        "categories": views,
+        "duplicates": duplicates.len(),
+        "flash": flash,
+        "category_form": category_form,
+        "edit_form": edit_form,
+    });
+    Template::render("categories", &context)
+}
+
+/// Duplicates of one name and kind together, in the order they were listed.
+fn duplicate_groups(list: Vec<CategoryDuplicate>) -> Vec<Vec<CategoryDuplicate>> {
+    let mut groups: Vec<Vec<CategoryDuplicate>> = Vec::new();
+    for duplicate in list {
+        match groups.last_mut() {
+            Some(group)
+                if group[0].kind == duplicate.kind && group[0].name_key == duplicate.name_key =>
+            {
+                group.push(duplicate)
+            }
+            _ => groups.push(vec![duplicate]),
+        }
+    }
+    groups
+}
+
+/// Categories whose names only differ in case or spacing, to be merged into one.
+#[get("/categories/duplicates")]
+fn category_duplicates(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let list =
+        db::category_duplicates(&conn).map_err(|_| rocket::http::Status::InternalServerError)?;
+    let groups = duplicate_groups(list)
+        .into_iter()
+        .map(|group| {
+            // The one with the most history is the likely keeper.
+            let keep = group
+                .iter()
+                .max_by_key(|duplicate| (duplicate.transactions, -duplicate.id))
+                .map(|duplicate| duplicate.id);
+            serde_json::json!({
+                "kind": kind_name(&group[0].kind),
+                "keep": keep,
+                "members": group,
+            })
+        })
+        .collect::<Vec<_>>();
+    let context = serde_json::json!({
+        "username": user.username,
+        "groups": groups,
+    });
+    Ok(Template::render("category_duplicates", &context))
+}
+
+/// Merges the other duplicates of `keep` into it, the way deleting them with
+/// their transactions moved to `keep` would.
+#[post("/categories/duplicates", data = "<form>")]
+fn merge_category_duplicates(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    form: Form<CategoryMergeForm>,
+) -> Result<Redirect, AppError> {
+    require_user(pool, cookies)?;
+    let mut conn = pool.get()?;
+    let list =
+        db::category_duplicates(&conn).map_err(|_| rocket::http::Status::InternalServerError)?;
+    let keep = list
+        .iter()
+        .find(|duplicate| duplicate.id == form.keep)
+        .ok_or(rocket::http::Status::BadRequest)?;
+    let merged = list
+        .iter()
+        .filter(|duplicate| {
+            duplicate.id != keep.id
+                && duplicate.kind == keep.kind
+                && duplicate.name_key == keep.name_key
+        })
+        .map(|duplicate| duplicate.id)
+        .collect::<Vec<_>>();
+    let tx = conn
+        .transaction()
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    for id in merged {
+        db::delete_category(&tx, id, Some(keep.id))
+            .map_err(|_| rocket::http::Status::InternalServerError)?;
+    }
+    let unique = db::ensure_category_name_index(&tx)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    tx.commit()
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    if unique {
+        Ok(Redirect::to("/categories"))
+    } else {
+        Ok(Redirect::to("/categories/duplicates"))
+    }
+}
+
+#[post("/categories", data = "<form>")]
+fn add_category(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    form: Form<CategoryForm>,
+) -> Result<Flash<Redirect>, AppError> {
+    let user = require_user(pool, cookies)?;
+    let form = form.into_inner();
+    let mut sent = FormState::default();
+    sent.value("name", &form.name);
+    sent.value("kind", &form.kind);
+    sent.value("parent_id", &form.parent_id.map(|id| id.to_string()).unwrap_or_default());
+    let name = db::clean_category_name(&form.name);
+    if name.is_empty() {
+        sent.error("name", "Введите название");
+    }
+    if !matches!(form.kind.as_str(), "income" | "expense") {
+        sent.error("kind", "Выберите доход или расход");
+    }
+    let conn = pool.get()?;
+    if let Some(parent_id) = form.parent_id {
+        check_parent(&conn, &mut sent, parent_id, &form.kind, None)?;
+    }
+    check_name_free(&conn, &mut sent, &form.kind, &name, None)?;
+    if !sent.is_valid() {
+        let page = render_categories(&conn, &user, None, sent, FormState::default());
+        return Err(AppError::Invalid(Box::new(page)));
+    }
+    db::insert_category(&conn, &name, &form.kind, form.parent_id)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(Flash::success(Redirect::to("/categories"), format!("Категория «{name}» добавлена")))
+}
+
+/// Names are unique per kind, ignoring case and spacing.
+fn check_name_free(
+    conn: &rusqlite::Connection,
+    sent: &mut FormState,
+    kind: &str,
+    name: &str,
+    except_id: Option<i64>,
+) -> Result<(), rocket::http::Status> {
+    let taken = db::category_name_taken(conn, kind, name, except_id)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    if taken.is_some() {
+        sent.error("name", "Категория с таким названием уже есть");
+    }
+    Ok(())
+}
+
+/// Only one level of nesting, and a child keeps its parent's kind.
+fn check_parent(
+    conn: &rusqlite::Connection,
+    sent: &mut FormState,
+    parent_id: i64,
+    kind: &str,
+    child_id: Option<i64>,
+) -> Result<(), rocket::http::Status> {
+    let parent = db::category_by_id(conn, parent_id)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    let fits = parent.is_some_and(|parent| {
+        parent.parent_id.is_none() && parent.kind == kind && Some(parent.id) != child_id
+    });
+    if !fits {
+        sent.error("parent_id", "Подойдет только категория верхнего уровня того же типа");
+    }
+    Ok(())
+}
+
+#[post("/categories/<id>", data = "<form>")]
+fn update_category(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+    form: Form<CategoryUpdateForm>,
+) -> Result<Flash<Redirect>, AppError> {
+    let user = require_user(pool, cookies)?;
+    let form = form.into_inner();
+    let mut sent = FormState::for_row(id);
+    sent.value("name", &form.name);
+    sent.value("parent_id", &form.parent_id.map(|id| id.to_string()).unwrap_or_default());
+    sent.value("default_amount", form.default_amount.as_deref().unwrap_or(""));
+    sent.value("allow_receipt", if form.allow_receipt { "true" } else { "" });
+    let name = db::clean_category_name(&form.name);
+    if name.is_empty() {
+        sent.error("name", "Введите название");
+    }
+    let default_amount_cents = form
+        .default_amount
+        .as_deref()
+        .and_then(optional_field)
+        .map(|value| form_amount(&mut sent, "default_amount", &value));
+    let conn = pool.get()?;
+    let list = db::list_categories(&conn).map_err(|_| rocket::http::Status::InternalServerError)?;
+    let category = list
+        .iter()
+        .find(|category| category.id == id)
+        .ok_or(rocket::http::Status::NotFound)?;
+    if let Some(parent_id) = form.parent_id {
+        if list.iter().any(|child| child.parent_id == Some(id)) {
+            sent.error("parent_id", "У категории есть вложенные, она не может войти в другую");
+        }
+        check_parent(&conn, &mut sent, parent_id, &category.kind, Some(id))?;
+    }
+    check_name_free(&conn, &mut sent, &category.kind, &name, Some(id))?;
+    if !sent.is_valid() {
+        let page = render_categories(&conn, &user, None, FormState::default(), sent);
+        return Err(AppError::Invalid(Box::new(page)));
+    }
+    let allow_receipt = form.allow_receipt && category.kind == "expense";
+    db::update_category(&conn, id, &name, form.parent_id, default_amount_cents, allow_receipt)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(Flash::success(Redirect::to("/categories"), format!("Категория «{name}» сохранена")))
+}
+
+#[post("/categories/<id>/delete", data = "<form>")]
+fn delete_category(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+    form: Form<CategoryDeleteForm>,
+) -> Result<Redirect, AppError> {
+    require_user(pool, cookies)?;
+    let mut conn = pool.get()?;
+    let category = db::category_by_id(&conn, id)
+        .map_err(|_| rocket::http::Status::InternalServerError)?
+        .ok_or(rocket::http::Status::NotFound)?;
+    if let Some(target_id) = form.reassign_to {
+        let target = db::category_by_id(&conn, target_id)
+            .map_err(|_| rocket::http::Status::InternalServerError)?
+            .ok_or(rocket::http::Status::BadRequest)?;
+        if target.id == id || target.kind != category.kind {
+            return Err(rocket::http::Status::BadRequest.into());
+        }
+    }
+    let tx = conn
+        .transaction()
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    db::delete_category(&tx, id, form.reassign_to)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    tx.commit()
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(Redirect::to("/categories"))
+}
+
+fn kind_name(kind: &str) -> &'static str {
+    if kind == "income" { "доход" } else { "расход" }
+}
+
+/// The category with what switching its kind would touch.
+fn category_kind_impact(
+    conn: &rusqlite::Connection,
+    id: i64,
+) -> Result<(Vec<Category>, category_kind::Impact), AppError> {
+    let list = db::list_categories(conn).map_err(|_| rocket::http::Status::InternalServerError)?;
+    let category = list
+        .iter()
+        .find(|category| category.id == id)
+        .ok_or(rocket::http::Status::NotFound)?;
+    let impact = category_kind::impact(conn, category, &list)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok((list, impact))
+}
+
+/// Explains what changing the category's kind affects before anything changes.
+#[get("/categories/<id>/kind")]
+fn category_kind_page(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let (list, impact) = category_kind_impact(&conn, id)?;
+    let members = impact.categories.iter().map(|category| category.id).collect::<Vec<_>>();
+    let targets = list
+        .iter()
+        .filter(|category| category.kind == impact.from && !members.contains(&category.id))
+        .collect::<Vec<_>>();
+    let context = serde_json::json!({
+        "username": user.username,
+        "category": impact.categories[0],
+        "children": impact.categories[1..],
+        "from": kind_name(&impact.from),
+        "to": kind_name(&impact.to),
+        "transactions": impact.transactions,
+        "amount": format_money(impact.amount_cents),
+        "twice_amount": format_money(impact.amount_cents * 2),
+        "hooks": impact.hooks,
+        "budgets": impact.budgets.len(),
+        "detaches": impact.detaches,
+        "clashes": impact.clashes,
+        "targets": targets,
+    });
+    Ok(Template::render("category_kind", &context))
+}
+
+#[post("/categories/<id>/kind", data = "<form>")]
+fn change_category_kind(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+    form: Form<CategoryKindForm>,
+) -> Result<Redirect, AppError> {
+    require_user(pool, cookies)?;
+    let form = form.into_inner();
+    let mut conn = pool.get()?;
+    let (_, impact) = category_kind_impact(&conn, id)?;
+    if !impact.clashes.is_empty() {
+        return Err(rocket::http::Status::Conflict.into());
+    }
+    let history = match (form.history.as_str(), form.reassign_to) {
+        ("keep", _) => category_kind::History::Keep,
+        ("rekind", _) => category_kind::History::Rekind,
+        ("reassign", Some(target_id)) => {
+            let target = db::category_by_id(&conn, target_id)
+                .map_err(|_| rocket::http::Status::InternalServerError)?
+                .ok_or(rocket::http::Status::BadRequest)?;
+            let moving = impact.categories.iter().any(|category| category.id == target.id);
+            if target.kind != impact.from || moving {
+                return Err(rocket::http::Status::BadRequest.into());
+            }
+            category_kind::History::Reassign(target.id)
+        }
+        _ => return Err(rocket::http::Status::BadRequest.into()),
+    };
+    let label = format!(
+        "Категория «{}»: {} → {}",
+        impact.categories[0].name,
+        kind_name(&impact.from),
+        kind_name(&impact.to)
+    );
+    bulk::run(&mut conn, false, "category_kind", &label, |tx, changes| {
+        category_kind::change_kind(tx, changes, &impact, history)
+    })
+    .map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(Redirect::to("/categories"))
+}
+
+#[get("/accounts")]
+fn accounts(pool: &State<DbPool>, cookies: &CookieJar<'_>) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let list = db::list_accounts(&conn).unwrap_or_default();
+    let net_worth = list.iter().filter_map(account_value_cents).sum::<i64>();
+    let unvalued = list
+        .iter()
+        .filter(|account| account_value_cents(account).is_none())
+        .count();
+    let views = list.into_iter().map(account_view).collect::<Vec<_>>();
+    let context = serde_json::json!({
+        "username": user.username,
+        "accounts": views,
+        "net_worth": format_money(net_worth),
+        "unvalued": unvalued,
+        "month": current_month(),
+    });
+    Ok(Template::render("accounts", &context))
+}
+
+fn parse_account_form(form: &AccountForm) -> Result<(String, String, i64), rocket::http::Status> {
+    let name = form.name.trim();
+    if name.is_empty() || !matches!(form.kind.as_str(), "cash" | "card" | "bank") {
+        return Err(rocket::http::Status::BadRequest);
+    }
+    let currency = fx::normalize_currency(&form.currency).ok_or(rocket::http::Status::BadRequest)?;
+    let opening = form.opening_balance.trim();
+    let opening_cents = if opening.is_empty() {
+        0
+    } else if let Some(abs) = opening.strip_prefix('-') {
+        -parse_amount_to_cents(abs).ok_or(rocket::http::Status::BadRequest)?
+    } else {
+        parse_amount_to_cents(opening).ok_or(rocket::http::Status::BadRequest)?
+    };
+    Ok((name.to_string(), currency, opening_cents))
+}
+
+#[post("/accounts", data = "<form>")]
+fn add_account(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    form: Form<AccountForm>,
+) -> Result<Redirect, AppError> {
+    require_user(pool, cookies)?;
+    let form = form.into_inner();
+    let (name, currency, opening_cents) = parse_account_form(&form)?;
+    let conn = pool.get()?;
+    db::insert_account(&conn, &name, &form.kind, &currency, opening_cents)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(Redirect::to("/accounts"))
+}
+
+#[post("/accounts/<id>", data = "<form>")]
+fn update_account(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+    form: Form<AccountForm>,
+) -> Result<Redirect, AppError> {
+    require_user(pool, cookies)?;
+    let form = form.into_inner();
+    let (name, currency, opening_cents) = parse_account_form(&form)?;
+    let conn = pool.get()?;
+    db::update_account(&conn, id, &name, &form.kind, &currency, opening_cents)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(Redirect::to("/accounts"))
+}
+
+#[post("/accounts/<id>/revalue", data = "<form>")]
+fn revalue_account(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+    form: Form<RevaluationForm>,
+) -> Result<Redirect, AppError> {
+    require_user(pool, cookies)?;
+    let form = form.into_inner();
+    let month = form.month.trim();
+    let until = fx::month_end(month).ok_or(rocket::http::Status::BadRequest)?;
+    let mut conn = pool.get()?;
+    let account = db::list_accounts(&conn)
+        .map_err(|_| rocket::http::Status::InternalServerError)?
+        .into_iter()
+        .find(|account| account.id == id)
+        .ok_or(rocket::http::Status::NotFound)?;
+    if account.currency == fx::BASE_CURRENCY {
+        return Err(rocket::http::Status::BadRequest.into());
+    }
+    // An empty rate means the month-end rate from the exchange-rate table.
+    let rate = if form.rate.trim().is_empty() {
+        db::exchange_rate_on(&conn, &account.currency, &until)
+            .map_err(|_| rocket::http::Status::InternalServerError)?
+    } else {
+        fx::parse_rate(&form.rate)
+    }
+    .ok_or(rocket::http::Status::BadRequest)?;
+    fx::revalue(&mut conn, &account, month, rate)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(Redirect::to("/accounts"))
+}
+
+#[post("/accounts/<id>/delete")]
+fn delete_account(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+) -> Result<Redirect, AppError> {
+    require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    db::delete_account(&conn, id).map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(Redirect::to("/accounts"))
+}
+
+/// The statement period from the query, the current month so far by default.
+fn statement_period(
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<(NaiveDate, NaiveDate), rocket::http::Status> {
+    let parse = |value: Option<String>| -> Result<Option<NaiveDate>, rocket::http::Status> {
+        match value.map(|value| value.trim().to_string()).filter(|value| !value.is_empty()) {
+            Some(value) => NaiveDate::parse_from_str(&value, "%Y-%m-%d")
+                .map(Some)
+                .map_err(|_| rocket::http::Status::BadRequest),
+            None => Ok(None),
+        }
+    };
+    let today = Local::now().date_naive();
+    let from = parse(from)?.unwrap_or_else(|| today.with_day(1).unwrap_or(today));
+    let to = parse(to)?.unwrap_or(today);
+    if from > to {
+        return Err(rocket::http::Status::BadRequest);
+    }
+    Ok((from, to))
+}
+
+fn load_statement(
+    pool: &State<DbPool>,
+    id: i64,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<(Account, statement::Statement), AppError> {
+    let (from, to) = statement_period(from, to)?;
+    let conn = pool.get()?;
+    let account = db::list_accounts(&conn)
+        .map_err(|_| rocket::http::Status::InternalServerError)?
+        .into_iter()
+        .find(|account| account.id == id)
+        .ok_or(rocket::http::Status::NotFound)?;
+    let statement = statement::build(&conn, &account, from, to)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok((account, statement))
+}
+
+#[get("/accounts/<id>/statement?<from>&<to>")]
+fn account_statement(
+    pool: &State<DbPool>,
+    font: &State<Option<pdf::Font>>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let (account, statement) = load_statement(pool, id, from, to)?;
+    let entries = statement
+        .entries
+        .iter()
+        .map(|entry| {
+            let (credit, debit) = statement::credit_debit(entry.line.amount_cents);
+            serde_json::json!({
+                "occurred_on": entry.line.occurred_on,
+                "description": statement::description(&entry.line),
+                "note": entry.line.note,
+                "credit": credit,
+                "debit": debit,
+                "balance": format_money(entry.balance_cents),
+            })
+        })
+        .collect::<Vec<_>>();
+    let context = serde_json::json!({
+        "username": user.username,
+        "account": account_view(account),
+        "from": statement.from,
+        "to": statement.to,
+        "opening": format_money(statement.opening_cents),
+        "closing": format_money(statement.closing_cents),
+        "credit": format_money(statement.credit_cents),
+        "debit": format_money(statement.debit_cents),
+        "entries": entries,
+        "pdf": font.is_some(),
+    });
+    Ok(Template::render("statement", &context))
+}
+
+#[get("/accounts/<id>/statement.csv?<from>&<to>")]
+fn account_statement_csv(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<export::Attachment, AppError> {
+    let user = require_user(pool, cookies)?;
+    let (account, statement) = load_statement(pool, id, from, to)?;
+    let body = export::statement_csv(&statement)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    let filename = format!("statement-{}-{}-{}.csv", account.id, statement.from, statement.to);
+    let label = format!("Выписка «{}» за {} — {}", account.name, statement.from, statement.to);
+    let conn = pool.get()?;
+    let _ = jobs::keep_file(&conn, user.id, &label, &filename, &body);
+    Ok(export::Attachment::csv(&filename, body))
+}
+
+/// Unavailable (503) when no font with Cyrillic was found at startup.
+#[get("/accounts/<id>/statement.pdf?<from>&<to>")]
+fn account_statement_pdf(
+    pool: &State<DbPool>,
+    font: &State<Option<pdf::Font>>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<export::Attachment, AppError> {
+    let user = require_user(pool, cookies)?;
+    let font = font.as_ref().ok_or(rocket::http::Status::ServiceUnavailable)?;
+    let (account, statement) = load_statement(pool, id, from, to)?;
+    let body = statement::pdf(font, &account, &statement)
+        .ok_or(rocket::http::Status::InternalServerError)?;
+    let filename = format!("statement-{}-{}-{}.pdf", account.id, statement.from, statement.to);
+    let label = format!("Выписка «{}» за {} — {}", account.name, statement.from, statement.to);
+    let conn = pool.get()?;
+    let _ = jobs::keep_file(&conn, user.id, &label, &filename, &body);
+    Ok(export::Attachment::pdf(&filename, body))
+}
+
+#[get("/loans")]
+fn loan_list(pool: &State<DbPool>, cookies: &CookieJar<'_>) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let views = db::list_loans(&conn)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|loan| {
+            let payments = db::loan_payments(&conn, loan.id).unwrap_or_default();
+            loan_view(loan, &payments)
+        })
+        .collect::<Vec<_>>();
+    let context = serde_json::json!({
+        "username": user.username,
+        "loans": views,
+        "month": current_month(),
+    });
+    Ok(Template::render("loans", &context))
+}
+
+fn parse_loan_form(form: &LoanForm) -> Result<NewLoan, rocket::http::Status> {
+    let name = form.name.trim();
+    let principal_cents = parse_amount_to_cents(&form.principal)
+        .filter(|cents| *cents > 0)
+        .ok_or(rocket::http::Status::BadRequest)?;
+    let annual_rate: f64 = form
+        .annual_rate
+        .trim()
+        .replace(',', ".")
+        .parse()
+        .map_err(|_| rocket::http::Status::BadRequest)?;
+    let first_month = form.first_month.trim();
+    if name.is_empty()
+        || !annual_rate.is_finite()
+        || !(0.0..=100.0).contains(&annual_rate)
+        || !(1..=600).contains(&form.term_months)
+        || !(1..=31).contains(&form.payment_day)
+        || fx::month_end(first_month).is_none()
+    {
+        return Err(rocket::http::Status::BadRequest);
+    }
+    Ok(NewLoan {
+        name: name.to_string(),
+        principal_cents,
+        annual_rate,
+        term_months: form.term_months,
+        first_month: first_month.to_string(),
+        payment_day: form.payment_day,
+    })
+}
+
+#[post("/loans", data = "<form>")]
+fn add_loan(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    form: Form<LoanForm>,
+) -> Result<Redirect, AppError> {
+    require_user(pool, cookies)?;
+    let loan = parse_loan_form(&form)?;
+    let conn = pool.get()?;
+    let id = db::insert_loan(&conn, &loan).map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(Redirect::to(format!("/loans/{id}")))
+}
+
+#[get("/loans/<id>")]
+fn loan_detail(pool: &State<DbPool>, cookies: &CookieJar<'_>, id: i64) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let loan = db::loan_by_id(&conn, id)
+        .map_err(|_| rocket::http::Status::InternalServerError)?
+        .ok_or(rocket::http::Status::NotFound)?;
+    let payments = db::loan_payments(&conn, id).unwrap_or_default();
+    let installments = loans::schedule(&loan)
+        .into_iter()
+        .map(|installment| installment_view(installment, payments.len()))
+        .collect::<Vec<_>>();
+    let payment_views = payments
+        .iter()
+        .map(|payment| LoanPaymentView {
+            occurred_on: payment.occurred_on.clone(),
+            amount: format_money(payment.amount_cents),
+        })
+        .collect::<Vec<_>>();
+    let context = serde_json::json!({
+        "username": user.username,
+        "loan": loan_view(loan, &payments),
+        "installments": installments,
+        "payments": payment_views,
+        "accounts": db::list_accounts(&conn).unwrap_or_default(),
+        "categories": db::list_categories(&conn).unwrap_or_default(),
+        "today": today_ymd(),
+    });
+    Ok(Template::render("loan", &context))
+}
+
+/// Records a payment as an expense and links it to the loan.
+#[post("/loans/<id>/payments", data = "<form>")]
+fn add_loan_payment(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+    form: Form<LoanPaymentForm>,
+) -> Result<Redirect, AppError> {
+    let user = require_user(pool, cookies)?;
+    let form = form.into_inner();
+    let amount_cents = parse_amount_to_cents(&form.amount)
+        .filter(|cents| *cents > 0)
+        .ok_or(rocket::http::Status::BadRequest)?;
+    let occurred_on = if form.occurred_on.trim().is_empty() {
+        today_ymd()
+    } else {
+        NaiveDate::parse_from_str(form.occurred_on.trim(), "%Y-%m-%d")
+            .map_err(|_| rocket::http::Status::BadRequest)?
+            .format("%Y-%m-%d")
+            .to_string()
+    };
+    let mut conn = pool.get()?;
+    let loan = db::loan_by_id(&conn, id)
+        .map_err(|_| rocket::http::Status::InternalServerError)?
+        .ok_or(rocket::http::Status::NotFound)?;
+    let payment = NewTransaction {
+        kind: "expense".to_string(),
+        amount_cents,
+        category_id: form.category_id,
+        occurred_on: occurred_on.clone(),
+        note: Some(format!("Платёж по кредиту: {}", loan.name)),
+        payee: None,
+        account_id: form.account_id,
+        to_account_id: None,
+    };
+    let tx = conn
+        .transaction()
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    let transaction_id = db::insert_transaction(&tx, &payment, None)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    db::link_loan_payment(&tx, id, transaction_id)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    tx.commit()
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    if let Some(category_id) = form.category_id {
+        let month = effective_months::month_of(&occurred_on, None);
+        notify_budget_exceeded(&conn, user.id, category_id, month, amount_cents);
+    }
+    Ok(Redirect::to(format!("/loans/{id}")))
+}
+
+#[post("/loans/<id>/delete")]
+fn delete_loan(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+) -> Result<Redirect, AppError> {
+    require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    db::delete_loan(&conn, id).map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(Redirect::to("/loans"))
+}
+
+#[get("/meters")]
+fn meter_list(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    flash: Option<FlashMessage<'_>>,
+) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    render_meters(&conn, &user, flash, FormState::default(), FormState::default())
+}
+
+fn render_meters(
+    conn: &rusqlite::Connection,
+    user: &User,
+    flash: Option<FlashMessage<'_>>,
+    meter_form: FormState,
+    tariff_form: FormState,
+) -> Result<Template, AppError> {
+    let mut meters = Vec::new();
+    for meter in db::list_meters(conn).map_err(|_| rocket::http::Status::InternalServerError)? {
+        let readings = db::meter_readings(conn, meter.id)
+            .map_err(|_| rocket::http::Status::InternalServerError)?;
+        meters.push((meter, readings));
+    }
+    let tariffs = db::list_tariffs(conn).map_err(|_| rocket::http::Status::InternalServerError)?;
+    let bills = meters::bills(&meters, &tariffs, meters::BILL_MONTHS)
+        .into_iter()
+        .map(|bill| {
+            serde_json::json!({
+                "month": bill.month,
+                "metered": bill.metered,
+                "expected": format_money(bill.expected_cents),
+                "unpriced": bill.unpriced,
+                "paid": bill.paid_cents.map(format_money),
+                "difference": bill.paid_cents.map(|paid| format_money(paid - bill.expected_cents)),
+                "overcharge": bill.overcharge_cents().map(format_money),
+            })
+        })
+        .collect::<Vec<_>>();
+    let tariffs = tariffs
+        .into_iter()
+        .map(|tariff| {
+            serde_json::json!({
+                "id": tariff.id,
+                "kind": meters::kind_name(&tariff.kind),
+                "unit": meters::unit(&tariff.kind),
+                "rate": format_money(tariff.rate_cents),
+                "effective_from": tariff.effective_from,
+            })
+        })
+        .collect::<Vec<_>>();
+    let mut views = Vec::new();
+    for (meter, readings) in &meters {
+        let used = meters::usage(readings).pop().flatten();
+        let latest = readings.last();
+        views.push(serde_json::json!({
+            "id": meter.id,
+            "name": meter.name,
+            "kind": meters::kind_name(&meter.kind),
+            "unit": meters::unit(&meter.kind),
+            "month": latest.map(|reading| reading.month.clone()),
+            "value": latest.map(|reading| meters::format_value(reading.value)),
+            "usage": used.map(meters::format_value),
+        }));
+    }
+    let kinds = meters::KINDS
+        .iter()
+        .map(|(kind, name, _)| serde_json::json!({ "kind": kind, "name": name }))
+        .collect::<Vec<_>>();
+    let context = serde_json::json!({
+        "username": user.username,
+        "meters": views,
+        "kinds": kinds,
+        "tariffs": tariffs,
+        "bills": bills,
+        "today": today_ymd(),
+        "flash": flash,
+        "meter_form": meter_form,
+        "tariff_form": tariff_form,
+    });
+    Ok(Template::render("meters", &context))
+}
+
+#[post("/meters", data = "<form>")]
+fn add_meter(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    form: Form<MeterForm>,
+) -> Result<Redirect, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let mut sent = FormState::default();
+    sent.value("name", &form.name);
+    sent.value("kind", &form.kind);
+    let name = form.name.split_whitespace().collect::<Vec<_>>().join(" ");
+    if name.is_empty() {
+        sent.error("name", "Введите название, например «Квартира»");
+    }
+    if !meters::is_kind(&form.kind) {
+        sent.error("kind", "Выберите, что считает счетчик");
+    }
+    if !sent.is_valid() {
+        return Err(AppError::Invalid(Box::new(render_meters(
+            &conn,
+            &user,
+            None,
+            sent,
+            FormState::default(),
+        )?)));
+    }
+    let id = db::insert_meter(&conn, &name, &form.kind)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(Redirect::to(format!("/meters/{id}")))
+}
+
+#[post("/meters/tariffs", data = "<form>")]
+fn add_tariff(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    form: Form<TariffForm>,
+) -> Result<Flash<Redirect>, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let mut sent = FormState::default();
+    sent.value("kind", &form.kind);
+    sent.value("rate", &form.rate);
+    sent.value("effective_from", &form.effective_from);
+    if !meters::is_kind(&form.kind) {
+        sent.error("kind", "Выберите, что считает счетчик");
+    }
+    let rate_cents = form_amount(&mut sent, "rate", &form.rate);
+    let effective_from = form_date(&mut sent, "effective_from", &form.effective_from);
+    if !sent.is_valid() {
+        return Err(AppError::Invalid(Box::new(render_meters(
+            &conn,
+            &user,
+            None,
+            FormState::default(),
+            sent,
+        )?)));
+    }
+    db::set_tariff(&conn, &form.kind, rate_cents, &effective_from)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(Flash::success(
+        Redirect::to("/meters"),
+        format!(
+            "Тариф «{}» с {effective_from} записан",
+            meters::kind_name(&form.kind)
+        ),
+    ))
+}
+
+#[post("/meters/tariffs/<id>/delete")]
+fn delete_tariff(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+) -> Result<Redirect, AppError> {
+    require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    db::delete_tariff(&conn, id).map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(Redirect::to("/meters"))
+}
+
+#[get("/meters/<id>")]
+fn meter_detail(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+    flash: Option<FlashMessage<'_>>,
+) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    render_meter(&conn, &user, id, flash, FormState::default())
+}
+
+fn render_meter(
+    conn: &rusqlite::Connection,
+    user: &User,
+    id: i64,
+    flash: Option<FlashMessage<'_>>,
+    reading_form: FormState,
+) -> Result<Template, AppError> {
+    let meter = db::meter_by_id(conn, id)
+        .map_err(|_| rocket::http::Status::InternalServerError)?
+        .ok_or(rocket::http::Status::NotFound)?;
+    let readings = db::meter_readings(conn, id)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    let usage = meters::usage(&readings);
+    let tariffs = db::list_tariffs(conn).map_err(|_| rocket::http::Status::InternalServerError)?;
+    let mut views = Vec::new();
+    for (reading, used) in readings.iter().zip(usage) {
+        let expenses = match (reading.paid_on.is_some(), meters::payment_window(&reading.month)) {
+            (false, Some((from, to))) => db::utility_expenses(conn, &from, &to)
+                .map_err(|_| rocket::http::Status::InternalServerError)?,
+            _ => Vec::new(),
+        };
+        let expenses = expenses
+            .into_iter()
+            .map(|expense| {
+                serde_json::json!({
+                    "transaction_id": expense.transaction_id,
+                    "occurred_on": expense.occurred_on,
+                    "amount": format_money(expense.amount_cents),
+                    "category_name": expense.category_name,
+                    "payee": expense.payee,
+                })
+            })
+            .collect::<Vec<_>>();
+        views.push(serde_json::json!({
+            "id": reading.id,
+            "month": reading.month,
+            "value": meters::format_value(reading.value),
+            "read_on": reading.read_on,
+            "usage": used.map(meters::format_value),
+            "cost": used
+                .zip(meters::rate_on(&tariffs, &meter.kind, &reading.month))
+                .map(|(used, rate)| format_money(meters::cost_cents(used, rate))),
+            "transaction_id": reading.transaction_id,
+            "paid_on": reading.paid_on,
+            "paid": reading.paid_cents.map(format_money),
+            "expenses": expenses,
+        }));
+    }
+    views.reverse();
+    let context = serde_json::json!({
+        "username": user.username,
+        "meter": {
+            "id": meter.id,
+            "name": meter.name,
+            "kind": meters::kind_name(&meter.kind),
+            "unit": meters::unit(&meter.kind),
+        },
+        "readings": views,
+        "month": current_month(),
+        "flash": flash,
+        "reading_form": reading_form,
+    });
+    Ok(Template::render("meter", &context))
+}
+
+#[post("/meters/<id>/readings", data = "<form>")]
+fn add_meter_reading(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+    form: Form<MeterReadingForm>,
+) -> Result<Flash<Redirect>, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    db::meter_by_id(&conn, id)
+        .map_err(|_| rocket::http::Status::InternalServerError)?
+        .ok_or(rocket::http::Status::NotFound)?;
+    let mut sent = FormState::default();
+    sent.value("month", &form.month);
+    sent.value("value", &form.value);
+    let month = form.month.trim();
+    if fx::month_end(month).is_none() {
+        sent.error("month", "Месяц в формате ГГГГ-ММ");
+    }
+    let value = meters::parse_value(&form.value);
+    if value.is_none() {
+        sent.error("value", "Введите показание, например 12345 или 123,45");
+    }
+    let readings = db::meter_readings(&conn, id)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    if let Some(message) = value.and_then(|value| meters::out_of_order(&readings, month, value)) {
+        sent.error("value", message);
+    }
+    let Some(value) = value.filter(|_| sent.is_valid()) else {
+        let page = render_meter(&conn, &user, id, None, sent)?;
+        return Err(AppError::Invalid(Box::new(page)));
+    };
+    db::set_meter_reading(&conn, id, month, value, &today_ymd())
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(Flash::success(
+        Redirect::to(format!("/meters/{id}")),
+        format!("Показание за {month} записано"),
+    ))
+}
+
+/// Links a reading to the ЖКХ expense that paid for it, or unlinks it.
+#[post("/meters/<id>/readings/<reading_id>/expense", data = "<form>")]
+fn link_meter_expense(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+    reading_id: i64,
+    form: Form<MeterExpenseForm>,
+) -> Result<Flash<Redirect>, AppError> {
+    require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let reading = db::meter_readings(&conn, id)
+        .map_err(|_| rocket::http::Status::InternalServerError)?
+        .into_iter()
+        .find(|reading| reading.id == reading_id)
+        .ok_or(rocket::http::Status::NotFound)?;
+    if let Some(transaction_id) = form.transaction_id {
+        let (from, to) = meters::payment_window(&reading.month)
+            .ok_or(rocket::http::Status::InternalServerError)?;
+        let expenses = db::utility_expenses(&conn, &from, &to)
+            .map_err(|_| rocket::http::Status::InternalServerError)?;
+        if !expenses.iter().any(|expense| expense.transaction_id == transaction_id) {
+            return Err(rocket::http::Status::BadRequest.into());
+        }
+    }
+    db::link_meter_reading(&conn, id, reading_id, form.transaction_id)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    let message = if form.transaction_id.is_some() {
+        "Расход привязан к показанию"
+    } else {
+        "Расход отвязан от показания"
+    };
+    Ok(Flash::success(Redirect::to(format!("/meters/{id}")), message))
+}
+
+#[post("/meters/<id>/delete")]
+fn delete_meter(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+) -> Result<Redirect, AppError> {
+    require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    db::delete_meter(&conn, id).map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(Redirect::to("/meters"))
+}
+
+#[get("/networth")]
+fn net_worth(pool: &State<DbPool>, cookies: &CookieJar<'_>) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let holdings = db::list_holdings(&conn).unwrap_or_default();
+    let series = networth::series(&conn, &recent_months(12)).unwrap_or_default();
+    let rounding = db::report_rounding(&conn, user.id).unwrap_or_default();
+    let context = serde_json::json!({
+        "username": user.username,
+        "current": series.first().map(|month| rounding.format(month.net_worth_cents)),
+        "holdings": holdings.into_iter().map(holding_view).collect::<Vec<_>>(),
+        "months": series
+            .into_iter()
+            .map(|month| net_worth_month_view(month, rounding))
+            .collect::<Vec<_>>(),
+        "today": today_ymd(),
+    });
+    Ok(Template::render("networth", &context))
+}
+
+/// A non-negative amount and a `YYYY-MM-DD` date, today when left empty.
+fn parse_valuation(amount: &str, valued_on: &str) -> Result<(i64, String), rocket::http::Status> {
+    let amount_cents = parse_amount_to_cents(amount.trim()).ok_or(rocket::http::Status::BadRequest)?;
+    let valued_on = valued_on.trim();
+    if valued_on.is_empty() {
+        return Ok((amount_cents, today_ymd()));
+    }
+    NaiveDate::parse_from_str(valued_on, "%Y-%m-%d")
+        .map(|date| (amount_cents, date.format("%Y-%m-%d").to_string()))
+        .map_err(|_| rocket::http::Status::BadRequest)
+}
+
+#[post("/networth", data = "<form>")]
+fn add_holding(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    form: Form<HoldingForm>,
+) -> Result<Redirect, AppError> {
+    require_user(pool, cookies)?;
+    let name = form.name.trim();
+    if name.is_empty() || !matches!(form.kind.as_str(), "asset" | "liability") {
+        return Err(rocket::http::Status::BadRequest.into());
+    }
+    // The first value is optional; a holding without one doesn't count yet.
+    let valuation = if form.amount.trim().is_empty() {
+        None
+    } else {
+        Some(parse_valuation(&form.amount, &form.valued_on)?)
+    };
+    let conn = pool.get()?;
+    let id = db::insert_holding(&conn, name, &form.kind)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    if let Some((amount_cents, valued_on)) = valuation {
+        db::record_valuation(&conn, id, &valued_on, amount_cents)
+            .map_err(|_| rocket::http::Status::InternalServerError)?;
+    }
+    Ok(Redirect::to("/networth"))
+}
+
+#[post("/networth/<id>/valuations", data = "<form>")]
+fn add_valuation(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+    form: Form<ValuationForm>,
+) -> Result<Redirect, AppError> {
+    require_user(pool, cookies)?;
+    let (amount_cents, valued_on) = parse_valuation(&form.amount, &form.valued_on)?;
+    let conn = pool.get()?;
+    db::record_valuation(&conn, id, &valued_on, amount_cents)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(Redirect::to("/networth"))
+}
+
+#[post("/networth/<id>/delete")]
+fn delete_holding(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+) -> Result<Redirect, AppError> {
+    require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    db::delete_holding(&conn, id).map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(Redirect::to("/networth"))
+}
+
+#[get("/hooks")]
+fn inbound_hooks(pool: &State<DbPool>, cookies: &CookieJar<'_>) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let context = serde_json::json!({
+        "username": user.username,
+        "hooks": db::list_inbound_hooks(&conn).unwrap_or_default(),
+        "categories": db::list_categories(&conn).unwrap_or_default(),
+        "accounts": db::list_accounts(&conn).unwrap_or_default(),
+    });
+    Ok(Template::render("hooks", &context))
+}
+
+#[get("/tokens")]
+fn api_token_page(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    flash: Option<FlashMessage<'_>>,
+) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let scopes = api_tokens::Scope::ALL
+        .iter()
+        .map(|scope| serde_json::json!({ "value": scope.as_str(), "label": scope.label() }))
+        .collect::<Vec<_>>();
+    let context = serde_json::json!({
+        "username": user.username,
+        "tokens": db::list_api_tokens(&conn, user.id).unwrap_or_default(),
+        "scopes": scopes,
+        "flash": flash,
+    });
+    Ok(Template::render("api_tokens", &context))
+}
+
+#[get("/rules")]
+fn rule_list(pool: &State<DbPool>, cookies: &CookieJar<'_>) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let rules = db::list_rules(&conn)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|rule| {
+            let min = rule.min_cents.map(format_money);
+            let max = rule.max_cents.map(format_money);
+            serde_json::json!({ "rule": rule, "min": min, "max": max })
+        })
+        .collect::<Vec<_>>();
+    let context = serde_json::json!({
+        "username": user.username,
+        "rules": rules,
+        "categories": db::list_categories(&conn).unwrap_or_default(),
+    });
+    Ok(Template::render("rules", &context))
+}
+
+#[post("/rules", data = "<form>")]
+fn add_rule(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    form: Form<RuleForm>,
+) -> Result<Redirect, AppError> {
+    require_user(pool, cookies)?;
     let form = form.into_inner();
-    let username = form.username.trim();
-    if username.is_empty() || form.password.is_empty() {
-        return Err(render_login(Some("Введите логин и пароль")));
-    }
-
-    let creds = db::user_credentials(&conn, username)
-        .map_err(|_| render_login(Some("Ошибка поиска пользователя")))?;
-    let Some((user_id, hash)) = creds else {
-        return Err(render_login(Some("Неверный логин или пароль")));
+    let amount = |value: &str| match value.trim() {
+        "" => Ok(None),
+        value => parse_amount_to_cents(value)
+            .map(Some)
+            .ok_or(rocket::http::Status::BadRequest),
     };
-    if !verify_password(&hash, &form.password) {
-        return Err(render_login(Some("Неверный логин или пароль")));
+    let min_cents = amount(&form.min_amount)?;
+    let max_cents = amount(&form.max_amount)?;
+    let kind = optional_field(&form.kind);
+    let pattern = form.pattern.trim();
+    let bounds_crossed = matches!((min_cents, max_cents), (Some(min), Some(max)) if min > max);
+    let does_nothing = form.category_id.is_none() && kind.is_none() && form.tags.trim().is_empty();
+    if pattern.is_empty() || bounds_crossed || does_nothing {
+        return Err(rocket::http::Status::BadRequest.into());
     }
+    if kind.as_deref().is_some_and(|kind| !matches!(kind, "income" | "expense")) {
+        return Err(rocket::http::Status::BadRequest.into());
+    }
+    let conn = pool.get()?;
+    if let Some(category_id) = form.category_id {
+        let category = db::category_by_id(&conn, category_id)
+            .map_err(|_| rocket::http::Status::InternalServerError)?
+            .ok_or(rocket::http::Status::BadRequest)?;
+        if kind.as_deref().is_some_and(|kind| kind != category.kind) {
+            return Err(rocket::http::Status::BadRequest.into());
+        }
+    }
+    let rule = NewRule {
+        pattern: pattern.to_string(),
+        min_cents,
+        max_cents,
+        category_id: form.category_id,
+        kind,
+        tags: optional_field(&form.tags),
+    };
+    db::insert_rule(&conn, &rule, &Local::now().to_rfc3339())
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(Redirect::to("/rules"))
+}
 
-    let token = Uuid::new_v4().to_string();
-    let created_at = Local::now().to_rfc3339();
-    db::create_session(&conn, user_id, &token, &created_at)
-        .map_err(|_| render_login(Some("Не удалось создать сессию")))?;
-    db::prune_sessions(&conn, user_id, MAX_SESSIONS)
-        .map_err(|_| render_login(Some("Не удалось обновить сессии")))?;
-
-    let mut cookie = Cookie::new("session", token);
-    cookie.set_path("/");
-    cookie.set_http_only(true);
-    cookie.set_same_site(SameSite::Lax);
-    cookies.add(cookie);
-
-    Ok(Redirect::to("/"))
+#[post("/rules/<id>/delete")]
+fn delete_rule(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+) -> Result<Redirect, AppError> {
+    require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    db::delete_rule(&conn, id).map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(Redirect::to("/rules"))
 }
 
-#[get("/settings")]
-fn settings(pool: &State<DbPool>, cookies: &CookieJar<'_>) -> Result<Template, Redirect> {
-    let user = require_user(pool, cookies)?;
-    let conn = pool.get().map_err(|_| Redirect::to("/login"))?;
-    let sessions = db::session_count(&conn, user.id).unwrap_or(1);
-    Ok(render_settings(&user.username, sessions, None, None))
+fn optional_field(value: &str) -> Option<String> {
+    Some(value.trim().to_string()).filter(|value| !value.is_empty())
 }
 
-#[post("/settings/password", data = "<form>")]
-fn settings_password(
+#[post("/hooks", data = "<form>")]
+fn add_inbound_hook(
     pool: &State<DbPool>,
     cookies: &CookieJar<'_>,
-    form: Form<ChangePasswordForm>,
-) -> Result<Template, Redirect> {
+    form: Form<InboundHookForm>,
+) -> Result<Redirect, AppError> {
     let user = require_user(pool, cookies)?;
-    let conn = pool.get().map_err(|_| Redirect::to("/login"))?;
-    let sessions = db::session_count(&conn, user.id).unwrap_or(1);
     let form = form.into_inner();
-
-    if form.new_password.len() < 6 {
-        return Ok(render_settings(
-            &user.username,
-            sessions,
-            Some("Новый пароль должен быть не короче 6 символов"),
-            None,
-        ));
+    let name = form.name.trim();
+    let amount_field = form.amount_field.trim();
+    if name.is_empty() || amount_field.is_empty() || !matches!(form.kind.as_str(), "income" | "expense") {
+        return Err(rocket::http::Status::BadRequest.into());
     }
-    if form.new_password != form.confirm_password {
-        return Ok(render_settings(
-            &user.username,
-            sessions,
-            Some("Пароли не совпадают"),
-            None,
-        ));
-    }
-
-    let creds = db::user_credentials(&conn, &user.username)
-        .map_err(|_| Redirect::to("/login"))?;
-    let Some((_user_id, hash)) = creds else {
-        return Ok(render_settings(
-            &user.username,
-            sessions,
-            Some("Пользователь не найден"),
-            None,
-        ));
+    let hook = NewInboundHook {
+        name: name.to_string(),
+        kind: form.kind,
+        amount_field: amount_field.to_string(),
+        date_field: optional_field(&form.date_field),
+        note_field: optional_field(&form.note_field),
+        category_field: optional_field(&form.category_field),
+        category_id: form.category_id,
+        account_id: form.account_id,
     };
-    if !verify_password(&hash, &form.current_password) {
-        return Ok(render_settings(
-            &user.username,
-            sessions,
-            Some("Текущий пароль неверный"),
-            None,
-        ));
-    }
+    let conn = pool.get()?;
+    let secret = Uuid::new_v4().to_string();
+    db::insert_inbound_hook(&conn, user.id, &hook, &secret, &Local::now().to_rfc3339())
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(Redirect::to("/hooks"))
+}
 
-    let new_hash = hash_password(&form.new_password).map_err(|_| Redirect::to("/login"))?;
-    conn.execute(
-        "UPDATE users SET password_hash = ?1 WHERE id = ?2",
-        params![new_hash, user.id],
-    )
-    .map_err(|_| Redirect::to("/login"))?;
-    Ok(render_settings(
-        &user.username,
-        sessions,
-        None,
-        Some("Пароль обновлен"),
-    ))
+#[post("/hooks/<id>/delete")]
+fn delete_inbound_hook(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+) -> Result<Redirect, AppError> {
+    require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    db::delete_inbound_hook(&conn, id).map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(Redirect::to("/hooks"))
 }
 
-#[post("/settings/logout_all")]
-fn settings_logout_all(pool: &State<DbPool>, cookies: &CookieJar<'_>) -> Redirect {
-    if let Ok(conn) = pool.get() {
-        if let Some(user) = current_user(pool, cookies) {
-            let _ = db::delete_sessions_for_user(&conn, user.id);
-        }
+#[post("/tokens", data = "<form>")]
+fn add_api_token(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    form: Form<ApiTokenForm>,
+) -> Result<Flash<Redirect>, AppError> {
+    let user = require_user(pool, cookies)?;
+    let form = form.into_inner();
+    let name = form.name.trim();
+    let scope = api_tokens::Scope::from_param(&form.scope).ok_or(rocket::http::Status::BadRequest)?;
+    if name.is_empty() {
+        return Err(rocket::http::Status::BadRequest.into());
     }
-    let mut cookie = Cookie::named("session");
-    cookie.set_path("/");
-    cookies.remove(cookie);
-    Redirect::to("/login")
+    let conn = pool.get()?;
+    let token = Uuid::new_v4().simple().to_string();
+    db::insert_api_token(&conn, user.id, name, &token, scope.as_str(), &Local::now().to_rfc3339())
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    // Only its hash is kept, so this is the one time it can be shown.
+    Ok(Flash::success(
+        Redirect::to("/tokens"),
+        format!("Токен «{name}» создан, сохраните его сейчас: {token}"),
+    ))
 }
 
-#[get("/logout")]
-fn logout(pool: &State<DbPool>, cookies: &CookieJar<'_>) -> Redirect {
-    if let Some(cookie) = cookies.get("session") {
-        if let Ok(conn) = pool.get() {
-            let _ = db::delete_session(&conn, cookie.value());
-        }
+#[post("/tokens/<id>/delete")]
+fn delete_api_token(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+) -> Result<Redirect, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    db::delete_api_token(&conn, user.id, id).map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(Redirect::to("/tokens"))
+}
+
+/// Inbound endpoint for Zapier/IFTTT; the secret in the path identifies the hook.
+#[post("/api/hooks/<secret>", data = "<body>")]
+fn receive_inbound_hook(
+    pool: &State<DbPool>,
+    secret: &str,
+    body: String,
+) -> Result<rocket::http::Status, (rocket::http::Status, String)> {
+    let conn = pool
+        .get()
+        .map_err(|_| (rocket::http::Status::InternalServerError, String::new()))?;
+    let hook = db::inbound_hook_by_secret(&conn, secret)
+        .ok()
+        .flatten()
+        .ok_or((rocket::http::Status::NotFound, String::new()))?;
+    let payload = serde_json::from_str::<serde_json::Value>(&body)
+        .map_err(|err| (rocket::http::Status::BadRequest, format!("некорректный JSON: {err}")))?;
+    let categories = db::list_categories(&conn).unwrap_or_default();
+    let today = Local::now().format("%Y-%m-%d").to_string();
+    let mut transaction = hooks::to_new_transaction(&hook, &payload, &categories, &today)
+        .map_err(|error| (rocket::http::Status::UnprocessableEntity, error))?;
+    let rules = db::list_rules(&conn).unwrap_or_default();
+    let tags = rules::apply(&rules, &categories, &mut transaction);
+    let transaction_id = db::insert_transaction(&conn, &transaction, None)
+        .map_err(|_| (rocket::http::Status::InternalServerError, String::new()))?;
+    let _ = db::add_transaction_tags(&conn, transaction_id, &tags);
+    let _ = db::mark_inbound_hook_used(&conn, hook.id, &Local::now().to_rfc3339());
+    if let (Some(category_id), "expense") = (transaction.category_id, transaction.kind.as_str()) {
+        notify_budget_exceeded(
+            &conn,
+            hook.user_id,
+            category_id,
+            effective_months::month_of(&transaction.occurred_on, None),
+            transaction.amount_cents,
+        );
     }
-    let mut cookie = Cookie::named("session");
-    cookie.set_path("/");
-    cookies.remove(cookie);
-    Redirect::to("/login")
+    Ok(rocket::http::Status::Created)
 }
 
-#[get("/?<month>")]
-fn dashboard(
+#[get("/budgets?<month>")]
+fn budgets(
     pool: &State<DbPool>,
     cookies: &CookieJar<'_>,
     month: Option<String>,
-) -> Result<Template, Redirect> {
+    flash: Option<FlashMessage<'_>>,
+) -> Result<Template, AppError> {
     let user = require_user(pool, cookies)?;
-    let selected = selected_month(month);
-    let conn = pool.get().expect("db connection");
-    let (income_cents, expense_cents) =
-        db::month_totals(&conn, &selected).unwrap_or((0, 0));
-    let budgets = db::dashboard_budgets(&conn, &selected).unwrap_or_default();
-    let budget_views = budgets
+    let conn = pool.get()?;
+    render_budgets(&conn, &user, selected_month(month), flash, FormState::default())
+}
+
+fn render_budgets(
+    conn: &rusqlite::Connection,
+    user: &User,
+    selected: String,
+    flash: Option<FlashMessage<'_>>,
+    budget_form: FormState,
+) -> Result<Template, AppError> {
+    let refunds = db::refund_month(conn, user.id).unwrap_or_default();
+    let list = db::list_budgets(conn, &selected, refunds).unwrap_or_default();
+    let categories = db::list_categories(conn).unwrap_or_default();
+    let views = list.into_iter().map(budget_view).collect::<Vec<_>>();
+    let standing = db::list_standing_budgets(conn)
+        .unwrap_or_default()
         .into_iter()
-        .map(dashboard_budget_view)
+        .map(|budget| StandingBudgetView {
+            id: budget.id,
+            category_name: budget.category_name,
+            amount: format_money(budget.amount_cents),
+            yearly_increase: budget.yearly_increase_percent,
+        })
         .collect::<Vec<_>>();
-    let months = available_months(&conn);
+    let nav = month_nav(conn, "/budgets", &selected)?;
 
     let context = serde_json::json!({
         "month": selected,
-        "months": months,
+        "nav": nav,
         "username": user.username,
-        "income": format_money(income_cents),
-        "expense": format_money(expense_cents),
-        "net": format_money(income_cents - expense_cents),
-        "budgets": budget_views,
+        "budgets": views,
+        "standing_budgets": standing,
+        "categories": categories,
+        "flash": flash,
+        "budget_form": budget_form,
     });
-    Ok(Template::render("dashboard", &context))
+    Ok(Template::render("budgets", &context))
 }
 
-#[get("/transactions?<month>")]
-fn transactions(
+#[post("/budgets", data = "<form>")]
+fn add_budget(
     pool: &State<DbPool>,
     cookies: &CookieJar<'_>,
-    month: Option<String>,
-) -> Result<Template, Redirect> {
+    form: Form<BudgetForm>,
+) -> Result<Flash<Redirect>, AppError> {
     let user = require_user(pool, cookies)?;
-    let conn = pool.get().expect("db connection");
-    let selected = selected_month(month);
-    let records = db::list_transactions(&conn, Some(&selected)).unwrap_or_default();
-    let categories = db::list_categories(&conn).unwrap_or_default();
-    let views = records.into_iter().map(transaction_view).collect::<Vec<_>>();
-    let months = available_months(&conn);
+    let form = form.into_inner();
+    let mut sent = FormState::default();
+    sent.value("category_id", &form.category_id.map(|id| id.to_string()).unwrap_or_default());
+    sent.value("month", &form.month);
+    sent.value("amount", &form.amount);
+    sent.value("rollover", if form.rollover { "true" } else { "" });
+    sent.value("standing", if form.standing { "true" } else { "" });
+    let conn = pool.get()?;
+    let category = match form.category_id {
+        Some(id) => db::category_by_id(&conn, id)
+            .map_err(|_| rocket::http::Status::InternalServerError)?,
+        None => None,
+    };
+    let category_id = match category {
+        Some(category) if category.kind == "expense" => category.id,
+        _ => {
+            sent.error("category_id", "Выберите категорию расходов");
+            0
+        }
+    };
+    let amount_cents = form_amount(&mut sent, "amount", &form.amount);
+    // A standing budget has no first month to start a rollover chain from.
+    if form.standing && form.rollover {
+        sent.error("rollover", "Ежемесячный бюджет не переносит остаток");
+    }
+    let month = match form.month.trim() {
+        "" => current_month(),
+        month if form.standing || previous_month(month).is_some() => month.to_string(),
+        _ => {
+            sent.error("month", "Месяц в формате ГГГГ-ММ");
+            current_month()
+        }
+    };
+    if !sent.is_valid() {
+        let page = render_budgets(&conn, &user, month, None, sent)?;
+        return Err(AppError::Invalid(Box::new(page)));
+    }
 
-    let context = serde_json::json!({
-        "month": selected,
-        "months": months,
-        "username": user.username,
-        "today": today_ymd(),
-        "transactions": views,
-        "categories": categories,
-    });
-    Ok(Template::render("transactions", &context))
+    if form.standing {
+        db::set_standing_budget(&conn, category_id, amount_cents)
+            .map_err(|_| rocket::http::Status::InternalServerError)?;
+        return Ok(Flash::success(Redirect::to("/budgets"), "Ежемесячный бюджет сохранен"));
+    }
+    db::insert_budget(&conn, category_id, &month, amount_cents, form.rollover)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(Flash::success(Redirect::to("/budgets"), "Бюджет сохранен"))
 }
 
-#[post("/transactions", data = "<form>")]
-async fn add_transaction(
+/// Sets how much the standing budget grows at the start of each fiscal year.
+#[post("/budgets/standing/<id>/increase", data = "<form>")]
+fn set_budget_increase(
     pool: &State<DbPool>,
     cookies: &CookieJar<'_>,
-    form: Form<TransactionForm<'_>>,
-) -> Result<Redirect, rocket::http::Status> {
-    if let Err(redirect) = require_user(pool, cookies) {
-        return Ok(redirect);
-    }
-    let mut form = form.into_inner();
-    let amount_cents = parse_amount_to_cents(&form.amount)
-        .ok_or(rocket::http::Status::BadRequest)?;
-    let occurred_on = if form.occurred_on.trim().is_empty() {
-        today_ymd()
-    } else {
-        form.occurred_on
+    id: i64,
+    form: Form<BudgetIncreaseForm>,
+) -> Result<Redirect, AppError> {
+    require_user(pool, cookies)?;
+    let percent = match form.percent.trim() {
+        "" => None,
+        value => Some(
+            value
+                .replace(',', ".")
+                .parse::<f64>()
+                .ok()
+                .filter(|percent| *percent > 0.0 && *percent <= 100.0)
+                .ok_or(rocket::http::Status::BadRequest)?,
+        ),
     };
+    let conn = pool.get()?;
+    let found = db::set_standing_budget_increase(&conn, id, percent)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    if !found {
+        return Err(rocket::http::Status::NotFound.into());
+    }
+    Ok(Redirect::to("/budgets"))
+}
 
-    let conn = pool.get().map_err(|_| rocket::http::Status::InternalServerError)?;
-    let category_name = if let Some(category_id) = form.category_id {
-        db::category_name_by_id(&conn, category_id)
-            .map_err(|_| rocket::http::Status::InternalServerError)?
-    } else {
-        None
-    };
-    drop(conn);
-    let receipt_path =
-        persist_receipt(form.receipt.take(), category_name.as_deref(), &form.kind).await?;
+/// The longest budget note, in characters.
+const BUDGET_NOTE_MAX: usize = 200;
 
-    let conn = pool.get().map_err(|_| rocket::http::Status::InternalServerError)?;
-    db::insert_transaction(
-        &conn,
-        &form.kind,
-        amount_cents,
-        form.category_id,
-        &occurred_on,
-        form.note.as_deref(),
-        receipt_path.as_deref(),
-    )
+/// Sets the note of the month's budget `id`, or of the standing budget `id`.
+fn set_budget_note(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+    standing: bool,
+    note: &str,
+) -> Result<Flash<Redirect>, AppError> {
+    require_user(pool, cookies)?;
+    let note = optional_field(note);
+    if note.as_ref().is_some_and(|note| note.chars().count() > BUDGET_NOTE_MAX) {
+        return Ok(Flash::error(
+            Redirect::to("/budgets"),
+            format!("Заметка длиннее {BUDGET_NOTE_MAX} символов"),
+        ));
+    }
+    let conn = pool.get()?;
+    let found = if standing {
+        db::set_standing_budget_note(&conn, id, note.as_deref())
+    } else {
+        db::set_budget_note(&conn, id, note.as_deref())
+    }
     .map_err(|_| rocket::http::Status::InternalServerError)?;
+    if !found {
+        return Err(rocket::http::Status::NotFound.into());
+    }
+    Ok(Flash::success(Redirect::to("/budgets"), "Заметка сохранена"))
+}
 
-    Ok(Redirect::to("/transactions"))
+#[post("/budgets/<id>/note", data = "<form>")]
+fn budget_note(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+    form: Form<BudgetNoteForm>,
+) -> Result<Flash<Redirect>, AppError> {
+    set_budget_note(pool, cookies, id, false, &form.note)
 }
 
-#[get("/categories")]
-fn categories(pool: &State<DbPool>, cookies: &CookieJar<'_>) -> Result<Template, Redirect> {
+#[post("/budgets/standing/<id>/note", data = "<form>")]
+fn standing_budget_note(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+    form: Form<BudgetNoteForm>,
+) -> Result<Flash<Redirect>, AppError> {
+    set_budget_note(pool, cookies, id, true, &form.note)
+}
+
+#[post("/budgets/<id>/delete")]
+fn delete_budget(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+) -> Result<Redirect, AppError> {
+    require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    db::trash_budget(&conn, id, &trash::deleted_at(chrono::Utc::now()))
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(Redirect::to("/budgets"))
+}
+
+#[post("/budgets/standing/<id>/delete")]
+fn delete_standing_budget(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+) -> Result<Redirect, AppError> {
+    require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    db::trash_standing_budget(&conn, id, &trash::deleted_at(chrono::Utc::now()))
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(Redirect::to("/budgets"))
+}
+
+#[post("/budgets/copy", data = "<form>")]
+fn copy_budgets(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    form: Form<BudgetCopyForm>,
+) -> Result<Redirect, AppError> {
+    require_user(pool, cookies)?;
+    let month = selected_month(Some(form.into_inner().month));
+    let previous = previous_month(&month).ok_or(rocket::http::Status::BadRequest)?;
+
+    let mut conn = pool.get()?;
+    let label = format!("Бюджеты {previous} скопированы в {month}");
+    bulk::run(&mut conn, false, "budget_copy", &label, |tx, changes| {
+        let ids = db::copy_budgets(tx, &previous, &month)?;
+        for id in &ids {
+            changes.inserted("budgets", *id);
+        }
+        Ok(ids)
+    })
+    .map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(Redirect::to(format!("/budgets?month={month}")))
+}
+
+/// The reports of `month`; with `compare` months picked, also those months
+/// side by side, see `month_comparison`.
+#[get("/reports?<month>&<compare>")]
+fn reports(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    month: Option<String>,
+    compare: Vec<String>,
+    flash: Option<FlashMessage<'_>>,
+) -> Result<Template, AppError> {
     let user = require_user(pool, cookies)?;
-    let conn = pool.get().expect("db connection");
-    let list = db::list_categories(&conn).unwrap_or_default();
+    let conn = pool.get()?;
+    let selected = selected_month(month);
+    let refunds = db::refund_month(&conn, user.id).unwrap_or_default();
+    let rounding = db::report_rounding(&conn, user.id).unwrap_or_default();
+    let months = db::report_months(&conn, 12, refunds).unwrap_or_default();
+    let categories = db::report_categories(&conn, &selected, refunds).unwrap_or_default();
+    let tags = db::report_tags(&conn, &selected, refunds).unwrap_or_default();
+    let days = db::report_days(&conn, &selected, refunds).unwrap_or_default();
+    let payees = db::report_payees(&conn, &selected, 10, refunds).unwrap_or_default();
+    let accounts = db::report_accounts(&conn, &selected, &selected, refunds).unwrap_or_default();
+    let net_worth = networth::series(&conn, &recent_months(12)).unwrap_or_default();
+    let nav = month_nav(&conn, "/reports", &selected)?;
+    let compared = month_comparison::months(&compare);
+    let comparison = match &compared {
+        Some(months) => Some(report_comparison_view(
+            month_comparison::compare(&conn, months, refunds)
+                .map_err(|_| rocket::http::Status::InternalServerError)?,
+            rounding,
+        )),
+        None => None,
+    };
+    let compare_options = months
+        .iter()
+        .map(|month| {
+            serde_json::json!({
+                "month": month.month,
+                "checked": compared.as_ref().is_some_and(|picked| picked.contains(&month.month)),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut month_views = Vec::new();
+    for month in months {
+        let snapshot = report_snapshot_view(&conn, &month.month, rounding)?;
+        month_views.push(report_month_view(month, rounding, snapshot.as_ref()));
+    }
+    let snapshot = report_snapshot_view(&conn, &selected, rounding)?;
+    let category_views = categories
+        .into_iter()
+        .map(|category| report_category_view(category, rounding))
+        .collect::<Vec<_>>();
+    let tag_views = tags
+        .into_iter()
+        .map(|tag| report_tag_view(tag, rounding))
+        .collect::<Vec<_>>();
+    let payee_views = payees
+        .into_iter()
+        .map(|payee| report_payee_view(payee, rounding))
+        .collect::<Vec<_>>();
+    let account_views = accounts
+        .iter()
+        .map(|record| ReportAccountView {
+            account: account_flow::account_name(record).to_string(),
+            income: rounding.format(record.income_cents),
+            expense: rounding.format(record.expense_cents),
+        })
+        .collect::<Vec<_>>();
+
     let context = serde_json::json!({
+        "can_close": selected < current_month(),
+        "month": selected,
+        "nav": nav,
         "username": user.username,
-        "categories": list,
+        "flash": flash,
+        "snapshot": snapshot,
+        "months": month_views,
+        "categories": category_views,
+        "tags": tag_views,
+        "payees": payee_views,
+        "accounts": account_views,
+        "compare_options": compare_options,
+        "comparison": comparison,
+        "comparison_error": !compare.is_empty() && comparison.is_none(),
+        "heatmap": heatmap_weeks(&selected, &days, rounding),
+        "net_worth": net_worth
+            .into_iter()
+            .map(|month| net_worth_month_view(month, rounding))
+            .collect::<Vec<_>>(),
     });
-    Ok(Template::render("categories", &context))
+    Ok(Template::render("reports", &context))
 }
 
-#[post("/categories", data = "<form>")]
-fn add_category(
+/// Income and expenses per account for the months up to `month`, as stacked
+/// series for a chart; amounts in kopecks.
+#[get("/reports/accounts.json?<month>")]
+fn report_accounts_series(
     pool: &State<DbPool>,
     cookies: &CookieJar<'_>,
-    form: Form<CategoryForm>,
-) -> Result<Redirect, rocket::http::Status> {
-    if let Err(redirect) = require_user(pool, cookies) {
-        return Ok(redirect);
-    }
-    let form = form.into_inner();
-    if form.name.trim().is_empty() {
-        return Err(rocket::http::Status::BadRequest);
-    }
-    let conn = pool.get().map_err(|_| rocket::http::Status::InternalServerError)?;
-    db::insert_category(&conn, form.name.trim(), &form.kind)
+    month: Option<String>,
+) -> Result<(rocket::http::ContentType, String), AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let months = account_flow::months_until(&selected_month(month));
+    let refunds = db::refund_month(&conn, user.id).unwrap_or_default();
+    let records = db::report_accounts(&conn, &months[0], &months[months.len() - 1], refunds)
         .map_err(|_| rocket::http::Status::InternalServerError)?;
-    Ok(Redirect::to("/categories"))
+    let body = serde_json::to_string(&account_flow::series(&months, &records))
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok((rocket::http::ContentType::JSON, body))
 }
-
-#[get("/budgets?<month>")]
-fn budgets(
+
+#[get("/reports/accounts.csv?<month>")]
+fn export_report_accounts(
     pool: &State<DbPool>,
     cookies: &CookieJar<'_>,
     month: Option<String>,
-) -> Result<Template, Redirect> {
+) -> Result<export::Attachment, AppError> {
     let user = require_user(pool, cookies)?;
-    let conn = pool.get().expect("db connection");
+    let conn = pool.get()?;
     let selected = selected_month(month);
-    let list = db::list_budgets(&conn, &selected).unwrap_or_default();
-    let categories = db::list_categories(&conn).unwrap_or_default();
-    let views = list.into_iter().map(budget_view).collect::<Vec<_>>();
-    let months = available_months(&conn);
+    let months = account_flow::months_until(&selected);
+    let refunds = db::refund_month(&conn, user.id).unwrap_or_default();
+    let rounding = db::report_rounding(&conn, user.id).unwrap_or_default();
+    let records = db::report_accounts(&conn, &months[0], &selected, refunds).unwrap_or_default();
+    let body = export::report_accounts_csv(&records, rounding).unwrap_or_default();
+    let filename = format!("accounts-{}-{selected}.csv", months[0]);
+    let label = format!("Доходы и расходы по счетам за {} — {selected}", months[0]);
+    let _ = jobs::keep_file(&conn, user.id, &label, &filename, &body);
+    Ok(export::Attachment::csv(&filename, body))
+}
 
+/// How the price of an item changed from purchase to purchase, for items
+/// whose name contains `item`.
+#[get("/reports/prices?<item>")]
+fn item_price_report(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    item: Option<String>,
+) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let term = item.as_deref().map(str::trim).filter(|term| !term.is_empty());
+    let prices = match term {
+        Some(term) => db::item_prices(&conn, term, receipt_items::HISTORY_LIMIT)
+            .map_err(|_| rocket::http::Status::InternalServerError)?,
+        None => Vec::new(),
+    };
+    // Each purchase is compared with the one before of the same item; the
+    // summary has the first and the latest price of each.
+    let mut summary: Vec<(String, String, i64, i64, usize)> = Vec::new();
+    let mut purchases = Vec::new();
+    for price in prices {
+        let key = db::category_name_key(&price.name);
+        let change = match summary.iter_mut().find(|(seen, ..)| *seen == key) {
+            Some((_, _, _, latest, count)) => {
+                let change = receipt_items::change(*latest, price.price_cents);
+                *latest = price.price_cents;
+                *count += 1;
+                change
+            }
+            None => {
+                summary.push((key, price.name.clone(), price.price_cents, price.price_cents, 1));
+                None
+            }
+        };
+        purchases.push(serde_json::json!({
+            "transaction_id": price.transaction_id,
+            "occurred_on": price.occurred_on,
+            "name": price.name,
+            "payee": price.payee,
+            "quantity": receipt_items::format_quantity(price.quantity),
+            "price": format_money(price.price_cents),
+            "change": change,
+        }));
+    }
+    let summary = summary
+        .into_iter()
+        .map(|(_, name, first, latest, count)| {
+            serde_json::json!({
+                "name": name,
+                "purchases": count,
+                "first": format_money(first),
+                "latest": format_money(latest),
+                "change": receipt_items::change(first, latest).filter(|_| count > 1),
+            })
+        })
+        .collect::<Vec<_>>();
+    purchases.reverse();
+    let frequent = db::frequent_items(&conn, 20).unwrap_or_default();
     let context = serde_json::json!({
-        "month": selected,
-        "months": months,
         "username": user.username,
-        "budgets": views,
-        "categories": categories,
+        "item": term,
+        "summary": summary,
+        "purchases": purchases,
+        "frequent": frequent,
     });
-    Ok(Template::render("budgets", &context))
+    Ok(Template::render("item_prices", &context))
 }
 
-#[post("/budgets", data = "<form>")]
-fn add_budget(
+/// Freezes the report figures of a past month, or refreshes a closed one's.
+#[post("/reports/close", data = "<form>")]
+fn close_report_month(
     pool: &State<DbPool>,
     cookies: &CookieJar<'_>,
-    form: Form<BudgetForm>,
-) -> Result<Redirect, rocket::http::Status> {
-    if let Err(redirect) = require_user(pool, cookies) {
-        return Ok(redirect);
+    form: Form<ReportMonthForm>,
+) -> Result<Flash<Redirect>, AppError> {
+    let user = require_user(pool, cookies)?;
+    let month = selected_month(Some(form.into_inner().month));
+    previous_month(&month).ok_or(rocket::http::Status::BadRequest)?;
+    let back = Redirect::to(format!("/reports?month={month}"));
+    if month >= current_month() {
+        return Ok(Flash::error(back, "Закрыть можно только прошедший месяц"));
     }
-    let form = form.into_inner();
-    let amount_cents = parse_amount_to_cents(&form.amount)
-        .ok_or(rocket::http::Status::BadRequest)?;
-    let month = if form.month.trim().is_empty() {
-        current_month()
-    } else {
-        form.month
-    };
+    let conn = pool.get()?;
+    let refunds = db::refund_month(&conn, user.id).unwrap_or_default();
+    report_snapshots::close(&conn, &month, refunds, chrono::Utc::now())
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(Flash::success(back, format!("Месяц {month} закрыт")))
+}
 
-    let conn = pool.get().map_err(|_| rocket::http::Status::InternalServerError)?;
-    db::insert_budget(&conn, form.category_id, &month, amount_cents)
+#[post("/reports/reopen", data = "<form>")]
+fn reopen_report_month(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    form: Form<ReportMonthForm>,
+) -> Result<Flash<Redirect>, AppError> {
+    require_user(pool, cookies)?;
+    let month = selected_month(Some(form.into_inner().month));
+    previous_month(&month).ok_or(rocket::http::Status::BadRequest)?;
+    let conn = pool.get()?;
+    db::delete_report_snapshot(&conn, &month)
         .map_err(|_| rocket::http::Status::InternalServerError)?;
-    Ok(Redirect::to("/budgets"))
+    Ok(Flash::success(
+        Redirect::to(format!("/reports?month={month}")),
+        format!("Месяц {month} снова открыт"),
+    ))
 }
 
-#[get("/reports?<month>")]
-fn reports(
+#[get("/reports/categories.csv?<month>")]
+fn export_report_categories(
     pool: &State<DbPool>,
     cookies: &CookieJar<'_>,
     month: Option<String>,
-) -> Result<Template, Redirect> {
+) -> Result<export::Attachment, AppError> {
     let user = require_user(pool, cookies)?;
-    let conn = pool.get().expect("db connection");
+    let conn = pool.get()?;
     let selected = selected_month(month);
-    let months = db::report_months(&conn, 12).unwrap_or_default();
-    let categories = db::report_categories(&conn, &selected).unwrap_or_default();
-    let month_options = available_months(&conn);
-
-    let month_views = months
-        .into_iter()
-        .map(report_month_view)
-        .collect::<Vec<_>>();
-    let category_views = categories
-        .into_iter()
-        .map(report_category_view)
-        .collect::<Vec<_>>();
+    let refunds = db::refund_month(&conn, user.id).unwrap_or_default();
+    let rounding = db::report_rounding(&conn, user.id).unwrap_or_default();
+    let categories = db::report_categories(&conn, &selected, refunds).unwrap_or_default();
+    let body = export::report_categories_csv(&categories, rounding).unwrap_or_default();
+    let filename = format!("categories-{selected}.csv");
+    let label = format!("Расходы по категориям за {selected}");
+    let _ = jobs::keep_file(&conn, user.id, &label, &filename, &body);
+    Ok(export::Attachment::csv(&filename, body))
+}
 
-    let context = serde_json::json!({
-        "month": selected,
-        "month_options": month_options,
-        "username": user.username,
-        "months": month_views,
-        "categories": category_views,
-    });
-    Ok(Template::render("reports", &context))
+/// Splits a comma-separated tag list into lowercase names, dropping blanks and
+/// repeats. SQLite only folds ASCII case, so Cyrillic tags are normalized here.
+fn parse_tags(input: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in input.split(',').map(|tag| tag.trim().to_lowercase()) {
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
 }
 
 fn transaction_view(record: TransactionRecord) -> TransactionView {
@@ -716,60 +5796,297 @@ fn transaction_view(record: TransactionRecord) -> TransactionView {
         kind: record.kind,
         amount: format_money(record.amount_cents),
         occurred_on: record.occurred_on,
+        effective_month: record.effective_month,
         note: record.note,
+        payee: record.payee,
         category_name: record.category_name,
+        account_name: record.account_name,
+        to_account_name: record.to_account_name,
+        tags: record
+            .tags
+            .map(|tags| tags.split(", ").map(str::to_string).collect())
+            .unwrap_or_default(),
         receipt_url: record
             .receipt_path
+            .as_ref()
             .map(|name| format!("/receipts/{name}")),
+        thumbnail_url: record.receipt_path.as_ref().map(|name| thumbnail_url(name)),
+        receipt_name: record.receipt_path,
+        starred: record.starred,
+        status: record.status,
+        refund: record.refund_of.is_some(),
     }
 }
 
-fn budget_view(record: BudgetRecord) -> BudgetView {
-    let remaining = record.amount_cents - record.spent_cents;
-    let percent = if record.amount_cents == 0 {
+/// Ruble value of the account; unknown for foreign currency until it is revalued.
+fn account_value_cents(record: &Account) -> Option<i64> {
+    if record.currency == fx::BASE_CURRENCY {
+        return Some(record.balance_cents);
+    }
+    record
+        .latest_rate
+        .map(|rate| fx::to_base_cents(record.balance_cents, rate))
+}
+
+fn category_view(record: Category) -> CategoryView {
+    CategoryView {
+        id: record.id,
+        name: record.name,
+        kind: record.kind,
+        parent_id: record.parent_id,
+        default_amount: record.default_amount_cents.map(format_money),
+        allow_receipt: record.allow_receipt,
+    }
+}
+
+fn account_view(record: Account) -> AccountView {
+    let value = account_value_cents(&record)
+        .filter(|_| record.currency != fx::BASE_CURRENCY)
+        .map(format_money);
+    AccountView {
+        id: record.id,
+        name: record.name,
+        kind: record.kind,
+        currency: record.currency,
+        opening_balance: format_money(record.opening_balance_cents),
+        balance: format_money(record.balance_cents),
+        value,
+        rate: record.latest_rate,
+    }
+}
+
+/// Share of the budget spent, rounded; a zero budget reads as 0%.
+fn budget_percent(spent_cents: i64, budget_cents: i64) -> i64 {
+    if budget_cents == 0 {
         0
     } else {
-        ((record.spent_cents as f64 / record.amount_cents as f64) * 100.0).round() as i64
-    };
+        ((spent_cents as f64 / budget_cents as f64) * 100.0).round() as i64
+    }
+}
+
+fn budget_view(record: BudgetRecord) -> BudgetView {
+    let limit = record.amount_cents + record.carried_cents;
+    let remaining = limit - record.spent_cents;
+    let percent = budget_percent(record.spent_cents, limit);
     BudgetView {
         id: record.id,
         category_name: record.category_name,
         month: record.month,
         amount: format_money(record.amount_cents),
+        rollover: record.rollover,
+        standing: record.standing,
+        carried: (record.carried_cents != 0).then(|| format_money(record.carried_cents)),
+        limit: format_money(limit),
         spent: format_money(record.spent_cents),
         remaining: format_money(remaining),
         percent,
+        note: record.note,
     }
 }
 
 fn dashboard_budget_view(record: DashboardBudget) -> DashboardBudgetView {
-    let percent = if record.budget_cents == 0 {
-        0
-    } else {
-        ((record.spent_cents as f64 / record.budget_cents as f64) * 100.0).round() as i64
-    };
+    let limit = record.budget_cents + record.carried_cents;
+    let percent = budget_percent(record.spent_cents, limit);
     DashboardBudgetView {
         category_name: record.category_name,
-        budget: format_money(record.budget_cents),
+        budget: format_money(limit),
+        carried: (record.carried_cents != 0).then(|| format_money(record.carried_cents)),
         spent: format_money(record.spent_cents),
         remaining: format_money(record.remaining_cents),
         percent,
     }
 }
 
-fn report_month_view(record: ReportMonth) -> ReportMonthView {
+fn loan_view(record: Loan, payments: &[LoanPayment]) -> LoanView {
+    let status = loans::status(&record, payments);
+    let next_due = loans::next_installment(&record, payments).map(|installment| installment.due_on);
+    LoanView {
+        id: record.id,
+        principal: format_money(record.principal_cents),
+        annual_rate: record.annual_rate,
+        term_months: record.term_months,
+        payment: format_money(loans::monthly_payment(&record)),
+        paid: format_money(status.paid_cents),
+        interest_paid: format_money(status.interest_paid_cents),
+        remaining: format_money(status.remaining_cents),
+        payments_made: payments.len(),
+        next_due,
+        name: record.name,
+        first_month: record.first_month,
+        payment_day: record.payment_day,
+    }
+}
+
+/// The first `payments_made` installments count as paid.
+fn installment_view(record: loans::Installment, payments_made: usize) -> InstallmentView {
+    InstallmentView {
+        number: record.number,
+        due_on: record.due_on,
+        payment: format_money(record.payment_cents),
+        interest: format_money(record.interest_cents),
+        principal: format_money(record.principal_cents),
+        balance: format_money(record.balance_cents),
+        paid: usize::try_from(record.number).is_ok_and(|number| number <= payments_made),
+    }
+}
+
+fn holding_view(record: Holding) -> HoldingView {
+    HoldingView {
+        id: record.id,
+        name: record.name,
+        kind: record.kind,
+        amount: record.amount_cents.map(format_money),
+        valued_on: record.valued_on,
+    }
+}
+
+fn net_worth_month_view(record: NetWorthMonth, rounding: Rounding) -> NetWorthMonthView {
+    NetWorthMonthView {
+        month: record.month,
+        accounts: rounding.format(record.accounts_cents),
+        assets: rounding.format(record.assets_cents),
+        liabilities: rounding.format(record.liabilities_cents),
+        net_worth: rounding.format(record.net_worth_cents),
+    }
+}
+
+fn report_month_view(
+    record: ReportMonth,
+    rounding: Rounding,
+    snapshot: Option<&ReportSnapshotView>,
+) -> ReportMonthView {
     ReportMonthView {
         month: record.month,
-        income: format_money(record.income_cents),
-        expense: format_money(record.expense_cents),
-        net: format_money(record.net_cents),
+        income: rounding.format(record.income_cents),
+        expense: rounding.format(record.expense_cents),
+        net: rounding.format(record.net_cents),
+        closed: snapshot.is_some(),
+        differs: snapshot.is_some_and(|snapshot| !snapshot.differences.is_empty()),
     }
 }
 
-fn report_category_view(record: ReportCategory) -> ReportCategoryView {
+fn report_comparison_view(
+    comparison: month_comparison::Comparison,
+    rounding: Rounding,
+) -> ReportComparisonView {
+    let cells = |amounts: &[i64]| {
+        amounts
+            .iter()
+            .zip(month_comparison::deltas(amounts))
+            .map(|(cents, delta)| ReportComparisonCellView {
+                expense: rounding.format(*cents),
+                delta: delta.map(|delta| {
+                    let sign = if delta > 0 { "+" } else { "" };
+                    format!("{sign}{}", rounding.format(delta))
+                }),
+                increase: delta.is_some_and(|delta| delta > 0),
+            })
+            .collect::<Vec<_>>()
+    };
+    ReportComparisonView {
+        rows: comparison
+            .rows
+            .iter()
+            .map(|row| ReportComparisonRowView {
+                category_name: row.category_name.clone(),
+                cells: cells(&row.expense_cents),
+            })
+            .collect(),
+        totals: cells(&comparison.totals),
+        months: comparison.months,
+    }
+}
+
+/// When `month` was closed and which of its figures changed since; `None`
+/// for an open month.
+fn report_snapshot_view(
+    conn: &rusqlite::Connection,
+    month: &str,
+    rounding: Rounding,
+) -> Result<Option<ReportSnapshotView>, rocket::http::Status> {
+    let Some(snapshot) = db::report_snapshot(conn, month)
+        .map_err(|_| rocket::http::Status::InternalServerError)?
+    else {
+        return Ok(None);
+    };
+    let differences = report_snapshots::differences(conn, &snapshot)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(Some(ReportSnapshotView {
+        closed_at: local_time(&snapshot.closed_at),
+        differences: differences
+            .into_iter()
+            .map(|difference| ReportDifferenceView {
+                label: difference.label,
+                snapshot: rounding.format(difference.snapshot_cents),
+                current: rounding.format(difference.current_cents),
+            })
+            .collect(),
+    }))
+}
+
+fn report_category_view(record: ReportCategory, rounding: Rounding) -> ReportCategoryView {
     ReportCategoryView {
         category_name: record.category_name,
-        expense: format_money(record.expense_cents),
+        parent_name: record.parent_name,
+        expense: rounding.format(record.expense_cents),
+    }
+}
+
+/// The month as calendar weeks from Monday; days outside it are `None`.
+fn heatmap_weeks(
+    month: &str,
+    days: &[ReportDay],
+    rounding: Rounding,
+) -> Vec<Vec<Option<HeatmapDayView>>> {
+    let Ok(first) = NaiveDate::parse_from_str(&format!("{month}-01"), "%Y-%m-%d") else {
+        return Vec::new();
+    };
+    let max = days.iter().map(|day| day.expense_cents).max().unwrap_or(0);
+    let mut weeks = Vec::new();
+    let mut week: Vec<Option<HeatmapDayView>> = (0..first.weekday().num_days_from_monday())
+        .map(|_| None)
+        .collect();
+    for date in first.iter_days().take_while(|date| date.month() == first.month()) {
+        let ymd = date.format("%Y-%m-%d").to_string();
+        let cents = days
+            .iter()
+            .find(|day| day.day == ymd)
+            .map(|day| day.expense_cents)
+            .unwrap_or(0);
+        let level = if cents <= 0 || max <= 0 {
+            0
+        } else {
+            (cents * 4 + max - 1) / max
+        };
+        week.push(Some(HeatmapDayView {
+            date: ymd,
+            day: date.day(),
+            expense: rounding.format(cents),
+            level,
+        }));
+        if week.len() == 7 {
+            weeks.push(std::mem::take(&mut week));
+        }
+    }
+    if !week.is_empty() {
+        week.resize_with(7, || None);
+        weeks.push(week);
+    }
+    weeks
+}
+
+fn report_tag_view(record: ReportTag, rounding: Rounding) -> ReportTagView {
+    ReportTagView {
+        tag_name: record.tag_name,
+        expense: rounding.format(record.expense_cents),
+    }
+}
+
+fn report_payee_view(record: ReportPayee, rounding: Rounding) -> ReportPayeeView {
+    ReportPayeeView {
+        payee: record.payee,
+        transactions: record.transactions,
+        expense: rounding.format(record.expense_cents),
     }
 }
 
@@ -778,12 +6095,593 @@ fn rocket() -> _ {
     let mut db_path = PathBuf::from("data");
     std::fs::create_dir_all(&db_path).expect("create data directory");
     db_path.push("lumen.sqlite");
-    let pool = db::init_db(&db_path);
-    let receipts = receipts_dir();
-    std::fs::create_dir_all(&receipts).expect("create receipts directory");
+    let metrics = Arc::new(db::PoolMetrics::default());
+    let pool = db::init_db(&db_path, &db::PoolConfig::from_env(), metrics.clone());
+    if let Ok(conn) = pool.get() {
+        // Nothing is uploading yet, so every temporary receipt is left over.
+        let _ = receipt_files::recover(&conn, &receipts_dir());
+    }
+
+    build_rocket(
+        pool,
+        metrics,
+        telegram::TelegramConfig::from_env(),
+        fx::RatesSource::from_env(),
+        accountant::Delivery::from_env(),
+        indexation::Schedule::from_env(),
+        allowlist::Allowlist::from_env(),
+    )
+}
+
+#[get("/receipts/inbox")]
+fn receipt_inbox_page(pool: &State<DbPool>, cookies: &CookieJar<'_>) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    render_receipt_inbox(&conn, &user, FormState::default())
+}
+
+fn render_receipt_inbox(
+    conn: &rusqlite::Connection,
+    user: &User,
+    qr_form: FormState,
+) -> Result<Template, AppError> {
+    let uploads = db::list_receipt_uploads(conn)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    let mut views = Vec::new();
+    for upload in &uploads {
+        let proposals = receipt_inbox::proposals(conn, upload)
+            .map_err(|_| rocket::http::Status::InternalServerError)?;
+        let attached = db::receipt_in_use(conn, &upload.file_name)
+            .map_err(|_| rocket::http::Status::InternalServerError)?;
+        let reused = if attached {
+            Some("Этот чек уже приложен к операции")
+        } else if uploads
+            .iter()
+            .any(|other| other.id != upload.id && other.file_name == upload.file_name)
+        {
+            Some("Этот чек загружен несколько раз")
+        } else {
+            None
+        };
+        views.push(ReceiptUploadView {
+            id: upload.id,
+            url: format!("/receipts/{}", upload.file_name),
+            thumbnail_url: thumbnail_url(&upload.file_name),
+            original_name: upload.original_name.clone(),
+            occurred_on: upload.occurred_on.clone(),
+            amount: upload.amount_cents.map(format_money),
+            recognized: upload.recognized,
+            new_expense_url: upload
+                .occurred_on
+                .as_deref()
+                .zip(upload.amount_cents)
+                .map(|(occurred_on, amount_cents)| {
+                    format!(
+                        "/transactions/new?kind=expense&amount={}&occurred_on={occurred_on}",
+                        format_money(amount_cents)
+                    )
+                }),
+            proposals,
+            reused,
+        });
+    }
+    let context = serde_json::json!({
+        "username": user.username,
+        "uploads": views,
+        "match_days": receipt_inbox::MATCH_DAYS,
+        "qr_form": qr_form,
+        "categories": db::list_categories(conn).unwrap_or_default(),
+        "accounts": db::list_accounts(conn).unwrap_or_default(),
+    });
+    Ok(Template::render("receipt_inbox", &context))
+}
+
+/// A purchase, or its refund, from the QR code of a fiscal receipt, with the
+/// photo of the receipt attached when one is sent along.
+#[post("/receipts/qr", data = "<form>")]
+async fn add_from_fiscal_qr(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    storage: &State<receipt_storage::Receipts>,
+    metadata: &State<photo_metadata::Policy>,
+    lookup: &State<Option<fiscal_receipts::Lookup>>,
+    form: Form<FiscalQrForm<'_>>,
+) -> Result<Flash<Redirect>, AppError> {
+    let user = require_user(pool, cookies)?;
+    let mut form = form.into_inner();
+    let mut sent = FormState::default();
+    sent.value("qr", &form.qr);
+    sent.value("category_id", &form.category_id.map(|id| id.to_string()).unwrap_or_default());
+    sent.value("account_id", &form.account_id.map(|id| id.to_string()).unwrap_or_default());
+    sent.value("payee", form.payee.as_deref().unwrap_or(""));
+    let fiscal = fiscal_receipts::FiscalReceipt::parse(&form.qr)
+        .map_err(|message| sent.error("qr", message))
+        .ok();
+    let conn = pool.get()?;
+    let added = match &fiscal {
+        Some(fiscal) => db::transaction_by_fiscal_id(&conn, &fiscal.id)
+            .map_err(|_| rocket::http::Status::InternalServerError)?,
+        None => None,
+    };
+    if added.is_some() {
+        sent.error("qr", "Операция по этому чеку уже добавлена");
+    }
+    let category = match form.category_id {
+        Some(category_id) => db::category_by_id(&conn, category_id)
+            .map_err(|_| rocket::http::Status::InternalServerError)?,
+        None => None,
+    };
+    match (&category, &fiscal) {
+        (None, _) if form.category_id.is_some() => sent.error("category_id", "Категория не найдена"),
+        (Some(category), Some(fiscal)) if category.kind != fiscal.kind => sent.error(
+            "category_id",
+            if fiscal.kind == "expense" {
+                "Это чек покупки, выберите категорию расходов"
+            } else {
+                "Это чек возврата, выберите категорию доходов"
+            },
+        ),
+        _ => {}
+    }
+    let Some(fiscal) = fiscal.filter(|_| sent.is_valid()) else {
+        let page = render_receipt_inbox(&conn, &user, sent)?;
+        return Err(AppError::Invalid(Box::new(page)));
+    };
+    drop(conn);
+
+    let checked = match lookup.inner().clone() {
+        Some(lookup) => {
+            let qr = form.qr.clone();
+            rocket::tokio::task::spawn_blocking(move || lookup.check(&qr))
+                .await
+                .ok()
+                .flatten()
+                .unwrap_or_default()
+        }
+        None => fiscal_receipts::Checked::default(),
+    };
+    let payee = form.payee.as_deref().and_then(optional_field).or(checked.seller);
+    // A refund's lines are what was returned, not prices paid.
+    let items = if fiscal.kind == "expense" {
+        checked.items
+    } else {
+        Vec::new()
+    };
+    let receipt = stage_receipt(sent_receipt(form.receipt.take()), pool, storage, metadata).await?;
+    let receipt_name = receipt
+        .as_ref()
+        .filter(|staged| !staged.reused())
+        .map(|staged| staged.name().to_string());
+
+    let mut conn = pool.get()?;
+    let occurred_on = fiscal.qr.occurred_on.format("%Y-%m-%d").to_string();
+    let mut transaction = NewTransaction {
+        kind: fiscal.kind.to_string(),
+        amount_cents: fiscal.qr.amount_cents,
+        category_id: form.category_id,
+        occurred_on: occurred_on.clone(),
+        note: None,
+        payee,
+        account_id: form.account_id,
+        to_account_id: None,
+    };
+    let rules = db::list_rules(&conn).unwrap_or_default();
+    let categories = db::list_categories(&conn).unwrap_or_default();
+    let tags = rules::apply(&rules, &categories, &mut transaction);
+    let (_, receipt_kept) = receipt_files::save(&mut conn, receipt, |tx, receipt_path| {
+        let transaction_id = db::insert_transaction(tx, &transaction, receipt_path)?;
+        db::set_fiscal_id(tx, transaction_id, &fiscal.id)?;
+        db::add_receipt_items(tx, transaction_id, &items)?;
+        db::add_transaction_tags(tx, transaction_id, &tags)
+    })
+    .map_err(|_| rocket::http::Status::InternalServerError)?;
+    if let (Some(category_id), "expense") = (transaction.category_id, transaction.kind.as_str()) {
+        let month = effective_months::month_of(&occurred_on, None);
+        notify_budget_exceeded(&conn, user.id, category_id, month, transaction.amount_cents);
+    }
+    drop(conn);
+    if let Some(name) = receipt_name {
+        store_receipt(storage, name).await;
+    }
+
+    let month = &occurred_on[..7];
+    let added = if fiscal.kind == "expense" && !items.is_empty() {
+        format!(
+            "Расход {} по чеку добавлен, позиций: {}",
+            format_money(transaction.amount_cents),
+            items.len()
+        )
+    } else if fiscal.kind == "expense" {
+        format!("Расход {} по чеку добавлен", format_money(transaction.amount_cents))
+    } else {
+        format!("Возврат {} по чеку добавлен", format_money(transaction.amount_cents))
+    };
+    let redirect = Redirect::to(format!("/transactions?month={month}"));
+    if !receipt_kept {
+        return Ok(Flash::error(redirect, format!("{added}, но фото чека сохранить не удалось")));
+    }
+    Ok(Flash::success(redirect, added))
+}
+
+/// Saves every photo to the inbox with the date and total of its QR code,
+/// when there was one, or else as recognized on the photo when OCR is
+/// configured. A photo with the same bytes as a kept receipt, or as another
+/// one sent along, gets that file instead of a copy.
+#[post("/receipts/inbox", data = "<form>")]
+async fn upload_receipts(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    storage: &State<receipt_storage::Receipts>,
+    metadata: &State<photo_metadata::Policy>,
+    ocr: &State<Option<receipt_ocr::Ocr>>,
+    form: Form<ReceiptUploadForm<'_>>,
+) -> Result<Redirect, AppError> {
+    require_user(pool, cookies)?;
+    let ReceiptUploadForm { mut files, qr } = form.into_inner();
+    let stem = format!("receipt-{}", Local::now().timestamp_millis());
+    let dir = receipts_dir();
+
+    let mut photos: Vec<InboxUpload> = Vec::new();
+    for (index, file) in files.iter_mut().enumerate() {
+        if file.len() == 0 {
+            continue;
+        }
+        let staged = match stage_photo(file, format!("{stem}-{index}"), pool, storage, metadata).await {
+            Ok(staged) => staged,
+            Err(status) => {
+                photos.iter().for_each(|photo| photo.staged.discard());
+                return Err(status.into());
+            }
+        };
+        let sent_along = photos
+            .iter()
+            .find(|photo| staged.sha256().is_some_and(|sha256| photo.staged.sha256() == Some(sha256)));
+        let staged = match sent_along {
+            Some(photo) => {
+                staged.discard();
+                receipt_files::Staged::existing(&dir, photo.staged.name().to_string())
+            }
+            None => staged,
+        };
+        photos.push(InboxUpload {
+            staged,
+            original_name: file.name().map(str::to_string),
+            fiscal: qr.get(index).and_then(|text| receipt_inbox::FiscalQr::parse(text)),
+        });
+    }
+
+    let uploaded_at = Local::now().to_rfc3339();
+    let mut dropped: Vec<String> = Vec::new();
+    let mut photos = photos.into_iter();
+    while let Some(InboxUpload { staged, original_name, fiscal }) = photos.next() {
+        let name = staged.name().to_string();
+        let fresh = !staged.reused();
+        if !fresh && dropped.contains(&name) {
+            continue;
+        }
+        let occurred_on = fiscal
+            .as_ref()
+            .map(|fiscal| fiscal.occurred_on.format("%Y-%m-%d").to_string());
+        let receipt = occurred_on.as_deref().zip(fiscal.as_ref().map(|fiscal| fiscal.amount_cents));
+        let saved = pool.get().map_err(AppError::from).and_then(|mut conn| {
+            let (id, kept) = receipt_files::save(&mut conn, Some(staged), |tx, _| {
+                db::insert_receipt_upload(tx, &name, original_name.as_deref(), receipt, false, &uploaded_at)
+            })
+            .map_err(|_| rocket::http::Status::InternalServerError)?;
+            if !kept {
+                db::delete_receipt_upload(&conn, id).map_err(|_| rocket::http::Status::InternalServerError)?;
+            }
+            Ok((id, kept))
+        });
+        let (id, kept) = match saved {
+            Ok(saved) => saved,
+            Err(err) => {
+                photos.for_each(|photo| photo.staged.discard());
+                return Err(err);
+            }
+        };
+        if !kept {
+            dropped.push(name);
+            continue;
+        }
+        if !fresh {
+            continue;
+        }
+        // Read before the photo may leave for a bucket.
+        let read = match (&fiscal, ocr.inner()) {
+            (None, Some(ocr)) => receipt_ocr::read_in_background(ocr, dir.join(&name)).await,
+            _ => None,
+        };
+        // The photo is saved already; what was read only saves typing.
+        if let (Some(read), Ok(conn)) = (read, pool.get()) {
+            let occurred_on = read.occurred_on.format("%Y-%m-%d").to_string();
+            let _ = db::set_receipt_upload_details(&conn, id, &occurred_on, read.amount_cents, true);
+        }
+        store_receipt(storage, name).await;
+    }
+    Ok(Redirect::to("/receipts/inbox"))
+}
+
+/// A photo of an inbox upload, staged until its row is saved; see
+/// `receipt_files`.
+struct InboxUpload {
+    staged: receipt_files::Staged,
+    original_name: Option<String>,
+    fiscal: Option<receipt_inbox::FiscalQr>,
+}
+
+/// Date and total typed in for a photo whose QR code couldn't be read.
+#[post("/receipts/inbox/<id>", data = "<form>")]
+fn set_receipt_details(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+    form: Form<ReceiptDetailsForm>,
+) -> Result<Redirect, AppError> {
+    require_user(pool, cookies)?;
+    let occurred_on = NaiveDate::parse_from_str(form.occurred_on.trim(), "%Y-%m-%d")
+        .map_err(|_| rocket::http::Status::BadRequest)?;
+    let amount_cents = parse_amount_to_cents(&form.amount).ok_or(rocket::http::Status::BadRequest)?;
+    let conn = pool.get()?;
+    let found = db::set_receipt_upload_details(
+        &conn,
+        id,
+        &occurred_on.format("%Y-%m-%d").to_string(),
+        amount_cents,
+        false,
+    )
+    .map_err(|_| rocket::http::Status::InternalServerError)?;
+    if !found {
+        return Err(rocket::http::Status::NotFound.into());
+    }
+    Ok(Redirect::to("/receipts/inbox"))
+}
+
+#[post("/receipts/inbox/<id>/attach/<transaction_id>")]
+fn attach_uploaded_receipt(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+    transaction_id: i64,
+) -> Result<Redirect, AppError> {
+    require_user(pool, cookies)?;
+    let mut conn = pool.get()?;
+    let tx = conn
+        .transaction()
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    let file_name = db::receipt_upload_file(&tx, id)
+        .map_err(|_| rocket::http::Status::InternalServerError)?
+        .ok_or(rocket::http::Status::NotFound)?;
+    let attached = db::attach_receipt(&tx, transaction_id, &file_name)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    if !attached {
+        return Err(rocket::http::Status::BadRequest.into());
+    }
+    db::delete_receipt_upload(&tx, id).map_err(|_| rocket::http::Status::InternalServerError)?;
+    tx.commit()
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    Ok(Redirect::to("/receipts/inbox"))
+}
+
+#[post("/receipts/inbox/<id>/delete")]
+async fn delete_uploaded_receipt(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    storage: &State<receipt_storage::Receipts>,
+    id: i64,
+) -> Result<Redirect, AppError> {
+    require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let Some(file_name) = db::receipt_upload_file(&conn, id)
+        .map_err(|_| rocket::http::Status::InternalServerError)?
+    else {
+        return Ok(Redirect::to("/receipts/inbox"));
+    };
+    db::delete_receipt_upload(&conn, id).map_err(|_| rocket::http::Status::InternalServerError)?;
+    // The same photo may have been uploaded again, or attached.
+    let released = db::release_receipt(&conn, &file_name)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    drop(conn);
+    if released {
+        let storage = storage.inner().clone();
+        let _ = rocket::tokio::task::spawn_blocking(move || {
+            storage.remove(&thumbnails::name(&file_name))?;
+            storage.remove(&file_name)
+        })
+        .await;
+    }
+    Ok(Redirect::to("/receipts/inbox"))
+}
+
+/// A receipt of the household; a file that only happens to be in the
+/// receipts directory, left over or not one of ours, is not found.
+#[get("/receipts/<name>")]
+async fn receipt(
+    name: &str,
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    storage: &State<receipt_storage::Receipts>,
+) -> Result<Option<receipts::Receipt>, AppError> {
+    require_user(pool, cookies)?;
+    let known = {
+        let conn = pool.get()?;
+        db::receipt_known(&conn, name).map_err(|_| rocket::http::Status::InternalServerError)?
+    };
+    if !known {
+        return Ok(None);
+    }
+    Ok(receipts::Receipt::open(storage, name).await)
+}
+
+/// The small copy of a receipt for lists, or the receipt itself when it has
+/// none.
+#[get("/receipts/thumbnails/<name>")]
+async fn receipt_thumbnail(
+    name: &str,
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    storage: &State<receipt_storage::Receipts>,
+) -> Result<Option<receipts::Receipt>, AppError> {
+    require_user(pool, cookies)?;
+    let known = {
+        let conn = pool.get()?;
+        db::receipt_known(&conn, name).map_err(|_| rocket::http::Status::InternalServerError)?
+    };
+    if !known {
+        return Ok(None);
+    }
+    match receipts::Receipt::open(storage, &thumbnails::name(name)).await {
+        Some(thumbnail) => Ok(Some(thumbnail)),
+        None => Ok(receipts::Receipt::open(storage, name).await),
+    }
+}
+
+/// The user's receipt links, with who opened them.
+#[get("/receipts/shares")]
+fn receipt_shares_page(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    flash: Option<FlashMessage<'_>>,
+) -> Result<Template, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let now = api_sessions::timestamp(chrono::Utc::now());
+    let shares = db::list_receipt_shares(&conn, user.id)
+        .map_err(|_| rocket::http::Status::InternalServerError)?
+        .into_iter()
+        .map(|share| {
+            let expired = share.expires_at <= now;
+            serde_json::json!({ "share": share, "expired": expired })
+        })
+        .collect::<Vec<_>>();
+    let context = serde_json::json!({
+        "username": user.username,
+        "flash": flash,
+        "shares": shares,
+    });
+    Ok(Template::render("receipt_shares", &context))
+}
+
+#[post("/receipts/shares", data = "<form>")]
+fn share_receipt(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    form: Form<ReceiptShareForm>,
+) -> Result<Flash<Redirect>, AppError> {
+    let user = require_user(pool, cookies)?;
+    let name = form.name.as_str();
+    let days = form.days.unwrap_or(receipt_shares::DEFAULT_DAYS);
+    if !receipt_shares::DAYS.contains(&days) {
+        return Err(rocket::http::Status::BadRequest.into());
+    }
+    let conn = pool.get()?;
+    let known = db::receipt_known(&conn, name).map_err(|_| rocket::http::Status::InternalServerError)?;
+    if !known {
+        return Err(rocket::http::Status::NotFound.into());
+    }
+    let created = receipt_shares::create(&conn, user.id, name, days, chrono::Utc::now())
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    let until = created.expires_at.get(..16).unwrap_or_default().replace('T', " ");
+    Ok(Flash::success(
+        Redirect::to("/receipts/shares"),
+        format!(
+            "Ссылка на чек работает до {until} UTC, сохраните ее сейчас: {}",
+            receipt_shares::url(&created.token)
+        ),
+    ))
+}
+
+#[post("/receipts/shares/<id>/revoke")]
+fn revoke_receipt_share(
+    pool: &State<DbPool>,
+    cookies: &CookieJar<'_>,
+    id: i64,
+) -> Result<Flash<Redirect>, AppError> {
+    let user = require_user(pool, cookies)?;
+    let conn = pool.get()?;
+    let now = api_sessions::timestamp(chrono::Utc::now());
+    let revoked = db::revoke_receipt_share(&conn, user.id, id, &now)
+        .map_err(|_| rocket::http::Status::InternalServerError)?;
+    if !revoked {
+        return Err(rocket::http::Status::NotFound.into());
+    }
+    Ok(Flash::success(Redirect::to("/receipts/shares"), "Ссылка отозвана"))
+}
+
+/// A shared receipt, for whoever holds the link; no login needed.
+#[get("/shared/receipts/<token>")]
+async fn shared_receipt(
+    pool: &State<DbPool>,
+    storage: &State<receipt_storage::Receipts>,
+    token: &str,
+    user_agent: login_alert::UserAgent,
+    client: allowlist::ClientAddr,
+) -> Result<Option<receipts::Receipt>, AppError> {
+    let file_name = {
+        let conn = pool.get()?;
+        receipt_shares::open(&conn, token, &user_agent.0, client.0, chrono::Utc::now())
+            .map_err(|_| rocket::http::Status::InternalServerError)?
+    };
+    let Some(file_name) = file_name else {
+        return Ok(None);
+    };
+    Ok(receipts::Receipt::open(storage, &file_name).await)
+}
+
+#[get("/static/<path..>")]
+async fn static_file(path: PathBuf, assets: &State<assets::Assets>) -> Option<assets::StaticFile> {
+    assets.open(path.to_str()?).await
+}
+
+/// Rocket's own configuration, with `LUMEN_TEMPLATE_DIR` overriding where
+/// templates are loaded from (`templates/` by default).
+fn figment() -> rocket::figment::Figment {
+    // Room for a batch of phone photos in the receipt inbox.
+    let figment = rocket::Config::figment()
+        .merge(("limits.data-form", "64 MiB"))
+        .merge(("limits.file", "16 MiB"));
+    match std::env::var("LUMEN_TEMPLATE_DIR") {
+        Ok(dir) if !dir.trim().is_empty() => figment.merge(("template_dir", dir.trim().to_string())),
+        _ => figment,
+    }
+}
+
+/// Assembles the app around `pool`; integrations left as `None` start no background jobs,
+/// and without `admin_networks` the maintenance pages are open to any address.
+fn build_rocket(
+    pool: DbPool,
+    metrics: Arc<db::PoolMetrics>,
+    telegram_config: Option<telegram::TelegramConfig>,
+    rates_source: Option<fx::RatesSource>,
+    accountant_delivery: Option<accountant::Delivery>,
+    budget_schedule: Option<indexation::Schedule>,
+    admin_networks: Option<allowlist::Allowlist>,
+) -> rocket::Rocket<rocket::Build> {
+    std::fs::create_dir_all(receipts_dir()).expect("create receipts directory");
+    let rates_pool = pool.clone();
+    let accountant_pool = pool.clone();
+    let indexation_pool = pool.clone();
+    let trash_pool = pool.clone();
+    let digest_pool = pool.clone();
+    let digest_config = telegram_config.clone();
+    let mailer = login_alert::Mailer::from_env();
+    let summary_pool = pool.clone();
+    let summary_channels = (telegram_config.clone(), mailer.clone());
+    // Fingerprinted names only in release builds, where templates aren't reloaded either.
+    let assets = assets::Assets::load(Path::new(assets::DIR), !cfg!(debug_assertions));
+    let asset_function = assets.clone();
 
-    rocket::build()
-        .manage(pool)
+    rocket::custom(figment())
+        .manage(pool.clone())
+        .manage(metrics)
+        .manage(assets)
+        .manage(jobs::Jobs::new(pool.clone()))
+        .manage(pdf::Font::from_env())
+        .manage(fiscal_receipts::Lookup::from_env())
+        .manage(mailer)
+        .manage(telegram_config.clone())
+        .manage(admin_networks)
+        .manage(allowlist::TrustedProxies::from_env())
         .mount(
             "/",
             routes![
@@ -792,20 +6690,287 @@ fn rocket() -> _ {
                 login,
                 login_post,
                 logout,
+                csrf_rejected,
                 settings,
                 settings_password,
+                settings_notifications,
+                settings_telegram,
+                settings_email,
+                settings_daily_summary,
+                settings_delete_account,
+                settings_refunds,
+                settings_rounding,
+                revoke_from_alert_page,
+                revoke_from_alert,
+                settings_rates,
+                settings_rates_delete,
+                telegram_webhook,
+                notifications_page,
+                notifications_read_all,
+                settings_revoke_session,
                 settings_logout_all,
+                date_repair,
+                fix_dates,
+                set_transaction_date,
+                users_page,
+                merge_users,
+                pool_metrics,
+                recent_queries,
                 dashboard,
                 transactions,
+                transaction_defaults,
+                category_suggestions,
+                transaction_categories,
+                transaction_draft,
                 add_transaction,
+                effective_months_page,
+                shift_effective_months,
+                add_transfer,
+                duplicate_transaction,
+                star_transaction,
+                delete_transaction,
+                export_transactions,
+                import_page,
+                import_upload,
+                import_preview,
+                import_commit,
+                trash_page,
+                restore_from_trash,
+                disputes_page,
+                open_dispute,
+                refund_dispute,
+                close_dispute,
+                receipt_items_page,
+                add_receipt_item,
+                delete_receipt_item,
+                cash_page,
+                add_withdrawal,
+                convert_withdrawal,
+                activity,
+                activity_undo,
+                export_activity,
                 categories,
                 add_category,
+                update_category,
+                delete_category,
+                category_duplicates,
+                merge_category_duplicates,
+                category_kind_page,
+                change_category_kind,
+                accounts,
+                add_account,
+                update_account,
+                revalue_account,
+                delete_account,
+                account_statement,
+                account_statement_csv,
+                account_statement_pdf,
+                loan_list,
+                add_loan,
+                loan_detail,
+                add_loan_payment,
+                delete_loan,
+                meter_list,
+                add_meter,
+                meter_detail,
+                add_meter_reading,
+                link_meter_expense,
+                delete_meter,
+                add_tariff,
+                delete_tariff,
+                net_worth,
+                add_holding,
+                add_valuation,
+                delete_holding,
+                inbound_hooks,
+                add_inbound_hook,
+                delete_inbound_hook,
+                rule_list,
+                add_rule,
+                delete_rule,
+                receive_inbound_hook,
                 budgets,
                 add_budget,
-                reports
+                copy_budgets,
+                set_budget_increase,
+                budget_note,
+                standing_budget_note,
+                delete_budget,
+                delete_standing_budget,
+                reports,
+                close_report_month,
+                reopen_report_month,
+                export_report_categories,
+                report_accounts_series,
+                export_report_accounts,
+                item_price_report,
+                export_archive,
+                job_page,
+                api_job,
+                api_sign_in,
+                api_refresh,
+                api_revoke,
+                api_transactions,
+                api_add_transaction,
+                api_star_transaction,
+                api_token_list,
+                api_backup,
+                api_token_page,
+                add_api_token,
+                delete_api_token,
+                downloads,
+                job_download,
+                receipt_inbox_page,
+                upload_receipts,
+                add_from_fiscal_qr,
+                set_receipt_details,
+                attach_uploaded_receipt,
+                delete_uploaded_receipt,
+                receipt,
+                receipt_thumbnail,
+                receipt_shares_page,
+                share_receipt,
+                revoke_receipt_share,
+                shared_receipt,
+                static_file
             ],
         )
-        .mount("/static", FileServer::from("static"))
-        .mount("/receipts", FileServer::from(receipts))
-        .attach(Template::fairing())
+        // Response fairings run in the order attached: tokens go into a page
+        // before it is compressed.
+        .attach(query_inspector::Inspector)
+        .attach(photo_metadata::Setting)
+        .attach(receipt_storage::Setting)
+        .attach(receipt_ocr::Setting)
+        .attach(security_headers::Headers)
+        .attach(remember::Resume)
+        .attach(csrf::Protection)
+        .attach(compression::Compression)
+        .attach(Template::custom(move |engines| {
+            engines
+                .tera
+                .register_function("asset", asset_function.tera_function());
+        }))
+        .attach(AdHoc::on_liftoff("Telegram outbox", |_| {
+            Box::pin(async move {
+                let Some(config) = telegram_config else {
+                    return;
+                };
+                rocket::tokio::spawn(async move {
+                    loop {
+                        let (pool, config) = (pool.clone(), config.clone());
+                        let _ = rocket::tokio::task::spawn_blocking(move || {
+                            telegram::deliver_pending(&pool, &config)
+                        })
+                        .await;
+                        rocket::tokio::time::sleep(telegram::OUTBOX_INTERVAL).await;
+                    }
+                });
+            })
+        }))
+        .attach(AdHoc::on_liftoff("Telegram digest", |_| {
+            Box::pin(async move {
+                let Some(config) = digest_config else {
+                    return;
+                };
+                rocket::tokio::spawn(async move {
+                    loop {
+                        let (pool, config) = (digest_pool.clone(), config.clone());
+                        let _ = rocket::tokio::task::spawn_blocking(move || {
+                            digest::run_due(&pool, &config)
+                        })
+                        .await;
+                        rocket::tokio::time::sleep(digest::CHECK_INTERVAL).await;
+                    }
+                });
+            })
+        }))
+        .attach(AdHoc::on_liftoff("Daily summary", |_| {
+            Box::pin(async move {
+                if let (None, None) = summary_channels {
+                    return;
+                }
+                rocket::tokio::spawn(async move {
+                    loop {
+                        let (pool, (telegram, mailer)) =
+                            (summary_pool.clone(), summary_channels.clone());
+                        let _ = rocket::tokio::task::spawn_blocking(move || {
+                            daily_summary::run_due(&pool, telegram.as_ref(), mailer.as_ref())
+                        })
+                        .await;
+                        rocket::tokio::time::sleep(daily_summary::CHECK_INTERVAL).await;
+                    }
+                });
+            })
+        }))
+        .attach(AdHoc::on_liftoff("Exchange rates", |_| {
+            Box::pin(async move {
+                let Some(source) = rates_source else {
+                    return;
+                };
+                rocket::tokio::spawn(async move {
+                    loop {
+                        let (pool, source) = (rates_pool.clone(), source.clone());
+                        let _ = rocket::tokio::task::spawn_blocking(move || {
+                            fx::refresh_rates(&pool, &source)
+                        })
+                        .await;
+                        rocket::tokio::time::sleep(fx::REFRESH_INTERVAL).await;
+                    }
+                });
+            })
+        }))
+        .attach(AdHoc::on_liftoff("Accountant export", |rocket| {
+            let storage = rocket.state::<receipt_storage::Receipts>().cloned();
+            Box::pin(async move {
+                let (Some(delivery), Some(storage)) = (accountant_delivery, storage) else {
+                    return;
+                };
+                rocket::tokio::spawn(async move {
+                    loop {
+                        let (pool, storage, delivery) =
+                            (accountant_pool.clone(), storage.clone(), delivery.clone());
+                        let _ = rocket::tokio::task::spawn_blocking(move || {
+                            accountant::run_due(&pool, &*storage, &delivery)
+                        })
+                        .await;
+                        rocket::tokio::time::sleep(accountant::CHECK_INTERVAL).await;
+                    }
+                });
+            })
+        }))
+        .attach(AdHoc::on_liftoff("Budget indexation", move |_| {
+            Box::pin(async move {
+                let Some(schedule) = budget_schedule else {
+                    return;
+                };
+                rocket::tokio::spawn(async move {
+                    loop {
+                        let pool = indexation_pool.clone();
+                        let _ = rocket::tokio::task::spawn_blocking(move || {
+                            indexation::run_due(&pool, schedule)
+                        })
+                        .await;
+                        rocket::tokio::time::sleep(indexation::CHECK_INTERVAL).await;
+                    }
+                });
+            })
+        }))
+        .attach(AdHoc::on_liftoff("Trash purge", |rocket| {
+            let storage = rocket.state::<receipt_storage::Receipts>().cloned();
+            Box::pin(async move {
+                let Some(storage) = storage else {
+                    return;
+                };
+                rocket::tokio::spawn(async move {
+                    loop {
+                        let (pool, storage) = (trash_pool.clone(), storage.clone());
+                        let _ = rocket::tokio::task::spawn_blocking(move || {
+                            trash::run_due(&pool, &*storage)
+                        })
+                        .await;
+                        rocket::tokio::time::sleep(trash::CHECK_INTERVAL).await;
+                    }
+                });
+            })
+        }))
 }
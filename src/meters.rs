@@ -0,0 +1,195 @@
+//! Utility meters of the household: electricity, water and gas readings
+//! taken once a month, the usage between them, and the ЖКХ expense that
+//! paid for it.
+//!
+//! A reading is for a month and replaces an earlier one of the same month.
+//! The usage of a month is its reading minus the one before, so the first
+//! reading of a meter has none. Bills for a month are usually paid in the
+//! next one, so expenses from both are offered to be linked to a reading.
+//!
+//! Tariffs give the price of a unit for a kind of meter from a date on; a
+//! month's usage is priced at the tariff in effect on its first day. The
+//! expected bill of a month is the priced usage of all meters, and comparing
+//! it with the expenses linked to the month's readings shows an overcharge.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{Months, NaiveDate};
+
+use crate::models::{Meter, MeterReading, Tariff};
+
+/// How many months of bills the meters page shows.
+pub const BILL_MONTHS: usize = 12;
+
+/// Kinds of meters with their names and units.
+pub const KINDS: [(&str, &str, &str); 4] = [
+    ("electricity", "Электричество", "кВт·ч"),
+    ("cold_water", "Холодная вода", "м³"),
+    ("hot_water", "Горячая вода", "м³"),
+    ("gas", "Газ", "м³"),
+];
+
+pub fn is_kind(kind: &str) -> bool {
+    KINDS.iter().any(|(known, ..)| *known == kind)
+}
+
+pub fn kind_name(kind: &str) -> &'static str {
+    KINDS
+        .iter()
+        .find(|(known, ..)| *known == kind)
+        .map_or("", |(_, name, _)| name)
+}
+
+pub fn unit(kind: &str) -> &'static str {
+    KINDS
+        .iter()
+        .find(|(known, ..)| *known == kind)
+        .map_or("", |(.., unit)| unit)
+}
+
+/// Reads a meter value such as `12345` or `1234,56`.
+pub fn parse_value(value: &str) -> Option<f64> {
+    value
+        .trim()
+        .replace(',', ".")
+        .parse::<f64>()
+        .ok()
+        .filter(|value| value.is_finite() && *value >= 0.0)
+}
+
+/// A value as meters show it, without trailing zeros: `12345`, `1234.56`.
+pub fn format_value(value: f64) -> String {
+    let text = format!("{value:.3}");
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// The usage of each reading since the one before; `readings` are oldest
+/// first.
+pub fn usage(readings: &[MeterReading]) -> Vec<Option<f64>> {
+    let mut previous: Option<f64> = None;
+    readings
+        .iter()
+        .map(|reading| {
+            let used = previous.map(|value| reading.value - value);
+            previous = Some(reading.value);
+            used
+        })
+        .collect()
+}
+
+/// Why `value` can't be the reading of `month`: meters only count up, so it
+/// may be neither below an earlier month's reading nor above a later one's.
+pub fn out_of_order(readings: &[MeterReading], month: &str, value: f64) -> Option<&'static str> {
+    let earlier = readings
+        .iter()
+        .filter(|reading| reading.month.as_str() < month);
+    let later = readings
+        .iter()
+        .filter(|reading| reading.month.as_str() > month);
+    if earlier
+        .map(|reading| reading.value)
+        .any(|before| before > value)
+    {
+        Some("Показание меньше прошлого")
+    } else if later
+        .map(|reading| reading.value)
+        .any(|after| after < value)
+    {
+        Some("Показание больше следующего")
+    } else {
+        None
+    }
+}
+
+/// First and last day of the dates an expense paying for `month` may have:
+/// the month itself and the next one.
+pub fn payment_window(month: &str) -> Option<(String, String)> {
+    let first = NaiveDate::parse_from_str(&format!("{month}-01"), "%Y-%m-%d").ok()?;
+    let last = first.checked_add_months(Months::new(2))?.pred_opt()?;
+    Some((
+        first.format("%Y-%m-%d").to_string(),
+        last.format("%Y-%m-%d").to_string(),
+    ))
+}
+
+/// The rate of `kind` in effect on the first day of `month`.
+pub fn rate_on(tariffs: &[Tariff], kind: &str, month: &str) -> Option<i64> {
+    let first = format!("{month}-01");
+    tariffs
+        .iter()
+        .filter(|tariff| tariff.kind == kind && tariff.effective_from <= first)
+        .max_by(|a, b| a.effective_from.cmp(&b.effective_from))
+        .map(|tariff| tariff.rate_cents)
+}
+
+/// What `usage` units at `rate_cents` each cost, to the kopeck.
+pub fn cost_cents(usage: f64, rate_cents: i64) -> i64 {
+    (usage * rate_cents as f64).round() as i64
+}
+
+/// The ЖКХ bill of a month by the meters.
+#[derive(Debug, PartialEq)]
+pub struct Bill {
+    /// `YYYY-MM`.
+    pub month: String,
+    /// How many meters have usage in the month.
+    pub metered: usize,
+    /// The usage priced at the tariffs.
+    pub expected_cents: i64,
+    /// Meters whose usage has no tariff, so isn't in `expected_cents`.
+    pub unpriced: Vec<String>,
+    /// The expenses linked to the month's readings, each counted once.
+    pub paid_cents: Option<i64>,
+}
+
+impl Bill {
+    /// How much more was paid than expected; `None` while the bill is
+    /// incomplete or unpaid.
+    pub fn overcharge_cents(&self) -> Option<i64> {
+        if !self.unpriced.is_empty() {
+            return None;
+        }
+        self.paid_cents
+            .map(|paid| paid - self.expected_cents)
+            .filter(|difference| *difference > 0)
+    }
+}
+
+/// The bills of the latest `limit` months with usage, newest first;
+/// readings of each meter are oldest first.
+pub fn bills(meters: &[(Meter, Vec<MeterReading>)], tariffs: &[Tariff], limit: usize) -> Vec<Bill> {
+    let mut months: BTreeMap<&str, (Bill, BTreeSet<i64>)> = BTreeMap::new();
+    for (meter, readings) in meters {
+        for (reading, used) in readings.iter().zip(usage(readings)) {
+            let (bill, counted) = months.entry(&reading.month).or_insert_with(|| {
+                let bill = Bill {
+                    month: reading.month.clone(),
+                    metered: 0,
+                    expected_cents: 0,
+                    unpriced: Vec::new(),
+                    paid_cents: None,
+                };
+                (bill, BTreeSet::new())
+            });
+            if let Some(used) = used {
+                bill.metered += 1;
+                match rate_on(tariffs, &meter.kind, &reading.month) {
+                    Some(rate) => bill.expected_cents += cost_cents(used, rate),
+                    None => bill.unpriced.push(meter.name.clone()),
+                }
+            }
+            if let (Some(id), Some(paid)) = (reading.transaction_id, reading.paid_cents)
+                && counted.insert(id)
+            {
+                bill.paid_cents = Some(bill.paid_cents.unwrap_or(0) + paid);
+            }
+        }
+    }
+    months
+        .into_values()
+        .rev()
+        .map(|(bill, _)| bill)
+        .filter(|bill| bill.metered > 0)
+        .take(limit)
+        .collect()
+}
@@ -1,10 +1,28 @@
 use serde::Serialize;
 
-#[derive(Serialize)]
+use crate::disputes::RefundMonth;
+
+#[derive(Clone, Serialize)]
 pub struct Category {
     pub id: i64,
     pub name: String,
     pub kind: String,
+    pub parent_id: Option<i64>,
+    /// Pre-filled in the transaction form when the category is picked.
+    pub default_amount_cents: Option<i64>,
+    /// Expenses in it may have a receipt attached.
+    pub allow_receipt: bool,
+}
+
+#[derive(Serialize)]
+pub struct Account {
+    pub id: i64,
+    pub name: String,
+    pub kind: String,
+    pub opening_balance_cents: i64,
+    pub balance_cents: i64,
+    pub currency: String,
+    pub latest_rate: Option<f64>,
 }
 
 #[derive(Serialize)]
@@ -14,8 +32,105 @@ pub struct TransactionRecord {
     pub amount_cents: i64,
     pub occurred_on: String,
     pub note: Option<String>,
+    pub payee: Option<String>,
     pub category_name: Option<String>,
     pub receipt_path: Option<String>,
+    pub account_name: Option<String>,
+    pub category_id: Option<i64>,
+    pub to_account_name: Option<String>,
+    pub tags: Option<String>,
+    /// Marked as notable, such as a big purchase or a disputed charge.
+    pub starred: bool,
+    /// `normal`, `disputed` or `refunded`, see `disputes`.
+    pub status: String,
+    /// The expense this income refunds.
+    pub refund_of: Option<i64>,
+    /// `YYYY-MM` the transaction counts toward instead of its date's month,
+    /// see `effective_months`.
+    pub effective_month: Option<String>,
+}
+
+/// A disputed or refunded expense.
+pub struct Dispute {
+    pub transaction_id: i64,
+    pub occurred_on: String,
+    pub amount_cents: i64,
+    pub category_name: Option<String>,
+    pub payee: Option<String>,
+    pub note: Option<String>,
+    pub status: String,
+    pub disputed_on: Option<String>,
+    /// What came back and when, once refunded.
+    pub refund_cents: Option<i64>,
+    pub refunded_on: Option<String>,
+}
+
+/// A cash account and how it was filled and spent in a month.
+pub struct CashEnvelope {
+    pub account_id: i64,
+    pub name: String,
+    /// Withdrawals and other transfers in.
+    pub withdrawn_cents: i64,
+    pub spent_cents: i64,
+    pub last_withdrawal_on: Option<String>,
+}
+
+/// An expense from a card or bank account that may be an ATM withdrawal.
+pub struct WithdrawalCandidate {
+    pub transaction_id: i64,
+    pub occurred_on: String,
+    pub amount_cents: i64,
+    pub account_name: String,
+    pub payee: Option<String>,
+    pub note: Option<String>,
+}
+
+/// A transaction as it moved one account's balance.
+#[derive(Clone, Serialize)]
+pub struct StatementLine {
+    pub id: i64,
+    pub occurred_on: String,
+    pub kind: String,
+    pub note: Option<String>,
+    pub category_name: Option<String>,
+    /// The other account of a transfer.
+    pub counterparty: Option<String>,
+    /// Positive when money came in.
+    pub amount_cents: i64,
+}
+
+/// A category sharing its kind and name, up to case and spacing, with another.
+#[derive(Serialize)]
+pub struct CategoryDuplicate {
+    pub id: i64,
+    pub name: String,
+    pub kind: String,
+    pub name_key: String,
+    pub parent_name: Option<String>,
+    pub transactions: i64,
+}
+
+/// A transaction whose `occurred_on` isn't a `YYYY-MM-DD` date.
+#[derive(Serialize)]
+pub struct MalformedDate {
+    pub id: i64,
+    pub occurred_on: String,
+    pub kind: String,
+    pub amount_cents: i64,
+    pub note: Option<String>,
+    pub category_name: Option<String>,
+}
+
+pub struct NewTransaction {
+    pub kind: String,
+    pub amount_cents: i64,
+    pub category_id: Option<i64>,
+    pub occurred_on: String,
+    pub note: Option<String>,
+    /// Who was paid or who paid; free text.
+    pub payee: Option<String>,
+    pub account_id: Option<i64>,
+    pub to_account_id: Option<i64>,
 }
 
 #[derive(Serialize)]
@@ -24,6 +139,29 @@ pub struct User {
     pub username: String,
 }
 
+/// A browser logged in to the account.
+#[derive(Serialize)]
+pub struct BrowserSession {
+    pub id: i64,
+    /// Empty when the browser sent none.
+    pub user_agent: String,
+    pub ip: Option<String>,
+    pub created_at: String,
+    pub last_used_at: String,
+    /// The session of the browser looking at the list.
+    pub current: bool,
+}
+
+#[derive(Serialize)]
+pub struct ApiToken {
+    pub id: i64,
+    pub name: String,
+    /// `read`, `write` or `admin`, see `api_tokens::Scope`.
+    pub scope: String,
+    pub created_at: String,
+    pub last_used_at: Option<String>,
+}
+
 #[derive(Serialize)]
 pub struct BudgetRecord {
     pub id: i64,
@@ -32,6 +170,233 @@ pub struct BudgetRecord {
     pub month: String,
     pub amount_cents: i64,
     pub spent_cents: i64,
+    pub rollover: bool,
+    /// A standing budget filling in for a month without its own; `id` is then
+    /// the standing budget's.
+    pub standing: bool,
+    /// Remainder brought in from earlier rollover months; negative after overspending.
+    pub carried_cents: i64,
+    /// What the budget is meant to achieve, e.g. «не больше 2 походов в ресторан».
+    pub note: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct StandingBudget {
+    pub id: i64,
+    pub category_id: i64,
+    pub category_name: String,
+    pub amount_cents: i64,
+    /// Raised by this much at the start of every fiscal year, see `indexation`.
+    pub yearly_increase_percent: Option<f64>,
+}
+
+/// A receipt photo uploaded without a transaction, see `receipt_inbox`.
+#[derive(Serialize)]
+pub struct ReceiptUpload {
+    pub id: i64,
+    pub file_name: String,
+    pub original_name: Option<String>,
+    /// Date and total from the receipt, once read off its QR code or photo or
+    /// typed in.
+    pub occurred_on: Option<String>,
+    pub amount_cents: Option<i64>,
+    /// The date and total were recognized on the photo, see `receipt_ocr`,
+    /// and nobody has confirmed them yet.
+    pub recognized: bool,
+    pub uploaded_at: String,
+}
+
+/// A link that opens one receipt without an account, see `receipt_shares`.
+#[derive(Serialize)]
+pub struct ReceiptShare {
+    pub id: i64,
+    pub file_name: String,
+    pub created_at: String,
+    pub expires_at: String,
+    pub revoked_at: Option<String>,
+    /// Every opening of the link, the latest first.
+    pub views: Vec<ReceiptShareView>,
+}
+
+#[derive(Serialize)]
+pub struct ReceiptShareView {
+    pub viewed_at: String,
+    pub ip: Option<String>,
+    pub user_agent: String,
+}
+
+/// An expense an uploaded receipt may belong to.
+#[derive(Serialize)]
+pub struct ReceiptCandidate {
+    pub transaction_id: i64,
+    pub occurred_on: String,
+    pub category_name: Option<String>,
+    pub payee: Option<String>,
+    pub note: Option<String>,
+}
+
+/// A line of a receipt: `quantity` units at `price_cents` each, see
+/// `receipt_items`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NewReceiptItem {
+    pub name: String,
+    pub quantity: f64,
+    pub price_cents: i64,
+}
+
+pub struct ReceiptItem {
+    pub id: i64,
+    pub name: String,
+    pub quantity: f64,
+    pub price_cents: i64,
+}
+
+/// One purchase of an item, for its price history.
+pub struct ItemPrice {
+    pub transaction_id: i64,
+    pub occurred_on: String,
+    pub name: String,
+    pub payee: Option<String>,
+    pub quantity: f64,
+    pub price_cents: i64,
+}
+
+/// An item and how many expenses listed it.
+#[derive(Serialize)]
+pub struct ItemCount {
+    pub name: String,
+    pub purchases: i64,
+}
+
+/// A deleted transaction or budget waiting in the trash.
+#[derive(Serialize)]
+pub struct TrashItem {
+    /// `transactions`, `budgets` or `standing_budgets`.
+    pub table: String,
+    pub id: i64,
+    pub deleted_at: String,
+    /// The transaction's date, the budget's month, `None` for a standing budget.
+    pub occurred_on: Option<String>,
+    pub category_name: Option<String>,
+    pub note: Option<String>,
+    pub amount_cents: i64,
+}
+
+/// A background job and, once it is over, its outcome.
+#[derive(Clone, Serialize)]
+pub struct Job {
+    pub id: i64,
+    pub user_id: i64,
+    pub label: String,
+    /// `running`, `done` or `failed`.
+    pub state: String,
+    pub done: i64,
+    pub total: i64,
+    /// Where to go once the job is done: a page, or the job's download.
+    pub result_url: Option<String>,
+    pub error: Option<String>,
+    /// A generated file and the name it is downloaded under.
+    pub file_path: Option<String>,
+    pub file_name: Option<String>,
+    pub created_at: String,
+    pub finished_at: Option<String>,
+    /// When the file is deleted; until then it is listed under downloads.
+    pub expires_at: Option<String>,
+}
+
+/// An annuity loan: equal monthly payments from `first_month` on.
+#[derive(Serialize)]
+pub struct Loan {
+    pub id: i64,
+    pub name: String,
+    pub principal_cents: i64,
+    /// Yearly interest in percent, e.g. `12.5`.
+    pub annual_rate: f64,
+    pub term_months: i64,
+    /// `YYYY-MM` of the first payment.
+    pub first_month: String,
+    /// Day of the month payments are due; shorter months use their last day.
+    pub payment_day: i64,
+}
+
+/// A utility meter, see `meters`.
+#[derive(Serialize)]
+pub struct Meter {
+    pub id: i64,
+    pub name: String,
+    /// `electricity`, `cold_water`, `hot_water` or `gas`.
+    pub kind: String,
+}
+
+/// A meter's reading for a month, with the utility expense that paid for
+/// the usage up to it.
+pub struct MeterReading {
+    pub id: i64,
+    /// `YYYY-MM`.
+    pub month: String,
+    pub value: f64,
+    pub read_on: String,
+    pub transaction_id: Option<i64>,
+    /// Date and amount of that expense while it is outside the trash.
+    pub paid_on: Option<String>,
+    pub paid_cents: Option<i64>,
+}
+
+/// The price of a unit for a kind of meter from a date on.
+pub struct Tariff {
+    pub id: i64,
+    pub kind: String,
+    pub rate_cents: i64,
+    /// `YYYY-MM-DD`.
+    pub effective_from: String,
+}
+
+/// A ЖКХ expense a meter reading may be paid by.
+#[derive(Serialize)]
+pub struct UtilityExpense {
+    pub transaction_id: i64,
+    pub occurred_on: String,
+    pub amount_cents: i64,
+    pub category_name: String,
+    pub payee: Option<String>,
+}
+
+pub struct NewLoan {
+    pub name: String,
+    pub principal_cents: i64,
+    pub annual_rate: f64,
+    pub term_months: i64,
+    pub first_month: String,
+    pub payment_day: i64,
+}
+
+/// A transaction recorded as a payment on a loan.
+#[derive(Serialize)]
+pub struct LoanPayment {
+    pub transaction_id: i64,
+    pub occurred_on: String,
+    pub amount_cents: i64,
+}
+
+/// An asset or liability valued by hand, with its latest valuation.
+#[derive(Serialize)]
+pub struct Holding {
+    pub id: i64,
+    pub name: String,
+    /// `asset` or `liability`.
+    pub kind: String,
+    pub amount_cents: Option<i64>,
+    pub valued_on: Option<String>,
+}
+
+/// Net worth at the end of a month, in rubles.
+#[derive(Serialize)]
+pub struct NetWorthMonth {
+    pub month: String,
+    pub accounts_cents: i64,
+    pub assets_cents: i64,
+    pub liabilities_cents: i64,
+    pub net_worth_cents: i64,
 }
 
 #[derive(Serialize)]
@@ -45,6 +410,104 @@ pub struct ReportMonth {
 #[derive(Serialize)]
 pub struct ReportCategory {
     pub category_name: String,
+    pub parent_name: Option<String>,
+    pub expense_cents: i64,
+}
+
+/// A top-level category's expenses in one month, children included; the name
+/// is empty for expenses without a category.
+pub struct ReportCategoryMonth {
+    pub month: String,
+    pub category_name: String,
+    pub expense_cents: i64,
+}
+
+/// A payee and note pair of earlier transactions and how often it went to
+/// the category.
+pub struct CategoryUse {
+    pub payee: Option<String>,
+    pub note: Option<String>,
+    pub category_id: i64,
+    pub uses: i64,
+}
+
+/// A category suggestion taken `accepted` times for the normalized `text`
+/// typed into `field`, `payee` or `note`.
+pub struct AcceptedSuggestion {
+    pub field: String,
+    pub text: String,
+    pub category_id: i64,
+    pub accepted: i64,
+}
+
+/// A month's income and expenses on one account; `account_name` is `None`
+/// for transactions without an account.
+#[derive(Serialize)]
+pub struct ReportAccountMonth {
+    pub month: String,
+    pub account_name: Option<String>,
+    pub income_cents: i64,
+    pub expense_cents: i64,
+}
+
+/// A day's income and expenses and how many transactions it has.
+pub struct DayTotals {
+    pub entries: i64,
+    pub income_cents: i64,
+    pub expense_cents: i64,
+}
+
+/// When the end-of-day summary goes out and where; `at` is a local `HH:MM`,
+/// `None` while the summary is off.
+#[derive(Serialize, Default)]
+pub struct DailySummarySettings {
+    pub at: Option<String>,
+    pub telegram: bool,
+    pub email: bool,
+}
+
+/// Someone due an end-of-day summary, with the chat and address of the
+/// channels they picked.
+pub struct DailySummaryRecipient {
+    pub user_id: i64,
+    pub chat_id: Option<i64>,
+    pub email: Option<String>,
+}
+
+/// One figure of a month's report: `income`, `expense`, or the expenses of
+/// the top-level category `category_name` for `category`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReportFigure {
+    pub kind: String,
+    pub category_name: String,
+    pub amount_cents: i64,
+}
+
+/// A closed month's figures as they were when it was closed.
+pub struct ReportSnapshot {
+    pub month: String,
+    /// How refunds were counted then; the live figures are compared the same way.
+    pub refunds: RefundMonth,
+    pub closed_at: String,
+    pub figures: Vec<ReportFigure>,
+}
+
+/// Expenses of one day, `YYYY-MM-DD`.
+pub struct ReportDay {
+    pub day: String,
+    pub expense_cents: i64,
+}
+
+#[derive(Serialize)]
+pub struct ReportTag {
+    pub tag_name: String,
+    pub expense_cents: i64,
+}
+
+#[derive(Serialize)]
+pub struct ReportPayee {
+    pub payee: String,
+    pub transactions: i64,
     pub expense_cents: i64,
 }
 
@@ -52,6 +515,170 @@ pub struct ReportCategory {
 pub struct DashboardBudget {
     pub category_name: String,
     pub budget_cents: i64,
+    pub carried_cents: i64,
     pub spent_cents: i64,
     pub remaining_cents: i64,
 }
+
+#[derive(Serialize)]
+pub struct NotificationPreference {
+    pub event: String,
+    pub channel: String,
+    pub enabled: bool,
+}
+
+#[derive(Serialize)]
+pub struct NotificationRecord {
+    pub id: i64,
+    pub event: String,
+    pub title: String,
+    pub body: String,
+    pub created_at: String,
+    pub read_at: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct BulkOperationRecord {
+    pub id: i64,
+    pub kind: String,
+    pub label: String,
+    pub created_at: String,
+    pub undone_at: Option<String>,
+    pub change_count: i64,
+}
+
+/// One change of an operation in the activity log, or the operation alone
+/// when it changed nothing.
+#[derive(Serialize)]
+pub struct AuditEntry {
+    pub operation_id: i64,
+    pub created_at: String,
+    pub kind: String,
+    pub label: String,
+    pub undone_at: Option<String>,
+    pub table_name: Option<String>,
+    pub row_id: Option<i64>,
+    pub action: Option<String>,
+    /// The row before the change, as JSON; none for inserts.
+    pub snapshot: Option<String>,
+}
+
+pub struct BulkChange {
+    pub table_name: String,
+    pub row_id: i64,
+    pub action: String,
+    pub snapshot: Option<String>,
+}
+
+pub struct NewNotification {
+    pub event: String,
+    pub title: String,
+    pub body: String,
+    pub payload: Option<String>,
+}
+
+pub struct PendingNotification {
+    pub id: i64,
+    pub event: String,
+    pub title: String,
+    pub body: String,
+    pub payload: Option<String>,
+    pub telegram_chat_id: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct InboundHook {
+    pub id: i64,
+    pub user_id: i64,
+    pub name: String,
+    pub secret: String,
+    pub kind: String,
+    pub amount_field: String,
+    pub date_field: Option<String>,
+    pub note_field: Option<String>,
+    pub category_field: Option<String>,
+    pub category_id: Option<i64>,
+    pub account_id: Option<i64>,
+    pub last_used_at: Option<String>,
+}
+
+/// A "remember me" series and the hash of its current token.
+pub struct RememberToken {
+    pub id: i64,
+    pub user_id: i64,
+    pub token_hash: String,
+    pub expires_at: String,
+}
+
+/// One access/refresh pair; each refresh issues the next pair in the family.
+pub struct ApiSession {
+    pub id: i64,
+    pub user_id: i64,
+    pub family: String,
+    pub refresh_expires_at: String,
+    pub rotated_at: Option<String>,
+    pub revoked_at: Option<String>,
+}
+
+pub struct NewApiSession {
+    pub user_id: i64,
+    pub family: String,
+    pub access_token: String,
+    pub access_expires_at: String,
+    pub refresh_token: String,
+    pub refresh_expires_at: String,
+}
+
+pub struct NewInboundHook {
+    pub name: String,
+    pub kind: String,
+    pub amount_field: String,
+    pub date_field: Option<String>,
+    pub note_field: Option<String>,
+    pub category_field: Option<String>,
+    pub category_id: Option<i64>,
+    pub account_id: Option<i64>,
+}
+
+/// Categorizes new transactions whose note or payee contains `pattern`.
+#[derive(Serialize)]
+pub struct Rule {
+    pub id: i64,
+    pub pattern: String,
+    pub min_cents: Option<i64>,
+    pub max_cents: Option<i64>,
+    pub category_id: Option<i64>,
+    pub category_name: Option<String>,
+    /// Only used without a category; otherwise the category decides the kind.
+    pub kind: Option<String>,
+    /// Comma-separated, as typed.
+    pub tags: Option<String>,
+}
+
+pub struct NewRule {
+    pub pattern: String,
+    pub min_cents: Option<i64>,
+    pub max_cents: Option<i64>,
+    pub category_id: Option<i64>,
+    pub kind: Option<String>,
+    pub tags: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ExchangeRate {
+    pub currency: String,
+    pub rate_date: String,
+    pub rate: f64,
+    pub source: String,
+}
+
+#[derive(Serialize)]
+pub struct PoolStats {
+    pub max_size: u32,
+    pub connections: u32,
+    pub idle_connections: u32,
+    pub checkouts: u64,
+    pub timeouts: u64,
+    pub avg_wait_ms: f64,
+    pub max_wait_ms: f64,
+}
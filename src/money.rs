@@ -0,0 +1,114 @@
+//! Amounts are stored as integer kopecks and shown with two decimals.
+
+pub fn format_money(cents: i64) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    let abs = cents.unsigned_abs();
+    let whole = abs / 100;
+    let frac = abs % 100;
+    format!("{sign}{whole}.{frac:02}")
+}
+
+pub fn parse_amount_to_cents(input: &str) -> Option<i64> {
+    let mut s = input.trim().to_string();
+    if s.is_empty() {
+        return None;
+    }
+    if s.starts_with('-') {
+        return None;
+    }
+    s = s.replace(',', ".");
+    let mut parts = s.split('.');
+    let whole_str = parts.next()?;
+    let frac_str = parts.next();
+    if parts.next().is_some() {
+        return None;
+    }
+    // `parse` alone would also accept signs such as "1.-5" or "+3".
+    if !whole_str.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let whole: i64 = whole_str.parse().ok()?;
+    let frac = match frac_str {
+        None => 0,
+        Some(frac) => {
+            if frac.len() > 2 || !frac.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            let mut padded = frac.to_string();
+            while padded.len() < 2 {
+                padded.push('0');
+            }
+            padded.parse::<i64>().ok()?
+        }
+    };
+    whole.checked_mul(100)?.checked_add(frac)
+}
+
+/// How report figures are shown; stored amounts always keep their kopecks.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Rounding {
+    #[default]
+    Kopecks,
+    /// Whole rubles.
+    Rubles,
+    /// Thousands of rubles to one decimal.
+    Thousands,
+}
+
+impl Rounding {
+    /// The setting as stored and sent by the settings form; `None` for
+    /// anything else.
+    pub fn from_param(value: &str) -> Option<Rounding> {
+        [Rounding::Kopecks, Rounding::Rubles, Rounding::Thousands]
+            .into_iter()
+            .find(|rounding| rounding.param() == value)
+    }
+
+    pub fn param(self) -> &'static str {
+        match self {
+            Rounding::Kopecks => "kopecks",
+            Rounding::Rubles => "rubles",
+            Rounding::Thousands => "thousands",
+        }
+    }
+
+    /// `cents` as a bare number in the chosen unit, as exports write it.
+    pub fn number(self, cents: i64) -> String {
+        match self {
+            Rounding::Kopecks => format_money(cents),
+            Rounding::Rubles => rounded_div(cents, 100).to_string(),
+            Rounding::Thousands => {
+                let tenths = rounded_div(cents, 10_000);
+                let sign = if tenths < 0 { "-" } else { "" };
+                let abs = tenths.unsigned_abs();
+                format!("{sign}{}.{}", abs / 10, abs % 10)
+            }
+        }
+    }
+
+    /// `cents` for a page; thousands carry their unit.
+    pub fn format(self, cents: i64) -> String {
+        match self {
+            Rounding::Thousands => format!("{} тыс.", self.number(cents)),
+            _ => self.number(cents),
+        }
+    }
+
+    /// A column heading with the unit its numbers are in.
+    pub fn heading(self, name: &str) -> String {
+        match self {
+            Rounding::Thousands => format!("{name}, тыс. ₽"),
+            _ => name.to_string(),
+        }
+    }
+}
+
+/// `value / by` rounded half away from zero.
+fn rounded_div(value: i64, by: i64) -> i64 {
+    let half = by / 2;
+    if value < 0 {
+        -((-value + half) / by)
+    } else {
+        (value + half) / by
+    }
+}
@@ -0,0 +1,102 @@
+//! Months side by side on the reports page.
+//!
+//! Two or three picked months get a column each with the expenses of every
+//! top-level category any of them has, zero where a month has none, and the
+//! change from the column before. Categories come in the order of the last
+//! month's expenses, so the biggest current ones lead.
+
+use rusqlite::{Connection, Result};
+
+use crate::db;
+use crate::disputes::RefundMonth;
+use crate::models::ReportCategoryMonth;
+
+pub const MIN_MONTHS: usize = 2;
+pub const MAX_MONTHS: usize = 3;
+/// The row expenses without a category are shown under.
+pub const NO_CATEGORY: &str = "Без категории";
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Comparison {
+    /// Oldest first.
+    pub months: Vec<String>,
+    pub rows: Vec<Row>,
+    /// Every category's expenses added up, per month.
+    pub totals: Vec<i64>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Row {
+    pub category_name: String,
+    /// Per month, in the order of [`Comparison::months`].
+    pub expense_cents: Vec<i64>,
+}
+
+/// The picked months in order without repeats, or `None` unless there are
+/// [`MIN_MONTHS`] to [`MAX_MONTHS`] of them and all are `YYYY-MM`.
+pub fn months(picked: &[String]) -> Option<Vec<String>> {
+    let mut months = Vec::new();
+    for month in picked {
+        let month = month.trim();
+        crate::previous_month(month)?;
+        months.push(month.to_string());
+    }
+    months.sort();
+    months.dedup();
+    (MIN_MONTHS..=MAX_MONTHS)
+        .contains(&months.len())
+        .then_some(months)
+}
+
+/// The change of each amount from the one before it; the first has none.
+pub fn deltas(amounts: &[i64]) -> Vec<Option<i64>> {
+    std::iter::once(None)
+        .chain(amounts.windows(2).map(|pair| Some(pair[1] - pair[0])))
+        .take(amounts.len())
+        .collect()
+}
+
+pub fn compare(conn: &Connection, months: &[String], refunds: RefundMonth) -> Result<Comparison> {
+    let records = db::report_category_months(conn, months, refunds)?;
+    Ok(build(months, &records))
+}
+
+fn build(months: &[String], records: &[ReportCategoryMonth]) -> Comparison {
+    let mut rows: Vec<Row> = Vec::new();
+    for record in records {
+        let Some(index) = months.iter().position(|month| *month == record.month) else {
+            continue;
+        };
+        let name = if record.category_name.is_empty() {
+            NO_CATEGORY
+        } else {
+            &record.category_name
+        };
+        let row = match rows.iter().position(|row| row.category_name == name) {
+            Some(row) => row,
+            None => {
+                rows.push(Row {
+                    category_name: name.to_string(),
+                    expense_cents: vec![0; months.len()],
+                });
+                rows.len() - 1
+            }
+        };
+        rows[row].expense_cents[index] += record.expense_cents;
+    }
+    rows.sort_by(|a, b| {
+        b.expense_cents
+            .iter()
+            .rev()
+            .cmp(a.expense_cents.iter().rev())
+            .then_with(|| a.category_name.cmp(&b.category_name))
+    });
+    let totals = (0..months.len())
+        .map(|index| rows.iter().map(|row| row.expense_cents[index]).sum())
+        .collect();
+    Comparison {
+        months: months.to_vec(),
+        rows,
+        totals,
+    }
+}
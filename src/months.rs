@@ -0,0 +1,116 @@
+//! The month navigator above the dashboard, transactions, budgets and reports.
+//!
+//! It spans every month from the first date with data to the last, widened to
+//! take in today and the month being shown, and groups them by year. Months
+//! without transactions or budgets stay in the list, only greyed out, so the
+//! arrows always step one calendar month and a gap in the data is visible
+//! rather than skipped.
+
+use chrono::{Datelike, Months, NaiveDate};
+use rusqlite::{Connection, Result};
+use serde::Serialize;
+
+use crate::db;
+
+const LABELS: [&str; 12] = [
+    "янв", "фев", "мар", "апр", "май", "июн", "июл", "авг", "сен", "окт", "ноя", "дек",
+];
+
+#[derive(Debug, Serialize)]
+pub struct MonthNav {
+    /// Page the month links point at, e.g. `/budgets`.
+    pub path: &'static str,
+    pub month: String,
+    pub prev: Option<String>,
+    pub next: Option<String>,
+    /// Newest year first.
+    pub years: Vec<NavYear>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NavYear {
+    pub year: i32,
+    /// January first; only the months within the span.
+    pub months: Vec<NavMonth>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NavMonth {
+    pub month: String,
+    pub label: &'static str,
+    pub has_data: bool,
+}
+
+/// First day of a `YYYY-MM` month, or `None` when it isn't one.
+fn first_day(month: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(&format!("{month}-01"), "%Y-%m-%d").ok()
+}
+
+fn month_of(date: NaiveDate) -> String {
+    date.format("%Y-%m").to_string()
+}
+
+/// The navigator for `month` on the page at `path`; a `month` that isn't
+/// `YYYY-MM` gets no arrows.
+pub fn navigator(
+    conn: &Connection,
+    path: &'static str,
+    month: &str,
+    today: NaiveDate,
+) -> Result<MonthNav> {
+    let selected = first_day(month);
+    let mut first = today.with_day(1).unwrap_or(today);
+    let mut last = first;
+    let bounds = db::date_bounds(conn)?;
+    let bound_days = bounds
+        .iter()
+        .flat_map(|(from, to)| [from, to])
+        .filter_map(|day| NaiveDate::parse_from_str(day, "%Y-%m-%d").ok());
+    for day in bound_days
+        .map(|day| day.with_day(1).unwrap_or(day))
+        .chain(selected)
+    {
+        first = first.min(day);
+        last = last.max(day);
+    }
+
+    let span = (last.year() - first.year()) * 12 + last.month() as i32 - first.month() as i32 + 1;
+    let mut with_data = db::list_months(conn, span.into())?;
+    with_data.extend(db::list_budget_months(conn, span.into())?);
+
+    let mut years: Vec<NavYear> = Vec::new();
+    let mut day = first;
+    while day <= last {
+        let entry = NavMonth {
+            month: month_of(day),
+            label: LABELS[day.month0() as usize],
+            has_data: with_data.contains(&month_of(day)),
+        };
+        match years.last_mut() {
+            Some(year) if year.year == day.year() => year.months.push(entry),
+            _ => years.push(NavYear {
+                year: day.year(),
+                months: vec![entry],
+            }),
+        }
+        let Some(next) = day.checked_add_months(Months::new(1)) else {
+            break;
+        };
+        day = next;
+    }
+    years.reverse();
+
+    let step = |months: fn(NaiveDate) -> Option<NaiveDate>| {
+        selected
+            .and_then(months)
+            .filter(|day| (first..=last).contains(day))
+            .map(month_of)
+    };
+    Ok(MonthNav {
+        path,
+        month: month.to_string(),
+        prev: step(|day| day.checked_sub_months(Months::new(1))),
+        next: step(|day| day.checked_add_months(Months::new(1))),
+        years,
+    })
+}
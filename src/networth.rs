@@ -0,0 +1,40 @@
+//! Net worth: account balances plus assets valued by hand, minus liabilities.
+
+use rusqlite::{Connection, Result};
+
+use crate::db;
+use crate::fx;
+use crate::models::NetWorthMonth;
+
+/// Net worth at the end of each of `months`, in the same order.
+///
+/// Accounts count with their balance at the month end. Foreign-currency
+/// accounts use the latest revaluation rate up to that month and are left out
+/// before their first revaluation, as on the accounts page. Holdings count
+/// with their latest valuation by the month end.
+pub fn series(conn: &Connection, months: &[String]) -> Result<Vec<NetWorthMonth>> {
+    let accounts = db::list_accounts(conn)?;
+    let mut out = Vec::with_capacity(months.len());
+    for month in months {
+        let until = fx::month_end(month)
+            .ok_or_else(|| rusqlite::Error::InvalidParameterName(month.clone()))?;
+        let mut accounts_cents = 0;
+        for account in &accounts {
+            let balance = db::account_balance_until(conn, account.id, &until)?;
+            if account.currency == fx::BASE_CURRENCY {
+                accounts_cents += balance;
+            } else if let Some(rate) = db::revaluation_rate_until(conn, account.id, month)? {
+                accounts_cents += fx::to_base_cents(balance, rate);
+            }
+        }
+        let (assets_cents, liabilities_cents) = db::holdings_until(conn, &until)?;
+        out.push(NetWorthMonth {
+            month: month.clone(),
+            accounts_cents,
+            assets_cents,
+            liabilities_cents,
+            net_worth_cents: accounts_cents + assets_cents - liabilities_cents,
+        });
+    }
+    Ok(out)
+}
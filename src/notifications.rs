@@ -0,0 +1,101 @@
+use chrono::Local;
+use rusqlite::{Connection, Result};
+use serde::Serialize;
+
+use crate::db;
+use crate::models::{NewNotification, NotificationPreference};
+
+pub const EVENTS: &[(&str, &str)] = &[
+    ("budget_exceeded", "Превышение бюджета"),
+    ("login", "Вход в аккаунт"),
+    ("accountant_export", "Отправка чеков бухгалтеру"),
+    ("budget_increase", "Индексация бюджетов"),
+];
+
+pub const CHANNELS: &[(&str, &str)] = &[
+    ("in_app", "В приложении"),
+    ("email", "Email"),
+    ("telegram", "Telegram"),
+    ("webhook", "Webhook"),
+];
+
+#[derive(Serialize)]
+pub struct ChannelToggle {
+    pub channel: &'static str,
+    pub label: &'static str,
+    pub enabled: bool,
+}
+
+#[derive(Serialize)]
+pub struct EventPreferences {
+    pub event: &'static str,
+    pub label: &'static str,
+    pub channels: Vec<ChannelToggle>,
+}
+
+pub fn event_label(event: &str) -> &str {
+    EVENTS
+        .iter()
+        .find(|(known, _)| *known == event)
+        .map(|(_, label)| *label)
+        .unwrap_or(event)
+}
+
+fn default_enabled(channel: &str) -> bool {
+    channel == "in_app"
+}
+
+fn is_enabled(preferences: &[NotificationPreference], event: &str, channel: &str) -> bool {
+    preferences
+        .iter()
+        .find(|pref| pref.event == event && pref.channel == channel)
+        .map(|pref| pref.enabled)
+        .unwrap_or_else(|| default_enabled(channel))
+}
+
+pub fn preference_matrix(conn: &Connection, user_id: i64) -> Result<Vec<EventPreferences>> {
+    let preferences = db::notification_preferences(conn, user_id)?;
+    let matrix = EVENTS
+        .iter()
+        .map(|(event, label)| EventPreferences {
+            event,
+            label,
+            channels: CHANNELS
+                .iter()
+                .map(|(channel, channel_label)| ChannelToggle {
+                    channel,
+                    label: channel_label,
+                    enabled: is_enabled(&preferences, event, channel),
+                })
+                .collect(),
+        })
+        .collect();
+    Ok(matrix)
+}
+
+/// Saves the full event × channel matrix; `enabled` holds `event:channel` pairs.
+pub fn save_preferences(conn: &Connection, user_id: i64, enabled: &[String]) -> Result<()> {
+    for (event, _) in EVENTS {
+        for (channel, _) in CHANNELS {
+            let key = format!("{event}:{channel}");
+            let on = enabled.iter().any(|value| value == &key);
+            db::set_notification_preference(conn, user_id, event, channel, on)?;
+        }
+    }
+    Ok(())
+}
+
+/// Records a notification for every channel the user enabled for its event.
+///
+/// In-app rows are shown right away; rows for external channels stay unsent
+/// until a transport for that channel picks them up.
+pub fn dispatch(conn: &Connection, user_id: i64, notification: &NewNotification) -> Result<()> {
+    let preferences = db::notification_preferences(conn, user_id)?;
+    let created_at = Local::now().to_rfc3339();
+    for (channel, _) in CHANNELS {
+        if is_enabled(&preferences, &notification.event, channel) {
+            db::insert_notification(conn, user_id, channel, notification, &created_at)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,11 @@
+//! Telling rendered pages from the other responses, for fairings that
+//! rewrite pages on their way out.
+
+use rocket::http::ContentType;
+
+/// Pages are templates, sent as HTML or, when named plain `*.tera`, as text.
+pub fn is_page(content_type: &ContentType) -> bool {
+    [ContentType::HTML, ContentType::Plain]
+        .iter()
+        .any(|candidate| candidate.media_type() == content_type.media_type())
+}
@@ -0,0 +1,292 @@
+//! How hard a new password is to guess.
+//!
+//! In the spirit of zxcvbn: the password is read as the cheapest run of
+//! patterns an attacker would try first, common passwords, the username,
+//! keyboard rows, sequences, repeats and years, with any other character
+//! taken on its own, and the guesses for the parts multiply up. A password
+//! needs at least 10^[`MIN_GUESSES_LOG10`] guesses; a weaker one is refused
+//! with what gave it away.
+
+/// Common passwords, the most common first; the rank is how soon one is tried.
+const COMMON: &[&str] = &[
+    "123456",
+    "password",
+    "123456789",
+    "12345678",
+    "12345",
+    "qwerty",
+    "1234567",
+    "111111",
+    "1234567890",
+    "123123",
+    "abc123",
+    "password1",
+    "iloveyou",
+    "1q2w3e4r",
+    "000000",
+    "qwerty123",
+    "zaq12wsx",
+    "dragon",
+    "sunshine",
+    "princess",
+    "letmein",
+    "654321",
+    "monkey",
+    "1qaz2wsx",
+    "123321",
+    "qwertyuiop",
+    "superman",
+    "asdfghjkl",
+    "666666",
+    "7777777",
+    "football",
+    "baseball",
+    "welcome",
+    "admin",
+    "master",
+    "secret",
+    "shadow",
+    "michael",
+    "login",
+    "passw0rd",
+    "starwars",
+    "hello",
+    "freedom",
+    "whatever",
+    "trustno1",
+    "qwe123",
+    "1q2w3e",
+    "пароль",
+    "йцукен",
+    "йцукенг",
+    "любовь",
+    "наташа",
+    "марина",
+    "максим",
+    "деньги",
+    "money",
+    "finance",
+    "lumen",
+    "привет",
+    "солнышко",
+];
+const KEYBOARD_ROWS: &[&str] = &[
+    "1234567890",
+    "qwertyuiop",
+    "asdfghjkl",
+    "zxcvbnm",
+    "йцукенгшщзхъ",
+    "фывапролджэ",
+    "ячсмитьбю",
+];
+pub const MIN_GUESSES_LOG10: f64 = 8.0;
+/// Shortest run counted as a sequence, repeat or keyboard row.
+const MIN_RUN: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pattern {
+    Common,
+    Username,
+    Keyboard,
+    Sequence,
+    Repeat,
+    Year,
+    /// Characters that match nothing: the password is simply too short.
+    Short,
+}
+
+/// Why a password was refused.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Weakness {
+    /// The part that made it easiest to guess.
+    pub pattern: Pattern,
+}
+
+impl Weakness {
+    pub fn message(&self) -> String {
+        let reason = match self.pattern {
+            Pattern::Common => "это один из самых распространенных паролей или похож на него",
+            Pattern::Username => "в нем есть логин",
+            Pattern::Keyboard => "ряды клавиш вроде qwerty или йцукен подбирают первыми",
+            Pattern::Sequence => "последовательности вроде abc или 123 подбирают первыми",
+            Pattern::Repeat => "повторы вроде aaa почти не усложняют пароль",
+            Pattern::Year => "годы подбирают первыми",
+            Pattern::Short => "он слишком короткий",
+        };
+        format!("Пароль легко подобрать: {reason}. Добавьте пару необычных слов или символов")
+    }
+}
+
+struct Part {
+    pattern: Pattern,
+    guesses_log10: f64,
+}
+
+/// Refuses `password` when it is too easy to guess for the account `username`.
+pub fn check(password: &str, username: &str) -> Result<(), Weakness> {
+    let (guesses_log10, parts) = estimate(password, username);
+    if guesses_log10 >= MIN_GUESSES_LOG10 {
+        return Ok(());
+    }
+    // The pattern that saved the attacker the most over guessing blindly.
+    let pattern = parts
+        .iter()
+        .filter(|(part, length)| part.pattern != Pattern::Short && *length > 0)
+        .max_by(|(a, a_length), (b, b_length)| {
+            let saved = |part: &Part, length: usize| blind_log10(length) - part.guesses_log10;
+            saved(a, *a_length).total_cmp(&saved(b, *b_length))
+        })
+        .map_or(Pattern::Short, |(part, _)| part.pattern);
+    Err(Weakness { pattern })
+}
+
+/// Guesses for a run of `length` characters picked from 26.
+fn blind_log10(length: usize) -> f64 {
+    length as f64 * 26f64.log10()
+}
+
+/// The fewest guesses, as a power of ten, and the parts with their lengths.
+fn estimate(password: &str, username: &str) -> (f64, Vec<(Part, usize)>) {
+    let original = password.chars().collect::<Vec<_>>();
+    let chars = password.to_lowercase().chars().collect::<Vec<_>>();
+    if chars.len() != original.len() {
+        // A letter whose lowercase form has a different length; no patterns.
+        let guesses = original.iter().map(|c| (charset(*c) as f64).log10()).sum();
+        return (guesses, Vec::new());
+    }
+    let username = username.trim().to_lowercase().chars().collect::<Vec<_>>();
+
+    // best[i]: fewest guesses for the first i characters, and the last part.
+    let mut best: Vec<Option<(f64, usize, Pattern, f64)>> = vec![None; chars.len() + 1];
+    best[0] = Some((0.0, 0, Pattern::Short, 0.0));
+    for start in 0..chars.len() {
+        let Some((so_far, ..)) = best[start] else {
+            continue;
+        };
+        for (end, pattern, guesses_log10) in patterns_at(&chars, &original, &username, start) {
+            let total = so_far + guesses_log10;
+            if best[end].is_none_or(|(known, ..)| total < known) {
+                best[end] = Some((total, start, pattern, guesses_log10));
+            }
+        }
+    }
+
+    let mut parts = Vec::new();
+    let mut end = chars.len();
+    while end > 0 {
+        let Some((_, start, pattern, guesses_log10)) = best[end] else {
+            break;
+        };
+        parts.push((
+            Part {
+                pattern,
+                guesses_log10,
+            },
+            end - start,
+        ));
+        end = start;
+    }
+    parts.reverse();
+    (best[chars.len()].map_or(0.0, |(total, ..)| total), parts)
+}
+
+/// Every pattern starting at `start`: where it ends, what it is and its
+/// guesses as a power of ten.
+fn patterns_at(
+    chars: &[char],
+    original: &[char],
+    username: &[char],
+    start: usize,
+) -> Vec<(usize, Pattern, f64)> {
+    let rest = &chars[start..];
+    let mut found = vec![(
+        start + 1,
+        Pattern::Short,
+        (charset(original[start]) as f64).log10(),
+    )];
+    let capitals = |end: usize| {
+        if original[start..end].iter().any(|c| c.is_uppercase()) {
+            2f64.log10()
+        } else {
+            0.0
+        }
+    };
+
+    for (rank, word) in COMMON.iter().enumerate() {
+        let word = word.chars().collect::<Vec<_>>();
+        if rest.starts_with(&word) {
+            let end = start + word.len();
+            found.push((
+                end,
+                Pattern::Common,
+                ((rank + 1) as f64).log10() + capitals(end),
+            ));
+        }
+    }
+    if username.len() >= MIN_RUN && rest.starts_with(username) {
+        found.push((start + username.len(), Pattern::Username, 0.0));
+    }
+
+    let repeat = rest.iter().take_while(|c| **c == rest[0]).count();
+    if repeat >= MIN_RUN {
+        let guesses = (charset(rest[0]) * repeat) as f64;
+        found.push((start + repeat, Pattern::Repeat, guesses.log10()));
+    }
+
+    for step in [1i64, -1] {
+        let length = 1 + rest
+            .windows(2)
+            .take_while(|pair| pair[1] as i64 - pair[0] as i64 == step)
+            .count();
+        if length >= MIN_RUN {
+            let first_guesses = if matches!(rest[0], 'a' | 'а' | '0' | '1') {
+                4
+            } else {
+                26
+            };
+            let guesses = (first_guesses * length * if step < 0 { 2 } else { 1 }) as f64;
+            found.push((start + length, Pattern::Sequence, guesses.log10()));
+        }
+    }
+
+    for row in KEYBOARD_ROWS {
+        let row = row.chars().collect::<Vec<_>>();
+        for row in [row.clone(), row.into_iter().rev().collect()] {
+            let Some(at) = row.iter().position(|c| *c == rest[0]) else {
+                continue;
+            };
+            let length = rest
+                .iter()
+                .zip(&row[at..])
+                .take_while(|(a, b)| a == b)
+                .count();
+            if length >= MIN_RUN {
+                let guesses = (row.len() * 2 * length) as f64;
+                found.push((start + length, Pattern::Keyboard, guesses.log10()));
+            }
+        }
+    }
+
+    if rest.len() >= 4 {
+        let year = rest[..4].iter().collect::<String>();
+        if year
+            .parse::<u32>()
+            .is_ok_and(|year| (1900..=2039).contains(&year))
+        {
+            found.push((start + 4, Pattern::Year, 140f64.log10()));
+        }
+    }
+    found
+}
+
+/// How many characters of the same kind as `c` there are to pick from.
+fn charset(c: char) -> usize {
+    if c.is_ascii_digit() {
+        10
+    } else if c.is_ascii_alphabetic() {
+        26
+    } else {
+        // The Russian alphabet, and about as many symbols on a keyboard.
+        33
+    }
+}
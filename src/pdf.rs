@@ -0,0 +1,329 @@
+//! Printable tables as PDF, for exports such as account statements.
+//!
+//! The fonts every PDF reader ships have no Cyrillic, so a TrueType font is
+//! embedded: `LUMEN_PDF_FONT`, or DejaVu Sans where most Linux systems keep
+//! it. Without a usable font PDF exports are unavailable.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+
+use flate2::write::ZlibEncoder;
+use pdf_writer::types::{CidFontType, FontFlags, SystemInfo};
+use pdf_writer::{Content, Filter, Finish, Name, Pdf, Rect, Ref, Str, TextStr};
+use ttf_parser::{Face, GlyphId};
+
+const DEFAULT_FONT: &str = "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf";
+const FONT: Name<'static> = Name(b"F1");
+
+/// A4 portrait, in points.
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 40.0;
+const TITLE_SIZE: f32 = 14.0;
+const TEXT_SIZE: f32 = 9.0;
+const CELL_SIZE: f32 = 8.0;
+const ROW_HEIGHT: f32 = 13.0;
+
+pub struct Font {
+    data: Vec<u8>,
+}
+
+impl Font {
+    pub fn from_env() -> Option<Font> {
+        match std::env::var("LUMEN_PDF_FONT") {
+            Ok(path) if !path.trim().is_empty() => Font::load(Path::new(path.trim())),
+            _ => Font::load(Path::new(DEFAULT_FONT)),
+        }
+    }
+
+    /// The font at `path`, if it is a TrueType font with Cyrillic letters.
+    pub fn load(path: &Path) -> Option<Font> {
+        let data = std::fs::read(path).ok()?;
+        let face = Face::parse(&data, 0).ok()?;
+        face.glyph_index('Ж')?;
+        Some(Font { data })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Right,
+}
+
+pub struct Column {
+    pub title: String,
+    /// Share of the page width between the margins.
+    pub weight: f32,
+    pub align: Align,
+}
+
+pub struct Table {
+    pub title: String,
+    /// Lines under the title, e.g. the period and the opening balance.
+    pub header: Vec<String>,
+    pub columns: Vec<Column>,
+    pub rows: Vec<Vec<String>>,
+    /// Lines after the last row, e.g. totals.
+    pub footer: Vec<String>,
+}
+
+/// Glyphs of the embedded font; remembers which ones were used, since only
+/// those need widths and a way back to Unicode for copying text.
+struct Glyphs<'a> {
+    face: Face<'a>,
+    used: BTreeMap<u16, char>,
+}
+
+impl Glyphs<'_> {
+    /// Two-byte glyph ids, as the `Identity-H` encoding expects.
+    fn encode(&mut self, text: &str) -> Vec<u8> {
+        let mut out = Vec::with_capacity(text.len() * 2);
+        for ch in text.chars() {
+            let glyph = self.face.glyph_index(ch).unwrap_or(GlyphId(0));
+            self.used.entry(glyph.0).or_insert(ch);
+            out.extend(glyph.0.to_be_bytes());
+        }
+        out
+    }
+
+    /// Advance of `glyph` in thousandths of the font size.
+    fn advance(&self, glyph: GlyphId) -> f32 {
+        let advance = self.face.glyph_hor_advance(glyph).unwrap_or_default();
+        self.scale(advance as f32)
+    }
+
+    fn scale(&self, units: f32) -> f32 {
+        units * 1000.0 / self.face.units_per_em() as f32
+    }
+
+    fn width(&self, text: &str, size: f32) -> f32 {
+        text.chars()
+            .map(|ch| self.advance(self.face.glyph_index(ch).unwrap_or(GlyphId(0))))
+            .sum::<f32>()
+            * size
+            / 1000.0
+    }
+
+    /// `text` cut to `width` with an ellipsis.
+    fn fit(&self, text: &str, size: f32, width: f32) -> String {
+        if self.width(text, size) <= width {
+            return text.to_string();
+        }
+        let mut fitted = text.to_string();
+        while !fitted.is_empty() && self.width(&format!("{fitted}…"), size) > width {
+            fitted.pop();
+        }
+        format!("{}…", fitted.trim_end())
+    }
+
+    fn show(&mut self, content: &mut Content, text: &str, size: f32, x: f32, y: f32) {
+        let encoded = self.encode(text);
+        content.begin_text();
+        content.set_font(FONT, size);
+        content.next_line(x, y);
+        content.show(Str(&encoded));
+        content.end_text();
+    }
+}
+
+/// Lays the table out over as many pages as it takes, repeating the column
+/// titles on each and numbering the pages. `None` when the font can't be read.
+pub fn render(font: &Font, table: &Table) -> Option<Vec<u8>> {
+    let mut glyphs = Glyphs {
+        face: Face::parse(&font.data, 0).ok()?,
+        used: BTreeMap::new(),
+    };
+    let inner = PAGE_WIDTH - 2.0 * MARGIN;
+    let total_weight = table
+        .columns
+        .iter()
+        .map(|column| column.weight)
+        .sum::<f32>();
+    let widths = table
+        .columns
+        .iter()
+        .map(|column| inner * column.weight / total_weight)
+        .collect::<Vec<_>>();
+
+    let mut pages = Vec::new();
+    let mut content = Content::new();
+    let mut y = PAGE_HEIGHT - MARGIN - TITLE_SIZE;
+    glyphs.show(&mut content, &table.title, TITLE_SIZE, MARGIN, y);
+    y -= TITLE_SIZE;
+    for line in &table.header {
+        y -= ROW_HEIGHT;
+        glyphs.show(&mut content, line, TEXT_SIZE, MARGIN, y);
+    }
+    y -= ROW_HEIGHT;
+    let titles = table
+        .columns
+        .iter()
+        .map(|column| column.title.clone())
+        .collect::<Vec<_>>();
+    y = row(&mut glyphs, &mut content, table, &widths, &titles, y, true);
+
+    let bottom = MARGIN + ROW_HEIGHT * 2.0;
+    for cells in &table.rows {
+        if y - ROW_HEIGHT < bottom {
+            pages.push(std::mem::replace(&mut content, Content::new()));
+            y = row(
+                &mut glyphs,
+                &mut content,
+                table,
+                &widths,
+                &titles,
+                PAGE_HEIGHT - MARGIN,
+                true,
+            );
+        }
+        y = row(&mut glyphs, &mut content, table, &widths, cells, y, false);
+    }
+    if y - ROW_HEIGHT * (table.footer.len() as f32 + 1.0) < bottom {
+        pages.push(std::mem::replace(&mut content, Content::new()));
+        y = PAGE_HEIGHT - MARGIN;
+    }
+    y -= ROW_HEIGHT;
+    for line in &table.footer {
+        y -= ROW_HEIGHT;
+        glyphs.show(&mut content, line, TEXT_SIZE, MARGIN, y);
+    }
+    pages.push(content);
+
+    let count = pages.len();
+    for (index, page) in pages.iter_mut().enumerate() {
+        let number = format!("Стр. {} из {count}", index + 1);
+        let x = PAGE_WIDTH - MARGIN - glyphs.width(&number, CELL_SIZE);
+        glyphs.show(page, &number, CELL_SIZE, x, MARGIN);
+    }
+    Some(write(font, &glyphs, &table.title, pages))
+}
+
+/// One table row at `y`, titles on a grey band; returns the next row's `y`.
+fn row(
+    glyphs: &mut Glyphs<'_>,
+    content: &mut Content,
+    table: &Table,
+    widths: &[f32],
+    cells: &[String],
+    y: f32,
+    titles: bool,
+) -> f32 {
+    let y = y - ROW_HEIGHT;
+    if titles {
+        content.set_fill_gray(0.9);
+        content.rect(MARGIN, y - 3.0, PAGE_WIDTH - 2.0 * MARGIN, ROW_HEIGHT);
+        content.fill_nonzero();
+        content.set_fill_gray(0.0);
+    }
+    let mut x = MARGIN;
+    for ((column, width), cell) in table.columns.iter().zip(widths).zip(cells) {
+        let text = glyphs.fit(cell, CELL_SIZE, width - 4.0);
+        let offset = match column.align {
+            Align::Left => 2.0,
+            Align::Right => width - 2.0 - glyphs.width(&text, CELL_SIZE),
+        };
+        glyphs.show(content, &text, CELL_SIZE, x + offset, y);
+        x += width;
+    }
+    y
+}
+
+fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    // Writing to a Vec can't fail.
+    let _ = encoder.write_all(data);
+    encoder.finish().unwrap_or_default()
+}
+
+fn write(font: &Font, glyphs: &Glyphs<'_>, title: &str, pages: Vec<Content>) -> Vec<u8> {
+    let catalog_id = Ref::new(1);
+    let tree_id = Ref::new(2);
+    let info_id = Ref::new(3);
+    let font_id = Ref::new(4);
+    let cid_id = Ref::new(5);
+    let descriptor_id = Ref::new(6);
+    let file_id = Ref::new(7);
+    let cmap_id = Ref::new(8);
+    let page_ids = (0..pages.len())
+        .map(|index| Ref::new(9 + 2 * index as i32))
+        .collect::<Vec<_>>();
+
+    let mut pdf = Pdf::new();
+    pdf.catalog(catalog_id).pages(tree_id);
+    pdf.document_info(info_id).title(TextStr(title));
+    pdf.pages(tree_id)
+        .kids(page_ids.iter().copied())
+        .count(pages.len() as i32);
+    for (page_id, content) in page_ids.iter().zip(pages) {
+        let content_id = Ref::new(page_id.get() + 1);
+        let mut page = pdf.page(*page_id);
+        page.media_box(Rect::new(0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT));
+        page.parent(tree_id);
+        page.contents(content_id);
+        page.resources().fonts().pair(FONT, font_id);
+        page.finish();
+        pdf.stream(content_id, &deflate(&content.finish()))
+            .filter(Filter::FlateDecode);
+    }
+
+    let face = &glyphs.face;
+    let name = face
+        .names()
+        .into_iter()
+        .filter(|name| name.name_id == ttf_parser::name_id::POST_SCRIPT_NAME)
+        .find_map(|name| name.to_string())
+        .unwrap_or_else(|| "Embedded".to_string());
+    let base_font = Name(name.as_bytes());
+    let system_info = SystemInfo {
+        registry: Str(b"Adobe"),
+        ordering: Str(b"Identity"),
+        supplement: 0,
+    };
+
+    pdf.type0_font(font_id)
+        .base_font(base_font)
+        .encoding_predefined(Name(b"Identity-H"))
+        .descendant_font(cid_id)
+        .to_unicode(cmap_id);
+    let mut cid = pdf.cid_font(cid_id);
+    cid.subtype(CidFontType::Type2)
+        .base_font(base_font)
+        .system_info(system_info)
+        .font_descriptor(descriptor_id)
+        .cid_to_gid_map_predefined(Name(b"Identity"));
+    let mut widths = cid.widths();
+    for &glyph in glyphs.used.keys() {
+        widths.consecutive(glyph, [glyphs.advance(GlyphId(glyph))]);
+    }
+    widths.finish();
+    cid.finish();
+
+    let bbox = face.global_bounding_box();
+    pdf.font_descriptor(descriptor_id)
+        .name(base_font)
+        .flags(FontFlags::NON_SYMBOLIC)
+        .bbox(Rect::new(
+            glyphs.scale(bbox.x_min as f32),
+            glyphs.scale(bbox.y_min as f32),
+            glyphs.scale(bbox.x_max as f32),
+            glyphs.scale(bbox.y_max as f32),
+        ))
+        .italic_angle(face.italic_angle())
+        .ascent(glyphs.scale(face.ascender() as f32))
+        .descent(glyphs.scale(face.descender() as f32))
+        .cap_height(glyphs.scale(face.capital_height().unwrap_or(face.ascender()) as f32))
+        .stem_v(80.0)
+        .font_file2(file_id);
+    pdf.stream(file_id, &deflate(&font.data))
+        .filter(Filter::FlateDecode)
+        .pair(Name(b"Length1"), font.data.len() as i32);
+
+    let mut cmap = pdf_writer::types::UnicodeCmap::new(Name(b"Lumen-UTF16"), system_info);
+    for (&glyph, &ch) in &glyphs.used {
+        cmap.pair(glyph, ch);
+    }
+    pdf.cmap(cmap_id, &cmap.finish());
+    pdf.finish()
+}
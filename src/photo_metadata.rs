@@ -0,0 +1,95 @@
+//! Where and with what a receipt photo was taken, removed on upload.
+//!
+//! Phones write the GPS position, the device and the time into a photo's
+//! EXIF block. An uploaded photo is decoded, turned upright (its orientation
+//! is part of that block) and encoded again in its own format, which leaves
+//! the block behind. A file the decoder can't read, such as HEIC without the
+//! `heic` feature, is kept as it came.
+//!
+//! `keep_photo_metadata = true` in Rocket's configuration, e.g.
+//! `ROCKET_KEEP_PHOTO_METADATA=true`, keeps uploads byte for byte.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader, ImageResult};
+use rocket::fairing::{self, Fairing, Info, Kind};
+use rocket::{Build, Rocket};
+
+/// Configuration key of the opt-out.
+pub const KEY: &str = "keep_photo_metadata";
+/// JPEG quality of photos encoded again; high enough that a receipt reads
+/// as before.
+const QUALITY: u8 = 90;
+
+/// Whether uploads lose their metadata, managed by [`Setting`].
+#[derive(Clone, Copy, Debug)]
+pub struct Policy {
+    pub keep: bool,
+}
+
+/// Writes the photo at `path` again without its metadata.
+pub fn strip(path: &Path) -> ImageResult<()> {
+    let reader = ImageReader::open(path)?.with_guessed_format()?;
+    let Some(format) = reader.format() else {
+        return Ok(());
+    };
+    let mut decoder = reader.into_decoder()?;
+    let orientation = decoder.orientation()?;
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    // Hidden and ending in `.part`, so `receipt_files::recover` clears a
+    // leftover.
+    let temp = path.with_file_name(format!(".{file_name}.clean.part"));
+    let written = match format {
+        ImageFormat::Jpeg => fs::File::create(&temp)
+            .map_err(image::ImageError::from)
+            .and_then(|file| {
+                let encoder = JpegEncoder::new_with_quality(std::io::BufWriter::new(file), QUALITY);
+                image.into_rgb8().write_with_encoder(encoder)
+            }),
+        format => image.save_with_format(&temp, format),
+    };
+    if let Err(err) = written {
+        let _ = fs::remove_file(&temp);
+        return Err(err);
+    }
+    fs::rename(&temp, path)?;
+    Ok(())
+}
+
+/// [`strip`] on a blocking thread, for upload handlers, unless `policy`
+/// keeps metadata. A photo that can't be encoded again is kept as it came.
+pub async fn strip_in_background(policy: &Policy, path: PathBuf) {
+    if policy.keep {
+        return;
+    }
+    let _ = rocket::tokio::task::spawn_blocking(move || strip(&path)).await;
+}
+
+/// Reads [`KEY`] from the configuration into a managed [`Policy`].
+pub struct Setting;
+
+#[rocket::async_trait]
+impl Fairing for Setting {
+    fn info(&self) -> Info {
+        Info {
+            name: "Photo metadata",
+            kind: Kind::Ignite,
+        }
+    }
+
+    async fn on_ignite(&self, rocket: Rocket<Build>) -> fairing::Result {
+        match rocket.figment().extract_inner::<bool>(KEY) {
+            Ok(keep) => Ok(rocket.manage(Policy { keep })),
+            Err(err) if err.missing() => Ok(rocket.manage(Policy { keep: false })),
+            Err(err) => {
+                rocket::error!("invalid {KEY} configuration: {err}");
+                Err(rocket)
+            }
+        }
+    }
+}
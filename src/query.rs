@@ -0,0 +1,274 @@
+//! SQL for listings whose filters depend on the request.
+//!
+//! Clauses are `&'static str` and every request value is bound to a `?`
+//! placeholder, so input never becomes SQL text. Building a query needs no
+//! connection, which keeps the composed SQL testable on its own.
+
+use rusqlite::types::Value;
+
+const TRANSACTION_SELECT: &str = "
+    SELECT t.id, t.kind, t.amount_cents, t.occurred_on, t.note, c.name, t.receipt_path, a.name,
+           t.category_id, ta.name,
+           (
+             SELECT GROUP_CONCAT(g.name, ', ')
+             FROM transaction_tags tt
+             JOIN tags g ON g.id = tt.tag_id
+             WHERE tt.transaction_id = t.id
+           ),
+           t.payee, t.starred, t.status, t.refund_of, t.effective_month
+    FROM transactions t
+    LEFT JOIN categories c ON t.category_id = c.id
+    LEFT JOIN accounts a ON t.account_id = a.id
+    LEFT JOIN accounts ta ON t.to_account_id = ta.id";
+
+/// `WHERE` clauses joined with `AND`, each with the value for its one placeholder.
+#[derive(Debug, Default)]
+pub struct Conditions {
+    clauses: Vec<&'static str>,
+    params: Vec<Value>,
+}
+
+impl Conditions {
+    pub fn push(&mut self, clause: &'static str, value: impl Into<Value>) {
+        debug_assert_eq!(clause.matches('?').count(), 1, "{clause}");
+        self.clauses.push(clause);
+        self.params.push(value.into());
+    }
+
+    /// A clause without a value, such as leaving out trashed rows.
+    pub fn require(&mut self, clause: &'static str) {
+        debug_assert!(!clause.contains('?'), "{clause}");
+        self.clauses.push(clause);
+    }
+
+    /// The `WHERE ...` text, empty when nothing filters.
+    pub fn sql(&self) -> String {
+        if self.clauses.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", self.clauses.join(" AND "))
+        }
+    }
+
+    pub fn into_params(self) -> Vec<Value> {
+        self.params
+    }
+}
+
+/// Orders the transaction list can be sorted in; only these reach the SQL.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Sort {
+    #[default]
+    Newest,
+    Oldest,
+    Largest,
+    Smallest,
+    /// By category name, uncategorized last, newest first within one.
+    Category,
+}
+
+impl Sort {
+    const ALL: [Sort; 5] = [
+        Sort::Newest,
+        Sort::Oldest,
+        Sort::Largest,
+        Sort::Smallest,
+        Sort::Category,
+    ];
+
+    /// The sort named `value` in a URL, `None` for anything else.
+    pub fn from_param(value: &str) -> Option<Sort> {
+        Sort::ALL.into_iter().find(|sort| sort.param() == value)
+    }
+
+    pub fn param(self) -> &'static str {
+        match self {
+            Sort::Newest => "date",
+            Sort::Oldest => "date_asc",
+            Sort::Largest => "amount",
+            Sort::Smallest => "amount_asc",
+            Sort::Category => "category",
+        }
+    }
+
+    fn order(self) -> &'static str {
+        match self {
+            Sort::Newest => "t.occurred_on DESC, t.id DESC",
+            Sort::Oldest => "t.occurred_on, t.id",
+            Sort::Largest => "t.amount_cents DESC, t.occurred_on DESC, t.id DESC",
+            Sort::Smallest => "t.amount_cents, t.occurred_on DESC, t.id DESC",
+            Sort::Category => "c.name IS NULL, c.name, t.occurred_on DESC, t.id DESC",
+        }
+    }
+}
+
+/// Filters for the transaction list; `None` fields don't filter.
+#[derive(Debug, Default)]
+pub struct TransactionQuery {
+    /// `YYYY-MM`, the month of the date.
+    pub month: Option<String>,
+    /// Inclusive `YYYY-MM-DD` bounds on the date.
+    pub from: Option<String>,
+    pub to: Option<String>,
+    /// Only dates on this day of their month or later, e.g. 28 for the last
+    /// few days.
+    pub from_day: Option<u32>,
+    /// Tag name as stored, i.e. lowercase.
+    pub tag: Option<String>,
+    /// `income`, `expense` or `transfer`.
+    pub kind: Option<String>,
+    /// Matches the category and its subcategories.
+    pub category_id: Option<i64>,
+    /// Inclusive bounds on `amount_cents`.
+    pub min_cents: Option<i64>,
+    pub max_cents: Option<i64>,
+    /// Words the note or payee must contain, see [`match_expression`].
+    pub term: Option<String>,
+    /// Only starred transactions.
+    pub starred: bool,
+    /// Ignored by [`search`](TransactionQuery::search), which ranks by relevance.
+    pub sort: Sort,
+    /// Row cap; `None` returns every match.
+    pub limit: Option<i64>,
+    /// Matching rows to skip before the first one returned.
+    pub offset: Option<i64>,
+}
+
+impl TransactionQuery {
+    pub fn month(month: &str) -> Self {
+        TransactionQuery {
+            month: Some(month.to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// Select for `db::list_transactions` in `sort` order and its parameters.
+    pub fn build(&self) -> (String, Vec<Value>) {
+        self.select("", self.conditions(), self.sort.order())
+    }
+
+    /// `COUNT(*)` of every row [`build`] matches, ignoring limit and offset,
+    /// for `db::count_transactions`.
+    ///
+    /// [`build`]: TransactionQuery::build
+    pub fn count(&self) -> (String, Vec<Value>) {
+        let conditions = self.conditions();
+        let sql = format!(
+            "SELECT COUNT(*) FROM transactions t\n    {}",
+            conditions.sql()
+        );
+        (sql, conditions.into_params())
+    }
+
+    fn conditions(&self) -> Conditions {
+        let mut conditions = Conditions::default();
+        if let Some(expression) = self.expression() {
+            conditions.push(
+                "t.id IN (SELECT rowid FROM transactions_fts WHERE transactions_fts MATCH ?)",
+                expression,
+            );
+        }
+        self.filter(&mut conditions);
+        conditions
+    }
+
+    /// Select for `db::search_transactions`: the same rows as [`build`], but
+    /// best matches for `term` first; `None` when the term has no words.
+    ///
+    /// [`build`]: TransactionQuery::build
+    pub fn search(&self) -> Option<(String, Vec<Value>)> {
+        let mut conditions = Conditions::default();
+        conditions.push("transactions_fts MATCH ?", self.expression()?);
+        self.filter(&mut conditions);
+        Some(self.select(
+            "\n    JOIN transactions_fts ON transactions_fts.rowid = t.id",
+            conditions,
+            "transactions_fts.rank, t.occurred_on DESC, t.id DESC",
+        ))
+    }
+
+    fn expression(&self) -> Option<String> {
+        self.term.as_deref().and_then(match_expression)
+    }
+
+    /// Everything but the term, which `build` and `search` match differently;
+    /// rows in the trash never match.
+    fn filter(&self, conditions: &mut Conditions) {
+        if let Some(month) = &self.month {
+            conditions.push("t.occurred_on LIKE ?", format!("{month}-%"));
+        }
+        if let Some(from) = &self.from {
+            conditions.push("t.occurred_on >= ?", from.clone());
+        }
+        if let Some(to) = &self.to {
+            conditions.push("t.occurred_on <= ?", to.clone());
+        }
+        if let Some(from_day) = self.from_day {
+            conditions.push("CAST(substr(t.occurred_on, 9, 2) AS INTEGER) >= ?", from_day);
+        }
+        if let Some(kind) = &self.kind {
+            conditions.push("t.kind = ?", kind.clone());
+        }
+        if let Some(category_id) = self.category_id {
+            conditions.push(
+                "? IN (t.category_id, (SELECT parent_id FROM categories WHERE id = t.category_id))",
+                category_id,
+            );
+        }
+        if let Some(min_cents) = self.min_cents {
+            conditions.push("t.amount_cents >= ?", min_cents);
+        }
+        if let Some(max_cents) = self.max_cents {
+            conditions.push("t.amount_cents <= ?", max_cents);
+        }
+        if self.starred {
+            conditions.require("t.starred = 1");
+        }
+        if let Some(tag) = &self.tag {
+            conditions.push(
+                "EXISTS (
+                    SELECT 1
+                    FROM transaction_tags tt
+                    JOIN tags g ON g.id = tt.tag_id
+                    WHERE tt.transaction_id = t.id AND g.name = ?
+                )",
+                tag.clone(),
+            );
+        }
+        conditions.require("t.deleted_at IS NULL");
+    }
+
+    fn select(&self, join: &str, conditions: Conditions, order: &str) -> (String, Vec<Value>) {
+        let mut sql = format!(
+            "{TRANSACTION_SELECT}{join}\n    {}\n    ORDER BY {order}",
+            conditions.sql()
+        );
+        let mut params = conditions.into_params();
+        if self.limit.is_some() || self.offset.is_some() {
+            // SQLite only takes an offset after a limit; -1 means none.
+            sql.push_str("\n    LIMIT ?");
+            params.push(Value::Integer(self.limit.unwrap_or(-1)));
+        }
+        if let Some(offset) = self.offset {
+            sql.push_str(" OFFSET ?");
+            params.push(Value::Integer(offset));
+        }
+        (sql, params)
+    }
+}
+
+/// An FTS5 query for the words of `term`, each matched as a word beginning;
+/// `None` when there are no words. Quoting every word keeps FTS5 syntax such
+/// as `OR` or `-` typed in the search box from being interpreted.
+pub fn match_expression(term: &str) -> Option<String> {
+    let words = term
+        .split(|ch: char| !ch.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("\"{word}\"*"))
+        .collect::<Vec<_>>();
+    if words.is_empty() {
+        None
+    } else {
+        Some(words.join(" "))
+    }
+}
@@ -0,0 +1,218 @@
+//! Developer mode: the SQL statements each request ran, with their timings.
+//!
+//! Off unless Rocket's configuration sets `query_inspector = true`, e.g. in
+//! `Rocket.toml` or as `ROCKET_QUERY_INSPECTOR=true`. Every pooled connection
+//! reports finished statements through SQLite's trace hook, see [`install`].
+//! The hook takes a plain function, so statements are collected per thread
+//! and a response claims those its thread finished since the request came
+//! in. A handler runs on one thread, but with several requests at once a page
+//! may list a statement of another one: it is a tool for a developer's own
+//! browser, not for production.
+//!
+//! HTML pages get a collapsible panel at the bottom, and the last [`KEPT`]
+//! requests are listed at `/admin/queries`.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io::Cursor;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+use chrono::Local;
+use rocket::fairing::{self, Fairing, Info, Kind};
+use rocket::{Build, Data, Request, Response, Rocket};
+use rocket_dyn_templates::Template;
+use rusqlite::Connection;
+use rusqlite::trace::{TraceEvent, TraceEventCodes};
+use serde::Serialize;
+
+use crate::pages;
+
+/// Configuration key of the switch.
+pub const KEY: &str = "query_inspector";
+/// Requests listed at `/admin/queries`.
+pub const KEPT: usize = 50;
+/// Statements a thread holds on to when no response claims them, such as
+/// those of background jobs.
+const PER_THREAD: usize = 1000;
+
+/// Whether any app in the process has the inspector on; the trace hook is
+/// installed on every connection and does nothing otherwise.
+static RECORDING: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    static FINISHED: RefCell<VecDeque<Statement>> = const { RefCell::new(VecDeque::new()) };
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Statement {
+    pub sql: String,
+    pub millis: f64,
+    #[serde(skip)]
+    finished: Instant,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RequestLog {
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    pub at: String,
+    pub total_millis: f64,
+    pub statements: Vec<Statement>,
+}
+
+impl RequestLog {
+    fn new(request: &Request<'_>, response: &Response<'_>, statements: Vec<Statement>) -> Self {
+        RequestLog {
+            method: request.method().to_string(),
+            path: request.uri().to_string(),
+            status: response.status().code,
+            at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            total_millis: statements.iter().map(|statement| statement.millis).sum(),
+            statements,
+        }
+    }
+}
+
+/// The switch and the last requests, managed by [`Inspector`].
+#[derive(Debug, Default)]
+pub struct Queries {
+    pub enabled: bool,
+    recent: Mutex<VecDeque<RequestLog>>,
+}
+
+impl Queries {
+    /// The kept requests, newest first.
+    pub fn recent(&self) -> Vec<RequestLog> {
+        let recent = self
+            .recent
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        recent.iter().rev().cloned().collect()
+    }
+
+    fn keep(&self, log: RequestLog) {
+        let mut recent = self
+            .recent
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if recent.len() == KEPT {
+            recent.pop_front();
+        }
+        recent.push_back(log);
+    }
+}
+
+/// Reports the statements `conn` finishes to the inspector.
+pub fn install(conn: &Connection) {
+    conn.trace_v2(TraceEventCodes::SQLITE_TRACE_PROFILE, Some(record));
+}
+
+fn record(event: TraceEvent<'_>) {
+    if !RECORDING.load(Ordering::Relaxed) {
+        return;
+    }
+    let TraceEvent::Profile(statement, duration) = event else {
+        return;
+    };
+    let statement = Statement {
+        sql: statement.sql().trim().to_string(),
+        millis: duration.as_secs_f64() * 1000.0,
+        finished: Instant::now(),
+    };
+    FINISHED.with_borrow_mut(|finished| {
+        if finished.len() == PER_THREAD {
+            finished.pop_front();
+        }
+        finished.push_back(statement);
+    });
+}
+
+/// The statements this thread finished since `start`, leaving none behind.
+fn take_since(start: Instant) -> Vec<Statement> {
+    FINISHED.with_borrow_mut(|finished| {
+        finished
+            .drain(..)
+            .filter(|statement| statement.finished >= start)
+            .collect()
+    })
+}
+
+/// `page` with `panel` just before its closing body tag; `None` for a page
+/// without one, such as a plain text template.
+pub fn with_panel(page: &str, panel: &str) -> Option<String> {
+    let end = page.rfind("</body>")?;
+    Some(format!("{}{panel}{}", &page[..end], &page[end..]))
+}
+
+/// When the request came in.
+struct Started(Option<Instant>);
+
+pub struct Inspector;
+
+#[rocket::async_trait]
+impl Fairing for Inspector {
+    fn info(&self) -> Info {
+        Info {
+            name: "Query inspector",
+            kind: Kind::Ignite | Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_ignite(&self, rocket: Rocket<Build>) -> fairing::Result {
+        match rocket.figment().extract_inner::<bool>(KEY) {
+            Ok(enabled) => {
+                if enabled {
+                    RECORDING.store(true, Ordering::Relaxed);
+                }
+                Ok(rocket.manage(Queries {
+                    enabled,
+                    ..Default::default()
+                }))
+            }
+            Err(err) if err.missing() => Ok(rocket.manage(Queries::default())),
+            Err(err) => {
+                rocket::error!("invalid {KEY} configuration: {err}");
+                Err(rocket)
+            }
+        }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _: &mut Data<'_>) {
+        let enabled = request
+            .rocket()
+            .state::<Queries>()
+            .is_some_and(|queries| queries.enabled);
+        request.local_cache(|| Started(enabled.then(Instant::now)));
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let Started(Some(start)) = request.local_cache(|| Started(None)) else {
+            return;
+        };
+        let Some(queries) = request.rocket().state::<Queries>() else {
+            return;
+        };
+        if request.uri().path().starts_with("/static/") {
+            return;
+        }
+        let log = RequestLog::new(request, response, take_since(*start));
+        let context = serde_json::json!({ "log": log });
+        queries.keep(log);
+        if response.headers().contains("Content-Encoding")
+            || !response.content_type().is_some_and(|ct| pages::is_page(&ct))
+        {
+            return;
+        }
+        let Some(panel) = Template::show(request.rocket(), "query_panel", &context) else {
+            return;
+        };
+        let Ok(page) = response.body_mut().to_string().await else {
+            return;
+        };
+        let page = with_panel(&page, &panel).unwrap_or(page);
+        response.set_sized_body(page.len(), Cursor::new(page));
+    }
+}
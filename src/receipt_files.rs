@@ -0,0 +1,196 @@
+//! Saving a receipt file together with the transaction it belongs to.
+//!
+//! An upload is first written under a hidden temporary name (see [`Staged`]),
+//! which `/receipts` never serves. Then the transaction naming the final file
+//! is committed, and only after that is the file renamed into place; a rename
+//! within one directory is atomic. A failed insert removes the temporary file,
+//! so no file is left without its transaction. A crash after the commit
+//! leaves the temporary file behind, and [`recover`] at the next start moves
+//! it into place, or removes it when no transaction names it.
+//!
+//! Every upload is hashed as it came. One with the same bytes as a receipt
+//! that is still kept is not saved again: [`Staged::existing`] hands out the
+//! earlier file, and deleting a receipt leaves a file that something else
+//! still has.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use rocket::fs::TempFile;
+use rusqlite::{Connection, Result, Transaction};
+use sha2::{Digest, Sha256};
+
+use crate::db;
+
+const TEMP_SUFFIX: &str = ".part";
+
+/// A receipt written under its temporary name, waiting for its transaction.
+#[derive(Debug)]
+pub struct Staged {
+    dir: PathBuf,
+    name: String,
+    sha256: Option<String>,
+    reused: bool,
+}
+
+impl Staged {
+    pub fn new(dir: &Path, name: String) -> Self {
+        Staged {
+            dir: dir.to_path_buf(),
+            name,
+            sha256: None,
+            reused: false,
+        }
+    }
+
+    /// The receipt `name` already in `dir`, given to an upload with the same
+    /// bytes; nothing is written or removed for it.
+    pub fn existing(dir: &Path, name: String) -> Self {
+        Staged {
+            reused: true,
+            ..Staged::new(dir, name)
+        }
+    }
+
+    /// The same upload under another name, such as after a conversion.
+    pub fn renamed(&self, name: String) -> Self {
+        Staged {
+            sha256: self.sha256.clone(),
+            ..Staged::new(&self.dir, name)
+        }
+    }
+
+    /// The hash of the bytes as they were uploaded.
+    pub fn sha256(&self) -> Option<&str> {
+        self.sha256.as_deref()
+    }
+
+    /// Whether this is an earlier receipt, see [`Staged::existing`].
+    pub fn reused(&self) -> bool {
+        self.reused
+    }
+
+    /// The file name the transaction gets.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn path(&self) -> PathBuf {
+        self.dir.join(&self.name)
+    }
+
+    pub fn temp_path(&self) -> PathBuf {
+        self.dir.join(format!(".{}{TEMP_SUFFIX}", self.name))
+    }
+
+    fn commit(&self) -> io::Result<()> {
+        if self.reused {
+            return Ok(());
+        }
+        fs::rename(self.temp_path(), self.path())
+    }
+
+    pub fn discard(&self) {
+        if !self.reused {
+            let _ = fs::remove_file(self.temp_path());
+        }
+    }
+}
+
+/// The SHA-256 of the file at `path`, in hex.
+pub async fn sha256(path: &Path) -> io::Result<String> {
+    let bytes = rocket::tokio::fs::read(path).await?;
+    Ok(Sha256::digest(&bytes)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect())
+}
+
+/// Writes `file` to `dir` under the temporary name for `name`, and hashes it.
+pub async fn stage(file: &mut TempFile<'_>, dir: &Path, name: String) -> io::Result<Staged> {
+    fs::create_dir_all(dir)?;
+    let mut staged = Staged::new(dir, name);
+    let hashed = match file.persist_to(staged.temp_path()).await {
+        Ok(()) => sha256(&staged.temp_path()).await,
+        Err(err) => Err(err),
+    };
+    match hashed {
+        Ok(sha256) => staged.sha256 = Some(sha256),
+        Err(err) => {
+            staged.discard();
+            return Err(err);
+        }
+    }
+    Ok(staged)
+}
+
+/// Runs `insert` in a database transaction, giving it the receipt's file name
+/// if there is one, and puts the receipt in place once that is committed.
+/// Returns what `insert` did and whether the receipt stayed: it is dropped
+/// from the transaction when it couldn't be put in place.
+pub fn save<T>(
+    conn: &mut Connection,
+    staged: Option<Staged>,
+    insert: impl FnOnce(&Transaction, Option<&str>) -> Result<T>,
+) -> Result<(T, bool)> {
+    let inserted = conn.transaction().and_then(|tx| {
+        let value = insert(&tx, staged.as_ref().map(Staged::name))?;
+        let hashed = staged
+            .as_ref()
+            .filter(|staged| !staged.reused)
+            .and_then(|staged| Some((staged.name(), staged.sha256()?)));
+        if let Some((name, sha256)) = hashed {
+            db::record_receipt_hash(&tx, name, sha256)?;
+        }
+        tx.commit()?;
+        Ok(value)
+    });
+    let value = match inserted {
+        Ok(value) => value,
+        Err(err) => {
+            if let Some(staged) = &staged {
+                staged.discard();
+            }
+            return Err(err);
+        }
+    };
+    let Some(staged) = staged else {
+        return Ok((value, true));
+    };
+    if staged.commit().is_ok() {
+        return Ok((value, true));
+    }
+    // Better no receipt than a link to a file that isn't there.
+    staged.discard();
+    db::detach_receipt(conn, staged.name())?;
+    Ok((value, false))
+}
+
+/// Finishes what a crash interrupted in `dir`: a temporary file whose
+/// transaction was committed is moved into place, any other is removed.
+/// Returns how many were moved. Only for startup, while no upload is running.
+pub fn recover(conn: &Connection, dir: &Path) -> Result<usize> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Ok(0);
+    };
+    let mut moved = 0;
+    for entry in entries.flatten() {
+        let file_name = entry.file_name();
+        let Some(name) = file_name
+            .to_str()
+            .and_then(|name| name.strip_prefix('.'))
+            .and_then(|name| name.strip_suffix(TEMP_SUFFIX))
+            .filter(|name| !name.is_empty())
+        else {
+            continue;
+        };
+        let staged = Staged::new(dir, name.to_string());
+        if db::receipt_in_use(conn, name)? && !staged.path().exists() && staged.commit().is_ok() {
+            moved += 1;
+        } else {
+            staged.discard();
+        }
+    }
+    Ok(moved)
+}
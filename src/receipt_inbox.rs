@@ -0,0 +1,57 @@
+//! Receipt photos uploaded in bulk and matched to expenses afterwards.
+//!
+//! Uploads wait in an inbox. A Russian fiscal receipt carries a QR code with
+//! its date and total (`t=20260310T1530&s=1234.50&fn=...`); the upload page
+//! reads it in the browser where `BarcodeDetector` is available and sends the
+//! text along, otherwise the date and total can be typed in. Expenses of the
+//! same amount without a receipt from nearby days are proposed, and attaching
+//! one moves the photo out of the inbox.
+
+use chrono::NaiveDate;
+use rusqlite::{Connection, Result};
+
+use crate::db;
+use crate::models::{ReceiptCandidate, ReceiptUpload};
+use crate::money::parse_amount_to_cents;
+
+/// How many days a receipt and its expense may be apart; card payments are
+/// often booked a day or two after the purchase.
+pub const MATCH_DAYS: i64 = 3;
+
+pub const MAX_PROPOSALS: i64 = 5;
+
+/// What the QR code of a fiscal receipt says.
+#[derive(Debug, PartialEq, Eq)]
+pub struct FiscalQr {
+    pub occurred_on: NaiveDate,
+    pub amount_cents: i64,
+}
+
+impl FiscalQr {
+    /// `None` unless `text` has both the date (`t`) and the total (`s`).
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut occurred_on = None;
+        let mut amount_cents = None;
+        for pair in text.trim().split('&') {
+            match pair.split_once('=') {
+                Some(("t", value)) => {
+                    occurred_on = NaiveDate::parse_from_str(value.get(..8)?, "%Y%m%d").ok();
+                }
+                Some(("s", value)) => amount_cents = parse_amount_to_cents(value),
+                _ => {}
+            }
+        }
+        Some(FiscalQr {
+            occurred_on: occurred_on?,
+            amount_cents: amount_cents?,
+        })
+    }
+}
+
+/// Expenses `upload` may belong to; none until its date and total are known.
+pub fn proposals(conn: &Connection, upload: &ReceiptUpload) -> Result<Vec<ReceiptCandidate>> {
+    let (Some(occurred_on), Some(amount_cents)) = (&upload.occurred_on, upload.amount_cents) else {
+        return Ok(Vec::new());
+    };
+    db::receipt_candidates(conn, occurred_on, amount_cents, MATCH_DAYS, MAX_PROPOSALS)
+}
@@ -0,0 +1,46 @@
+//! The lines of a receipt and how the price of an item moves.
+//!
+//! An expense can list what was bought, typed in on its items page or taken
+//! from the receipt check service when the expense is made from a fiscal QR
+//! code (`fiscal_receipts`). Prices are per unit, so half a kilo of cheese
+//! and a whole one compare, and items are found by part of their name
+//! regardless of case and spacing: «молоко» finds «Молоко 3,2% 1 л».
+
+/// How many purchases the price history of an item shows.
+pub const HISTORY_LIMIT: i64 = 200;
+
+/// Reads a quantity such as `2` or `0,532`; empty means one.
+pub fn parse_quantity(value: &str) -> Option<f64> {
+    match value.trim() {
+        "" => Some(1.0),
+        value => value
+            .replace(',', ".")
+            .parse::<f64>()
+            .ok()
+            .filter(|quantity| quantity.is_finite() && *quantity > 0.0),
+    }
+}
+
+/// A quantity the way receipts print it: `2`, `0.532`.
+pub fn format_quantity(quantity: f64) -> String {
+    let text = format!("{quantity:.3}");
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// What `quantity` units at `price_cents` each cost, to the kopeck.
+pub fn sum_cents(quantity: f64, price_cents: i64) -> i64 {
+    (quantity * price_cents as f64).round() as i64
+}
+
+/// How `price_cents` differs from the `previous_cents` paid before, e.g.
+/// `+12.5%`; `None` when nothing was paid before.
+pub fn change(previous_cents: i64, price_cents: i64) -> Option<String> {
+    if previous_cents <= 0 {
+        return None;
+    }
+    if price_cents == previous_cents {
+        return Some("0%".to_string());
+    }
+    let percent = (price_cents - previous_cents) as f64 * 100.0 / previous_cents as f64;
+    Some(format!("{percent:+.1}%"))
+}
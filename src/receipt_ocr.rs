@@ -0,0 +1,213 @@
+//! The date and total read off a receipt photo whose QR code couldn't be
+//! read, so the inbox can propose expenses and a pre-filled new one.
+//!
+//! Optional: `receipt_ocr` in Rocket's configuration names either an HTTP
+//! service, which gets the photo as the request body and answers with its
+//! text, plain or as JSON with a `text` field,
+//!
+//! ```toml
+//! [default.receipt_ocr]
+//! kind = "service"
+//! url = "http://127.0.0.1:8884/ocr"
+//! token = "…"
+//! ```
+//!
+//! or a local tesseract (`kind = "tesseract"`, with optional `command` and
+//! `languages`, by default `tesseract` and `rus+eng`). The token, if any, is
+//! sent as a bearer token.
+//!
+//! The total is the last amount on the first line with «ИТОГ», «К ОПЛАТЕ» or
+//! «ВСЕГО», and the date the first one in the text. Both are shown in the
+//! inbox to be checked; nothing is saved as a transaction until the form is
+//! sent.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+use chrono::NaiveDate;
+use rocket::fairing::{self, Fairing, Info, Kind};
+use rocket::http::ContentType;
+use rocket::{Build, Rocket};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::money::parse_amount_to_cents;
+
+/// Configuration key of the OCR step.
+pub const KEY: &str = "receipt_ocr";
+const SERVICE_TIMEOUT: Duration = Duration::from_secs(30);
+/// Words of the line with the total, in capitals.
+const TOTAL_WORDS: [&str; 3] = ["ИТОГ", "К ОПЛАТЕ", "ВСЕГО"];
+/// Date formats of receipts by the length of the date.
+const DATE_FORMATS: [(usize, &str); 4] = [
+    (8, "%d.%m.%y"),
+    (10, "%d.%m.%Y"),
+    (8, "%d/%m/%y"),
+    (10, "%d/%m/%Y"),
+];
+
+/// What was read off a photo.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Reading {
+    pub occurred_on: NaiveDate,
+    pub amount_cents: i64,
+}
+
+/// Where the text of a photo comes from, see the module documentation.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Ocr {
+    Service(Service),
+    Tesseract(Tesseract),
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Service {
+    pub url: String,
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Tesseract {
+    #[serde(default = "default_command")]
+    pub command: String,
+    #[serde(default = "default_languages")]
+    pub languages: String,
+}
+
+fn default_command() -> String {
+    "tesseract".to_string()
+}
+
+fn default_languages() -> String {
+    "rus+eng".to_string()
+}
+
+impl Ocr {
+    /// The text on the photo at `path`; `None` when it couldn't be read.
+    pub fn text(&self, path: &Path) -> Option<String> {
+        match self {
+            Ocr::Service(service) => {
+                let body = fs::read(path).ok()?;
+                let content_type = path
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .and_then(ContentType::from_extension)
+                    .unwrap_or(ContentType::Binary);
+                let mut request = ureq::post(&service.url)
+                    .timeout(SERVICE_TIMEOUT)
+                    .set("Content-Type", &content_type.to_string());
+                if let Some(token) = &service.token {
+                    request = request.set("Authorization", &format!("Bearer {token}"));
+                }
+                let response = request.send_bytes(&body).ok()?;
+                if response.content_type() == "application/json" {
+                    let payload: Value = response.into_json().ok()?;
+                    payload["text"].as_str().map(str::to_string)
+                } else {
+                    response.into_string().ok()
+                }
+            }
+            Ocr::Tesseract(tesseract) => {
+                let output = Command::new(&tesseract.command)
+                    .arg(path)
+                    .arg("stdout")
+                    .args(["-l", &tesseract.languages])
+                    .output()
+                    .ok()?;
+                output
+                    .status
+                    .success()
+                    .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+            }
+        }
+    }
+}
+
+/// [`Ocr::text`] and [`parse`] on a blocking thread, for upload handlers.
+pub async fn read_in_background(ocr: &Ocr, path: PathBuf) -> Option<Reading> {
+    let ocr = ocr.clone();
+    rocket::tokio::task::spawn_blocking(move || parse(&ocr.text(&path)?))
+        .await
+        .ok()
+        .flatten()
+}
+
+/// The date and total in the text of a receipt, when both are there.
+pub fn parse(text: &str) -> Option<Reading> {
+    let amount_cents = text.lines().find_map(|line| {
+        let line = line.to_uppercase();
+        let start = TOTAL_WORDS
+            .iter()
+            .filter_map(|word| line.find(word).map(|at| at + word.len()))
+            .min()?;
+        last_amount(&line[start..])
+    })?;
+    let occurred_on = text
+        .split(|c: char| !(c.is_ascii_digit() || c == '.' || c == '/'))
+        .find_map(|word| {
+            let word = word.trim_end_matches('.');
+            DATE_FORMATS
+                .iter()
+                .filter(|(length, _)| *length == word.len())
+                .find_map(|(_, format)| NaiveDate::parse_from_str(word, format).ok())
+        })?;
+    Some(Reading {
+        occurred_on,
+        amount_cents,
+    })
+}
+
+/// The last amount with kopecks in `text`, such as `=1 234,50`; OCR keeps
+/// the spaces between groups of digits.
+fn last_amount(text: &str) -> Option<i64> {
+    let digits = text
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<Vec<_>>();
+    (1..digits.len().saturating_sub(2))
+        .rev()
+        .filter(|&at| matches!(digits[at], '.' | ','))
+        .filter(|&at| digits[at + 1..=at + 2].iter().all(char::is_ascii_digit))
+        .filter(|&at| digits.get(at + 3).is_none_or(|c| !c.is_ascii_digit()))
+        .find_map(|at| {
+            let whole = digits[..at]
+                .iter()
+                .rev()
+                .take_while(|c| c.is_ascii_digit())
+                .collect::<Vec<_>>();
+            if whole.is_empty() {
+                return None;
+            }
+            let whole = whole.into_iter().rev().collect::<String>();
+            let kopecks = digits[at + 1..=at + 2].iter().collect::<String>();
+            parse_amount_to_cents(&format!("{whole}.{kopecks}"))
+        })
+}
+
+/// Reads [`KEY`] from the configuration into a managed `Option<Ocr>`.
+pub struct Setting;
+
+#[rocket::async_trait]
+impl Fairing for Setting {
+    fn info(&self) -> Info {
+        Info {
+            name: "Receipt OCR",
+            kind: Kind::Ignite,
+        }
+    }
+
+    async fn on_ignite(&self, rocket: Rocket<Build>) -> fairing::Result {
+        match rocket.figment().extract_inner::<Ocr>(KEY) {
+            Ok(ocr) => Ok(rocket.manage(Some(ocr))),
+            Err(err) if err.missing() => Ok(rocket.manage(None::<Ocr>)),
+            Err(err) => {
+                rocket::error!("invalid {KEY} configuration: {err}");
+                Err(rocket)
+            }
+        }
+    }
+}
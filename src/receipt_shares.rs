@@ -0,0 +1,81 @@
+//! Links that open one receipt without an account, for sending a utility
+//! bill to the management company without sharing a login.
+//!
+//! A link carries a random token. As with sessions, only its hash is stored,
+//! so a copy of the database gives no links away. The link stops working at
+//! its expiry, when revoked, or once the receipt is gone. Every opening is
+//! logged with the address and browser, for the owner's shares page.
+
+use std::net::IpAddr;
+
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{Connection, Result};
+use uuid::Uuid;
+
+use crate::api_sessions::timestamp;
+use crate::db;
+
+pub const DEFAULT_DAYS: i64 = 7;
+/// The lifetimes a link can be given, in days.
+pub const DAYS: [i64; 3] = [1, DEFAULT_DAYS, 30];
+
+/// A new link; the token is only known now.
+#[derive(Debug)]
+pub struct Created {
+    pub token: String,
+    pub expires_at: String,
+}
+
+/// Creates a link to the receipt `file_name` that works for `days` days.
+pub fn create(
+    conn: &Connection,
+    user_id: i64,
+    file_name: &str,
+    days: i64,
+    now: DateTime<Utc>,
+) -> Result<Created> {
+    // Two UUIDs: a link travels in letters and chats, unlike a cookie.
+    let token = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+    let expires_at = timestamp(now + Duration::days(days));
+    db::insert_receipt_share(
+        conn,
+        user_id,
+        &token,
+        file_name,
+        &timestamp(now),
+        &expires_at,
+    )?;
+    Ok(Created { token, expires_at })
+}
+
+/// The receipt the link with `token` opens, logging the opening; `None` once
+/// the link has expired or been revoked, or its receipt is gone.
+pub fn open(
+    conn: &Connection,
+    token: &str,
+    user_agent: &str,
+    client_ip: Option<IpAddr>,
+    now: DateTime<Utc>,
+) -> Result<Option<String>> {
+    let Some((id, file_name)) = db::live_receipt_share(conn, token, &timestamp(now))? else {
+        return Ok(None);
+    };
+    if !db::receipt_known(conn, &file_name)? {
+        return Ok(None);
+    }
+    let ip = client_ip.map(|ip| ip.to_string());
+    db::insert_receipt_share_view(conn, id, &timestamp(now), ip.as_deref(), user_agent)?;
+    Ok(Some(file_name))
+}
+
+/// Where the link with `token` points, on the server's own address.
+pub fn path(token: &str) -> String {
+    format!("/shared/receipts/{token}")
+}
+
+/// The link with `token` in full when `LUMEN_PUBLIC_URL` is set, else its
+/// path.
+pub fn url(token: &str) -> String {
+    let base = std::env::var("LUMEN_PUBLIC_URL").unwrap_or_default();
+    format!("{}{}", base.trim().trim_end_matches('/'), path(token))
+}
@@ -0,0 +1,419 @@
+//! Where finished receipts are kept: the receipts directory, or a bucket of
+//! an S3-compatible service such as MinIO, for hosts with little disk.
+//!
+//! Uploads are still staged, converted and given thumbnails in the receipts
+//! directory; [`Storage::put`] then takes each finished file from there. The
+//! bucket removes its local copy once the file is sent, and looks in the
+//! directory before asking the service, so receipts from before the bucket
+//! was configured, and any that couldn't be sent, are still found.
+//!
+//! `receipt_storage` in Rocket's configuration selects the bucket, e.g. in
+//! `Rocket.toml`:
+//!
+//! ```toml
+//! [default.receipt_storage]
+//! kind = "s3"
+//! endpoint = "http://127.0.0.1:9000"
+//! bucket = "receipts"
+//! access_key = "lumen"
+//! secret_key = "…"
+//! ```
+//!
+//! `region` defaults to `us-east-1` and `prefix`, put in front of every key,
+//! to none. Requests are signed with AWS Signature Version 4 and name the
+//! bucket in the path, which MinIO and the other services accept.
+
+use std::fs;
+use std::io::{self, Read};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, KeyInit, Mac};
+use rocket::fairing::{self, Fairing, Info, Kind};
+use rocket::http::ContentType;
+use rocket::{Build, Rocket};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+/// Configuration key of the storage.
+pub const KEY: &str = "receipt_storage";
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Sending a receipt; reading one is a stream and has no overall limit.
+const PUT_TIMEOUT: Duration = Duration::from_secs(60);
+/// Waiting for the next bytes of an answer, so a stalled one doesn't hold a
+/// thread for good.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A stored file, open for reading.
+pub struct Object {
+    pub len: u64,
+    pub modified: DateTime<Utc>,
+    pub body: Box<dyn Read + Send>,
+}
+
+/// The receipt files: receipts and their thumbnails. Calls block, so
+/// handlers make them on a blocking thread.
+pub trait Storage: Send + Sync {
+    /// Takes the finished file `name` from the receipts directory; a file
+    /// that isn't there, such as a thumbnail that couldn't be made, is left
+    /// at that.
+    fn put(&self, name: &str) -> io::Result<()>;
+    /// The file `name`, or `None` when there is none.
+    fn open(&self, name: &str) -> io::Result<Option<Object>>;
+    fn exists(&self, name: &str) -> io::Result<bool>;
+    /// Removes `name`; one that is already gone is no error.
+    fn remove(&self, name: &str) -> io::Result<()>;
+}
+
+/// The configured [`Storage`], managed by [`Setting`].
+#[derive(Clone)]
+pub struct Receipts(Arc<dyn Storage>);
+
+impl Receipts {
+    pub fn new(storage: impl Storage + 'static) -> Self {
+        Receipts(Arc::new(storage))
+    }
+}
+
+impl Deref for Receipts {
+    type Target = dyn Storage;
+
+    fn deref(&self) -> &Self::Target {
+        &*self.0
+    }
+}
+
+/// Receipts kept in the receipts directory, where uploads finish.
+pub struct Local {
+    dir: PathBuf,
+}
+
+impl Local {
+    pub fn new(dir: &Path) -> Self {
+        Local {
+            dir: dir.to_path_buf(),
+        }
+    }
+}
+
+impl Storage for Local {
+    fn put(&self, _name: &str) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn open(&self, name: &str) -> io::Result<Option<Object>> {
+        let file = match fs::File::open(self.dir.join(name)) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        let metadata = file.metadata()?;
+        if !metadata.is_file() {
+            return Ok(None);
+        }
+        Ok(Some(Object {
+            len: metadata.len(),
+            modified: metadata.modified()?.into(),
+            body: Box::new(file),
+        }))
+    }
+
+    fn exists(&self, name: &str) -> io::Result<bool> {
+        Ok(self.dir.join(name).is_file())
+    }
+
+    fn remove(&self, name: &str) -> io::Result<()> {
+        match fs::remove_file(self.dir.join(name)) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The `receipt_storage` settings of a bucket.
+#[derive(Clone, Debug, Deserialize)]
+pub struct BucketConfig {
+    /// The service's address, such as `https://s3.eu-central-1.amazonaws.com`.
+    pub endpoint: String,
+    pub bucket: String,
+    #[serde(default = "default_region")]
+    pub region: String,
+    pub access_key: String,
+    pub secret_key: String,
+    #[serde(default)]
+    pub prefix: String,
+}
+
+fn default_region() -> String {
+    "us-east-1".to_string()
+}
+
+/// Receipts kept in a bucket, see the module documentation.
+pub struct Bucket {
+    local: Local,
+    config: BucketConfig,
+    host: String,
+    agent: ureq::Agent,
+}
+
+impl Bucket {
+    pub fn new(dir: &Path, mut config: BucketConfig) -> Result<Self, String> {
+        config.endpoint = config.endpoint.trim_end_matches('/').to_string();
+        let host = ["https://", "http://"]
+            .iter()
+            .find_map(|scheme| config.endpoint.strip_prefix(scheme))
+            .filter(|rest| !rest.is_empty() && !rest.contains('/'))
+            .ok_or("endpoint must be an http or https address without a path")?
+            .to_string();
+        Ok(Bucket {
+            local: Local::new(dir),
+            config,
+            host,
+            agent: ureq::AgentBuilder::new()
+                .timeout_connect(CONNECT_TIMEOUT)
+                .timeout_read(READ_TIMEOUT)
+                .build(),
+        })
+    }
+
+    /// Sends a signed request for the object `name`.
+    fn request(
+        &self,
+        method: &str,
+        name: &str,
+        payload: &[u8],
+    ) -> Result<ureq::Response, Failure> {
+        let path = uri_encode(&format!(
+            "/{}/{}{name}",
+            self.config.bucket, self.config.prefix
+        ));
+        let payload_sha256 = hex(&Sha256::digest(payload));
+        let at = Utc::now();
+        let amz_date = at.format("%Y%m%dT%H%M%SZ").to_string();
+        let headers = [
+            ("host", self.host.as_str()),
+            ("x-amz-content-sha256", payload_sha256.as_str()),
+            ("x-amz-date", amz_date.as_str()),
+        ];
+        let mut request = self
+            .agent
+            .request(method, &format!("{}{path}", self.config.endpoint))
+            .set(
+                "Authorization",
+                &authorization(&self.config, method, &path, &headers, at),
+            );
+        for (header, value) in headers {
+            request = request.set(header, value);
+        }
+        if method == "PUT" {
+            let content_type = Path::new(name)
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .and_then(ContentType::from_extension)
+                .unwrap_or(ContentType::Binary);
+            return Ok(request
+                .timeout(PUT_TIMEOUT)
+                .set("Content-Type", &content_type.to_string())
+                .send_bytes(payload)?);
+        }
+        Ok(request.call()?)
+    }
+}
+
+/// A request the service refused or that didn't get through. Only the status
+/// or the kind of failure is kept; the address stays out of it.
+enum Failure {
+    Status(u16),
+    Transport(ureq::ErrorKind),
+}
+
+impl From<ureq::Error> for Failure {
+    fn from(err: ureq::Error) -> Self {
+        match err {
+            ureq::Error::Status(code, _) => Failure::Status(code),
+            ureq::Error::Transport(transport) => Failure::Transport(transport.kind()),
+        }
+    }
+}
+
+impl From<Failure> for io::Error {
+    fn from(failure: Failure) -> Self {
+        match failure {
+            Failure::Status(code) => io::Error::other(format!("S3 returned {code}")),
+            Failure::Transport(kind) => io::Error::other(kind.to_string()),
+        }
+    }
+}
+
+impl Storage for Bucket {
+    fn put(&self, name: &str) -> io::Result<()> {
+        let path = self.local.dir.join(name);
+        let content = match fs::read(&path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        self.request("PUT", name, &content)?;
+        self.local.remove(name)
+    }
+
+    fn open(&self, name: &str) -> io::Result<Option<Object>> {
+        if let Some(object) = self.local.open(name)? {
+            return Ok(Some(object));
+        }
+        let response = match self.request("GET", name, &[]) {
+            Ok(response) => response,
+            Err(Failure::Status(404)) => return Ok(None),
+            Err(failure) => return Err(failure.into()),
+        };
+        // The length goes into the receipt's validator, so it can't be guessed.
+        let len = response
+            .header("Content-Length")
+            .and_then(|len| len.parse().ok())
+            .ok_or_else(|| io::Error::other("S3 sent no valid Content-Length"))?;
+        let modified = response
+            .header("Last-Modified")
+            .and_then(|modified| DateTime::parse_from_rfc2822(modified).ok())
+            .map(|modified| modified.with_timezone(&Utc))
+            .unwrap_or_default();
+        Ok(Some(Object {
+            len,
+            modified,
+            body: Box::new(response.into_reader()),
+        }))
+    }
+
+    fn exists(&self, name: &str) -> io::Result<bool> {
+        if self.local.exists(name)? {
+            return Ok(true);
+        }
+        match self.request("HEAD", name, &[]) {
+            Ok(_) => Ok(true),
+            Err(Failure::Status(404)) => Ok(false),
+            Err(failure) => Err(failure.into()),
+        }
+    }
+
+    fn remove(&self, name: &str) -> io::Result<()> {
+        self.local.remove(name)?;
+        match self.request("DELETE", name, &[]) {
+            Ok(_) | Err(Failure::Status(404)) => Ok(()),
+            Err(failure) => Err(failure.into()),
+        }
+    }
+}
+
+/// The `Authorization` header of an AWS Signature Version 4 request to S3,
+/// made at `at`. `headers` are those signed, lowercase and sorted, and hold
+/// `x-amz-content-sha256` and `x-amz-date`; `path` is URI-encoded already.
+pub fn authorization(
+    config: &BucketConfig,
+    method: &str,
+    path: &str,
+    headers: &[(&str, &str)],
+    at: DateTime<Utc>,
+) -> String {
+    let date = at.format("%Y%m%d").to_string();
+    let amz_date = at.format("%Y%m%dT%H%M%SZ").to_string();
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(";");
+    let canonical_headers = headers
+        .iter()
+        .map(|(name, value)| format!("{name}:{}\n", value.trim()))
+        .collect::<String>();
+    let payload_sha256 = headers
+        .iter()
+        .find(|(name, _)| *name == "x-amz-content-sha256")
+        .map_or("UNSIGNED-PAYLOAD", |(_, value)| *value);
+    let canonical_request =
+        format!("{method}\n{path}\n\n{canonical_headers}\n{signed_headers}\n{payload_sha256}");
+    let scope = format!("{date}/{}/s3/aws4_request", config.region);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+        hex(&Sha256::digest(canonical_request.as_bytes()))
+    );
+    let signing_key = [date.as_str(), config.region.as_str(), "s3", "aws4_request"]
+        .iter()
+        .fold(
+            format!("AWS4{}", config.secret_key).into_bytes(),
+            |key, part| hmac(&key, part.as_bytes()),
+        );
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={}",
+        config.access_key,
+        hex(&hmac(&signing_key, string_to_sign.as_bytes()))
+    )
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// `path` with every byte but the unreserved ones and `/` percent-encoded,
+/// as S3 signs it.
+fn uri_encode(path: &str) -> String {
+    path.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+enum Config {
+    Local,
+    S3(BucketConfig),
+}
+
+/// Reads [`KEY`] from the configuration into managed [`Receipts`]; the
+/// receipts directory without it.
+pub struct Setting;
+
+#[rocket::async_trait]
+impl Fairing for Setting {
+    fn info(&self) -> Info {
+        Info {
+            name: "Receipt storage",
+            kind: Kind::Ignite,
+        }
+    }
+
+    async fn on_ignite(&self, rocket: Rocket<Build>) -> fairing::Result {
+        let dir = crate::receipts_dir();
+        let config = match rocket.figment().extract_inner::<Config>(KEY) {
+            Ok(config) => config,
+            Err(err) if err.missing() => Config::Local,
+            Err(err) => {
+                rocket::error!("invalid {KEY} configuration: {err}");
+                return Err(rocket);
+            }
+        };
+        let storage = match config {
+            Config::Local => Receipts::new(Local::new(&dir)),
+            Config::S3(config) => match Bucket::new(&dir, config) {
+                Ok(bucket) => Receipts::new(bucket),
+                Err(err) => {
+                    rocket::error!("invalid {KEY} configuration: {err}");
+                    return Err(rocket);
+                }
+            },
+        };
+        Ok(rocket.manage(storage))
+    }
+}
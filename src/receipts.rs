@@ -0,0 +1,114 @@
+//! Receipt images with validators, so a page full of them revalidates with
+//! `304 Not Modified` instead of downloading every image again. The image is
+//! streamed from its storage as the client reads it.
+
+use std::io::Read;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use rocket::http::{ContentType, Header, Status};
+use rocket::response::{self, Responder};
+use rocket::tokio::io::{AsyncWriteExt, DuplexStream};
+use rocket::tokio::runtime::Handle;
+use rocket::{Request, Response};
+
+use crate::receipt_storage::Receipts;
+
+/// Receipts sit behind the login, so only the browser may keep them, and it
+/// must ask before reusing them.
+const CACHE_CONTROL: &str = "private, no-cache";
+const HTTP_DATE: &str = "%a, %d %b %Y %H:%M:%S GMT";
+/// Bytes read from storage ahead of the client.
+const CHUNK: usize = 64 * 1024;
+
+pub struct Receipt {
+    body: Box<dyn Read + Send>,
+    content_type: ContentType,
+    etag: String,
+    modified: DateTime<Utc>,
+}
+
+impl Receipt {
+    /// Opens `name` from `storage`; anything but a plain file name is refused.
+    pub async fn open(storage: &Receipts, name: &str) -> Option<Receipt> {
+        if name.starts_with('.') || Path::new(name).file_name()?.to_str()? != name {
+            return None;
+        }
+        let (storage, owned) = (storage.clone(), name.to_string());
+        let object = rocket::tokio::task::spawn_blocking(move || storage.open(&owned))
+            .await
+            .ok()?
+            .ok()??;
+        let etag = format!(
+            "\"{:x}-{:x}\"",
+            object.len,
+            object.modified.timestamp_nanos_opt().unwrap_or_default()
+        );
+        let content_type = Path::new(name)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(ContentType::from_extension)
+            .unwrap_or(ContentType::Binary);
+        Some(Receipt {
+            body: object.body,
+            content_type,
+            etag,
+            modified: object.modified,
+        })
+    }
+
+    /// Whether the client's copy is still current. `If-None-Match` wins over
+    /// `If-Modified-Since` when both are sent.
+    fn not_modified(&self, request: &Request<'_>) -> bool {
+        let headers = request.headers();
+        if let Some(tags) = headers.get_one("If-None-Match") {
+            return tags.split(',').map(str::trim).any(|tag| {
+                tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == self.etag
+            });
+        }
+        headers
+            .get_one("If-Modified-Since")
+            .and_then(|since| DateTime::parse_from_rfc2822(since).ok())
+            .is_some_and(|since| self.modified.timestamp() <= since.timestamp())
+    }
+}
+
+impl<'r> Responder<'r, 'static> for Receipt {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let mut response = if self.not_modified(request) {
+            Response::build().status(Status::NotModified).finalize()
+        } else {
+            Response::build()
+                .header(self.content_type)
+                .streamed_body(stream(self.body))
+                .finalize()
+        };
+        response.set_header(Header::new("ETag", self.etag));
+        response.set_header(Header::new(
+            "Last-Modified",
+            self.modified.format(HTTP_DATE).to_string(),
+        ));
+        response.set_header(Header::new("Cache-Control", CACHE_CONTROL));
+        Ok(response)
+    }
+}
+
+/// `body`, read on a blocking thread as the client takes it.
+fn stream(mut body: Box<dyn Read + Send>) -> DuplexStream {
+    let (mut writer, reader) = rocket::tokio::io::duplex(CHUNK);
+    let runtime = Handle::current();
+    rocket::tokio::task::spawn_blocking(move || {
+        let mut buffer = vec![0; CHUNK];
+        loop {
+            let read = match body.read(&mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(read) => read,
+            };
+            // Fails when the client has gone.
+            if runtime.block_on(writer.write_all(&buffer[..read])).is_err() {
+                break;
+            }
+        }
+    });
+    reader
+}
@@ -0,0 +1,178 @@
+//! "Remember me": staying signed in after the browser session is over.
+//!
+//! A login with the box ticked also sets a [`COOKIE`] holding a series and a
+//! token, `series:token`. The series and a hash of the token are stored in
+//! `remember_tokens` for [`REMEMBER_FOR`] from the login. A request with no
+//! live session but that cookie gets a new session from the [`Resume`]
+//! fairing, and the series a new token: each token works once. An old token
+//! of a known series means the cookie was copied and one copy has been used
+//! already, so every session and remembered login of the account ends.
+//!
+//! Sessions themselves keep their short lifetime, see `sessions`.
+
+use chrono::{DateTime, Duration, Utc};
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::{Cookie, CookieJar, SameSite};
+use rocket::{Data, Request};
+use rusqlite::{Connection, Result};
+use uuid::Uuid;
+
+use crate::allowlist;
+use crate::api_sessions::timestamp;
+use crate::db::{self, DbPool};
+use crate::sessions;
+
+pub const COOKIE: &str = "remember";
+pub const REMEMBER_FOR: Duration = Duration::days(60);
+
+/// What a `remember` cookie holds.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Remembered {
+    pub series: String,
+    pub token: String,
+}
+
+impl Remembered {
+    fn new(series: String) -> Self {
+        Remembered {
+            series,
+            token: Uuid::new_v4().simple().to_string(),
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        let (series, token) = value.split_once(':')?;
+        (!series.is_empty() && !token.is_empty()).then(|| Remembered {
+            series: series.to_string(),
+            token: token.to_string(),
+        })
+    }
+
+    pub fn value(&self) -> String {
+        format!("{}:{}", self.series, self.token)
+    }
+}
+
+/// The cookie for `remembered`, kept by the browser as long as the series
+/// lasts.
+pub fn cookie(remembered: &Remembered) -> Cookie<'static> {
+    let mut cookie = Cookie::new(COOKIE, remembered.value());
+    cookie.set_path("/");
+    cookie.set_http_only(true);
+    cookie.set_same_site(SameSite::Lax);
+    cookie.set_max_age(rocket::time::Duration::days(REMEMBER_FOR.num_days()));
+    cookie
+}
+
+pub fn removal() -> Cookie<'static> {
+    let mut cookie = Cookie::from(COOKIE);
+    cookie.set_path("/");
+    cookie
+}
+
+/// Starts a series for `user_id`.
+pub fn issue(conn: &Connection, user_id: i64, now: DateTime<Utc>) -> Result<Remembered> {
+    let remembered = Remembered::new(Uuid::new_v4().simple().to_string());
+    db::insert_remember_token(
+        conn,
+        user_id,
+        &remembered.series,
+        &db::session_token_hash(&remembered.token),
+        &timestamp(now),
+        &timestamp(now + REMEMBER_FOR),
+    )?;
+    Ok(remembered)
+}
+
+/// Trades `remembered` for whose it is and the series' next token; `None`
+/// when it no longer signs anyone in.
+pub fn resume(
+    conn: &Connection,
+    remembered: &Remembered,
+    now: DateTime<Utc>,
+) -> Result<Option<(i64, Remembered)>> {
+    let Some(stored) = db::remember_token(conn, &remembered.series)? else {
+        return Ok(None);
+    };
+    let at = timestamp(now);
+    if stored.expires_at <= at {
+        db::delete_remember_series(conn, &remembered.series)?;
+        return Ok(None);
+    }
+    if stored.token_hash != db::session_token_hash(&remembered.token) {
+        db::delete_sessions_for_user(conn, stored.user_id)?;
+        db::delete_remember_tokens_for_user(conn, stored.user_id)?;
+        return Ok(None);
+    }
+    let next = Remembered::new(remembered.series.clone());
+    db::rotate_remember_token(conn, stored.id, &db::session_token_hash(&next.token), &at)?;
+    Ok(Some((stored.user_id, next)))
+}
+
+/// Ends the series the browser's cookie belongs to, on logout.
+pub fn forget(conn: &Connection, cookies: &CookieJar<'_>) -> Result<()> {
+    if let Some(remembered) = cookies
+        .get(COOKIE)
+        .and_then(|cookie| Remembered::parse(cookie.value()))
+    {
+        db::delete_remember_series(conn, &remembered.series)?;
+    }
+    cookies.remove(removal());
+    Ok(())
+}
+
+/// Signs a remembered browser back in before the request reaches CSRF
+/// protection and the handlers, which then see the new session cookie.
+pub struct Resume;
+
+#[rocket::async_trait]
+impl Fairing for Resume {
+    fn info(&self) -> Info {
+        Info {
+            name: "Remembered logins",
+            kind: Kind::Request,
+        }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _: &mut Data<'_>) {
+        let Some(value) = request
+            .cookies()
+            .get(COOKIE)
+            .map(|cookie| cookie.value().to_string())
+        else {
+            return;
+        };
+        let Some(pool) = request.rocket().state::<DbPool>() else {
+            return;
+        };
+        let Ok(conn) = pool.get() else {
+            return;
+        };
+        let now = Utc::now();
+        let cookies = request.cookies();
+        if let Some(token) = sessions::token(cookies)
+            && matches!(sessions::user(&conn, &token, now), Ok(Some(_)))
+        {
+            return;
+        }
+        let Some(remembered) = Remembered::parse(&value) else {
+            cookies.remove(removal());
+            return;
+        };
+        let (user_id, next) = match resume(&conn, &remembered, now) {
+            Ok(Some(resumed)) => resumed,
+            Ok(None) => {
+                cookies.remove(removal());
+                return;
+            }
+            Err(_) => return,
+        };
+        let token = Uuid::new_v4().to_string();
+        let user_agent = request.headers().get_one("User-Agent").unwrap_or("").trim();
+        // The series has moved on either way, so the browser has to as well.
+        cookies.add(cookie(&next));
+        if sessions::start(&conn, user_id, &token, user_agent, allowlist::client_addr(request), now).is_ok() {
+            cookies.add(sessions::cookie(token));
+        }
+    }
+}
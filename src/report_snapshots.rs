@@ -0,0 +1,105 @@
+//! Closed months.
+//!
+//! Closing a month freezes its report figures, income, expenses and the
+//! expenses of each top-level category, in `report_snapshots`. Later edits,
+//! imports and backfills still change the live report, but the reports page
+//! then marks the month as differing from its snapshot and lists both
+//! figures. Closing the month again takes a new snapshot; reopening drops it.
+
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, Result};
+
+use crate::api_sessions::timestamp;
+use crate::db;
+use crate::disputes::RefundMonth;
+use crate::models::{ReportFigure, ReportSnapshot};
+
+/// A figure that no longer matches the snapshot.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Difference {
+    pub label: String,
+    pub snapshot_cents: i64,
+    pub current_cents: i64,
+}
+
+/// The month's figures as the report shows them now.
+pub fn figures(conn: &Connection, month: &str, refunds: RefundMonth) -> Result<Vec<ReportFigure>> {
+    let (income_cents, expense_cents) = db::report_month_totals(conn, month, refunds)?;
+    let mut figures = vec![
+        ReportFigure {
+            kind: "income".to_string(),
+            category_name: String::new(),
+            amount_cents: income_cents,
+        },
+        ReportFigure {
+            kind: "expense".to_string(),
+            category_name: String::new(),
+            amount_cents: expense_cents,
+        },
+    ];
+    for category in db::report_categories(conn, month, refunds)? {
+        if category.parent_name.is_none() {
+            figures.push(ReportFigure {
+                kind: "category".to_string(),
+                category_name: category.category_name,
+                amount_cents: category.expense_cents,
+            });
+        }
+    }
+    Ok(figures)
+}
+
+/// Closes `month`, or takes a new snapshot of a closed one.
+pub fn close(
+    conn: &Connection,
+    month: &str,
+    refunds: RefundMonth,
+    now: DateTime<Utc>,
+) -> Result<()> {
+    let figures = figures(conn, month, refunds)?;
+    db::save_report_snapshot(conn, month, &figures, refunds, &timestamp(now))
+}
+
+/// The closed month's figures that changed since, in the snapshot's order
+/// with new categories last; counted the way refunds were counted then.
+pub fn differences(conn: &Connection, snapshot: &ReportSnapshot) -> Result<Vec<Difference>> {
+    let current = figures(conn, &snapshot.month, snapshot.refunds)?;
+    Ok(compare(&snapshot.figures, &current))
+}
+
+/// The figures of `current` that differ from `snapshot`; a figure missing on
+/// one side counts as zero there.
+fn compare(snapshot: &[ReportFigure], current: &[ReportFigure]) -> Vec<Difference> {
+    let same =
+        |a: &ReportFigure, b: &ReportFigure| a.kind == b.kind && a.category_name == b.category_name;
+    let amount = |figures: &[ReportFigure], figure: &ReportFigure| {
+        figures
+            .iter()
+            .find(|other| same(other, figure))
+            .map_or(0, |other| other.amount_cents)
+    };
+    let new = current
+        .iter()
+        .filter(|figure| !snapshot.iter().any(|other| same(other, figure)));
+    snapshot
+        .iter()
+        .chain(new)
+        .filter_map(|figure| {
+            let snapshot_cents = amount(snapshot, figure);
+            let current_cents = amount(current, figure);
+            (snapshot_cents != current_cents).then(|| Difference {
+                label: label(figure),
+                snapshot_cents,
+                current_cents,
+            })
+        })
+        .collect()
+}
+
+fn label(figure: &ReportFigure) -> String {
+    match figure.kind.as_str() {
+        "income" => "Доход".to_string(),
+        "expense" => "Расход".to_string(),
+        _ => figure.category_name.clone(),
+    }
+}
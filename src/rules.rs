@@ -0,0 +1,46 @@
+//! Automatic categorization of new transactions.
+//!
+//! A rule matches when the note or payee contains its pattern, ignoring case,
+//! and the amount is within its bounds. Only transactions that arrive without
+//! a category are touched, so a category picked by hand always wins; the first
+//! matching rule decides.
+
+use crate::models::{Category, NewTransaction, Rule};
+use crate::parse_tags;
+
+pub fn matches(rule: &Rule, transaction: &NewTransaction) -> bool {
+    let pattern = rule.pattern.to_lowercase();
+    let contains = |text: &Option<String>| {
+        text.as_deref()
+            .is_some_and(|text| text.to_lowercase().contains(&pattern))
+    };
+    let amount = transaction.amount_cents;
+    (contains(&transaction.note) || contains(&transaction.payee))
+        && rule.min_cents.is_none_or(|min| amount >= min)
+        && rule.max_cents.is_none_or(|max| amount <= max)
+}
+
+/// Fills in the category and kind from the first matching rule and returns
+/// the tags it adds, which the caller attaches once the transaction is saved.
+pub fn apply(
+    rules: &[Rule],
+    categories: &[Category],
+    transaction: &mut NewTransaction,
+) -> Vec<String> {
+    if transaction.category_id.is_some() || transaction.kind == "transfer" {
+        return Vec::new();
+    }
+    let Some(rule) = rules.iter().find(|rule| matches(rule, transaction)) else {
+        return Vec::new();
+    };
+    let category = rule
+        .category_id
+        .and_then(|id| categories.iter().find(|category| category.id == id));
+    if let Some(category) = category {
+        transaction.category_id = Some(category.id);
+        transaction.kind = category.kind.clone();
+    } else if let Some(kind) = &rule.kind {
+        transaction.kind = kind.clone();
+    }
+    parse_tags(rule.tags.as_deref().unwrap_or(""))
+}
@@ -0,0 +1,92 @@
+//! Security headers on every response.
+//!
+//! Values come from the `security_headers` table of Rocket's configuration,
+//! e.g. `Rocket.toml` or `ROCKET_SECURITY_HEADERS={hsts="max-age=31536000"}`;
+//! a key left out keeps its default and an empty value drops the header.
+//! Rocket's own Shield sets some of the same headers first, the configured
+//! values replace them.
+
+use rocket::fairing::{self, Fairing, Info, Kind};
+use rocket::http::Header;
+use rocket::{Build, Request, Response, Rocket};
+use serde::Deserialize;
+
+/// Configuration key of [`SecurityHeaders`].
+pub const KEY: &str = "security_headers";
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct SecurityHeaders {
+    /// The pages carry small inline scripts and progress bar widths.
+    pub content_security_policy: String,
+    pub content_type_options: String,
+    pub referrer_policy: String,
+    pub frame_options: String,
+    /// `Strict-Transport-Security`, off by default: only a deployment served
+    /// over HTTPS should make browsers insist on it.
+    pub hsts: String,
+}
+
+impl Default for SecurityHeaders {
+    fn default() -> Self {
+        SecurityHeaders {
+            content_security_policy: "default-src 'self'; script-src 'self' 'unsafe-inline'; \
+                                      style-src 'self' 'unsafe-inline'; img-src 'self' data:; \
+                                      form-action 'self'; frame-ancestors 'none'; base-uri 'self'"
+                .to_string(),
+            content_type_options: "nosniff".to_string(),
+            // Revoke links and import tokens live in URLs.
+            referrer_policy: "same-origin".to_string(),
+            frame_options: "DENY".to_string(),
+            hsts: String::new(),
+        }
+    }
+}
+
+impl SecurityHeaders {
+    fn headers(&self) -> [(&'static str, &str); 5] {
+        [
+            ("Content-Security-Policy", &self.content_security_policy),
+            ("X-Content-Type-Options", &self.content_type_options),
+            ("Referrer-Policy", &self.referrer_policy),
+            ("X-Frame-Options", &self.frame_options),
+            ("Strict-Transport-Security", &self.hsts),
+        ]
+    }
+}
+
+pub struct Headers;
+
+#[rocket::async_trait]
+impl Fairing for Headers {
+    fn info(&self) -> Info {
+        Info {
+            name: "Security headers",
+            kind: Kind::Ignite | Kind::Response,
+        }
+    }
+
+    async fn on_ignite(&self, rocket: Rocket<Build>) -> fairing::Result {
+        match rocket.figment().extract_inner::<SecurityHeaders>(KEY) {
+            Ok(headers) => Ok(rocket.manage(headers)),
+            Err(err) if err.missing() => Ok(rocket.manage(SecurityHeaders::default())),
+            Err(err) => {
+                rocket::error!("invalid {KEY} configuration: {err}");
+                Err(rocket)
+            }
+        }
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let Some(headers) = request.rocket().state::<SecurityHeaders>() else {
+            return;
+        };
+        for (name, value) in headers.headers() {
+            if value.is_empty() {
+                response.remove_header(name);
+            } else {
+                response.set_header(Header::new(name, value.to_string()));
+            }
+        }
+    }
+}
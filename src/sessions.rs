@@ -0,0 +1,163 @@
+//! How long a browser session lasts.
+//!
+//! A session ends [`Lifetime::max_age`] after the login whatever happens, and
+//! earlier once it has gone unused for [`Lifetime::idle`]; every request it
+//! makes pushes the idle deadline back. Both are read once from
+//! `LUMEN_SESSION_MAX_AGE_HOURS` and `LUMEN_SESSION_IDLE_MINUTES`.
+//!
+//! Each session keeps the browser's user agent and address from the login, so
+//! the settings page can tell them apart and sign out any one of them.
+
+use std::net::IpAddr;
+use std::sync::OnceLock;
+
+use chrono::{DateTime, Duration, Utc};
+use rocket::http::{Cookie, CookieJar, SameSite};
+use rusqlite::{Connection, Result};
+
+use crate::api_sessions::timestamp;
+use crate::db;
+use crate::models::{BrowserSession, User};
+
+/// The last use is written at most this often, not on every request.
+const TOUCH_EVERY: Duration = Duration::minutes(1);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Lifetime {
+    pub max_age: Duration,
+    pub idle: Duration,
+}
+
+impl Default for Lifetime {
+    fn default() -> Self {
+        Lifetime {
+            max_age: Duration::days(30),
+            idle: Duration::days(1),
+        }
+    }
+}
+
+impl Lifetime {
+    pub fn from_env() -> Self {
+        let var = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .and_then(|value| value.trim().parse::<i64>().ok())
+                .filter(|value| *value > 0)
+        };
+        let default = Lifetime::default();
+        Lifetime {
+            max_age: var("LUMEN_SESSION_MAX_AGE_HOURS").map_or(default.max_age, Duration::hours),
+            idle: var("LUMEN_SESSION_IDLE_MINUTES").map_or(default.idle, Duration::minutes),
+        }
+    }
+}
+
+/// The configured lifetime, read from the environment on first use.
+pub fn lifetime() -> Lifetime {
+    static LIFETIME: OnceLock<Lifetime> = OnceLock::new();
+    *LIFETIME.get_or_init(Lifetime::from_env)
+}
+
+/// The browser's session token, including one `remember::Resume` set while
+/// handling this very request.
+pub fn token(cookies: &CookieJar<'_>) -> Option<String> {
+    cookies
+        .get_pending("session")
+        .map(|cookie| cookie.value().to_string())
+        .filter(|token| !token.is_empty())
+}
+
+/// The cookie that carries a session; it ends with the browser.
+pub fn cookie(token: String) -> Cookie<'static> {
+    let mut cookie = Cookie::new("session", token);
+    cookie.set_path("/");
+    cookie.set_http_only(true);
+    cookie.set_same_site(SameSite::Lax);
+    cookie
+}
+
+/// Stores a new session for `user_id` under `token`, and drops the user's
+/// sessions that have run out.
+pub fn start(
+    conn: &Connection,
+    user_id: i64,
+    token: &str,
+    user_agent: &str,
+    client_ip: Option<IpAddr>,
+    now: DateTime<Utc>,
+) -> Result<()> {
+    let lifetime = lifetime();
+    let at = timestamp(now);
+    db::delete_expired_sessions(conn, user_id, &at, &timestamp(now - lifetime.idle))?;
+    db::create_session(
+        conn,
+        user_id,
+        token,
+        user_agent,
+        client_ip.map(|ip| ip.to_string()).as_deref(),
+        &at,
+        &timestamp(now + lifetime.max_age),
+    )
+}
+
+/// The user's live sessions; the one under `current` is marked.
+pub fn list(conn: &Connection, user_id: i64, current: &str, now: DateTime<Utc>) -> Result<Vec<BrowserSession>> {
+    db::browser_sessions(
+        conn,
+        user_id,
+        current,
+        &timestamp(now),
+        &timestamp(now - lifetime().idle),
+    )
+}
+
+/// A short name for the browser and system a user agent belongs to, such as
+/// "Firefox, Windows"; whatever is recognized of the two.
+pub fn device(user_agent: &str) -> String {
+    // Order matters: Edge and Opera also claim to be Chrome, Chrome claims
+    // to be Safari, Android claims to be Linux.
+    const BROWSERS: &[(&str, &str)] = &[
+        ("Edg/", "Edge"),
+        ("OPR/", "Opera"),
+        ("YaBrowser/", "Яндекс Браузер"),
+        ("Firefox/", "Firefox"),
+        ("Chrome/", "Chrome"),
+        ("Safari/", "Safari"),
+    ];
+    const SYSTEMS: &[(&str, &str)] = &[
+        ("Android", "Android"),
+        ("iPhone", "iOS"),
+        ("iPad", "iPadOS"),
+        ("Windows", "Windows"),
+        ("Mac OS X", "macOS"),
+        ("Linux", "Linux"),
+    ];
+    let find = |names: &[(&str, &'static str)]| {
+        names
+            .iter()
+            .find(|(marker, _)| user_agent.contains(marker))
+            .map(|(_, name)| *name)
+    };
+    let parts = [find(BROWSERS), find(SYSTEMS)]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+    if parts.is_empty() {
+        "Неизвестное устройство".to_string()
+    } else {
+        parts.join(", ")
+    }
+}
+
+/// Whose session `token` is, unless it has expired or sat idle too long;
+/// counts as a use. Recording the use may fail while a long job holds the
+/// database for writing, which doesn't make the session any less valid.
+pub fn user(conn: &Connection, token: &str, now: DateTime<Utc>) -> Result<Option<User>> {
+    let at = timestamp(now);
+    let user = db::user_by_session(conn, token, &at, &timestamp(now - lifetime().idle))?;
+    if user.is_some() {
+        let _ = db::touch_session(conn, token, &at, &timestamp(now - TOUCH_EVERY));
+    }
+    Ok(user)
+}
@@ -0,0 +1,148 @@
+//! Account statements: the balance at the start of a period, every movement
+//! in it with the running balance, and the balance at its end, the way banks
+//! print them.
+
+use chrono::NaiveDate;
+use rusqlite::Connection;
+
+use crate::db;
+use crate::format_money;
+use crate::models::{Account, StatementLine};
+use crate::pdf::{self, Align, Column};
+
+pub struct Entry {
+    pub line: StatementLine,
+    /// Balance right after this line.
+    pub balance_cents: i64,
+}
+
+pub struct Statement {
+    pub from: String,
+    pub to: String,
+    pub opening_cents: i64,
+    pub closing_cents: i64,
+    /// Money that came in over the period.
+    pub credit_cents: i64,
+    /// Money that went out, as a positive amount.
+    pub debit_cents: i64,
+    pub entries: Vec<Entry>,
+}
+
+/// The statement of `account` from `from` to `to`, both days included.
+pub fn build(
+    conn: &Connection,
+    account: &Account,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> rusqlite::Result<Statement> {
+    let from_ymd = from.format("%Y-%m-%d").to_string();
+    let to_ymd = to.format("%Y-%m-%d").to_string();
+    let opening_cents = match from.pred_opt() {
+        Some(day_before) => {
+            db::account_balance_until(conn, account.id, &day_before.format("%Y-%m-%d").to_string())?
+        }
+        None => account.opening_balance_cents,
+    };
+    let mut statement = Statement {
+        from: from_ymd,
+        to: to_ymd,
+        opening_cents,
+        closing_cents: opening_cents,
+        credit_cents: 0,
+        debit_cents: 0,
+        entries: Vec::new(),
+    };
+    for line in db::account_statement(conn, account.id, &statement.from, &statement.to)? {
+        if line.amount_cents >= 0 {
+            statement.credit_cents += line.amount_cents;
+        } else {
+            statement.debit_cents -= line.amount_cents;
+        }
+        statement.closing_cents += line.amount_cents;
+        statement.entries.push(Entry {
+            line,
+            balance_cents: statement.closing_cents,
+        });
+    }
+    Ok(statement)
+}
+
+/// What the line was: its category, or where a transfer went or came from.
+pub fn description(line: &StatementLine) -> String {
+    let counterparty = line.counterparty.as_deref().unwrap_or("-");
+    match line.kind.as_str() {
+        "transfer" if line.amount_cents < 0 => format!("Перевод → {counterparty}"),
+        "transfer" => format!("Перевод ← {counterparty}"),
+        "income" => line
+            .category_name
+            .clone()
+            .unwrap_or_else(|| "Доход".to_string()),
+        _ => line
+            .category_name
+            .clone()
+            .unwrap_or_else(|| "Расход".to_string()),
+    }
+}
+
+/// Incoming and outgoing amounts as the two columns statements show.
+pub fn credit_debit(amount_cents: i64) -> (String, String) {
+    if amount_cents >= 0 {
+        (format_money(amount_cents), String::new())
+    } else {
+        (String::new(), format_money(-amount_cents))
+    }
+}
+
+pub fn pdf(font: &pdf::Font, account: &Account, statement: &Statement) -> Option<Vec<u8>> {
+    let column = |title: &str, weight: f32, align: Align| Column {
+        title: title.to_string(),
+        weight,
+        align,
+    };
+    let rows = statement
+        .entries
+        .iter()
+        .map(|entry| {
+            let (credit, debit) = credit_debit(entry.line.amount_cents);
+            vec![
+                entry.line.occurred_on.clone(),
+                description(&entry.line),
+                entry.line.note.clone().unwrap_or_default(),
+                credit,
+                debit,
+                format_money(entry.balance_cents),
+            ]
+        })
+        .collect();
+    let table = pdf::Table {
+        title: format!("Выписка по счету «{}»", account.name),
+        header: vec![
+            format!(
+                "Период: {} — {}, валюта {}",
+                statement.from, statement.to, account.currency
+            ),
+            format!(
+                "Входящий остаток: {}",
+                format_money(statement.opening_cents)
+            ),
+        ],
+        columns: vec![
+            column("Дата", 1.3, Align::Left),
+            column("Операция", 2.6, Align::Left),
+            column("Заметка", 2.6, Align::Left),
+            column("Приход", 1.3, Align::Right),
+            column("Расход", 1.3, Align::Right),
+            column("Остаток", 1.4, Align::Right),
+        ],
+        rows,
+        footer: vec![
+            format!("Поступления: {}", format_money(statement.credit_cents)),
+            format!("Списания: {}", format_money(statement.debit_cents)),
+            format!(
+                "Исходящий остаток: {}",
+                format_money(statement.closing_cents)
+            ),
+        ],
+    };
+    pdf::render(font, &table)
+}
@@ -0,0 +1,157 @@
+//! Category suggestions for the new-transaction form.
+//!
+//! The payee and note typed so far are compared with those of earlier
+//! categorized income and expenses: equal once normalized, one starting with
+//! the other, or sharing enough trigrams. Every match votes for its category
+//! with how similar it is, a little more for pairs used often. When the user
+//! takes a suggestion it is remembered for the text it was made for, and
+//! from then on counts [`ACCEPTED_WEIGHT`] times as much as a history match
+//! each time it was taken.
+
+use std::collections::HashSet;
+
+use rusqlite::{Connection, Result};
+use serde::Serialize;
+
+use crate::db;
+
+/// Suggestions returned at most.
+pub const LIMIT: usize = 3;
+const MIN_SIMILARITY: f64 = 0.4;
+/// Shortest text a prefix match counts for.
+const MIN_PREFIX: usize = 3;
+const ACCEPTED_WEIGHT: f64 = 2.0;
+/// Earlier payee and note pairs looked at, the most recently used first.
+const HISTORY: i64 = 5_000;
+
+#[derive(Debug, Serialize)]
+pub struct Suggestion {
+    pub category_id: i64,
+    pub category_name: String,
+    pub score: f64,
+}
+
+/// Lowercase words of `text` separated by single spaces, punctuation dropped.
+pub fn normalize(text: &str) -> String {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn trigrams(text: &str) -> HashSet<[char; 3]> {
+    let chars = format!("  {text} ").chars().collect::<Vec<_>>();
+    chars.windows(3).map(|w| [w[0], w[1], w[2]]).collect()
+}
+
+/// How alike two normalized texts are, from 0 to 1: 1 when equal, 0.9 when
+/// one starts with the other, else the share of trigrams they have in common.
+pub fn similarity(a: &str, b: &str) -> f64 {
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    if a == b {
+        return 1.0;
+    }
+    let (short, long) = if a.chars().count() <= b.chars().count() {
+        (a, b)
+    } else {
+        (b, a)
+    };
+    if short.chars().count() >= MIN_PREFIX && long.starts_with(short) {
+        return 0.9;
+    }
+    let (a, b) = (trigrams(a), trigrams(b));
+    let shared = a.intersection(&b).count();
+    shared as f64 / (a.len() + b.len() - shared) as f64
+}
+
+/// The fields of a transaction suggestions look at, normalized; empty ones
+/// are left out.
+fn fields(payee: Option<&str>, note: Option<&str>) -> Vec<(&'static str, String)> {
+    [("payee", payee), ("note", note)]
+        .into_iter()
+        .filter_map(|(field, text)| {
+            let text = normalize(text?);
+            (!text.is_empty()).then_some((field, text))
+        })
+        .collect()
+}
+
+/// Categories for a transaction with `payee` and `note`, the likeliest first;
+/// only those of `kind` when it is given.
+pub fn suggest(
+    conn: &Connection,
+    payee: Option<&str>,
+    note: Option<&str>,
+    kind: Option<&str>,
+) -> Result<Vec<Suggestion>> {
+    let typed = fields(payee, note);
+    if typed.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut scores: Vec<(i64, f64)> = Vec::new();
+    let mut vote =
+        |category_id: i64, score: f64| match scores.iter_mut().find(|(id, _)| *id == category_id) {
+            Some((_, total)) => *total += score,
+            None => scores.push((category_id, score)),
+        };
+    for earlier in db::category_history(conn, HISTORY)? {
+        for (field, text_then) in fields(earlier.payee.as_deref(), earlier.note.as_deref()) {
+            let Some((_, text)) = typed.iter().find(|(typed, _)| *typed == field) else {
+                continue;
+            };
+            let similarity = similarity(text, &text_then);
+            if similarity >= MIN_SIMILARITY {
+                vote(
+                    earlier.category_id,
+                    similarity * (1.0 + (earlier.uses as f64).ln()),
+                );
+            }
+        }
+    }
+    for taken in db::accepted_suggestions(conn)? {
+        let Some((_, text)) = typed.iter().find(|(typed, _)| *typed == taken.field) else {
+            continue;
+        };
+        let similarity = similarity(text, &taken.text);
+        if similarity >= MIN_SIMILARITY {
+            vote(
+                taken.category_id,
+                similarity * ACCEPTED_WEIGHT * taken.accepted as f64,
+            );
+        }
+    }
+
+    scores.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+    let categories = db::list_categories(conn)?;
+    Ok(scores
+        .into_iter()
+        .filter_map(|(category_id, score)| {
+            let category = categories.iter().find(|category| {
+                category.id == category_id && kind.is_none_or(|kind| category.kind == kind)
+            })?;
+            Some(Suggestion {
+                category_id,
+                category_name: category.name.clone(),
+                score: (score * 100.0).round() / 100.0,
+            })
+        })
+        .take(LIMIT)
+        .collect())
+}
+
+/// Remembers that `category_id` was taken for a transaction with `payee` and
+/// `note`.
+pub fn record_accepted(
+    conn: &Connection,
+    payee: Option<&str>,
+    note: Option<&str>,
+    category_id: i64,
+) -> Result<()> {
+    for (field, text) in fields(payee, note) {
+        db::record_accepted_suggestion(conn, field, &text, category_id)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,179 @@
+use std::time::Duration;
+
+use chrono::Local;
+use rocket::request::{FromRequest, Outcome, Request};
+use serde_json::{Value, json};
+
+use crate::db::{self, DbPool};
+use crate::format_money;
+use crate::models::PendingNotification;
+use crate::query::TransactionQuery;
+
+const API_BASE: &str = "https://api.telegram.org";
+pub const OUTBOX_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Bot credentials; Telegram is disabled unless `LUMEN_TELEGRAM_TOKEN` is set.
+///
+/// `LUMEN_TELEGRAM_SECRET` must match the `secret_token` passed to `setWebhook`,
+/// Telegram echoes it back on every webhook call.
+#[derive(Clone)]
+pub struct TelegramConfig {
+    pub token: String,
+    pub secret: String,
+}
+
+impl TelegramConfig {
+    pub fn from_env() -> Option<Self> {
+        let token = std::env::var("LUMEN_TELEGRAM_TOKEN").ok()?;
+        let secret = std::env::var("LUMEN_TELEGRAM_SECRET").ok()?;
+        if token.trim().is_empty() || secret.trim().is_empty() {
+            return None;
+        }
+        Some(TelegramConfig { token, secret })
+    }
+
+    fn call(&self, method: &str, body: Value) -> Result<(), Box<ureq::Error>> {
+        ureq::post(&format!("{API_BASE}/bot{}/{method}", self.token))
+            .timeout(Duration::from_secs(10))
+            .send_json(body)
+            .map_err(Box::new)?;
+        Ok(())
+    }
+
+    pub fn send_message(
+        &self,
+        chat_id: i64,
+        text: &str,
+        keyboard: Option<Value>,
+    ) -> Result<(), Box<ureq::Error>> {
+        let mut body = json!({ "chat_id": chat_id, "text": text });
+        if let Some(keyboard) = keyboard {
+            body["reply_markup"] = json!({ "inline_keyboard": keyboard });
+        }
+        self.call("sendMessage", body)
+    }
+
+    pub fn answer_callback(&self, callback_id: &str, text: &str) -> Result<(), Box<ureq::Error>> {
+        self.call(
+            "answerCallbackQuery",
+            json!({ "callback_query_id": callback_id, "text": text }),
+        )
+    }
+}
+
+/// Value of the `X-Telegram-Bot-Api-Secret-Token` header; empty when missing.
+pub struct SecretToken<'r>(pub &'r str);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for SecretToken<'r> {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, ()> {
+        let value = request
+            .headers()
+            .get_one("X-Telegram-Bot-Api-Secret-Token")
+            .unwrap_or("");
+        Outcome::Success(SecretToken(value))
+    }
+}
+
+/// Inline buttons for a notification; callback data is `action:category_id:month`.
+fn keyboard_for(notification: &PendingNotification) -> Option<Value> {
+    if notification.event != "budget_exceeded" {
+        return None;
+    }
+    let payload: Value = serde_json::from_str(notification.payload.as_deref()?).ok()?;
+    let category_id = payload["category_id"].as_i64()?;
+    let month = payload["month"].as_str()?;
+    Some(json!([[
+        { "text": "Показать транзакции", "callback_data": format!("tx:{category_id}:{month}") },
+        { "text": "Увеличить бюджет на 10%", "callback_data": format!("bump:{category_id}:{month}") },
+    ]]))
+}
+
+/// Sends unsent Telegram notifications; rows of users without a linked chat are skipped.
+pub fn deliver_pending(pool: &DbPool, config: &TelegramConfig) {
+    let Ok(conn) = pool.get() else {
+        return;
+    };
+    for notification in db::pending_notifications(&conn, "telegram").unwrap_or_default() {
+        let Some(chat_id) = notification.telegram_chat_id else {
+            continue;
+        };
+        let text = format!("{}\n{}", notification.title, notification.body);
+        if config
+            .send_message(chat_id, &text, keyboard_for(&notification))
+            .is_ok()
+        {
+            let _ = db::mark_notification_sent(&conn, notification.id, &Local::now().to_rfc3339());
+        }
+    }
+}
+
+fn parse_callback(data: &str) -> Option<(&str, i64, &str)> {
+    let mut parts = data.splitn(3, ':');
+    let action = parts.next()?;
+    let category_id = parts.next()?.parse().ok()?;
+    let month = parts.next()?;
+    Some((action, category_id, month))
+}
+
+/// Handles an inline button press from a linked chat and replies to it.
+pub fn handle_update(pool: &DbPool, config: &TelegramConfig, update: &Value) {
+    let callback = &update["callback_query"];
+    let (Some(callback_id), Some(chat_id), Some(data)) = (
+        callback["id"].as_str(),
+        callback["message"]["chat"]["id"].as_i64(),
+        callback["data"].as_str(),
+    ) else {
+        return;
+    };
+    let Ok(conn) = pool.get() else {
+        return;
+    };
+    if !matches!(db::user_by_telegram_chat(&conn, chat_id), Ok(Some(_))) {
+        let _ = config.answer_callback(callback_id, "Чат не привязан к аккаунту");
+        return;
+    }
+    let Some((action, category_id, month)) = parse_callback(data) else {
+        let _ = config.answer_callback(callback_id, "Неизвестное действие");
+        return;
+    };
+
+    match action {
+        "tx" => {
+            let records = db::list_transactions(&conn, &TransactionQuery::month(month)).unwrap_or_default();
+            let lines = records
+                .iter()
+                .filter(|t| t.category_id == Some(category_id) && t.kind == "expense")
+                .take(15)
+                .map(|t| {
+                    format!(
+                        "{} {} {}",
+                        t.occurred_on,
+                        format_money(t.amount_cents),
+                        t.note.as_deref().unwrap_or("")
+                    )
+                })
+                .collect::<Vec<_>>();
+            let text = if lines.is_empty() {
+                format!("Нет расходов за {month}")
+            } else {
+                format!("Расходы за {month}:\n{}", lines.join("\n"))
+            };
+            let _ = config.answer_callback(callback_id, "");
+            let _ = config.send_message(chat_id, &text, None);
+        }
+        "bump" => {
+            let text = match db::scale_budget(&conn, category_id, month, 110) {
+                Ok(Some(total)) => format!("Бюджет увеличен до {}", format_money(total)),
+                Ok(None) => "Бюджет не найден".to_string(),
+                Err(_) => "Не удалось обновить бюджет".to_string(),
+            };
+            let _ = config.answer_callback(callback_id, &text);
+        }
+        _ => {
+            let _ = config.answer_callback(callback_id, "Неизвестное действие");
+        }
+    }
+}
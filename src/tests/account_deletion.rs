@@ -0,0 +1,129 @@
+use rocket::http::Status;
+
+use super::{PASSWORD, TestApp, USERNAME, location};
+use crate::account_deletion;
+use crate::db;
+use crate::receipt_storage::Local;
+
+fn count(app: &TestApp, sql: &str) -> i64 {
+    app.conn().query_row(sql, [], |row| row.get(0)).unwrap()
+}
+
+fn add_expense_with_receipt(app: &TestApp) -> std::path::PathBuf {
+    app.spend("250", "2026-03-10", &[]);
+    let name = format!("deletion-test-{}.jpg", uuid::Uuid::new_v4());
+    let dir = crate::receipts_dir();
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join(&name), b"not really a jpeg").unwrap();
+    app.conn()
+        .execute("UPDATE transactions SET receipt_path = ?1", [&name])
+        .unwrap();
+    dir.join(name)
+}
+
+#[test]
+fn wrong_password_keeps_the_account() {
+    let app = TestApp::logged_in();
+    let response = app.post_form("/settings/delete-account", &[("password", "not-it")]);
+    assert_eq!(response.status(), Status::Ok);
+    assert!(
+        response
+            .into_string()
+            .unwrap()
+            .contains("Пароль неверный, аккаунт не удален")
+    );
+    assert!(
+        db::user_credentials(&app.conn(), USERNAME)
+            .unwrap()
+            .is_some()
+    );
+    assert_eq!(app.get("/settings").status(), Status::Ok);
+}
+
+#[test]
+fn last_account_takes_the_household_with_it() {
+    let app = TestApp::logged_in();
+    let receipt = add_expense_with_receipt(&app);
+    let food_id = app.fixtures.food_id.to_string();
+    app.post_form(
+        "/budgets",
+        &[
+            ("category_id", &food_id),
+            ("month", "2026-03"),
+            ("amount", "1000"),
+        ],
+    );
+
+    let response = app.post_form("/settings/delete-account", &[("password", PASSWORD)]);
+    assert_eq!(response.status(), Status::SeeOther);
+    assert_eq!(location(&response), Some("/login"));
+    assert!(!db::has_users(&app.conn()).unwrap());
+    for table in [
+        "transactions",
+        "budgets",
+        "categories",
+        "accounts",
+        "sessions",
+    ] {
+        assert_eq!(
+            count(&app, &format!("SELECT COUNT(*) FROM {table}")),
+            0,
+            "{table}"
+        );
+    }
+    assert!(!receipt.exists());
+    assert_eq!(location(&app.get("/login")), Some("/setup"));
+}
+
+#[test]
+fn other_accounts_keep_the_household() {
+    let app = TestApp::logged_in();
+    let receipt = add_expense_with_receipt(&app);
+    let other_id =
+        db::insert_user(&app.conn(), "partner", "unused", "2026-01-01T00:00:00Z").unwrap();
+
+    let response = app.post_form("/settings/delete-account", &[("password", PASSWORD)]);
+    assert_eq!(response.status(), Status::SeeOther);
+    assert_eq!(db::user_ids(&app.conn()).unwrap(), vec![other_id]);
+    assert_eq!(count(&app, "SELECT COUNT(*) FROM transactions"), 1);
+    assert!(receipt.exists());
+    let _ = std::fs::remove_file(receipt);
+
+    // The browser is signed out.
+    assert_eq!(location(&app.get("/settings")), Some("/login"));
+}
+
+#[test]
+fn any_pooled_connection_deletes_the_logins_rows() {
+    let app = TestApp::logged_in();
+    let other_id =
+        db::insert_user(&app.conn(), "partner", "unused", "2026-01-01T00:00:00Z").unwrap();
+    db::insert_api_token(
+        &app.conn(),
+        other_id,
+        "Телефон",
+        "partner-token",
+        "read",
+        "2026-01-01T00:00:00Z",
+    )
+    .unwrap();
+    db::insert_login_alert(
+        &app.conn(),
+        "partner-alert",
+        other_id,
+        "Телефон",
+        "2026-01-01T00:00:00Z",
+    )
+    .unwrap();
+
+    let _first = app.conn();
+    let mut second = app.conn();
+    // As on a connection opened without the pragma.
+    second.execute_batch("PRAGMA foreign_keys = OFF").unwrap();
+    account_deletion::delete(&mut second, other_id, &Local::new(&crate::receipts_dir())).unwrap();
+    second.execute_batch("PRAGMA foreign_keys = ON").unwrap();
+    for table in ["api_tokens", "login_alerts"] {
+        let sql = format!("SELECT COUNT(*) FROM {table} WHERE user_id = {other_id}");
+        assert_eq!(count(&app, &sql), 0, "{table}");
+    }
+}
@@ -0,0 +1,46 @@
+use rocket::http::Status;
+use serde_json::Value;
+
+use super::TestApp;
+
+#[test]
+fn activity_export_lists_changes_in_range() {
+    let app = TestApp::logged_in();
+    app.conn()
+        .execute(
+            "INSERT INTO transactions (kind, amount_cents, occurred_on) VALUES ('expense', 1000, '2024-7-3')",
+            [],
+        )
+        .unwrap();
+    app.post_form("/settings/dates", &[]);
+    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+
+    let response = app.get(&format!("/activity/export?from={today}&to={today}"));
+    assert_eq!(response.status(), Status::Ok);
+    let csv = response.into_string().unwrap();
+    let row = csv.lines().nth(1).unwrap();
+    assert!(row.contains("date_repair"));
+    assert!(row.contains(",transactions,"));
+    assert!(row.contains(",update,"));
+    assert!(row.contains("2024-7-3"));
+
+    let response = app.get(&format!("/activity/export?format=json&from={today}"));
+    let entries: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    assert_eq!(entries.as_array().unwrap().len(), 1);
+    assert_eq!(entries[0]["action"], "update");
+
+    let earlier = app
+        .get("/activity/export?to=2000-01-01")
+        .into_string()
+        .unwrap();
+    assert_eq!(earlier.lines().count(), 1);
+    assert_eq!(
+        app.get("/activity/export?format=xml").status(),
+        Status::BadRequest
+    );
+    assert_eq!(
+        app.get("/activity/export?from=2024-13-01").status(),
+        Status::BadRequest
+    );
+    app.remove_downloads();
+}
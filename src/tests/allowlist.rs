@@ -0,0 +1,91 @@
+use std::net::{IpAddr, SocketAddr};
+
+use rocket::http::{Header, Status};
+
+use super::TestApp;
+use crate::allowlist::{Allowlist, Network, TrustedProxies};
+use crate::csrf;
+
+fn ip(value: &str) -> IpAddr {
+    value.parse().unwrap()
+}
+
+#[test]
+fn networks_match_their_prefix() {
+    let home: Network = "192.168.1.0/24".parse().unwrap();
+    assert!(home.contains(ip("192.168.1.77")));
+    assert!(!home.contains(ip("192.168.2.1")));
+    assert!(home.contains(ip("::ffff:192.168.1.5")));
+    assert!(!home.contains(ip("fe80::1")));
+
+    let host: Network = "::1".parse().unwrap();
+    assert!(host.contains(ip("::1")));
+    assert!(!host.contains(ip("::2")));
+    let everything: Network = "0.0.0.0/0".parse().unwrap();
+    assert!(everything.contains(ip("8.8.8.8")));
+
+    assert!("10.0.0.0/33".parse::<Network>().is_err());
+    assert!("router/24".parse::<Network>().is_err());
+}
+
+#[test]
+fn blank_setting_means_no_allowlist() {
+    assert!(Allowlist::parse("  ").is_none());
+    let list = Allowlist::parse("10.0.0.0/8, 127.0.0.1 bogus").unwrap();
+    assert_eq!(list.networks.len(), 2);
+    assert!(list.allows(ip("10.1.2.3")));
+    assert!(!list.allows(ip("11.0.0.1")));
+}
+
+#[test]
+fn maintenance_pages_refuse_outside_addresses() {
+    let app = TestApp::with_admin_networks(Allowlist::parse("192.168.1.0/24").unwrap());
+    app.login(super::USERNAME, super::PASSWORD);
+    let from = |path: &str, addr: &str| {
+        let remote: SocketAddr = addr.parse().unwrap();
+        app.client.get(path).remote(remote).dispatch().status()
+    };
+
+    assert_eq!(from("/settings/dates", "192.168.1.20:50000"), Status::Ok);
+    assert_eq!(from("/metrics/pool", "192.168.1.20:50000"), Status::Ok);
+    assert_eq!(
+        from("/settings/dates", "203.0.113.9:50000"),
+        Status::Forbidden
+    );
+    assert_eq!(
+        from("/metrics/pool", "203.0.113.9:50000"),
+        Status::Forbidden
+    );
+    // The session itself still works for everything else.
+    assert_eq!(from("/transactions", "203.0.113.9:50000"), Status::Ok);
+
+    let response = app
+        .client
+        .post("/settings/dates")
+        .header(Header::new(csrf::HEADER, app.csrf_token().unwrap()))
+        .remote("203.0.113.9:50000".parse().unwrap())
+        .dispatch();
+    assert_eq!(response.status(), Status::Forbidden);
+
+    // A header naming an inside address doesn't help from outside.
+    let response = app
+        .client
+        .get("/settings/dates")
+        .header(Header::new("X-Real-IP", "192.168.1.20"))
+        .remote("203.0.113.9:50000".parse().unwrap())
+        .dispatch();
+    assert_eq!(response.status(), Status::Forbidden);
+}
+
+#[test]
+fn only_trusted_proxies_name_the_client() {
+    let inside = Some(ip("192.168.1.20"));
+    let proxy = Some(ip("127.0.0.1"));
+    let outsider = Some(ip("203.0.113.9"));
+    assert_eq!(TrustedProxies(None).client(outsider, inside), outsider);
+
+    let proxies = TrustedProxies(Allowlist::parse("127.0.0.1"));
+    assert_eq!(proxies.client(proxy, inside), inside);
+    assert_eq!(proxies.client(proxy, None), proxy);
+    assert_eq!(proxies.client(outsider, inside), outsider);
+}
@@ -0,0 +1,376 @@
+use chrono::{Duration, Utc};
+use rocket::http::{ContentType, Header, Status};
+use rocket::local::blocking::LocalResponse;
+use serde_json::{Value, json};
+
+use super::{TestApp, location};
+use crate::api_sessions;
+use crate::db;
+use crate::query::TransactionQuery;
+
+/// Creates a token through the settings page and returns its value, shown
+/// once on the page it leads to.
+fn create_token(app: &TestApp, name: &str, scope: &str) -> String {
+    let response = app.post_form("/tokens", &[("name", name), ("scope", scope)]);
+    assert_eq!(location(&response), Some("/tokens"));
+    let page = app.get("/tokens").into_string().unwrap();
+    page.split("сохраните его сейчас: ")
+        .nth(1)
+        .and_then(|rest| rest.split(|c: char| !c.is_ascii_alphanumeric()).next())
+        .unwrap()
+        .to_string()
+}
+
+fn get_with<'a>(app: &'a TestApp, path: &str, token: &str) -> LocalResponse<'a> {
+    app.client
+        .get(path.to_string())
+        .header(Header::new("Authorization", format!("Bearer {token}")))
+        .dispatch()
+}
+
+fn post_transaction<'a>(app: &'a TestApp, token: &str, body: &str) -> LocalResponse<'a> {
+    app.client
+        .post("/api/v1/transactions")
+        .header(ContentType::JSON)
+        .header(Header::new("Authorization", format!("Bearer {token}")))
+        .body(body)
+        .dispatch()
+}
+
+fn transaction_count(app: &TestApp) -> usize {
+    db::list_transactions(&app.conn(), &TransactionQuery::default())
+        .unwrap()
+        .len()
+}
+
+#[test]
+fn read_token_lists_but_cannot_create() {
+    let app = TestApp::logged_in();
+    let token = create_token(&app, "Grafana", "read");
+
+    let response = get_with(&app, "/api/v1/transactions?month=2024-03", &token);
+    assert_eq!(response.status(), Status::Ok);
+    let body: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    assert_eq!(body["month"], "2024-03");
+
+    // The bearer token decides, even though the client also holds a session.
+    let body = r#"{"kind": "expense", "amount_cents": 1000}"#;
+    assert_eq!(
+        post_transaction(&app, &token, body).status(),
+        Status::Forbidden
+    );
+    assert_eq!(
+        get_with(&app, "/api/v1/tokens", &token).status(),
+        Status::Forbidden
+    );
+    assert_eq!(transaction_count(&app), 0);
+}
+
+#[test]
+fn write_token_creates_transactions() {
+    let app = TestApp::logged_in();
+    let token = create_token(&app, "Скрипт", "write");
+    let body = format!(
+        r#"{{"kind": "expense", "amount_cents": 45000, "category_id": {}, "occurred_on": "2024-03-05", "note": "Обед", "tags": "работа"}}"#,
+        app.fixtures.food_id
+    );
+
+    let response = post_transaction(&app, &token, &body);
+    assert_eq!(response.status(), Status::Created);
+    let created: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    assert!(created["id"].as_i64().is_some());
+
+    let listed = get_with(&app, "/api/v1/transactions?month=2024-03", &token);
+    let listed: Value = serde_json::from_str(&listed.into_string().unwrap()).unwrap();
+    let transactions = listed["transactions"].as_array().unwrap();
+    assert_eq!(transactions.len(), 1);
+    assert_eq!(transactions[0]["amount_cents"], 45000);
+    assert_eq!(transactions[0]["tags"], "работа");
+
+    let invalid = r#"{"kind": "transfer", "amount_cents": 100}"#;
+    assert_eq!(
+        post_transaction(&app, &token, invalid).status(),
+        Status::UnprocessableEntity
+    );
+    assert_eq!(
+        post_transaction(&app, &token, "not json").status(),
+        Status::BadRequest
+    );
+    assert_eq!(
+        get_with(&app, "/api/v1/tokens", &token).status(),
+        Status::Forbidden
+    );
+}
+
+#[test]
+fn admin_token_lists_tokens_without_values() {
+    let app = TestApp::logged_in();
+    let read = create_token(&app, "Grafana", "read");
+    let admin = create_token(&app, "Админ", "admin");
+
+    let response = get_with(&app, "/api/v1/tokens", &admin);
+    assert_eq!(response.status(), Status::Ok);
+    let body = response.into_string().unwrap();
+    assert!(!body.contains(&read));
+    let listed: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(listed["tokens"].as_array().unwrap().len(), 2);
+
+    let body = r#"{"kind": "income", "amount_cents": 100}"#;
+    assert_eq!(
+        post_transaction(&app, &admin, body).status(),
+        Status::Created
+    );
+}
+
+#[test]
+fn tokens_are_kept_as_hashes() {
+    let app = TestApp::logged_in();
+    let token = create_token(&app, "Grafana", "read");
+    let conn = app.conn();
+    let stored: String = conn
+        .query_row("SELECT token FROM api_tokens", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(stored, db::session_token_hash(&token));
+    assert!(!app.get("/tokens").into_string().unwrap().contains(&token));
+
+    // Tokens saved before hashing keep working once migrated.
+    conn.execute("UPDATE api_tokens SET token = 'legacy-token'", [])
+        .unwrap();
+    db::run_migrations(&conn).unwrap();
+    assert_eq!(
+        get_with(&app, "/api/v1/transactions", "legacy-token").status(),
+        Status::Ok
+    );
+}
+
+#[test]
+fn unknown_and_revoked_tokens_are_unauthorized() {
+    let app = TestApp::logged_in();
+    let token = create_token(&app, "Grafana", "read");
+    let (user_id, _) = db::user_credentials(&app.conn(), super::USERNAME)
+        .unwrap()
+        .unwrap();
+    let id = db::list_api_tokens(&app.conn(), user_id).unwrap()[0].id;
+    assert_eq!(
+        location(&app.post_form(&format!("/tokens/{id}/delete"), &[])),
+        Some("/tokens")
+    );
+
+    let path = "/api/v1/transactions";
+    assert_eq!(get_with(&app, path, &token).status(), Status::Unauthorized);
+    assert_eq!(get_with(&app, path, "nope").status(), Status::Unauthorized);
+    // Without a token the session is enough.
+    assert_eq!(app.get(path).status(), Status::Ok);
+    assert_eq!(TestApp::new().get(path).status(), Status::Unauthorized);
+}
+
+#[test]
+fn invalid_scope_is_rejected() {
+    let app = TestApp::logged_in();
+    let response = app.post_form("/tokens", &[("name", "Root"), ("scope", "root")]);
+    assert_eq!(response.status(), Status::BadRequest);
+    let page = app.get("/tokens").into_string().unwrap();
+    assert!(page.contains("Токенов пока нет"));
+}
+
+fn post_json<'a>(app: &'a TestApp, path: &str, body: Value) -> LocalResponse<'a> {
+    app.client
+        .post(path.to_string())
+        .header(ContentType::JSON)
+        .body(body.to_string())
+        .dispatch()
+}
+
+fn sign_in(app: &TestApp) -> Value {
+    let credentials = json!({ "username": super::USERNAME, "password": super::PASSWORD });
+    let response = post_json(app, "/api/v1/auth/token", credentials);
+    assert_eq!(response.status(), Status::Ok);
+    serde_json::from_str(&response.into_string().unwrap()).unwrap()
+}
+
+fn refresh<'a>(app: &'a TestApp, pair: &Value) -> LocalResponse<'a> {
+    let body = json!({ "refresh_token": pair["refresh_token"] });
+    post_json(app, "/api/v1/auth/refresh", body)
+}
+
+fn access_works(app: &TestApp, pair: &Value) -> bool {
+    let token = pair["access_token"].as_str().unwrap();
+    let status = get_with(app, "/api/v1/transactions", token).status();
+    assert!(matches!(status.code, 200 | 401), "unexpected {status}");
+    status == Status::Ok
+}
+
+#[test]
+fn refresh_rotates_the_pair() {
+    let app = TestApp::new();
+    let first = sign_in(&app);
+    assert_eq!(first["expires_in"], 900);
+    assert!(access_works(&app, &first));
+
+    let response = refresh(&app, &first);
+    assert_eq!(response.status(), Status::Ok);
+    let second: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    assert_ne!(second["refresh_token"], first["refresh_token"]);
+    assert!(access_works(&app, &second));
+    assert!(!access_works(&app, &first));
+
+    // Replaying the spent refresh token signs the whole chain out.
+    assert_eq!(refresh(&app, &first).status(), Status::Unauthorized);
+    assert!(!access_works(&app, &second));
+    assert_eq!(refresh(&app, &second).status(), Status::Unauthorized);
+}
+
+#[test]
+fn signed_in_clients_are_kept_as_hashes() {
+    let app = TestApp::new();
+    let pair = sign_in(&app);
+    let access = pair["access_token"].as_str().unwrap();
+    let refresh_token = pair["refresh_token"].as_str().unwrap();
+    let conn = app.conn();
+    let stored: (String, String) = conn
+        .query_row(
+            "SELECT access_token, refresh_token FROM api_sessions",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap();
+    assert_eq!(
+        stored,
+        (
+            db::session_token_hash(access),
+            db::session_token_hash(refresh_token)
+        )
+    );
+
+    // Pairs handed out before hashing keep working once migrated.
+    conn.execute(
+        "UPDATE api_sessions SET access_token = ?1, refresh_token = ?2",
+        [access, refresh_token],
+    )
+    .unwrap();
+    db::run_migrations(&conn).unwrap();
+    assert!(access_works(&app, &pair));
+    assert_eq!(refresh(&app, &pair).status(), Status::Ok);
+}
+
+#[test]
+fn access_tokens_expire_but_refresh_tokens_last() {
+    let app = TestApp::new();
+    let (user_id, _) = db::user_credentials(&app.conn(), super::USERNAME)
+        .unwrap()
+        .unwrap();
+    let signed_in = Utc::now() - Duration::hours(1);
+    let pair = api_sessions::sign_in(&app.conn(), user_id, signed_in).unwrap();
+    let pair = serde_json::to_value(pair).unwrap();
+    assert!(!access_works(&app, &pair));
+    assert_eq!(refresh(&app, &pair).status(), Status::Ok);
+
+    let long_ago = Utc::now() - api_sessions::REFRESH_TTL - Duration::hours(1);
+    let stale = api_sessions::sign_in(&app.conn(), user_id, long_ago).unwrap();
+    let refused = api_sessions::refresh(&mut app.conn(), &stale.refresh_token, Utc::now()).unwrap();
+    assert_eq!(refused.unwrap_err(), api_sessions::Refused::Expired);
+}
+
+#[test]
+fn revoked_clients_must_sign_in_again() {
+    let app = TestApp::new();
+    let bad = json!({ "username": super::USERNAME, "password": "wrong" });
+    assert_eq!(
+        post_json(&app, "/api/v1/auth/token", bad).status(),
+        Status::Unauthorized
+    );
+
+    let pair = sign_in(&app);
+    let body = json!({ "refresh_token": pair["refresh_token"] });
+    let response = post_json(&app, "/api/v1/auth/revoke", body);
+    assert_eq!(response.status(), Status::NoContent);
+    assert!(!access_works(&app, &pair));
+    assert_eq!(refresh(&app, &pair).status(), Status::Unauthorized);
+
+    // Signing out everywhere from the settings covers API clients too.
+    let pair = sign_in(&app);
+    assert_eq!(
+        app.login(super::USERNAME, super::PASSWORD).status(),
+        Status::SeeOther
+    );
+    let settings = app.get("/settings").into_string().unwrap();
+    assert!(settings.contains("Приложений, вошедших через API: 1"));
+    app.post_form("/settings/logout_all", &[]);
+    assert!(!access_works(&app, &pair));
+}
+#[test]
+fn api_stars_transactions_and_filters_by_the_star() {
+    let app = TestApp::logged_in();
+    let token = create_token(&app, "Скрипт", "write");
+    let starred = r#"{"kind": "expense", "amount_cents": 5000000, "occurred_on": "2024-03-05", "starred": true}"#;
+    let plain = r#"{"kind": "expense", "amount_cents": 30000, "occurred_on": "2024-03-06"}"#;
+    post_transaction(&app, &token, starred);
+    let response = post_transaction(&app, &token, plain);
+    let created: Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    let plain_id = created["id"].as_i64().unwrap();
+
+    let star = |id: i64, body: &str| {
+        app.client
+            .post(format!("/api/v1/transactions/{id}/star"))
+            .header(ContentType::JSON)
+            .header(Header::new("Authorization", format!("Bearer {token}")))
+            .body(body)
+            .dispatch()
+            .status()
+    };
+    assert_eq!(star(plain_id, r#"{"starred": true}"#), Status::NoContent);
+    assert_eq!(star(999, r#"{"starred": true}"#), Status::NotFound);
+    assert_eq!(star(plain_id, r#"{"starred": false}"#), Status::NoContent);
+
+    let listed = get_with(
+        &app,
+        "/api/v1/transactions?month=2024-03&starred=true",
+        &token,
+    );
+    let listed: Value = serde_json::from_str(&listed.into_string().unwrap()).unwrap();
+    let transactions = listed["transactions"].as_array().unwrap();
+    assert_eq!(transactions.len(), 1);
+    assert_eq!(transactions[0]["amount_cents"], 5000000);
+    assert_eq!(transactions[0]["starred"], true);
+}
+
+#[test]
+fn admin_token_downloads_a_backup_of_the_database() {
+    let app = TestApp::logged_in();
+    let admin = create_token(&app, "restic", "admin");
+    let read = create_token(&app, "Grafana", "read");
+    let body = r#"{"kind": "expense", "amount_cents": 1000, "occurred_on": "2024-03-05"}"#;
+    post_transaction(&app, &admin, body);
+
+    assert_eq!(
+        get_with(&app, "/api/v1/backup", &read).status(),
+        Status::Forbidden
+    );
+    let anonymous = TestApp::new();
+    assert_eq!(
+        anonymous.get("/api/v1/backup").status(),
+        Status::Unauthorized
+    );
+
+    let response = get_with(&app, "/api/v1/backup", &admin);
+    assert_eq!(response.status(), Status::Ok);
+    let disposition = response
+        .headers()
+        .get_one("Content-Disposition")
+        .unwrap()
+        .to_string();
+    assert!(disposition.starts_with("attachment; filename=\"lumen-"));
+    let bytes = response.into_bytes().unwrap();
+    assert!(bytes.starts_with(b"SQLite format 3\0"));
+
+    let name = format!("lumen-backup-{}.sqlite3", uuid::Uuid::new_v4());
+    let path = std::env::temp_dir().join(name);
+    std::fs::write(&path, &bytes).unwrap();
+    let copy = rusqlite::Connection::open(&path).unwrap();
+    let count: i64 = copy
+        .query_row("SELECT COUNT(*) FROM transactions", [], |row| row.get(0))
+        .unwrap();
+    drop(copy);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(count, 1);
+}
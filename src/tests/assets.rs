@@ -0,0 +1,73 @@
+use std::path::PathBuf;
+
+use rocket::http::Status;
+use rocket::local::blocking::Client;
+
+use super::TestApp;
+use crate::assets::Assets;
+
+fn static_dir(files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("lumen-assets-{}", uuid::Uuid::new_v4()));
+    for (name, contents) in files {
+        let path = dir.join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+    dir
+}
+
+#[test]
+fn fingerprinted_names_follow_the_content() {
+    let dir = static_dir(&[("style.css", "body {}"), ("img/logo.svg", "<svg/>")]);
+    let assets = Assets::load(&dir, true);
+    let url = assets.url("style.css");
+    let hash = url
+        .strip_prefix("/static/style.")
+        .and_then(|rest| rest.strip_suffix(".css"))
+        .unwrap();
+    assert_eq!(hash.len(), 16);
+    assert!(assets.url("img/logo.svg").starts_with("/static/img/logo."));
+    assert_eq!(assets.url("missing.css"), "/static/missing.css");
+    assert_eq!(Assets::load(&dir, true).url("style.css"), url);
+
+    std::fs::write(dir.join("style.css"), "body { color: red }").unwrap();
+    assert_ne!(Assets::load(&dir, true).url("style.css"), url);
+    assert_eq!(Assets::load(&dir, false).url("style.css"), "/static/style.css");
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn hashed_files_are_cached_for_good() {
+    let dir = static_dir(&[("style.css", "body {}")]);
+    let assets = Assets::load(&dir, true);
+    let url = assets.url("style.css");
+    let rocket = rocket::build()
+        .manage(assets)
+        .mount("/", rocket::routes![crate::static_file]);
+    let client = Client::tracked(rocket).unwrap();
+
+    let response = client.get(url).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        response.headers().get_one("Cache-Control"),
+        Some("public, max-age=31536000, immutable")
+    );
+    assert_eq!(response.into_string().as_deref(), Some("body {}"));
+    let response = client.get("/static/style.css").dispatch();
+    assert_eq!(response.headers().get_one("Cache-Control"), Some("no-cache"));
+    let stale = client.get("/static/style.0000000000000000.css").dispatch();
+    assert_eq!(stale.status(), Status::NotFound);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn plain_static_names_are_revalidated() {
+    let app = TestApp::new();
+    let body = app.get("/login").into_string().unwrap();
+    assert!(body.contains(r#"href="/static/style.css""#));
+
+    let response = app.get("/static/style.css");
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.headers().get_one("Cache-Control"), Some("no-cache"));
+    assert_eq!(app.get("/static/missing.css").status(), Status::NotFound);
+}
@@ -0,0 +1,176 @@
+use chrono::Utc;
+use rocket::http::{ContentType, Header, Status};
+
+use super::{PASSWORD, TestApp, USERNAME, location};
+use crate::db;
+use crate::login_alert;
+
+#[test]
+fn empty_database_redirects_to_setup() {
+    let app = TestApp::empty();
+    let response = app.get("/transactions");
+    assert_eq!(response.status(), Status::SeeOther);
+    assert_eq!(location(&response), Some("/setup"));
+}
+
+#[test]
+fn setup_creates_first_user_and_logs_in() {
+    let app = TestApp::empty();
+    let response = app.post_form(
+        "/setup",
+        &[
+            ("username", "owner"),
+            ("password", "зеленый чайник 47"),
+            ("confirm_password", "зеленый чайник 47"),
+        ],
+    );
+    assert_eq!(response.status(), Status::SeeOther);
+    assert!(db::has_users(&app.conn()).unwrap());
+
+    let body = app.get("/").into_string().unwrap();
+    assert!(body.contains("owner"));
+}
+
+#[test]
+fn setup_rejects_mismatched_passwords() {
+    let app = TestApp::empty();
+    let response = app.post_form(
+        "/setup",
+        &[
+            ("username", "owner"),
+            ("password", "secret12"),
+            ("confirm_password", "secret13"),
+        ],
+    );
+    assert_eq!(response.status(), Status::Ok);
+    assert!(
+        response
+            .into_string()
+            .unwrap()
+            .contains("Пароли не совпадают")
+    );
+    assert!(!db::has_users(&app.conn()).unwrap());
+}
+
+#[test]
+fn pages_require_login() {
+    let app = TestApp::new();
+    for path in ["/", "/transactions", "/budgets", "/reports", "/settings"] {
+        let response = app.get(path);
+        assert_eq!(response.status(), Status::SeeOther, "{path}");
+        assert_eq!(location(&response), Some("/login"), "{path}");
+    }
+}
+
+#[test]
+fn wrong_password_is_rejected() {
+    let app = TestApp::new();
+    let response = app.login(USERNAME, "wrong-password");
+    assert_eq!(response.status(), Status::Ok);
+    assert!(
+        response
+            .into_string()
+            .unwrap()
+            .contains("Неверный логин или пароль")
+    );
+    assert_eq!(location(&app.get("/")), Some("/login"));
+}
+
+#[test]
+fn login_and_logout() {
+    let app = TestApp::new();
+    let response = app.login(USERNAME, PASSWORD);
+    assert_eq!(response.status(), Status::SeeOther);
+    assert_eq!(location(&response), Some("/"));
+
+    let response = app.get("/");
+    assert_eq!(response.status(), Status::Ok);
+    assert!(response.into_string().unwrap().contains(USERNAME));
+
+    app.post_form("/logout", &[]);
+    assert_eq!(location(&app.get("/")), Some("/login"));
+}
+
+#[test]
+fn pool_metrics_need_login_and_count_checkouts() {
+    let app = TestApp::new();
+    assert_eq!(location(&app.get("/metrics/pool")), Some("/login"));
+
+    app.login(USERNAME, PASSWORD);
+    let response = app.get("/metrics/pool");
+    assert_eq!(response.status(), Status::Ok);
+    let stats: serde_json::Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    assert!(stats["checkouts"].as_u64().unwrap() > 0);
+    assert_eq!(stats["timeouts"], 0);
+    assert_eq!(stats["max_size"], 10);
+}
+
+#[test]
+fn login_from_new_device_offers_a_revoke_link() {
+    let app = TestApp::logged_in();
+    let (user_id, _) = db::user_credentials(&app.conn(), USERNAME)
+        .unwrap()
+        .unwrap();
+    let page = app.post_form("/settings/email", &[("email", "not an address")]);
+    assert!(page.into_string().unwrap().contains("Некорректный email"));
+    app.post_form("/settings/email", &[("email", "owner@example.com")]);
+    assert_eq!(
+        db::user_email(&app.conn(), user_id).unwrap().as_deref(),
+        Some("owner@example.com")
+    );
+
+    let body = format!("username={USERNAME}&password={PASSWORD}");
+    let response = app
+        .client
+        .post("/login")
+        .header(ContentType::Form)
+        .header(Header::new("User-Agent", "Firefox на ноутбуке"))
+        .body(body)
+        .dispatch();
+    assert_eq!(response.status(), Status::SeeOther);
+    assert_eq!(db::known_device_count(&app.conn(), user_id).unwrap(), 2);
+
+    let now = Utc::now();
+    let check = |agent: &str, mail: bool| {
+        login_alert::check(&app.conn(), mail, user_id, USERNAME, agent, now).unwrap()
+    };
+    assert!(check("Firefox на ноутбуке", true).is_none());
+    assert!(check("Телефон", false).is_none());
+    let alert = check("Планшет", true).unwrap();
+    assert_eq!(alert.to, "owner@example.com");
+
+    db::insert_api_token(
+        &app.conn(),
+        user_id,
+        "Телефон",
+        "phone-token",
+        "read",
+        "2026-01-01T00:00:00Z",
+    )
+    .unwrap();
+
+    // Opening the link only asks, so a mail scanner following it changes nothing.
+    let page = app.get(&alert.revoke_path()).into_string().unwrap();
+    assert!(page.contains(&format!("action=\"{}\"", alert.revoke_path())));
+    assert_eq!(app.get("/").status(), Status::Ok);
+
+    let page = app.post_form(&alert.revoke_path(), &[]).into_string().unwrap();
+    assert!(page.contains("Все сессии завершены"));
+    assert_eq!(location(&app.get("/")), Some("/login"));
+    assert!(db::list_api_tokens(&app.conn(), user_id).unwrap().is_empty());
+    let page = app.post_form(&alert.revoke_path(), &[]).into_string().unwrap();
+    assert!(page.contains("Ссылка недействительна"));
+}
+#[test]
+fn unknown_username_is_rejected_like_a_wrong_password() {
+    let app = TestApp::new();
+    let unknown = app.login("nobody", PASSWORD).into_string().unwrap();
+    let wrong = app.login(USERNAME, "wrong-password").into_string().unwrap();
+    assert!(unknown.contains("Неверный логин или пароль"));
+    assert_eq!(unknown, wrong);
+
+    let creds = db::user_credentials(&app.conn(), USERNAME).unwrap();
+    let user_id = creds.as_ref().map(|(id, _)| *id);
+    assert_eq!(crate::verify_login(creds, PASSWORD), user_id);
+    assert_eq!(crate::verify_login(None, PASSWORD), None);
+}
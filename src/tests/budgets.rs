@@ -0,0 +1,415 @@
+use chrono::NaiveDate;
+use rocket::http::Status;
+
+use super::TestApp;
+use crate::disputes::RefundMonth;
+use crate::{db, indexation};
+
+#[test]
+fn budget_percent_rounds_and_handles_edges() {
+    assert_eq!(crate::budget_percent(0, 0), 0);
+    assert_eq!(crate::budget_percent(500, 0), 0);
+    assert_eq!(crate::budget_percent(0, 10_000), 0);
+    assert_eq!(crate::budget_percent(3_333, 10_000), 33);
+    assert_eq!(crate::budget_percent(6_667, 10_000), 67);
+    assert_eq!(crate::budget_percent(10_000, 10_000), 100);
+    assert_eq!(crate::budget_percent(15_000, 10_000), 150);
+}
+
+#[test]
+fn spent_counts_only_the_months_category_expenses() {
+    let app = TestApp::logged_in();
+    let conn = app.conn();
+    let food_id = app.fixtures.food_id;
+    db::insert_budget(&conn, food_id, "2026-03", 10_000, false).unwrap();
+    app.spend("40", "2026-03-02", &[]);
+    app.spend("25", "2026-03-31", &[]);
+    app.spend("90", "2026-04-01", &[]);
+    app.spend("70", "2026-03-10", &[("category_id", "")]);
+    let salary_id = app.fixtures.salary_id.to_string();
+    app.spend(
+        "10",
+        "2026-03-05",
+        &[("kind", "income"), ("category_id", &salary_id)],
+    );
+
+    let budgets = db::dashboard_budgets(&conn, "2026-03", RefundMonth::Refund).unwrap();
+    assert_eq!(budgets.len(), 1);
+    assert_eq!(budgets[0].budget_cents, 10_000);
+    assert_eq!(budgets[0].spent_cents, 6_500);
+    assert_eq!(budgets[0].remaining_cents, 3_500);
+
+    let listed = db::list_budgets(&conn, "2026-03", RefundMonth::Refund).unwrap();
+    assert_eq!(listed[0].spent_cents, 6_500);
+    assert!(
+        db::dashboard_budgets(&conn, "2026-05", RefundMonth::Refund)
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn overspent_budget_shows_negative_remaining() {
+    let app = TestApp::logged_in();
+    let food_id = app.fixtures.food_id.to_string();
+    let response = app.post_form(
+        "/budgets",
+        &[
+            ("category_id", &food_id),
+            ("month", "2026-03"),
+            ("amount", "100"),
+        ],
+    );
+    assert_eq!(response.status(), Status::SeeOther);
+    app.post_form(
+        "/transactions",
+        &[
+            ("kind", "expense"),
+            ("amount", "150"),
+            ("category_id", &food_id),
+            ("occurred_on", "2026-03-14"),
+        ],
+    );
+
+    let budgets = db::dashboard_budgets(&app.conn(), "2026-03", RefundMonth::Refund).unwrap();
+    assert_eq!(budgets[0].remaining_cents, -5_000);
+
+    let response = app.get("/budgets?month=2026-03");
+    assert_eq!(response.status(), Status::Ok);
+    assert!(response.into_string().unwrap().contains("width: 150%"));
+}
+
+#[test]
+fn budget_rejects_bad_amount() {
+    let app = TestApp::logged_in();
+    let food_id = app.fixtures.food_id.to_string();
+    let response = app.post_form(
+        "/budgets",
+        &[
+            ("category_id", &food_id),
+            ("month", "2026-03"),
+            ("amount", "-1"),
+        ],
+    );
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+}
+
+#[test]
+fn resubmitting_a_budget_replaces_the_amount() {
+    let app = TestApp::logged_in();
+    let food_id = app.fixtures.food_id.to_string();
+    for amount in ["100", "100", "250"] {
+        let response = app.post_form(
+            "/budgets",
+            &[
+                ("category_id", &food_id),
+                ("month", "2026-03"),
+                ("amount", amount),
+            ],
+        );
+        assert_eq!(response.status(), Status::SeeOther);
+    }
+
+    let budgets = db::list_budgets(&app.conn(), "2026-03", RefundMonth::Refund).unwrap();
+    assert_eq!(budgets.len(), 1);
+    assert_eq!(budgets[0].amount_cents, 25_000);
+}
+
+#[test]
+fn copy_from_previous_month_skips_existing_budgets() {
+    let app = TestApp::logged_in();
+    let conn = app.conn();
+    let food_id = app.fixtures.food_id;
+    db::insert_category(&conn, "Кафе", "expense", None).unwrap();
+    let cafe_id = conn.last_insert_rowid();
+    db::insert_budget(&conn, food_id, "2026-02", 10_000, false).unwrap();
+    db::insert_budget(&conn, cafe_id, "2026-02", 3_000, false).unwrap();
+    db::insert_budget(&conn, food_id, "2026-03", 12_000, false).unwrap();
+
+    for _ in 0..2 {
+        let response = app.post_form("/budgets/copy", &[("month", "2026-03")]);
+        assert_eq!(response.status(), Status::SeeOther);
+    }
+    let amounts = db::list_budgets(&conn, "2026-03", RefundMonth::Refund)
+        .unwrap()
+        .iter()
+        .map(|budget| (budget.category_id, budget.amount_cents))
+        .collect::<Vec<_>>();
+    assert_eq!(amounts, [(food_id, 12_000), (cafe_id, 3_000)]);
+
+    let response = app.post_form("/budgets/copy", &[("month", "март")]);
+    assert_eq!(response.status(), Status::BadRequest);
+}
+
+#[test]
+fn rollover_carries_remainder_and_overspend_forward() {
+    let app = TestApp::logged_in();
+    let conn = app.conn();
+    let food_id = app.fixtures.food_id;
+    for (month, rollover, spent) in [
+        ("2026-01", true, "30"),
+        ("2026-02", true, "250"),
+        ("2026-03", false, ""),
+        ("2026-04", true, "10"),
+    ] {
+        db::insert_budget(&conn, food_id, month, 10_000, rollover).unwrap();
+        if !spent.is_empty() {
+            app.spend(spent, &format!("{month}-10"), &[]);
+        }
+    }
+
+    let carried =
+        |month: &str| db::list_budgets(&conn, month, RefundMonth::Refund).unwrap()[0].carried_cents;
+    assert_eq!(carried("2026-01"), 0);
+    assert_eq!(carried("2026-02"), 7_000);
+    assert_eq!(carried("2026-03"), -8_000);
+    // March doesn't roll over, so April starts fresh.
+    assert_eq!(carried("2026-04"), 0);
+
+    // A month without a budget breaks the chain as well.
+    db::insert_budget(&conn, food_id, "2026-06", 10_000, false).unwrap();
+    assert_eq!(carried("2026-06"), 0);
+    db::insert_budget(&conn, food_id, "2026-05", 10_000, false).unwrap();
+    db::insert_budget(&conn, food_id, "2026-05", 10_000, true).unwrap();
+    assert_eq!(carried("2026-06"), 19_000);
+
+    let dashboard = db::dashboard_budgets(&conn, "2026-03", RefundMonth::Refund).unwrap();
+    assert_eq!(dashboard[0].carried_cents, -8_000);
+    assert_eq!(dashboard[0].remaining_cents, 2_000);
+}
+
+#[test]
+fn rollover_checkbox_is_saved_and_shown() {
+    let app = TestApp::logged_in();
+    let food_id = app.fixtures.food_id.to_string();
+    for (month, rollover) in [("2026-02", "true"), ("2026-03", "")] {
+        let mut fields = vec![
+            ("category_id", food_id.as_str()),
+            ("month", month),
+            ("amount", "100"),
+        ];
+        if !rollover.is_empty() {
+            fields.push(("rollover", rollover));
+        }
+        assert_eq!(
+            app.post_form("/budgets", &fields).status(),
+            Status::SeeOther
+        );
+    }
+
+    let conn = app.conn();
+    assert!(db::list_budgets(&conn, "2026-02", RefundMonth::Refund).unwrap()[0].rollover);
+    let march = &db::list_budgets(&conn, "2026-03", RefundMonth::Refund).unwrap()[0];
+    assert!(!march.rollover);
+    assert_eq!(march.carried_cents, 10_000);
+
+    let body = app.get("/budgets?month=2026-03").into_string().unwrap();
+    assert!(body.contains("200.00"));
+    assert!(body.contains("перенос 100.00"));
+}
+
+#[test]
+fn standing_budget_fills_months_without_their_own() {
+    let app = TestApp::logged_in();
+    let food_id = app.fixtures.food_id.to_string();
+    let response = app.post_form(
+        "/budgets",
+        &[
+            ("category_id", &food_id),
+            ("month", "2026-03"),
+            ("amount", "500"),
+            ("standing", "true"),
+        ],
+    );
+    assert_eq!(response.status(), Status::SeeOther);
+    let conn = app.conn();
+    db::insert_budget(&conn, app.fixtures.food_id, "2026-04", 20_000, false).unwrap();
+    app.spend("70", "2026-05-02", &[]);
+
+    let limit = |month: &str| {
+        let budgets = db::list_budgets(&conn, month, RefundMonth::Refund).unwrap();
+        assert_eq!(budgets.len(), 1, "{month}");
+        (budgets[0].amount_cents, budgets[0].standing)
+    };
+    assert_eq!(limit("2025-11"), (50_000, true));
+    assert_eq!(limit("2026-04"), (20_000, false));
+    assert_eq!(limit("2026-05"), (50_000, true));
+    let dashboard = db::dashboard_budgets(&conn, "2026-05", RefundMonth::Refund).unwrap();
+    assert_eq!(dashboard[0].remaining_cents, 43_000);
+
+    // Raising a month the standing budget fills gives it a budget of its own.
+    assert_eq!(
+        db::scale_budget(&conn, app.fixtures.food_id, "2026-05", 110).unwrap(),
+        Some(55_000)
+    );
+    assert_eq!(limit("2026-05"), (55_000, false));
+    assert_eq!(limit("2026-06"), (50_000, true));
+
+    let response = app.post_form(
+        "/budgets",
+        &[
+            ("category_id", &food_id),
+            ("month", "2026-03"),
+            ("amount", "500"),
+            ("standing", "true"),
+            ("rollover", "true"),
+        ],
+    );
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+
+    let standing = db::list_standing_budgets(&conn).unwrap();
+    let response = app.post_form(&format!("/budgets/standing/{}/delete", standing[0].id), &[]);
+    assert_eq!(response.status(), Status::SeeOther);
+    assert!(
+        db::list_budgets(&conn, "2026-06", RefundMonth::Refund)
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn standing_budgets_grow_once_per_fiscal_year_and_can_be_undone() {
+    let app = TestApp::logged_in();
+    let mut conn = app.conn();
+    db::set_standing_budget(&conn, app.fixtures.food_id, 30_000).unwrap();
+    db::insert_category(&conn, "Кафе", "expense", None).unwrap();
+    let cafe_id = conn.last_insert_rowid();
+    db::set_standing_budget(&conn, cafe_id, 10_000).unwrap();
+    let standing = |conn: &rusqlite::Connection| {
+        db::list_standing_budgets(conn)
+            .unwrap()
+            .into_iter()
+            .map(|budget| (budget.category_name, budget.amount_cents))
+            .collect::<Vec<_>>()
+    };
+    let food = db::list_standing_budgets(&conn)
+        .unwrap()
+        .into_iter()
+        .find(|budget| budget.category_id == app.fixtures.food_id)
+        .unwrap();
+    let path = format!("/budgets/standing/{}/increase", food.id);
+    for bad in ["abc", "0", "-3", "150"] {
+        let response = app.post_form(&path, &[("percent", bad)]);
+        assert_eq!(response.status(), Status::BadRequest, "{bad}");
+    }
+    let response = app.post_form("/budgets/standing/9999/increase", &[("percent", "5")]);
+    assert_eq!(response.status(), Status::NotFound);
+    let response = app.post_form(&path, &[("percent", "7,5")]);
+    assert_eq!(response.status(), Status::SeeOther);
+
+    // The fiscal year starts in April: March 2027 still belongs to 2026.
+    let schedule = indexation::Schedule { start_month: 4 };
+    let day = |year, month| NaiveDate::from_ymd_opt(year, month, 10).unwrap();
+    assert_eq!(schedule.fiscal_year(day(2027, 3)), 2026);
+    assert_eq!(schedule.fiscal_year(day(2027, 4)), 2027);
+
+    let raised = indexation::apply(&mut conn, schedule, day(2027, 4)).unwrap();
+    assert_eq!(raised, [("Еда".to_string(), 30_000, 32_250)]);
+    assert_eq!(
+        standing(&conn),
+        [("Еда".to_string(), 32_250), ("Кафе".to_string(), 10_000)]
+    );
+    // Later checks in the same fiscal year leave it alone.
+    assert!(
+        indexation::apply(&mut conn, schedule, day(2028, 3))
+            .unwrap()
+            .is_empty()
+    );
+    assert_eq!(standing(&conn)[0].1, 32_250);
+
+    let operations = db::list_bulk_operations(&conn, 10).unwrap();
+    assert_eq!(operations[0].kind, "budget_increase");
+    let response = app.post_form(&format!("/activity/{}/undo", operations[0].id), &[]);
+    assert_eq!(response.status(), Status::SeeOther);
+    assert_eq!(standing(&conn)[0].1, 30_000);
+    assert!(
+        indexation::apply(&mut conn, schedule, day(2027, 12))
+            .unwrap()
+            .is_empty()
+    );
+    assert_eq!(standing(&conn)[0].1, 30_000);
+
+    let next = indexation::apply(&mut conn, schedule, day(2028, 4)).unwrap();
+    assert_eq!(next, [("Еда".to_string(), 30_000, 32_250)]);
+    let response = app.post_form(&path, &[("percent", "")]);
+    assert_eq!(response.status(), Status::SeeOther);
+    assert!(
+        indexation::apply(&mut conn, schedule, day(2029, 4))
+            .unwrap()
+            .is_empty()
+    );
+    assert_eq!(standing(&conn)[0].1, 32_250);
+}
+
+#[test]
+fn budget_note_is_edited_inline_and_shown_in_the_overspend_alert() {
+    let app = TestApp::logged_in();
+    let conn = app.conn();
+    let month = crate::current_month();
+    db::insert_budget(&conn, app.fixtures.food_id, &month, 100_000, false).unwrap();
+    let id = db::list_budgets(&conn, &month, RefundMonth::Refund).unwrap()[0].id;
+
+    let note = "не больше 2 походов в ресторан";
+    let response = app.post_form(&format!("/budgets/{id}/note"), &[("note", note)]);
+    assert_eq!(response.status(), Status::SeeOther);
+    assert_eq!(
+        db::list_budgets(&conn, &month, RefundMonth::Refund).unwrap()[0]
+            .note
+            .as_deref(),
+        Some(note)
+    );
+    let body = app.get("/budgets").into_string().unwrap();
+    assert!(body.contains(note));
+
+    app.spend("1500", &format!("{month}-01"), &[]);
+    let (user_id, _) = db::user_credentials(&conn, super::USERNAME)
+        .unwrap()
+        .unwrap();
+    let alerts = db::list_notifications(&conn, user_id, Some("budget_exceeded")).unwrap();
+    assert_eq!(alerts.len(), 1);
+    assert!(alerts[0].body.ends_with(&format!("Цель: {note}")));
+
+    let next = db::copy_budgets(&conn, &month, "2099-01").unwrap();
+    assert_eq!(next.len(), 1);
+    assert_eq!(
+        db::list_budgets(&conn, "2099-01", RefundMonth::Refund).unwrap()[0]
+            .note
+            .as_deref(),
+        Some(note)
+    );
+
+    let response = app.post_form(&format!("/budgets/{id}/note"), &[("note", "  ")]);
+    assert_eq!(response.status(), Status::SeeOther);
+    assert_eq!(
+        db::list_budgets(&conn, &month, RefundMonth::Refund).unwrap()[0].note,
+        None
+    );
+}
+
+#[test]
+fn standing_budget_note_applies_to_the_months_it_fills() {
+    let app = TestApp::logged_in();
+    let conn = app.conn();
+    db::set_standing_budget(&conn, app.fixtures.food_id, 50_000).unwrap();
+    let id = db::list_standing_budgets(&conn).unwrap()[0].id;
+
+    let response = app.post_form(
+        &format!("/budgets/standing/{id}/note"),
+        &[("note", "готовить дома")],
+    );
+    assert_eq!(response.status(), Status::SeeOther);
+    let budget = &db::list_budgets(&conn, "2026-05", RefundMonth::Refund).unwrap()[0];
+    assert!(budget.standing);
+    assert_eq!(budget.note.as_deref(), Some("готовить дома"));
+
+    let long = "а".repeat(201);
+    app.post_form(&format!("/budgets/standing/{id}/note"), &[("note", &long)]);
+    assert_eq!(
+        db::list_budgets(&conn, "2026-05", RefundMonth::Refund).unwrap()[0]
+            .note
+            .as_deref(),
+        Some("готовить дома")
+    );
+    let response = app.post_form("/budgets/999999/note", &[("note", "x")]);
+    assert_eq!(response.status(), Status::NotFound);
+}
@@ -0,0 +1,114 @@
+use rocket::http::Status;
+
+use super::{TestApp, location};
+use crate::cash::looks_like_withdrawal;
+use crate::db;
+use crate::query::TransactionQuery;
+
+fn balance(app: &TestApp, id: i64) -> i64 {
+    let accounts = db::list_accounts(&app.conn()).unwrap();
+    accounts
+        .iter()
+        .find(|account| account.id == id)
+        .unwrap()
+        .balance_cents
+}
+
+#[test]
+fn withdrawal_notes_are_recognized() {
+    assert!(looks_like_withdrawal("ATM 0042 MOSCOW"));
+    assert!(looks_like_withdrawal("Снятие наличных в банкомате"));
+    assert!(looks_like_withdrawal("Выдача наличных"));
+    assert!(!looks_like_withdrawal("Batman figure"));
+    assert!(!looks_like_withdrawal("Пятерочка"));
+}
+
+#[test]
+fn withdrawal_moves_money_into_the_cash_envelope() {
+    let app = TestApp::logged_in();
+    let (card, cash) = (app.fixtures.card_id, app.fixtures.cash_id);
+    let withdraw = |from: i64, fee: &str| {
+        app.post_form(
+            "/cash/withdrawals",
+            &[
+                ("from_account_id", &from.to_string()),
+                ("to_account_id", &cash.to_string()),
+                ("amount", "3000"),
+                ("fee", fee),
+                ("occurred_on", &crate::today_ymd()),
+            ],
+        )
+    };
+    let from_cash = withdraw(cash, "");
+    assert_eq!(from_cash.status(), Status::UnprocessableEntity);
+    assert!(
+        from_cash
+            .into_string()
+            .unwrap()
+            .contains("Выберите карту или счет")
+    );
+
+    assert_eq!(location(&withdraw(card, "100")), Some("/cash"));
+    assert_eq!(balance(&app, card), 100_000 - 300_000 - 10_000);
+    assert_eq!(balance(&app, cash), 300_000);
+    let transactions = db::list_transactions(&app.conn(), &TransactionQuery::default()).unwrap();
+    let kinds = transactions
+        .iter()
+        .map(|t| t.kind.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(kinds.iter().filter(|kind| **kind == "expense").count(), 1);
+    assert_eq!(kinds.iter().filter(|kind| **kind == "transfer").count(), 1);
+
+    let (today, cash_id) = (crate::today_ymd(), cash.to_string());
+    app.spend(
+        "500",
+        &today,
+        &[("payee", "Рынок"), ("account_id", &cash_id)],
+    );
+    assert_eq!(balance(&app, cash), 250_000);
+    let page = app.get("/cash").into_string().unwrap();
+    assert!(page.contains("3000.00"));
+    assert!(page.contains("2500.00"));
+}
+
+#[test]
+fn expense_entered_for_a_withdrawal_is_converted() {
+    let app = TestApp::logged_in();
+    let (card, cash) = (app.fixtures.card_id, app.fixtures.cash_id);
+    let (today, card_id) = (crate::today_ymd(), card.to_string());
+    app.spend(
+        "2000",
+        &today,
+        &[("payee", "ATM Сбербанк"), ("account_id", &card_id)],
+    );
+    app.spend(
+        "700",
+        &today,
+        &[("payee", "Пятерочка"), ("account_id", &card_id)],
+    );
+    let page = app.get("/cash").into_string().unwrap();
+    assert!(page.contains("ATM Сбербанк"));
+    assert!(!page.contains("Пятерочка"));
+
+    let transactions = db::list_transactions(&app.conn(), &TransactionQuery::default()).unwrap();
+    let atm = transactions
+        .iter()
+        .find(|t| t.payee.as_deref() == Some("ATM Сбербанк"))
+        .unwrap();
+    let convert = || {
+        app.post_form(
+            &format!("/cash/convert/{}", atm.id),
+            &[("to_account_id", &cash.to_string())],
+        )
+    };
+    assert_eq!(location(&convert()), Some("/cash"));
+    assert_eq!(balance(&app, cash), 200_000);
+    assert_eq!(balance(&app, card), 100_000 - 200_000 - 70_000);
+    assert_eq!(convert().status(), Status::BadRequest);
+    assert!(
+        !app.get("/cash")
+            .into_string()
+            .unwrap()
+            .contains("ATM Сбербанк")
+    );
+}
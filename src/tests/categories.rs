@@ -0,0 +1,516 @@
+use rocket::http::Status;
+
+use super::TestApp;
+use crate::db;
+use crate::disputes::RefundMonth;
+use crate::models::NewTransaction;
+use crate::query::TransactionQuery;
+
+fn add_child(app: &TestApp, name: &str, kind: &str, parent_id: i64) -> Status {
+    let parent_id = parent_id.to_string();
+    app.post_form(
+        "/categories",
+        &[("name", name), ("kind", kind), ("parent_id", &parent_id)],
+    )
+    .status()
+}
+
+fn category_id(app: &TestApp, name: &str) -> i64 {
+    db::list_categories(&app.conn())
+        .unwrap()
+        .into_iter()
+        .find(|category| category.name == name)
+        .map(|category| category.id)
+        .unwrap()
+}
+
+#[test]
+fn children_follow_their_parent() {
+    let app = TestApp::logged_in();
+    let food_id = app.fixtures.food_id;
+    assert_eq!(
+        add_child(&app, "Продукты", "expense", food_id),
+        Status::SeeOther
+    );
+    assert_eq!(
+        add_child(&app, "Кафе", "expense", food_id),
+        Status::SeeOther
+    );
+    app.post_form("/categories", &[("name", "Авто"), ("kind", "expense")]);
+
+    let names = db::list_categories(&app.conn())
+        .unwrap()
+        .into_iter()
+        .filter(|category| category.kind == "expense")
+        .map(|category| (category.name, category.parent_id))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        [
+            ("Авто".to_string(), None),
+            ("Еда".to_string(), None),
+            ("Кафе".to_string(), Some(food_id)),
+            ("Продукты".to_string(), Some(food_id)),
+        ]
+    );
+}
+
+#[test]
+fn nesting_is_one_level_and_keeps_kind() {
+    let app = TestApp::logged_in();
+    let food_id = app.fixtures.food_id;
+    assert_eq!(
+        add_child(&app, "Зарплата+", "income", food_id),
+        Status::UnprocessableEntity
+    );
+    assert_eq!(
+        add_child(&app, "Кафе", "expense", food_id),
+        Status::SeeOther
+    );
+    let cafe_id = category_id(&app, "Кафе");
+    assert_eq!(
+        add_child(&app, "Кофе", "expense", cafe_id),
+        Status::UnprocessableEntity
+    );
+    assert_eq!(
+        add_child(&app, "Кофе", "expense", 9_999),
+        Status::UnprocessableEntity
+    );
+}
+
+#[test]
+fn report_rolls_children_into_parent() {
+    let app = TestApp::logged_in();
+    let food_id = app.fixtures.food_id;
+    add_child(&app, "Кафе", "expense", food_id);
+    add_child(&app, "Продукты", "expense", food_id);
+    app.post_form("/categories", &[("name", "Авто"), ("kind", "expense")]);
+    let conn = app.conn();
+    for (category, amount_cents) in [
+        ("Еда", 1_000),
+        ("Кафе", 2_500),
+        ("Продукты", 4_000),
+        ("Авто", 6_000),
+    ] {
+        let transaction = NewTransaction {
+            kind: "expense".to_string(),
+            amount_cents,
+            category_id: Some(category_id(&app, category)),
+            occurred_on: "2026-03-10".to_string(),
+            note: None,
+            payee: None,
+            account_id: None,
+            to_account_id: None,
+        };
+        db::insert_transaction(&conn, &transaction, None).unwrap();
+    }
+
+    let rows = db::report_categories(&conn, "2026-03", RefundMonth::Refund)
+        .unwrap()
+        .into_iter()
+        .map(|row| (row.category_name, row.parent_name, row.expense_cents))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rows,
+        [
+            ("Еда".to_string(), None, 7_500),
+            ("Продукты".to_string(), Some("Еда".to_string()), 4_000),
+            ("Кафе".to_string(), Some("Еда".to_string()), 2_500),
+            ("Авто".to_string(), None, 6_000),
+        ]
+    );
+}
+
+fn seed_spending(app: &TestApp, category_id: i64, month: &str, budget_cents: i64) {
+    let conn = app.conn();
+    db::insert_budget(&conn, category_id, month, budget_cents, false).unwrap();
+    let transaction = NewTransaction {
+        kind: "expense".to_string(),
+        amount_cents: 1_000,
+        category_id: Some(category_id),
+        occurred_on: format!("{month}-10"),
+        note: None,
+        payee: None,
+        account_id: None,
+        to_account_id: None,
+    };
+    db::insert_transaction(&conn, &transaction, None).unwrap();
+}
+
+#[test]
+fn rename_and_move_under_parent() {
+    let app = TestApp::logged_in();
+    let food_id = app.fixtures.food_id;
+    app.post_form("/categories", &[("name", "Кафе"), ("kind", "expense")]);
+    let cafe_id = category_id(&app, "Кафе");
+    let parent = food_id.to_string();
+    let response = app.post_form(
+        &format!("/categories/{cafe_id}"),
+        &[("name", "Рестораны"), ("parent_id", &parent)],
+    );
+    assert_eq!(response.status(), Status::SeeOther);
+    let cafe = db::category_by_id(&app.conn(), cafe_id).unwrap().unwrap();
+    assert_eq!(cafe.name, "Рестораны");
+    assert_eq!(cafe.parent_id, Some(food_id));
+
+    // A category with children cannot itself become a child.
+    let cafe = cafe_id.to_string();
+    let response = app.post_form(
+        &format!("/categories/{food_id}"),
+        &[("name", "Еда"), ("parent_id", &cafe)],
+    );
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+}
+
+#[test]
+fn delete_reassigns_transactions_and_merges_budgets() {
+    let app = TestApp::logged_in();
+    let food_id = app.fixtures.food_id;
+    add_child(&app, "Кафе", "expense", food_id);
+    app.post_form("/categories", &[("name", "Рестораны"), ("kind", "expense")]);
+    let restaurants_id = category_id(&app, "Рестораны");
+    seed_spending(&app, food_id, "2026-03", 10_000);
+    seed_spending(&app, food_id, "2026-04", 5_000);
+    seed_spending(&app, restaurants_id, "2026-03", 3_000);
+    db::set_standing_budget(&app.conn(), food_id, 2_000).unwrap();
+    db::set_standing_budget(&app.conn(), restaurants_id, 1_000).unwrap();
+
+    let target = restaurants_id.to_string();
+    let response = app.post_form(
+        &format!("/categories/{food_id}/delete"),
+        &[("reassign_to", &target)],
+    );
+    assert_eq!(response.status(), Status::SeeOther);
+
+    let conn = app.conn();
+    assert!(db::category_by_id(&conn, food_id).unwrap().is_none());
+    let cafe = db::category_by_id(&conn, category_id(&app, "Кафе"))
+        .unwrap()
+        .unwrap();
+    assert_eq!(cafe.parent_id, Some(restaurants_id));
+    let moved = db::list_transactions(&conn, &TransactionQuery::default())
+        .unwrap()
+        .into_iter()
+        .filter(|t| t.category_id == Some(restaurants_id))
+        .count();
+    assert_eq!(moved, 3);
+    let march = db::list_budgets(&conn, "2026-03", RefundMonth::Refund).unwrap();
+    assert_eq!(march.len(), 1);
+    assert_eq!(march[0].amount_cents, 13_000);
+    assert_eq!(march[0].spent_cents, 2_000);
+    let april = db::list_budgets(&conn, "2026-04", RefundMonth::Refund).unwrap();
+    assert_eq!(april[0].category_id, restaurants_id);
+    let standing = db::list_standing_budgets(&conn).unwrap();
+    assert_eq!(standing.len(), 1);
+    assert_eq!(standing[0].amount_cents, 3_000);
+}
+
+#[test]
+fn delete_without_target_uncategorizes() {
+    let app = TestApp::logged_in();
+    let food_id = app.fixtures.food_id;
+    seed_spending(&app, food_id, "2026-03", 10_000);
+
+    let response = app.post_form(
+        &format!("/categories/{food_id}/delete"),
+        &[("reassign_to", "")],
+    );
+    assert_eq!(response.status(), Status::SeeOther);
+
+    let conn = app.conn();
+    let records = db::list_transactions(&conn, &TransactionQuery::default()).unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].category_id, None);
+    assert!(
+        db::list_budgets(&conn, "2026-03", RefundMonth::Refund)
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn delete_rejects_target_of_other_kind() {
+    let app = TestApp::logged_in();
+    let target = app.fixtures.salary_id.to_string();
+    let response = app.post_form(
+        &format!("/categories/{}/delete", app.fixtures.food_id),
+        &[("reassign_to", &target)],
+    );
+    assert_eq!(response.status(), Status::BadRequest);
+    assert!(
+        db::category_by_id(&app.conn(), app.fixtures.food_id)
+            .unwrap()
+            .is_some()
+    );
+}
+
+fn kinds(app: &TestApp) -> Vec<(String, Option<i64>)> {
+    db::list_transactions(&app.conn(), &TransactionQuery::default())
+        .unwrap()
+        .into_iter()
+        .map(|t| (t.kind, t.category_id))
+        .collect()
+}
+
+#[test]
+fn kind_change_rekinds_history_and_can_be_undone() {
+    let app = TestApp::logged_in();
+    let food_id = app.fixtures.food_id;
+    add_child(&app, "Кафе", "expense", food_id);
+    let cafe_id = category_id(&app, "Кафе");
+    seed_spending(&app, food_id, "2026-03", 10_000);
+    seed_spending(&app, cafe_id, "2026-03", 2_000);
+
+    let page = app.get(&format!("/categories/{food_id}/kind"));
+    assert_eq!(page.status(), Status::Ok);
+    let body = page.into_string().unwrap();
+    assert!(body.contains("«Кафе»"));
+    assert!(body.contains("Бюджетов будет удалено: 2"));
+
+    let response = app.post_form(
+        &format!("/categories/{food_id}/kind"),
+        &[("history", "rekind")],
+    );
+    assert_eq!(response.status(), Status::SeeOther);
+    let conn = app.conn();
+    for id in [food_id, cafe_id] {
+        let category = db::category_by_id(&conn, id).unwrap().unwrap();
+        assert_eq!(category.kind, "income");
+    }
+    assert!(kinds(&app).iter().all(|(kind, _)| kind == "income"));
+    assert!(
+        db::list_budgets(&conn, "2026-03", RefundMonth::Refund)
+            .unwrap()
+            .is_empty()
+    );
+
+    let operations = db::list_bulk_operations(&conn, 10).unwrap();
+    assert_eq!(operations[0].kind, "category_kind");
+    let response = app.post_form(&format!("/activity/{}/undo", operations[0].id), &[]);
+    assert_eq!(response.status(), Status::SeeOther);
+    for id in [food_id, cafe_id] {
+        let category = db::category_by_id(&conn, id).unwrap().unwrap();
+        assert_eq!(category.kind, "expense");
+    }
+    assert!(kinds(&app).iter().all(|(kind, _)| kind == "expense"));
+    assert_eq!(
+        db::list_budgets(&conn, "2026-03", RefundMonth::Refund)
+            .unwrap()
+            .len(),
+        2
+    );
+}
+
+#[test]
+fn kind_change_can_keep_or_move_history() {
+    let app = TestApp::logged_in();
+    let food_id = app.fixtures.food_id;
+    app.post_form("/categories", &[("name", "Авто"), ("kind", "expense")]);
+    app.post_form("/categories", &[("name", "Подарки"), ("kind", "expense")]);
+    let car_id = category_id(&app, "Авто");
+    let gifts_id = category_id(&app, "Подарки");
+    seed_spending(&app, food_id, "2026-03", 10_000);
+    seed_spending(&app, gifts_id, "2026-03", 3_000);
+
+    let target = car_id.to_string();
+    let response = app.post_form(
+        &format!("/categories/{food_id}/kind"),
+        &[("history", "reassign"), ("reassign_to", &target)],
+    );
+    assert_eq!(response.status(), Status::SeeOther);
+    assert!(kinds(&app).contains(&("expense".to_string(), Some(car_id))));
+
+    let response = app.post_form(
+        &format!("/categories/{gifts_id}/kind"),
+        &[("history", "keep")],
+    );
+    assert_eq!(response.status(), Status::SeeOther);
+    assert!(kinds(&app).contains(&("expense".to_string(), Some(gifts_id))));
+}
+
+#[test]
+fn kind_change_rejects_bad_targets() {
+    let app = TestApp::logged_in();
+    let food_id = app.fixtures.food_id;
+    for target in [app.fixtures.salary_id, food_id, 9_999] {
+        let target = target.to_string();
+        let response = app.post_form(
+            &format!("/categories/{food_id}/kind"),
+            &[("history", "reassign"), ("reassign_to", &target)],
+        );
+        assert_eq!(response.status(), Status::BadRequest);
+    }
+    let response = app.post_form(
+        &format!("/categories/{food_id}/kind"),
+        &[("history", "other")],
+    );
+    assert_eq!(response.status(), Status::BadRequest);
+    let food = db::category_by_id(&app.conn(), food_id).unwrap().unwrap();
+    assert_eq!(food.kind, "expense");
+}
+
+#[test]
+fn names_are_unique_per_kind_ignoring_case_and_spaces() {
+    let app = TestApp::logged_in();
+    let add = |name: &str, kind: &str| {
+        app.post_form("/categories", &[("name", name), ("kind", kind)])
+            .status()
+    };
+    assert_eq!(add(" еда ", "expense"), Status::UnprocessableEntity);
+    assert_eq!(add("Еда", "income"), Status::SeeOther);
+    assert_eq!(add("Кафе   и  бары", "expense"), Status::SeeOther);
+    assert_eq!(add("кафе и бары", "expense"), Status::UnprocessableEntity);
+    let cafe_id = category_id(&app, "Кафе и бары");
+
+    let rename = |id: i64, name: &str| {
+        app.post_form(&format!("/categories/{id}"), &[("name", name)])
+            .status()
+    };
+    assert_eq!(rename(cafe_id, "ЕДА"), Status::UnprocessableEntity);
+    assert_eq!(rename(cafe_id, "КАФЕ И БАРЫ"), Status::SeeOther);
+
+    // «Еда» can't become income while an income «Еда» exists.
+    let food_id = app.fixtures.food_id;
+    let page = app
+        .get(&format!("/categories/{food_id}/kind"))
+        .into_string()
+        .unwrap();
+    assert!(page.contains("уже есть «Еда»"));
+    let response = app.post_form(
+        &format!("/categories/{food_id}/kind"),
+        &[("history", "keep")],
+    );
+    assert_eq!(response.status(), Status::Conflict);
+}
+
+#[test]
+fn legacy_duplicates_are_found_and_merged() {
+    let path = std::env::temp_dir().join(format!("lumen-{}.sqlite", uuid::Uuid::new_v4()));
+    {
+        let conn = rusqlite::Connection::open(&path).unwrap();
+        conn.execute_batch(
+            "
+            CREATE TABLE categories (id INTEGER PRIMARY KEY, name TEXT NOT NULL, kind TEXT NOT NULL);
+            INSERT INTO categories (name, kind) VALUES ('Еда', 'expense'), ('еда ', 'expense'),
+                ('Еда', 'income'), ('  Такси  ', 'expense');
+            ",
+        )
+        .unwrap();
+    }
+    let metrics = std::sync::Arc::new(db::PoolMetrics::default());
+    let pool = db::init_db(&path, &db::PoolConfig::default(), metrics);
+    let conn = pool.get().unwrap();
+    let names = db::list_categories(&conn)
+        .unwrap()
+        .into_iter()
+        .map(|category| category.name)
+        .collect::<Vec<_>>();
+    assert!(names.contains(&"Такси".to_string()));
+    let duplicates = db::category_duplicates(&conn).unwrap();
+    let found = duplicates
+        .iter()
+        .map(|duplicate| (duplicate.name.as_str(), duplicate.kind.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(found, [("Еда", "expense"), ("еда", "expense")]);
+    assert!(!db::ensure_category_name_index(&conn).unwrap());
+    drop(conn);
+    drop(pool);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn merging_duplicates_moves_their_history() {
+    let app = TestApp::logged_in();
+    let food_id = app.fixtures.food_id;
+    let conn = app.conn();
+    conn.execute_batch(
+        "
+        DROP INDEX categories_kind_name;
+        INSERT INTO categories (name, kind, name_key) VALUES ('еда', 'expense', 'еда');
+        ",
+    )
+    .unwrap();
+    let lower_id = conn.last_insert_rowid();
+    seed_spending(&app, food_id, "2026-03", 10_000);
+    seed_spending(&app, lower_id, "2026-03", 5_000);
+    seed_spending(&app, lower_id, "2026-04", 5_000);
+
+    let page = app.get("/categories").into_string().unwrap();
+    assert!(page.contains("Найдены похожие названия категорий: 1"));
+    let page = app.get("/categories/duplicates").into_string().unwrap();
+    assert!(page.contains(&format!(r#"value="{lower_id}" checked"#)));
+
+    let keep = food_id.to_string();
+    let response = app.post_form("/categories/duplicates", &[("keep", &keep)]);
+    assert_eq!(response.status(), Status::SeeOther);
+    assert_eq!(super::location(&response), Some("/categories"));
+    assert!(db::category_by_id(&conn, lower_id).unwrap().is_none());
+    let records = db::list_transactions(&conn, &TransactionQuery::default()).unwrap();
+    assert!(records.iter().all(|t| t.category_id == Some(food_id)));
+    assert_eq!(
+        db::list_budgets(&conn, "2026-03", RefundMonth::Refund).unwrap()[0].amount_cents,
+        15_000
+    );
+    // The names are unique again, and the index says so.
+    assert_eq!(
+        app.post_form("/categories", &[("name", "ЕДА"), ("kind", "expense")])
+            .status(),
+        Status::UnprocessableEntity
+    );
+    let indexed: bool = conn
+        .query_row(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE name = 'categories_kind_name')",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert!(indexed);
+}
+
+#[test]
+fn default_amount_prefills_the_transaction_form() {
+    let app = TestApp::logged_in();
+    let transit = add_child(&app, "Проездной", "expense", app.fixtures.food_id);
+    assert_eq!(transit, Status::SeeOther);
+    let id = category_id(&app, "Проездной");
+    let path = format!("/categories/{id}");
+    let food_id = app.fixtures.food_id.to_string();
+    let update = |amount: &str| {
+        app.post_form(
+            &path,
+            &[
+                ("name", "Проездной"),
+                ("parent_id", &food_id),
+                ("default_amount", amount),
+            ],
+        )
+        .status()
+    };
+    assert_eq!(update("abc"), Status::UnprocessableEntity);
+    assert_eq!(update("-5"), Status::UnprocessableEntity);
+    assert_eq!(update("2900,50"), Status::SeeOther);
+    let category = db::category_by_id(&app.conn(), id).unwrap().unwrap();
+    assert_eq!(category.default_amount_cents, Some(290_050));
+
+    let fields = |query: &str| {
+        app.get(&format!("/transactions/defaults?{query}"))
+            .into_string()
+            .unwrap()
+    };
+    let picked = fields(&format!("category_id={id}"));
+    assert!(picked.contains(r#"<option value="expense" selected>"#));
+    assert!(picked.contains(r#"value="2900.50""#));
+    // A typed amount stays, a garbled one is replaced.
+    assert!(fields(&format!("category_id={id}&amount=120")).contains(r#"value="120""#));
+    assert!(fields(&format!("category_id={id}&amount=%3Cb%3E")).contains(r#"value="2900.50""#));
+    let salary = fields(&format!("category_id={}", app.fixtures.salary_id));
+    assert!(salary.contains(r#"<option value="income" selected>"#));
+    assert!(salary.contains(r#"name="amount" value="""#));
+    assert!(!fields("category_id=").contains("selected"));
+    let missing = app.get("/transactions/defaults?category_id=9999");
+    assert_eq!(missing.status(), Status::NotFound);
+
+    assert_eq!(update(""), Status::SeeOther);
+    let category = db::category_by_id(&app.conn(), id).unwrap().unwrap();
+    assert_eq!(category.default_amount_cents, None);
+}
@@ -0,0 +1,68 @@
+use std::io::Read;
+
+use rocket::http::{Header, Status};
+
+use super::TestApp;
+use crate::compression::{negotiate, Encoding};
+
+#[test]
+fn accept_encoding_is_negotiated() {
+    assert_eq!(negotiate("gzip, deflate, br"), Some(Encoding::Brotli));
+    assert_eq!(negotiate("gzip, deflate"), Some(Encoding::Gzip));
+    assert_eq!(negotiate("br;q=0.5, gzip"), Some(Encoding::Gzip));
+    assert_eq!(negotiate("br;q=0, *"), Some(Encoding::Gzip));
+    assert_eq!(negotiate("*;q=0.1"), Some(Encoding::Brotli));
+    assert_eq!(negotiate("identity"), None);
+    assert_eq!(negotiate("gzip;q=0"), None);
+    assert_eq!(negotiate(""), None);
+}
+
+fn page(app: &TestApp, accept_encoding: &str) -> (Option<String>, Vec<u8>) {
+    let response = app
+        .client
+        .get("/transactions")
+        .header(Header::new("Accept-Encoding", accept_encoding.to_string()))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let vary: Vec<_> = response.headers().get("Vary").collect();
+    assert!(vary.contains(&"Accept-Encoding"), "{vary:?}");
+    let encoding = response.headers().get_one("Content-Encoding").map(str::to_string);
+    (encoding, response.into_bytes().unwrap())
+}
+
+#[test]
+fn pages_are_compressed_when_the_client_asks() {
+    let app = TestApp::logged_in();
+    let (encoding, plain) = page(&app, "identity");
+    assert_eq!(encoding, None);
+    assert!(String::from_utf8_lossy(&plain).contains("<html"));
+
+    let (encoding, gzipped) = page(&app, "gzip");
+    assert_eq!(encoding.as_deref(), Some("gzip"));
+    assert!(gzipped.len() < plain.len());
+    let mut decoded = Vec::new();
+    flate2::read::GzDecoder::new(&gzipped[..])
+        .read_to_end(&mut decoded)
+        .unwrap();
+    assert_eq!(decoded, plain);
+
+    let (encoding, brotli) = page(&app, "gzip, br");
+    assert_eq!(encoding.as_deref(), Some("br"));
+    let mut decoded = Vec::new();
+    brotli::Decompressor::new(&brotli[..], 4096)
+        .read_to_end(&mut decoded)
+        .unwrap();
+    assert_eq!(decoded, plain);
+}
+
+#[test]
+fn static_files_are_left_alone() {
+    let app = TestApp::new();
+    let response = app
+        .client
+        .get("/static/style.css")
+        .header(Header::new("Accept-Encoding", "gzip, br"))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.headers().get_one("Content-Encoding"), None);
+}
@@ -0,0 +1,88 @@
+use rocket::http::{ContentType, Header, Status};
+
+use super::{TestApp, location};
+use crate::{csrf, db};
+
+#[test]
+fn post_without_the_session_token_is_refused() {
+    let app = TestApp::logged_in();
+    let post = |body: &str| {
+        app.client
+            .post("/accounts")
+            .header(ContentType::Form)
+            .body(body)
+            .dispatch()
+            .status()
+    };
+    assert_eq!(
+        post("name=Deposit&kind=bank&currency=RUB&opening_balance=0"),
+        Status::Forbidden
+    );
+    assert_eq!(
+        post(
+            "csrf_token=0123456789abcdef0123456789abcdef&name=Deposit&kind=bank&currency=RUB&opening_balance=0"
+        ),
+        Status::Forbidden
+    );
+    assert_eq!(db::list_accounts(&app.conn()).unwrap().len(), 2);
+
+    let token = app.csrf_token().unwrap();
+    assert_eq!(
+        post(&format!(
+            "csrf_token={token}&name=Deposit&kind=bank&currency=RUB&opening_balance=0"
+        )),
+        Status::SeeOther
+    );
+    let response = app
+        .client
+        .post("/notifications/read_all")
+        .header(Header::new(csrf::HEADER, token))
+        .dispatch();
+    assert_eq!(location(&response), Some("/notifications"));
+}
+
+#[test]
+fn every_post_form_on_a_page_carries_the_token() {
+    let app = TestApp::logged_in();
+    let token = app.csrf_token().unwrap();
+    let page = app.get("/transactions").into_string().unwrap();
+    let field = format!(r#"name="csrf_token" value="{token}""#);
+    assert_eq!(
+        page.matches(&field).count(),
+        page.matches(r#"method="post""#).count()
+    );
+    assert!(page.matches(&field).count() > 1);
+
+    // A new login gets a token of its own.
+    app.post_form("/logout", &[]);
+    assert_eq!(app.csrf_token(), None);
+    let login = app.get("/login").into_string().unwrap();
+    assert!(!login.contains("csrf_token"));
+    app.login(super::USERNAME, super::PASSWORD);
+    assert_ne!(app.csrf_token(), Some(token));
+}
+
+#[test]
+fn api_calls_on_the_session_cookie_need_the_token() {
+    let app = TestApp::logged_in();
+    let body = r#"{"kind": "expense", "amount_cents": 30000, "occurred_on": "2024-03-06"}"#;
+    let post = |extra: Option<Header<'static>>| {
+        let mut request = app
+            .client
+            .post("/api/v1/transactions")
+            .header(ContentType::JSON)
+            .body(body);
+        if let Some(header) = extra {
+            request = request.header(header);
+        }
+        request.dispatch().status()
+    };
+    // A bearer header is what exempts a call, not the path.
+    assert_eq!(post(None), Status::Forbidden);
+    assert_eq!(
+        post(Some(Header::new("Authorization", "Bearer not-a-token"))),
+        Status::Unauthorized
+    );
+    let token = app.csrf_token().unwrap();
+    assert_eq!(post(Some(Header::new(csrf::HEADER, token))), Status::Created);
+}
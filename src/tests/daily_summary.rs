@@ -0,0 +1,90 @@
+use chrono::NaiveDate;
+
+use super::TestApp;
+use crate::{daily_summary, db};
+
+#[test]
+fn summary_lists_the_day_and_what_is_left_of_the_week() {
+    let app = TestApp::logged_in();
+    let (food_id, salary_id) = (
+        app.fixtures.food_id.to_string(),
+        app.fixtures.salary_id.to_string(),
+    );
+    app.spend("200", "2026-03-09", &[]);
+    app.spend("100", "2026-03-10", &[]);
+    app.spend(
+        "500",
+        "2026-03-10",
+        &[("kind", "income"), ("category_id", &salary_id)],
+    );
+    app.post_form(
+        "/budgets",
+        &[
+            ("category_id", &food_id),
+            ("month", "2026-03"),
+            ("amount", "3100"),
+        ],
+    );
+
+    let user_id = db::user_ids(&app.conn()).unwrap()[0];
+    let tuesday = NaiveDate::from_ymd_opt(2026, 3, 10).unwrap();
+    let text = daily_summary::build(&app.conn(), user_id, tuesday).unwrap();
+    assert!(text.starts_with("Итоги дня 2026-03-10"));
+    assert!(text.contains("Операций: 2\nДоход: 500.00\nРасход: 100.00\nЕда — 100.00"));
+    // 3100 over 31 days is 700 a week, 300 of it spent since Monday.
+    assert!(text.contains("Бюджет недели: осталось 400.00 из 700.00"));
+
+    app.spend("450", "2026-03-11", &[]);
+    let text = daily_summary::build(&app.conn(), user_id, tuesday + chrono::Days::new(1)).unwrap();
+    assert!(text.contains("Бюджет недели превышен на 50.00 из 700.00"));
+
+    let text = daily_summary::build(&app.conn(), user_id, tuesday + chrono::Days::new(2)).unwrap();
+    assert!(text.contains("Сегодня операций нет"));
+}
+
+#[test]
+fn summary_goes_out_once_after_the_chosen_time() {
+    let app = TestApp::logged_in();
+    let user_id = db::user_ids(&app.conn()).unwrap()[0];
+    app.post_form("/settings/email", &[("email", "owner@example.com")]);
+    let body = app
+        .post_form(
+            "/settings/daily-summary",
+            &[("at", "19:00"), ("email", "true")],
+        )
+        .into_string()
+        .unwrap();
+    assert!(body.contains("Итоги дня обновлены"));
+
+    let due = |now: &str| db::daily_summary_recipients(&app.conn(), "2026-03-10", now).unwrap();
+    assert!(due("18:59").is_empty());
+    let recipients = due("19:00");
+    assert_eq!(recipients.len(), 1);
+    assert_eq!(recipients[0].email.as_deref(), Some("owner@example.com"));
+    // Telegram wasn't picked, linked chat or not.
+    assert_eq!(recipients[0].chat_id, None);
+
+    db::mark_daily_summary_sent(&app.conn(), user_id, "2026-03-10").unwrap();
+    assert!(due("23:00").is_empty());
+
+    app.post_form("/settings/daily-summary", &[("at", "")]);
+    assert_eq!(db::daily_summary(&app.conn(), user_id).unwrap().at, None);
+}
+
+#[test]
+fn summary_settings_need_a_time_and_a_channel() {
+    let app = TestApp::logged_in();
+    let body = app
+        .post_form(
+            "/settings/daily-summary",
+            &[("at", "25:00"), ("telegram", "true")],
+        )
+        .into_string()
+        .unwrap();
+    assert!(body.contains("Время итогов дня указано неверно"));
+    let body = app
+        .post_form("/settings/daily-summary", &[("at", "21:30")])
+        .into_string()
+        .unwrap();
+    assert!(body.contains("Выберите, куда присылать итоги дня"));
+}
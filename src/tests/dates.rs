@@ -0,0 +1,106 @@
+use chrono::NaiveDate;
+use rocket::http::Status;
+
+use super::TestApp;
+use crate::dates::{self, Reading};
+use crate::db;
+
+fn day(year: i32, month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, day).unwrap()
+}
+
+fn insert_dated(app: &TestApp, occurred_on: &str) -> i64 {
+    let conn = app.conn();
+    conn.execute(
+        "INSERT INTO transactions (kind, amount_cents, occurred_on) VALUES ('expense', 1000, ?1)",
+        [occurred_on],
+    )
+    .unwrap();
+    conn.last_insert_rowid()
+}
+
+fn date_of(app: &TestApp, id: i64) -> String {
+    app.conn()
+        .query_row(
+            "SELECT occurred_on FROM transactions WHERE id = ?1",
+            [id],
+            |row| row.get(0),
+        )
+        .unwrap()
+}
+
+#[test]
+fn dates_are_read_the_way_they_were_written() {
+    assert_eq!(dates::read("2024-07-03"), None);
+    assert_eq!(
+        dates::read("2024-7-3"),
+        Some(Reading::Fixed(day(2024, 7, 3)))
+    );
+    assert_eq!(
+        dates::read(" 2024/07/03"),
+        Some(Reading::Fixed(day(2024, 7, 3)))
+    );
+    assert_eq!(
+        dates::read("03.07.2024"),
+        Some(Reading::Fixed(day(2024, 7, 3)))
+    );
+    assert_eq!(dates::read("3.7.24"), Some(Reading::Fixed(day(2024, 7, 3))));
+    assert_eq!(
+        dates::read("25/07/2024"),
+        Some(Reading::Fixed(day(2024, 7, 25)))
+    );
+    assert_eq!(
+        dates::read("03/07/2024"),
+        Some(Reading::Ambiguous(vec![day(2024, 7, 3), day(2024, 3, 7)]))
+    );
+    assert_eq!(
+        dates::read("07/07/2024"),
+        Some(Reading::Fixed(day(2024, 7, 7)))
+    );
+    assert_eq!(dates::read("2024-02-30"), Some(Reading::Unreadable));
+    assert_eq!(dates::read("вчера"), Some(Reading::Unreadable));
+}
+
+#[test]
+fn repair_fixes_clear_dates_and_leaves_the_rest_for_review() {
+    let app = TestApp::logged_in();
+    let clean = insert_dated(&app, "2024-07-03");
+    let short = insert_dated(&app, "2024-7-3");
+    let dotted = insert_dated(&app, "15.03.2024");
+    let ambiguous = insert_dated(&app, "03/04/2024");
+    let unreadable = insert_dated(&app, "позавчера");
+
+    let page = app.get("/settings/dates").into_string().unwrap();
+    assert!(page.contains("Исправить 2"));
+    assert!(page.contains(r#"value="2024-04-03""#));
+    assert!(page.contains(r#"value="2024-03-04""#));
+
+    let response = app.post_form("/settings/dates", &[]);
+    assert_eq!(response.status(), Status::SeeOther);
+    assert_eq!(date_of(&app, clean), "2024-07-03");
+    assert_eq!(date_of(&app, short), "2024-07-03");
+    assert_eq!(date_of(&app, dotted), "2024-03-15");
+    assert_eq!(date_of(&app, ambiguous), "03/04/2024");
+
+    let url = format!("/settings/dates/{ambiguous}");
+    let response = app.post_form(&url, &[("occurred_on", "2024-04-03")]);
+    assert_eq!(response.status(), Status::SeeOther);
+    assert_eq!(date_of(&app, ambiguous), "2024-04-03");
+
+    let url = format!("/settings/dates/{unreadable}");
+    let response = app.post_form(&url, &[("occurred_on", "позавчера")]);
+    assert_eq!(response.status(), Status::BadRequest);
+    // Only transactions on the list can be changed here.
+    let url = format!("/settings/dates/{clean}");
+    let response = app.post_form(&url, &[("occurred_on", "2024-01-01")]);
+    assert_eq!(response.status(), Status::NotFound);
+
+    let conn = app.conn();
+    let operations = db::list_bulk_operations(&conn, 10).unwrap();
+    assert_eq!(operations.len(), 2);
+    assert_eq!(operations[1].change_count, 2);
+    let response = app.post_form(&format!("/activity/{}/undo", operations[1].id), &[]);
+    assert_eq!(response.status(), Status::SeeOther);
+    assert_eq!(date_of(&app, short), "2024-7-3");
+    assert_eq!(date_of(&app, ambiguous), "2024-04-03");
+}
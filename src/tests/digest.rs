@@ -0,0 +1,86 @@
+use chrono::NaiveDate;
+
+use super::TestApp;
+use crate::{db, digest};
+
+#[test]
+fn digest_sums_the_week_and_lists_budgets_and_bills() {
+    let app = TestApp::logged_in();
+    app.spend("999", "2026-03-08", &[]);
+    app.spend("100", "2026-03-09", &[]);
+    app.spend("50", "2026-03-15", &[]);
+    let food_id = app.fixtures.food_id.to_string();
+    app.post_form(
+        "/budgets",
+        &[
+            ("category_id", &food_id),
+            ("month", "2026-03"),
+            ("amount", "1000"),
+        ],
+    );
+    app.post_form(
+        "/loans",
+        &[
+            ("name", "Ипотека"),
+            ("principal", "120000"),
+            ("annual_rate", "0"),
+            ("term_months", "12"),
+            ("first_month", "2026-03"),
+            ("payment_day", "20"),
+        ],
+    );
+
+    let sunday = NaiveDate::from_ymd_opt(2026, 3, 15).unwrap();
+    assert_eq!(
+        digest::week_start(sunday),
+        NaiveDate::from_ymd_opt(2026, 3, 9).unwrap()
+    );
+    let user_id = db::user_ids(&app.conn()).unwrap()[0];
+    let text = digest::build(&app.conn(), user_id, sunday).unwrap();
+    assert!(text.contains("Итоги недели 2026-03-09 — 2026-03-15"));
+    assert!(text.contains("Расходы: 150.00\nЕда — 150.00"));
+    assert!(text.contains("Еда: 1149.00 из 1000.00 — превышен"));
+    assert!(text.contains("2026-03-20 Ипотека — 10000.00"));
+
+    // Once it is paid the next payment is a month off.
+    let loan_id = db::list_loans(&app.conn()).unwrap()[0].id;
+    app.post_form(
+        &format!("/loans/{loan_id}/payments"),
+        &[("amount", "10000"), ("occurred_on", "2026-03-20")],
+    );
+    let text = digest::build(&app.conn(), user_id, sunday + chrono::Days::new(7)).unwrap();
+    assert!(text.contains("Без категории — 10000.00"));
+    assert!(!text.contains("Ипотека"));
+}
+
+#[test]
+fn digest_is_opt_in_and_needs_a_chat() {
+    let app = TestApp::logged_in();
+    let user_id = db::user_ids(&app.conn()).unwrap()[0];
+    app.post_form("/settings/telegram", &[("chat_id", "42")]);
+    assert!(!db::telegram_digest(&app.conn(), user_id).unwrap());
+    assert!(
+        db::digest_recipients(&app.conn(), "2026-03-15")
+            .unwrap()
+            .is_empty()
+    );
+
+    app.post_form(
+        "/settings/telegram",
+        &[("chat_id", "42"), ("digest", "true")],
+    );
+    assert_eq!(
+        db::digest_recipients(&app.conn(), "2026-03-15").unwrap(),
+        [(user_id, 42)]
+    );
+    db::mark_digest_sent(&app.conn(), user_id, "2026-03-15").unwrap();
+    assert!(
+        db::digest_recipients(&app.conn(), "2026-03-15")
+            .unwrap()
+            .is_empty()
+    );
+    assert_eq!(
+        db::digest_recipients(&app.conn(), "2026-03-22").unwrap(),
+        [(user_id, 42)]
+    );
+}
@@ -0,0 +1,101 @@
+use rocket::http::Status;
+
+use super::{TestApp, location};
+use crate::db;
+use crate::disputes::RefundMonth;
+use crate::query::TransactionQuery;
+
+fn month_expense(app: &TestApp, month: &str, refunds: RefundMonth) -> (i64, i64) {
+    let months = db::report_months(&app.conn(), 12, refunds).unwrap();
+    let row = months.iter().find(|row| row.month == month).unwrap();
+    (row.income_cents, row.expense_cents)
+}
+
+#[test]
+fn refund_nets_against_the_chosen_month() {
+    let app = TestApp::logged_in();
+    let id = app.spend("5000", "2026-03-10", &[("payee", "Магазин техники")]);
+
+    let response = app.post_form(&format!("/transactions/{id}/dispute"), &[]);
+    assert_eq!(location(&response), Some("/disputes"));
+    let page = app.get("/disputes").into_string().unwrap();
+    assert!(page.contains("Магазин техники"));
+    assert!(page.contains("Деньги вернули"));
+
+    let refund = |amount: &str| {
+        app.post_form(
+            &format!("/disputes/{id}/refund"),
+            &[("amount", amount), ("occurred_on", "2026-04-05")],
+        )
+    };
+    let too_much = refund("6000");
+    assert_eq!(too_much.status(), Status::UnprocessableEntity);
+    assert!(too_much.into_string().unwrap().contains("больше покупки"));
+    assert_eq!(location(&refund("3000")), Some("/disputes"));
+    assert_eq!(refund("100").status(), Status::BadRequest);
+
+    assert_eq!(
+        month_expense(&app, "2026-03", RefundMonth::Refund),
+        (0, 500_000)
+    );
+    assert_eq!(
+        month_expense(&app, "2026-04", RefundMonth::Refund),
+        (0, -300_000)
+    );
+    // The dashboard and the budgets count the refund the same way.
+    let conn = app.conn();
+    for month in ["2026-03", "2026-04"] {
+        db::insert_budget(&conn, app.fixtures.food_id, month, 600_000, false).unwrap();
+    }
+    let dashboard = |month: &str, refunds| {
+        let budgets = db::dashboard_budgets(&conn, month, refunds).unwrap();
+        let listed = db::list_budgets(&conn, month, refunds).unwrap();
+        assert_eq!(budgets[0].spent_cents, listed[0].spent_cents);
+        let totals = db::report_month_totals(&conn, month, refunds).unwrap();
+        (totals, budgets[0].spent_cents)
+    };
+    assert_eq!(
+        dashboard("2026-03", RefundMonth::Refund),
+        ((0, 500_000), 500_000)
+    );
+    assert_eq!(
+        dashboard("2026-04", RefundMonth::Refund),
+        ((0, -300_000), -300_000)
+    );
+    assert_eq!(
+        dashboard("2026-03", RefundMonth::Original),
+        ((0, 200_000), 200_000)
+    );
+    assert_eq!(dashboard("2026-04", RefundMonth::Original), ((0, 0), 0));
+
+    app.post_form("/settings/refunds", &[("refund_month", "original")]);
+    let (user_id, _) = db::user_credentials(&app.conn(), super::USERNAME)
+        .unwrap()
+        .unwrap();
+    let refunds = db::refund_month(&app.conn(), user_id).unwrap();
+    assert_eq!(refunds, RefundMonth::Original);
+    assert_eq!(month_expense(&app, "2026-03", refunds), (0, 200_000));
+    let categories = db::report_categories(&app.conn(), "2026-03", refunds).unwrap();
+    assert_eq!(categories[0].expense_cents, 200_000);
+
+    let page = app.get("/disputes").into_string().unwrap();
+    assert!(page.contains("возвращено"));
+    assert!(page.contains("3000.00 · 2026-04-05"));
+}
+
+#[test]
+fn dispute_can_close_without_a_refund() {
+    let app = TestApp::logged_in();
+    let id = app.spend("700", "2026-03-10", &[("payee", "Магазин техники")]);
+    app.post_form(&format!("/transactions/{id}/dispute"), &[]);
+    let again = app.post_form(&format!("/transactions/{id}/dispute"), &[]);
+    assert_eq!(again.status(), Status::BadRequest);
+
+    let response = app.post_form(&format!("/disputes/{id}/close"), &[]);
+    assert_eq!(location(&response), Some("/disputes"));
+    let listed = db::list_transactions(&app.conn(), &TransactionQuery::default()).unwrap();
+    assert_eq!(listed[0].status, "normal");
+    assert!(db::list_disputes(&app.conn(), 20).unwrap().is_empty());
+    let closed = app.post_form(&format!("/disputes/{id}/close"), &[]);
+    assert_eq!(closed.status(), Status::NotFound);
+}
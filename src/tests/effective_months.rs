@@ -0,0 +1,116 @@
+use rocket::http::Status;
+
+use super::{TestApp, location};
+use crate::db;
+use crate::disputes::RefundMonth;
+use crate::effective_months::{Shift, parse_month};
+
+fn income(app: &TestApp, month: &str) -> (i64, i64) {
+    let conn = app.conn();
+    let (totals, _) = db::report_month_totals(&conn, month, RefundMonth::Refund).unwrap();
+    let report = db::report_months(&conn, 120, RefundMonth::Refund)
+        .unwrap()
+        .into_iter()
+        .find(|row| row.month == month)
+        .map_or(0, |row| row.income_cents);
+    (totals, report)
+}
+
+#[test]
+fn shifts_and_typed_months_are_read() {
+    assert_eq!(Shift::Next.month("2026-03-31"), Some("2026-04".to_string()));
+    assert_eq!(
+        Shift::Previous.month("2026-01-02"),
+        Some("2025-12".to_string())
+    );
+    assert_eq!(Shift::Own.month("2026-03-31"), None);
+    assert_eq!(Shift::Next.month("31.03.2026"), None);
+    assert_eq!(parse_month(" 2026-04 "), Some("2026-04".to_string()));
+    assert_eq!(parse_month("2026-4"), None);
+    assert_eq!(parse_month("2026-13"), None);
+}
+
+#[test]
+fn late_salaries_move_to_the_next_month_and_back() {
+    let app = TestApp::logged_in();
+    let salary = [
+        ("kind", "income"),
+        ("category_id", &app.fixtures.salary_id.to_string()),
+    ];
+    app.spend("100000", "2026-03-31", &salary);
+    app.spend("5000", "2026-03-10", &salary);
+    assert_eq!(income(&app, "2026-03"), (10_500_000, 10_500_000));
+
+    let page = app
+        .get(
+            "/transactions/months?from=2026-03-01&to=2026-03-31&kind=income&from_day=28&shift=next",
+        )
+        .into_string()
+        .unwrap();
+    assert!(page.contains("Перенести 1"));
+    assert_eq!(income(&app, "2026-04"), (0, 0));
+
+    let response = app.post_form(
+        "/transactions/months",
+        &[
+            ("from", "2026-03-01"),
+            ("to", "2026-03-31"),
+            ("kind", "income"),
+            ("from_day", "28"),
+            ("shift", "next"),
+        ],
+    );
+    assert_eq!(location(&response), Some("/transactions/months"));
+    assert_eq!(income(&app, "2026-03"), (500_000, 500_000));
+    assert_eq!(income(&app, "2026-04"), (10_000_000, 10_000_000));
+    let list = app
+        .get("/transactions?month=2026-03")
+        .into_string()
+        .unwrap();
+    assert!(list.contains("→ 2026-04"));
+
+    let conn = app.conn();
+    let operations = db::list_bulk_operations(&conn, 10).unwrap();
+    assert_eq!(operations[0].kind, "effective_month");
+    let response = app.post_form(&format!("/activity/{}/undo", operations[0].id), &[]);
+    assert_eq!(response.status(), Status::SeeOther);
+    assert_eq!(income(&app, "2026-03"), (10_500_000, 10_500_000));
+    assert_eq!(income(&app, "2026-04"), (0, 0));
+}
+
+#[test]
+fn a_new_transaction_can_count_toward_another_month() {
+    let app = TestApp::logged_in();
+    let food_id = app.fixtures.food_id.to_string();
+    let fields = |month| {
+        [
+            ("kind", "expense"),
+            ("amount", "700"),
+            ("category_id", food_id.as_str()),
+            ("occurred_on", "2026-05-01"),
+            ("effective_month", month),
+        ]
+    };
+    let response = app.post_form("/transactions", &fields("май"));
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+    assert!(
+        response
+            .into_string()
+            .unwrap()
+            .contains("Месяц в формате ГГГГ-ММ")
+    );
+
+    let conn = app.conn();
+    db::insert_budget(&conn, app.fixtures.food_id, "2026-04", 50_000, false).unwrap();
+    app.post_form("/transactions", &fields("2026-04"));
+    let user_id = db::user_ids(&conn).unwrap()[0];
+    let alerts = db::list_notifications(&conn, user_id, Some("budget_exceeded")).unwrap();
+    assert_eq!(alerts.len(), 1);
+    assert!(alerts[0].body.ends_with("за 2026-04"), "{}", alerts[0].body);
+    let totals = |month| db::report_month_totals(&conn, month, RefundMonth::Refund).unwrap();
+    assert_eq!(totals("2026-04"), (0, 70_000));
+    assert_eq!(totals("2026-05"), (0, 0));
+    let months = db::list_months(&conn, 120).unwrap();
+    assert!(months.contains(&"2026-04".to_string()), "{months:?}");
+    assert!(!months.contains(&"2026-05".to_string()), "{months:?}");
+}
@@ -0,0 +1,35 @@
+use std::time::Duration;
+
+use rocket::http::Status;
+
+use super::{TestApp, location};
+use crate::db::PoolConfig;
+
+#[test]
+fn saturated_pool_answers_503_instead_of_panicking() {
+    let app = TestApp::with_pool(&PoolConfig {
+        max_size: 2,
+        connection_timeout: Duration::from_millis(200),
+        ..PoolConfig::default()
+    });
+    app.login(super::USERNAME, super::PASSWORD);
+    let food_id = app.fixtures.food_id.to_string();
+
+    let held = (app.conn(), app.conn());
+    for path in ["/", "/transactions", "/budgets", "/reports", "/login"] {
+        assert_eq!(app.get(path).status(), Status::ServiceUnavailable, "{path}");
+    }
+    let response = app.post_form(
+        "/budgets",
+        &[
+            ("category_id", &food_id),
+            ("month", "2026-03"),
+            ("amount", "100"),
+        ],
+    );
+    assert_eq!(response.status(), Status::ServiceUnavailable);
+    assert_ne!(location(&response), Some("/login"));
+
+    drop(held);
+    assert_eq!(app.get("/transactions").status(), Status::Ok);
+}
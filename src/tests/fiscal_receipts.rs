@@ -0,0 +1,78 @@
+use chrono::NaiveDate;
+use rocket::http::Status;
+use serde_json::json;
+
+use super::{TestApp, location};
+use crate::db;
+use crate::fiscal_receipts::{FiscalReceipt, seller_of};
+use crate::query::TransactionQuery;
+
+const PURCHASE: &str = "t=20260310T1530&s=1234.50&fn=9289000100123456&i=12345&fp=3849203813&n=1";
+
+#[test]
+fn qr_codes_give_the_receipt_and_its_kind() {
+    let receipt = FiscalReceipt::parse(PURCHASE).unwrap();
+    assert_eq!(
+        receipt.qr.occurred_on,
+        NaiveDate::from_ymd_opt(2026, 3, 10).unwrap()
+    );
+    assert_eq!(receipt.qr.amount_cents, 123_450);
+    assert_eq!(receipt.id, "9289000100123456-12345-3849203813");
+    assert_eq!(receipt.kind, "expense");
+    let refund = PURCHASE.replace("n=1", "n=2");
+    assert_eq!(FiscalReceipt::parse(&refund).unwrap().kind, "income");
+    assert!(FiscalReceipt::parse(&PURCHASE.replace("n=1", "n=3")).is_err());
+    assert!(FiscalReceipt::parse("t=20260310T1530&s=1234.50").is_err());
+    assert!(FiscalReceipt::parse("https://example.com").is_err());
+}
+
+#[test]
+fn the_seller_comes_from_the_check_service() {
+    let payload = json!({
+        "code": 1,
+        "data": { "json": { "user": "ООО  \"Агроторг\"", "retailPlace": "Пятерочка" } },
+    });
+    assert_eq!(seller_of(&payload), Some("ООО \"Агроторг\"".to_string()));
+    let unknown = json!({ "code": 3, "data": "чек не найден" });
+    assert_eq!(seller_of(&unknown), None);
+}
+
+#[test]
+fn scanned_receipts_become_transactions_once() {
+    let app = TestApp::logged_in();
+    let food_id = app.fixtures.food_id.to_string();
+    let fields = |qr| {
+        [
+            ("qr", qr),
+            ("category_id", food_id.as_str()),
+            ("payee", "Пятерочка"),
+        ]
+    };
+    let response = app.post_form("/receipts/qr", &fields(PURCHASE));
+    assert_eq!(location(&response), Some("/transactions?month=2026-03"));
+    let saved = db::list_transactions(&app.conn(), &TransactionQuery::month("2026-03")).unwrap();
+    assert_eq!(saved.len(), 1);
+    assert_eq!(saved[0].amount_cents, 123_450);
+    assert_eq!(saved[0].occurred_on, "2026-03-10");
+    assert_eq!(saved[0].payee.as_deref(), Some("Пятерочка"));
+    assert_eq!(saved[0].category_id, Some(app.fixtures.food_id));
+
+    let again = app.post_form("/receipts/qr", &fields(PURCHASE));
+    assert_eq!(again.status(), Status::UnprocessableEntity);
+    assert!(
+        again
+            .into_string()
+            .unwrap()
+            .contains("Операция по этому чеку уже добавлена")
+    );
+
+    let refund = PURCHASE.replace("n=1", "n=2").replace("i=12345", "i=12346");
+    let response = app.post_form("/receipts/qr", &fields(&refund));
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+    assert!(
+        response
+            .into_string()
+            .unwrap()
+            .contains("Это чек возврата, выберите категорию доходов")
+    );
+}
@@ -0,0 +1,238 @@
+use std::io::Read;
+use std::time::{Duration, Instant};
+
+use rocket::http::Status;
+use serde_json::Value;
+
+use super::{location, TestApp};
+use crate::query::TransactionQuery;
+use crate::receipt_storage::Local;
+use crate::{accountant, db};
+
+fn job_id(url: &str) -> i64 {
+    url.trim_start_matches("/jobs/").parse().unwrap()
+}
+
+/// Polls the job's API until it stops running.
+pub(super) fn wait_for(app: &TestApp, url: &str) -> Value {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let api = format!("/api/v1/jobs/{}", job_id(url));
+    loop {
+        let body = app.get(&api).into_string().unwrap();
+        let progress: Value = serde_json::from_str(&body).unwrap();
+        if progress["state"] != "running" {
+            return progress;
+        }
+        assert!(Instant::now() < deadline, "job still running: {body}");
+        std::thread::sleep(Duration::from_millis(20));
+    }
+}
+
+/// Removes the file a finished job left in the exports directory.
+fn remove_job_file(app: &TestApp, url: &str) {
+    let job = db::job_by_id(&app.conn(), job_id(url)).unwrap().unwrap();
+    if let Some(path) = job.file_path {
+        std::fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+fn imports_run_as_jobs() {
+    let app = TestApp::logged_in();
+    let token = uuid::Uuid::new_v4();
+    let dir = crate::import::imports_dir();
+    std::fs::create_dir_all(&dir).unwrap();
+    let csv = (1..=1200)
+        .map(|day| format!("2026-01-{:02},-{day}\n", day % 28 + 1))
+        .collect::<String>();
+    let path = dir.join(format!("{token}.csv"));
+    std::fs::write(&path, format!("Дата,Сумма\n{csv}")).unwrap();
+
+    let response = app.post_form(
+        &format!("/import/{token}"),
+        &[("date_col", "0"), ("amount_col", "1"), ("has_header", "true")],
+    );
+    assert_eq!(response.status(), Status::SeeOther);
+    let url = location(&response).unwrap().to_string();
+    assert!(url.starts_with("/jobs/"), "{url}");
+
+    let progress = wait_for(&app, &url);
+    assert_eq!(progress["state"], "done");
+    assert_eq!(progress["done"], 1200);
+    assert_eq!(progress["total"], 1200);
+    assert_eq!(progress["percent"], 100);
+    assert_eq!(progress["links"]["result"], "/transactions");
+    assert_eq!(progress["links"]["page"], url);
+    let records = db::list_transactions(&app.conn(), &TransactionQuery::default()).unwrap();
+    assert_eq!(records.len(), 1200);
+    assert!(!path.exists());
+    let page = app.get(&url).into_string().unwrap();
+    assert!(page.contains("Импорт CSV, операций: 1200"));
+    assert!(!page.contains("http-equiv=\"refresh\""));
+}
+
+#[test]
+fn archives_are_built_in_the_background() {
+    let app = TestApp::logged_in();
+    let response = app.post_form("/transactions/archive", &[("month", "2026-01")]);
+    let url = location(&response).unwrap().to_string();
+    let progress = wait_for(&app, &url);
+    assert_eq!(progress["state"], "done");
+    let download = progress["links"]["result"].as_str().unwrap().to_string();
+    assert_eq!(download, format!("{url}/download"));
+
+    let response = app.get(&download);
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        response.headers().get_one("Content-Disposition"),
+        Some("attachment; filename=\"lumen-2026-01.zip\"")
+    );
+    let bytes = response.into_bytes().unwrap();
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).unwrap();
+    let mut csv = String::new();
+    archive
+        .by_name("transactions-2026-01.csv")
+        .unwrap()
+        .read_to_string(&mut csv)
+        .unwrap();
+    assert!(csv.contains("Дата"));
+    remove_job_file(&app, &url);
+
+    assert_eq!(
+        app.post_form("/transactions/archive", &[("month", "2026-13")])
+            .status(),
+        Status::BadRequest
+    );
+}
+
+#[test]
+fn receipt_shared_by_two_transactions_is_archived_once() {
+    let app = TestApp::logged_in();
+    let dir = std::env::temp_dir().join(format!("lumen-archive-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("shared.jpg"), b"not really a jpeg").unwrap();
+    for amount in ["100", "200"] {
+        let id = app.spend(amount, "2026-03-10", &[]);
+        assert!(db::attach_receipt(&app.conn(), id, "shared.jpg").unwrap());
+    }
+
+    let mut written = Vec::new();
+    let archive =
+        accountant::build_archive(&app.conn(), &Local::new(&dir), "2026-03", |done, total| {
+            written.push((done, total))
+        });
+    std::fs::remove_dir_all(&dir).unwrap();
+    let archive = zip::ZipArchive::new(std::io::Cursor::new(archive.unwrap())).unwrap();
+    let mut names = archive.file_names().collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(names, ["receipts/shared.jpg", "transactions-2026-03.csv"]);
+    assert_eq!(written, [(1, 2), (2, 2)]);
+}
+
+#[test]
+fn jobs_belong_to_their_user() {
+    let app = TestApp::logged_in();
+    let url = location(&app.post_form("/transactions/archive", &[("month", "2026-01")]))
+        .unwrap()
+        .to_string();
+    wait_for(&app, &url);
+    remove_job_file(&app, &url);
+    assert_eq!(app.get("/jobs/999").status(), Status::NotFound);
+    assert_eq!(app.get("/api/v1/jobs/999").status(), Status::NotFound);
+
+    app.post_form("/logout", &[]);
+    let response = app.get(&url);
+    assert_eq!(response.status(), Status::SeeOther);
+    assert_eq!(location(&response), Some("/login"));
+    let api = format!("/api/v1/jobs/{}", job_id(&url));
+    assert_eq!(app.get(&api).status(), Status::Unauthorized);
+}
+
+#[test]
+fn jobs_outlive_a_restart() {
+    let app = TestApp::logged_in();
+    let url = location(&app.post_form("/transactions/archive", &[("month", "2026-01")]))
+        .unwrap()
+        .to_string();
+    wait_for(&app, &url);
+    remove_job_file(&app, &url);
+    let conn = app.conn();
+    let user_id = db::job_by_id(&conn, job_id(&url)).unwrap().unwrap().user_id;
+    let orphan = db::insert_job(&conn, user_id, "Импорт CSV", "2026-01-01T00:00:00+03:00").unwrap();
+
+    let jobs = crate::jobs::Jobs::new(app.pool.clone());
+    let orphan = jobs.get(&conn, orphan).unwrap().unwrap();
+    assert_eq!(orphan.state, "failed");
+    assert_eq!(orphan.error.as_deref(), Some("Прервано перезапуском сервера"));
+    assert!(orphan.finished_at.is_some());
+    let finished = jobs.get(&conn, job_id(&url)).unwrap().unwrap();
+    assert_eq!(finished.state, "done");
+    assert_eq!(finished.result_url, Some(format!("{url}/download")));
+}
+
+#[test]
+fn byte_ranges() {
+    use crate::jobs::ByteRange;
+
+    assert_eq!(ByteRange::parse("bytes=500-"), Some(ByteRange::From(500)));
+    assert_eq!(ByteRange::parse("bytes=0-99"), Some(ByteRange::Between(0, 99)));
+    assert_eq!(ByteRange::parse("bytes=-20"), Some(ByteRange::Last(20)));
+    assert_eq!(ByteRange::parse("bytes=5-1"), None);
+    assert_eq!(ByteRange::parse("bytes=0-1,5-9"), None);
+    assert_eq!(ByteRange::parse("items=0-1"), None);
+
+    assert_eq!(ByteRange::From(500).resolve(1000), Some((500, 999)));
+    assert_eq!(ByteRange::Between(900, 5000).resolve(1000), Some((900, 999)));
+    assert_eq!(ByteRange::Last(2000).resolve(1000), Some((0, 999)));
+    assert_eq!(ByteRange::From(1000).resolve(1000), None);
+    assert_eq!(ByteRange::Last(0).resolve(1000), None);
+}
+
+#[test]
+fn exports_are_kept_for_download() {
+    let app = TestApp::logged_in();
+    let export = app.get("/transactions/export.csv?month=2026-01");
+    assert_eq!(export.status(), Status::Ok);
+    let csv = export.into_bytes().unwrap();
+
+    let page = app.get("/downloads").into_string().unwrap();
+    assert!(page.contains("Операции за 2026-01"));
+    assert!(page.contains("transactions-2026-01.csv"));
+    let conn = app.conn();
+    let (user_id, _) = db::user_credentials(&conn, super::USERNAME).unwrap().unwrap();
+    let kept = db::list_downloads(&conn, user_id, "2026-01-01T00:00:00+03:00").unwrap();
+    assert_eq!(kept.len(), 1);
+    let url = format!("/jobs/{}", kept[0].id);
+
+    let whole = app.get(&format!("{url}/download"));
+    assert_eq!(whole.headers().get_one("Accept-Ranges"), Some("bytes"));
+    assert_eq!(whole.headers().get_one("Content-Encoding"), None);
+    assert_eq!(whole.into_bytes().unwrap(), csv);
+
+    let tail = app
+        .client
+        .get(format!("{url}/download"))
+        .header(rocket::http::Header::new("Range", "bytes=3-"))
+        .header(rocket::http::Header::new("Accept-Encoding", "gzip"))
+        .dispatch();
+    assert_eq!(tail.status(), Status::PartialContent);
+    assert_eq!(
+        tail.headers().get_one("Content-Range"),
+        Some(format!("bytes 3-{}/{}", csv.len() - 1, csv.len()).as_str())
+    );
+    assert_eq!(tail.into_bytes().unwrap(), csv[3..]);
+
+    let past_end = app
+        .client
+        .get(format!("{url}/download"))
+        .header(rocket::http::Header::new("Range", format!("bytes={}-", csv.len())))
+        .dispatch();
+    assert_eq!(past_end.status(), Status::RangeNotSatisfiable);
+
+    let path = kept[0].file_path.clone().unwrap();
+    let far_future = "9999-01-01T00:00:00+00:00";
+    assert_eq!(db::expire_downloads(&conn, far_future).unwrap(), vec![path.clone()]);
+    std::fs::remove_file(path).unwrap();
+    assert!(!app.get("/downloads").into_string().unwrap().contains("transactions-2026-01.csv"));
+    assert_eq!(app.get(&format!("{url}/download")).status(), Status::NotFound);
+}
@@ -0,0 +1,177 @@
+use rocket::http::Status;
+
+use super::{TestApp, location};
+use crate::db;
+use crate::loans::{self, LoanStatus};
+use crate::models::{Loan, LoanPayment};
+use crate::query::TransactionQuery;
+
+fn loan(principal_cents: i64, annual_rate: f64, term_months: i64) -> Loan {
+    Loan {
+        id: 1,
+        name: "Ипотека".to_string(),
+        principal_cents,
+        annual_rate,
+        term_months,
+        first_month: "2026-01".to_string(),
+        payment_day: 31,
+    }
+}
+
+#[test]
+fn annuity_schedule_repays_the_principal() {
+    let loan = loan(10_000_000, 12.0, 12);
+    assert_eq!(loans::monthly_payment(&loan), 888_488);
+    let schedule = loans::schedule(&loan);
+    assert_eq!(schedule.len(), 12);
+    assert_eq!(schedule[0].interest_cents, 100_000);
+    assert_eq!(schedule[0].principal_cents, 788_488);
+    assert_eq!(schedule[0].balance_cents, 9_211_512);
+    assert_eq!(
+        schedule.iter().map(|i| i.principal_cents).sum::<i64>(),
+        10_000_000
+    );
+    assert_eq!(schedule[11].balance_cents, 0);
+    assert!((schedule[11].payment_cents - 888_488).abs() < 10);
+    let dates = schedule[..3]
+        .iter()
+        .map(|i| i.due_on.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(dates, ["2026-01-31", "2026-02-28", "2026-03-31"]);
+}
+
+#[test]
+fn interest_free_loans_split_evenly() {
+    let schedule = loans::schedule(&loan(100_000, 0.0, 3));
+    let payments = schedule
+        .iter()
+        .map(|i| (i.payment_cents, i.interest_cents))
+        .collect::<Vec<_>>();
+    assert_eq!(payments, [(33_334, 0), (33_334, 0), (33_332, 0)]);
+}
+
+#[test]
+fn status_follows_the_actual_payments() {
+    let loan = loan(10_000_000, 12.0, 12);
+    let payment = |amount_cents| LoanPayment {
+        transaction_id: 0,
+        occurred_on: "2026-01-31".to_string(),
+        amount_cents,
+    };
+    let schedule = loans::schedule(&loan);
+    let status = loans::status(&loan, &[payment(888_488), payment(888_488)]);
+    assert_eq!(status.remaining_cents, schedule[1].balance_cents);
+    assert_eq!(
+        status.interest_paid_cents,
+        schedule[0].interest_cents + schedule[1].interest_cents
+    );
+    assert_eq!(status.paid_cents, 1_776_976);
+
+    // An early payoff stops at zero instead of going negative.
+    let status = loans::status(&loan, &[payment(20_000_000)]);
+    assert_eq!(
+        status,
+        LoanStatus {
+            paid_cents: 20_000_000,
+            principal_paid_cents: 10_000_000,
+            interest_paid_cents: 100_000,
+            remaining_cents: 0,
+        }
+    );
+}
+
+#[test]
+fn payments_are_recorded_as_linked_expenses() {
+    let app = TestApp::logged_in();
+    let response = app.post_form(
+        "/loans",
+        &[
+            ("name", "Машина"),
+            ("principal", "100000"),
+            ("annual_rate", "12"),
+            ("term_months", "12"),
+            ("first_month", "2026-01"),
+            ("payment_day", "15"),
+        ],
+    );
+    assert_eq!(response.status(), Status::SeeOther);
+    let url = location(&response).unwrap().to_string();
+    let id = db::list_loans(&app.conn()).unwrap()[0].id;
+    assert_eq!(url, format!("/loans/{id}"));
+
+    let card_id = app.fixtures.card_id.to_string();
+    let status = app
+        .post_form(
+            &format!("/loans/{id}/payments"),
+            &[
+                ("amount", "8884.88"),
+                ("occurred_on", "2026-01-15"),
+                ("account_id", &card_id),
+                ("category_id", ""),
+            ],
+        )
+        .status();
+    assert_eq!(status, Status::SeeOther);
+
+    let payments = db::loan_payments(&app.conn(), id).unwrap();
+    assert_eq!(payments.len(), 1);
+    let records = db::list_transactions(&app.conn(), &TransactionQuery::default()).unwrap();
+    assert_eq!(records[0].id, payments[0].transaction_id);
+    assert_eq!(records[0].kind, "expense");
+    assert_eq!(
+        records[0].note.as_deref(),
+        Some("Платёж по кредиту: Машина")
+    );
+
+    let page = app.get(&url).into_string().unwrap();
+    assert!(page.contains("92115.12"), "remaining balance");
+    assert!(page.contains("2026-02-15"), "next due date");
+    assert!(app.get("/loans").into_string().unwrap().contains("Машина"));
+
+    app.post_form(&format!("/loans/{id}/delete"), &[]);
+    assert!(db::list_loans(&app.conn()).unwrap().is_empty());
+    assert_eq!(app.get(&url).status(), Status::NotFound);
+    let records = db::list_transactions(&app.conn(), &TransactionQuery::default()).unwrap();
+    assert_eq!(records.len(), 1);
+}
+
+#[test]
+fn invalid_loans_are_rejected() {
+    let app = TestApp::logged_in();
+    let valid = [
+        ("name", "Кредит"),
+        ("principal", "1000"),
+        ("annual_rate", "10"),
+        ("term_months", "12"),
+        ("first_month", "2026-01"),
+        ("payment_day", "10"),
+    ];
+    for (field, value) in [
+        ("name", " "),
+        ("principal", "0"),
+        ("annual_rate", "-1"),
+        ("annual_rate", "ten"),
+        ("term_months", "0"),
+        ("first_month", "2026-13"),
+        ("payment_day", "32"),
+    ] {
+        let fields = valid
+            .iter()
+            .map(|&(name, default)| (name, if name == field { value } else { default }))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            app.post_form("/loans", &fields).status(),
+            Status::BadRequest,
+            "{field}={value}"
+        );
+    }
+    assert!(db::list_loans(&app.conn()).unwrap().is_empty());
+    assert_eq!(
+        app.post_form(
+            "/loans/99/payments",
+            &[("amount", "10"), ("occurred_on", "")]
+        )
+        .status(),
+        Status::NotFound
+    );
+}
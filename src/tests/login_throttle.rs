@@ -0,0 +1,111 @@
+use std::net::SocketAddr;
+
+use chrono::Duration;
+use rocket::http::{ContentType, Header, Status};
+use rocket::local::blocking::LocalResponse;
+
+use super::{PASSWORD, TestApp, USERNAME, location};
+use crate::login_throttle::{LOCKOUT, delay};
+
+fn login_from<'a>(app: &'a TestApp, remote: &str, password: &str) -> LocalResponse<'a> {
+    app.client
+        .post("/login")
+        .remote(remote.parse::<SocketAddr>().unwrap())
+        .header(ContentType::Form)
+        .body(format!("username={USERNAME}&password={password}"))
+        .dispatch()
+}
+
+fn body(response: LocalResponse<'_>) -> String {
+    response.into_string().unwrap()
+}
+
+/// Sets the failure counts as if more attempts had just failed; a real run of
+/// them would outlast the shorter waits, hashing passwords in a debug build.
+fn set_failures(app: &TestApp, failures: i64) {
+    app.conn()
+        .execute("UPDATE login_failures SET failures = ?1", [failures])
+        .unwrap();
+}
+
+#[test]
+fn waits_double_up_to_the_lockout() {
+    assert_eq!(delay(3), Duration::zero());
+    assert_eq!(delay(4), Duration::seconds(1));
+    assert_eq!(delay(6), Duration::seconds(4));
+    assert_eq!(delay(10), LOCKOUT);
+}
+
+#[test]
+fn repeated_failures_hold_off_even_the_right_password() {
+    let app = TestApp::new();
+    for _ in 0..2 {
+        let page = body(login_from(&app, "10.0.0.1:5000", "wrong"));
+        assert!(page.contains("Неверный логин или пароль"));
+    }
+    set_failures(&app, 9);
+    let held = body(login_from(&app, "10.0.0.1:5000", PASSWORD));
+    assert!(held.contains("Повторите через"), "{held}");
+    // The name is held off from another address too.
+    let elsewhere = body(login_from(&app, "10.0.0.2:5000", PASSWORD));
+    assert!(elsewhere.contains("Повторите через"));
+
+    app.conn()
+        .execute(
+            "UPDATE login_failures SET last_failed_at = '2000-01-01T00:00:00Z'",
+            [],
+        )
+        .unwrap();
+    assert_eq!(
+        location(&login_from(&app, "10.0.0.1:5000", PASSWORD)),
+        Some("/")
+    );
+    let count: i64 = app
+        .conn()
+        .query_row("SELECT COUNT(*) FROM login_failures", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(count, 0);
+}
+
+#[test]
+fn forged_addresses_count_against_the_peer() {
+    let app = TestApp::new();
+    for forged in ["198.51.100.1", "198.51.100.2", "198.51.100.3"] {
+        app.client
+            .post("/login")
+            .remote("10.0.0.1:5000".parse::<SocketAddr>().unwrap())
+            .header(Header::new("X-Real-IP", forged))
+            .header(ContentType::Form)
+            .body("username=someone&password=wrong")
+            .dispatch();
+    }
+    let failures = |key: &str| -> Option<i64> {
+        app.conn()
+            .query_row(
+                "SELECT failures FROM login_failures WHERE key = ?1",
+                [key],
+                |row| row.get(0),
+            )
+            .ok()
+    };
+    assert_eq!(failures("ip:10.0.0.1"), Some(3));
+    assert_eq!(failures("ip:198.51.100.1"), None);
+}
+
+#[test]
+fn locked_out_address_sees_the_wait_on_the_login_page() {
+    let app = TestApp::new();
+    login_from(&app, "10.0.0.1:5000", "wrong");
+    set_failures(&app, 10);
+    let page = |remote: &str| {
+        let response = app
+            .client
+            .get("/login")
+            .remote(remote.parse::<SocketAddr>().unwrap())
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        body(response)
+    };
+    assert!(page("10.0.0.1:5000").contains("Повторите через 15 мин"));
+    assert!(!page("10.0.0.2:5000").contains("Повторите через"));
+}
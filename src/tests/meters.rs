@@ -0,0 +1,276 @@
+use rocket::http::Status;
+
+use super::{TestApp, location};
+use crate::db;
+use crate::meters::{
+    Bill, bills, format_value, out_of_order, parse_value, payment_window, rate_on, usage,
+};
+use crate::models::{Meter, MeterReading, Tariff};
+use crate::query::TransactionQuery;
+
+fn reading(month: &str, value: f64) -> MeterReading {
+    MeterReading {
+        id: 0,
+        month: month.to_string(),
+        value,
+        read_on: format!("{month}-25"),
+        transaction_id: None,
+        paid_on: None,
+        paid_cents: None,
+    }
+}
+
+fn tariff(kind: &str, rate_cents: i64, effective_from: &str) -> Tariff {
+    Tariff {
+        id: 0,
+        kind: kind.to_string(),
+        rate_cents,
+        effective_from: effective_from.to_string(),
+    }
+}
+
+fn meter(name: &str, kind: &str) -> Meter {
+    Meter {
+        id: 0,
+        name: name.to_string(),
+        kind: kind.to_string(),
+    }
+}
+
+fn record(app: &TestApp, id: i64, month: &str, value: &str) -> Status {
+    app.post_form(
+        &format!("/meters/{id}/readings"),
+        &[("month", month), ("value", value)],
+    )
+    .status()
+}
+
+#[test]
+fn usage_is_the_difference_from_the_month_before() {
+    let readings = [
+        reading("2026-01", 1200.0),
+        reading("2026-02", 1350.5),
+        reading("2026-04", 1500.0),
+    ];
+    assert_eq!(usage(&readings), [None, Some(150.5), Some(149.5)]);
+    assert_eq!(out_of_order(&readings, "2026-03", 1400.0), None);
+    assert!(out_of_order(&readings, "2026-03", 1300.0).is_some());
+    assert!(out_of_order(&readings, "2026-03", 1600.0).is_some());
+    assert_eq!(parse_value("1234,56"), Some(1234.56));
+    assert_eq!(parse_value("-1"), None);
+    assert_eq!(format_value(1350.5), "1350.5");
+    assert_eq!(format_value(1200.0), "1200");
+    assert_eq!(
+        payment_window("2026-12"),
+        Some(("2026-12-01".to_string(), "2027-01-31".to_string()))
+    );
+}
+
+#[test]
+fn readings_are_linked_to_the_utility_expense_that_paid_them() {
+    let app = TestApp::logged_in();
+    let response = app.post_form("/meters", &[("name", "Квартира"), ("kind", "electricity")]);
+    assert_eq!(response.status(), Status::SeeOther);
+    let id = location(&response)
+        .and_then(|path| path.strip_prefix("/meters/"))
+        .and_then(|id| id.parse::<i64>().ok())
+        .unwrap();
+    assert_eq!(
+        app.post_form("/meters", &[("name", "Дача"), ("kind", "steam")])
+            .status(),
+        Status::UnprocessableEntity
+    );
+
+    assert_eq!(record(&app, id, "2026-02", "1200"), Status::SeeOther);
+    assert_eq!(record(&app, id, "2026-03", "1350,5"), Status::SeeOther);
+    assert_eq!(
+        record(&app, id, "2026-04", "1300"),
+        Status::UnprocessableEntity
+    );
+    assert_eq!(
+        record(&app, id, "март", "1400"),
+        Status::UnprocessableEntity
+    );
+    let page = app.get(&format!("/meters/{id}")).into_string().unwrap();
+    assert!(page.contains("150.5 кВт·ч"));
+    assert!(page.contains("Расходов ЖКХ за 2026-03"));
+
+    let conn = app.conn();
+    db::insert_category(&conn, "ЖКХ", "expense", None).unwrap();
+    let utilities = conn.last_insert_rowid().to_string();
+    for (category_id, occurred_on) in [
+        (utilities.as_str(), "2026-04-10"),
+        (&app.fixtures.food_id.to_string(), "2026-04-11"),
+    ] {
+        app.post_form(
+            "/transactions",
+            &[
+                ("kind", "expense"),
+                ("amount", "2480.40"),
+                ("category_id", category_id),
+                ("occurred_on", occurred_on),
+                ("payee", "Мосэнергосбыт"),
+            ],
+        );
+    }
+    let transactions = db::list_transactions(&app.conn(), &TransactionQuery::default()).unwrap();
+    let paid = transactions
+        .iter()
+        .find(|t| t.occurred_on == "2026-04-10")
+        .unwrap()
+        .id;
+    let groceries = transactions
+        .iter()
+        .find(|t| t.occurred_on == "2026-04-11")
+        .unwrap()
+        .id;
+    let readings = db::meter_readings(&app.conn(), id).unwrap();
+    let march = readings.iter().find(|r| r.month == "2026-03").unwrap().id;
+    let link = format!("/meters/{id}/readings/{march}/expense");
+    assert_eq!(
+        app.post_form(&link, &[("transaction_id", &groceries.to_string())])
+            .status(),
+        Status::BadRequest
+    );
+    assert_eq!(
+        app.post_form(&link, &[("transaction_id", &paid.to_string())])
+            .status(),
+        Status::SeeOther
+    );
+    let readings = db::meter_readings(&app.conn(), id).unwrap();
+    let march = readings.iter().find(|r| r.month == "2026-03").unwrap();
+    assert_eq!(march.transaction_id, Some(paid));
+    assert_eq!(march.paid_cents, Some(248040));
+    assert!(
+        app.get(&format!("/meters/{id}"))
+            .into_string()
+            .unwrap()
+            .contains("2026-04-10 · 2480.40")
+    );
+
+    app.post_form(&format!("/transactions/{paid}/delete"), &[]);
+    let readings = db::meter_readings(&app.conn(), id).unwrap();
+    assert_eq!(readings[1].paid_on, None);
+}
+
+#[test]
+fn bills_price_the_usage_at_the_tariff_of_the_month() {
+    let tariffs = [
+        tariff("electricity", 600, "2026-01-01"),
+        tariff("electricity", 650, "2026-07-01"),
+        tariff("cold_water", 5000, "2026-01-01"),
+    ];
+    assert_eq!(rate_on(&tariffs, "electricity", "2026-06"), Some(600));
+    assert_eq!(rate_on(&tariffs, "electricity", "2026-07"), Some(650));
+    assert_eq!(rate_on(&tariffs, "gas", "2026-07"), None);
+
+    let paid = |mut reading: MeterReading, id: i64, cents: i64| {
+        reading.transaction_id = Some(id);
+        reading.paid_on = Some(format!("{}-10", reading.month));
+        reading.paid_cents = Some(cents);
+        reading
+    };
+    let meters = [
+        (
+            meter("Свет", "electricity"),
+            vec![
+                reading("2026-06", 1000.0),
+                paid(reading("2026-07", 1100.0), 7, 120000),
+            ],
+        ),
+        (
+            meter("Вода", "cold_water"),
+            vec![
+                reading("2026-06", 50.0),
+                paid(reading("2026-07", 60.0), 7, 120000),
+            ],
+        ),
+        (
+            meter("Газ", "gas"),
+            vec![reading("2026-07", 10.0), reading("2026-08", 12.0)],
+        ),
+    ];
+    let july = Bill {
+        month: "2026-07".to_string(),
+        metered: 2,
+        expected_cents: 100 * 650 + 10 * 5000,
+        unpriced: Vec::new(),
+        paid_cents: Some(120000),
+    };
+    let august = Bill {
+        month: "2026-08".to_string(),
+        metered: 1,
+        expected_cents: 0,
+        unpriced: vec!["Газ".to_string()],
+        paid_cents: None,
+    };
+    let found = bills(&meters, &tariffs, 12);
+    assert_eq!(found, [august, july]);
+    assert_eq!(found[1].overcharge_cents(), Some(5000));
+    assert_eq!(found[0].overcharge_cents(), None);
+    assert_eq!(bills(&meters, &tariffs, 1).len(), 1);
+}
+
+#[test]
+fn tariffs_show_an_overcharge_against_the_linked_payment() {
+    let app = TestApp::logged_in();
+    let response = app.post_form("/meters", &[("name", "Свет"), ("kind", "electricity")]);
+    let id = location(&response)
+        .and_then(|path| path.strip_prefix("/meters/"))
+        .and_then(|id| id.parse::<i64>().ok())
+        .unwrap();
+    assert_eq!(record(&app, id, "2026-05", "1000"), Status::SeeOther);
+    assert_eq!(record(&app, id, "2026-06", "1200"), Status::SeeOther);
+    assert!(
+        app.get("/meters")
+            .into_string()
+            .unwrap()
+            .contains("нет тарифа: Свет")
+    );
+
+    let tariff = |rate: &str, effective_from: &str| {
+        app.post_form(
+            "/meters/tariffs",
+            &[
+                ("kind", "electricity"),
+                ("rate", rate),
+                ("effective_from", effective_from),
+            ],
+        )
+        .status()
+    };
+    assert_eq!(tariff("0", "2026-01-01"), Status::UnprocessableEntity);
+    assert_eq!(tariff("6.43", "1 января"), Status::UnprocessableEntity);
+    assert_eq!(tariff("6.43", "2026-01-01"), Status::SeeOther);
+    assert_eq!(tariff("5.00", "2026-01-01"), Status::SeeOther);
+    assert_eq!(db::list_tariffs(&app.conn()).unwrap().len(), 1);
+
+    let conn = app.conn();
+    db::insert_category(&conn, "ЖКХ", "expense", None).unwrap();
+    let utilities = conn.last_insert_rowid().to_string();
+    app.post_form(
+        "/transactions",
+        &[
+            ("kind", "expense"),
+            ("amount", "1100"),
+            ("category_id", &utilities),
+            ("occurred_on", "2026-07-05"),
+        ],
+    );
+    let paid = db::list_transactions(&conn, &TransactionQuery::default()).unwrap()[0].id;
+    let june = db::meter_readings(&conn, id).unwrap()[1].id;
+    app.post_form(
+        &format!("/meters/{id}/readings/{june}/expense"),
+        &[("transaction_id", &paid.to_string())],
+    );
+
+    let page = app.get("/meters").into_string().unwrap();
+    assert!(page.contains("1000.00"));
+    assert!(page.contains("переплата 100.00"));
+    assert!(
+        app.get(&format!("/meters/{id}"))
+            .into_string()
+            .unwrap()
+            .contains("1000.00")
+    );
+}
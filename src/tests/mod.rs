@@ -0,0 +1,245 @@
+//! Test support: the full app on an in-memory database with seeded fixtures.
+
+mod account_deletion;
+mod activity;
+mod allowlist;
+mod api;
+mod assets;
+mod auth;
+mod budgets;
+mod cash;
+mod categories;
+mod compression;
+mod csrf;
+mod daily_summary;
+mod dates;
+mod digest;
+mod disputes;
+mod effective_months;
+mod errors;
+mod fiscal_receipts;
+mod jobs;
+mod loans;
+mod login_throttle;
+mod meters;
+mod months;
+mod networth;
+mod no_js;
+mod password_strength;
+mod properties;
+mod query;
+mod query_inspector;
+mod receipt_files;
+mod receipt_items;
+mod receipts;
+mod remember;
+mod reports;
+mod rules;
+mod security_headers;
+mod sessions;
+mod statements;
+mod suggestions;
+mod transactions;
+mod trash;
+mod users;
+mod validation;
+
+use std::sync::Arc;
+
+use chrono::Local;
+use rocket::http::{ContentType, RawStr, Status};
+use rocket::local::blocking::{Client, LocalResponse};
+use rocket::{Build, Rocket};
+
+use crate::allowlist::Allowlist;
+use crate::db::{self, DbPool, PoolConfig, PoolMetrics};
+
+pub const USERNAME: &str = "tester";
+pub const PASSWORD: &str = "secret12";
+
+/// Ids of the rows every seeded app starts with.
+pub struct Fixtures {
+    pub food_id: i64,
+    pub salary_id: i64,
+    pub card_id: i64,
+    pub cash_id: i64,
+}
+
+pub struct TestApp {
+    pub client: Client,
+    pub pool: DbPool,
+    pub fixtures: Fixtures,
+}
+
+impl TestApp {
+    /// App with a user, an expense and an income category and two ruble
+    /// accounts; nobody is logged in yet.
+    pub fn new() -> Self {
+        Self::with_pool(&PoolConfig::default())
+    }
+
+    /// Seeded app on a pool built from `config`.
+    pub fn with_pool(config: &PoolConfig) -> Self {
+        Self::seeded(config, None)
+    }
+
+    /// Seeded app whose maintenance pages only answer `networks`.
+    pub fn with_admin_networks(networks: Allowlist) -> Self {
+        Self::seeded(&PoolConfig::default(), Some(networks))
+    }
+
+    /// Seeded app with `value` at `key` in Rocket's configuration.
+    pub fn with_config(key: &str, value: impl serde::Serialize) -> Self {
+        Self::seeded_with(&PoolConfig::default(), None, |rocket| {
+            let figment = rocket.figment().clone().merge((key, value));
+            rocket.configure(figment)
+        })
+    }
+
+    fn seeded(config: &PoolConfig, admin_networks: Option<Allowlist>) -> Self {
+        Self::seeded_with(config, admin_networks, |rocket| rocket)
+    }
+
+    fn seeded_with(
+        config: &PoolConfig,
+        admin_networks: Option<Allowlist>,
+        configure: impl FnOnce(Rocket<Build>) -> Rocket<Build>,
+    ) -> Self {
+        let metrics = Arc::new(PoolMetrics::default());
+        let pool = db::init_memory_db(config, metrics.clone());
+        let fixtures = seed(&pool);
+        let rocket = crate::build_rocket(pool.clone(), metrics, None, None, None, None, admin_networks);
+        let client = Client::tracked(configure(rocket)).expect("valid rocket instance");
+        TestApp {
+            client,
+            pool,
+            fixtures,
+        }
+    }
+
+    /// Seeded app with the fixture user's session cookie already set.
+    pub fn logged_in() -> Self {
+        let app = Self::new();
+        let status = app.login(USERNAME, PASSWORD).status();
+        assert_eq!(status, Status::SeeOther);
+        app
+    }
+
+    /// App on an empty database, before the first user is set up.
+    pub fn empty() -> Self {
+        let metrics = Arc::new(PoolMetrics::default());
+        let pool = db::init_memory_db(&PoolConfig::default(), metrics.clone());
+        let rocket = crate::build_rocket(pool.clone(), metrics, None, None, None, None, None);
+        let client = Client::tracked(rocket).expect("valid rocket instance");
+        TestApp {
+            client,
+            pool,
+            fixtures: Fixtures {
+                food_id: 0,
+                salary_id: 0,
+                card_id: 0,
+                cash_id: 0,
+            },
+        }
+    }
+
+    pub fn conn(&self) -> r2d2::PooledConnection<r2d2_sqlite::SqliteConnectionManager> {
+        self.pool.get().expect("db connection")
+    }
+
+    /// Deletes the files exports left in the exports directory, which the
+    /// in-memory database doesn't own.
+    pub fn remove_downloads(&self) {
+        for path in db::expire_downloads(&self.conn(), "9999-12-31").unwrap() {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    /// Adds a food expense of `amount` rubles through the transaction form and
+    /// returns its id; `extra` fields are sent too and replace the defaults of
+    /// the same name. Needs a logged-in app.
+    pub fn spend(&self, amount: &str, occurred_on: &str, extra: &[(&str, &str)]) -> i64 {
+        let food_id = self.fixtures.food_id.to_string();
+        let defaults = [
+            ("kind", "expense"),
+            ("amount", amount),
+            ("category_id", food_id.as_str()),
+            ("occurred_on", occurred_on),
+        ];
+        let fields: Vec<(&str, &str)> = defaults
+            .into_iter()
+            .filter(|(name, _)| extra.iter().all(|(other, _)| other != name))
+            .chain(extra.iter().copied())
+            .collect();
+        let response = self.post_form("/transactions", &fields);
+        assert_eq!(response.status(), Status::SeeOther);
+        self.conn()
+            .query_row("SELECT MAX(id) FROM transactions", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    pub fn login(&self, username: &str, password: &str) -> LocalResponse<'_> {
+        self.post_form("/login", &[("username", username), ("password", password)])
+    }
+
+    pub fn get(&self, path: &str) -> LocalResponse<'_> {
+        self.client.get(path.to_string()).dispatch()
+    }
+
+    /// The token the logged-in session's forms carry, see `csrf`; none while
+    /// the pool has no connection to spare.
+    pub fn csrf_token(&self) -> Option<String> {
+        let session = self.client.cookies().get("session")?.value().to_string();
+        let conn = self.pool.get().ok()?;
+        db::session_csrf_token(&conn, &session).unwrap()
+    }
+
+    /// Posts `fields` URL-encoded, the way the HTML forms submit them: the
+    /// session's CSRF token first.
+    pub fn post_form(&self, path: &str, fields: &[(&str, &str)]) -> LocalResponse<'_> {
+        let token = self.csrf_token();
+        let body = token
+            .iter()
+            .map(|token| (crate::csrf::FIELD, token.as_str()))
+            .chain(fields.iter().copied())
+            .map(|(name, value)| {
+                format!(
+                    "{}={}",
+                    RawStr::new(name).percent_encode(),
+                    RawStr::new(value).percent_encode()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("&");
+        self.client
+            .post(path.to_string())
+            .header(ContentType::Form)
+            .body(body)
+            .dispatch()
+    }
+}
+
+fn seed(pool: &DbPool) -> Fixtures {
+    let conn = pool.get().expect("db connection");
+    let hash = crate::hash_password(PASSWORD).expect("hash password");
+    db::insert_user(&conn, USERNAME, &hash, &Local::now().to_rfc3339()).expect("insert user");
+    db::insert_category(&conn, "Еда", "expense", None).expect("insert category");
+    let food_id = conn.last_insert_rowid();
+    db::insert_category(&conn, "Зарплата", "income", None).expect("insert category");
+    let salary_id = conn.last_insert_rowid();
+    db::insert_account(&conn, "Карта", "card", "RUB", 100_000).expect("insert account");
+    let card_id = conn.last_insert_rowid();
+    db::insert_account(&conn, "Наличные", "cash", "RUB", 0).expect("insert account");
+    let cash_id = conn.last_insert_rowid();
+    Fixtures {
+        food_id,
+        salary_id,
+        card_id,
+        cash_id,
+    }
+}
+
+/// Location header of a redirect response.
+pub fn location<'a>(response: &'a LocalResponse<'_>) -> Option<&'a str> {
+    response.headers().get_one("Location")
+}
@@ -0,0 +1,101 @@
+use chrono::NaiveDate;
+
+use super::TestApp;
+use crate::db;
+use crate::months::{self, MonthNav};
+
+fn insert_dated(app: &TestApp, occurred_on: &str) {
+    app.conn()
+        .execute(
+            "INSERT INTO transactions (kind, amount_cents, occurred_on) VALUES ('expense', 1000, ?1)",
+            [occurred_on],
+        )
+        .unwrap();
+}
+
+fn nav(app: &TestApp, month: &str) -> MonthNav {
+    let today = NaiveDate::from_ymd_opt(2025, 3, 15).unwrap();
+    months::navigator(&app.conn(), "/budgets", month, today).unwrap()
+}
+
+fn marks(nav: &MonthNav) -> Vec<(String, bool)> {
+    nav.years
+        .iter()
+        .flat_map(|year| &year.months)
+        .map(|month| (month.month.clone(), month.has_data))
+        .collect()
+}
+
+#[test]
+fn navigator_spans_the_data_and_keeps_gaps() {
+    let app = TestApp::logged_in();
+    assert_eq!(db::date_bounds(&app.conn()).unwrap(), None);
+    let empty = nav(&app, "2025-03");
+    assert_eq!(marks(&empty), [("2025-03".to_string(), false)]);
+    assert_eq!((empty.prev, empty.next), (None, None));
+
+    insert_dated(&app, "2024-11-05");
+    insert_dated(&app, "2025-02-10");
+    // Malformed dates don't stretch the span.
+    insert_dated(&app, "03.07.2019");
+    app.conn()
+        .execute(
+            "INSERT INTO budgets (category_id, month, amount_cents) VALUES (?1, '2025-04', 5000)",
+            [app.fixtures.food_id],
+        )
+        .unwrap();
+    assert_eq!(
+        db::date_bounds(&app.conn()).unwrap(),
+        Some(("2024-11-05".to_string(), "2025-04-01".to_string()))
+    );
+
+    let january = nav(&app, "2025-01");
+    assert_eq!(january.prev.as_deref(), Some("2024-12"));
+    assert_eq!(january.next.as_deref(), Some("2025-02"));
+    let years = january
+        .years
+        .iter()
+        .map(|year| year.year)
+        .collect::<Vec<_>>();
+    assert_eq!(years, [2025, 2024]);
+    let expected = [
+        ("2025-01", false),
+        ("2025-02", true),
+        ("2025-03", false),
+        ("2025-04", true),
+        ("2024-11", true),
+        ("2024-12", false),
+    ]
+    .map(|(month, has_data)| (month.to_string(), has_data));
+    assert_eq!(marks(&january), expected);
+
+    assert_eq!(nav(&app, "2024-11").prev, None);
+    assert_eq!(nav(&app, "2025-04").next, None);
+    // A month outside the data widens the span to reach it.
+    let old = nav(&app, "2023-12");
+    assert_eq!(old.prev, None);
+    assert_eq!(old.next.as_deref(), Some("2024-01"));
+    assert_eq!(old.years.last().unwrap().year, 2023);
+    let junk = nav(&app, "март");
+    assert_eq!((junk.prev, junk.next), (None, None));
+}
+
+#[test]
+fn month_pages_link_to_neighbouring_months() {
+    let app = TestApp::logged_in();
+    insert_dated(&app, "2024-11-05");
+    for path in ["/", "/transactions", "/budgets", "/reports"] {
+        let page = app
+            .get(&format!("{path}?month=2024-12"))
+            .into_string()
+            .unwrap();
+        assert!(
+            page.contains(&format!("href=\"{path}?month=2024-11\"")),
+            "{path}"
+        );
+        assert!(
+            page.contains(&format!("href=\"{path}?month=2025-01\"")),
+            "{path}"
+        );
+    }
+}
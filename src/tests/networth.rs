@@ -0,0 +1,160 @@
+use rocket::http::Status;
+
+use super::TestApp;
+use crate::db;
+use crate::models::NewTransaction;
+use crate::networth;
+
+fn months(list: &[&str]) -> Vec<String> {
+    list.iter().map(|month| month.to_string()).collect()
+}
+
+#[test]
+fn net_worth_uses_the_values_known_at_each_month_end() {
+    let app = TestApp::new();
+    let conn = app.conn();
+    let flat = db::insert_holding(&conn, "Квартира", "asset").unwrap();
+    let loan = db::insert_holding(&conn, "Ипотека", "liability").unwrap();
+    db::insert_holding(&conn, "Дача", "asset").unwrap();
+    db::record_valuation(&conn, flat, "2026-01-15", 500_000).unwrap();
+    db::record_valuation(&conn, flat, "2026-03-01", 600_000).unwrap();
+    db::record_valuation(&conn, flat, "2026-03-01", 650_000).unwrap();
+    db::record_valuation(&conn, loan, "2026-02-28", 300_000).unwrap();
+    db::insert_transaction(
+        &conn,
+        &NewTransaction {
+            kind: "expense".to_string(),
+            amount_cents: 20_000,
+            category_id: Some(app.fixtures.food_id),
+            occurred_on: "2026-02-10".to_string(),
+            note: None,
+            payee: None,
+            account_id: Some(app.fixtures.card_id),
+            to_account_id: None,
+        },
+        None,
+    )
+    .unwrap();
+    db::insert_account(&conn, "Доллары", "bank", "USD", 1_000).unwrap();
+
+    let series = networth::series(
+        &conn,
+        &months(&["2026-03", "2026-02", "2026-01", "2025-12"]),
+    )
+    .unwrap();
+    let summary = series
+        .iter()
+        .map(|m| {
+            (
+                m.month.as_str(),
+                m.accounts_cents,
+                m.assets_cents,
+                m.liabilities_cents,
+                m.net_worth_cents,
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        summary,
+        [
+            ("2026-03", 80_000, 650_000, 300_000, 430_000),
+            ("2026-02", 80_000, 500_000, 300_000, 280_000),
+            ("2026-01", 100_000, 500_000, 0, 600_000),
+            ("2025-12", 100_000, 0, 0, 100_000),
+        ]
+    );
+
+    let holdings = db::list_holdings(&conn).unwrap();
+    let latest = holdings
+        .iter()
+        .map(|h| (h.name.as_str(), h.amount_cents, h.valued_on.as_deref()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        latest,
+        [
+            ("Дача", None, None),
+            ("Квартира", Some(650_000), Some("2026-03-01")),
+            ("Ипотека", Some(300_000), Some("2026-02-28")),
+        ]
+    );
+}
+
+#[test]
+fn holdings_are_managed_from_the_page() {
+    let app = TestApp::logged_in();
+    let status = app
+        .post_form(
+            "/networth",
+            &[
+                ("name", "Машина"),
+                ("kind", "asset"),
+                ("amount", "1500000"),
+                ("valued_on", "2026-01-10"),
+            ],
+        )
+        .status();
+    assert_eq!(status, Status::SeeOther);
+    let id = db::list_holdings(&app.conn()).unwrap()[0].id;
+    let status = app
+        .post_form(
+            &format!("/networth/{id}/valuations"),
+            &[("amount", "1400000,50"), ("valued_on", "")],
+        )
+        .status();
+    assert_eq!(status, Status::SeeOther);
+
+    let holding = db::list_holdings(&app.conn()).unwrap().remove(0);
+    assert_eq!(holding.amount_cents, Some(140_000_050));
+    assert_eq!(holding.valued_on, Some(crate::today_ymd()));
+    let page = app.get("/networth").into_string().unwrap();
+    assert!(page.contains("Машина"));
+    assert!(page.contains("1400000.50"));
+    assert!(
+        app.get("/reports")
+            .into_string()
+            .unwrap()
+            .contains("/networth")
+    );
+
+    app.post_form(&format!("/networth/{id}/delete"), &[]);
+    assert!(db::list_holdings(&app.conn()).unwrap().is_empty());
+    let (assets, _) = db::holdings_until(&app.conn(), "2100-01-01").unwrap();
+    assert_eq!(assets, 0);
+}
+
+#[test]
+fn invalid_holdings_are_rejected() {
+    let app = TestApp::logged_in();
+    for fields in [
+        [
+            ("name", "Вклад"),
+            ("kind", "savings"),
+            ("amount", ""),
+            ("valued_on", ""),
+        ],
+        [
+            ("name", " "),
+            ("kind", "asset"),
+            ("amount", ""),
+            ("valued_on", ""),
+        ],
+        [
+            ("name", "Вклад"),
+            ("kind", "asset"),
+            ("amount", "-5"),
+            ("valued_on", ""),
+        ],
+        [
+            ("name", "Вклад"),
+            ("kind", "asset"),
+            ("amount", "5"),
+            ("valued_on", "2026-02-30"),
+        ],
+    ] {
+        assert_eq!(
+            app.post_form("/networth", &fields).status(),
+            Status::BadRequest
+        );
+    }
+    assert!(db::list_holdings(&app.conn()).unwrap().is_empty());
+}
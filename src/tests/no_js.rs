@@ -0,0 +1,96 @@
+//! Every flow has to work for a browser without JavaScript, which is what the
+//! test client is: scripts may only improve on a server-rendered page.
+
+use rocket::http::Status;
+
+use super::TestApp;
+
+const PAGES: &[&str] = &[
+    "/",
+    "/transactions",
+    "/transactions?month=all&starred=1",
+    "/import",
+    "/accounts",
+    "/cash",
+    "/categories",
+    "/categories/duplicates",
+    "/budgets",
+    "/loans",
+    "/meters",
+    "/networth",
+    "/reports",
+    "/reports/prices",
+    "/disputes",
+    "/receipts/inbox",
+    "/receipts/shares",
+    "/transactions/months",
+    "/rules",
+    "/hooks",
+    "/tokens",
+    "/activity",
+    "/trash",
+    "/downloads",
+    "/notifications",
+    "/settings",
+    "/settings/dates",
+    "/settings/users",
+];
+
+fn form_tags(page: &str) -> Vec<&str> {
+    page.match_indices("<form")
+        .map(|(start, _)| {
+            let end = page[start..].find('>').unwrap();
+            &page[start..start + end]
+        })
+        .collect()
+}
+
+#[test]
+fn pages_work_without_scripts() {
+    let app = TestApp::logged_in();
+    for path in PAGES {
+        let response = app.get(path);
+        assert_eq!(response.status(), Status::Ok, "{path}");
+        let page = response.into_string().unwrap();
+        assert!(!page.contains("javascript:"), "{path}");
+        for tag in form_tags(&page) {
+            assert!(tag.contains("action=\"/"), "{path}: {tag}");
+            assert!(tag.contains("method=\""), "{path}: {tag}");
+        }
+        if page.contains("<script") {
+            assert!(page.contains("<noscript>"), "{path} has no fallback");
+        }
+    }
+}
+
+#[test]
+fn category_defaults_are_filled_in_by_a_full_page() {
+    let app = TestApp::logged_in();
+    let food_id = app.fixtures.food_id;
+    app.conn()
+        .execute(
+            "UPDATE categories SET default_amount_cents = 45000 WHERE id = ?1",
+            [food_id],
+        )
+        .unwrap();
+    let page = app
+        .get(&format!(
+            "/transactions/new?csrf_token=x&kind=income&amount=&category_id={food_id}\
+             &account_id=&occurred_on=2026-03-02&payee=Market&note=&tags=&receipt="
+        ))
+        .into_string()
+        .unwrap();
+    assert!(page.contains(r#"name="amount" value="450.00""#));
+    assert!(page.contains(r#"<option value="expense" selected>"#));
+    assert!(page.contains(r#"value="Market""#));
+    assert!(page.contains(r#"value="2026-03-02""#));
+    assert!(page.contains("formaction=\"/transactions/new\""));
+
+    let typed = app
+        .get(&format!(
+            "/transactions/new?category_id={food_id}&amount=99"
+        ))
+        .into_string()
+        .unwrap();
+    assert!(typed.contains(r#"name="amount" value="99""#));
+}
@@ -0,0 +1,64 @@
+use rocket::http::Status;
+
+use super::{PASSWORD, TestApp, USERNAME};
+use crate::db;
+use crate::password_strength::{Pattern, check};
+
+fn refused(password: &str, username: &str) -> Option<Pattern> {
+    check(password, username)
+        .err()
+        .map(|weakness| weakness.pattern)
+}
+
+#[test]
+fn common_and_patterned_passwords_are_refused_with_the_reason() {
+    assert_eq!(refused("password1", "owner"), Some(Pattern::Common));
+    assert_eq!(refused("Secret12", "owner"), Some(Pattern::Common));
+    assert_eq!(refused("пароль2024", "owner"), Some(Pattern::Common));
+    assert_eq!(refused("asdfghjk", "owner"), Some(Pattern::Keyboard));
+    assert_eq!(refused("abcdefgh9", "owner"), Some(Pattern::Sequence));
+    assert_eq!(refused("zzzzzzzzzz", "owner"), Some(Pattern::Repeat));
+    assert_eq!(refused("owner1987", "owner"), Some(Pattern::Username));
+    assert_eq!(refused("kx7q", "owner"), Some(Pattern::Short));
+}
+
+#[test]
+fn passphrases_and_random_passwords_pass() {
+    assert_eq!(check("зеленый чайник 47", "owner"), Ok(()));
+    assert_eq!(check("correct horse battery staple", "owner"), Ok(()));
+    assert_eq!(check("t9#Vq2!mLp", "owner"), Ok(()));
+}
+
+#[test]
+fn setup_explains_why_a_password_is_refused() {
+    let app = TestApp::empty();
+    let response = app.post_form(
+        "/setup",
+        &[
+            ("username", "owner"),
+            ("password", "qwerty123"),
+            ("confirm_password", "qwerty123"),
+        ],
+    );
+    assert_eq!(response.status(), Status::Ok);
+    let body = response.into_string().unwrap();
+    assert!(body.contains("Пароль легко подобрать"));
+    assert!(body.contains("самых распространенных паролей"));
+    assert!(!db::has_users(&app.conn()).unwrap());
+}
+
+#[test]
+fn password_change_refuses_the_username() {
+    let app = TestApp::logged_in();
+    let weak = format!("{USERNAME}{USERNAME}!");
+    let response = app.post_form(
+        "/settings/password",
+        &[
+            ("current_password", PASSWORD),
+            ("new_password", &weak),
+            ("confirm_password", &weak),
+        ],
+    );
+    assert_eq!(response.status(), Status::Ok);
+    assert!(response.into_string().unwrap().contains("в нем есть логин"));
+}
@@ -0,0 +1,91 @@
+use chrono::{Datelike, NaiveDate};
+use proptest::prelude::*;
+
+use crate::{budget_percent, format_money, fx, parse_amount_to_cents};
+
+proptest! {
+    #[test]
+    fn money_round_trips(cents in 0..=i64::MAX) {
+        prop_assert_eq!(parse_amount_to_cents(&format_money(cents)), Some(cents));
+    }
+
+    #[test]
+    fn comma_works_as_decimal_separator(cents in 0..=i64::MAX) {
+        let text = format_money(cents).replace('.', ",");
+        prop_assert_eq!(parse_amount_to_cents(&text), Some(cents));
+    }
+
+    #[test]
+    fn negative_money_keeps_its_sign(cents in (i64::MIN + 1)..0) {
+        let text = format_money(cents);
+        prop_assert_eq!(parse_amount_to_cents(&text), None);
+        prop_assert_eq!(text.strip_prefix('-').and_then(parse_amount_to_cents), Some(-cents));
+    }
+
+    #[test]
+    fn parse_never_panics(input in "\\PC*") {
+        let _ = parse_amount_to_cents(&input);
+    }
+
+    #[test]
+    fn huge_amounts_are_rejected(whole in "[1-9][0-9]{17,24}", frac in "[0-9]{0,2}") {
+        prop_assert_eq!(parse_amount_to_cents(&format!("{whole}.{frac}")), None);
+    }
+
+    #[test]
+    fn signs_inside_the_amount_are_rejected(whole in 0..1_000_000i64, frac in 0..10u8, sign in "[-+]") {
+        prop_assert_eq!(parse_amount_to_cents(&format!("{whole}.{sign}{frac}")), None);
+        prop_assert_eq!(parse_amount_to_cents(&format!("{sign}{whole}")), None);
+    }
+
+    #[test]
+    fn parsed_amounts_are_never_negative(input in "[-+0-9.,]{0,24}") {
+        if let Some(cents) = parse_amount_to_cents(&input) {
+            prop_assert!(cents >= 0);
+        }
+    }
+
+    #[test]
+    fn month_end_is_the_last_day(year in 1900..2200i32, month in 1..=12u32) {
+        let key = format!("{year:04}-{month:02}");
+        let end = fx::month_end(&key).unwrap();
+        prop_assert!(end.starts_with(&key));
+        let date = NaiveDate::parse_from_str(&end, "%Y-%m-%d").unwrap();
+        prop_assert_eq!(date.succ_opt().unwrap().day(), 1);
+    }
+
+    #[test]
+    fn month_end_rejects_invalid_months(year in 1900..2200i32, month in 13..100u32) {
+        prop_assert_eq!(fx::month_end(&format!("{year:04}-{month:02}")), None);
+        prop_assert_eq!(fx::month_end(&format!("{year:04}-00")), None);
+    }
+
+    #[test]
+    fn budget_percent_tracks_spending(
+        spent in 0..1_000_000_000_000i64,
+        budget in 1..1_000_000_000_000i64,
+    ) {
+        let percent = budget_percent(spent, budget);
+        prop_assert!(percent >= 0);
+        prop_assert!(budget_percent(spent + 1, budget) >= percent);
+        if spent >= budget {
+            prop_assert!(percent >= 100);
+        }
+        if spent * 100 < budget * 99 {
+            prop_assert!(percent < 100);
+        }
+    }
+
+    #[test]
+    fn zero_budget_reads_as_zero_percent(spent in 0..=i64::MAX) {
+        prop_assert_eq!(budget_percent(spent, 0), 0);
+    }
+
+    #[test]
+    fn overspent_budget_exceeds_hundred(budget in 1..1_000_000_000i64, extra in 1..1_000_000_000i64) {
+        let spent = budget + extra;
+        let expected = ((spent as f64 / budget as f64) * 100.0).round() as i64;
+        prop_assert_eq!(budget_percent(spent, budget), expected);
+        prop_assert!(budget_percent(spent, budget) >= 100);
+    }
+}
@@ -0,0 +1,159 @@
+use rusqlite::types::Value;
+
+use crate::query::{self, Sort, TransactionQuery};
+
+#[test]
+fn unfiltered_query_only_leaves_out_trash_and_has_no_params() {
+    let (sql, params) = TransactionQuery::default().build();
+    assert!(sql.contains("WHERE t.deleted_at IS NULL\n"));
+    assert!(!sql.contains("LIMIT"));
+    assert!(
+        sql.trim_end()
+            .ends_with("ORDER BY t.occurred_on DESC, t.id DESC")
+    );
+    assert!(params.is_empty());
+}
+
+#[test]
+fn filters_bind_values_in_placeholder_order() {
+    let query = TransactionQuery {
+        tag: Some("море".to_string()),
+        limit: Some(200),
+        ..TransactionQuery::month("2026-03")
+    };
+    let (sql, params) = query.build();
+    assert!(sql.contains("WHERE t.occurred_on LIKE ? AND EXISTS ("));
+    assert!(sql.trim_end().ends_with("LIMIT ?"));
+    assert_eq!(sql.matches('?').count(), params.len());
+    assert_eq!(
+        params,
+        [
+            Value::Text("2026-03-%".to_string()),
+            Value::Text("море".to_string()),
+            Value::Integer(200),
+        ]
+    );
+}
+
+#[test]
+fn request_values_never_reach_the_sql_text() {
+    let tag = "x' OR 1=1; DROP TABLE transactions; --";
+    let query = TransactionQuery {
+        month: Some("2026-03' --".to_string()),
+        tag: Some(tag.to_string()),
+        kind: Some("expense' --".to_string()),
+        term: Some("x' OR 1=1".to_string()),
+        ..Default::default()
+    };
+    let (sql, params) = query.build();
+    assert!(!sql.contains("2026-03"));
+    assert!(!sql.contains("OR 1=1"));
+    assert!(!sql.contains("DROP"));
+    assert!(!sql.contains("expense"));
+    assert!(params.contains(&Value::Text(tag.to_string())));
+}
+
+#[test]
+fn kind_category_amount_and_term_filters_bind_their_values() {
+    let query = TransactionQuery {
+        kind: Some("expense".to_string()),
+        category_id: Some(7),
+        min_cents: Some(1_000),
+        max_cents: Some(50_000),
+        term: Some("такси".to_string()),
+        ..Default::default()
+    };
+    let (sql, params) = query.build();
+    assert!(sql.contains("transactions_fts MATCH ?"));
+    assert!(sql.contains("t.kind = ?"));
+    assert!(sql.contains("t.amount_cents >= ? AND t.amount_cents <= ?"));
+    assert_eq!(sql.matches('?').count(), params.len());
+    assert_eq!(
+        params,
+        [
+            Value::Text(r#""такси"*"#.to_string()),
+            Value::Text("expense".to_string()),
+            Value::Integer(7),
+            Value::Integer(1_000),
+            Value::Integer(50_000),
+        ]
+    );
+
+    let (ranked, ranked_params) = query.search().unwrap();
+    assert!(ranked.contains("ORDER BY transactions_fts.rank"));
+    assert_eq!(ranked_params, params);
+    let no_words = TransactionQuery {
+        term: Some(" - ".to_string()),
+        ..Default::default()
+    };
+    assert!(no_words.search().is_none());
+}
+
+#[test]
+fn search_terms_become_quoted_prefixes() {
+    assert_eq!(
+        query::match_expression("  Электрик, вызов "),
+        Some(r#""Электрик"* "вызов"*"#.to_string())
+    );
+    assert_eq!(
+        query::match_expression(r#"a" OR -b"#),
+        Some(r#""a"* "OR"* "b"*"#.to_string())
+    );
+    assert_eq!(query::match_expression(" -\"* "), None);
+}
+
+#[test]
+fn pages_bind_limit_and_offset_and_count_ignores_them() {
+    let query = TransactionQuery {
+        limit: Some(100),
+        offset: Some(200),
+        ..TransactionQuery::month("2026-03")
+    };
+    let (sql, params) = query.build();
+    assert!(sql.trim_end().ends_with("LIMIT ? OFFSET ?"));
+    assert_eq!(params[1..], [Value::Integer(100), Value::Integer(200)]);
+    let skip_only = TransactionQuery {
+        offset: Some(5),
+        ..Default::default()
+    };
+    assert_eq!(skip_only.build().1, [Value::Integer(-1), Value::Integer(5)]);
+
+    let (count, count_params) = query.count();
+    assert!(count.starts_with("SELECT COUNT(*) FROM transactions t"));
+    assert!(!count.contains("LIMIT"));
+    assert_eq!(count_params, [Value::Text("2026-03-%".to_string())]);
+}
+
+#[test]
+fn sorts_come_from_a_fixed_list() {
+    for param in ["date", "date_asc", "amount", "amount_asc", "category"] {
+        let sort = Sort::from_param(param).unwrap();
+        assert_eq!(sort.param(), param);
+    }
+    assert_eq!(
+        Sort::from_param("t.amount_cents; DROP TABLE transactions"),
+        None
+    );
+    assert_eq!(Sort::from_param("Amount"), None);
+
+    let query = TransactionQuery {
+        sort: Sort::Smallest,
+        ..Default::default()
+    };
+    let (sql, _) = query.build();
+    assert!(
+        sql.trim_end()
+            .ends_with("ORDER BY t.amount_cents, t.occurred_on DESC, t.id DESC")
+    );
+    let ranked = TransactionQuery {
+        term: Some("такси".to_string()),
+        ..query
+    };
+    assert!(
+        ranked
+            .search()
+            .unwrap()
+            .0
+            .contains("ORDER BY transactions_fts.rank")
+    );
+}
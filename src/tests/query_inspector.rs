@@ -0,0 +1,41 @@
+use rocket::http::Status;
+
+use super::{PASSWORD, TestApp, USERNAME};
+use crate::query_inspector::{self, with_panel};
+
+/// Seeded app with the inspector switched on, logged in.
+fn inspected() -> TestApp {
+    let app = TestApp::with_config(query_inspector::KEY, true);
+    assert_eq!(app.login(USERNAME, PASSWORD).status(), Status::SeeOther);
+    app
+}
+
+#[test]
+fn the_panel_goes_before_the_end_of_the_body() {
+    assert_eq!(
+        with_panel("<body><main></main></body></html>", "<p>SQL</p>"),
+        Some("<body><main></main><p>SQL</p></body></html>".to_string())
+    );
+    assert_eq!(with_panel("ok", "<p>SQL</p>"), None);
+}
+
+#[test]
+fn pages_list_their_statements_when_the_inspector_is_on() {
+    let app = inspected();
+    let page = app.get("/transactions").into_string().unwrap();
+    assert!(page.contains("query-panel"));
+    assert!(page.contains("FROM transactions"));
+
+    let recent = app.get("/admin/queries").into_string().unwrap();
+    // Paths and SQL are shown as text, not markup.
+    assert!(recent.contains("GET &#x2F;transactions"));
+    assert!(recent.contains("&lt;"));
+}
+
+#[test]
+fn the_inspector_is_off_by_default() {
+    let app = TestApp::logged_in();
+    let page = app.get("/transactions").into_string().unwrap();
+    assert!(!page.contains("query-panel"));
+    assert_eq!(app.get("/admin/queries").status(), Status::NotFound);
+}
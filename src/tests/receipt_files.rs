@@ -0,0 +1,119 @@
+use std::path::{Path, PathBuf};
+
+use super::TestApp;
+use crate::db;
+use crate::receipt_files::{self, Staged};
+
+/// A directory of its own for each test, removed on drop.
+struct Dir(PathBuf);
+
+impl Dir {
+    fn new() -> Self {
+        let dir = std::env::temp_dir().join(format!("lumen-receipts-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        Dir(dir)
+    }
+}
+
+impl Drop for Dir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+fn staged(dir: &Path, name: &str) -> Staged {
+    let staged = Staged::new(dir, name.to_string());
+    std::fs::write(staged.temp_path(), b"not really a jpeg").unwrap();
+    staged
+}
+
+/// Stands in for the handlers' insert: gives the expense `id` the receipt.
+fn attach(tx: &rusqlite::Transaction, id: i64, receipt: Option<&str>) -> rusqlite::Result<i64> {
+    if let Some(receipt) = receipt {
+        db::attach_receipt(tx, id, receipt)?;
+    }
+    Ok(id)
+}
+
+fn files(dir: &Path) -> Vec<String> {
+    let mut names = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<_>>();
+    names.sort();
+    names
+}
+
+#[test]
+fn saved_receipt_is_put_in_place_after_the_insert() {
+    let app = TestApp::logged_in();
+    let dir = Dir::new();
+    let id = app.spend("4500", "2026-03-10", &[]);
+    let mut conn = app.conn();
+    let (_, receipt_kept) = receipt_files::save(
+        &mut conn,
+        Some(staged(&dir.0, "receipt-1.jpg")),
+        |tx, receipt| attach(tx, id, receipt),
+    )
+    .unwrap();
+    assert!(receipt_kept);
+    assert_eq!(files(&dir.0), ["receipt-1.jpg"]);
+    assert!(db::receipt_in_use(&conn, "receipt-1.jpg").unwrap());
+}
+
+#[test]
+fn failed_insert_leaves_no_file_behind() {
+    let app = TestApp::logged_in();
+    let dir = Dir::new();
+    let id = app.spend("4500", "2026-03-10", &[]);
+    let mut conn = app.conn();
+    let result = receipt_files::save(
+        &mut conn,
+        Some(staged(&dir.0, "receipt-2.jpg")),
+        |tx, receipt| {
+            attach(tx, id, receipt)?;
+            Err::<(), _>(rusqlite::Error::InvalidQuery)
+        },
+    );
+    assert!(result.is_err());
+    assert!(files(&dir.0).is_empty());
+    assert!(!db::receipt_in_use(&conn, "receipt-2.jpg").unwrap());
+}
+
+#[test]
+fn receipt_that_cannot_be_put_in_place_is_dropped_from_the_transaction() {
+    let app = TestApp::logged_in();
+    let dir = Dir::new();
+    let id = app.spend("4500", "2026-03-10", &[]);
+    let mut conn = app.conn();
+    let receipt = staged(&dir.0, "receipt-3.jpg");
+    std::fs::remove_file(receipt.temp_path()).unwrap();
+    let (transaction_id, receipt_kept) =
+        receipt_files::save(&mut conn, Some(receipt), |tx, receipt| {
+            attach(tx, id, receipt)
+        })
+        .unwrap();
+    assert!(!receipt_kept);
+    assert!(
+        db::transaction_by_id(&conn, transaction_id)
+            .unwrap()
+            .is_some()
+    );
+    assert!(!db::receipt_in_use(&conn, "receipt-3.jpg").unwrap());
+}
+
+#[test]
+fn recovery_finishes_committed_receipts_and_removes_the_rest() {
+    let app = TestApp::logged_in();
+    let dir = Dir::new();
+    let conn = app.conn();
+    // A crash between the commit and the rename.
+    db::attach_receipt(&conn, app.spend("4500", "2026-03-10", &[]), "receipt-4.jpg").unwrap();
+    staged(&dir.0, "receipt-4.jpg");
+    // A crash before the commit.
+    staged(&dir.0, "receipt-5.jpg");
+    std::fs::write(dir.0.join("receipt-6.jpg"), b"in place").unwrap();
+
+    assert_eq!(receipt_files::recover(&conn, &dir.0).unwrap(), 1);
+    assert_eq!(files(&dir.0), ["receipt-4.jpg", "receipt-6.jpg"]);
+}
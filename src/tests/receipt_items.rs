@@ -0,0 +1,151 @@
+use rocket::http::{RawStr, Status};
+use serde_json::json;
+
+use super::{TestApp, location};
+use crate::db;
+use crate::fiscal_receipts::items_of;
+use crate::models::NewReceiptItem;
+use crate::query::TransactionQuery;
+use crate::receipt_items::{change, format_quantity, parse_quantity};
+
+fn prices(app: &TestApp, item: &str) -> String {
+    let item = RawStr::new(item).percent_encode();
+    app.get(&format!("/reports/prices?item={item}"))
+        .into_string()
+        .unwrap()
+}
+
+fn add_item(app: &TestApp, id: i64, name: &str, quantity: &str, price: &str) -> Status {
+    let response = app.post_form(
+        &format!("/transactions/{id}/items"),
+        &[("name", name), ("quantity", quantity), ("price", price)],
+    );
+    if response.status() == Status::SeeOther {
+        assert_eq!(
+            location(&response),
+            Some(format!("/transactions/{id}/items").as_str())
+        );
+    }
+    response.status()
+}
+
+#[test]
+fn quantities_and_price_changes_read_like_receipts() {
+    assert_eq!(parse_quantity(""), Some(1.0));
+    assert_eq!(parse_quantity("0,532"), Some(0.532));
+    assert_eq!(parse_quantity("0"), None);
+    assert_eq!(parse_quantity("два"), None);
+    assert_eq!(format_quantity(2.0), "2");
+    assert_eq!(format_quantity(0.532), "0.532");
+    assert_eq!(change(8000, 8800).as_deref(), Some("+10.0%"));
+    assert_eq!(change(8000, 7600).as_deref(), Some("-5.0%"));
+    assert_eq!(change(8000, 8000).as_deref(), Some("0%"));
+}
+
+#[test]
+fn receipt_lines_come_from_the_check_service() {
+    let payload = json!({
+        "code": 1,
+        "data": { "json": { "items": [
+            { "name": "Молоко  3,2% 1 л", "price": 8999, "quantity": 2, "sum": 17998 },
+            { "name": "Сыр", "price": 79900, "quantity": 0.35, "sum": 27965 },
+            { "name": "", "price": 100, "quantity": 1 },
+        ] } },
+    });
+    assert_eq!(
+        items_of(&payload),
+        vec![
+            NewReceiptItem {
+                name: "Молоко 3,2% 1 л".to_string(),
+                quantity: 2.0,
+                price_cents: 8999,
+            },
+            NewReceiptItem {
+                name: "Сыр".to_string(),
+                quantity: 0.35,
+                price_cents: 79900,
+            },
+        ]
+    );
+    assert!(items_of(&json!({ "code": 3, "data": "чек не найден" })).is_empty());
+}
+
+#[test]
+fn item_prices_are_followed_across_receipts() {
+    let app = TestApp::logged_in();
+    let march = app.spend("500", "2026-03-02", &[("payee", "Пятерочка")]);
+    let april = app.spend("700", "2026-04-06", &[("payee", "Магнит")]);
+
+    assert_eq!(
+        add_item(&app, march, "Молоко 3,2%", "2", "80"),
+        Status::SeeOther
+    );
+    assert_eq!(add_item(&app, march, "Хлеб", "", "45.50"), Status::SeeOther);
+    assert_eq!(
+        add_item(&app, april, "молоко  3,2%", "1", "88"),
+        Status::SeeOther
+    );
+    assert_eq!(
+        add_item(&app, april, "Молоко", "0", "88"),
+        Status::UnprocessableEntity
+    );
+    assert_eq!(
+        add_item(&app, april, "", "1", "88"),
+        Status::UnprocessableEntity
+    );
+
+    let page = app
+        .get(&format!("/transactions/{march}/items"))
+        .into_string()
+        .unwrap();
+    assert!(page.contains("Хлеб"));
+    assert!(page.contains("не расписано"));
+
+    let history = prices(&app, "МОЛОКО");
+    assert!(history.contains("Магнит"));
+    assert!(history.contains("Пятерочка"));
+    assert!(history.contains("+10.0%"));
+
+    // Trashed expenses leave the history.
+    app.post_form(&format!("/transactions/{april}/delete"), &[]);
+    let history = prices(&app, "молоко");
+    assert!(!history.contains("Магнит"));
+
+    let items = db::list_receipt_items(&app.conn(), march).unwrap();
+    let bread = items.iter().find(|item| item.name == "Хлеб").unwrap();
+    let response = app.post_form(
+        &format!("/transactions/{march}/items/{}/delete", bread.id),
+        &[],
+    );
+    assert_eq!(response.status(), Status::SeeOther);
+    assert_eq!(db::list_receipt_items(&app.conn(), march).unwrap().len(), 1);
+    let again = app.post_form(
+        &format!("/transactions/{march}/items/{}/delete", bread.id),
+        &[],
+    );
+    assert_eq!(again.status(), Status::NotFound);
+}
+
+#[test]
+fn only_expenses_have_items() {
+    let app = TestApp::logged_in();
+    let salary_id = app.fixtures.salary_id.to_string();
+    app.post_form(
+        "/transactions",
+        &[
+            ("kind", "income"),
+            ("amount", "1000"),
+            ("category_id", &salary_id),
+            ("occurred_on", "2026-03-01"),
+        ],
+    );
+    let id = db::list_transactions(&app.conn(), &TransactionQuery::default()).unwrap()[0].id;
+    assert_eq!(
+        app.get(&format!("/transactions/{id}/items")).status(),
+        Status::NotFound
+    );
+    assert_eq!(
+        add_item(&app, id, "Зарплата", "1", "1000"),
+        Status::NotFound
+    );
+}
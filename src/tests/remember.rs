@@ -0,0 +1,80 @@
+use std::sync::Arc;
+
+use rocket::http::{Cookie, Status};
+use rocket::local::blocking::Client;
+
+use super::{PASSWORD, TestApp, USERNAME, location};
+use crate::db::PoolMetrics;
+
+fn remember_cookie(app: &TestApp) -> String {
+    app.client
+        .cookies()
+        .get("remember")
+        .unwrap()
+        .value()
+        .to_string()
+}
+
+/// Logs in with the box ticked, then drops the session the way a closed
+/// browser or an expired session does.
+fn remembered_app() -> TestApp {
+    let app = TestApp::new();
+    app.post_form(
+        "/login",
+        &[
+            ("username", USERNAME),
+            ("password", PASSWORD),
+            ("remember", "true"),
+        ],
+    );
+    app.conn().execute("DELETE FROM sessions", []).unwrap();
+    app
+}
+
+#[test]
+fn remembered_browser_gets_a_new_session_and_token() {
+    let app = remembered_app();
+    let first = remember_cookie(&app);
+    assert_eq!(app.get("/settings").status(), Status::Ok);
+    let second = remember_cookie(&app);
+    assert_ne!(first, second);
+    // The new session's forms carry its CSRF token.
+    assert_eq!(
+        app.post_form("/settings/rounding", &[("report_rounding", "rubles")])
+            .status(),
+        Status::Ok
+    );
+}
+
+#[test]
+fn replayed_token_ends_every_session() {
+    let app = remembered_app();
+    let stolen = remember_cookie(&app);
+    assert_eq!(app.get("/").status(), Status::Ok);
+
+    // Another browser on the same database, with a copy of the old cookie.
+    let rocket = crate::build_rocket(
+        app.pool.clone(),
+        Arc::new(PoolMetrics::default()),
+        None,
+        None,
+        None,
+        None,
+        None,
+    );
+    let thief = Client::untracked(rocket).unwrap();
+    let response = thief
+        .get("/")
+        .cookie(Cookie::new("remember", stolen))
+        .dispatch();
+    assert_eq!(location(&response), Some("/login"));
+    assert_eq!(location(&app.get("/")), Some("/login"));
+}
+
+#[test]
+fn login_without_the_box_remembers_nothing() {
+    let app = TestApp::logged_in();
+    assert!(app.client.cookies().get("remember").is_none());
+    app.conn().execute("DELETE FROM sessions", []).unwrap();
+    assert_eq!(location(&app.get("/")), Some("/login"));
+}
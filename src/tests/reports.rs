@@ -0,0 +1,290 @@
+use rocket::http::Status;
+
+use super::TestApp;
+use crate::db;
+use crate::disputes::RefundMonth;
+use crate::models::NewTransaction;
+use crate::money::Rounding;
+use crate::month_comparison;
+
+#[test]
+fn daily_spending_feeds_the_heatmap() {
+    let app = TestApp::logged_in();
+    app.spend("100", "2026-03-02", &[]);
+    app.spend("300", "2026-03-02", &[]);
+    app.spend("100", "2026-03-17", &[]);
+    app.spend("999", "2026-04-01", &[]);
+
+    let days = db::report_days(&app.conn(), "2026-03", RefundMonth::Refund).unwrap();
+    let totals = days
+        .iter()
+        .map(|day| (day.day.as_str(), day.expense_cents))
+        .collect::<Vec<_>>();
+    assert_eq!(totals, [("2026-03-02", 40_000), ("2026-03-17", 10_000)]);
+
+    let page = app.get("/reports?month=2026-03").into_string().unwrap();
+    assert!(page.contains(r#"class="heatmap-day level-4" title="2026-03-02: 400.00""#));
+    assert!(page.contains(r#"class="heatmap-day level-1" title="2026-03-17: 100.00""#));
+    assert!(page.contains(r#"class="heatmap-day level-0" title="2026-03-31: 0.00""#));
+    // March 2026 starts on a Sunday: six blank cells before it.
+    let first = page.find("2026-03-01").unwrap();
+    let blanks = page[..first].matches("<div></div>").count();
+    assert_eq!(blanks, 6);
+}
+
+#[test]
+fn top_payees_sum_the_month_expenses() {
+    let app = TestApp::logged_in();
+    for (payee, amount, occurred_on) in [
+        (" Пятёрочка ", "300", "2026-03-02"),
+        ("Пятёрочка", "200", "2026-03-09"),
+        ("Кофейня", "400", "2026-03-10"),
+        ("", "999", "2026-03-11"),
+        ("Кофейня", "999", "2026-04-01"),
+    ] {
+        app.post_form(
+            "/transactions",
+            &[
+                ("kind", "expense"),
+                ("amount", amount),
+                ("payee", payee),
+                ("occurred_on", occurred_on),
+            ],
+        );
+    }
+
+    let payees = db::report_payees(&app.conn(), "2026-03", 10, RefundMonth::Refund).unwrap();
+    let totals = payees
+        .iter()
+        .map(|row| (row.payee.as_str(), row.transactions, row.expense_cents))
+        .collect::<Vec<_>>();
+    assert_eq!(totals, [("Пятёрочка", 2, 50_000), ("Кофейня", 1, 40_000)]);
+
+    let page = app.get("/reports?month=2026-03").into_string().unwrap();
+    assert!(page.contains("Крупнейшие получатели"));
+    assert!(page.contains("<div>Пятёрочка</div>"));
+    let list = app.get("/transactions?month=2026-03").into_string().unwrap();
+    assert!(list.contains("<div>Кофейня</div>"));
+}
+#[test]
+fn rounding_keeps_the_sign_and_rounds_half_away_from_zero() {
+    assert_eq!(Rounding::Kopecks.format(123_456), "1234.56");
+    assert_eq!(Rounding::Rubles.format(123_450), "1235");
+    assert_eq!(Rounding::Rubles.format(-123_450), "-1235");
+    assert_eq!(Rounding::Rubles.format(-40), "0");
+    assert_eq!(Rounding::Thousands.format(123_456), "1.2 тыс.");
+    assert_eq!(Rounding::Thousands.format(-1_250_000), "-12.5 тыс.");
+    assert_eq!(Rounding::Thousands.number(4_999), "0.0");
+}
+
+#[test]
+fn reports_and_their_export_follow_the_rounding_setting() {
+    let app = TestApp::logged_in();
+    app.spend("1234.56", "2026-03-02", &[]);
+    let round = |value: &str| {
+        let response = app.post_form("/settings/rounding", &[("report_rounding", value)]);
+        assert_eq!(response.status(), Status::Ok);
+    };
+
+    round("rubles");
+    let page = app.get("/reports?month=2026-03").into_string().unwrap();
+    assert!(page.contains(r#"title="2026-03-02: 1235""#));
+    assert!(!page.contains("1234.56"));
+
+    round("thousands");
+    let page = app.get("/reports?month=2026-03").into_string().unwrap();
+    assert!(page.contains(r#"title="2026-03-02: 1.2 тыс.""#));
+    let csv = app
+        .get("/reports/categories.csv?month=2026-03")
+        .into_string()
+        .unwrap();
+    assert!(csv.contains("Расход, тыс. ₽"), "{csv}");
+    assert!(csv.contains("Еда,,1.2"), "{csv}");
+    app.remove_downloads();
+
+    let days = db::report_days(&app.conn(), "2026-03", RefundMonth::Refund).unwrap();
+    assert_eq!(days[0].expense_cents, 123_456);
+    let bad = app.post_form("/settings/rounding", &[("report_rounding", "millions")]);
+    assert_eq!(bad.status(), Status::BadRequest);
+}
+
+#[test]
+fn closed_month_keeps_its_figures_and_flags_later_changes() {
+    let app = TestApp::logged_in();
+    app.spend("100", "2026-03-02", &[]);
+    let response = app.post_form("/reports/close", &[("month", "2026-03")]);
+    assert_eq!(super::location(&response), Some("/reports?month=2026-03"));
+
+    let snapshot = db::report_snapshot(&app.conn(), "2026-03").unwrap().unwrap();
+    let expense = snapshot
+        .figures
+        .iter()
+        .find(|figure| figure.kind == "expense")
+        .unwrap();
+    assert_eq!(expense.amount_cents, 10_000);
+    let page = app.get("/reports?month=2026-03").into_string().unwrap();
+    assert!(page.contains("Цифры совпадают со снимком"));
+
+    // A receipt found later, backfilled into the closed month.
+    app.spend("50", "2026-03-20", &[]);
+    let page = app.get("/reports?month=2026-03").into_string().unwrap();
+    assert!(page.contains("Отличается от снимка"));
+    assert!(page.contains("≠ снимок"));
+    let differences = crate::report_snapshots::differences(&app.conn(), &snapshot).unwrap();
+    let changed = differences
+        .iter()
+        .map(|difference| {
+            (
+                difference.label.as_str(),
+                difference.snapshot_cents,
+                difference.current_cents,
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        changed,
+        [("Расход", 10_000, 15_000), ("Еда", 10_000, 15_000)]
+    );
+
+    app.post_form("/reports/close", &[("month", "2026-03")]);
+    let page = app.get("/reports?month=2026-03").into_string().unwrap();
+    assert!(page.contains("Цифры совпадают со снимком"));
+
+    app.post_form("/reports/reopen", &[("month", "2026-03")]);
+    assert!(db::report_snapshot(&app.conn(), "2026-03").unwrap().is_none());
+}
+
+#[test]
+fn only_past_months_can_be_closed() {
+    let app = TestApp::logged_in();
+    let month = crate::current_month();
+    app.post_form("/reports/close", &[("month", &month)]);
+    assert!(db::report_snapshot(&app.conn(), &month).unwrap().is_none());
+    let response = app.post_form("/reports/close", &[("month", "март")]);
+    assert_eq!(response.status(), Status::BadRequest);
+}
+
+#[test]
+fn cash_flow_is_split_by_account() {
+    let app = TestApp::logged_in();
+    let conn = app.conn();
+    let record = |kind: &str, amount_cents, account_id, occurred_on: &str| {
+        let transaction = NewTransaction {
+            kind: kind.to_string(),
+            amount_cents,
+            category_id: None,
+            occurred_on: occurred_on.to_string(),
+            note: None,
+            payee: None,
+            account_id,
+            to_account_id: None,
+        };
+        db::insert_transaction(&conn, &transaction, None).unwrap()
+    };
+    let card = Some(app.fixtures.card_id);
+    let cash = Some(app.fixtures.cash_id);
+    record("income", 100_000, card, "2026-02-05");
+    let shoes = record("expense", 30_000, card, "2026-03-03");
+    record("expense", 5_000, cash, "2026-03-04");
+    record("expense", 1_000, None, "2026-03-05");
+    db::record_refund(&conn, shoes, 10_000, "2026-03-20").unwrap();
+
+    let march = db::report_accounts(&conn, "2026-03", "2026-03", RefundMonth::Refund).unwrap();
+    let split = march
+        .iter()
+        .map(|row| {
+            (
+                crate::account_flow::account_name(row),
+                row.income_cents,
+                row.expense_cents,
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        split,
+        [
+            ("Карта", 0, 20_000),
+            ("Наличные", 0, 5_000),
+            ("Без счета", 0, 1_000)
+        ]
+    );
+
+    let response = app.get("/reports/accounts.json?month=2026-03");
+    assert_eq!(response.status(), Status::Ok);
+    let series: serde_json::Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    let months = series["months"].as_array().unwrap();
+    assert_eq!(months.len(), 12);
+    assert_eq!(months[11], "2026-03");
+    assert_eq!(months[0], "2025-04");
+    let card = &series["accounts"][0];
+    assert_eq!(card["account"], "Карта");
+    assert_eq!(card["income_cents"][10], 100_000);
+    assert_eq!(card["expense_cents"][11], 20_000);
+    assert_eq!(series["accounts"][2]["account"], "Без счета");
+
+    let csv = app
+        .get("/reports/accounts.csv?month=2026-03")
+        .into_string()
+        .unwrap();
+    assert!(csv.contains("2026-02,Карта,1000.00,0.00"));
+    assert!(csv.contains("2026-03,Наличные,0.00,50.00"));
+    app.remove_downloads();
+
+    let page = app.get("/reports?month=2026-03").into_string().unwrap();
+    assert!(page.contains("По счетам"));
+    assert!(page.contains("Без счета"));
+}
+
+#[test]
+fn picked_months_are_compared_side_by_side() {
+    let app = TestApp::logged_in();
+    app.spend("100", "2026-01-10", &[]);
+    app.spend("300", "2026-02-10", &[]);
+    app.spend("250", "2026-03-10", &[]);
+    app.post_form(
+        "/transactions",
+        &[
+            ("kind", "expense"),
+            ("amount", "50"),
+            ("occurred_on", "2026-02-11"),
+        ],
+    );
+
+    let picked = ["2026-03", "2026-01", "2026-02", "2026-01"].map(String::from);
+    let months = month_comparison::months(&picked).unwrap();
+    assert_eq!(months, ["2026-01", "2026-02", "2026-03"]);
+    let comparison = month_comparison::compare(&app.conn(), &months, RefundMonth::Refund).unwrap();
+    let rows = comparison
+        .rows
+        .iter()
+        .map(|row| (row.category_name.as_str(), row.expense_cents.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rows,
+        [
+            ("Еда", vec![10_000, 30_000, 25_000]),
+            ("Без категории", vec![0, 5_000, 0]),
+        ]
+    );
+    assert_eq!(comparison.totals, [10_000, 35_000, 25_000]);
+    assert_eq!(
+        month_comparison::deltas(&comparison.totals),
+        [None, Some(25_000), Some(-10_000)]
+    );
+
+    let page = app
+        .get("/reports?month=2026-03&compare=2026-02&compare=2026-03")
+        .into_string()
+        .unwrap();
+    assert!(page.contains(r#"<div class="table-row table-head cols-3">"#));
+    assert!(page.contains(r#"<span class="positive">(-50.00)</span>"#));
+    assert!(page.contains(r#"value="2026-02" checked"#));
+
+    assert!(month_comparison::months(&picked[..1]).is_none());
+    assert!(month_comparison::months(&["2026-13".to_string(), "2026-01".to_string()]).is_none());
+    let page = app
+        .get("/reports?month=2026-03&compare=2026-03")
+        .into_string()
+        .unwrap();
+    assert!(page.contains("Выберите от 2 до 3 месяцев"));
+}
@@ -0,0 +1,175 @@
+use rocket::http::{ContentType, Status};
+
+use super::{TestApp, location};
+use crate::db;
+use crate::models::NewInboundHook;
+use crate::query::TransactionQuery;
+
+fn add_rule(app: &TestApp, fields: &[(&str, &str)]) -> Status {
+    let mut form = vec![
+        ("min_amount", ""),
+        ("max_amount", ""),
+        ("category_id", ""),
+        ("kind", ""),
+        ("tags", ""),
+    ];
+    for (name, value) in fields {
+        form.retain(|(field, _)| field != name);
+        form.push((name, value));
+    }
+    app.post_form("/rules", &form).status()
+}
+
+/// Kind, category and tags of every transaction, oldest first.
+fn categorized(app: &TestApp) -> Vec<(String, Option<i64>, Option<String>)> {
+    let mut records = db::list_transactions(&app.conn(), &TransactionQuery::default()).unwrap();
+    records.reverse();
+    records
+        .into_iter()
+        .map(|t| (t.kind, t.category_id, t.tags))
+        .collect()
+}
+
+#[test]
+fn rules_categorize_manual_entries() {
+    let app = TestApp::logged_in();
+    let food = app.fixtures.food_id.to_string();
+    let status = add_rule(
+        &app,
+        &[
+            ("pattern", "пятёрочка"),
+            ("max_amount", "5000"),
+            ("category_id", &food),
+            ("tags", "продукты"),
+        ],
+    );
+    assert_eq!(status, Status::SeeOther);
+    assert_eq!(
+        add_rule(&app, &[("pattern", "кэшбэк"), ("kind", "income")]),
+        Status::SeeOther
+    );
+
+    for (kind, amount, payee, note, category) in [
+        ("income", "300", "ПЯТЁРОЧКА №12", "", ""),
+        ("expense", "9000", "Пятёрочка", "", ""),
+        ("expense", "300", "Пятёрочка", "", food.as_str()),
+        ("expense", "50", "", "Кэшбэк за март", ""),
+    ] {
+        app.post_form(
+            "/transactions",
+            &[
+                ("kind", kind),
+                ("amount", amount),
+                ("payee", payee),
+                ("note", note),
+                ("category_id", category),
+                ("occurred_on", "2026-03-02"),
+            ],
+        );
+    }
+
+    let food_id = Some(app.fixtures.food_id);
+    assert_eq!(
+        categorized(&app),
+        [
+            ("expense".to_string(), food_id, Some("продукты".to_string())),
+            ("expense".to_string(), None, None),
+            ("expense".to_string(), food_id, None),
+            ("income".to_string(), None, None),
+        ]
+    );
+    let page = app.get("/rules").into_string().unwrap();
+    assert!(page.contains("«пятёрочка»"));
+    assert!(page.contains("до 5000.00"));
+}
+
+#[test]
+fn rules_categorize_imports_and_webhooks() {
+    let app = TestApp::logged_in();
+    let food = app.fixtures.food_id.to_string();
+    add_rule(
+        &app,
+        &[("pattern", "ашан"), ("category_id", &food), ("tags", "опт")],
+    );
+
+    let token = uuid::Uuid::new_v4();
+    let dir = crate::import::imports_dir();
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join(format!("{token}.csv")),
+        "Дата,Сумма,Описание\n2026-03-01,-1500,АШАН Марьино\n2026-03-02,-200,Такси\n",
+    )
+    .unwrap();
+    let response = app.post_form(
+        &format!("/import/{token}"),
+        &[
+            ("date_col", "0"),
+            ("amount_col", "1"),
+            ("note_col", "2"),
+            ("has_header", "true"),
+        ],
+    );
+    let url = location(&response).unwrap().to_string();
+    assert_eq!(super::jobs::wait_for(&app, &url)["state"], "done");
+
+    let hook = NewInboundHook {
+        name: "Почта".to_string(),
+        kind: "expense".to_string(),
+        amount_field: "amount".to_string(),
+        date_field: None,
+        note_field: Some("subject".to_string()),
+        category_field: None,
+        category_id: None,
+        account_id: None,
+    };
+    db::insert_inbound_hook(&app.conn(), 1, &hook, "rules-secret", "2026-03-01T00:00:00").unwrap();
+    let response = app
+        .client
+        .post("/api/hooks/rules-secret")
+        .header(ContentType::JSON)
+        .body(r#"{"amount": "700", "subject": "Чек Ашан"}"#)
+        .dispatch();
+    assert_eq!(response.status(), Status::Created);
+
+    let food_id = Some(app.fixtures.food_id);
+    let tagged = Some("опт".to_string());
+    assert_eq!(
+        categorized(&app),
+        [
+            ("expense".to_string(), food_id, tagged.clone()),
+            ("expense".to_string(), None, None),
+            ("expense".to_string(), food_id, tagged),
+        ]
+    );
+}
+
+#[test]
+fn rules_that_cannot_work_are_rejected() {
+    let app = TestApp::logged_in();
+    let food = app.fixtures.food_id.to_string();
+    for fields in [
+        vec![("pattern", " "), ("category_id", food.as_str())],
+        vec![("pattern", "кафе")],
+        vec![
+            ("pattern", "кафе"),
+            ("category_id", &food),
+            ("kind", "income"),
+        ],
+        vec![("pattern", "кафе"), ("category_id", "9999")],
+        vec![("pattern", "кафе"), ("kind", "transfer")],
+        vec![
+            ("pattern", "кафе"),
+            ("category_id", &food),
+            ("min_amount", "500"),
+            ("max_amount", "100"),
+        ],
+        vec![
+            ("pattern", "кафе"),
+            ("tags", "еда"),
+            ("min_amount", "много"),
+        ],
+    ] {
+        assert_eq!(add_rule(&app, &fields), Status::BadRequest, "{fields:?}");
+    }
+    assert!(db::list_rules(&app.conn()).unwrap().is_empty());
+}
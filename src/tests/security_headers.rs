@@ -0,0 +1,54 @@
+use rocket::figment::Figment;
+use rocket::local::blocking::Client;
+
+use super::TestApp;
+use crate::security_headers::{Headers, SecurityHeaders};
+
+#[test]
+fn pages_carry_the_default_headers() {
+    let app = TestApp::logged_in();
+    let response = app.get("/transactions");
+    let headers = response.headers();
+    let defaults = SecurityHeaders::default();
+    assert_eq!(
+        headers.get_one("Content-Security-Policy"),
+        Some(defaults.content_security_policy.as_str())
+    );
+    assert_eq!(headers.get_one("X-Content-Type-Options"), Some("nosniff"));
+    assert_eq!(headers.get_one("Referrer-Policy"), Some("same-origin"));
+    // Replaces Shield's SAMEORIGIN rather than adding to it.
+    assert_eq!(headers.get("X-Frame-Options").collect::<Vec<_>>(), ["DENY"]);
+    assert_eq!(headers.get_one("Strict-Transport-Security"), None);
+}
+
+#[rocket::get("/")]
+fn index() -> &'static str {
+    "ok"
+}
+
+/// Reading the error's kind marks it handled, so dropping it doesn't panic.
+fn launch(figment: Figment) -> Result<Client, String> {
+    let rocket = rocket::custom(figment)
+        .mount("/", rocket::routes![index])
+        .attach(Headers);
+    Client::untracked(rocket).map_err(|err| err.kind().to_string())
+}
+
+#[test]
+fn headers_follow_the_configuration() {
+    let figment = rocket::Config::figment()
+        .merge(("security_headers.hsts", "max-age=31536000"))
+        .merge(("security_headers.frame_options", ""));
+    let client = launch(figment).unwrap();
+    let response = client.get("/").dispatch();
+    let headers = response.headers();
+    assert_eq!(
+        headers.get_one("Strict-Transport-Security"),
+        Some("max-age=31536000")
+    );
+    assert_eq!(headers.get_one("X-Frame-Options"), None);
+    assert_eq!(headers.get_one("Referrer-Policy"), Some("same-origin"));
+
+    let invalid = rocket::Config::figment().merge(("security_headers.hsts", 5));
+    assert!(launch(invalid).is_err());
+}
@@ -0,0 +1,124 @@
+use chrono::{Duration, Utc};
+use rocket::http::Status;
+
+use super::{TestApp, location};
+use crate::api_sessions::timestamp;
+use crate::db;
+use crate::sessions;
+
+fn set_session(app: &TestApp, column: &str, at: &str) {
+    app.conn()
+        .execute(&format!("UPDATE sessions SET {column} = ?1"), [at])
+        .unwrap();
+}
+
+fn last_used_at(app: &TestApp) -> String {
+    app.conn()
+        .query_row("SELECT last_used_at FROM sessions", [], |row| row.get(0))
+        .unwrap()
+}
+
+#[test]
+fn use_pushes_the_idle_deadline_back() {
+    let app = TestApp::logged_in();
+    let earlier = timestamp(Utc::now() - Duration::minutes(10));
+    set_session(&app, "last_used_at", &earlier);
+    assert_eq!(app.get("/").status(), Status::Ok);
+    assert!(last_used_at(&app) > earlier);
+}
+
+#[test]
+fn idle_or_expired_sessions_log_out() {
+    let app = TestApp::logged_in();
+    set_session(&app, "last_used_at", "2000-01-01T00:00:00Z");
+    assert_eq!(location(&app.get("/")), Some("/login"));
+
+    let app = TestApp::logged_in();
+    set_session(&app, "expires_at", &timestamp(Utc::now()));
+    assert_eq!(location(&app.get("/transactions")), Some("/login"));
+    // The next login clears the dead session away.
+    app.login(super::USERNAME, super::PASSWORD);
+    let sessions: i64 = app
+        .conn()
+        .query_row("SELECT COUNT(*) FROM sessions", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(sessions, 1);
+}
+#[test]
+fn settings_list_sessions_and_revoke_one() {
+    let app = TestApp::logged_in();
+    let user_id: i64 = app
+        .conn()
+        .query_row("SELECT id FROM users", [], |row| row.get(0))
+        .unwrap();
+    let firefox =
+        "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:128.0) Gecko/20100101 Firefox/128.0";
+    sessions::start(
+        &app.conn(),
+        user_id,
+        "other-browser",
+        firefox,
+        Some("203.0.113.5".parse().unwrap()),
+        Utc::now(),
+    )
+    .unwrap();
+
+    let page = app.get("/settings").into_string().unwrap();
+    assert!(page.contains("Firefox, Windows"));
+    assert!(page.contains("203.0.113.5"));
+    assert!(page.contains("это устройство"));
+
+    let other: i64 = app
+        .conn()
+        .query_row(
+            "SELECT id FROM sessions WHERE token = ?1",
+            [db::session_token_hash("other-browser")],
+            |row| row.get(0),
+        )
+        .unwrap();
+    let response = app.post_form(&format!("/settings/sessions/{other}/revoke"), &[]);
+    assert_eq!(location(&response), Some("/settings"));
+    assert_eq!(app.get("/settings").status(), Status::Ok);
+
+    let own: i64 = app
+        .conn()
+        .query_row("SELECT id FROM sessions", [], |row| row.get(0))
+        .unwrap();
+    let response = app.post_form(&format!("/settings/sessions/{own}/revoke"), &[]);
+    assert_eq!(location(&response), Some("/login"));
+}
+
+#[test]
+fn device_names_browser_and_system() {
+    let chrome = "Mozilla/5.0 (Linux; Android 14) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0 Mobile Safari/537.36";
+    assert_eq!(sessions::device(chrome), "Chrome, Android");
+    let edge = "Mozilla/5.0 (Windows NT 10.0) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0 Safari/537.36 Edg/126.0";
+    assert_eq!(sessions::device(edge), "Edge, Windows");
+    assert_eq!(sessions::device("curl/8.5"), "Неизвестное устройство");
+}
+
+#[test]
+fn tokens_are_stored_hashed() {
+    let app = TestApp::logged_in();
+    let cookie = app
+        .client
+        .cookies()
+        .get("session")
+        .unwrap()
+        .value()
+        .to_string();
+    let stored: String = app
+        .conn()
+        .query_row("SELECT token FROM sessions", [], |row| row.get(0))
+        .unwrap();
+    assert_ne!(stored, cookie);
+    assert_eq!(stored, db::session_token_hash(&cookie));
+
+    // A token stored before hashing is hashed on the next start and still
+    // lets its browser in.
+    app.conn()
+        .execute("UPDATE sessions SET token = ?1", [&cookie])
+        .unwrap();
+    db::run_migrations(&app.conn()).unwrap();
+    assert_eq!(app.get("/settings").status(), Status::Ok);
+}
@@ -0,0 +1,156 @@
+use rocket::http::Status;
+
+use super::TestApp;
+use crate::db;
+use crate::models::NewTransaction;
+
+/// Card (opening balance 1000.00): salary before the period, then food,
+/// a transfer to cash and a salary inside it.
+fn seed_card(app: &TestApp) {
+    let conn = app.conn();
+    let card = Some(app.fixtures.card_id);
+    let rows = [
+        (
+            "income",
+            50_000,
+            Some(app.fixtures.salary_id),
+            "2026-02-25",
+            None,
+        ),
+        (
+            "expense",
+            12_345,
+            Some(app.fixtures.food_id),
+            "2026-03-02",
+            None,
+        ),
+        (
+            "transfer",
+            20_000,
+            None,
+            "2026-03-05",
+            Some(app.fixtures.cash_id),
+        ),
+        (
+            "income",
+            30_000,
+            Some(app.fixtures.salary_id),
+            "2026-03-10",
+            None,
+        ),
+        (
+            "expense",
+            1_000,
+            Some(app.fixtures.food_id),
+            "2026-04-01",
+            None,
+        ),
+    ];
+    for (kind, amount_cents, category_id, occurred_on, to_account_id) in rows {
+        let transaction = NewTransaction {
+            kind: kind.to_string(),
+            amount_cents,
+            category_id,
+            occurred_on: occurred_on.to_string(),
+            note: None,
+            payee: None,
+            account_id: card,
+            to_account_id,
+        };
+        db::insert_transaction(&conn, &transaction, None).unwrap();
+    }
+}
+
+fn statement_url(account_id: i64, suffix: &str) -> String {
+    format!("/accounts/{account_id}/statement{suffix}?from=2026-03-01&to=2026-03-31")
+}
+
+#[test]
+fn statement_runs_from_opening_to_closing_balance() {
+    let app = TestApp::logged_in();
+    seed_card(&app);
+    let account = {
+        let accounts = db::list_accounts(&app.conn()).unwrap();
+        accounts
+            .into_iter()
+            .find(|a| a.id == app.fixtures.card_id)
+            .unwrap()
+    };
+    let from = chrono::NaiveDate::from_ymd_opt(2026, 3, 1).unwrap();
+    let to = chrono::NaiveDate::from_ymd_opt(2026, 3, 31).unwrap();
+    let statement = crate::statement::build(&app.conn(), &account, from, to).unwrap();
+    assert_eq!(statement.opening_cents, 150_000);
+    assert_eq!(statement.credit_cents, 30_000);
+    assert_eq!(statement.debit_cents, 32_345);
+    assert_eq!(statement.closing_cents, 147_655);
+    let balances = statement
+        .entries
+        .iter()
+        .map(|entry| entry.balance_cents)
+        .collect::<Vec<_>>();
+    assert_eq!(balances, [137_655, 117_655, 147_655]);
+
+    let page = app
+        .get(&statement_url(app.fixtures.card_id, ""))
+        .into_string()
+        .unwrap();
+    assert!(page.contains("1500.00"));
+    assert!(page.contains("1476.55"));
+    assert!(page.contains("Перевод → Наличные"));
+
+    let cash = app
+        .get(&statement_url(app.fixtures.cash_id, ""))
+        .into_string()
+        .unwrap();
+    assert!(cash.contains("Перевод ← Карта"));
+    assert!(cash.contains("200.00"));
+}
+
+#[test]
+fn statement_exports_as_csv_and_pdf() {
+    let app = TestApp::logged_in();
+    seed_card(&app);
+
+    let response = app.get(&statement_url(app.fixtures.card_id, ".csv"));
+    assert_eq!(response.status(), Status::Ok);
+    let csv = response.into_string().unwrap();
+    let lines = csv.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 6);
+    assert!(lines[1].ends_with("Входящий остаток,,,,1500.00"));
+    assert!(lines[2].starts_with("2026-03-02,Еда,,,123.45,1376.55"));
+    assert!(lines[5].ends_with("Исходящий остаток,,,,1476.55"));
+
+    let response = app.get(&statement_url(app.fixtures.card_id, ".pdf"));
+    if app
+        .client
+        .rocket()
+        .state::<Option<crate::pdf::Font>>()
+        .unwrap()
+        .is_some()
+    {
+        assert_eq!(response.status(), Status::Ok);
+        let pdf = response.into_bytes().unwrap();
+        assert!(pdf.starts_with(b"%PDF-"));
+        assert!(pdf.windows(10).any(|window| window == b"/FontFile2"));
+    } else {
+        assert_eq!(response.status(), Status::ServiceUnavailable);
+    }
+    app.remove_downloads();
+}
+
+#[test]
+fn statement_checks_its_period_and_account() {
+    let app = TestApp::logged_in();
+    let card = app.fixtures.card_id;
+    let bad = |query: &str| {
+        app.get(&format!("/accounts/{card}/statement?{query}"))
+            .status()
+    };
+    assert_eq!(bad("from=2026-03-31&to=2026-03-01"), Status::BadRequest);
+    assert_eq!(bad("from=2026-02-30"), Status::BadRequest);
+    assert_eq!(bad(""), Status::Ok);
+    assert_eq!(
+        app.get("/accounts/999/statement").status(),
+        Status::NotFound
+    );
+}
@@ -0,0 +1,115 @@
+use rocket::http::{RawStr, Status};
+
+use super::TestApp;
+use crate::db;
+use crate::suggestions::{self, normalize, similarity};
+
+fn suggested(app: &TestApp, payee: &str) -> Vec<String> {
+    let uri = format!(
+        "/transactions/suggestions?payee={}",
+        RawStr::new(payee).percent_encode()
+    );
+    let response = app.get(&uri);
+    assert_eq!(response.status(), Status::Ok);
+    let body: serde_json::Value = serde_json::from_str(&response.into_string().unwrap()).unwrap();
+    body["suggestions"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|suggestion| suggestion["category_name"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn texts_match_when_equal_prefixed_or_close() {
+    assert_eq!(normalize("  Пятёрочка, ул. Ленина "), "пятёрочка ул ленина");
+    assert_eq!(similarity("пятерочка", "пятерочка"), 1.0);
+    assert_eq!(similarity("пят", "пятерочка ул ленина"), 0.9);
+    assert!(similarity("пя", "пятерочка") < 0.9);
+    assert!(similarity("пятерочка", "пятерачка") >= 0.4);
+    assert!(similarity("пятерочка", "аптека") < 0.4);
+    assert_eq!(similarity("", "аптека"), 0.0);
+}
+
+#[test]
+fn history_suggests_the_category_of_similar_payees() {
+    let app = TestApp::logged_in();
+    let conn = app.conn();
+    db::insert_category(&conn, "Кафе", "expense", None).unwrap();
+    let cafe_id = conn.last_insert_rowid();
+    app.spend("100", "2026-03-10", &[("payee", "Пятёрочка")]);
+    app.spend("100", "2026-03-10", &[("payee", "Пятёрочка у дома")]);
+    app.spend(
+        "100",
+        "2026-03-10",
+        &[
+            ("category_id", &cafe_id.to_string()),
+            ("payee", "Кофейня на углу"),
+        ],
+    );
+
+    assert_eq!(suggested(&app, "пятёр"), ["Еда"]);
+    assert_eq!(suggested(&app, "Кофейня на углу"), ["Кафе"]);
+    assert!(suggested(&app, "Аптека").is_empty());
+    let income_only = format!(
+        "/transactions/suggestions?kind=income&payee={}",
+        RawStr::new("пятёр").percent_encode()
+    );
+    assert!(
+        app.get(&income_only)
+            .into_string()
+            .unwrap()
+            .contains("\"suggestions\":[]")
+    );
+    assert!(suggested(&app, "").is_empty());
+}
+
+#[test]
+fn taken_suggestions_rank_higher_next_time() {
+    let app = TestApp::logged_in();
+    let conn = app.conn();
+    db::insert_category(&conn, "Дом", "expense", None).unwrap();
+    let home_id = conn.last_insert_rowid();
+    app.spend("100", "2026-03-10", &[("payee", "Ашан")]);
+    app.spend("100", "2026-03-10", &[("payee", "Ашан")]);
+    app.spend(
+        "100",
+        "2026-03-10",
+        &[("category_id", &home_id.to_string()), ("payee", "Ашан")],
+    );
+    assert_eq!(suggested(&app, "ашан"), ["Еда", "Дом"]);
+
+    suggestions::record_accepted(&conn, Some("Ашан"), None, home_id).unwrap();
+    assert_eq!(suggested(&app, "ашан"), ["Дом", "Еда"]);
+}
+
+#[test]
+fn the_form_records_a_suggestion_only_when_it_was_taken() {
+    let app = TestApp::logged_in();
+    let food_id = app.fixtures.food_id.to_string();
+    let post = |suggested: &str| {
+        let response = app.post_form(
+            "/transactions",
+            &[
+                ("kind", "expense"),
+                ("amount", "100"),
+                ("category_id", &food_id),
+                ("occurred_on", "2026-03-10"),
+                ("payee", "Лента"),
+                ("suggested_category_id", suggested),
+            ],
+        );
+        assert_eq!(response.status(), Status::SeeOther);
+    };
+    post("");
+    assert!(db::accepted_suggestions(&app.conn()).unwrap().is_empty());
+    post(&food_id);
+    let accepted = db::accepted_suggestions(&app.conn()).unwrap();
+    assert_eq!(accepted.len(), 1);
+    assert_eq!(
+        (accepted[0].field.as_str(), accepted[0].text.as_str()),
+        ("payee", "лента")
+    );
+    assert_eq!(accepted[0].category_id, app.fixtures.food_id);
+    assert_eq!(accepted[0].accepted, 1);
+}
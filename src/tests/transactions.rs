@@ -0,0 +1,534 @@
+use rocket::http::Status;
+
+use super::{TestApp, location};
+use crate::db;
+use crate::disputes::RefundMonth;
+use crate::query::{Sort, TransactionQuery};
+
+#[test]
+fn add_expense_lists_it() {
+    let app = TestApp::logged_in();
+    let food_id = app.fixtures.food_id.to_string();
+    let card_id = app.fixtures.card_id.to_string();
+    let response = app.post_form(
+        "/transactions",
+        &[
+            ("kind", "expense"),
+            ("amount", "1234,50"),
+            ("category_id", &food_id),
+            ("account_id", &card_id),
+            ("occurred_on", "2026-03-14"),
+            ("note", "Обед"),
+        ],
+    );
+    assert_eq!(response.status(), Status::SeeOther);
+    assert_eq!(location(&response), Some("/transactions"));
+
+    let records = db::list_transactions(&app.conn(), &TransactionQuery::month("2026-03")).unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].kind, "expense");
+    assert_eq!(records[0].amount_cents, 123_450);
+    assert_eq!(records[0].category_name.as_deref(), Some("Еда"));
+    assert_eq!(records[0].account_name.as_deref(), Some("Карта"));
+
+    let response = app.get("/transactions?month=2026-03");
+    assert_eq!(response.status(), Status::Ok);
+    let body = response.into_string().unwrap();
+    assert!(body.contains("Обед"));
+    assert!(body.contains("1234.50"));
+}
+
+#[test]
+fn invalid_transactions_are_rejected() {
+    let app = TestApp::logged_in();
+    for (kind, amount) in [
+        ("expense", "-10"),
+        ("expense", "abc"),
+        ("expense", ""),
+        ("transfer", "10"),
+        ("adjustment", "10"),
+    ] {
+        let response = app.post_form(
+            "/transactions",
+            &[
+                ("kind", kind),
+                ("amount", amount),
+                ("occurred_on", "2026-03-14"),
+            ],
+        );
+        assert_eq!(
+            response.status(),
+            Status::UnprocessableEntity,
+            "{kind} {amount}"
+        );
+    }
+    let records = db::list_transactions(&app.conn(), &TransactionQuery::default()).unwrap();
+    assert!(records.is_empty());
+}
+
+#[test]
+fn category_must_match_the_kind() {
+    let app = TestApp::logged_in();
+    let food_id = app.fixtures.food_id.to_string();
+    let response = app.post_form(
+        "/transactions",
+        &[
+            ("kind", "income"),
+            ("amount", "100"),
+            ("category_id", &food_id),
+            ("occurred_on", "2026-03-14"),
+        ],
+    );
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+    let body = response.into_string().unwrap();
+    assert!(body.contains("Это категория расходов, выберите категорию доходов"));
+    // The form is sent back for income, offering income categories only.
+    let options = body
+        .split("id=\"transaction-category\"")
+        .nth(1)
+        .and_then(|rest| rest.split("</select>").next())
+        .unwrap();
+    assert!(options.contains("Зарплата"));
+    assert!(!options.contains("Еда"));
+    let records = db::list_transactions(&app.conn(), &TransactionQuery::default()).unwrap();
+    assert!(records.is_empty());
+
+    let options = app
+        .get(&format!(
+            "/transactions/categories?kind=expense&category_id={food_id}"
+        ))
+        .into_string()
+        .unwrap();
+    assert!(options.contains(&format!("value=\"{food_id}\" selected")));
+    assert!(!options.contains("Зарплата"));
+    assert_eq!(
+        app.get("/transactions/categories?kind=transfer").status(),
+        Status::BadRequest
+    );
+}
+
+#[test]
+fn transfer_moves_balance_between_accounts() {
+    let app = TestApp::logged_in();
+    let (card_id, cash_id) = (
+        app.fixtures.card_id.to_string(),
+        app.fixtures.cash_id.to_string(),
+    );
+    let response = app.post_form(
+        "/transactions/transfer",
+        &[
+            ("from_account_id", &card_id),
+            ("to_account_id", &cash_id),
+            ("amount", "300"),
+            ("occurred_on", "2026-03-14"),
+        ],
+    );
+    assert_eq!(response.status(), Status::SeeOther);
+
+    let accounts = db::list_accounts(&app.conn()).unwrap();
+    let balance = |id: i64| {
+        accounts
+            .iter()
+            .find(|account| account.id == id)
+            .map(|account| account.balance_cents)
+    };
+    assert_eq!(balance(app.fixtures.card_id), Some(70_000));
+    assert_eq!(balance(app.fixtures.cash_id), Some(30_000));
+
+    let response = app.post_form(
+        "/transactions/transfer",
+        &[
+            ("from_account_id", &card_id),
+            ("to_account_id", &card_id),
+            ("amount", "300"),
+            ("occurred_on", "2026-03-14"),
+        ],
+    );
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+}
+
+#[test]
+fn tags_filter_transactions() {
+    let app = TestApp::logged_in();
+    for (amount, tags) in [("100", "Отпуск, море"), ("50", "море"), ("20", "")] {
+        let response = app.post_form(
+            "/transactions",
+            &[
+                ("kind", "expense"),
+                ("amount", amount),
+                ("occurred_on", "2026-03-14"),
+                ("tags", tags),
+            ],
+        );
+        assert_eq!(response.status(), Status::SeeOther);
+    }
+
+    let conn = app.conn();
+    let tagged = |tag: &str| TransactionQuery {
+        tag: Some(tag.to_string()),
+        ..TransactionQuery::month("2026-03")
+    };
+    let sea = db::list_transactions(&conn, &tagged("море")).unwrap();
+    assert_eq!(sea.len(), 2);
+    let vacation = db::list_transactions(&conn, &tagged("отпуск")).unwrap();
+    assert_eq!(vacation.len(), 1);
+    assert_eq!(vacation[0].tags.as_deref(), Some("отпуск, море"));
+
+    let report = db::report_tags(&conn, "2026-03", RefundMonth::Refund).unwrap();
+    let totals = report
+        .iter()
+        .map(|tag| (tag.tag_name.as_str(), tag.expense_cents))
+        .collect::<Vec<_>>();
+    assert_eq!(totals, [("море", 15_000), ("отпуск", 10_000)]);
+}
+
+#[test]
+fn export_includes_month_rows() {
+    let app = TestApp::logged_in();
+    let salary_id = app.fixtures.salary_id.to_string();
+    app.post_form(
+        "/transactions",
+        &[
+            ("kind", "income"),
+            ("amount", "5000"),
+            ("category_id", &salary_id),
+            ("occurred_on", "2026-03-01"),
+        ],
+    );
+    app.post_form(
+        "/transactions",
+        &[
+            ("kind", "income"),
+            ("amount", "7000"),
+            ("occurred_on", "2026-04-01"),
+        ],
+    );
+
+    let response = app.get("/transactions/export.csv?month=2026-03");
+    assert_eq!(response.status(), Status::Ok);
+    let body = response.into_string().unwrap();
+    assert!(body.contains("2026-03-01,income,Зарплата,5000.00"));
+    assert!(!body.contains("2026-04-01"));
+    app.remove_downloads();
+}
+
+#[test]
+fn search_finds_notes_and_payees_in_any_month() {
+    let app = TestApp::logged_in();
+    for (note, payee, occurred_on) in [
+        ("Вызов электрика", "", "2025-11-03"),
+        ("Продукты", "ЭлектрикПро", "2026-03-14"),
+        ("Лампочки", "Леруа", "2026-03-15"),
+    ] {
+        app.post_form(
+            "/transactions",
+            &[
+                ("kind", "expense"),
+                ("amount", "100"),
+                ("note", note),
+                ("payee", payee),
+                ("occurred_on", occurred_on),
+            ],
+        );
+    }
+
+    let conn = app.conn();
+    let found = |term: &str| {
+        let query = TransactionQuery {
+            term: Some(term.to_string()),
+            ..Default::default()
+        };
+        db::search_transactions(&conn, &query)
+            .unwrap()
+            .into_iter()
+            .map(|record| record.occurred_on)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(found("электрик").len(), 2);
+    assert_eq!(found("ВЫЗОВ электрика"), ["2025-11-03"]);
+    assert!(found("OR").is_empty());
+    assert!(found("").is_empty());
+
+    // The index follows edits and deletions.
+    conn.execute(
+        "UPDATE transactions SET note = 'Патроны' WHERE note = 'Лампочки'",
+        [],
+    )
+    .unwrap();
+    assert!(found("лампочки").is_empty());
+    assert_eq!(found("патрон"), ["2026-03-15"]);
+    conn.execute("DELETE FROM transactions WHERE payee = 'ЭлектрикПро'", [])
+        .unwrap();
+    assert_eq!(found("электрик"), ["2025-11-03"]);
+
+    let page = app
+        .get("/transactions?q=%D1%8D%D0%BB%D0%B5%D0%BA%D1%82%D1%80%D0%B8%D0%BA")
+        .into_string()
+        .unwrap();
+    assert!(page.contains("Найдено: 1"));
+    assert!(page.contains("Вызов электрика"));
+}
+
+#[test]
+fn list_filters_by_kind_category_and_amount() {
+    let app = TestApp::logged_in();
+    let conn = app.conn();
+    let food_id = app.fixtures.food_id.to_string();
+    let salary_id = app.fixtures.salary_id.to_string();
+    conn.execute(
+        "INSERT INTO categories (name, kind, parent_id) VALUES ('Кафе', 'expense', ?1)",
+        [&food_id],
+    )
+    .unwrap();
+    let cafe_id = conn.last_insert_rowid().to_string();
+    for (kind, amount, category, note) in [
+        ("expense", "150", food_id.as_str(), "Рынок"),
+        ("expense", "900", cafe_id.as_str(), "Обед"),
+        ("expense", "4000", "", "Такси"),
+        ("income", "90000", salary_id.as_str(), "Аванс"),
+    ] {
+        app.post_form(
+            "/transactions",
+            &[
+                ("kind", kind),
+                ("amount", amount),
+                ("category_id", category),
+                ("note", note),
+                ("occurred_on", "2026-03-10"),
+            ],
+        );
+    }
+
+    let notes = |query: TransactionQuery| {
+        db::list_transactions(&conn, &query)
+            .unwrap()
+            .into_iter()
+            .map(|record| record.note.unwrap_or_default())
+            .collect::<Vec<_>>()
+    };
+    let march = || TransactionQuery::month("2026-03");
+    let food = TransactionQuery {
+        category_id: Some(food_id.parse().unwrap()),
+        ..march()
+    };
+    // A parent category takes in its subcategories.
+    assert_eq!(notes(food).len(), 2);
+    let expenses = TransactionQuery {
+        kind: Some("expense".to_string()),
+        min_cents: Some(50_000),
+        max_cents: Some(400_000),
+        ..march()
+    };
+    let mut found = notes(expenses);
+    found.sort();
+    assert_eq!(found, ["Обед", "Такси"]);
+
+    let page = app
+        .get("/transactions?month=2026-03&kind=income&min=1000")
+        .into_string()
+        .unwrap();
+    assert!(page.contains("Найдено: 1"));
+    assert!(page.contains("Аванс"));
+    assert!(!page.contains("Рынок"));
+    let all = app
+        .get(&format!("/transactions?month=all&category={cafe_id}"))
+        .into_string()
+        .unwrap();
+    assert!(all.contains("Обед"));
+    assert!(!all.contains("Рынок"));
+
+    for bad in ["kind=loan", "min=abc", "category=x"] {
+        let response = app.get(&format!("/transactions?{bad}"));
+        assert_eq!(response.status(), Status::BadRequest, "{bad}");
+    }
+}
+
+#[test]
+fn busy_months_are_paged_instead_of_cut_off() {
+    let app = TestApp::logged_in();
+    let conn = app.conn();
+    for day in 0..230 {
+        conn.execute(
+            "INSERT INTO transactions (kind, amount_cents, occurred_on, note)
+             VALUES ('expense', 100, ?1, ?2)",
+            (
+                format!("2026-03-{:02}", day % 28 + 1),
+                format!("Покупка {day}"),
+            ),
+        )
+        .unwrap();
+    }
+    let query = TransactionQuery::month("2026-03");
+    assert_eq!(db::count_transactions(&conn, &query).unwrap(), 230);
+
+    let page = |path: &str| app.get(path).into_string().unwrap();
+    let first = page("/transactions?month=2026-03&kind=expense");
+    assert!(first.contains("1–100 из 230 · страница 1 из 3"));
+    assert!(first.contains(r#"href="/transactions?month=2026-03&kind=expense&page=2""#));
+    assert!(!first.contains("← Новее"));
+    let last = page("/transactions?month=2026-03&kind=expense&page=3");
+    assert!(last.contains("201–230 из 230"));
+    assert!(last.contains(r#"href="/transactions?month=2026-03&kind=expense&page=2""#));
+    assert!(!last.contains("Старее →"));
+    assert!(page("/transactions?month=2026-03&page=99").contains("страница 3 из 3"));
+
+    // Every row turns up on exactly one page.
+    let mut seen = Vec::new();
+    for offset in [0, 100, 200] {
+        let query = TransactionQuery {
+            limit: Some(100),
+            offset: Some(offset),
+            ..TransactionQuery::month("2026-03")
+        };
+        seen.extend(
+            db::list_transactions(&conn, &query)
+                .unwrap()
+                .into_iter()
+                .map(|record| record.id),
+        );
+    }
+    seen.sort();
+    seen.dedup();
+    assert_eq!(seen.len(), 230);
+}
+
+#[test]
+fn list_sorts_by_amount_date_or_category() {
+    let app = TestApp::logged_in();
+    let food_id = app.fixtures.food_id.to_string();
+    let salary_id = app.fixtures.salary_id.to_string();
+    for (kind, amount, category, note, occurred_on) in [
+        ("expense", "300", food_id.as_str(), "Рынок", "2026-03-02"),
+        ("expense", "50", "", "Парковка", "2026-03-05"),
+        ("income", "1000", salary_id.as_str(), "Аванс", "2026-03-01"),
+    ] {
+        app.post_form(
+            "/transactions",
+            &[
+                ("kind", kind),
+                ("amount", amount),
+                ("category_id", category),
+                ("note", note),
+                ("occurred_on", occurred_on),
+            ],
+        );
+    }
+
+    let conn = app.conn();
+    let notes = |sort: Sort| {
+        let query = TransactionQuery {
+            sort,
+            ..TransactionQuery::month("2026-03")
+        };
+        db::list_transactions(&conn, &query)
+            .unwrap()
+            .into_iter()
+            .map(|record| record.note.unwrap_or_default())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(notes(Sort::Newest), ["Парковка", "Рынок", "Аванс"]);
+    assert_eq!(notes(Sort::Oldest), ["Аванс", "Рынок", "Парковка"]);
+    assert_eq!(notes(Sort::Largest), ["Аванс", "Рынок", "Парковка"]);
+    assert_eq!(notes(Sort::Smallest), ["Парковка", "Рынок", "Аванс"]);
+    assert_eq!(notes(Sort::Category), ["Рынок", "Аванс", "Парковка"]);
+
+    let page = app
+        .get("/transactions?month=2026-03&sort=amount")
+        .into_string()
+        .unwrap();
+    assert!(page.find("Аванс").unwrap() < page.find("Парковка").unwrap());
+    assert!(page.contains("Сумма ↓"));
+    // Clicking the sorted column again flips it.
+    assert!(page.contains(r#"href="/transactions?month=2026-03&sort=amount_asc""#));
+    let response = app.get("/transactions?sort=amount%20DESC");
+    assert_eq!(response.status(), Status::BadRequest);
+}
+
+#[test]
+fn duplicate_copies_a_transaction_to_today() {
+    let app = TestApp::logged_in();
+    let food_id = app.fixtures.food_id.to_string();
+    let card_id = app.fixtures.card_id.to_string();
+    app.post_form(
+        "/transactions",
+        &[
+            ("kind", "expense"),
+            ("amount", "320"),
+            ("category_id", &food_id),
+            ("account_id", &card_id),
+            ("occurred_on", "2026-03-14"),
+            ("payee", "Кофейня"),
+            ("tags", "кофе"),
+        ],
+    );
+    let original = db::list_transactions(&app.conn(), &TransactionQuery::month("2026-03")).unwrap();
+
+    let response = app.post_form(&format!("/transactions/{}/duplicate", original[0].id), &[]);
+    assert_eq!(location(&response), Some("/transactions"));
+
+    let today = chrono::Local::now().date_naive();
+    let month = today.format("%Y-%m").to_string();
+    let copies = db::list_transactions(&app.conn(), &TransactionQuery::month(&month)).unwrap();
+    let copy = copies
+        .iter()
+        .find(|record| record.id != original[0].id)
+        .unwrap();
+    assert_eq!(copy.occurred_on, today.format("%Y-%m-%d").to_string());
+    assert_eq!(copy.amount_cents, 32_000);
+    assert_eq!(copy.category_name.as_deref(), Some("Еда"));
+    assert_eq!(copy.account_name.as_deref(), Some("Карта"));
+    assert_eq!(copy.payee.as_deref(), Some("Кофейня"));
+    assert_eq!(copy.tags.as_deref(), Some("кофе"));
+
+    let missing = app.post_form("/transactions/999/duplicate", &[]);
+    assert_eq!(missing.status(), Status::NotFound);
+}
+#[test]
+fn starred_transactions_are_filtered_and_linked_from_the_dashboard() {
+    let app = TestApp::logged_in();
+    let food_id = app.fixtures.food_id.to_string();
+    for (amount, note) in [("89000", "Ноутбук"), ("450", "Обед")] {
+        app.post_form(
+            "/transactions",
+            &[
+                ("kind", "expense"),
+                ("amount", amount),
+                ("category_id", &food_id),
+                ("occurred_on", "2026-03-14"),
+                ("note", note),
+            ],
+        );
+    }
+    let listed = db::list_transactions(&app.conn(), &TransactionQuery::month("2026-03")).unwrap();
+    let laptop = listed
+        .iter()
+        .find(|t| t.amount_cents == 8_900_000)
+        .unwrap()
+        .id;
+
+    let response = app.post_form(
+        &format!("/transactions/{laptop}/star"),
+        &[("starred", "true")],
+    );
+    assert_eq!(location(&response), Some("/transactions"));
+    let page = app
+        .get("/transactions?month=all&starred=1")
+        .into_string()
+        .unwrap();
+    assert!(page.contains("Ноутбук"));
+    assert!(!page.contains("Обед"));
+    let dashboard = app.get("/").into_string().unwrap();
+    assert!(dashboard.contains("Отмеченные операции: 1"));
+
+    app.post_form(
+        &format!("/transactions/{laptop}/star"),
+        &[("starred", "false")],
+    );
+    let starred = TransactionQuery {
+        starred: true,
+        ..Default::default()
+    };
+    assert_eq!(db::count_transactions(&app.conn(), &starred).unwrap(), 0);
+    let missing = app.post_form("/transactions/999/star", &[("starred", "true")]);
+    assert_eq!(missing.status(), Status::NotFound);
+}
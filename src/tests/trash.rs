@@ -0,0 +1,105 @@
+use chrono::Utc;
+use rocket::http::Status;
+
+use super::{TestApp, location};
+use crate::disputes::RefundMonth;
+use crate::query::TransactionQuery;
+use crate::receipt_storage::Local;
+use crate::{db, trash};
+
+#[test]
+fn deleted_transaction_leaves_lists_and_totals_until_restored() {
+    let app = TestApp::logged_in();
+    let id = app.spend("15", "2026-03-10", &[("note", "Ужин")]);
+    app.spend("5", "2026-03-10", &[("note", "Ужин")]);
+
+    let response = app.post_form(&format!("/transactions/{id}/delete"), &[]);
+    assert_eq!(location(&response), Some("/transactions"));
+
+    let conn = app.conn();
+    let listed = db::list_transactions(&conn, &TransactionQuery::month("2026-03")).unwrap();
+    assert_eq!(listed.len(), 1);
+    assert_eq!(
+        db::report_month_totals(&conn, "2026-03", RefundMonth::Refund).unwrap(),
+        (0, 500)
+    );
+    assert_eq!(
+        db::report_days(&conn, "2026-03", RefundMonth::Refund).unwrap()[0].expense_cents,
+        500
+    );
+    drop(conn);
+
+    let page = app.get("/trash").into_string().unwrap();
+    assert!(page.contains("Ужин"));
+    assert!(page.contains(&format!("/trash/transactions/{id}/restore")));
+
+    let again = app.post_form(&format!("/transactions/{id}/delete"), &[]);
+    assert_eq!(again.status(), Status::BadRequest);
+
+    let response = app.post_form(&format!("/trash/transactions/{id}/restore"), &[]);
+    assert_eq!(location(&response), Some("/trash"));
+    assert_eq!(
+        db::report_month_totals(&app.conn(), "2026-03", RefundMonth::Refund).unwrap(),
+        (0, 2_000)
+    );
+    assert!(db::list_trash(&app.conn()).unwrap().is_empty());
+
+    let unknown = app.post_form(&format!("/trash/users/{id}/restore"), &[]);
+    assert_eq!(unknown.status(), Status::NotFound);
+}
+
+#[test]
+fn deleted_budgets_stop_counting_and_a_new_one_replaces_them() {
+    let app = TestApp::logged_in();
+    let food_id = app.fixtures.food_id;
+    let conn = app.conn();
+    db::insert_budget(&conn, food_id, "2026-03", 10_000, false).unwrap();
+    db::set_standing_budget(&conn, food_id, 7_000).unwrap();
+    let budget_id = db::list_budgets(&conn, "2026-03", RefundMonth::Refund).unwrap()[0].id;
+    let standing_id = db::list_standing_budgets(&conn).unwrap()[0].id;
+    drop(conn);
+
+    app.post_form(&format!("/budgets/{budget_id}/delete"), &[]);
+    let budgets = db::list_budgets(&app.conn(), "2026-03", RefundMonth::Refund).unwrap();
+    assert_eq!(budgets.len(), 1);
+    assert!(budgets[0].standing);
+
+    app.post_form(&format!("/budgets/standing/{standing_id}/delete"), &[]);
+    assert!(
+        db::list_budgets(&app.conn(), "2026-03", RefundMonth::Refund)
+            .unwrap()
+            .is_empty()
+    );
+    assert_eq!(db::list_trash(&app.conn()).unwrap().len(), 2);
+
+    db::insert_budget(&app.conn(), food_id, "2026-03", 12_000, false).unwrap();
+    let budgets = db::list_budgets(&app.conn(), "2026-03", RefundMonth::Refund).unwrap();
+    assert_eq!(budgets[0].id, budget_id);
+    assert_eq!(budgets[0].amount_cents, 12_000);
+
+    app.post_form(
+        &format!("/trash/standing_budgets/{standing_id}/restore"),
+        &[],
+    );
+    assert_eq!(db::list_standing_budgets(&app.conn()).unwrap().len(), 1);
+}
+
+#[test]
+fn purge_deletes_only_what_outlived_the_retention() {
+    let app = TestApp::logged_in();
+    let conn = app.conn();
+    let old = app.spend("1", "2026-03-10", &[("note", "Ужин")]);
+    let recent = app.spend("2", "2026-03-10", &[("note", "Ужин")]);
+    let now = Utc::now();
+    let long_ago = trash::deleted_at(now - trash::RETENTION - chrono::Duration::hours(1));
+    db::trash_transaction(&conn, old, &long_ago).unwrap();
+    db::trash_transaction(&conn, recent, &trash::deleted_at(now)).unwrap();
+
+    trash::purge(&conn, &Local::new(&crate::receipts_dir()), now).unwrap();
+
+    let left = db::list_trash(&conn).unwrap();
+    assert_eq!(left.len(), 1);
+    assert_eq!(left[0].id, recent);
+    assert!(db::restore_from_trash(&conn, "transactions", recent).unwrap());
+    assert!(!db::restore_from_trash(&conn, "transactions", old).unwrap());
+}
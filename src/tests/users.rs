@@ -0,0 +1,120 @@
+use rocket::http::Status;
+
+use super::{PASSWORD, TestApp, location};
+use crate::db;
+use crate::user_merge::{self, Refused};
+
+fn add_user(app: &TestApp, username: &str) -> i64 {
+    db::insert_user(&app.conn(), username, "unused", "2026-01-01T00:00:00Z").unwrap()
+}
+
+fn count(app: &TestApp, sql: &str) -> i64 {
+    app.conn().query_row(sql, [], |row| row.get(0)).unwrap()
+}
+
+#[test]
+fn merging_hands_the_duplicates_things_over() {
+    let app = TestApp::logged_in();
+    let me = db::user_ids(&app.conn()).unwrap()[0];
+    let twin = add_user(&app, "tester2");
+    let conn = app.conn();
+    db::insert_api_token(
+        &conn,
+        twin,
+        "Телефон",
+        "twin-token",
+        "read",
+        "2026-01-01T00:00:00Z",
+    )
+    .unwrap();
+    db::set_telegram_chat_id(&conn, twin, Some(42)).unwrap();
+    db::set_notification_preference(&conn, twin, "login", "telegram", true).unwrap();
+    db::set_notification_preference(&conn, me, "login", "telegram", false).unwrap();
+    drop(conn);
+
+    let merge = |password: &str| {
+        let response = app.post_form(
+            "/settings/users/merge",
+            &[
+                ("from_id", &twin.to_string()),
+                ("into_id", &me.to_string()),
+                ("password", password),
+            ],
+        );
+        assert_eq!(location(&response), Some("/settings/users"));
+    };
+    merge("wrong-password");
+    assert_eq!(db::user_ids(&app.conn()).unwrap(), [me, twin]);
+    assert!(
+        app.get("/settings/users")
+            .into_string()
+            .unwrap()
+            .contains("Пароль неверный")
+    );
+    merge(PASSWORD);
+
+    assert_eq!(db::user_ids(&app.conn()).unwrap(), [me]);
+    let (owner, _) = db::user_by_api_token(&app.conn(), "twin-token", "2026-01-02T00:00:00Z")
+        .unwrap()
+        .unwrap();
+    assert_eq!(owner.id, me);
+    assert_eq!(db::telegram_chat_id(&app.conn(), me).unwrap(), Some(42));
+    // The remaining account's own choice stays.
+    assert_eq!(
+        count(
+            &app,
+            "SELECT enabled FROM notification_preferences WHERE event = 'login' AND channel = 'telegram'"
+        ),
+        0
+    );
+    assert_eq!(app.get("/settings/users").status(), Status::Ok);
+}
+
+#[test]
+fn merge_refuses_the_same_unknown_or_own_account() {
+    let app = TestApp::logged_in();
+    let me = db::user_ids(&app.conn()).unwrap()[0];
+    let twin = add_user(&app, "tester2");
+    let mut conn = app.conn();
+    assert_eq!(
+        user_merge::merge(&mut conn, me, twin, twin).unwrap(),
+        Err(Refused::Same)
+    );
+    assert_eq!(
+        user_merge::merge(&mut conn, me, twin, 999).unwrap(),
+        Err(Refused::Unknown)
+    );
+    assert_eq!(
+        user_merge::merge(&mut conn, me, me, twin).unwrap(),
+        Err(Refused::SignedIn)
+    );
+    assert_eq!(db::user_ids(&conn).unwrap().len(), 2);
+}
+
+#[test]
+fn merging_ends_the_duplicates_sign_ins_without_cascades() {
+    let app = TestApp::logged_in();
+    let me = db::user_ids(&app.conn()).unwrap()[0];
+    let twin = add_user(&app, "tester2");
+    let mut conn = app.conn();
+    // As on a connection opened without the pragma.
+    conn.execute_batch("PRAGMA foreign_keys = OFF").unwrap();
+    db::create_session(
+        &conn,
+        twin,
+        "twin-session",
+        "Телефон",
+        None,
+        "2026-01-01T00:00:00Z",
+        "2099-01-01T00:00:00Z",
+    )
+    .unwrap();
+    db::insert_login_alert(&conn, "twin-alert", twin, "Телефон", "2026-01-01T00:00:00Z").unwrap();
+
+    assert_eq!(user_merge::merge(&mut conn, me, twin, me).unwrap(), Ok(()));
+    for table in ["sessions", "login_alerts"] {
+        let sql = format!("SELECT COUNT(*) FROM {table} WHERE user_id = {twin}");
+        assert_eq!(count(&app, &sql), 0, "{table}");
+    }
+    conn.execute_batch("PRAGMA foreign_keys = ON").unwrap();
+}
@@ -0,0 +1,55 @@
+use rocket::http::Status;
+
+use super::{TestApp, location};
+use crate::db;
+use crate::query::TransactionQuery;
+
+#[test]
+fn bad_transaction_comes_back_with_what_was_typed() {
+    let app = TestApp::logged_in();
+    let food_id = app.fixtures.food_id.to_string();
+    let response = app.post_form(
+        "/transactions",
+        &[
+            ("kind", "expense"),
+            ("amount", "12,5x"),
+            ("category_id", &food_id),
+            ("occurred_on", "2026-03-10"),
+            ("note", "Обед с коллегами"),
+        ],
+    );
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+    let page = response.into_string().unwrap();
+    assert!(page.contains("Введите сумму больше нуля"));
+    assert!(page.contains("value=\"12,5x\""));
+    assert!(page.contains("Обед с коллегами"));
+    assert!(page.contains("value=\"2026-03-10\""));
+    let listed = db::list_transactions(&app.conn(), &TransactionQuery::month("2026-03")).unwrap();
+    assert!(listed.is_empty());
+}
+
+#[test]
+fn saved_form_redirects_with_a_notice_shown_once() {
+    let app = TestApp::logged_in();
+    let response = app.post_form("/categories", &[("name", "Кафе"), ("kind", "expense")]);
+    assert_eq!(location(&response), Some("/categories"));
+
+    let page = app.get("/categories").into_string().unwrap();
+    assert!(page.contains("Категория «Кафе» добавлена"));
+    let page = app.get("/categories").into_string().unwrap();
+    assert!(!page.contains("добавлена"));
+}
+
+#[test]
+fn only_the_row_that_was_sent_shows_its_error() {
+    let app = TestApp::logged_in();
+    let food_id = app.fixtures.food_id;
+    let response = app.post_form(
+        &format!("/categories/{food_id}"),
+        &[("name", "Еда"), ("default_amount", "-5")],
+    );
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+    let page = response.into_string().unwrap();
+    assert_eq!(page.matches("class=\"field-error\"").count(), 1);
+    assert!(page.contains("value=\"-5\""));
+}
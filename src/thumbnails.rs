@@ -0,0 +1,47 @@
+//! Small copies of receipt photos for pages that list them.
+//!
+//! A thumbnail is made when a receipt is uploaded and kept next to it as
+//! `<receipt>.thumb.jpg`, turned the way the phone held the camera. Receipts
+//! from before thumbnails, and files the image decoder can't read such as
+//! HEIC, have none; pages then get the receipt itself.
+//!
+//! The thumbnail is written under a hidden temporary name first, like a
+//! receipt, so a crash halfway leaves nothing that looks finished, and
+//! `receipt_files::recover` removes the leftover.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader, ImageResult};
+
+/// The longer side of a thumbnail, in pixels.
+pub const SIZE: u32 = 320;
+
+/// The file name of the thumbnail of `receipt`.
+pub fn name(receipt: &str) -> String {
+    format!("{receipt}.thumb.jpg")
+}
+
+/// Makes the thumbnail of `receipt` in `dir`, replacing any earlier one.
+pub fn create(dir: &Path, receipt: &str) -> ImageResult<()> {
+    let mut decoder = ImageReader::open(dir.join(receipt))?
+        .with_guessed_format()?
+        .into_decoder()?;
+    let orientation = decoder.orientation()?;
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+    let thumbnail = image.thumbnail(SIZE, SIZE).into_rgb8();
+    let temp = dir.join(format!(".{}.part", name(receipt)));
+    if let Err(err) = thumbnail.save_with_format(&temp, ImageFormat::Jpeg) {
+        let _ = fs::remove_file(&temp);
+        return Err(err);
+    }
+    fs::rename(&temp, dir.join(name(receipt)))?;
+    Ok(())
+}
+
+/// [`create`] on a blocking thread, for upload handlers; a receipt without
+/// a thumbnail is shown whole, so failures are left at that.
+pub async fn create_in_background(dir: PathBuf, receipt: String) {
+    let _ = rocket::tokio::task::spawn_blocking(move || create(&dir, &receipt)).await;
+}
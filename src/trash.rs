@@ -0,0 +1,50 @@
+//! Deleted transactions and budgets wait in the trash before they are gone.
+//!
+//! Deleting only stamps `deleted_at`, which every listing, total and report
+//! leaves out, so a slip can be taken back from the trash page. The daily
+//! purge deletes for good whatever has been there longer than [`RETENTION`],
+//! together with the receipts of purged transactions.
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, Result};
+
+use crate::api_sessions;
+use crate::db::{self, DbPool};
+use crate::receipt_storage::Storage;
+use crate::thumbnails;
+
+/// How long deleted rows can still be restored.
+pub const RETENTION: chrono::Duration = chrono::Duration::days(30);
+
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The tables with a trash; the URL names them, this maps them to SQL.
+pub fn table(param: &str) -> Option<&'static str> {
+    ["transactions", "budgets", "standing_budgets"]
+        .into_iter()
+        .find(|table| *table == param)
+}
+
+/// The value stored in `deleted_at` for a deletion at `now`.
+pub fn deleted_at(now: DateTime<Utc>) -> String {
+    api_sessions::timestamp(now)
+}
+
+/// Deletes what has been in the trash longer than [`RETENTION`], receipts
+/// in `receipts` included.
+pub fn purge(conn: &Connection, receipts: &dyn Storage, now: DateTime<Utc>) -> Result<()> {
+    for name in db::purge_trash(conn, &deleted_at(now - RETENTION))? {
+        let _ = receipts.remove(&thumbnails::name(&name));
+        let _ = receipts.remove(&name);
+    }
+    Ok(())
+}
+
+/// The scheduled purge.
+pub fn run_due(pool: &DbPool, receipts: &dyn Storage) {
+    if let Ok(conn) = pool.get() {
+        let _ = purge(&conn, receipts, Utc::now());
+    }
+}
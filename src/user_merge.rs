@@ -0,0 +1,57 @@
+//! Folding a duplicate user account into another.
+//!
+//! The household's data is shared by its logins (see `account_deletion`), so
+//! a merge leaves it as it is and no category names can clash. What moves is
+//! what the duplicate owns itself: API tokens, webhooks, notifications and
+//! background jobs, plus its notification settings, known devices, Telegram
+//! chat and email where the remaining account has none. Its sessions end with
+//! it.
+
+use rusqlite::{Connection, Result};
+
+use crate::db;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Refused {
+    Same,
+    Unknown,
+    /// The duplicate is the account doing the merge; it would sign itself out.
+    SignedIn,
+}
+
+impl Refused {
+    pub fn message(&self) -> &'static str {
+        match self {
+            Refused::Same => "Выберите два разных аккаунта",
+            Refused::Unknown => "Аккаунт не найден",
+            Refused::SignedIn => "Нельзя удалить аккаунт, под которым вы вошли",
+        }
+    }
+}
+
+/// Merges user `from` into `into` on behalf of `current_user`, all in one
+/// transaction.
+pub fn merge(
+    conn: &mut Connection,
+    current_user: i64,
+    from: i64,
+    into: i64,
+) -> Result<std::result::Result<(), Refused>> {
+    if from == into {
+        return Ok(Err(Refused::Same));
+    }
+    if from == current_user {
+        return Ok(Err(Refused::SignedIn));
+    }
+    let tx = conn.transaction()?;
+    let users = db::list_users(&tx)?;
+    if ![from, into]
+        .iter()
+        .all(|id| users.iter().any(|user| user.id == *id))
+    {
+        return Ok(Err(Refused::Unknown));
+    }
+    db::merge_user(&tx, from, into)?;
+    tx.commit()?;
+    Ok(Ok(()))
+}
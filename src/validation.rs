@@ -0,0 +1,44 @@
+//! Sending a form back with what was typed and a message at each bad field.
+//!
+//! A handler records the submitted values and the problems it finds in a
+//! [`FormState`]. When there are any, the page is rendered again with it and
+//! answered with 422 through `AppError::Invalid`; templates put
+//! `<form>.values.<field>` back into the inputs and show
+//! `<form>.errors.<field>` under them. A successful save redirects with a
+//! Rocket `Flash` notice instead, which the layout shows once.
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+#[derive(Debug, Default, Serialize)]
+pub struct FormState {
+    /// The row a per-row form was sent for, such as a category being renamed.
+    pub id: Option<i64>,
+    pub values: BTreeMap<&'static str, String>,
+    pub errors: BTreeMap<&'static str, &'static str>,
+}
+
+impl FormState {
+    pub fn for_row(id: i64) -> Self {
+        FormState {
+            id: Some(id),
+            ..Default::default()
+        }
+    }
+
+    /// Keeps `value` to fill `field` in again.
+    pub fn value(&mut self, field: &'static str, value: &str) {
+        self.values.insert(field, value.to_string());
+    }
+
+    /// The first message for a field is kept; later checks of it are usually
+    /// consequences of the first.
+    pub fn error(&mut self, field: &'static str, message: &'static str) {
+        self.errors.entry(field).or_insert(message);
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+}